@@ -0,0 +1,157 @@
+// 运行 wasm32 后端生成的模块：node wasm_run.js prog.wasm
+// 提供 printf、fmod 等 env 导入，把 argv 放在 __heap_base 处，main 的返回值作为退出码
+const fs = require("fs");
+
+const bytes = fs.readFileSync(process.argv[2]);
+let memory = null;
+let out = "";
+
+const view = () => new DataView(memory.buffer);
+
+function cstring(addr) {
+    const mem = new Uint8Array(memory.buffer);
+    let end = addr;
+    while (mem[end] !== 0) end++;
+    return Buffer.from(mem.subarray(addr, end)).toString("latin1");
+}
+
+function strip(text) {
+    if (!text.includes(".")) return text;
+    return text.replace(/0+$/, "").replace(/\.$/, "");
+}
+
+function formatG(x, prec) {
+    if (Number.isNaN(x)) return "nan";
+    if (!Number.isFinite(x)) return x < 0 ? "-inf" : "inf";
+    if (x === 0) return 1 / x < 0 ? "-0" : "0";
+    const p = prec === 0 ? 1 : prec;
+    const exp = parseInt(x.toExponential(p - 1).split("e")[1]);
+    if (exp < -4 || exp >= p) {
+        const [m, e] = x.toExponential(p - 1).split("e");
+        const n = parseInt(e);
+        return strip(m) + "e" + (n < 0 ? "-" : "+") + String(Math.abs(n)).padStart(2, "0");
+    }
+    return strip(x.toFixed(p - 1 - exp));
+}
+
+// 变参区域中的参数按大小对齐
+function printf(fmt, args) {
+    const v = view();
+    let ap = args;
+    const next = (size) => {
+        ap = (ap + size - 1) & -size;
+        const addr = ap;
+        ap += size;
+        return addr;
+    };
+    const text = cstring(fmt);
+    let result = "";
+    for (let i = 0; i < text.length; i++) {
+        if (text[i] !== "%") {
+            result += text[i];
+            continue;
+        }
+        const m = /^([-0+ #]*)(\d*)(?:\.(\d+))?(hh|h|ll|l|z)?([diuxXcsgfep%])/.exec(text.slice(i + 1));
+        i += m[0].length;
+        const [, flags, width, precision, length, conv] = m;
+        const wide = length === "ll";
+        let s;
+        switch (conv) {
+            case "%":
+                s = "%";
+                break;
+            case "d":
+            case "i":
+                s = wide ? v.getBigInt64(next(8), true).toString() : String(v.getInt32(next(4), true));
+                break;
+            case "u":
+                s = wide ? v.getBigUint64(next(8), true).toString() : String(v.getUint32(next(4), true));
+                break;
+            case "x":
+            case "X":
+            case "p":
+                s = (wide ? v.getBigUint64(next(8), true) : v.getUint32(next(4), true)).toString(16);
+                if (conv === "X") s = s.toUpperCase();
+                if (conv === "p") s = "0x" + s;
+                break;
+            case "c":
+                s = String.fromCharCode(v.getInt32(next(4), true) & 0xff);
+                break;
+            case "s":
+                s = cstring(v.getUint32(next(4), true));
+                break;
+            case "g":
+                s = formatG(v.getFloat64(next(8), true), precision === undefined ? 6 : +precision);
+                break;
+            case "f":
+                s = v.getFloat64(next(8), true).toFixed(precision === undefined ? 6 : +precision);
+                break;
+            case "e":
+                s = v.getFloat64(next(8), true).toExponential(precision === undefined ? 6 : +precision);
+                s = s.replace(/e([+-])(\d)$/, "e$10$2");
+                break;
+        }
+        const w = width ? +width : 0;
+        if (s.length < w) {
+            if (flags.includes("-")) s = s.padEnd(w);
+            else if (flags.includes("0") && conv !== "s") s = s.padStart(w, "0");
+            else s = s.padStart(w);
+        }
+        result += s;
+    }
+    out += result;
+    return result.length;
+}
+
+class Exit {
+    constructor(code) {
+        this.code = code;
+    }
+}
+
+const env = {
+    printf,
+    puts: (s) => {
+        out += cstring(s) + "\n";
+        return 0;
+    },
+    putchar: (c) => {
+        out += String.fromCharCode(c & 0xff);
+        return c;
+    },
+    fmod: (a, b) => a % b,
+    fmodf: (a, b) => Math.fround(a % b),
+    exit: (code) => {
+        throw new Exit(code);
+    },
+    abort: () => {
+        throw new Exit(134);
+    },
+};
+
+const wasm = new WebAssembly.Module(bytes);
+const imports = { env: {} };
+for (const item of WebAssembly.Module.imports(wasm)) {
+    imports.env[item.name] = env[item.name] || (() => {
+        throw new Error("missing import " + item.name);
+    });
+}
+const instance = new WebAssembly.Instance(wasm, imports);
+memory = instance.exports.memory;
+
+// argv[0] = "prog"
+const base = instance.exports.__heap_base.value;
+const v = view();
+new Uint8Array(memory.buffer).set(Buffer.from("prog\0"), base + 8);
+v.setUint32(base, base + 8, true);
+v.setUint32(base + 4, 0, true);
+
+let code;
+try {
+    code = instance.exports.main(1, base);
+} catch (e) {
+    if (!(e instanceof Exit)) throw e;
+    code = e.code;
+}
+process.stdout.write(out);
+process.exitCode = code & 0xff;
@@ -0,0 +1,82 @@
+target datalayout = "e-m:e-p:64:64-i64:64-i128:128-n64-S128"
+target triple = "riscv64-unknown-linux-gnu"
+
+@pts = global [32 x i8] zeroinitializer, align 4
+@second = internal constant ptr getelementptr (i8, ptr @pts, i64 8), align 8
+@name = internal constant [7 x i8] c"pt\22\5C\0A\00\00", align 1
+@errno = external global i8, align 1
+@count = weak global [4 x i8] c"\03\00\00\00", align 4
+
+declare signext i32 @printf(ptr, ...)
+
+define i64 @set_y(i32 signext %a0, float %a1) {
+bb0:
+  %t0 = getelementptr [8 x i8], ptr @pts, i32 %a0
+  %0 = getelementptr i8, ptr %t0, i64 4
+  store volatile float %a1, ptr %0
+  %1 = ptrtoint ptr %0 to i64
+  ret i64 %1
+}
+
+define internal { i32, float } @make(i32 signext %a1) {
+bb0:
+  %a0 = alloca [8 x i8], align 4
+  store i32 %a1, ptr %a0
+  %0 = getelementptr i8, ptr %a0, i64 4
+  %1 = sitofp i32 %a1 to float
+  %2 = fmul float %1, 0x3FE0000000000000
+  store float %2, ptr %0
+  %t0 = load i32, ptr %a0, align 4
+  %t1 = insertvalue { i32, float } undef, i32 %t0, 0
+  %t2 = getelementptr i8, ptr %a0, i64 4
+  %t3 = load float, ptr %t2, align 4
+  %t4 = insertvalue { i32, float } %t1, float %t3, 1
+  ret { i32, float } %t4
+}
+
+define internal signext i32 @classify(i32 signext %a0) {
+bb0:
+  switch i32 %a0, label %bb3 [ i32 -1, label %bb1 i32 7, label %bb2 ]
+bb1:
+  br label %bb4
+bb2:
+  br label %bb4
+bb3:
+  br label %bb4
+bb4:
+  %0 = phi i32 [ 10, %bb1 ], [ 20, %bb2 ], [ 0, %bb3 ]
+  ret i32 %0
+}
+
+define weak double @total(i32 signext %a0, ...) {
+bb0:
+  %0 = alloca [24 x i8], align 8
+  call void @llvm.va_start(ptr %0)
+  br label %bb1
+bb1:
+  %1 = phi i32 [ 0, %bb0 ], [ %4, %bb2 ]
+  %2 = phi double [ 0x0000000000000000, %bb0 ], [ %6, %bb2 ]
+  %3 = icmp slt i32 %1, %a0
+  br i1 %3, label %bb2, label %bb3
+bb2:
+  %4 = add nsw i32 %1, 1
+  %5 = va_arg ptr %0, double
+  %6 = fadd double %2, %5
+  br label %bb1
+bb3:
+  call void @llvm.va_end(ptr %0)
+  %7 = call signext i32 (ptr, ...) @printf(ptr @name, double %2)
+  %8 = call signext i32 @classify(i32 signext %7)
+  %9 = alloca [8 x i8], align 4
+  %t0 = call { i32, float } @make(i32 signext %8)
+  %t1 = extractvalue { i32, float } %t0, 0
+  store i32 %t1, ptr %9, align 4
+  %t2 = extractvalue { i32, float } %t0, 1
+  %t3 = getelementptr i8, ptr %9, i64 4
+  store float %t2, ptr %t3, align 4
+  %10 = load i32, ptr @count
+  ret double %2
+}
+
+declare void @llvm.va_start(ptr)
+declare void @llvm.va_end(ptr)
@@ -0,0 +1,55 @@
+/// 代码生成，把 IR 翻译为目标机器的汇编或目标文件
+/// # Contents
+/// - `asm`: 与目标无关的汇编输出（数据段）
+/// - `data`: 全局变量的节和符号，写入目标文件
+/// - `dwarf`: DWARF 调试信息的生成，目前只有 x86-64 使用
+/// - `mir`: 与目标无关的机器指令框架：寄存器、栈帧对象、机器函数
+/// - `regalloc`: 寄存器分配
+/// - `riscv64`: RV64GC 后端
+/// - `llvm`: LLVM IR 文本输出，不属于 `TargetIsa`
+/// - `wasm32`: WebAssembly 后端
+/// - `x86_64`: x86-64 System V 后端
+pub mod asm;
+pub mod data;
+pub mod dwarf;
+pub mod llvm;
+pub mod mir;
+pub mod regalloc;
+pub mod riscv64;
+pub mod wasm32;
+pub mod x86_64;
+
+use crate::err::codegen_error::{CodegenError, CodegenResult};
+use crate::ir::Module;
+use crate::target::{Arch, TargetInfo};
+
+///
+/// 目标机器的代码生成器
+///
+pub trait TargetIsa {
+    fn info(&self) -> &TargetInfo;
+
+    /// 生成汇编文本，wasm32 是 `.wat` 文本格式
+    fn emit_asm(&self, module: &Module) -> CodegenResult<String>;
+
+    /// 生成 ELF 可重定位目标文件，wasm32 是完整的 `.wasm` 模块
+    fn emit_object(&self, _module: &Module) -> CodegenResult<Vec<u8>> {
+        Err(CodegenError::NoObjectWriter(self.info().triple.to_string()))
+    }
+}
+
+/// 目标对应的代码生成器
+pub fn isa(info: TargetInfo) -> Box<dyn TargetIsa> {
+    match info.arch {
+        Arch::X86_64 => Box::new(x86_64::X86_64::new(info)),
+        Arch::Riscv64 => Box::new(riscv64::Riscv64::new(info)),
+        Arch::Wasm32 => Box::new(wasm32::Wasm32::new(info)),
+    }
+}
+
+/// 按三元组查找代码生成器
+pub fn isa_by_triple(triple: &str) -> CodegenResult<Box<dyn TargetIsa>> {
+    let info = TargetInfo::from_triple(triple)
+        .ok_or_else(|| CodegenError::UnknownTarget(triple.to_string()))?;
+    Ok(isa(info))
+}
@@ -0,0 +1,96 @@
+use crate::codegen::data::{is_zero, section_kind};
+use crate::ir::{Global, InitItem, Linkage, Module, Visibility};
+use crate::object::SectionKind;
+use std::fmt::Write;
+
+/// 输出所有全局变量的定义、外部声明的可见性和文件末尾的 `.note.GNU-stack`，各目标的 GNU 汇编共用
+pub fn emit_data(out: &mut String, module: &Module) {
+    for id in module.global_ids() {
+        let global = &module.globals[id];
+        if !global.is_declaration() {
+            emit_global(out, module, global);
+        } else {
+            emit_visibility(out, &global.name, global.visibility);
+        }
+    }
+    for id in module.func_ids() {
+        let func = &module.funcs[id];
+        if func.is_declaration() {
+            emit_visibility(out, &func.name, func.visibility);
+        }
+    }
+    writeln!(out, "\t.section .note.GNU-stack,\"\",@progbits").unwrap();
+}
+
+/// 外部符号的 `.globl` / `.weak`，以及非默认可见性的 `.hidden` / `.protected`
+pub fn emit_linkage(out: &mut String, name: &str, linkage: Linkage, visibility: Visibility) {
+    match linkage {
+        Linkage::External => writeln!(out, "\t.globl {}", name).unwrap(),
+        Linkage::Weak => writeln!(out, "\t.weak {}", name).unwrap(),
+        Linkage::Internal => {}
+    }
+    emit_visibility(out, name, visibility);
+}
+
+/// 可见性，外部声明的符号也需要，链接器据此决定能否直接访问
+pub fn emit_visibility(out: &mut String, name: &str, visibility: Visibility) {
+    match visibility {
+        Visibility::Default => {}
+        Visibility::Hidden => writeln!(out, "\t.hidden {}", name).unwrap(),
+        Visibility::Protected => writeln!(out, "\t.protected {}", name).unwrap(),
+    }
+}
+
+/// 节的选择见 `section_kind`
+pub fn emit_global(out: &mut String, module: &Module, global: &Global) {
+    let init = global.init.as_ref().unwrap();
+    let zero = is_zero(init);
+    let section = match section_kind(module, global) {
+        SectionKind::ReadOnly => ".section .rodata",
+        SectionKind::Bss => ".bss",
+        SectionKind::Tls => ".section .tdata,\"awT\",@progbits",
+        SectionKind::TlsBss => ".section .tbss,\"awT\",@nobits",
+        _ => ".data",
+    };
+    writeln!(out, "\t{}", section).unwrap();
+    emit_linkage(out, &global.name, global.linkage, global.visibility);
+    writeln!(out, "\t.p2align {}", global.align.max(1).trailing_zeros()).unwrap();
+    let kind = if global.thread_local {
+        "tls_object"
+    } else {
+        "object"
+    };
+    writeln!(out, "\t.type {}, @{}", global.name, kind).unwrap();
+    writeln!(out, "\t.size {}, {}", global.name, global.size).unwrap();
+    writeln!(out, "{}:", global.name).unwrap();
+
+    let mut emitted = 0;
+    for item in init {
+        match item {
+            _ if zero => {}
+            InitItem::Bytes(bytes) => {
+                for chunk in bytes.chunks(16) {
+                    let bytes: Vec<String> = chunk.iter().map(|x| x.to_string()).collect();
+                    writeln!(out, "\t.byte {}", bytes.join(", ")).unwrap();
+                }
+                emitted += bytes.len() as u64;
+            }
+            InitItem::Zero(n) => {
+                writeln!(out, "\t.zero {}", n).unwrap();
+                emitted += n;
+            }
+            InitItem::Addr { target, addend } => {
+                let name = module.symbol_name(*target);
+                match addend {
+                    0 => writeln!(out, "\t.quad {}", name).unwrap(),
+                    _ => writeln!(out, "\t.quad {}{:+}", name, addend).unwrap(),
+                }
+                emitted += 8;
+            }
+        }
+    }
+    if global.size > emitted {
+        writeln!(out, "\t.zero {}", global.size - emitted).unwrap();
+    }
+    writeln!(out).unwrap();
+}
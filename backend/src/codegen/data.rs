@@ -0,0 +1,96 @@
+use crate::ir::{Global, InitItem, Linkage, Module, RelocModel};
+use crate::object::{Binding, Object, Reloc, RelocKind, SectionKind, Symbol, SymbolKind};
+
+/// 初始值是否全零
+pub fn is_zero(init: &[InitItem]) -> bool {
+    init.iter().all(|x| match x {
+        InitItem::Bytes(bytes) => bytes.iter().all(|x| *x == 0),
+        InitItem::Zero(_) => true,
+        InitItem::Addr { .. } => false,
+    })
+}
+
+/// 全局变量所在的节：常量放在只读数据，全零的放在 `.bss`，其余放在 `.data`；
+/// 位置无关代码中含有地址的常量需要加载时重定位，也放在 `.data`；
+/// 线程局部变量全零的放在 `.tbss`，其余放在 `.tdata`
+pub fn section_kind(module: &Module, global: &Global) -> SectionKind {
+    let init = global.init.as_ref().unwrap();
+    if global.thread_local {
+        return match is_zero(init) {
+            true => SectionKind::TlsBss,
+            false => SectionKind::Tls,
+        };
+    }
+    let relocated = module.reloc_model != RelocModel::Static
+        && init.iter().any(|x| matches!(x, InitItem::Addr { .. }));
+    match (global.constant && !relocated, is_zero(init)) {
+        (true, _) => SectionKind::ReadOnly,
+        (false, true) => SectionKind::Bss,
+        (false, false) => SectionKind::Data,
+    }
+}
+
+/// 链接属性对应的符号绑定
+pub fn binding(linkage: Linkage) -> Binding {
+    match linkage {
+        Linkage::External => Binding::Global,
+        Linkage::Internal => Binding::Local,
+        Linkage::Weak => Binding::Weak,
+    }
+}
+
+/// 把所有全局变量的定义写入目标文件，地址初始值生成 `Abs64` 重定位
+pub fn add_globals(obj: &mut Object, module: &Module) {
+    for id in module.global_ids() {
+        let global = &module.globals[id];
+        if global.is_declaration() {
+            // 对外部线程局部变量的引用也是 TLS 符号，链接器据此检查定义和引用是否一致
+            if global.thread_local
+                && let Some(symbol) = obj.symbols.iter_mut().find(|x| x.name == global.name)
+            {
+                symbol.kind = SymbolKind::Tls;
+            }
+            continue;
+        }
+        let index = obj.section(section_kind(module, global));
+        let offset = obj.sections[index].align_to(global.align.max(1) as u64, 0);
+        obj.define(Symbol {
+            name: global.name.clone(),
+            binding: binding(global.linkage),
+            kind: match global.thread_local {
+                true => SymbolKind::Tls,
+                false => SymbolKind::Object,
+            },
+            visibility: global.visibility,
+            section: Some(index),
+            value: offset,
+            size: global.size,
+        });
+        if obj.sections[index].kind.is_bss() {
+            obj.sections[index].bss_size += global.size;
+            continue;
+        }
+
+        let mut data = Vec::with_capacity(global.size as usize);
+        let mut relocs = Vec::new();
+        for item in global.init.as_ref().unwrap() {
+            match item {
+                InitItem::Bytes(bytes) => data.extend_from_slice(bytes),
+                InitItem::Zero(n) => data.resize(data.len() + *n as usize, 0),
+                InitItem::Addr { target, addend } => {
+                    relocs.push(Reloc {
+                        offset: offset + data.len() as u64,
+                        symbol: obj.symbol(module.symbol_name(*target)),
+                        kind: RelocKind::Abs64,
+                        addend: *addend,
+                    });
+                    data.extend_from_slice(&[0; 8]);
+                }
+            }
+        }
+        data.resize(data.len().max(global.size as usize), 0);
+        let section = &mut obj.sections[index];
+        section.data.extend_from_slice(&data);
+        section.relocs.extend(relocs);
+    }
+}
@@ -0,0 +1,829 @@
+use crate::ir::debug::{DebugInfo, DebugLoc, DiEncoding, DiSubprogram, DiType, DiTypeId};
+use crate::ir::{InstId, Module};
+use crate::object::{Object, Reloc, RelocKind};
+use rustc_hash::FxHashMap;
+use std::fmt::Write;
+
+const DW_TAG_ARRAY_TYPE: u16 = 0x01;
+const DW_TAG_ENUMERATION_TYPE: u16 = 0x04;
+const DW_TAG_FORMAL_PARAMETER: u16 = 0x05;
+const DW_TAG_MEMBER: u16 = 0x0d;
+const DW_TAG_POINTER_TYPE: u16 = 0x0f;
+const DW_TAG_COMPILE_UNIT: u16 = 0x11;
+const DW_TAG_STRUCTURE_TYPE: u16 = 0x13;
+const DW_TAG_SUBROUTINE_TYPE: u16 = 0x15;
+const DW_TAG_TYPEDEF: u16 = 0x16;
+const DW_TAG_UNION_TYPE: u16 = 0x17;
+const DW_TAG_UNSPECIFIED_PARAMETERS: u16 = 0x18;
+const DW_TAG_SUBRANGE_TYPE: u16 = 0x21;
+const DW_TAG_BASE_TYPE: u16 = 0x24;
+const DW_TAG_CONST_TYPE: u16 = 0x26;
+const DW_TAG_ENUMERATOR: u16 = 0x28;
+const DW_TAG_SUBPROGRAM: u16 = 0x2e;
+const DW_TAG_VARIABLE: u16 = 0x34;
+const DW_TAG_VOLATILE_TYPE: u16 = 0x35;
+
+const DW_AT_LOCATION: u16 = 0x02;
+const DW_AT_NAME: u16 = 0x03;
+const DW_AT_BYTE_SIZE: u16 = 0x0b;
+const DW_AT_STMT_LIST: u16 = 0x10;
+const DW_AT_LOW_PC: u16 = 0x11;
+const DW_AT_HIGH_PC: u16 = 0x12;
+const DW_AT_LANGUAGE: u16 = 0x13;
+const DW_AT_COMP_DIR: u16 = 0x1b;
+const DW_AT_CONST_VALUE: u16 = 0x1c;
+const DW_AT_PRODUCER: u16 = 0x25;
+const DW_AT_PROTOTYPED: u16 = 0x27;
+const DW_AT_COUNT: u16 = 0x37;
+const DW_AT_DATA_MEMBER_LOCATION: u16 = 0x38;
+const DW_AT_DECL_FILE: u16 = 0x3a;
+const DW_AT_DECL_LINE: u16 = 0x3b;
+const DW_AT_DECLARATION: u16 = 0x3c;
+const DW_AT_ENCODING: u16 = 0x3e;
+const DW_AT_EXTERNAL: u16 = 0x3f;
+const DW_AT_FRAME_BASE: u16 = 0x40;
+const DW_AT_TYPE: u16 = 0x49;
+const DW_AT_BIT_SIZE: u16 = 0x0d;
+const DW_AT_DATA_BIT_OFFSET: u16 = 0x6b;
+
+const DW_FORM_ADDR: u16 = 0x01;
+const DW_FORM_DATA2: u16 = 0x05;
+const DW_FORM_DATA8: u16 = 0x07;
+const DW_FORM_STRING: u16 = 0x08;
+const DW_FORM_DATA1: u16 = 0x0b;
+const DW_FORM_SDATA: u16 = 0x0d;
+const DW_FORM_UDATA: u16 = 0x0f;
+const DW_FORM_REF4: u16 = 0x13;
+const DW_FORM_SEC_OFFSET: u16 = 0x17;
+const DW_FORM_EXPRLOC: u16 = 0x18;
+const DW_FORM_FLAG_PRESENT: u16 = 0x19;
+
+const DW_ATE_BOOLEAN: u8 = 0x02;
+const DW_ATE_FLOAT: u8 = 0x04;
+const DW_ATE_SIGNED: u8 = 0x05;
+const DW_ATE_SIGNED_CHAR: u8 = 0x06;
+const DW_ATE_UNSIGNED: u8 = 0x07;
+const DW_ATE_UNSIGNED_CHAR: u8 = 0x08;
+
+const DW_OP_ADDR: u8 = 0x03;
+const DW_OP_REG0: u8 = 0x50;
+const DW_OP_FBREG: u8 = 0x91;
+
+const DW_LANG_C99: u16 = 0x0c;
+const DW_UT_COMPILE: u8 = 0x01;
+
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+const DW_LNCT_PATH: u16 = 0x01;
+const DW_LNCT_DIRECTORY_INDEX: u16 = 0x02;
+
+/// 行号程序的参数，和 GNU 汇编器的默认值相同
+const LINE_BASE: i8 = -5;
+const LINE_RANGE: u8 = 14;
+const OPCODE_BASE: u8 = 13;
+const STANDARD_OPCODE_LENGTHS: [u8; 12] = [0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+
+///
+/// 调试信息节中需要在汇编或链接时填写的位置
+/// - `Addr`: 符号地址加偏移，8 字节
+/// - `Section`: 另一个调试信息节的开头在节中的偏移，4 字节
+/// - `Diff`: 两个符号的地址之差，8 字节，只在汇编输出中出现
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fixup {
+    Addr { symbol: String, addend: i64 },
+    Section(&'static str),
+    Diff { end: String, start: String },
+}
+
+///
+/// 生成的调试信息节
+///
+/// # Members
+/// - `name`: 节名
+/// - `data`: 内容，需要填写的位置为 0
+/// - `fixups`: 需要填写的位置和内容
+///
+#[derive(Debug, Clone)]
+pub struct DebugSection {
+    pub name: &'static str,
+    pub data: Vec<u8>,
+    pub fixups: Vec<(u64, Fixup)>,
+}
+
+///
+/// 函数在目标文件中的位置
+///
+/// # Members
+/// - `start` `size`: 在 `.text` 中的偏移和大小
+/// - `lines`: 行号表，相对函数开头的偏移和源码位置，按偏移排列
+///
+#[derive(Debug, Clone)]
+pub struct FuncCode {
+    pub start: u64,
+    pub size: u64,
+    pub lines: Vec<(u64, DebugLoc)>,
+}
+
+///
+/// 一个函数的调试信息输入
+///
+/// # Members
+/// - `symbol`: 符号名
+/// - `sub`: IR 中的调试信息
+/// - `frame`: 变量所在的 `alloca` 相对帧基址的偏移，没有的变量被优化掉了
+/// - `code`: 输出目标文件时函数的位置和行号表；输出汇编时为 None，
+///   函数结束处有 `end_label` 给出的标签，行号表由汇编器根据 `.loc` 生成
+///
+#[derive(Debug, Clone)]
+pub struct FuncDebug<'a> {
+    pub symbol: &'a str,
+    pub sub: &'a DiSubprogram,
+    pub frame: FxHashMap<InstId, i64>,
+    pub code: Option<FuncCode>,
+}
+
+/// 汇编输出中函数结束处的标签
+pub fn end_label(symbol: &str) -> String {
+    format!(".Lfunc_end_{}", symbol)
+}
+
+/// 汇编输出中调试信息节开头的标签
+fn section_label(name: &str) -> String {
+    format!(".L{}0", name.trim_start_matches('.'))
+}
+
+fn uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    out.push(0);
+}
+
+/// 属性的值，决定属性的形式
+enum Attr {
+    Str(String),
+    Data1(u8),
+    Data2(u16),
+    Udata(u64),
+    Sdata(i64),
+    Data8(u64),
+    Flag,
+    Ref(DiTypeId),
+    Addr(Fixup),
+    Size(Fixup),
+    SecOffset(&'static str),
+    Expr(Vec<u8>),
+    /// `DW_OP_addr` 加符号地址
+    AddrExpr(String),
+}
+
+impl Attr {
+    fn form(&self) -> u16 {
+        match self {
+            Attr::Str(_) => DW_FORM_STRING,
+            Attr::Data1(_) => DW_FORM_DATA1,
+            Attr::Data2(_) => DW_FORM_DATA2,
+            Attr::Udata(_) => DW_FORM_UDATA,
+            Attr::Sdata(_) => DW_FORM_SDATA,
+            Attr::Data8(_) => DW_FORM_DATA8,
+            Attr::Flag => DW_FORM_FLAG_PRESENT,
+            Attr::Ref(_) => DW_FORM_REF4,
+            Attr::Addr(_) => DW_FORM_ADDR,
+            Attr::Size(_) => DW_FORM_DATA8,
+            Attr::SecOffset(_) => DW_FORM_SEC_OFFSET,
+            Attr::Expr(_) | Attr::AddrExpr(_) => DW_FORM_EXPRLOC,
+        }
+    }
+}
+
+/// 缩写的内容：标签、是否有子节点、属性和形式
+type AbbrevKey = (u16, bool, Vec<(u16, u16)>);
+
+///
+/// `.debug_info` 和 `.debug_abbrev` 的生成，整个模块是一个编译单元
+///
+/// # Members
+/// - `abbrevs`: 已经生成的缩写和它的编号
+/// - `types`: 每个类型的 DIE 在编译单元中的偏移
+/// - `refs`: 引用类型的位置，类型都输出后填写
+///
+struct InfoWriter {
+    abbrev: Vec<u8>,
+    info: Vec<u8>,
+    fixups: Vec<(u64, Fixup)>,
+    abbrevs: FxHashMap<AbbrevKey, u64>,
+    types: Vec<u64>,
+    refs: Vec<(usize, DiTypeId)>,
+}
+
+impl InfoWriter {
+    fn die(&mut self, tag: u16, children: bool, attrs: Vec<(u16, Attr)>) {
+        let key: AbbrevKey = (
+            tag,
+            children,
+            attrs.iter().map(|(at, x)| (*at, x.form())).collect(),
+        );
+        let next = self.abbrevs.len() as u64 + 1;
+        let code = match self.abbrevs.get(&key) {
+            Some(code) => *code,
+            None => {
+                uleb(&mut self.abbrev, next);
+                uleb(&mut self.abbrev, tag as u64);
+                self.abbrev.push(children as u8);
+                for (at, form) in key.2.iter() {
+                    uleb(&mut self.abbrev, *at as u64);
+                    uleb(&mut self.abbrev, *form as u64);
+                }
+                self.abbrev.extend_from_slice(&[0, 0]);
+                self.abbrevs.insert(key, next);
+                next
+            }
+        };
+        uleb(&mut self.info, code);
+        for (_, attr) in attrs {
+            self.attr(attr);
+        }
+    }
+
+    fn attr(&mut self, attr: Attr) {
+        let out = &mut self.info;
+        match attr {
+            Attr::Str(s) => string(out, &s),
+            Attr::Data1(x) => out.push(x),
+            Attr::Data2(x) => out.extend_from_slice(&x.to_le_bytes()),
+            Attr::Udata(x) => uleb(out, x),
+            Attr::Sdata(x) => sleb(out, x),
+            Attr::Data8(x) => out.extend_from_slice(&x.to_le_bytes()),
+            Attr::Flag => {}
+            Attr::Ref(ty) => {
+                self.refs.push((out.len(), ty));
+                out.extend_from_slice(&[0; 4]);
+            }
+            Attr::Addr(fixup) | Attr::Size(fixup) => {
+                self.fixups.push((out.len() as u64, fixup));
+                out.extend_from_slice(&[0; 8]);
+            }
+            Attr::SecOffset(name) => {
+                self.fixups.push((out.len() as u64, Fixup::Section(name)));
+                out.extend_from_slice(&[0; 4]);
+            }
+            Attr::Expr(expr) => {
+                uleb(out, expr.len() as u64);
+                out.extend_from_slice(&expr);
+            }
+            Attr::AddrExpr(symbol) => {
+                out.extend_from_slice(&[9, DW_OP_ADDR]);
+                let fixup = Fixup::Addr { symbol, addend: 0 };
+                self.fixups.push((out.len() as u64, fixup));
+                out.extend_from_slice(&[0; 8]);
+            }
+        }
+    }
+
+    /// 子节点的结束
+    fn end(&mut self) {
+        self.info.push(0);
+    }
+
+    fn ty(&mut self, ty: &DiType) {
+        let type_attr = |ty: Option<DiTypeId>| ty.map(|x| (DW_AT_TYPE, Attr::Ref(x)));
+        match ty {
+            DiType::Base {
+                name,
+                size,
+                encoding,
+            } => {
+                let encoding = match encoding {
+                    DiEncoding::Signed => DW_ATE_SIGNED,
+                    DiEncoding::Unsigned => DW_ATE_UNSIGNED,
+                    DiEncoding::SignedChar => DW_ATE_SIGNED_CHAR,
+                    DiEncoding::UnsignedChar => DW_ATE_UNSIGNED_CHAR,
+                    DiEncoding::Float => DW_ATE_FLOAT,
+                    DiEncoding::Boolean => DW_ATE_BOOLEAN,
+                };
+                let attrs = vec![
+                    (DW_AT_NAME, Attr::Str(name.clone())),
+                    (DW_AT_ENCODING, Attr::Data1(encoding)),
+                    (DW_AT_BYTE_SIZE, Attr::Udata(*size)),
+                ];
+                self.die(DW_TAG_BASE_TYPE, false, attrs);
+            }
+            DiType::Pointer { pointee, size } => {
+                let mut attrs = vec![(DW_AT_BYTE_SIZE, Attr::Udata(*size))];
+                attrs.extend(type_attr(*pointee));
+                self.die(DW_TAG_POINTER_TYPE, false, attrs);
+            }
+            DiType::Array { elem, count } => {
+                self.die(
+                    DW_TAG_ARRAY_TYPE,
+                    true,
+                    vec![(DW_AT_TYPE, Attr::Ref(*elem))],
+                );
+                let attrs = count.map(|x| (DW_AT_COUNT, Attr::Udata(x)));
+                self.die(DW_TAG_SUBRANGE_TYPE, false, attrs.into_iter().collect());
+                self.end();
+            }
+            DiType::Record {
+                is_union,
+                name,
+                size,
+                members,
+            } => {
+                let tag = match is_union {
+                    true => DW_TAG_UNION_TYPE,
+                    false => DW_TAG_STRUCTURE_TYPE,
+                };
+                let mut attrs: Vec<_> = name
+                    .iter()
+                    .map(|x| (DW_AT_NAME, Attr::Str(x.clone())))
+                    .collect();
+                match size {
+                    Some(size) => attrs.push((DW_AT_BYTE_SIZE, Attr::Udata(*size))),
+                    None => attrs.push((DW_AT_DECLARATION, Attr::Flag)),
+                }
+                self.die(tag, !members.is_empty(), attrs);
+                for member in members.iter() {
+                    let mut attrs: Vec<_> = member
+                        .name
+                        .iter()
+                        .map(|x| (DW_AT_NAME, Attr::Str(x.clone())))
+                        .collect();
+                    attrs.push((DW_AT_TYPE, Attr::Ref(member.ty)));
+                    match member.bit_field {
+                        Some((bit_offset, width)) => {
+                            let bits = member.offset * 8 + bit_offset as u64;
+                            attrs.push((DW_AT_DATA_BIT_OFFSET, Attr::Udata(bits)));
+                            attrs.push((DW_AT_BIT_SIZE, Attr::Udata(width as u64)));
+                        }
+                        None => {
+                            attrs.push((DW_AT_DATA_MEMBER_LOCATION, Attr::Udata(member.offset)))
+                        }
+                    }
+                    self.die(DW_TAG_MEMBER, false, attrs);
+                }
+                if !members.is_empty() {
+                    self.end();
+                }
+            }
+            DiType::Enum {
+                name,
+                size,
+                enumerators,
+            } => {
+                let mut attrs: Vec<_> = name
+                    .iter()
+                    .map(|x| (DW_AT_NAME, Attr::Str(x.clone())))
+                    .collect();
+                attrs.push((DW_AT_BYTE_SIZE, Attr::Udata(*size)));
+                self.die(DW_TAG_ENUMERATION_TYPE, !enumerators.is_empty(), attrs);
+                for (name, value) in enumerators.iter() {
+                    let attrs = vec![
+                        (DW_AT_NAME, Attr::Str(name.clone())),
+                        (DW_AT_CONST_VALUE, Attr::Sdata(*value)),
+                    ];
+                    self.die(DW_TAG_ENUMERATOR, false, attrs);
+                }
+                if !enumerators.is_empty() {
+                    self.end();
+                }
+            }
+            DiType::Typedef {
+                name,
+                ty,
+                file,
+                line,
+            } => {
+                let mut attrs = vec![(DW_AT_NAME, Attr::Str(name.clone()))];
+                attrs.extend(type_attr(*ty));
+                attrs.push((DW_AT_DECL_FILE, Attr::Udata(*file as u64)));
+                attrs.push((DW_AT_DECL_LINE, Attr::Udata(*line as u64)));
+                self.die(DW_TAG_TYPEDEF, false, attrs);
+            }
+            DiType::Function {
+                ret,
+                params,
+                is_variadic,
+            } => {
+                let children = !params.is_empty() || *is_variadic;
+                let mut attrs = vec![(DW_AT_PROTOTYPED, Attr::Flag)];
+                attrs.extend(type_attr(*ret));
+                self.die(DW_TAG_SUBROUTINE_TYPE, children, attrs);
+                for param in params.iter() {
+                    self.die(
+                        DW_TAG_FORMAL_PARAMETER,
+                        false,
+                        vec![(DW_AT_TYPE, Attr::Ref(*param))],
+                    );
+                }
+                if *is_variadic {
+                    self.die(DW_TAG_UNSPECIFIED_PARAMETERS, false, Vec::new());
+                }
+                if children {
+                    self.end();
+                }
+            }
+            DiType::Const(ty) => self.die(
+                DW_TAG_CONST_TYPE,
+                false,
+                type_attr(*ty).into_iter().collect(),
+            ),
+            DiType::Volatile(ty) => self.die(
+                DW_TAG_VOLATILE_TYPE,
+                false,
+                type_attr(*ty).into_iter().collect(),
+            ),
+        }
+    }
+
+    fn subprogram(&mut self, debug: &DebugInfo, func: &FuncDebug, frame_reg: u8) {
+        let sub = func.sub;
+        let ret = match &debug.types[sub.ty.0 as usize] {
+            DiType::Function { ret, .. } => *ret,
+            _ => None,
+        };
+        let mut attrs = Vec::new();
+        if sub.external {
+            attrs.push((DW_AT_EXTERNAL, Attr::Flag));
+        }
+        attrs.push((DW_AT_NAME, Attr::Str(sub.name.clone())));
+        attrs.push((DW_AT_DECL_FILE, Attr::Udata(sub.file as u64)));
+        attrs.push((DW_AT_DECL_LINE, Attr::Udata(sub.line as u64)));
+        attrs.push((DW_AT_PROTOTYPED, Attr::Flag));
+        attrs.extend(ret.map(|x| (DW_AT_TYPE, Attr::Ref(x))));
+        let low_pc = Fixup::Addr {
+            symbol: func.symbol.to_string(),
+            addend: 0,
+        };
+        attrs.push((DW_AT_LOW_PC, Attr::Addr(low_pc)));
+        // `DW_AT_high_pc` 是常量时表示函数的大小
+        let size = match &func.code {
+            Some(code) => Attr::Data8(code.size),
+            None => Attr::Size(Fixup::Diff {
+                end: end_label(func.symbol),
+                start: func.symbol.to_string(),
+            }),
+        };
+        attrs.push((DW_AT_HIGH_PC, size));
+        attrs.push((DW_AT_FRAME_BASE, Attr::Expr(vec![DW_OP_REG0 + frame_reg])));
+        self.die(DW_TAG_SUBPROGRAM, !sub.vars.is_empty(), attrs);
+
+        for var in sub.vars.iter() {
+            let tag = match var.arg {
+                Some(_) => DW_TAG_FORMAL_PARAMETER,
+                None => DW_TAG_VARIABLE,
+            };
+            let mut attrs = vec![
+                (DW_AT_NAME, Attr::Str(var.name.clone())),
+                (DW_AT_DECL_FILE, Attr::Udata(var.file as u64)),
+                (DW_AT_DECL_LINE, Attr::Udata(var.line as u64)),
+                (DW_AT_TYPE, Attr::Ref(var.ty)),
+            ];
+            if let Some(offset) = func.frame.get(&var.addr) {
+                let mut expr = vec![DW_OP_FBREG];
+                sleb(&mut expr, *offset);
+                attrs.push((DW_AT_LOCATION, Attr::Expr(expr)));
+            }
+            self.die(tag, false, attrs);
+        }
+        if !sub.vars.is_empty() {
+            self.end();
+        }
+    }
+}
+
+///
+/// 生成 `.debug_abbrev` `.debug_info`，函数都有 `code` 时还生成 `.debug_line`
+///
+/// 整个模块是一个编译单元，字符串都直接放在 `.debug_info` 中，类型在编译单元的开头，
+/// 然后是全局变量和函数；`frame_reg` 是帧基址寄存器的 DWARF 编号
+///
+pub fn write(
+    module: &Module,
+    debug: &DebugInfo,
+    funcs: &[FuncDebug],
+    frame_reg: u8,
+) -> Vec<DebugSection> {
+    let mut w = InfoWriter {
+        abbrev: Vec::new(),
+        info: Vec::new(),
+        fixups: Vec::new(),
+        abbrevs: FxHashMap::default(),
+        types: Vec::with_capacity(debug.types.len()),
+        refs: Vec::new(),
+    };
+    let version = debug.version;
+    let objects = funcs.iter().all(|x| x.code.is_some());
+
+    // 编译单元头，长度最后填写
+    w.info.extend_from_slice(&[0; 4]);
+    w.info.extend_from_slice(&version.to_le_bytes());
+    if version >= 5 {
+        w.info.extend_from_slice(&[DW_UT_COMPILE, 8]);
+    }
+    w.fixups
+        .push((w.info.len() as u64, Fixup::Section(".debug_abbrev")));
+    w.info.extend_from_slice(&[0; 4]);
+    if version < 5 {
+        w.info.push(8);
+    }
+
+    let mut attrs = vec![
+        (DW_AT_PRODUCER, Attr::Str(debug.producer.clone())),
+        (DW_AT_LANGUAGE, Attr::Data2(DW_LANG_C99)),
+        (DW_AT_NAME, Attr::Str(debug.file.clone())),
+        (DW_AT_COMP_DIR, Attr::Str(debug.dir.clone())),
+    ];
+    // 函数在 `.text` 中按顺序连续排列，编译单元的范围从第一个函数到最后一个函数
+    if let (Some(first), Some(last)) = (funcs.first(), funcs.last()) {
+        let low_pc = Fixup::Addr {
+            symbol: first.symbol.to_string(),
+            addend: 0,
+        };
+        attrs.push((DW_AT_LOW_PC, Attr::Addr(low_pc)));
+        let size = match (&first.code, &last.code) {
+            (Some(first), Some(last)) => Attr::Data8(last.start + last.size - first.start),
+            _ => Attr::Size(Fixup::Diff {
+                end: end_label(last.symbol),
+                start: first.symbol.to_string(),
+            }),
+        };
+        attrs.push((DW_AT_HIGH_PC, size));
+    }
+    attrs.push((DW_AT_STMT_LIST, Attr::SecOffset(".debug_line")));
+    w.die(DW_TAG_COMPILE_UNIT, true, attrs);
+
+    for ty in debug.types.iter() {
+        w.types.push(w.info.len() as u64);
+        w.ty(ty);
+    }
+    for var in debug.globals.iter() {
+        let global = &module.globals[var.global];
+        let mut attrs = vec![
+            (DW_AT_NAME, Attr::Str(var.name.clone())),
+            (DW_AT_TYPE, Attr::Ref(var.ty)),
+        ];
+        if var.external {
+            attrs.push((DW_AT_EXTERNAL, Attr::Flag));
+        }
+        attrs.push((DW_AT_DECL_FILE, Attr::Udata(var.file as u64)));
+        attrs.push((DW_AT_DECL_LINE, Attr::Udata(var.line as u64)));
+        // 线程局部变量的位置需要 `DW_OP_form_tls_address` 和 DTPOFF 重定位，不输出
+        match global.is_declaration() {
+            true => attrs.push((DW_AT_DECLARATION, Attr::Flag)),
+            false if global.thread_local => {}
+            false => attrs.push((DW_AT_LOCATION, Attr::AddrExpr(global.name.clone()))),
+        }
+        w.die(DW_TAG_VARIABLE, false, attrs);
+    }
+    for func in funcs.iter() {
+        w.subprogram(debug, func, frame_reg);
+    }
+    w.end();
+
+    for (pos, ty) in w.refs.iter() {
+        let offset = w.types[ty.0 as usize] as u32;
+        w.info[*pos..*pos + 4].copy_from_slice(&offset.to_le_bytes());
+    }
+    let length = w.info.len() as u32 - 4;
+    w.info[..4].copy_from_slice(&length.to_le_bytes());
+    w.abbrev.push(0);
+
+    let mut sections = vec![
+        DebugSection {
+            name: ".debug_abbrev",
+            data: w.abbrev,
+            fixups: Vec::new(),
+        },
+        DebugSection {
+            name: ".debug_info",
+            data: w.info,
+            fixups: w.fixups,
+        },
+    ];
+    if objects {
+        sections.push(line_program(debug, funcs));
+    }
+    sections
+}
+
+/// 行号程序，每个函数是一个序列，源文件是 1 号文件，`DebugInfo::files` 从 2 号开始
+fn line_program(debug: &DebugInfo, funcs: &[FuncDebug]) -> DebugSection {
+    let version = debug.version;
+    let mut out = Vec::new();
+    let mut fixups = Vec::new();
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&version.to_le_bytes());
+    if version >= 5 {
+        out.extend_from_slice(&[8, 0]);
+    }
+    let header_length = out.len();
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&[1, 1, 1, LINE_BASE as u8, LINE_RANGE, OPCODE_BASE]);
+    out.extend_from_slice(&STANDARD_OPCODE_LENGTHS);
+    if version >= 5 {
+        // 目录表只有编译目录；文件表的 0 号和 1 号都是源文件，之后是其余的文件
+        out.push(1);
+        uleb(&mut out, DW_LNCT_PATH as u64);
+        uleb(&mut out, DW_FORM_STRING as u64);
+        out.push(1);
+        string(&mut out, &debug.dir);
+        out.push(2);
+        uleb(&mut out, DW_LNCT_PATH as u64);
+        uleb(&mut out, DW_FORM_STRING as u64);
+        uleb(&mut out, DW_LNCT_DIRECTORY_INDEX as u64);
+        uleb(&mut out, DW_FORM_UDATA as u64);
+        uleb(&mut out, 2 + debug.files.len() as u64);
+        for file in [&debug.file, &debug.file]
+            .into_iter()
+            .chain(debug.files.iter())
+        {
+            string(&mut out, file);
+            out.push(0);
+        }
+    } else {
+        out.push(0);
+        for file in std::iter::once(&debug.file).chain(debug.files.iter()) {
+            string(&mut out, file);
+            out.extend_from_slice(&[0, 0, 0]);
+        }
+        out.push(0);
+    }
+    let length = (out.len() - header_length - 4) as u32;
+    out[header_length..header_length + 4].copy_from_slice(&length.to_le_bytes());
+
+    for func in funcs.iter() {
+        let code = func.code.as_ref().unwrap();
+        out.extend_from_slice(&[0, 9, DW_LNE_SET_ADDRESS]);
+        let fixup = Fixup::Addr {
+            symbol: func.symbol.to_string(),
+            addend: 0,
+        };
+        fixups.push((out.len() as u64, fixup));
+        out.extend_from_slice(&[0; 8]);
+
+        // 函数开头（序言）对应定义所在的行，同一位置的多个行取最后一个
+        let mut rows: Vec<(u64, DebugLoc)> = vec![(
+            0,
+            DebugLoc {
+                file: func.sub.file,
+                line: func.sub.line,
+                col: 0,
+            },
+        )];
+        for (offset, loc) in code.lines.iter() {
+            match rows.last_mut() {
+                Some(last) if last.0 == *offset => last.1 = *loc,
+                Some(last) if last.1 == *loc => {}
+                _ => rows.push((*offset, *loc)),
+            }
+        }
+
+        let (mut addr, mut file, mut line, mut col) = (0u64, 1u32, 1i64, 0u32);
+        for (offset, loc) in rows {
+            if offset != addr {
+                out.push(DW_LNS_ADVANCE_PC);
+                uleb(&mut out, offset - addr);
+                addr = offset;
+            }
+            if loc.file != file {
+                out.push(DW_LNS_SET_FILE);
+                uleb(&mut out, loc.file as u64);
+                file = loc.file;
+            }
+            if loc.line as i64 != line {
+                out.push(DW_LNS_ADVANCE_LINE);
+                sleb(&mut out, loc.line as i64 - line);
+                line = loc.line as i64;
+            }
+            if loc.col != col {
+                out.push(DW_LNS_SET_COLUMN);
+                uleb(&mut out, loc.col as u64);
+                col = loc.col;
+            }
+            out.push(DW_LNS_COPY);
+        }
+        if code.size != addr {
+            out.push(DW_LNS_ADVANCE_PC);
+            uleb(&mut out, code.size - addr);
+        }
+        out.extend_from_slice(&[0, 1, DW_LNE_END_SEQUENCE]);
+    }
+    let length = out.len() as u32 - 4;
+    out[..4].copy_from_slice(&length.to_le_bytes());
+    DebugSection {
+        name: ".debug_line",
+        data: out,
+        fixups,
+    }
+}
+
+/// 把调试信息节加入目标文件，节内偏移的引用使用节符号
+pub fn add_sections(obj: &mut Object, sections: Vec<DebugSection>) {
+    let indices: Vec<usize> = sections.iter().map(|x| obj.debug_section(x.name)).collect();
+    for (section, index) in sections.into_iter().zip(indices) {
+        for (offset, fixup) in section.fixups {
+            let (symbol, kind, addend) = match fixup {
+                Fixup::Addr { symbol, addend } => (obj.symbol(&symbol), RelocKind::Abs64, addend),
+                Fixup::Section(name) => {
+                    let target = obj.debug_section(name);
+                    (obj.section_symbol(target), RelocKind::Abs32, 0)
+                }
+                Fixup::Diff { .. } => unreachable!("symbol difference in an object file"),
+            };
+            obj.sections[index].relocs.push(Reloc {
+                offset,
+                symbol,
+                kind,
+                addend,
+            });
+        }
+        obj.sections[index].data = section.data;
+    }
+}
+
+/// 汇编输出的调试信息节，`.debug_line` 由汇编器根据 `.loc` 生成，只输出它开头的标签
+pub fn emit_sections(out: &mut String, sections: &[DebugSection]) {
+    for section in sections.iter() {
+        writeln!(out, "\t.section {},\"\",@progbits", section.name).unwrap();
+        writeln!(out, "{}:", section_label(section.name)).unwrap();
+        let mut fixups = section.fixups.iter().peekable();
+        let mut pos = 0;
+        while pos < section.data.len() {
+            if let Some((_, fixup)) = fixups.next_if(|x| x.0 as usize == pos) {
+                pos += match fixup {
+                    Fixup::Addr { symbol, addend: 0 } => {
+                        writeln!(out, "\t.quad {}", symbol).unwrap();
+                        8
+                    }
+                    Fixup::Addr { symbol, addend } => {
+                        writeln!(out, "\t.quad {}{:+}", symbol, addend).unwrap();
+                        8
+                    }
+                    Fixup::Section(name) => {
+                        writeln!(out, "\t.long {}", section_label(name)).unwrap();
+                        4
+                    }
+                    Fixup::Diff { end, start } => {
+                        writeln!(out, "\t.quad {}-{}", end, start).unwrap();
+                        8
+                    }
+                };
+                continue;
+            }
+            let end = fixups
+                .peek()
+                .map_or(section.data.len(), |x| x.0 as usize)
+                .min(pos + 16);
+            let bytes: Vec<String> = section.data[pos..end]
+                .iter()
+                .map(|x| x.to_string())
+                .collect();
+            writeln!(out, "\t.byte {}", bytes.join(", ")).unwrap();
+            pos = end;
+        }
+    }
+    writeln!(out, "\t.section .debug_line,\"\",@progbits").unwrap();
+    writeln!(out, "{}:", section_label(".debug_line")).unwrap();
+}
+
+/// 汇编输出开头的源文件声明，DWARF 5 的 0 号文件是编译单元的主文件
+pub fn emit_file(out: &mut String, debug: &DebugInfo) {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    if debug.version >= 5 {
+        let (dir, file) = (quote(&debug.dir), quote(&debug.file));
+        writeln!(out, "\t.file 0 {} {}", dir, file).unwrap();
+    }
+    writeln!(out, "\t.file 1 {}", quote(&debug.file)).unwrap();
+    for (i, file) in debug.files.iter().enumerate() {
+        writeln!(out, "\t.file {} {}", i + 2, quote(file)).unwrap();
+    }
+}
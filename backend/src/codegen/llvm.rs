@@ -0,0 +1,778 @@
+/// LLVM IR 文本（`.ll`）输出，不链接 LLVM 的库，生成的文本交给 `llc` / `opt` 继续优化和编译
+/// # Contents
+/// - `abi`: 按目标的调用约定把签名翻译为 LLVM 的形式：小的聚合类型拆成标量，其余 `byval` / `sret`
+pub mod abi;
+
+use crate::codegen::llvm::abi::{ArgKind, Lowered, RetKind, llvm_type, scalar_type};
+use crate::ir::printer::FuncPrinter;
+use crate::ir::value::sign_extend;
+use crate::ir::{
+    CastOp, Function, Global, InitItem, InlineAttr, InstId, InstKind, Linkage, Module, ParamAttr,
+    RelocModel, Signature, Type, Value, Visibility,
+};
+use crate::target::{Arch, TargetInfo};
+use std::fmt::Write;
+
+///
+/// 用到的内建函数，模块末尾输出它们的声明
+///
+#[derive(Debug, Default)]
+struct Intrinsics {
+    memcpy: bool,
+    va_start: bool,
+    va_end: bool,
+    va_copy: bool,
+}
+
+///
+/// 输出整个模块
+///
+/// 使用不透明指针（`ptr`），IR 中的聚合类型只是一段字节，`alloca` 和全局变量是 `[N x i8]`，
+/// `gep` 翻译为按字节或按元素大小的 `getelementptr`；命名和 IR 的文本格式相同：
+/// 参数为 `%aN`，指令为 `%N`，基本块为 `bbN`，翻译时新增的临时值为 `%tN`
+///
+pub fn emit_module(module: &Module, info: &TargetInfo) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "target datalayout = \"{}\"", info.data_layout);
+    let _ = writeln!(out, "target triple = \"{}\"", info.triple);
+    if !module.global_ids().is_empty() {
+        out.push('\n');
+    }
+    for id in module.global_ids() {
+        emit_global(&mut out, module, &module.globals[id], info.ptr_bytes);
+    }
+
+    let mut used = Intrinsics::default();
+    for id in module.func_ids() {
+        out.push('\n');
+        let func = &module.funcs[id];
+        let arg_types: Vec<Type> = func.sig.params.iter().map(|x| x.ty).collect();
+        let lowered = abi::lower(info.arch, &func.sig, &arg_types);
+        if func.is_declaration() {
+            let mut params = param_types(info.arch, &func.sig, &lowered, true);
+            if func.sig.variadic {
+                params.push("...".to_string());
+            }
+            let _ = writeln!(
+                out,
+                "declare {}{} {}({})",
+                visibility(func.linkage, func.visibility),
+                lowered.ret.ty(info.arch, func.sig.ret),
+                symbol(&func.name),
+                params.join(", ")
+            );
+            continue;
+        }
+        let emitter = FuncEmitter {
+            module,
+            func,
+            arch: info.arch,
+            printer: FuncPrinter::new(module, func),
+            lowered,
+            used: &mut used,
+            prologue: Vec::new(),
+            body: Vec::new(),
+            temps: 0,
+        };
+        emitter.emit(&mut out);
+    }
+
+    let decls = [
+        (
+            used.memcpy,
+            "void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)",
+        ),
+        (used.va_start, "void @llvm.va_start(ptr)"),
+        (used.va_end, "void @llvm.va_end(ptr)"),
+        (used.va_copy, "void @llvm.va_copy(ptr, ptr)"),
+    ];
+    if decls.iter().any(|x| x.0) {
+        out.push('\n');
+    }
+    for (_, decl) in decls.iter().filter(|x| x.0) {
+        let _ = writeln!(out, "declare {}", decl);
+    }
+    emit_reloc_model(&mut out, module.reloc_model);
+    out
+}
+
+/// 位置无关代码用模块标志告诉 `llc`，和 `clang -fPIC` / `-fPIE` 相同
+fn emit_reloc_model(out: &mut String, model: RelocModel) {
+    let flags: &[&str] = match model {
+        RelocModel::Static => return,
+        RelocModel::Pic => &["!{i32 8, !\"PIC Level\", i32 2}"],
+        RelocModel::Pie => &[
+            "!{i32 8, !\"PIC Level\", i32 2}",
+            "!{i32 7, !\"PIE Level\", i32 2}",
+        ],
+    };
+    let names: Vec<String> = (0..flags.len()).map(|x| format!("!{}", x)).collect();
+    let _ = writeln!(out, "\n!llvm.module.flags = !{{{}}}", names.join(", "));
+    for (name, flag) in names.iter().zip(flags) {
+        let _ = writeln!(out, "{} = {}", name, flag);
+    }
+}
+
+/// 符号名，不是合法标识符时加引号
+fn symbol(name: &str) -> String {
+    let plain = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '$' | '.' | '_' | '-'));
+    match plain {
+        true => format!("@{}", name),
+        false => format!("@\"{}\"", escape(name.as_bytes())),
+    }
+}
+
+/// 字符串常量的内容，不可打印的字节转义为 `\HH`
+fn escape(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for byte in bytes.iter().cloned() {
+        match byte {
+            0x20..=0x7e if byte != b'"' && byte != b'\\' => text.push(byte as char),
+            _ => text.push_str(&format!("\\{:02X}", byte)),
+        }
+    }
+    text
+}
+
+fn linkage(linkage: Linkage) -> &'static str {
+    match linkage {
+        Linkage::External => "",
+        Linkage::Internal => "internal ",
+        Linkage::Weak => "weak ",
+    }
+}
+
+/// 可见性，局部符号只能是默认可见性
+fn visibility(linkage: Linkage, visibility: Visibility) -> &'static str {
+    match (linkage, visibility) {
+        (Linkage::Internal, _) | (_, Visibility::Default) => "",
+        (_, Visibility::Hidden) => "hidden ",
+        (_, Visibility::Protected) => "protected ",
+    }
+}
+
+/// 函数属性，写在参数列表之后
+fn inline_attr(attr: InlineAttr) -> &'static str {
+    match attr {
+        InlineAttr::None => "",
+        InlineAttr::Hint => " inlinehint",
+        InlineAttr::Always => " alwaysinline",
+        InlineAttr::Never => " noinline",
+    }
+}
+
+/// 全局变量：只有字节时是 `[N x i8]`，有地址时是字节和 `ptr` 组成的 packed 结构体
+fn emit_global(out: &mut String, module: &Module, global: &Global, ptr_bytes: u32) {
+    let kind = if global.constant {
+        "constant"
+    } else {
+        "global"
+    };
+    let tls = if global.thread_local {
+        "thread_local "
+    } else {
+        ""
+    };
+    let name = symbol(&global.name);
+    let Some(init) = &global.init else {
+        let _ = writeln!(
+            out,
+            "{} = external {}{}{} i8, align {}",
+            name,
+            visibility(global.linkage, global.visibility),
+            tls,
+            kind,
+            global.align.max(1)
+        );
+        return;
+    };
+
+    // 相邻的字节合并为一个数组
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut size = 0;
+    for item in init.iter() {
+        match item {
+            InitItem::Bytes(x) => bytes.extend_from_slice(x),
+            InitItem::Zero(n) => bytes.resize(bytes.len() + *n as usize, 0),
+            InitItem::Addr { target, addend } => {
+                size += bytes.len() as u64;
+                fields.extend(byte_field(&std::mem::take(&mut bytes)));
+                let target = symbol(module.symbol_name(*target));
+                let addr = match addend {
+                    0 => target,
+                    _ => format!("getelementptr (i8, ptr {}, i64 {})", target, addend),
+                };
+                fields.push(("ptr".to_string(), addr));
+                size += ptr_bytes as u64;
+            }
+        }
+    }
+    if size + (bytes.len() as u64) < global.size {
+        bytes.resize((global.size - size) as usize, 0);
+    }
+    fields.extend(byte_field(&bytes));
+
+    let (ty, value) = match fields.len() {
+        0 => ("[0 x i8]".to_string(), "zeroinitializer".to_string()),
+        1 => fields.pop().unwrap(),
+        _ => {
+            let types: Vec<&str> = fields.iter().map(|x| x.0.as_str()).collect();
+            let values: Vec<String> = fields.iter().map(|x| format!("{} {}", x.0, x.1)).collect();
+            (
+                format!("<{{ {} }}>", types.join(", ")),
+                format!("<{{ {} }}>", values.join(", ")),
+            )
+        }
+    };
+    let _ = writeln!(
+        out,
+        "{} = {}{}{}{} {} {}, align {}",
+        name,
+        linkage(global.linkage),
+        visibility(global.linkage, global.visibility),
+        tls,
+        kind,
+        ty,
+        value,
+        global.align.max(1)
+    );
+}
+
+fn byte_field(bytes: &[u8]) -> Option<(String, String)> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let ty = format!("[{} x i8]", bytes.len());
+    let value = match bytes.iter().all(|x| *x == 0) {
+        true => "zeroinitializer".to_string(),
+        false => format!("c\"{}\"", escape(bytes)),
+    };
+    Some((ty, value))
+}
+
+/// 固定参数的类型，`attrs` 为 false 时不带参数属性（调用中的函数类型）
+fn param_types(arch: Arch, sig: &Signature, lowered: &Lowered, attrs: bool) -> Vec<String> {
+    let mut types = Vec::new();
+    for (param, kind) in sig.params.iter().zip(lowered.args.iter()) {
+        match kind {
+            ArgKind::Direct if attrs => types.push(scalar_type(arch, param.ty, false)),
+            ArgKind::Direct => types.push(llvm_type(param.ty).to_string()),
+            ArgKind::Parts(parts) => types.extend(parts.iter().map(|x| x.ty.clone())),
+            ArgKind::ByVal { size, align } if attrs => {
+                types.push(format!("ptr byval([{} x i8]) align {}", size, align))
+            }
+            ArgKind::SRet { size, align } if attrs => {
+                types.push(format!("ptr sret([{} x i8]) align {}", size, align))
+            }
+            ArgKind::ByVal { .. } | ArgKind::SRet { .. } | ArgKind::Indirect { .. } => {
+                types.push("ptr".to_string())
+            }
+            ArgKind::Ignored => {}
+        }
+    }
+    types
+}
+
+/// 聚合类型参数的大小和对齐
+fn agg_layout(sig: &Signature, i: usize) -> (u32, u32) {
+    match sig.params.get(i).map(|x| x.attr) {
+        Some(ParamAttr::ByVal { size, align, .. } | ParamAttr::SRet { size, align, .. }) => {
+            (size, align.max(1))
+        }
+        _ => (0, 1),
+    }
+}
+
+/// 对齐为 `align` 的对象中偏移 `offset` 处的对齐
+fn offset_align(align: u32, offset: u32) -> u32 {
+    match offset {
+        0 => align,
+        _ => align.min(1 << offset.trailing_zeros()),
+    }
+}
+
+///
+/// 翻译一个函数
+///
+/// # Members
+/// - `printer`: IR 文本格式的命名
+/// - `lowered`: 函数自己的签名在 LLVM 中的形式
+/// - `prologue`: 放在入口处的指令：拆开的参数拼回内存，调用时复制参数用的 `alloca`
+/// - `body`: 基本块的标签和指令
+/// - `temps`: 临时值的个数
+///
+struct FuncEmitter<'a> {
+    module: &'a Module,
+    func: &'a Function,
+    arch: Arch,
+    printer: FuncPrinter<'a>,
+    lowered: Lowered,
+    used: &'a mut Intrinsics,
+    prologue: Vec<String>,
+    body: Vec<String>,
+    temps: usize,
+}
+
+impl FuncEmitter<'_> {
+    fn emit(mut self, out: &mut String) {
+        let func = self.func;
+        let mut params = Vec::new();
+        let types = param_types(self.arch, &func.sig, &self.lowered, true);
+        let mut types = types.into_iter();
+        for (i, kind) in self.lowered.args.clone().into_iter().enumerate() {
+            let (size, align) = agg_layout(&func.sig, i);
+            match kind {
+                ArgKind::Parts(parts) => {
+                    let base = format!("%a{}", i);
+                    self.push(format!(
+                        "{} = alloca [{} x i8], align {}",
+                        base, size, align
+                    ));
+                    for (j, part) in parts.iter().enumerate() {
+                        let name = format!("%a{}.{}", i, j);
+                        params.push(format!("{} {}", types.next().unwrap(), name));
+                        let ptr = self.offset_ptr(&base, part.offset);
+                        self.push(format!(
+                            "store {} {}, ptr {}, align {}",
+                            part.ty,
+                            name,
+                            ptr,
+                            offset_align(align, part.offset)
+                        ));
+                    }
+                }
+                ArgKind::Ignored => {
+                    self.push(format!("%a{} = alloca [{} x i8], align {}", i, size, align));
+                }
+                _ => params.push(format!("{} %a{}", types.next().unwrap(), i)),
+            }
+        }
+        if func.sig.variadic {
+            params.push("...".to_string());
+        }
+        self.prologue = std::mem::take(&mut self.body);
+
+        for block in func.layout.iter().cloned() {
+            self.body.push(format!("{}:", self.printer.block(block)));
+            for inst in func.blocks[block].insts.iter().cloned() {
+                self.inst(inst);
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "define {}{}{} {}({}){} {{",
+            linkage(func.linkage),
+            visibility(func.linkage, func.visibility),
+            self.lowered.ret.ty(self.arch, func.sig.ret),
+            symbol(&func.name),
+            params.join(", "),
+            inline_attr(func.inline)
+        );
+        // 入口块有前驱时，入口处的指令放在单独的块中
+        let mut body = self.body.into_iter();
+        if !self.prologue.is_empty() && func.predecessors()[func.entry()].is_empty() {
+            let _ = writeln!(out, "{}", body.next().unwrap());
+        } else if !self.prologue.is_empty() {
+            let _ = writeln!(out, "entry:");
+            self.prologue
+                .push(format!("br label %{}", self.printer.block(func.entry())));
+        }
+        for line in self.prologue.iter() {
+            let _ = writeln!(out, "  {}", line);
+        }
+        for line in body {
+            match line.ends_with(':') {
+                true => {
+                    let _ = writeln!(out, "{}", line);
+                }
+                false => {
+                    let _ = writeln!(out, "  {}", line);
+                }
+            }
+        }
+        out.push_str("}\n");
+    }
+
+    fn push(&mut self, line: String) {
+        self.body.push(line);
+    }
+
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("%t{}", self.temps - 1)
+    }
+
+    fn value(&self, value: Value) -> String {
+        match value {
+            Value::Int {
+                ty: Type::Ptr,
+                bits,
+            } if bits != 0 => format!("inttoptr (i64 {} to ptr)", bits),
+            Value::Float { ty, bits } => {
+                let bits = match ty {
+                    Type::F32 => (f32::from_bits(bits as u32) as f64).to_bits(),
+                    _ => bits,
+                };
+                format!("0x{:016X}", bits)
+            }
+            Value::Global(_) | Value::Func(_) => symbol(self.module.symbol_name(value)),
+            _ => self.printer.value(value),
+        }
+    }
+
+    fn typed(&self, value: Value) -> String {
+        format!(
+            "{} {}",
+            llvm_type(self.func.value_type(value)),
+            self.value(value)
+        )
+    }
+
+    /// `base` 加上常量偏移的地址
+    fn offset_ptr(&mut self, base: &str, offset: u32) -> String {
+        if offset == 0 {
+            return base.to_string();
+        }
+        let ptr = self.temp();
+        self.push(format!(
+            "{} = getelementptr i8, ptr {}, i64 {}",
+            ptr, base, offset
+        ));
+        ptr
+    }
+
+    fn memcpy(&mut self, dst: &str, src: &str, size: u64, align: u32) {
+        self.used.memcpy = true;
+        let align = align.max(1);
+        self.push(format!(
+            "call void @llvm.memcpy.p0.p0.i64(ptr align {} {}, ptr align {} {}, i64 {}, i1 false)",
+            align, dst, align, src, size
+        ));
+    }
+
+    fn inst(&mut self, inst: InstId) {
+        use InstKind::*;
+        let func = self.func;
+        let data = &func.insts[inst];
+        let def = match data.ty.is_void() {
+            true => String::new(),
+            false => format!("{} = ", self.printer.value(Value::Inst(inst))),
+        };
+        let ty = llvm_type(data.ty);
+        let line = match &data.kind {
+            Binary { op, lhs, rhs, nsw } => {
+                let nsw = if *nsw { "nsw " } else { "" };
+                let (lhs, rhs) = (self.value(*lhs), self.value(*rhs));
+                format!("{}{} {}{} {}, {}", def, op, nsw, ty, lhs, rhs)
+            }
+            FNeg { val } => format!("{}fneg {}", def, self.typed(*val)),
+            Cmp { pred, lhs, rhs } => {
+                let cmp = if pred.is_float() { "fcmp" } else { "icmp" };
+                let (lhs, rhs) = (self.typed(*lhs), self.value(*rhs));
+                format!("{}{} {} {}, {}", def, cmp, pred, lhs, rhs)
+            }
+            Cast { op, val } => {
+                // IR 中指针和整数之间的 bitcast 在 LLVM 中是 ptrtoint / inttoptr
+                let from = func.value_type(*val);
+                let op = match op {
+                    CastOp::Bitcast if from.is_ptr() && data.ty.is_int() => "ptrtoint",
+                    CastOp::Bitcast if from.is_int() && data.ty.is_ptr() => "inttoptr",
+                    _ => op.name(),
+                };
+                format!("{}{} {} to {}", def, op, self.typed(*val), ty)
+            }
+            Select {
+                cond,
+                then_val,
+                else_val,
+            } => format!(
+                "{}select {}, {}, {}",
+                def,
+                self.typed(*cond),
+                self.typed(*then_val),
+                self.typed(*else_val)
+            ),
+            Alloca { size, align } => {
+                format!("{}alloca [{} x i8], align {}", def, size, align.max(&1))
+            }
+            Load { ptr, volatile, .. } => {
+                let volatile = if *volatile { "volatile " } else { "" };
+                format!("{}load {}{}, ptr {}", def, volatile, ty, self.value(*ptr))
+            }
+            Store {
+                ptr, val, volatile, ..
+            } => {
+                let volatile = if *volatile { "volatile " } else { "" };
+                let (val, ptr) = (self.typed(*val), self.value(*ptr));
+                format!("store {}{}, ptr {}", volatile, val, ptr)
+            }
+            Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } => {
+                self.gep(&def, *base, *index, *scale, *offset);
+                return;
+            }
+            MemCopy {
+                dst,
+                src,
+                size,
+                align,
+            } => {
+                let (dst, src) = (self.value(*dst), self.value(*src));
+                self.memcpy(&dst, &src, *size, *align);
+                return;
+            }
+            Call { sig, callee, args } => {
+                self.call(&def, data.ty, sig, *callee, args);
+                return;
+            }
+            Phi { incomings } => {
+                let incomings: Vec<String> = incomings
+                    .iter()
+                    .map(|(block, value)| {
+                        format!(
+                            "[ {}, %{} ]",
+                            self.value(*value),
+                            self.printer.block(*block)
+                        )
+                    })
+                    .collect();
+                format!("{}phi {} {}", def, ty, incomings.join(", "))
+            }
+            VaStart { list } => {
+                self.used.va_start = true;
+                format!("call void @llvm.va_start(ptr {})", self.value(*list))
+            }
+            VaArg { list } => format!("{}va_arg ptr {}, {}", def, self.value(*list), ty),
+            VaEnd { list } => {
+                self.used.va_end = true;
+                format!("call void @llvm.va_end(ptr {})", self.value(*list))
+            }
+            VaCopy { dst, src } => {
+                self.used.va_copy = true;
+                let (dst, src) = (self.value(*dst), self.value(*src));
+                format!("call void @llvm.va_copy(ptr {}, ptr {})", dst, src)
+            }
+            Br { dest } => format!("br label %{}", self.printer.block(*dest)),
+            CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => format!(
+                "br {}, label %{}, label %{}",
+                self.typed(*cond),
+                self.printer.block(*then_dest),
+                self.printer.block(*else_dest)
+            ),
+            Switch {
+                val,
+                default,
+                cases,
+            } => {
+                let val_ty = func.value_type(*val);
+                let cases: Vec<String> = cases
+                    .iter()
+                    .map(|(x, block)| {
+                        let case = Value::Int {
+                            ty: val_ty,
+                            bits: *x,
+                        };
+                        format!(
+                            "{}, label %{}",
+                            self.typed(case),
+                            self.printer.block(*block)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "switch {}, label %{} [ {} ]",
+                    self.typed(*val),
+                    self.printer.block(*default),
+                    cases.join(" ")
+                )
+            }
+            Ret { val } => {
+                self.ret(*val);
+                return;
+            }
+            Unreachable => "unreachable".to_string(),
+        };
+        self.push(line);
+    }
+
+    /// 常量下标时合并为一个按字节的偏移，否则先按元素大小索引，再加上常量偏移
+    fn gep(&mut self, def: &str, base: Value, index: Value, scale: u64, offset: i64) {
+        let base = self.value(base);
+        if let Value::Int { ty, bits } = index {
+            let index = sign_extend(bits, ty.bits(8));
+            let offset = index.wrapping_mul(scale as i64).wrapping_add(offset);
+            self.push(format!(
+                "{}getelementptr i8, ptr {}, i64 {}",
+                def, base, offset
+            ));
+            return;
+        }
+        let elem = match scale {
+            1 => "i8".to_string(),
+            _ => format!("[{} x i8]", scale),
+        };
+        let index = self.typed(index);
+        if offset == 0 {
+            self.push(format!(
+                "{}getelementptr {}, ptr {}, {}",
+                def, elem, base, index
+            ));
+            return;
+        }
+        let ptr = self.temp();
+        self.push(format!(
+            "{} = getelementptr {}, ptr {}, {}",
+            ptr, elem, base, index
+        ));
+        self.push(format!(
+            "{}getelementptr i8, ptr {}, i64 {}",
+            def, ptr, offset
+        ));
+    }
+
+    fn call(&mut self, def: &str, ty: Type, sig: &Signature, callee: Value, args: &[Value]) {
+        let func = self.func;
+        let arg_types: Vec<Type> = args.iter().map(|x| func.value_type(*x)).collect();
+        let lowered = abi::lower(self.arch, sig, &arg_types);
+        let mut list = Vec::new();
+        let mut sret = None;
+        for (i, (arg, kind)) in args.iter().zip(lowered.args.iter()).enumerate() {
+            let value = self.value(*arg);
+            let (_, align) = agg_layout(sig, i);
+            match kind {
+                ArgKind::Direct => {
+                    let ty = arg_types[i];
+                    list.push(format!("{} {}", scalar_type(self.arch, ty, false), value));
+                }
+                ArgKind::Parts(parts) => {
+                    for part in parts.iter() {
+                        let ptr = self.offset_ptr(&value, part.offset);
+                        let piece = self.temp();
+                        self.push(format!(
+                            "{} = load {}, ptr {}, align {}",
+                            piece,
+                            part.ty,
+                            ptr,
+                            offset_align(align, part.offset)
+                        ));
+                        list.push(format!("{} {}", part.ty, piece));
+                    }
+                }
+                ArgKind::ByVal { size, align } => list.push(format!(
+                    "ptr byval([{} x i8]) align {} {}",
+                    size, align, value
+                )),
+                ArgKind::SRet { size, align } => list.push(format!(
+                    "ptr sret([{} x i8]) align {} {}",
+                    size, align, value
+                )),
+                ArgKind::Indirect { size, align } => {
+                    let copy = self.temp();
+                    self.prologue.push(format!(
+                        "{} = alloca [{} x i8], align {}",
+                        copy, size, align
+                    ));
+                    self.memcpy(&copy, &value, *size as u64, *align);
+                    list.push(format!("ptr {}", copy));
+                }
+                ArgKind::Ignored => sret = Some((value, align)),
+            }
+        }
+
+        let ret_ty = lowered.ret.ty(self.arch, ty);
+        let fn_ty = match sig.variadic {
+            true => {
+                let mut params = param_types(self.arch, sig, &lowered, false);
+                params.push("...".to_string());
+                format!("{} ({})", ret_ty, params.join(", "))
+            }
+            false => ret_ty.clone(),
+        };
+        let callee = self.value(callee);
+        let call = format!("call {} {}({})", fn_ty, callee, list.join(", "));
+        let RetKind::Parts(parts) = &lowered.ret else {
+            self.push(format!("{}{}", def, call));
+            return;
+        };
+
+        // 通过寄存器返回的聚合类型存到调用者提供的地址
+        let result = self.temp();
+        self.push(format!("{} = {}", result, call));
+        let (dst, align) = sret.unwrap();
+        for (j, part) in parts.iter().enumerate() {
+            let value = match parts.len() {
+                1 => result.clone(),
+                _ => {
+                    let value = self.temp();
+                    self.push(format!(
+                        "{} = extractvalue {} {}, {}",
+                        value, ret_ty, result, j
+                    ));
+                    value
+                }
+            };
+            let ptr = self.offset_ptr(&dst, part.offset);
+            self.push(format!(
+                "store {} {}, ptr {}, align {}",
+                part.ty,
+                value,
+                ptr,
+                offset_align(align, part.offset)
+            ));
+        }
+    }
+
+    fn ret(&mut self, val: Option<Value>) {
+        let RetKind::Parts(parts) = self.lowered.ret.clone() else {
+            let line = match val {
+                Some(val) => format!("ret {}", self.typed(val)),
+                None => "ret void".to_string(),
+            };
+            self.push(line);
+            return;
+        };
+
+        // `sret` 参数指向的局部对象拆开返回
+        let index = self.lowered.sret_index().unwrap();
+        let (_, align) = agg_layout(&self.func.sig, index);
+        let ret_ty = self.lowered.ret.ty(self.arch, Type::Void);
+        let base = format!("%a{}", index);
+        let mut agg = "undef".to_string();
+        for (j, part) in parts.iter().enumerate() {
+            let ptr = self.offset_ptr(&base, part.offset);
+            let value = self.temp();
+            self.push(format!(
+                "{} = load {}, ptr {}, align {}",
+                value,
+                part.ty,
+                ptr,
+                offset_align(align, part.offset)
+            ));
+            if parts.len() == 1 {
+                agg = value;
+                break;
+            }
+            let next = self.temp();
+            self.push(format!(
+                "{} = insertvalue {} {}, {} {}, {}",
+                next, ret_ty, agg, part.ty, value, j
+            ));
+            agg = next;
+        }
+        self.push(format!("ret {} {}", ret_ty, agg));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::codegen::riscv64::abi as rv;
+use crate::codegen::x86_64::abi as x86;
+use crate::codegen::x86_64::inst::XMM0;
+use crate::ir::{AggShape, ParamAttr, Signature, Type};
+use crate::target::Arch;
+
+///
+/// 按寄存器传递的聚合类型的一个片段
+///
+/// # Members
+/// - `offset`: 在对象中的偏移
+/// - `ty`: 片段的 LLVM 类型，如 `double` `<2 x float>` `i24`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    pub offset: u32,
+    pub ty: String,
+}
+
+///
+/// 参数在 LLVM 函数类型中的形式
+/// - `Direct`: 标量，原样传递
+/// - `Parts`: 小的聚合类型拆成几个标量参数
+/// - `ByVal`: 聚合类型整体复制到栈上，`ptr byval`
+/// - `SRet`: 调用者提供的返回值地址，`ptr sret`
+/// - `Indirect`: 调用者复制一份，传递副本的地址
+/// - `Ignored`: 不传递，聚合类型通过寄存器返回
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgKind {
+    Direct,
+    Parts(Vec<Part>),
+    ByVal { size: u32, align: u32 },
+    SRet { size: u32, align: u32 },
+    Indirect { size: u32, align: u32 },
+    Ignored,
+}
+
+///
+/// 返回值的形式
+/// - `Direct`: 标量或 `void`
+/// - `Parts`: `sret` 的聚合类型通过寄存器返回，一个片段时是标量，否则是字面结构体
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetKind {
+    Direct,
+    Parts(Vec<Part>),
+}
+
+///
+/// 一次调用或一个函数定义在 LLVM 中的签名
+///
+/// # Members
+/// - `args`: 每个参数（包括变参）的形式
+/// - `ret`: 返回值的形式
+///
+#[derive(Debug, Clone)]
+pub struct Lowered {
+    pub args: Vec<ArgKind>,
+    pub ret: RetKind,
+}
+
+impl Lowered {
+    /// `sret` 参数的下标，聚合类型通过寄存器返回时它的地址由这个参数给出
+    pub fn sret_index(&self) -> Option<usize> {
+        self.args.iter().position(|x| *x == ArgKind::Ignored)
+    }
+}
+
+impl RetKind {
+    /// 返回值的 LLVM 类型，带扩展属性，`ty` 是 IR 中的返回类型
+    pub fn ty(&self, arch: Arch, ty: Type) -> String {
+        match self {
+            RetKind::Direct => scalar_type(arch, ty, true),
+            RetKind::Parts(parts) if parts.len() == 1 => parts[0].ty.clone(),
+            RetKind::Parts(parts) => {
+                let types: Vec<&str> = parts.iter().map(|x| x.ty.as_str()).collect();
+                format!("{{ {} }}", types.join(", "))
+            }
+        }
+    }
+}
+
+/// 标量类型的 LLVM 名字
+pub fn llvm_type(ty: Type) -> &'static str {
+    match ty {
+        Type::F32 => "float",
+        Type::F64 => "double",
+        Type::Void => "void",
+        Type::I1 => "i1",
+        Type::I8 => "i8",
+        Type::I16 => "i16",
+        Type::I32 => "i32",
+        Type::I64 => "i64",
+        Type::Ptr => "ptr",
+    }
+}
+
+/// 标量参数和返回值的扩展属性，RISC-V LP64 中 `i32` 总是符号扩展到 64 位
+pub fn ext_attr(arch: Arch, ty: Type) -> Option<&'static str> {
+    match (arch, ty) {
+        (Arch::Riscv64, Type::I32) => Some("signext"),
+        _ => None,
+    }
+}
+
+/// 带扩展属性的标量类型，参数中属性在类型之后，返回值中在类型之前
+pub fn scalar_type(arch: Arch, ty: Type, ret: bool) -> String {
+    match (ext_attr(arch, ty), ret) {
+        (Some(attr), true) => format!("{} {}", attr, llvm_type(ty)),
+        (Some(attr), false) => format!("{} {}", llvm_type(ty), attr),
+        (None, _) => llvm_type(ty).to_string(),
+    }
+}
+
+///
+/// 按目标的调用约定把签名翻译为 LLVM 的形式
+///
+/// x86-64 和 RISC-V 复用各自后端的参数分类，保证和 rcc 自己生成的代码互相调用时一致；
+/// wasm32 的聚合类型总是通过内存传递
+///
+pub fn lower(arch: Arch, sig: &Signature, arg_types: &[Type]) -> Lowered {
+    match arch {
+        Arch::X86_64 => lower_x86_64(sig, arg_types),
+        Arch::Riscv64 => lower_riscv64(sig, arg_types),
+        Arch::Wasm32 => lower_memory(sig, arg_types),
+    }
+}
+
+/// 参数的 `ByVal` 或 `SRet` 属性中的大小和对齐
+fn agg(sig: &Signature, i: usize) -> (u32, u32) {
+    match sig.params.get(i).map(|x| x.attr) {
+        Some(ParamAttr::ByVal { size, align, .. } | ParamAttr::SRet { size, align, .. }) => {
+            (size, align)
+        }
+        _ => (0, 1),
+    }
+}
+
+fn lower_memory(sig: &Signature, arg_types: &[Type]) -> Lowered {
+    let args = (0..arg_types.len())
+        .map(|i| match sig.params.get(i).map(|x| x.attr) {
+            Some(ParamAttr::ByVal { size, align, .. }) => ArgKind::ByVal { size, align },
+            Some(ParamAttr::SRet { size, align, .. }) => ArgKind::SRet { size, align },
+            _ => ArgKind::Direct,
+        })
+        .collect();
+    Lowered {
+        args,
+        ret: RetKind::Direct,
+    }
+}
+
+/// SSE 片段的类型：一个 `double`、一个 `float` 或两个 `float`
+fn sse_type(shape: &AggShape, offset: u32, size: u32) -> String {
+    let floats = shape
+        .fields()
+        .iter()
+        .filter(|(x, ty)| (offset..offset + size).contains(&(*x as u32)) && *ty == Type::F32)
+        .count();
+    match floats {
+        0 => "double".to_string(),
+        1 => "float".to_string(),
+        _ => "<2 x float>".to_string(),
+    }
+}
+
+fn x86_parts(pieces: &[x86::Piece], shape: &AggShape) -> Vec<Part> {
+    pieces
+        .iter()
+        .map(|x| Part {
+            offset: x.offset,
+            ty: match x.reg >= XMM0 {
+                true => sse_type(shape, x.offset, x.size),
+                false => format!("i{}", x.size * 8),
+            },
+        })
+        .collect()
+}
+
+fn lower_x86_64(sig: &Signature, arg_types: &[Type]) -> Lowered {
+    let conv = x86::classify(sig, arg_types);
+    let shape = |i: usize| match sig.params.get(i).map(|x| x.attr) {
+        Some(ParamAttr::ByVal { shape, .. } | ParamAttr::SRet { shape, .. }) => shape,
+        _ => AggShape::default(),
+    };
+    let mut ret = RetKind::Direct;
+    let mut args = Vec::new();
+    for (i, loc) in conv.args.iter().enumerate() {
+        let (size, align) = agg(sig, i);
+        let kind = match loc {
+            x86::ArgLoc::Pieces(pieces) => ArgKind::Parts(x86_parts(pieces, &shape(i))),
+            x86::ArgLoc::StackAgg(_) => ArgKind::ByVal { size, align },
+            x86::ArgLoc::Ignored => {
+                if let x86::RetLoc::Pieces(pieces) = &conv.ret {
+                    ret = RetKind::Parts(x86_parts(pieces, &shape(i)));
+                }
+                ArgKind::Ignored
+            }
+            _ if matches!(
+                sig.params.get(i).map(|x| x.attr),
+                Some(ParamAttr::SRet { .. })
+            ) =>
+            {
+                ArgKind::SRet { size, align }
+            }
+            _ => ArgKind::Direct,
+        };
+        args.push(kind);
+    }
+    Lowered { args, ret }
+}
+
+fn riscv_parts(pieces: &[rv::Piece]) -> Vec<Part> {
+    pieces
+        .iter()
+        .map(|x| Part {
+            offset: x.offset,
+            ty: match (x.float, x.size) {
+                (true, 4) => "float".to_string(),
+                (true, _) => "double".to_string(),
+                (false, size) => format!("i{}", size * 8),
+            },
+        })
+        .collect()
+}
+
+fn lower_riscv64(sig: &Signature, arg_types: &[Type]) -> Lowered {
+    let conv = rv::classify(sig, arg_types);
+    let mut ret = RetKind::Direct;
+    let mut args = Vec::new();
+    for (i, loc) in conv.args.iter().enumerate() {
+        let (size, align) = agg(sig, i);
+        let kind = match loc {
+            rv::ArgLoc::Pieces(pieces) => ArgKind::Parts(riscv_parts(pieces)),
+            rv::ArgLoc::Indirect(_) => ArgKind::Indirect { size, align },
+            rv::ArgLoc::Ignored => {
+                if let rv::RetLoc::Pieces(pieces) = &conv.ret {
+                    ret = RetKind::Parts(riscv_parts(pieces));
+                }
+                ArgKind::Ignored
+            }
+            _ if matches!(
+                sig.params.get(i).map(|x| x.attr),
+                Some(ParamAttr::SRet { .. })
+            ) =>
+            {
+                ArgKind::SRet { size, align }
+            }
+            _ => ArgKind::Direct,
+        };
+        args.push(kind);
+    }
+    Lowered { args, ret }
+}
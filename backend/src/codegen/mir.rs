@@ -0,0 +1,197 @@
+use crate::ir::{InstId, Linkage, Visibility};
+use std::fmt::Debug;
+
+///
+/// 机器指令中的寄存器
+/// - `Phys`: 物理寄存器，编号由目标决定
+/// - `Virt`: 虚拟寄存器，寄存器分配后全部替换为物理寄存器
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Reg {
+    Phys(u8),
+    Virt(u32),
+}
+
+impl Reg {
+    pub fn is_virt(self) -> bool {
+        matches!(self, Reg::Virt(_))
+    }
+
+    /// 物理寄存器编号，虚拟寄存器返回 None
+    pub fn phys(self) -> Option<u8> {
+        match self {
+            Reg::Phys(x) => Some(x),
+            Reg::Virt(_) => None,
+        }
+    }
+}
+
+/// 寄存器类别，整数（包括指针）和浮点使用不同的寄存器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegClass {
+    Int,
+    Float,
+}
+
+/// 栈帧中的一个对象（`alloca`、溢出的虚拟寄存器等），偏移在栈帧布局时确定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StackSlot(pub u32);
+
+///
+/// 寄存器操作数的读写方式
+/// - `Use`: 只读
+/// - `Def`: 只写，写之前的值不需要
+/// - `UseDef`: 读后写，二地址指令的目标操作数
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Use,
+    Def,
+    UseDef,
+}
+
+impl Role {
+    pub fn is_use(self) -> bool {
+        matches!(self, Role::Use | Role::UseDef)
+    }
+
+    pub fn is_def(self) -> bool {
+        matches!(self, Role::Def | Role::UseDef)
+    }
+}
+
+///
+/// 目标的机器指令，寄存器分配通过这个 trait 与目标无关地处理指令
+///
+pub trait MachInst: Clone + Debug {
+    /// 访问指令中的所有寄存器操作数（包括隐含使用的物理寄存器），可以修改
+    fn visit_regs(&mut self, f: &mut dyn FnMut(&mut Reg, Role));
+
+    /// 寄存器之间的复制 `dst = src`
+    fn as_move(&self) -> Option<(Reg, Reg)>;
+
+    /// 跳转目标，基本块的下标
+    fn successors(&self) -> Vec<usize>;
+
+    /// 调用破坏的物理寄存器
+    fn clobbers(&self) -> &'static [u8];
+
+    fn gen_move(dst: Reg, src: Reg, class: RegClass) -> Self;
+
+    /// 把寄存器保存到栈上
+    fn gen_spill(slot: StackSlot, src: Reg, class: RegClass) -> Self;
+
+    /// 从栈上恢复寄存器
+    fn gen_reload(dst: Reg, slot: StackSlot, class: RegClass) -> Self;
+}
+
+///
+/// 一个寄存器类别中的物理寄存器
+///
+/// # Members
+/// - `allocatable`: 可以分配给虚拟寄存器的寄存器，按优先顺序排列，调用者保存的在前
+/// - `scratch`: 保留给溢出的虚拟寄存器临时使用，指令选择不会把它们作为固定寄存器
+///
+#[derive(Debug, Clone)]
+pub struct RegClassInfo {
+    pub allocatable: &'static [u8],
+    pub scratch: &'static [u8],
+}
+
+///
+/// 目标的寄存器信息，寄存器分配只通过它了解目标
+///
+/// # Members
+/// - `int` `float`: 两个寄存器类别
+/// - `callee_saved`: 被调用者保存的寄存器，用到时由函数序言保存
+/// - `spill_size`: 每个类别溢出时占用的栈空间
+///
+#[derive(Debug, Clone)]
+pub struct RegInfo {
+    pub int: RegClassInfo,
+    pub float: RegClassInfo,
+    pub callee_saved: &'static [u8],
+    pub spill_size: u32,
+}
+
+impl RegInfo {
+    pub fn class(&self, class: RegClass) -> &RegClassInfo {
+        match class {
+            RegClass::Int => &self.int,
+            RegClass::Float => &self.float,
+        }
+    }
+
+    pub fn scratch(&self, class: RegClass) -> &'static [u8] {
+        self.class(class).scratch
+    }
+}
+
+///
+/// 指令选择后的函数，基本块的下标就是跳转目标，第一个基本块是入口
+///
+/// # Members
+/// - `name` `linkage` `visibility`: 符号名、链接属性和可见性
+/// - `blocks`: 基本块，按输出顺序排列
+/// - `vregs`: 每个虚拟寄存器的类别
+/// - `slots`: 栈帧中对象的大小和对齐
+/// - `outgoing`: 调用时通过栈传递参数所需的最大空间
+/// - `allocas`: IR 的 `alloca` 对应的栈帧对象，用于调试信息中变量的位置
+///
+#[derive(Debug, Clone)]
+pub struct MFunction<I> {
+    pub name: String,
+    pub linkage: Linkage,
+    pub visibility: Visibility,
+    pub blocks: Vec<Vec<I>>,
+    pub vregs: Vec<RegClass>,
+    pub slots: Vec<(u64, u32)>,
+    pub outgoing: u64,
+    pub allocas: Vec<(InstId, StackSlot)>,
+}
+
+impl<I: MachInst> MFunction<I> {
+    pub fn new(name: impl Into<String>, linkage: Linkage, visibility: Visibility) -> Self {
+        Self {
+            name: name.into(),
+            linkage,
+            visibility,
+            blocks: Vec::new(),
+            vregs: Vec::new(),
+            slots: Vec::new(),
+            outgoing: 0,
+            allocas: Vec::new(),
+        }
+    }
+
+    pub fn new_vreg(&mut self, class: RegClass) -> Reg {
+        self.vregs.push(class);
+        Reg::Virt(self.vregs.len() as u32 - 1)
+    }
+
+    pub fn new_slot(&mut self, size: u64, align: u32) -> StackSlot {
+        self.slots.push((size, align));
+        StackSlot(self.slots.len() as u32 - 1)
+    }
+
+    pub fn new_block(&mut self) -> usize {
+        self.blocks.push(Vec::new());
+        self.blocks.len() - 1
+    }
+
+    /// 分配完成后用到的物理寄存器
+    pub fn used_phys(&self) -> Vec<u8> {
+        let mut used = Vec::new();
+        for inst in self.blocks.iter().flatten() {
+            inst.clone().visit_regs(&mut |reg, _| {
+                if let Reg::Phys(x) = reg
+                    && !used.contains(x)
+                {
+                    used.push(*x);
+                }
+            });
+        }
+        used.sort();
+        used
+    }
+}
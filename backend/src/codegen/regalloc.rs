@@ -0,0 +1,104 @@
+/// 寄存器分配，与目标无关，目标通过 `RegInfo` 描述寄存器类别
+/// # Contents
+/// - `liveness`: 活跃性分析，虚拟寄存器的活跃区间和物理寄存器的占用区间
+/// - `linear_scan`: 线性扫描分配，溢出槽复用，复制合并
+pub mod linear_scan;
+pub mod liveness;
+
+pub use linear_scan::allocate;
+
+use crate::codegen::mir::{MFunction, MachInst, Reg, RegInfo, Role, StackSlot};
+
+///
+/// 虚拟寄存器的位置
+/// - `Reg`: 整个活跃区间都在一个物理寄存器中
+/// - `Slot`: 溢出到栈上，每次使用时借用临时寄存器
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Reg(u8),
+    Slot(StackSlot),
+}
+
+///
+/// 最简单的寄存器分配：每个虚拟寄存器都放在栈上，
+/// 指令执行前把用到的虚拟寄存器读到临时寄存器，执行后把结果写回栈上
+///
+/// 指令选择保证一条指令中同一类别的虚拟寄存器不超过临时寄存器的个数
+///
+pub fn spill_all<I: MachInst>(func: &mut MFunction<I>, regs: &RegInfo) {
+    let locations: Vec<Option<Location>> = (0..func.vregs.len())
+        .map(|_| {
+            let slot = func.new_slot(regs.spill_size as u64, regs.spill_size);
+            Some(Location::Slot(slot))
+        })
+        .collect();
+    rewrite(func, regs, &locations);
+}
+
+/// 把虚拟寄存器替换为分配的位置，溢出的虚拟寄存器在指令前读取、指令后写回
+pub fn rewrite<I: MachInst>(
+    func: &mut MFunction<I>,
+    regs: &RegInfo,
+    locations: &[Option<Location>],
+) {
+    for block in func.blocks.iter_mut() {
+        let mut insts = Vec::with_capacity(block.len());
+        for mut inst in block.drain(..) {
+            // 每个溢出的虚拟寄存器在这条指令中的读写方式和分到的临时寄存器
+            let mut assigned: Vec<(u32, Role, u8)> = Vec::new();
+            let mut counts = [0usize; 2];
+            inst.visit_regs(&mut |reg, role| {
+                let Reg::Virt(v) = *reg else {
+                    return;
+                };
+                if let Some(Location::Reg(phys)) = locations[v as usize] {
+                    *reg = Reg::Phys(phys);
+                    return;
+                }
+                let phys = match assigned.iter_mut().find(|x| x.0 == v) {
+                    Some(x) => {
+                        if x.1 != role {
+                            x.1 = Role::UseDef;
+                        }
+                        x.2
+                    }
+                    None => {
+                        let class = func.vregs[v as usize];
+                        let count = &mut counts[class as usize];
+                        let phys = regs.scratch(class)[*count];
+                        *count += 1;
+                        assigned.push((v, role, phys));
+                        phys
+                    }
+                };
+                *reg = Reg::Phys(phys);
+            });
+
+            let slot_of = |v: u32| match locations[v as usize] {
+                Some(Location::Slot(slot)) => slot,
+                _ => unreachable!("virtual register {} has no stack slot", v),
+            };
+            for (v, role, phys) in assigned.iter() {
+                if role.is_use() {
+                    let class = func.vregs[*v as usize];
+                    insts.push(I::gen_reload(Reg::Phys(*phys), slot_of(*v), class));
+                }
+            }
+            // 分配到同一个寄存器的复制不再需要
+            if let Some((dst, src)) = inst.as_move()
+                && dst == src
+            {
+                continue;
+            }
+            insts.push(inst);
+            for (v, role, phys) in assigned.iter() {
+                if role.is_def() {
+                    let class = func.vregs[*v as usize];
+                    insts.push(I::gen_spill(slot_of(*v), Reg::Phys(*phys), class));
+                }
+            }
+        }
+        *block = insts;
+    }
+}
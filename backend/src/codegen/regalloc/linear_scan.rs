@@ -0,0 +1,110 @@
+use crate::codegen::mir::{MFunction, MachInst, Reg, RegInfo, StackSlot};
+use crate::codegen::regalloc::liveness::Intervals;
+use crate::codegen::regalloc::{Location, rewrite};
+
+///
+/// 线性扫描寄存器分配
+///
+/// 按开始位置依次处理虚拟寄存器的活跃区间，优先选择复制指令另一端的寄存器（复制合并），
+/// 避开被指令直接使用或被调用破坏的物理寄存器，所以跨过调用的区间只会分到被调用者保存的寄存器；
+/// 没有空闲寄存器时溢出结束最晚的区间，溢出的区间按活跃区间复用栈槽
+///
+/// 返回用到的被调用者保存的寄存器
+///
+pub fn allocate<I: MachInst>(func: &mut MFunction<I>, regs: &RegInfo) -> Vec<u8> {
+    let intervals = Intervals::build(func);
+    let hints = move_hints(func);
+    let range = |v: u32| intervals.vregs[v as usize].unwrap();
+
+    let mut order: Vec<u32> = (0..func.vregs.len() as u32)
+        .filter(|v| intervals.vregs[*v as usize].is_some())
+        .collect();
+    order.sort_by_key(|v| (range(*v).0, *v));
+
+    let mut assigned: Vec<Option<u8>> = vec![None; func.vregs.len()];
+    let mut active: Vec<u32> = Vec::new();
+    let mut spilled: Vec<u32> = Vec::new();
+    for v in order {
+        let (start, end) = range(v);
+        active.retain(|x| range(*x).1 >= start);
+        let class = func.vregs[v as usize];
+        let candidates = regs.class(class).allocatable;
+        let free = |reg: u8| {
+            candidates.contains(&reg)
+                && !active.iter().any(|x| assigned[*x as usize] == Some(reg))
+                && !intervals.conflicts(reg, start, end)
+        };
+        let hinted = hints[v as usize].iter().filter_map(|x| match x {
+            Reg::Phys(p) => Some(*p),
+            Reg::Virt(x) => assigned[*x as usize],
+        });
+        if let Some(reg) = hinted.chain(candidates.iter().copied()).find(|x| free(*x)) {
+            assigned[v as usize] = Some(reg);
+            active.push(v);
+            continue;
+        }
+
+        // 把寄存器从结束更晚的区间中抢过来
+        let victim = active
+            .iter()
+            .copied()
+            .filter(|x| func.vregs[*x as usize] == class && range(*x).1 > end)
+            .filter(|x| !intervals.conflicts(assigned[*x as usize].unwrap(), start, end))
+            .max_by_key(|x| range(*x).1);
+        match victim {
+            Some(x) => {
+                assigned[v as usize] = assigned[x as usize].take();
+                active.retain(|y| *y != x);
+                active.push(v);
+                spilled.push(x);
+            }
+            None => spilled.push(v),
+        }
+    }
+
+    let mut locations: Vec<Option<Location>> =
+        assigned.iter().map(|x| x.map(Location::Reg)).collect();
+    spilled.sort_by_key(|v| range(*v).0);
+    // 每个栈槽和它最后一个占用者的结束位置
+    let mut slots: Vec<(StackSlot, u32)> = Vec::new();
+    for v in spilled {
+        let (start, end) = range(v);
+        let slot = match slots.iter_mut().find(|x| x.1 < start) {
+            Some(x) => {
+                x.1 = end;
+                x.0
+            }
+            None => {
+                let slot = func.new_slot(regs.spill_size as u64, regs.spill_size);
+                slots.push((slot, end));
+                slot
+            }
+        };
+        locations[v as usize] = Some(Location::Slot(slot));
+    }
+
+    rewrite(func, regs, &locations);
+    let used = func.used_phys();
+    regs.callee_saved
+        .iter()
+        .copied()
+        .filter(|x| used.contains(x))
+        .collect()
+}
+
+/// 每个虚拟寄存器通过复制指令相连的寄存器
+fn move_hints<I: MachInst>(func: &MFunction<I>) -> Vec<Vec<Reg>> {
+    let mut hints = vec![Vec::new(); func.vregs.len()];
+    for inst in func.blocks.iter().flatten() {
+        let Some((dst, src)) = inst.as_move() else {
+            continue;
+        };
+        if let Reg::Virt(v) = dst {
+            hints[v as usize].push(src);
+        }
+        if let Reg::Virt(v) = src {
+            hints[v as usize].push(dst);
+        }
+    }
+    hints
+}
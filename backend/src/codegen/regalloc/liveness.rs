@@ -0,0 +1,179 @@
+use crate::codegen::mir::{MFunction, MachInst, Reg, Role};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// 第 `i` 条指令读操作数的位置
+pub fn use_pos(i: usize) -> u32 {
+    2 * i as u32
+}
+
+/// 第 `i` 条指令写操作数的位置，在读之后，所以同一条指令的源和目标可以共用寄存器
+pub fn def_pos(i: usize) -> u32 {
+    2 * i as u32 + 1
+}
+
+/// 指令的寄存器操作数
+pub fn operands<I: MachInst>(inst: &I) -> Vec<(Reg, Role)> {
+    let mut result = Vec::new();
+    inst.clone()
+        .visit_regs(&mut |reg, role| result.push((*reg, role)));
+    result
+}
+
+///
+/// 虚拟寄存器的活跃性
+///
+/// # Members
+/// - `live_in` `live_out`: 每个基本块入口和出口活跃的虚拟寄存器
+///
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    pub live_in: Vec<FxHashSet<u32>>,
+    pub live_out: Vec<FxHashSet<u32>>,
+}
+
+impl Liveness {
+    /// 反向数据流迭代到不动点
+    pub fn compute<I: MachInst>(func: &MFunction<I>) -> Self {
+        let n = func.blocks.len();
+        let mut uses = vec![FxHashSet::default(); n];
+        let mut defs = vec![FxHashSet::default(); n];
+        let mut succs = vec![Vec::new(); n];
+        for (b, block) in func.blocks.iter().enumerate() {
+            for inst in block.iter() {
+                for (reg, role) in operands(inst) {
+                    let Reg::Virt(v) = reg else {
+                        continue;
+                    };
+                    if role.is_use() && !defs[b].contains(&v) {
+                        uses[b].insert(v);
+                    }
+                }
+                for (reg, role) in operands(inst) {
+                    if let (Reg::Virt(v), true) = (reg, role.is_def()) {
+                        defs[b].insert(v);
+                    }
+                }
+                for succ in inst.successors() {
+                    if !succs[b].contains(&succ) {
+                        succs[b].push(succ);
+                    }
+                }
+            }
+        }
+
+        let mut live_in: Vec<FxHashSet<u32>> = uses.clone();
+        let mut live_out = vec![FxHashSet::default(); n];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for b in (0..n).rev() {
+                let out: FxHashSet<u32> = succs[b]
+                    .iter()
+                    .flat_map(|x| live_in[*x].iter().copied())
+                    .collect();
+                let mut input = uses[b].clone();
+                input.extend(out.iter().filter(|x| !defs[b].contains(x)));
+                if input.len() != live_in[b].len() || out.len() != live_out[b].len() {
+                    changed = true;
+                }
+                live_in[b] = input;
+                live_out[b] = out;
+            }
+        }
+        Self { live_in, live_out }
+    }
+}
+
+///
+/// 寄存器分配使用的区间，位置见 `use_pos` `def_pos`
+///
+/// # Members
+/// - `vregs`: 每个虚拟寄存器的活跃区间（闭区间），覆盖所有活跃的位置，不出现的虚拟寄存器为 None
+/// - `fixed`: 每个物理寄存器被指令直接使用或被调用破坏的区间
+///
+#[derive(Debug, Clone)]
+pub struct Intervals {
+    pub vregs: Vec<Option<(u32, u32)>>,
+    pub fixed: FxHashMap<u8, Vec<(u32, u32)>>,
+}
+
+impl Intervals {
+    pub fn build<I: MachInst>(func: &MFunction<I>) -> Self {
+        let liveness = Liveness::compute(func);
+        let mut intervals = Self {
+            vregs: vec![None; func.vregs.len()],
+            fixed: FxHashMap::default(),
+        };
+        let mut index = 0;
+        for (b, block) in func.blocks.iter().enumerate() {
+            if block.is_empty() {
+                continue;
+            }
+            let first = index;
+            let last = index + block.len() - 1;
+            for v in liveness.live_in[b].iter() {
+                intervals.extend(*v, use_pos(first));
+            }
+            for v in liveness.live_out[b].iter() {
+                intervals.extend(*v, def_pos(last));
+            }
+            // 物理寄存器只在基本块内活跃：从写入到最后一次读取，没有写入时从基本块开头开始
+            let mut open: FxHashMap<u8, u32> = FxHashMap::default();
+            for (i, inst) in block.iter().enumerate() {
+                let i = index + i;
+                let ops = operands(inst);
+                for (reg, role) in ops.iter() {
+                    match (*reg, role.is_use()) {
+                        (Reg::Virt(v), true) => intervals.extend(v, use_pos(i)),
+                        (Reg::Phys(p), true) => {
+                            let start = open.get(&p).copied().unwrap_or(use_pos(first));
+                            intervals.add_fixed(p, start, use_pos(i));
+                        }
+                        _ => {}
+                    }
+                }
+                for (reg, role) in ops.iter() {
+                    match (*reg, role.is_def()) {
+                        (Reg::Virt(v), true) => intervals.extend(v, def_pos(i)),
+                        (Reg::Phys(p), true) => {
+                            open.insert(p, def_pos(i));
+                            intervals.add_fixed(p, def_pos(i), def_pos(i));
+                        }
+                        _ => {}
+                    }
+                }
+                // 调用破坏的寄存器不能分配给跨过调用的虚拟寄存器
+                for p in inst.clobbers() {
+                    intervals.add_fixed(*p, def_pos(i), def_pos(i));
+                }
+            }
+            index = last + 1;
+        }
+        for ranges in intervals.fixed.values_mut() {
+            ranges.sort();
+        }
+        intervals
+    }
+
+    fn extend(&mut self, v: u32, pos: u32) {
+        let range = &mut self.vregs[v as usize];
+        *range = Some(match *range {
+            Some((start, end)) => (start.min(pos), end.max(pos)),
+            None => (pos, pos),
+        });
+    }
+
+    fn add_fixed(&mut self, reg: u8, start: u32, end: u32) {
+        self.fixed.entry(reg).or_default().push((start, end));
+    }
+
+    /// 物理寄存器在 `[start, end]` 中是否被指令直接占用
+    pub fn conflicts(&self, reg: u8, start: u32, end: u32) -> bool {
+        let Some(ranges) = self.fixed.get(&reg) else {
+            return false;
+        };
+        // 区间按开始位置排序，只需要看开始位置不超过 end 的
+        let count = ranges.partition_point(|x| x.0 <= end);
+        ranges[..count].iter().any(|x| x.1 >= start)
+    }
+}
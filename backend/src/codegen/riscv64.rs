@@ -0,0 +1,35 @@
+/// RV64GC 后端，LP64D 调用约定，输出 GNU 汇编
+/// # Contents
+/// - `inst`: 机器指令和寄存器
+/// - `abi`: LP64D 调用约定，参数和返回值的位置，小的聚合类型按成员拆开
+/// - `isel`: 指令选择
+/// - `emit`: 栈帧布局和汇编输出
+pub mod abi;
+pub mod emit;
+pub mod inst;
+pub mod isel;
+
+use crate::codegen::TargetIsa;
+use crate::err::codegen_error::CodegenResult;
+use crate::ir::Module;
+use crate::target::TargetInfo;
+
+pub struct Riscv64 {
+    info: TargetInfo,
+}
+
+impl Riscv64 {
+    pub fn new(info: TargetInfo) -> Self {
+        Self { info }
+    }
+}
+
+impl TargetIsa for Riscv64 {
+    fn info(&self) -> &TargetInfo {
+        &self.info
+    }
+
+    fn emit_asm(&self, module: &Module) -> CodegenResult<String> {
+        emit::emit_module(module)
+    }
+}
@@ -0,0 +1,248 @@
+use crate::codegen::riscv64::inst::{A0, A1, FA0, FLOAT_ARGS, INT_ARGS};
+use crate::ir::{AggShape, ParamAttr, Signature, Type};
+
+///
+/// 片段的位置
+/// - `Reg`: 寄存器
+/// - `Stack`: 栈上，偏移相对于参数区域的开头
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceLoc {
+    Reg(u8),
+    Stack(u64),
+}
+
+///
+/// 小的聚合类型拆开后的一个片段
+///
+/// # Members
+/// - `offset` `size`: 在对象中的位置
+/// - `float`: 是否是浮点成员，为 true 时 `size` 为 4 或 8
+/// - `loc`: 位置，整数约定下剩下的寄存器不够时后一半在栈上
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece {
+    pub offset: u32,
+    pub size: u32,
+    pub float: bool,
+    pub loc: PieceLoc,
+}
+
+///
+/// 参数的位置
+/// - `Reg`: 标量在寄存器中，浮点数可能在整数寄存器中（变参，或浮点寄存器用完）
+/// - `Stack`: 标量在栈上
+/// - `Pieces`: 不超过 16 字节的聚合类型拆开传递
+/// - `Indirect`: 大的聚合类型由调用者复制，传递副本的地址，地址本身的位置为 `Reg` 或 `Stack`
+/// - `Ignored`: 不传递，通过寄存器返回聚合类型时的 `sret` 参数
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgLoc {
+    Reg(u8),
+    Stack(u64),
+    Pieces(Vec<Piece>),
+    Indirect(Box<ArgLoc>),
+    Ignored,
+}
+
+///
+/// 返回值的位置
+/// - `Reg`: 标量在 `a0` 或 `fa0` 中
+/// - `Pieces`: 聚合类型放在 `a0` `a1` `fa0` `fa1` 中
+/// - `Memory`: 调用者通过 `a0` 提供地址
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetLoc {
+    None,
+    Reg(u8),
+    Pieces(Vec<Piece>),
+    Memory,
+}
+
+///
+/// 一次调用的参数和返回值位置
+///
+/// # Members
+/// - `args`: 每个参数（包括变参）的位置
+/// - `ret`: 返回值的位置
+/// - `stack_size`: 栈上参数区域的大小，对齐到 16 字节
+/// - `gp_used`: 用掉的整数参数寄存器个数，变参函数据此保存剩下的寄存器
+/// - `stack_used`: 参数实际占用的栈空间，变参函数的 `va_start` 从这里之后开始读栈上的参数
+///
+#[derive(Debug, Clone)]
+pub struct CallConv {
+    pub args: Vec<ArgLoc>,
+    pub ret: RetLoc,
+    pub stack_size: u64,
+    pub gp_used: usize,
+    pub stack_used: u64,
+}
+
+/// 寄存器分配的状态
+struct Alloc {
+    gp: usize,
+    fp: usize,
+    stack: u64,
+    int_regs: &'static [u8],
+    float_regs: &'static [u8],
+}
+
+impl Alloc {
+    fn new(int_regs: &'static [u8], float_regs: &'static [u8]) -> Self {
+        Self {
+            gp: 0,
+            fp: 0,
+            stack: 0,
+            int_regs,
+            float_regs,
+        }
+    }
+
+    fn gp_left(&self) -> usize {
+        self.int_regs.len() - self.gp
+    }
+
+    fn fp_left(&self) -> usize {
+        self.float_regs.len() - self.fp
+    }
+
+    fn next_gp(&mut self) -> Option<u8> {
+        let reg = self.int_regs.get(self.gp).copied()?;
+        self.gp += 1;
+        Some(reg)
+    }
+
+    fn next_fp(&mut self) -> Option<u8> {
+        let reg = self.float_regs.get(self.fp).copied()?;
+        self.fp += 1;
+        Some(reg)
+    }
+
+    fn stack(&mut self) -> u64 {
+        self.stack += 8;
+        self.stack - 8
+    }
+
+    /// 标量：浮点优先用浮点寄存器，然后是整数寄存器，最后是栈
+    fn scalar(&mut self, float: bool) -> ArgLoc {
+        let reg = match float {
+            true => self.next_fp().or_else(|| self.next_gp()),
+            false => self.next_gp(),
+        };
+        match reg {
+            Some(reg) => ArgLoc::Reg(reg),
+            None => ArgLoc::Stack(self.stack()),
+        }
+    }
+
+    ///
+    /// 浮点约定：展开后只有一个浮点成员，两个浮点成员，或者一个浮点一个整数成员，
+    /// 并且寄存器足够时，浮点成员放在浮点寄存器中
+    ///
+    fn float_pieces(&mut self, shape: &AggShape) -> Option<Vec<Piece>> {
+        let fields = shape.fields();
+        if fields.is_empty() || fields.len() > 2 || !fields.iter().any(|x| x.1.is_float()) {
+            return None;
+        }
+        let n_float = fields.iter().filter(|x| x.1.is_float()).count();
+        if self.fp_left() < n_float || self.gp_left() < fields.len() - n_float {
+            return None;
+        }
+        let pieces = fields
+            .iter()
+            .map(|(offset, ty)| {
+                let reg = match ty.is_float() {
+                    true => self.next_fp(),
+                    false => self.next_gp(),
+                };
+                Piece {
+                    offset: *offset as u32,
+                    size: ty.bytes(8),
+                    float: ty.is_float(),
+                    loc: PieceLoc::Reg(reg.unwrap()),
+                }
+            })
+            .collect();
+        Some(pieces)
+    }
+
+    /// 整数约定：按 8 字节拆开，依次放在整数寄存器中，不够时放在栈上
+    fn int_pieces(&mut self, size: u32) -> Vec<Piece> {
+        (0..size.div_ceil(8))
+            .map(|i| {
+                let loc = match self.next_gp() {
+                    Some(reg) => PieceLoc::Reg(reg),
+                    None => PieceLoc::Stack(self.stack()),
+                };
+                Piece {
+                    offset: i * 8,
+                    size: (size - i * 8).min(8),
+                    float: false,
+                    loc,
+                }
+            })
+            .collect()
+    }
+
+    fn aggregate(&mut self, size: u32, shape: &AggShape, named: bool) -> ArgLoc {
+        if size > 16 {
+            let loc = self.scalar(false);
+            return ArgLoc::Indirect(Box::new(loc));
+        }
+        let pieces = match named {
+            true => self.float_pieces(shape),
+            false => None,
+        };
+        ArgLoc::Pieces(pieces.unwrap_or_else(|| self.int_pieces(size)))
+    }
+}
+
+/// 按 LP64D 计算参数和返回值的位置，`arg_types` 是实际参数（包括变参）的类型
+pub fn classify(sig: &Signature, arg_types: &[Type]) -> CallConv {
+    let mut alloc = Alloc::new(&INT_ARGS, &FLOAT_ARGS);
+    let mut args = Vec::new();
+    let mut ret = match sig.ret {
+        Type::Void => RetLoc::None,
+        ty if ty.is_float() => RetLoc::Reg(FA0),
+        _ => RetLoc::Reg(A0),
+    };
+
+    for (i, ty) in arg_types.iter().enumerate() {
+        let named = i < sig.params.len();
+        let attr = sig.params.get(i).map(|x| x.attr).unwrap_or_default();
+        let loc = match attr {
+            ParamAttr::SRet { size, shape, .. } => {
+                let mut ret_alloc = Alloc::new(&[A0, A1], &[FA0, FA0 + 1]);
+                let pieces = match size <= 16 {
+                    true => Some(
+                        ret_alloc
+                            .float_pieces(&shape)
+                            .unwrap_or_else(|| ret_alloc.int_pieces(size)),
+                    ),
+                    false => None,
+                };
+                match pieces {
+                    Some(pieces) => {
+                        ret = RetLoc::Pieces(pieces);
+                        ArgLoc::Ignored
+                    }
+                    None => {
+                        ret = RetLoc::Memory;
+                        alloc.scalar(false)
+                    }
+                }
+            }
+            ParamAttr::ByVal { size, shape, .. } => alloc.aggregate(size, &shape, named),
+            ParamAttr::None => alloc.scalar(named && ty.is_float()),
+        };
+        args.push(loc);
+    }
+
+    CallConv {
+        args,
+        ret,
+        stack_size: alloc.stack.next_multiple_of(16),
+        gp_used: alloc.gp,
+        stack_used: alloc.stack,
+    }
+}
@@ -0,0 +1,211 @@
+use crate::codegen::asm::{emit_data, emit_linkage};
+use crate::codegen::mir::{MFunction, Reg, RegClassInfo, RegInfo};
+use crate::codegen::regalloc::allocate;
+use crate::codegen::riscv64::inst::*;
+use crate::codegen::riscv64::isel::select;
+use crate::err::codegen_error::{CodegenError, CodegenResult};
+use crate::ir::Module;
+use std::fmt::Write;
+
+///
+/// 寄存器类别：`zero` `ra` `sp` `gp` `tp` `s0` 不参与分配，`t0` 留给汇编输出计算大偏移，
+/// `t4` - `t6` 和 `ft9` - `ft11` 留给溢出的虚拟寄存器（三地址指令最多用到三个）
+///
+pub const REG_INFO: RegInfo = RegInfo {
+    int: RegClassInfo {
+        allocatable: &[
+            10, 11, 12, 13, 14, 15, 16, 17, 6, 7, 28, 9, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+        ],
+        scratch: &[T5 - 1, T5, T6],
+    },
+    float: RegClassInfo {
+        allocatable: &[
+            32, 33, 34, 35, 36, 37, 38, 39, 60, 42, 43, 44, 45, 46, 47, 48, 49, 40, 41, 50, 51, 52,
+            53, 54, 55, 56, 57, 58, 59,
+        ],
+        scratch: &[FT10 - 1, FT10, FT11],
+    },
+    callee_saved: &CALLEE_SAVED,
+    spill_size: 8,
+};
+
+/// 变参函数在返回地址上面保存 `a0` - `a7`
+const VA_SAVE_AREA: u64 = 64;
+
+/// 生成整个模块的汇编
+pub fn emit_module(module: &Module) -> CodegenResult<String> {
+    // 还没有实现 RISC-V 的 TLS 访问序列
+    if let Some(global) = module.globals.values().find(|x| x.thread_local) {
+        return Err(CodegenError::ThreadLocal {
+            arch: "riscv64",
+            name: global.name.clone(),
+        });
+    }
+    let mut out = String::new();
+    let funcs: Vec<_> = module
+        .func_ids()
+        .into_iter()
+        .filter(|x| !module.funcs[*x].is_declaration())
+        .collect();
+    if !funcs.is_empty() {
+        writeln!(out, "\t.text").unwrap();
+    }
+    for id in funcs {
+        let func = &module.funcs[id];
+        let mut mf = select(module, func)?;
+        let saved = allocate(&mut mf, &REG_INFO);
+        let top = match func.sig.variadic {
+            true => VA_SAVE_AREA,
+            false => 0,
+        };
+        emit_function(&mut out, &mut mf, top, &saved);
+    }
+    emit_data(&mut out, module);
+    Ok(out)
+}
+
+///
+/// 栈帧布局，`s0` 是进入函数时的 `sp`：
+///
+/// ```text
+/// 调用者的栈参数       0(s0) 开始
+/// 变参寄存器保存区域    -64(s0) 开始，只有变参函数有
+/// 返回地址
+/// 保存的 s0
+/// 被调用者保存的寄存器
+/// 栈帧中的对象         负偏移
+/// 调用时的栈参数       0(sp) 开始
+/// ```
+///
+/// 返回栈帧的总大小
+///
+fn layout_frame(func: &mut MFunction<RvInst>, top: u64, saved: usize) -> u64 {
+    let mut cursor = top + 16 + saved as u64 * 8;
+    let mut offsets = Vec::with_capacity(func.slots.len());
+    for (size, align) in func.slots.iter() {
+        cursor = (cursor + size).next_multiple_of((*align).clamp(1, 16) as u64);
+        offsets.push(-(cursor as i64));
+    }
+    for inst in func.blocks.iter_mut().flatten() {
+        if let Some(mem) = mem_operand(inst) {
+            match mem.base {
+                Base::Slot(slot) => {
+                    mem.base = Base::Reg(Reg::Phys(S0));
+                    mem.disp += offsets[slot.0 as usize];
+                }
+                Base::Incoming => mem.base = Base::Reg(Reg::Phys(S0)),
+                Base::Reg(_) => {}
+            }
+        }
+    }
+    (cursor + func.outgoing).next_multiple_of(16)
+}
+
+fn mem_operand(inst: &mut RvInst) -> Option<&mut Mem> {
+    match inst {
+        RvInst::Lea { mem, .. }
+        | RvInst::Load { mem, .. }
+        | RvInst::Store { mem, .. }
+        | RvInst::FLoad { mem, .. }
+        | RvInst::FStore { mem, .. } => Some(mem),
+        _ => None,
+    }
+}
+
+fn emit_inst(out: &mut String, inst: &RvInst, label: &dyn Fn(usize) -> String) {
+    out.push('\t');
+    inst.fmt_asm(out, label).unwrap();
+    out.push('\n');
+}
+
+/// 偏移超出 12 位时先用 `t0` 算出地址
+fn emit_mem_inst(out: &mut String, inst: &RvInst, label: &dyn Fn(usize) -> String) {
+    let mut inst = inst.clone();
+    if let Some(mem) = mem_operand(&mut inst)
+        && !fits_imm12(mem.disp)
+    {
+        let Base::Reg(base) = mem.base else {
+            unreachable!()
+        };
+        let t0 = Reg::Phys(T0);
+        for x in materialize(t0, mem.disp) {
+            emit_inst(out, &x, label);
+        }
+        writeln!(out, "\tadd t0, t0, {}", reg_name(base)).unwrap();
+        *mem = Mem::reg(t0, 0);
+    }
+    emit_inst(out, &inst, label);
+}
+
+/// `sp` 加上一个可能超出 12 位的数
+fn adjust_sp(out: &mut String, delta: i64) {
+    if delta == 0 {
+        return;
+    }
+    if fits_imm12(delta) {
+        writeln!(out, "\taddi sp, sp, {}", delta).unwrap();
+        return;
+    }
+    for x in materialize(Reg::Phys(T0), delta) {
+        emit_inst(out, &x, &|_| unreachable!());
+    }
+    writeln!(out, "\tadd sp, sp, t0").unwrap();
+}
+
+fn emit_function(out: &mut String, func: &mut MFunction<RvInst>, top: u64, saved: &[u8]) {
+    let frame = layout_frame(func, top, saved.len());
+    let name = func.name.clone();
+    let label = |x: usize| format!(".LBB_{}_{}", name, x);
+    let link = top as i64 + 16;
+    let save = |reg: u8, k: usize| {
+        let op = match reg >= F0 {
+            true => "fsd",
+            false => "sd",
+        };
+        format!(
+            "{} {}, {}(s0)",
+            op,
+            reg_name(Reg::Phys(reg)),
+            -link - 8 * (k as i64 + 1)
+        )
+    };
+
+    emit_linkage(out, &name, func.linkage, func.visibility);
+    writeln!(out, "\t.p2align 2").unwrap();
+    writeln!(out, "\t.type {}, @function", name).unwrap();
+    writeln!(out, "{}:", name).unwrap();
+    writeln!(out, "\taddi sp, sp, {}", -link).unwrap();
+    writeln!(out, "\tsd ra, 8(sp)").unwrap();
+    writeln!(out, "\tsd s0, 0(sp)").unwrap();
+    writeln!(out, "\taddi s0, sp, {}", link).unwrap();
+    adjust_sp(out, link - frame as i64);
+    for (k, reg) in saved.iter().enumerate() {
+        writeln!(out, "\t{}", save(*reg, k)).unwrap();
+    }
+
+    for (i, block) in func.blocks.iter().enumerate() {
+        if i > 0 {
+            writeln!(out, "{}:", label(i)).unwrap();
+        }
+        for (j, inst) in block.iter().enumerate() {
+            match inst {
+                // 跳转到紧接着的基本块时省略
+                RvInst::Jmp { target } if *target == i + 1 && j + 1 == block.len() => {}
+                RvInst::Ret { .. } => {
+                    for (k, reg) in saved.iter().enumerate() {
+                        let restore = save(*reg, k).replacen("sd", "ld", 1);
+                        writeln!(out, "\t{}", restore).unwrap();
+                    }
+                    writeln!(out, "\taddi sp, s0, {}", -link).unwrap();
+                    writeln!(out, "\tld ra, 8(sp)").unwrap();
+                    writeln!(out, "\tld s0, 0(sp)").unwrap();
+                    writeln!(out, "\taddi sp, sp, {}", link).unwrap();
+                    writeln!(out, "\tret").unwrap();
+                }
+                _ => emit_mem_inst(out, inst, &label),
+            }
+        }
+    }
+    writeln!(out, "\t.size {}, .-{}", name, name).unwrap();
+    writeln!(out).unwrap();
+}
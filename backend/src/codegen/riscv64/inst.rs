@@ -0,0 +1,751 @@
+use crate::codegen::mir::{MachInst, Reg, RegClass, Role, StackSlot};
+use crate::ir::value::sign_extend;
+use std::fmt::{Display, Formatter};
+
+pub const ZERO: u8 = 0;
+pub const RA: u8 = 1;
+pub const SP: u8 = 2;
+pub const T0: u8 = 5;
+pub const S0: u8 = 8;
+pub const S1: u8 = 9;
+pub const A0: u8 = 10;
+pub const A1: u8 = 11;
+pub const A2: u8 = 12;
+pub const T5: u8 = 30;
+pub const T6: u8 = 31;
+/// `f0`，`f{n}` 的编号为 `F0 + n`
+pub const F0: u8 = 32;
+pub const FA0: u8 = F0 + 10;
+pub const FT10: u8 = F0 + 30;
+pub const FT11: u8 = F0 + 31;
+
+/// 整数参数寄存器 a0 - a7
+pub const INT_ARGS: [u8; 8] = [10, 11, 12, 13, 14, 15, 16, 17];
+/// 浮点参数寄存器 fa0 - fa7
+pub const FLOAT_ARGS: [u8; 8] = [42, 43, 44, 45, 46, 47, 48, 49];
+
+/// 调用者保存的寄存器：ra t0-t6 a0-a7 ft0-ft11 fa0-fa7
+pub const CALLER_SAVED: [u8; 36] = [
+    1, 5, 6, 7, 10, 11, 12, 13, 14, 15, 16, 17, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 42,
+    43, 44, 45, 46, 47, 48, 49, 60, 61, 62, 63,
+];
+
+/// 被调用者保存的寄存器：s1-s11 fs0-fs11（s0 是帧指针）
+pub const CALLEE_SAVED: [u8; 23] = [
+    9, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 40, 41, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59,
+];
+
+const INT_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+const FLOAT_NAMES: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+    "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+    "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+/// I 型指令立即数的范围
+pub fn fits_imm12(x: i64) -> bool {
+    (-2048..2048).contains(&x)
+}
+
+///
+/// 内存操作数的基址
+/// - `Reg`: 寄存器
+/// - `Slot`: 栈帧中的对象，布局后替换为 `s0` 加偏移
+/// - `Incoming`: 调用者通过栈传递的参数区域，从 `s0`（进入函数时的 `sp`）开始；
+///   变参函数的寄存器保存区域紧挨在它下面，偏移为负
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base {
+    Reg(Reg),
+    Slot(StackSlot),
+    Incoming,
+}
+
+/// 内存操作数 `disp(base)`，偏移超出 12 位时由汇编输出借助 `t0` 处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mem {
+    pub base: Base,
+    pub disp: i64,
+}
+
+impl Mem {
+    pub fn reg(reg: Reg, disp: i64) -> Self {
+        Self {
+            base: Base::Reg(reg),
+            disp,
+        }
+    }
+
+    pub fn slot(slot: StackSlot, disp: i64) -> Self {
+        Self {
+            base: Base::Slot(slot),
+            disp,
+        }
+    }
+
+    pub fn incoming(disp: i64) -> Self {
+        Self {
+            base: Base::Incoming,
+            disp,
+        }
+    }
+
+    pub fn offset(&self, disp: i64) -> Self {
+        Self {
+            base: self.base.clone(),
+            disp: self.disp + disp,
+        }
+    }
+
+    fn visit_regs(&mut self, f: &mut dyn FnMut(&mut Reg, Role)) {
+        if let Base::Reg(reg) = &mut self.base {
+            f(reg, Role::Use);
+        }
+    }
+}
+
+/// 第二个源操作数：寄存器或 12 位立即数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Src {
+    Reg(Reg),
+    Imm(i64),
+}
+
+///
+/// 整数运算，带立即数的形式只有 `add` `and` `or` `xor` `sll` `srl` `sra` `slt` `sltu`，
+/// `w` 形式只有 `add` `sub` `mul` `div` `divu` `rem` `remu` `sll` `srl` `sra`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+    And,
+    Or,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+    Slt,
+    Sltu,
+}
+
+impl AluOp {
+    fn name(self) -> &'static str {
+        match self {
+            AluOp::Add => "add",
+            AluOp::Sub => "sub",
+            AluOp::Mul => "mul",
+            AluOp::Div => "div",
+            AluOp::Divu => "divu",
+            AluOp::Rem => "rem",
+            AluOp::Remu => "remu",
+            AluOp::And => "and",
+            AluOp::Or => "or",
+            AluOp::Xor => "xor",
+            AluOp::Sll => "sll",
+            AluOp::Srl => "srl",
+            AluOp::Sra => "sra",
+            AluOp::Slt => "slt",
+            AluOp::Sltu => "sltu",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// 浮点比较，结果写到整数寄存器，NaN 时为 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FCmpOp {
+    Eq,
+    Lt,
+    Le,
+}
+
+/// 条件跳转
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrCond {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+impl BrCond {
+    fn name(self) -> &'static str {
+        match self {
+            BrCond::Eq => "beq",
+            BrCond::Ne => "bne",
+            BrCond::Lt => "blt",
+            BrCond::Ge => "bge",
+            BrCond::Ltu => "bltu",
+            BrCond::Geu => "bgeu",
+        }
+    }
+}
+
+/// 调用目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallTarget {
+    Sym(String),
+    Reg(Reg),
+}
+
+///
+/// RV64GC 机器指令，三地址形式
+///
+/// 浮点指令的 `double` 为 true 时是 `.d` 版本，否则是 `.s` 版本；整数和浮点转换的
+/// `wide` 为 true 时整数是 64 位（`l`），否则是 32 位（`w`）；
+/// `Call` 的 `uses` `defs` 是参数和返回值使用的物理寄存器，`Ret` 展开为函数尾声
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RvInst {
+    /// `lui`，`imm` 是 20 位的高位
+    Lui {
+        dst: Reg,
+        imm: i64,
+    },
+    Alu {
+        op: AluOp,
+        w: bool,
+        dst: Reg,
+        lhs: Reg,
+        rhs: Src,
+    },
+    Mv {
+        dst: Reg,
+        src: Reg,
+    },
+    /// `lla`，符号的地址
+    La {
+        dst: Reg,
+        sym: String,
+    },
+    /// `addi`，内存操作数的地址
+    Lea {
+        dst: Reg,
+        mem: Mem,
+    },
+    Load {
+        size: u32,
+        unsigned: bool,
+        dst: Reg,
+        mem: Mem,
+    },
+    Store {
+        size: u32,
+        src: Reg,
+        mem: Mem,
+    },
+    FLoad {
+        double: bool,
+        dst: Reg,
+        mem: Mem,
+    },
+    FStore {
+        double: bool,
+        src: Reg,
+        mem: Mem,
+    },
+    FAlu {
+        op: FloatOp,
+        double: bool,
+        dst: Reg,
+        lhs: Reg,
+        rhs: Reg,
+    },
+    FNeg {
+        double: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    FMv {
+        double: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    FCmp {
+        op: FCmpOp,
+        double: bool,
+        dst: Reg,
+        lhs: Reg,
+        rhs: Reg,
+    },
+    /// `fcvt.d.l` 等
+    CvtIntToFloat {
+        double: bool,
+        wide: bool,
+        unsigned: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    /// `fcvt.l.d` 等，向零舍入
+    CvtFloatToInt {
+        double: bool,
+        wide: bool,
+        unsigned: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    /// `fcvt.d.s` / `fcvt.s.d`
+    CvtFloat {
+        to_double: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    /// `fmv.d.x` / `fmv.w.x`，按位复制
+    MovToFloat {
+        double: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    /// `fmv.x.d` / `fmv.x.w`
+    MovFromFloat {
+        double: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    Branch {
+        cond: BrCond,
+        lhs: Reg,
+        rhs: Reg,
+        target: usize,
+    },
+    Jmp {
+        target: usize,
+    },
+    Call {
+        target: CallTarget,
+        uses: Vec<u8>,
+        defs: Vec<u8>,
+    },
+    Ret {
+        uses: Vec<u8>,
+    },
+    Unimp,
+}
+
+fn visit_fixed(regs: &mut [u8], role: Role, f: &mut dyn FnMut(&mut Reg, Role)) {
+    for x in regs.iter_mut() {
+        let mut reg = Reg::Phys(*x);
+        f(&mut reg, role);
+    }
+}
+
+impl MachInst for RvInst {
+    fn visit_regs(&mut self, f: &mut dyn FnMut(&mut Reg, Role)) {
+        use RvInst::*;
+        match self {
+            Lui { dst, .. } | La { dst, .. } => f(dst, Role::Def),
+            Alu { dst, lhs, rhs, .. } => {
+                f(lhs, Role::Use);
+                if let Src::Reg(rhs) = rhs {
+                    f(rhs, Role::Use);
+                }
+                f(dst, Role::Def);
+            }
+            FAlu { dst, lhs, rhs, .. } | FCmp { dst, lhs, rhs, .. } => {
+                f(lhs, Role::Use);
+                f(rhs, Role::Use);
+                f(dst, Role::Def);
+            }
+            Mv { dst, src }
+            | FNeg { dst, src, .. }
+            | FMv { dst, src, .. }
+            | CvtIntToFloat { dst, src, .. }
+            | CvtFloatToInt { dst, src, .. }
+            | CvtFloat { dst, src, .. }
+            | MovToFloat { dst, src, .. }
+            | MovFromFloat { dst, src, .. } => {
+                f(src, Role::Use);
+                f(dst, Role::Def);
+            }
+            Lea { dst, mem } | Load { dst, mem, .. } | FLoad { dst, mem, .. } => {
+                mem.visit_regs(f);
+                f(dst, Role::Def);
+            }
+            Store { src, mem, .. } | FStore { src, mem, .. } => {
+                f(src, Role::Use);
+                mem.visit_regs(f);
+            }
+            Branch { lhs, rhs, .. } => {
+                f(lhs, Role::Use);
+                f(rhs, Role::Use);
+            }
+            Call { target, uses, defs } => {
+                if let CallTarget::Reg(reg) = target {
+                    f(reg, Role::Use);
+                }
+                visit_fixed(uses, Role::Use, f);
+                visit_fixed(defs, Role::Def, f);
+            }
+            Ret { uses } => visit_fixed(uses, Role::Use, f),
+            Jmp { .. } | Unimp => {}
+        }
+    }
+
+    fn as_move(&self) -> Option<(Reg, Reg)> {
+        match self {
+            RvInst::Mv { dst, src }
+            | RvInst::FMv {
+                double: true,
+                dst,
+                src,
+            } => Some((*dst, *src)),
+            _ => None,
+        }
+    }
+
+    fn successors(&self) -> Vec<usize> {
+        match self {
+            RvInst::Jmp { target } | RvInst::Branch { target, .. } => vec![*target],
+            _ => Vec::new(),
+        }
+    }
+
+    fn clobbers(&self) -> &'static [u8] {
+        match self {
+            RvInst::Call { .. } => &CALLER_SAVED,
+            _ => &[],
+        }
+    }
+
+    fn gen_move(dst: Reg, src: Reg, class: RegClass) -> Self {
+        match class {
+            RegClass::Int => RvInst::Mv { dst, src },
+            RegClass::Float => RvInst::FMv {
+                double: true,
+                dst,
+                src,
+            },
+        }
+    }
+
+    fn gen_spill(slot: StackSlot, src: Reg, class: RegClass) -> Self {
+        let mem = Mem::slot(slot, 0);
+        match class {
+            RegClass::Int => RvInst::Store { size: 8, src, mem },
+            RegClass::Float => RvInst::FStore {
+                double: true,
+                src,
+                mem,
+            },
+        }
+    }
+
+    fn gen_reload(dst: Reg, slot: StackSlot, class: RegClass) -> Self {
+        let mem = Mem::slot(slot, 0);
+        match class {
+            RegClass::Int => RvInst::Load {
+                size: 8,
+                unsigned: false,
+                dst,
+                mem,
+            },
+            RegClass::Float => RvInst::FLoad {
+                double: true,
+                dst,
+                mem,
+            },
+        }
+    }
+}
+
+/// 把 64 位立即数展开为 `lui` `addi(w)` `slli` 序列
+pub fn materialize(dst: Reg, imm: i64) -> Vec<RvInst> {
+    let mut out = Vec::new();
+    materialize_into(dst, imm, &mut out);
+    out
+}
+
+fn materialize_into(dst: Reg, imm: i64, out: &mut Vec<RvInst>) {
+    let lo12 = sign_extend(imm as u64, 12);
+    let addi = |w: bool, lhs: Reg| RvInst::Alu {
+        op: AluOp::Add,
+        w,
+        dst,
+        lhs,
+        rhs: Src::Imm(lo12),
+    };
+    if i32::try_from(imm).is_ok() {
+        // lui 的结果符号扩展，用 addiw 保证结果在 32 位内回绕
+        let hi20 = ((imm + 0x800) >> 12) & 0xfffff;
+        if hi20 == 0 {
+            out.push(addi(false, Reg::Phys(ZERO)));
+            return;
+        }
+        out.push(RvInst::Lui { dst, imm: hi20 });
+        if lo12 != 0 {
+            out.push(addi(true, dst));
+        }
+        return;
+    }
+    // 先生成去掉低 12 位并右移到最低非零位的值，再左移回来
+    let hi52 = ((imm as u64).wrapping_add(0x800) >> 12) as i64;
+    let shift = 12 + hi52.trailing_zeros();
+    let hi = sign_extend((hi52 >> (shift - 12)) as u64, 64 - shift);
+    materialize_into(dst, hi, out);
+    out.push(RvInst::Alu {
+        op: AluOp::Sll,
+        w: false,
+        dst,
+        lhs: dst,
+        rhs: Src::Imm(shift as i64),
+    });
+    if lo12 != 0 {
+        out.push(addi(false, dst));
+    }
+}
+
+/// 寄存器名
+pub fn reg_name(reg: Reg) -> String {
+    match reg {
+        Reg::Phys(x) if x >= F0 => FLOAT_NAMES[(x - F0) as usize].to_string(),
+        Reg::Phys(x) => INT_NAMES[x as usize].to_string(),
+        Reg::Virt(x) => format!("%v{}", x),
+    }
+}
+
+struct R(Reg);
+
+impl Display for R {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", reg_name(self.0))
+    }
+}
+
+impl Display for Mem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.base {
+            Base::Reg(reg) => write!(f, "{}({})", self.disp, R(*reg)),
+            Base::Slot(x) => write!(f, "{}(slot{})", self.disp, x.0),
+            Base::Incoming => write!(f, "{}(s0)", self.disp),
+        }
+    }
+}
+
+fn fsuffix(double: bool) -> &'static str {
+    match double {
+        true => "d",
+        false => "s",
+    }
+}
+
+fn isuffix(wide: bool, unsigned: bool) -> &'static str {
+    match (wide, unsigned) {
+        (true, false) => "l",
+        (true, true) => "lu",
+        (false, false) => "w",
+        (false, true) => "wu",
+    }
+}
+
+impl RvInst {
+    /// GNU 汇编语法，`label` 给出基本块的标签名；`Ret` 由调用者展开
+    pub fn fmt_asm(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        label: &dyn Fn(usize) -> String,
+    ) -> std::fmt::Result {
+        use RvInst::*;
+        match self {
+            Lui { dst, imm } => write!(f, "lui {}, {}", R(*dst), imm),
+            Alu {
+                op,
+                w,
+                dst,
+                lhs,
+                rhs,
+            } => {
+                let w = if *w { "w" } else { "" };
+                match rhs {
+                    Src::Reg(rhs) => write!(
+                        f,
+                        "{}{} {}, {}, {}",
+                        op.name(),
+                        w,
+                        R(*dst),
+                        R(*lhs),
+                        R(*rhs)
+                    ),
+                    Src::Imm(imm) => match op {
+                        AluOp::Sltu => write!(f, "sltiu {}, {}, {}", R(*dst), R(*lhs), imm),
+                        _ => write!(f, "{}i{} {}, {}, {}", op.name(), w, R(*dst), R(*lhs), imm),
+                    },
+                }
+            }
+            Mv { dst, src } => write!(f, "mv {}, {}", R(*dst), R(*src)),
+            La { dst, sym } => write!(f, "lla {}, {}", R(*dst), sym),
+            Lea { dst, mem } => match &mem.base {
+                Base::Reg(base) => write!(f, "addi {}, {}, {}", R(*dst), R(*base), mem.disp),
+                _ => write!(f, "addi {}, {}", R(*dst), mem),
+            },
+            Load {
+                size,
+                unsigned,
+                dst,
+                mem,
+            } => {
+                let name = match size {
+                    1 => "lb",
+                    2 => "lh",
+                    4 => "lw",
+                    _ => "ld",
+                };
+                let u = if *unsigned && *size < 8 { "u" } else { "" };
+                write!(f, "{}{} {}, {}", name, u, R(*dst), mem)
+            }
+            Store { size, src, mem } => {
+                let name = match size {
+                    1 => "sb",
+                    2 => "sh",
+                    4 => "sw",
+                    _ => "sd",
+                };
+                write!(f, "{} {}, {}", name, R(*src), mem)
+            }
+            FLoad { double, dst, mem } => {
+                let name = if *double { "fld" } else { "flw" };
+                write!(f, "{} {}, {}", name, R(*dst), mem)
+            }
+            FStore { double, src, mem } => {
+                let name = if *double { "fsd" } else { "fsw" };
+                write!(f, "{} {}, {}", name, R(*src), mem)
+            }
+            FAlu {
+                op,
+                double,
+                dst,
+                lhs,
+                rhs,
+            } => {
+                let name = match op {
+                    FloatOp::Add => "fadd",
+                    FloatOp::Sub => "fsub",
+                    FloatOp::Mul => "fmul",
+                    FloatOp::Div => "fdiv",
+                };
+                write!(
+                    f,
+                    "{}.{} {}, {}, {}",
+                    name,
+                    fsuffix(*double),
+                    R(*dst),
+                    R(*lhs),
+                    R(*rhs)
+                )
+            }
+            FNeg { double, dst, src } => {
+                write!(f, "fneg.{} {}, {}", fsuffix(*double), R(*dst), R(*src))
+            }
+            FMv { double, dst, src } => {
+                write!(f, "fmv.{} {}, {}", fsuffix(*double), R(*dst), R(*src))
+            }
+            FCmp {
+                op,
+                double,
+                dst,
+                lhs,
+                rhs,
+            } => {
+                let name = match op {
+                    FCmpOp::Eq => "feq",
+                    FCmpOp::Lt => "flt",
+                    FCmpOp::Le => "fle",
+                };
+                write!(
+                    f,
+                    "{}.{} {}, {}, {}",
+                    name,
+                    fsuffix(*double),
+                    R(*dst),
+                    R(*lhs),
+                    R(*rhs)
+                )
+            }
+            CvtIntToFloat {
+                double,
+                wide,
+                unsigned,
+                dst,
+                src,
+            } => write!(
+                f,
+                "fcvt.{}.{} {}, {}",
+                fsuffix(*double),
+                isuffix(*wide, *unsigned),
+                R(*dst),
+                R(*src)
+            ),
+            CvtFloatToInt {
+                double,
+                wide,
+                unsigned,
+                dst,
+                src,
+            } => write!(
+                f,
+                "fcvt.{}.{} {}, {}, rtz",
+                isuffix(*wide, *unsigned),
+                fsuffix(*double),
+                R(*dst),
+                R(*src)
+            ),
+            CvtFloat {
+                to_double,
+                dst,
+                src,
+            } => match to_double {
+                true => write!(f, "fcvt.d.s {}, {}", R(*dst), R(*src)),
+                false => write!(f, "fcvt.s.d {}, {}", R(*dst), R(*src)),
+            },
+            MovToFloat { double, dst, src } => {
+                let name = if *double { "fmv.d.x" } else { "fmv.w.x" };
+                write!(f, "{} {}, {}", name, R(*dst), R(*src))
+            }
+            MovFromFloat { double, dst, src } => {
+                let name = if *double { "fmv.x.d" } else { "fmv.x.w" };
+                write!(f, "{} {}, {}", name, R(*dst), R(*src))
+            }
+            Branch {
+                cond,
+                lhs,
+                rhs,
+                target,
+            } => {
+                write!(
+                    f,
+                    "{} {}, {}, {}",
+                    cond.name(),
+                    R(*lhs),
+                    R(*rhs),
+                    label(*target)
+                )
+            }
+            Jmp { target } => write!(f, "j {}", label(*target)),
+            Call { target, .. } => match target {
+                CallTarget::Sym(name) => write!(f, "call {}", name),
+                CallTarget::Reg(reg) => write!(f, "jalr {}", R(*reg)),
+            },
+            Ret { .. } => write!(f, "ret"),
+            Unimp => write!(f, "unimp"),
+        }
+    }
+}
@@ -0,0 +1,1308 @@
+use crate::codegen::mir::{MFunction, Reg, RegClass, StackSlot};
+use crate::codegen::riscv64::abi::{ArgLoc, CallConv, Piece, PieceLoc, RetLoc, classify};
+use crate::codegen::riscv64::inst::*;
+use crate::err::codegen_error::{CodegenError, CodegenResult};
+use crate::ir::value::sign_extend;
+use crate::ir::{
+    AbiParam, BinaryOp, BlockId, CastOp, CmpPred, Function, InstId, InstKind, Module, ParamAttr,
+    Signature, Type, Value,
+};
+use rustc_hash::FxHashSet;
+use slotmap::SecondaryMap;
+
+/// 变参函数的寄存器保存区域，a0 - a7，紧挨在调用者的栈参数下面
+const VA_SAVE_AREA: i64 = 8 * 8;
+
+/// 内联展开 `memcpy` 的最大指令数，更多的调用 `memcpy`
+const INLINE_COPY: u64 = 16;
+
+///
+/// RV64 指令选择，把 IR 函数转换为使用虚拟寄存器的机器指令
+///
+/// 寄存器中不足 64 位的整数只保证低位有效（`i1` 除外，总是 0 或 1），
+/// 比较、除法、右移、转换等需要高位的地方再显式扩展
+///
+/// # Members
+/// - `mf`: 生成的机器函数
+/// - `cur`: 当前的机器基本块
+/// - `blocks`: IR 基本块对应的机器基本块
+/// - `values`: 有结果的指令对应的虚拟寄存器
+/// - `args`: 参数对应的虚拟寄存器，聚合类型参数是副本的地址
+/// - `allocas`: `alloca` 对应的栈对象
+/// - `deferred`: 只被条件跳转使用的比较，在跳转处生成
+/// - `ret`: 返回值的位置
+/// - `sret`: 通过寄存器返回聚合类型时的局部缓冲区，或调用者提供的地址
+/// - `va_start`: 变参函数第一个变参相对于调用者栈参数区域的偏移
+///
+pub struct Isel<'a> {
+    module: &'a Module,
+    func: &'a Function,
+    mf: MFunction<RvInst>,
+    cur: usize,
+    blocks: SecondaryMap<BlockId, usize>,
+    values: SecondaryMap<InstId, Reg>,
+    args: Vec<Reg>,
+    allocas: SecondaryMap<InstId, StackSlot>,
+    deferred: FxHashSet<InstId>,
+    ret: RetLoc,
+    sret: Option<Reg>,
+    va_start: Option<i64>,
+}
+
+fn class_of(ty: Type) -> RegClass {
+    match ty.is_float() {
+        true => RegClass::Float,
+        false => RegClass::Int,
+    }
+}
+
+fn is_fpr(reg: u8) -> bool {
+    reg >= F0
+}
+
+/// 整数类型的字节数，`i1` 按一个字节处理
+fn bytes_of(ty: Type) -> u32 {
+    ty.bytes(8).max(1)
+}
+
+fn zero() -> Reg {
+    Reg::Phys(ZERO)
+}
+
+/// 指令选择，生成的函数还没有分配寄存器
+pub fn select(module: &Module, func: &Function) -> CodegenResult<MFunction<RvInst>> {
+    let mut isel = Isel {
+        module,
+        func,
+        mf: MFunction::new(func.name.clone(), func.linkage, func.visibility),
+        cur: 0,
+        blocks: SecondaryMap::new(),
+        values: SecondaryMap::new(),
+        args: Vec::new(),
+        allocas: SecondaryMap::new(),
+        deferred: FxHashSet::default(),
+        ret: RetLoc::None,
+        sret: None,
+        va_start: None,
+    };
+    isel.run()?;
+    Ok(isel.mf)
+}
+
+impl<'a> Isel<'a> {
+    fn run(&mut self) -> CodegenResult<()> {
+        let func = self.func;
+        self.cur = self.mf.new_block();
+        for block in func.layout.iter() {
+            let index = self.mf.new_block();
+            self.blocks.insert(*block, index);
+        }
+        for (_, inst) in func.inst_iter() {
+            let data = &func.insts[inst];
+            match &data.kind {
+                InstKind::Alloca { size, align } => {
+                    let slot = self.mf.new_slot(*size, *align);
+                    self.allocas.insert(inst, slot);
+                }
+                _ if data.ty != Type::Void => {
+                    let reg = self.mf.new_vreg(class_of(data.ty));
+                    self.values.insert(inst, reg);
+                }
+                _ => {}
+            }
+        }
+        self.find_deferred();
+        self.entry();
+        let entry = self.blocks[func.entry()];
+        self.push(RvInst::Jmp { target: entry });
+
+        for block in func.layout.iter() {
+            self.cur = self.blocks[*block];
+            for inst in func.blocks[*block].insts.iter() {
+                self.inst(*block, *inst)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unsupported<T>(&self, msg: impl Into<String>) -> CodegenResult<T> {
+        Err(CodegenError::Unsupported {
+            arch: "riscv64",
+            func: self.func.name.clone(),
+            msg: msg.into(),
+        })
+    }
+
+    /// 只被同一个基本块的条件跳转使用的比较
+    fn find_deferred(&mut self) {
+        let uses = self.func.use_counts();
+        for block in self.func.layout.iter() {
+            let Some(term) = self.func.terminator(*block) else {
+                continue;
+            };
+            let InstKind::CondBr {
+                cond: Value::Inst(cond),
+                ..
+            } = self.func.insts[term].kind
+            else {
+                continue;
+            };
+            if self.func.inst_block(cond) == Some(*block)
+                && uses.get(cond).copied() == Some(1)
+                && matches!(self.func.insts[cond].kind, InstKind::Cmp { .. })
+            {
+                self.deferred.insert(cond);
+            }
+        }
+    }
+
+    fn push(&mut self, inst: RvInst) {
+        self.mf.blocks[self.cur].push(inst);
+    }
+
+    fn vreg(&mut self, class: RegClass) -> Reg {
+        self.mf.new_vreg(class)
+    }
+
+    fn value_type(&self, value: Value) -> Type {
+        self.func.value_type(value)
+    }
+
+    /// 整数常量，按有符号解释，`i1` 的真为 1
+    fn const_int(&self, value: Value) -> Option<i64> {
+        match value {
+            Value::Int { ty: Type::I1, bits } => Some(bits as i64),
+            _ => value.as_int(),
+        }
+    }
+
+    fn li(&mut self, imm: i64) -> Reg {
+        if imm == 0 {
+            return zero();
+        }
+        let dst = self.vreg(RegClass::Int);
+        for inst in materialize(dst, imm) {
+            self.push(inst);
+        }
+        dst
+    }
+
+    /// 值放到一个寄存器中，常量和地址在使用处生成
+    fn reg(&mut self, value: Value) -> Reg {
+        match value {
+            Value::Inst(x) => match self.allocas.get(x).copied() {
+                Some(slot) => {
+                    let dst = self.vreg(RegClass::Int);
+                    let mem = Mem::slot(slot, 0);
+                    self.push(RvInst::Lea { dst, mem });
+                    dst
+                }
+                None => self.values[x],
+            },
+            Value::Arg(x) => self.args[x as usize],
+            Value::Int { .. } => self.li(self.const_int(value).unwrap()),
+            Value::Float { ty, bits } => {
+                let src = self.li(bits as i64);
+                let dst = self.vreg(RegClass::Float);
+                self.push(RvInst::MovToFloat {
+                    double: ty == Type::F64,
+                    dst,
+                    src,
+                });
+                dst
+            }
+            Value::Global(_) | Value::Func(_) => {
+                let dst = self.vreg(RegClass::Int);
+                let sym = self.module.symbol_name(value).to_string();
+                self.push(RvInst::La { dst, sym });
+                dst
+            }
+            Value::Undef(ty) => match ty.is_float() {
+                true => self.reg(Value::Float { ty, bits: 0 }),
+                false => zero(),
+            },
+        }
+    }
+
+    /// 第二个源操作数，12 位以内的常量直接使用
+    fn src(&mut self, value: Value) -> Src {
+        match self.const_int(value) {
+            Some(x) if fits_imm12(x) => Src::Imm(x),
+            _ => Src::Reg(self.reg(value)),
+        }
+    }
+
+    /// 指针指向的内存，`alloca` 直接寻址
+    fn mem(&mut self, ptr: Value, disp: i64) -> Mem {
+        match ptr {
+            Value::Inst(x) if self.allocas.contains_key(x) => Mem::slot(self.allocas[x], disp),
+            _ => Mem::reg(self.reg(ptr), disp),
+        }
+    }
+
+    fn alu(&mut self, op: AluOp, w: bool, lhs: Reg, rhs: Src) -> Reg {
+        let dst = self.vreg(RegClass::Int);
+        self.push(RvInst::Alu {
+            op,
+            w,
+            dst,
+            lhs,
+            rhs,
+        });
+        dst
+    }
+
+    fn mv(&mut self, dst: Reg, src: Reg) {
+        self.push(RvInst::Mv { dst, src });
+    }
+
+    /// 同类寄存器之间复制
+    fn copy(&mut self, class: RegClass, dst: Reg, src: Reg) {
+        match class {
+            RegClass::Int => self.mv(dst, src),
+            RegClass::Float => self.push(RvInst::FMv {
+                double: true,
+                dst,
+                src,
+            }),
+        }
+    }
+
+    /// 符号扩展到 64 位
+    fn sext(&mut self, value: Reg, ty: Type) -> Reg {
+        match bytes_of(ty) {
+            8 => value,
+            _ if ty == Type::I1 => value,
+            4 => self.alu(AluOp::Add, true, value, Src::Imm(0)),
+            bytes => {
+                let shift = 64 - bytes as i64 * 8;
+                let tmp = self.alu(AluOp::Sll, false, value, Src::Imm(shift));
+                self.alu(AluOp::Sra, false, tmp, Src::Imm(shift))
+            }
+        }
+    }
+
+    /// 零扩展到 64 位
+    fn zext(&mut self, value: Reg, ty: Type) -> Reg {
+        match bytes_of(ty) {
+            8 => value,
+            _ if ty == Type::I1 => value,
+            1 => self.alu(AluOp::And, false, value, Src::Imm(0xff)),
+            bytes => {
+                let shift = 64 - bytes as i64 * 8;
+                let tmp = self.alu(AluOp::Sll, false, value, Src::Imm(shift));
+                self.alu(AluOp::Srl, false, tmp, Src::Imm(shift))
+            }
+        }
+    }
+
+    fn load(&mut self, ty: Type, dst: Reg, mem: Mem) {
+        match ty.is_float() {
+            true => self.push(RvInst::FLoad {
+                double: ty == Type::F64,
+                dst,
+                mem,
+            }),
+            false => self.push(RvInst::Load {
+                size: bytes_of(ty),
+                unsigned: ty == Type::I1,
+                dst,
+                mem,
+            }),
+        }
+    }
+
+    fn store(&mut self, ty: Type, src: Reg, mem: Mem) {
+        match ty.is_float() {
+            true => self.push(RvInst::FStore {
+                double: ty == Type::F64,
+                src,
+                mem,
+            }),
+            false => self.push(RvInst::Store {
+                size: bytes_of(ty),
+                src,
+                mem,
+            }),
+        }
+    }
+
+    /// 从内存读取 `size` 字节（1 到 8）到整数寄存器，不是 2 的幂时逐字节拼接
+    fn load_bytes(&mut self, mem: Mem, size: u32) -> Reg {
+        let dst = self.vreg(RegClass::Int);
+        if size.is_power_of_two() {
+            self.push(RvInst::Load {
+                size,
+                unsigned: false,
+                dst,
+                mem,
+            });
+            return dst;
+        }
+        self.mv(dst, zero());
+        for i in 0..size {
+            let byte = self.vreg(RegClass::Int);
+            self.push(RvInst::Load {
+                size: 1,
+                unsigned: true,
+                dst: byte,
+                mem: mem.offset(i as i64),
+            });
+            let shifted = self.alu(AluOp::Sll, false, byte, Src::Imm(i as i64 * 8));
+            self.push(RvInst::Alu {
+                op: AluOp::Or,
+                w: false,
+                dst,
+                lhs: dst,
+                rhs: Src::Reg(shifted),
+            });
+        }
+        dst
+    }
+
+    /// 把整数寄存器的低 `size` 字节写到内存
+    fn store_bytes(&mut self, mem: Mem, value: Reg, size: u32) {
+        if size.is_power_of_two() {
+            self.push(RvInst::Store {
+                size,
+                src: value,
+                mem,
+            });
+            return;
+        }
+        for i in 0..size {
+            let byte = self.alu(AluOp::Srl, false, value, Src::Imm(i as i64 * 8));
+            self.push(RvInst::Store {
+                size: 1,
+                src: byte,
+                mem: mem.offset(i as i64),
+            });
+        }
+    }
+
+    /// 聚合类型的一个片段读到寄存器中
+    fn load_piece(&mut self, mem: Mem, piece: &Piece) -> Reg {
+        let mem = mem.offset(piece.offset as i64);
+        match piece.float {
+            true => {
+                let dst = self.vreg(RegClass::Float);
+                self.push(RvInst::FLoad {
+                    double: piece.size == 8,
+                    dst,
+                    mem,
+                });
+                dst
+            }
+            false => self.load_bytes(mem, piece.size),
+        }
+    }
+
+    fn store_piece(&mut self, mem: Mem, piece: &Piece, value: Reg) {
+        let mem = mem.offset(piece.offset as i64);
+        match piece.float {
+            true => self.push(RvInst::FStore {
+                double: piece.size == 8,
+                src: value,
+                mem,
+            }),
+            false => self.store_bytes(mem, value, piece.size),
+        }
+    }
+
+    fn piece_class(piece: &Piece) -> RegClass {
+        match piece.float {
+            true => RegClass::Float,
+            false => RegClass::Int,
+        }
+    }
+
+    /// 复制内存，按对齐逐块复制，太大时调用 `memcpy`
+    fn memcpy(&mut self, dst: Mem, src: Mem, size: u64, align: u32) {
+        let chunk = (align as u64).clamp(1, 8);
+        if size.div_ceil(chunk) <= INLINE_COPY {
+            let mut offset = 0;
+            while offset < size {
+                let mut step = chunk;
+                while step > size - offset {
+                    step /= 2;
+                }
+                let tmp = self.vreg(RegClass::Int);
+                self.push(RvInst::Load {
+                    size: step as u32,
+                    unsigned: false,
+                    dst: tmp,
+                    mem: src.offset(offset as i64),
+                });
+                self.push(RvInst::Store {
+                    size: step as u32,
+                    src: tmp,
+                    mem: dst.offset(offset as i64),
+                });
+                offset += step;
+            }
+            return;
+        }
+        let size = self.li(size as i64);
+        self.push(RvInst::Lea {
+            dst: Reg::Phys(A0),
+            mem: dst,
+        });
+        self.push(RvInst::Lea {
+            dst: Reg::Phys(A1),
+            mem: src,
+        });
+        self.mv(Reg::Phys(A2), size);
+        self.push(RvInst::Call {
+            target: CallTarget::Sym("memcpy".to_string()),
+            uses: vec![A0, A1, A2],
+            defs: vec![A0],
+        });
+    }
+
+    /// 值从参数寄存器复制到虚拟寄存器，浮点数可能在整数寄存器中
+    fn copy_arg_reg(&mut self, ty: Type, reg: u8) -> Reg {
+        let dst = self.vreg(class_of(ty));
+        match (ty.is_float(), is_fpr(reg)) {
+            (true, false) => self.push(RvInst::MovToFloat {
+                double: ty == Type::F64,
+                dst,
+                src: Reg::Phys(reg),
+            }),
+            _ => self.copy(class_of(ty), dst, Reg::Phys(reg)),
+        }
+        dst
+    }
+
+    fn set_arg_reg(&mut self, ty: Type, reg: u8, value: Reg) {
+        match (ty.is_float(), is_fpr(reg)) {
+            (true, false) => self.push(RvInst::MovFromFloat {
+                double: ty == Type::F64,
+                dst: Reg::Phys(reg),
+                src: value,
+            }),
+            _ => self.copy(class_of(ty), Reg::Phys(reg), value),
+        }
+    }
+
+    /// 入口：参数从 ABI 规定的位置复制到虚拟寄存器
+    fn entry(&mut self) {
+        let sig = &self.func.sig;
+        let types: Vec<Type> = sig.params.iter().map(|x| x.ty).collect();
+        let conv = classify(sig, &types);
+
+        // 先把所有参数寄存器复制出来，后面的代码可能会使用这些寄存器
+        let mut incoming = Vec::new();
+        for (loc, ty) in conv.args.iter().zip(types.iter()) {
+            let regs: Vec<Reg> = match loc {
+                ArgLoc::Reg(r) => vec![self.copy_arg_reg(*ty, *r)],
+                ArgLoc::Indirect(ptr) => match **ptr {
+                    ArgLoc::Reg(r) => vec![self.copy_arg_reg(Type::Ptr, r)],
+                    _ => Vec::new(),
+                },
+                ArgLoc::Pieces(pieces) => pieces
+                    .iter()
+                    .filter_map(|piece| match piece.loc {
+                        PieceLoc::Reg(r) => {
+                            let class = Self::piece_class(piece);
+                            let dst = self.vreg(class);
+                            self.copy(class, dst, Reg::Phys(r));
+                            Some(dst)
+                        }
+                        PieceLoc::Stack(_) => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            incoming.push(regs);
+        }
+        if sig.variadic {
+            // 没有被固定参数使用的参数寄存器保存到栈参数区域的下面，和栈上的变参连续
+            for (i, reg) in INT_ARGS.iter().enumerate().skip(conv.gp_used) {
+                self.push(RvInst::Store {
+                    size: 8,
+                    src: Reg::Phys(*reg),
+                    mem: Mem::incoming(i as i64 * 8 - VA_SAVE_AREA),
+                });
+            }
+            self.va_start = Some(match conv.gp_used < INT_ARGS.len() {
+                true => conv.gp_used as i64 * 8 - VA_SAVE_AREA,
+                false => conv.stack_used as i64,
+            });
+        }
+
+        for (i, (loc, regs)) in conv.args.iter().zip(incoming).enumerate() {
+            let ty = types[i];
+            let reg = match loc {
+                ArgLoc::Reg(_) => regs[0],
+                ArgLoc::Stack(offset) => {
+                    let dst = self.vreg(class_of(ty));
+                    self.load(ty, dst, Mem::incoming(*offset as i64));
+                    dst
+                }
+                ArgLoc::Indirect(ptr) => match **ptr {
+                    ArgLoc::Stack(offset) => {
+                        let dst = self.vreg(RegClass::Int);
+                        self.load(Type::Ptr, dst, Mem::incoming(offset as i64));
+                        dst
+                    }
+                    _ => regs[0],
+                },
+                ArgLoc::Pieces(pieces) => {
+                    let ParamAttr::ByVal { size, align, .. } = sig.params[i].attr else {
+                        unreachable!()
+                    };
+                    let slot = self
+                        .mf
+                        .new_slot(size.next_multiple_of(8) as u64, align.max(8));
+                    let mut regs = regs.into_iter();
+                    for piece in pieces.iter() {
+                        let value = match piece.loc {
+                            PieceLoc::Reg(_) => regs.next().unwrap(),
+                            PieceLoc::Stack(offset) => {
+                                let dst = self.vreg(RegClass::Int);
+                                self.load(Type::I64, dst, Mem::incoming(offset as i64));
+                                dst
+                            }
+                        };
+                        self.store_piece(Mem::slot(slot, 0), piece, value);
+                    }
+                    let dst = self.vreg(RegClass::Int);
+                    self.push(RvInst::Lea {
+                        dst,
+                        mem: Mem::slot(slot, 0),
+                    });
+                    dst
+                }
+                ArgLoc::Ignored => {
+                    // 聚合类型通过寄存器返回，先写到局部缓冲区
+                    let ParamAttr::SRet { size, align, .. } = sig.params[i].attr else {
+                        unreachable!()
+                    };
+                    let slot = self.mf.new_slot(size as u64, align.max(8));
+                    let dst = self.vreg(RegClass::Int);
+                    self.push(RvInst::Lea {
+                        dst,
+                        mem: Mem::slot(slot, 0),
+                    });
+                    dst
+                }
+            };
+            self.args.push(reg);
+        }
+        if matches!(conv.ret, RetLoc::Pieces(_) | RetLoc::Memory) {
+            self.sret = Some(self.args[0]);
+        }
+        self.ret = conv.ret;
+    }
+
+    fn inst(&mut self, block: BlockId, inst: InstId) -> CodegenResult<()> {
+        let data = &self.func.insts[inst];
+        let ty = data.ty;
+        match &data.kind {
+            InstKind::Binary { op, lhs, rhs, .. } => {
+                let dst = self.values[inst];
+                self.binary(*op, ty, dst, *lhs, *rhs)?;
+            }
+            InstKind::FNeg { val } => {
+                let dst = self.values[inst];
+                let src = self.reg(*val);
+                self.push(RvInst::FNeg {
+                    double: ty == Type::F64,
+                    dst,
+                    src,
+                });
+            }
+            InstKind::Cmp { .. } if self.deferred.contains(&inst) => {}
+            InstKind::Cmp { pred, lhs, rhs } => {
+                let dst = self.values[inst];
+                self.compare(*pred, *lhs, *rhs, dst);
+            }
+            InstKind::Cast { op, val } => {
+                let dst = self.values[inst];
+                self.cast(*op, *val, ty, dst);
+            }
+            InstKind::Select {
+                cond,
+                then_val,
+                else_val,
+            } => {
+                let dst = self.values[inst];
+                let cond = self.reg(*cond);
+                let then_reg = self.reg(*then_val);
+                let else_reg = self.reg(*else_val);
+                if ty.is_float() {
+                    let then_block = self.mf.new_block();
+                    let join = self.mf.new_block();
+                    self.copy(RegClass::Float, dst, else_reg);
+                    self.push(RvInst::Branch {
+                        cond: BrCond::Ne,
+                        lhs: cond,
+                        rhs: zero(),
+                        target: then_block,
+                    });
+                    self.push(RvInst::Jmp { target: join });
+                    self.cur = then_block;
+                    self.copy(RegClass::Float, dst, then_reg);
+                    self.push(RvInst::Jmp { target: join });
+                    self.cur = join;
+                } else {
+                    // dst = else ^ ((then ^ else) & -cond)
+                    let mask = self.alu(AluOp::Sub, false, zero(), Src::Reg(cond));
+                    let diff = self.alu(AluOp::Xor, false, then_reg, Src::Reg(else_reg));
+                    let diff = self.alu(AluOp::And, false, diff, Src::Reg(mask));
+                    self.push(RvInst::Alu {
+                        op: AluOp::Xor,
+                        w: false,
+                        dst,
+                        lhs: diff,
+                        rhs: Src::Reg(else_reg),
+                    });
+                }
+            }
+            InstKind::Alloca { .. } | InstKind::Phi { .. } | InstKind::VaEnd { .. } => {}
+            InstKind::Load { ptr, .. } => {
+                let dst = self.values[inst];
+                let mem = self.mem(*ptr, 0);
+                self.load(ty, dst, mem);
+            }
+            InstKind::Store { ptr, val, .. } => {
+                let val_ty = self.value_type(*val);
+                let src = self.reg(*val);
+                let mem = self.mem(*ptr, 0);
+                self.store(val_ty, src, mem);
+            }
+            InstKind::Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } => {
+                let dst = self.values[inst];
+                let disp = match self.const_int(*index) {
+                    Some(index) => index.wrapping_mul(*scale as i64).wrapping_add(*offset),
+                    None => *offset,
+                };
+                let mut addr = match self.const_int(*index) {
+                    Some(_) => self.reg(*base),
+                    None => {
+                        let index_ty = self.value_type(*index);
+                        let index = self.reg(*index);
+                        let index = self.sext(index, index_ty);
+                        let scaled = match *scale {
+                            1 => index,
+                            x if x.is_power_of_two() => self.alu(
+                                AluOp::Sll,
+                                false,
+                                index,
+                                Src::Imm(x.trailing_zeros() as i64),
+                            ),
+                            x => {
+                                let x = self.li(x as i64);
+                                self.alu(AluOp::Mul, false, index, Src::Reg(x))
+                            }
+                        };
+                        let base = self.reg(*base);
+                        self.alu(AluOp::Add, false, base, Src::Reg(scaled))
+                    }
+                };
+                if !fits_imm12(disp) {
+                    let disp = self.li(disp);
+                    addr = self.alu(AluOp::Add, false, addr, Src::Reg(disp));
+                }
+                let disp = match fits_imm12(disp) {
+                    true => disp,
+                    false => 0,
+                };
+                self.push(RvInst::Alu {
+                    op: AluOp::Add,
+                    w: false,
+                    dst,
+                    lhs: addr,
+                    rhs: Src::Imm(disp),
+                });
+            }
+            InstKind::MemCopy {
+                dst,
+                src,
+                size,
+                align,
+            } => {
+                let dst = self.mem(*dst, 0);
+                let src = self.mem(*src, 0);
+                self.memcpy(dst, src, *size, *align);
+            }
+            InstKind::Call { sig, callee, args } => {
+                let dst = self.values.get(inst).copied();
+                self.call(sig, *callee, args, dst)?;
+            }
+            InstKind::VaStart { list } => {
+                let Some(start) = self.va_start else {
+                    return self.unsupported("va_start in a non-variadic function");
+                };
+                let addr = self.vreg(RegClass::Int);
+                self.push(RvInst::Lea {
+                    dst: addr,
+                    mem: Mem::incoming(start),
+                });
+                let list = self.mem(*list, 0);
+                self.store(Type::Ptr, addr, list);
+            }
+            InstKind::VaArg { list } => {
+                // va_list 是指向下一个变参的指针，每个变参占 8 字节
+                let dst = self.values[inst];
+                let list = self.mem(*list, 0);
+                let cursor = self.vreg(RegClass::Int);
+                self.load(Type::Ptr, cursor, list.clone());
+                self.load(ty, dst, Mem::reg(cursor, 0));
+                let next = self.alu(AluOp::Add, false, cursor, Src::Imm(8));
+                self.store(Type::Ptr, next, list);
+            }
+            InstKind::VaCopy { dst, src } => {
+                let src = self.mem(*src, 0);
+                let cursor = self.vreg(RegClass::Int);
+                self.load(Type::Ptr, cursor, src);
+                let dst = self.mem(*dst, 0);
+                self.store(Type::Ptr, cursor, dst);
+            }
+            InstKind::Br { dest } => {
+                self.phi_copies(block, *dest);
+                let target = self.blocks[*dest];
+                self.push(RvInst::Jmp { target });
+            }
+            InstKind::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => {
+                let (cond, lhs, rhs) = match cond {
+                    Value::Inst(x) if self.deferred.contains(x) => {
+                        let InstKind::Cmp { pred, lhs, rhs } = self.func.insts[*x].kind else {
+                            unreachable!()
+                        };
+                        self.branch_cond(pred, lhs, rhs)
+                    }
+                    _ => (BrCond::Ne, self.reg(*cond), zero()),
+                };
+                let then_target = self.edge(block, *then_dest);
+                let else_target = self.edge(block, *else_dest);
+                self.push(RvInst::Branch {
+                    cond,
+                    lhs,
+                    rhs,
+                    target: then_target,
+                });
+                self.push(RvInst::Jmp {
+                    target: else_target,
+                });
+            }
+            InstKind::Switch {
+                val,
+                default,
+                cases,
+            } => {
+                let val_ty = self.value_type(*val);
+                let val = self.reg(*val);
+                let val = self.sext(val, val_ty);
+                for (case, dest) in cases.iter() {
+                    let case = sign_extend(*case, val_ty.bits(8));
+                    let rhs = self.li(case);
+                    let target = self.edge(block, *dest);
+                    self.push(RvInst::Branch {
+                        cond: BrCond::Eq,
+                        lhs: val,
+                        rhs,
+                        target,
+                    });
+                }
+                let target = self.edge(block, *default);
+                self.push(RvInst::Jmp { target });
+            }
+            InstKind::Ret { val } => self.ret(*val),
+            InstKind::Unreachable => self.push(RvInst::Unimp),
+        }
+        Ok(())
+    }
+
+    /// `from` 到 `to` 的边，`to` 有 phi 时新建一个基本块放置复制
+    fn edge(&mut self, from: BlockId, to: BlockId) -> usize {
+        if self.func.phis(to).is_empty() {
+            return self.blocks[to];
+        }
+        let cur = self.cur;
+        let block = self.mf.new_block();
+        self.cur = block;
+        self.phi_copies(from, to);
+        let target = self.blocks[to];
+        self.push(RvInst::Jmp { target });
+        self.cur = cur;
+        block
+    }
+
+    /// phi 的并行复制，先复制到临时寄存器，避免互相覆盖
+    fn phi_copies(&mut self, from: BlockId, to: BlockId) {
+        let mut copies = Vec::new();
+        for phi in self.func.phis(to) {
+            let InstKind::Phi { incomings } = &self.func.insts[phi].kind else {
+                unreachable!()
+            };
+            let Some((_, value)) = incomings.iter().find(|(x, _)| *x == from) else {
+                continue;
+            };
+            let class = class_of(self.func.insts[phi].ty);
+            let src = self.reg(*value);
+            let tmp = self.vreg(class);
+            self.copy(class, tmp, src);
+            copies.push((self.values[phi], tmp, class));
+        }
+        for (dst, tmp, class) in copies {
+            self.copy(class, dst, tmp);
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        ty: Type,
+        dst: Reg,
+        lhs: Value,
+        rhs: Value,
+    ) -> CodegenResult<()> {
+        use BinaryOp::*;
+        let w = bytes_of(ty) == 4;
+        let signed = matches!(op, SDiv | SRem | AShr);
+        let (alu, imm) = match op {
+            Add => (AluOp::Add, true),
+            Sub => (AluOp::Sub, false),
+            Mul => (AluOp::Mul, false),
+            SDiv => (AluOp::Div, false),
+            UDiv => (AluOp::Divu, false),
+            SRem => (AluOp::Rem, false),
+            URem => (AluOp::Remu, false),
+            And => (AluOp::And, !w),
+            Or => (AluOp::Or, !w),
+            Xor => (AluOp::Xor, !w),
+            Shl => (AluOp::Sll, true),
+            LShr => (AluOp::Srl, true),
+            AShr => (AluOp::Sra, true),
+            FAdd | FSub | FMul | FDiv => {
+                let fop = match op {
+                    FAdd => FloatOp::Add,
+                    FSub => FloatOp::Sub,
+                    FMul => FloatOp::Mul,
+                    _ => FloatOp::Div,
+                };
+                let lhs = self.reg(lhs);
+                let rhs = self.reg(rhs);
+                self.push(RvInst::FAlu {
+                    op: fop,
+                    double: ty == Type::F64,
+                    dst,
+                    lhs,
+                    rhs,
+                });
+                return Ok(());
+            }
+            FRem => {
+                let name = match ty {
+                    Type::F32 => "fmodf",
+                    _ => "fmod",
+                };
+                let sig = Signature::new(vec![AbiParam::new(ty), AbiParam::new(ty)], ty, false);
+                let args = [self.reg(lhs), self.reg(rhs)];
+                let conv = classify(&sig, &[ty, ty]);
+                let target = CallTarget::Sym(name.to_string());
+                return self.call_conv(&conv, &sig, target, &args, &[ty, ty], Some(dst));
+            }
+        };
+        let mut lhs = self.reg(lhs);
+        // 不足 32 位的除法和右移需要高位
+        if !w && bytes_of(ty) < 8 && matches!(op, SDiv | UDiv | SRem | URem | LShr | AShr) {
+            lhs = match signed {
+                true => self.sext(lhs, ty),
+                false => self.zext(lhs, ty),
+            };
+        }
+        let mut rhs = match imm {
+            true => self.src(rhs),
+            false => Src::Reg(self.reg(rhs)),
+        };
+        if let (Src::Reg(reg), false, true) = (rhs, w, matches!(op, SDiv | UDiv | SRem | URem)) {
+            rhs = Src::Reg(match signed {
+                true => self.sext(reg, ty),
+                false => self.zext(reg, ty),
+            });
+        }
+        // `and` `or` `xor` 没有 w 形式，sub 没有立即数形式
+        let w = w && !matches!(op, And | Or | Xor);
+        self.push(RvInst::Alu {
+            op: alu,
+            w,
+            dst,
+            lhs,
+            rhs,
+        });
+        if ty == Type::I1 && matches!(op, Add | Sub | Mul | Shl) {
+            self.push(RvInst::Alu {
+                op: AluOp::And,
+                w: false,
+                dst,
+                lhs: dst,
+                rhs: Src::Imm(1),
+            });
+        }
+        Ok(())
+    }
+
+    /// 比较的操作数扩展到 64 位
+    fn cmp_operands(&mut self, pred: CmpPred, lhs: Value, rhs: Value) -> (Reg, Reg) {
+        let ty = self.value_type(lhs);
+        let unsigned = matches!(
+            pred,
+            CmpPred::Ult | CmpPred::Ule | CmpPred::Ugt | CmpPred::Uge
+        );
+        let mut regs = [lhs, rhs].map(|x| self.reg(x));
+        // 32 位的无符号比较也可以用符号扩展，顺序不变
+        if bytes_of(ty) < 4 && unsigned {
+            regs = regs.map(|x| self.zext(x, ty));
+        } else {
+            regs = regs.map(|x| self.sext(x, ty));
+        }
+        (regs[0], regs[1])
+    }
+
+    /// 条件跳转的条件和操作数
+    fn branch_cond(&mut self, pred: CmpPred, lhs: Value, rhs: Value) -> (BrCond, Reg, Reg) {
+        if pred.is_float() {
+            let tmp = self.vreg(RegClass::Int);
+            self.compare(pred, lhs, rhs, tmp);
+            return (BrCond::Ne, tmp, zero());
+        }
+        let (a, b) = self.cmp_operands(pred, lhs, rhs);
+        match pred {
+            CmpPred::Eq => (BrCond::Eq, a, b),
+            CmpPred::Ne => (BrCond::Ne, a, b),
+            CmpPred::Slt => (BrCond::Lt, a, b),
+            CmpPred::Sge => (BrCond::Ge, a, b),
+            CmpPred::Sgt => (BrCond::Lt, b, a),
+            CmpPred::Sle => (BrCond::Ge, b, a),
+            CmpPred::Ult => (BrCond::Ltu, a, b),
+            CmpPred::Uge => (BrCond::Geu, a, b),
+            CmpPred::Ugt => (BrCond::Ltu, b, a),
+            _ => (BrCond::Geu, b, a),
+        }
+    }
+
+    /// 比较结果写到 `dst`，0 或 1
+    fn compare(&mut self, pred: CmpPred, lhs: Value, rhs: Value, dst: Reg) {
+        use CmpPred::*;
+        if pred.is_float() {
+            let double = self.value_type(lhs) == Type::F64;
+            let a = self.reg(lhs);
+            let b = self.reg(rhs);
+            let (op, lhs, rhs) = match pred {
+                FOeq | FUne => (FCmpOp::Eq, a, b),
+                FOlt => (FCmpOp::Lt, a, b),
+                FOle => (FCmpOp::Le, a, b),
+                FOgt => (FCmpOp::Lt, b, a),
+                _ => (FCmpOp::Le, b, a),
+            };
+            let tmp = match pred {
+                FUne => self.vreg(RegClass::Int),
+                _ => dst,
+            };
+            self.push(RvInst::FCmp {
+                op,
+                double,
+                dst: tmp,
+                lhs,
+                rhs,
+            });
+            if pred == FUne {
+                self.push(RvInst::Alu {
+                    op: AluOp::Xor,
+                    w: false,
+                    dst,
+                    lhs: tmp,
+                    rhs: Src::Imm(1),
+                });
+            }
+            return;
+        }
+        let (a, b) = self.cmp_operands(pred, lhs, rhs);
+        let (op, lhs, rhs, invert) = match pred {
+            Eq | Ne => {
+                let diff = self.alu(AluOp::Xor, false, a, Src::Reg(b));
+                // eq: diff <u 1，ne: 0 <u diff
+                match pred {
+                    Eq => (AluOp::Sltu, diff, Src::Imm(1), false),
+                    _ => (AluOp::Sltu, zero(), Src::Reg(diff), false),
+                }
+            }
+            Slt => (AluOp::Slt, a, Src::Reg(b), false),
+            Sgt => (AluOp::Slt, b, Src::Reg(a), false),
+            Sle => (AluOp::Slt, b, Src::Reg(a), true),
+            Sge => (AluOp::Slt, a, Src::Reg(b), true),
+            Ult => (AluOp::Sltu, a, Src::Reg(b), false),
+            Ugt => (AluOp::Sltu, b, Src::Reg(a), false),
+            Ule => (AluOp::Sltu, b, Src::Reg(a), true),
+            _ => (AluOp::Sltu, a, Src::Reg(b), true),
+        };
+        let tmp = match invert {
+            true => self.vreg(RegClass::Int),
+            false => dst,
+        };
+        self.push(RvInst::Alu {
+            op,
+            w: false,
+            dst: tmp,
+            lhs,
+            rhs,
+        });
+        if invert {
+            self.push(RvInst::Alu {
+                op: AluOp::Xor,
+                w: false,
+                dst,
+                lhs: tmp,
+                rhs: Src::Imm(1),
+            });
+        }
+    }
+
+    fn cast(&mut self, op: CastOp, val: Value, to: Type, dst: Reg) {
+        use CastOp::*;
+        let from = self.value_type(val);
+        let src = self.reg(val);
+        match op {
+            Trunc if to == Type::I1 => self.push(RvInst::Alu {
+                op: AluOp::And,
+                w: false,
+                dst,
+                lhs: src,
+                rhs: Src::Imm(1),
+            }),
+            Trunc => self.mv(dst, src),
+            ZExt | PtrToInt | IntToPtr => {
+                let value = match bytes_of(from) < bytes_of(to) {
+                    true => self.zext(src, from),
+                    false => src,
+                };
+                self.mv(dst, value);
+            }
+            SExt if from == Type::I1 => self.push(RvInst::Alu {
+                op: AluOp::Sub,
+                w: false,
+                dst,
+                lhs: zero(),
+                rhs: Src::Reg(src),
+            }),
+            SExt => {
+                let value = self.sext(src, from);
+                self.mv(dst, value);
+            }
+            FpToSi | FpToUi => self.push(RvInst::CvtFloatToInt {
+                double: from == Type::F64,
+                wide: to == Type::I64,
+                unsigned: op == FpToUi,
+                dst,
+                src,
+            }),
+            SiToFp | UiToFp => {
+                let unsigned = op == UiToFp;
+                let src = match (from, unsigned) {
+                    (Type::I1, false) => self.alu(AluOp::Sub, false, zero(), Src::Reg(src)),
+                    (Type::I8 | Type::I16, false) => self.sext(src, from),
+                    (Type::I8 | Type::I16, true) => self.zext(src, from),
+                    _ => src,
+                };
+                self.push(RvInst::CvtIntToFloat {
+                    double: to == Type::F64,
+                    wide: bytes_of(from) != 4,
+                    unsigned,
+                    dst,
+                    src,
+                });
+            }
+            FpExt | FpTrunc => self.push(RvInst::CvtFloat {
+                to_double: to == Type::F64,
+                dst,
+                src,
+            }),
+            Bitcast => match (from.is_float(), to.is_float()) {
+                (false, true) => self.push(RvInst::MovToFloat {
+                    double: to == Type::F64,
+                    dst,
+                    src,
+                }),
+                (true, false) => self.push(RvInst::MovFromFloat {
+                    double: from == Type::F64,
+                    dst,
+                    src,
+                }),
+                _ => self.copy(class_of(to), dst, src),
+            },
+        }
+    }
+
+    fn call(
+        &mut self,
+        sig: &Signature,
+        callee: Value,
+        args: &[Value],
+        dst: Option<Reg>,
+    ) -> CodegenResult<()> {
+        // 先算出所有参数，之后才能设置参数寄存器
+        let arg_regs: Vec<Reg> = args.iter().map(|x| self.reg(*x)).collect();
+        let types: Vec<Type> = args.iter().map(|x| self.value_type(*x)).collect();
+        let target = match callee {
+            Value::Func(_) => CallTarget::Sym(self.module.symbol_name(callee).to_string()),
+            _ => CallTarget::Reg(self.reg(callee)),
+        };
+        let conv = classify(sig, &types);
+        self.call_conv(&conv, sig, target, &arg_regs, &types, dst)
+    }
+
+    fn call_conv(
+        &mut self,
+        conv: &CallConv,
+        sig: &Signature,
+        target: CallTarget,
+        args: &[Reg],
+        types: &[Type],
+        dst: Option<Reg>,
+    ) -> CodegenResult<()> {
+        self.mf.outgoing = self.mf.outgoing.max(conv.stack_size);
+        let sp = Reg::Phys(SP);
+
+        // 大的聚合类型复制到临时对象，可能调用 memcpy，要在设置参数寄存器之前
+        let mut args = args.to_vec();
+        for (i, loc) in conv.args.iter().enumerate() {
+            if let ArgLoc::Indirect(_) = loc {
+                let ParamAttr::ByVal { size, align, .. } = sig.params[i].attr else {
+                    unreachable!()
+                };
+                let slot = self.mf.new_slot(size as u64, align);
+                self.memcpy(Mem::slot(slot, 0), Mem::reg(args[i], 0), size as u64, align);
+                let copy = self.vreg(RegClass::Int);
+                self.push(RvInst::Lea {
+                    dst: copy,
+                    mem: Mem::slot(slot, 0),
+                });
+                args[i] = copy;
+            }
+        }
+
+        // 栈上的参数，以及放到寄存器中的值
+        let mut moves = Vec::new();
+        for (i, loc) in conv.args.iter().enumerate() {
+            let loc = match loc {
+                ArgLoc::Indirect(ptr) => ptr,
+                loc => loc,
+            };
+            let ty = match conv.args[i] {
+                ArgLoc::Indirect(_) => Type::Ptr,
+                _ => types[i],
+            };
+            match loc {
+                ArgLoc::Reg(reg) => moves.push((*reg, args[i], ty)),
+                ArgLoc::Stack(offset) => self.store(ty, args[i], Mem::reg(sp, *offset as i64)),
+                ArgLoc::Pieces(pieces) => {
+                    for piece in pieces {
+                        let value = self.load_piece(Mem::reg(args[i], 0), piece);
+                        let ty = match piece.float {
+                            true if piece.size == 4 => Type::F32,
+                            true => Type::F64,
+                            false => Type::I64,
+                        };
+                        match piece.loc {
+                            PieceLoc::Reg(reg) => moves.push((reg, value, ty)),
+                            PieceLoc::Stack(offset) => {
+                                self.store(ty, value, Mem::reg(sp, offset as i64))
+                            }
+                        }
+                    }
+                }
+                ArgLoc::Indirect(_) | ArgLoc::Ignored => {}
+            }
+        }
+        let mut uses = Vec::new();
+        for (reg, value, ty) in moves {
+            self.set_arg_reg(ty, reg, value);
+            uses.push(reg);
+        }
+
+        let defs = match &conv.ret {
+            RetLoc::None | RetLoc::Memory => vec![],
+            RetLoc::Reg(reg) => vec![*reg],
+            RetLoc::Pieces(pieces) => pieces
+                .iter()
+                .map(|x| match x.loc {
+                    PieceLoc::Reg(reg) => reg,
+                    PieceLoc::Stack(_) => unreachable!(),
+                })
+                .collect(),
+        };
+        self.push(RvInst::Call {
+            target,
+            uses,
+            defs: defs.clone(),
+        });
+
+        match &conv.ret {
+            RetLoc::Reg(reg) => {
+                if let Some(dst) = dst {
+                    let class = class_of(sig.ret);
+                    self.copy(class, dst, Reg::Phys(*reg));
+                }
+            }
+            RetLoc::Pieces(pieces) => {
+                let values: Vec<Reg> = pieces
+                    .iter()
+                    .zip(defs)
+                    .map(|(piece, reg)| {
+                        let class = Self::piece_class(piece);
+                        let value = self.vreg(class);
+                        self.copy(class, value, Reg::Phys(reg));
+                        value
+                    })
+                    .collect();
+                for (piece, value) in pieces.iter().zip(values) {
+                    self.store_piece(Mem::reg(args[0], 0), piece, value);
+                }
+            }
+            RetLoc::None | RetLoc::Memory => {}
+        }
+        Ok(())
+    }
+
+    fn ret(&mut self, val: Option<Value>) {
+        let uses = match self.ret.clone() {
+            RetLoc::None | RetLoc::Memory => vec![],
+            RetLoc::Reg(reg) => {
+                if let Some(val) = val {
+                    let class = class_of(self.value_type(val));
+                    let val = self.reg(val);
+                    self.copy(class, Reg::Phys(reg), val);
+                }
+                vec![reg]
+            }
+            RetLoc::Pieces(pieces) => {
+                let sret = self.sret.unwrap();
+                let values: Vec<_> = pieces
+                    .iter()
+                    .map(|piece| self.load_piece(Mem::reg(sret, 0), piece))
+                    .collect();
+                let mut uses = Vec::new();
+                for (piece, value) in pieces.iter().zip(values) {
+                    let PieceLoc::Reg(reg) = piece.loc else {
+                        unreachable!()
+                    };
+                    self.copy(Self::piece_class(piece), Reg::Phys(reg), value);
+                    uses.push(reg);
+                }
+                uses
+            }
+        };
+        self.push(RvInst::Ret { uses });
+    }
+}
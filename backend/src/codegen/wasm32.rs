@@ -0,0 +1,41 @@
+/// WebAssembly 后端（wasm32），输出文本格式 `.wat` 或二进制 `.wasm` 模块
+/// # Contents
+/// - `inst`: wasm 指令和值类型，文本和二进制编码
+/// - `structure`: 把控制流图转换为 `block` / `loop` 的结构化控制流需要的分析
+/// - `isel`: 把 IR 函数翻译为 wasm 函数：局部变量、线性内存中的栈帧、结构化控制流
+/// - `emit`: 模块级的翻译：导入、函数表、全局变量的静态布局、栈指针
+/// - `module`: wasm 模块和两种格式的输出
+pub mod emit;
+pub mod inst;
+pub mod isel;
+pub mod module;
+pub mod structure;
+
+use crate::codegen::TargetIsa;
+use crate::err::codegen_error::CodegenResult;
+use crate::ir::Module;
+use crate::target::TargetInfo;
+
+pub struct Wasm32 {
+    info: TargetInfo,
+}
+
+impl Wasm32 {
+    pub fn new(info: TargetInfo) -> Self {
+        Self { info }
+    }
+}
+
+impl TargetIsa for Wasm32 {
+    fn info(&self) -> &TargetInfo {
+        &self.info
+    }
+
+    fn emit_asm(&self, module: &Module) -> CodegenResult<String> {
+        Ok(emit::emit_module(module)?.to_wat())
+    }
+
+    fn emit_object(&self, module: &Module) -> CodegenResult<Vec<u8>> {
+        Ok(emit::emit_module(module)?.to_bytes())
+    }
+}
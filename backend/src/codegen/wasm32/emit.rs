@@ -0,0 +1,205 @@
+use crate::codegen::wasm32::inst::{Inst, ValType};
+use crate::codegen::wasm32::isel::{func_type, lower};
+use crate::codegen::wasm32::module::{
+    Data, Export, ExportKind, FuncType, Global, Import, WasmModule,
+};
+use crate::err::codegen_error::{CodegenError, CodegenResult};
+use crate::ir::{BinaryOp, FuncId, GlobalId, InitItem, InstKind, Linkage, Module, Type, Value};
+use slotmap::SecondaryMap;
+
+/// 全局变量从这个地址开始存放，更低的地址不使用，空指针不会指向有效的对象
+const DATA_START: u32 = 1024;
+/// 栈的大小
+const STACK_SIZE: u32 = 64 * 1024;
+const PAGE: u32 = 64 * 1024;
+/// 外部函数从这个模块导入
+const IMPORT_MODULE: &str = "env";
+
+/// 栈指针 `__stack_pointer` 的全局变量下标
+pub const SP: u32 = 0;
+
+///
+/// 模块级的符号，翻译函数时使用
+///
+/// # Members
+/// - `funcs`: IR 函数对应的 wasm 函数下标
+/// - `slots`: IR 函数在函数表中的下标，即函数指针的值
+/// - `addrs`: 全局变量在线性内存中的地址
+/// - `fmod` `fmodf`: 浮点取余调用的函数，模块中没有时导入
+///
+#[derive(Debug, Default)]
+pub struct Symbols {
+    pub funcs: SecondaryMap<FuncId, u32>,
+    pub slots: SecondaryMap<FuncId, u32>,
+    pub addrs: SecondaryMap<GlobalId, u32>,
+    pub fmod: Option<u32>,
+    pub fmodf: Option<u32>,
+}
+
+///
+/// 翻译整个模块
+///
+/// 函数声明作为 `env` 模块的导入，所有函数都放进函数表，函数指针是表中的下标；
+/// 全局变量在线性内存中静态分配，地址初始值在编译时算出；栈在全局变量之后，
+/// `__heap_base` 指向栈底之后的空闲内存
+///
+pub fn emit_module(module: &Module) -> CodegenResult<WasmModule> {
+    let mut wasm = WasmModule::default();
+    let mut syms = Symbols::default();
+
+    for id in module.func_ids() {
+        let func = &module.funcs[id];
+        if func.is_declaration() {
+            let ty = wasm.type_index(func_type(&func.sig));
+            syms.funcs.insert(id, wasm.imports.len() as u32);
+            wasm.imports.push(Import {
+                module: IMPORT_MODULE.to_string(),
+                name: func.name.clone(),
+                ty,
+            });
+        }
+    }
+    let (fmod, fmodf) = uses_frem(module);
+    for (used, name, ty) in [(fmod, "fmod", ValType::F64), (fmodf, "fmodf", ValType::F32)] {
+        if !used {
+            continue;
+        }
+        let index = match module.func_by_name(name) {
+            Some(id) if module.funcs[id].is_declaration() => syms.funcs[id],
+            _ => {
+                let ty = wasm.type_index(FuncType {
+                    params: vec![ty, ty],
+                    results: vec![ty],
+                });
+                wasm.imports.push(Import {
+                    module: IMPORT_MODULE.to_string(),
+                    name: name.to_string(),
+                    ty,
+                });
+                wasm.imports.len() as u32 - 1
+            }
+        };
+        match ty {
+            ValType::F64 => syms.fmod = Some(index),
+            _ => syms.fmodf = Some(index),
+        }
+    }
+    let defined: Vec<FuncId> = module
+        .func_ids()
+        .into_iter()
+        .filter(|x| !module.funcs[*x].is_declaration())
+        .collect();
+    for (i, id) in defined.iter().enumerate() {
+        syms.funcs.insert(*id, (wasm.imports.len() + i) as u32);
+    }
+    for id in module.func_ids() {
+        wasm.table.push(syms.funcs[id]);
+        syms.slots.insert(id, wasm.table.len() as u32);
+    }
+
+    // 全局变量的布局，然后是栈
+    let mut cursor = DATA_START;
+    for id in module.global_ids() {
+        let global = &module.globals[id];
+        if global.is_declaration() {
+            return Err(CodegenError::ExternalVariable {
+                arch: "wasm32",
+                name: global.name.clone(),
+            });
+        }
+        cursor = cursor.next_multiple_of(global.align.max(1));
+        syms.addrs.insert(id, cursor);
+        cursor += global.size as u32;
+    }
+    for id in module.global_ids() {
+        let global = &module.globals[id];
+        let bytes = init_bytes(&syms, global.init.as_deref().unwrap_or_default());
+        if bytes.iter().any(|x| *x != 0) {
+            wasm.data.push(Data {
+                offset: syms.addrs[id],
+                bytes,
+            });
+        }
+    }
+    let stack_top = cursor.next_multiple_of(16) + STACK_SIZE;
+    wasm.memory_pages = stack_top.div_ceil(PAGE);
+    wasm.globals.push(Global {
+        name: "__stack_pointer".to_string(),
+        ty: ValType::I32,
+        mutable: true,
+        init: Inst::I32Const(stack_top as i32),
+    });
+    wasm.globals.push(Global {
+        name: "__heap_base".to_string(),
+        ty: ValType::I32,
+        mutable: false,
+        init: Inst::I32Const(stack_top as i32),
+    });
+
+    for id in defined {
+        let func = &module.funcs[id];
+        let ty = wasm.type_index(func_type(&func.sig));
+        let code = lower(&syms, &mut wasm, func, ty)?;
+        wasm.funcs.push(code);
+        if func.linkage != Linkage::Internal {
+            wasm.exports.push(Export {
+                name: func.name.clone(),
+                kind: ExportKind::Func,
+                index: syms.funcs[id],
+            });
+        }
+    }
+    wasm.exports.push(Export {
+        name: "memory".to_string(),
+        kind: ExportKind::Memory,
+        index: 0,
+    });
+    wasm.exports.push(Export {
+        name: "__heap_base".to_string(),
+        kind: ExportKind::Global,
+        index: 1,
+    });
+    Ok(wasm)
+}
+
+/// 是否有 `f64` 和 `f32` 的浮点取余
+fn uses_frem(module: &Module) -> (bool, bool) {
+    let (mut f64, mut f32) = (false, false);
+    for id in module.func_ids() {
+        let func = &module.funcs[id];
+        for (_, inst) in func.inst_iter() {
+            let data = &func.insts[inst];
+            if let InstKind::Binary {
+                op: BinaryOp::FRem, ..
+            } = data.kind
+            {
+                match data.ty {
+                    Type::F32 => f32 = true,
+                    _ => f64 = true,
+                }
+            }
+        }
+    }
+    (f64, f32)
+}
+
+/// 初始值的字节，地址是 4 字节的线性内存地址或函数表下标
+fn init_bytes(syms: &Symbols, init: &[InitItem]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for item in init.iter() {
+        match item {
+            InitItem::Bytes(x) => bytes.extend_from_slice(x),
+            InitItem::Zero(n) => bytes.resize(bytes.len() + *n as usize, 0),
+            InitItem::Addr { target, addend } => {
+                let base = match target {
+                    Value::Global(x) => syms.addrs[*x],
+                    Value::Func(x) => syms.slots[*x],
+                    _ => 0,
+                };
+                let addr = base.wrapping_add(*addend as u32);
+                bytes.extend_from_slice(&addr.to_le_bytes());
+            }
+        }
+    }
+    bytes
+}
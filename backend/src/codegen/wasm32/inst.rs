@@ -0,0 +1,404 @@
+use std::fmt::{Display, Formatter};
+
+/// 带文本名和操作码的枚举
+macro_rules! op_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => ($text:literal, $code:literal)),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => $text),+
+                }
+            }
+
+            pub fn opcode(self) -> u8 {
+                match self {
+                    $($name::$variant => $code),+
+                }
+            }
+        }
+    };
+}
+
+/// 值类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl ValType {
+    pub fn code(self) -> u8 {
+        match self {
+            ValType::I32 => 0x7f,
+            ValType::I64 => 0x7e,
+            ValType::F32 => 0x7d,
+            ValType::F64 => 0x7c,
+        }
+    }
+}
+
+impl Display for ValType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F32 => "f32",
+            ValType::F64 => "f64",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+op_enum! {
+    /// 读写线性内存的指令
+    MemOp {
+        I32Load => ("i32.load", 0x28),
+        I64Load => ("i64.load", 0x29),
+        F32Load => ("f32.load", 0x2a),
+        F64Load => ("f64.load", 0x2b),
+        I32Load8U => ("i32.load8_u", 0x2d),
+        I32Load16U => ("i32.load16_u", 0x2f),
+        I32Store => ("i32.store", 0x36),
+        I64Store => ("i64.store", 0x37),
+        F32Store => ("f32.store", 0x38),
+        F64Store => ("f64.store", 0x39),
+        I32Store8 => ("i32.store8", 0x3a),
+        I32Store16 => ("i32.store16", 0x3b),
+    }
+}
+
+impl MemOp {
+    /// 访问宽度的 log2，作为默认的对齐
+    pub fn align(self) -> u32 {
+        match self {
+            MemOp::I32Load8U | MemOp::I32Store8 => 0,
+            MemOp::I32Load16U | MemOp::I32Store16 => 1,
+            MemOp::I32Load | MemOp::F32Load | MemOp::I32Store | MemOp::F32Store => 2,
+            MemOp::I64Load | MemOp::F64Load | MemOp::I64Store | MemOp::F64Store => 3,
+        }
+    }
+}
+
+op_enum! {
+    /// 没有立即数的数值指令
+    NumOp {
+        I32Eqz => ("i32.eqz", 0x45),
+        I32Eq => ("i32.eq", 0x46),
+        I32Ne => ("i32.ne", 0x47),
+        I32LtS => ("i32.lt_s", 0x48),
+        I32LtU => ("i32.lt_u", 0x49),
+        I32GtS => ("i32.gt_s", 0x4a),
+        I32GtU => ("i32.gt_u", 0x4b),
+        I32LeS => ("i32.le_s", 0x4c),
+        I32LeU => ("i32.le_u", 0x4d),
+        I32GeS => ("i32.ge_s", 0x4e),
+        I32GeU => ("i32.ge_u", 0x4f),
+        I64Eq => ("i64.eq", 0x51),
+        I64Ne => ("i64.ne", 0x52),
+        I64LtS => ("i64.lt_s", 0x53),
+        I64LtU => ("i64.lt_u", 0x54),
+        I64GtS => ("i64.gt_s", 0x55),
+        I64GtU => ("i64.gt_u", 0x56),
+        I64LeS => ("i64.le_s", 0x57),
+        I64LeU => ("i64.le_u", 0x58),
+        I64GeS => ("i64.ge_s", 0x59),
+        I64GeU => ("i64.ge_u", 0x5a),
+        F32Eq => ("f32.eq", 0x5b),
+        F32Ne => ("f32.ne", 0x5c),
+        F32Lt => ("f32.lt", 0x5d),
+        F32Gt => ("f32.gt", 0x5e),
+        F32Le => ("f32.le", 0x5f),
+        F32Ge => ("f32.ge", 0x60),
+        F64Eq => ("f64.eq", 0x61),
+        F64Ne => ("f64.ne", 0x62),
+        F64Lt => ("f64.lt", 0x63),
+        F64Gt => ("f64.gt", 0x64),
+        F64Le => ("f64.le", 0x65),
+        F64Ge => ("f64.ge", 0x66),
+        I32Add => ("i32.add", 0x6a),
+        I32Sub => ("i32.sub", 0x6b),
+        I32Mul => ("i32.mul", 0x6c),
+        I32DivS => ("i32.div_s", 0x6d),
+        I32DivU => ("i32.div_u", 0x6e),
+        I32RemS => ("i32.rem_s", 0x6f),
+        I32RemU => ("i32.rem_u", 0x70),
+        I32And => ("i32.and", 0x71),
+        I32Or => ("i32.or", 0x72),
+        I32Xor => ("i32.xor", 0x73),
+        I32Shl => ("i32.shl", 0x74),
+        I32ShrS => ("i32.shr_s", 0x75),
+        I32ShrU => ("i32.shr_u", 0x76),
+        I64Add => ("i64.add", 0x7c),
+        I64Sub => ("i64.sub", 0x7d),
+        I64Mul => ("i64.mul", 0x7e),
+        I64DivS => ("i64.div_s", 0x7f),
+        I64DivU => ("i64.div_u", 0x80),
+        I64RemS => ("i64.rem_s", 0x81),
+        I64RemU => ("i64.rem_u", 0x82),
+        I64And => ("i64.and", 0x83),
+        I64Or => ("i64.or", 0x84),
+        I64Xor => ("i64.xor", 0x85),
+        I64Shl => ("i64.shl", 0x86),
+        I64ShrS => ("i64.shr_s", 0x87),
+        I64ShrU => ("i64.shr_u", 0x88),
+        F32Neg => ("f32.neg", 0x8c),
+        F32Add => ("f32.add", 0x92),
+        F32Sub => ("f32.sub", 0x93),
+        F32Mul => ("f32.mul", 0x94),
+        F32Div => ("f32.div", 0x95),
+        F64Neg => ("f64.neg", 0x9a),
+        F64Add => ("f64.add", 0xa0),
+        F64Sub => ("f64.sub", 0xa1),
+        F64Mul => ("f64.mul", 0xa2),
+        F64Div => ("f64.div", 0xa3),
+        I32WrapI64 => ("i32.wrap_i64", 0xa7),
+        I32TruncF32S => ("i32.trunc_f32_s", 0xa8),
+        I32TruncF32U => ("i32.trunc_f32_u", 0xa9),
+        I32TruncF64S => ("i32.trunc_f64_s", 0xaa),
+        I32TruncF64U => ("i32.trunc_f64_u", 0xab),
+        I64ExtendI32S => ("i64.extend_i32_s", 0xac),
+        I64ExtendI32U => ("i64.extend_i32_u", 0xad),
+        I64TruncF32S => ("i64.trunc_f32_s", 0xae),
+        I64TruncF32U => ("i64.trunc_f32_u", 0xaf),
+        I64TruncF64S => ("i64.trunc_f64_s", 0xb0),
+        I64TruncF64U => ("i64.trunc_f64_u", 0xb1),
+        F32ConvertI32S => ("f32.convert_i32_s", 0xb2),
+        F32ConvertI32U => ("f32.convert_i32_u", 0xb3),
+        F32ConvertI64S => ("f32.convert_i64_s", 0xb4),
+        F32ConvertI64U => ("f32.convert_i64_u", 0xb5),
+        F32DemoteF64 => ("f32.demote_f64", 0xb6),
+        F64ConvertI32S => ("f64.convert_i32_s", 0xb7),
+        F64ConvertI32U => ("f64.convert_i32_u", 0xb8),
+        F64ConvertI64S => ("f64.convert_i64_s", 0xb9),
+        F64ConvertI64U => ("f64.convert_i64_u", 0xba),
+        F64PromoteF32 => ("f64.promote_f32", 0xbb),
+        I32ReinterpretF32 => ("i32.reinterpret_f32", 0xbc),
+        I64ReinterpretF64 => ("i64.reinterpret_f64", 0xbd),
+        F32ReinterpretI32 => ("f32.reinterpret_i32", 0xbe),
+        F64ReinterpretI64 => ("f64.reinterpret_i64", 0xbf),
+        I32Extend8S => ("i32.extend8_s", 0xc0),
+        I32Extend16S => ("i32.extend16_s", 0xc1),
+    }
+}
+
+///
+/// wasm 指令，块都没有参数和结果（块类型为空），跳转目标是相对深度
+///
+/// 函数和全局变量用下标引用，文本格式输出时换成 `$name`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst {
+    Block,
+    Loop,
+    If,
+    Else,
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Vec<u32>, u32),
+    Return,
+    Unreachable,
+    Drop,
+    Select,
+    Call(u32),
+    CallIndirect(u32),
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    Mem(MemOp),
+    I32Const(i32),
+    I64Const(i64),
+    F32Const(u32),
+    F64Const(u64),
+    Num(NumOp),
+    MemoryCopy,
+    MemoryFill,
+}
+
+impl Inst {
+    /// 是否离开和进入一层块：`block` `loop` `if` 进入，`end` 离开，`else` 两者都是
+    pub fn nesting(&self) -> (bool, bool) {
+        match self {
+            Inst::Block | Inst::Loop | Inst::If => (false, true),
+            Inst::Else => (true, true),
+            Inst::End => (true, false),
+            _ => (false, false),
+        }
+    }
+
+    /// 文本格式，`funcs` `globals` 是函数和全局变量的名字
+    pub fn wat(&self, funcs: &[String], globals: &[String]) -> String {
+        match self {
+            Inst::Block => "block".to_string(),
+            Inst::Loop => "loop".to_string(),
+            Inst::If => "if".to_string(),
+            Inst::Else => "else".to_string(),
+            Inst::End => "end".to_string(),
+            Inst::Br(x) => format!("br {}", x),
+            Inst::BrIf(x) => format!("br_if {}", x),
+            Inst::BrTable(targets, default) => {
+                let mut text = "br_table".to_string();
+                for x in targets.iter().chain([default]) {
+                    text.push_str(&format!(" {}", x));
+                }
+                text
+            }
+            Inst::Return => "return".to_string(),
+            Inst::Unreachable => "unreachable".to_string(),
+            Inst::Drop => "drop".to_string(),
+            Inst::Select => "select".to_string(),
+            Inst::Call(x) => format!("call ${}", funcs[*x as usize]),
+            Inst::CallIndirect(x) => format!("call_indirect (type {})", x),
+            Inst::LocalGet(x) => format!("local.get {}", x),
+            Inst::LocalSet(x) => format!("local.set {}", x),
+            Inst::LocalTee(x) => format!("local.tee {}", x),
+            Inst::GlobalGet(x) => format!("global.get ${}", globals[*x as usize]),
+            Inst::GlobalSet(x) => format!("global.set ${}", globals[*x as usize]),
+            Inst::Mem(op) => op.name().to_string(),
+            Inst::I32Const(x) => format!("i32.const {}", x),
+            Inst::I64Const(x) => format!("i64.const {}", x),
+            Inst::F32Const(x) => format!("f32.const {}", wat_float(*x as u64, 23, 8)),
+            Inst::F64Const(x) => format!("f64.const {}", wat_float(*x, 52, 11)),
+            Inst::Num(op) => op.name().to_string(),
+            Inst::MemoryCopy => "memory.copy".to_string(),
+            Inst::MemoryFill => "memory.fill".to_string(),
+        }
+    }
+
+    /// 二进制编码
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Inst::Block => out.extend_from_slice(&[0x02, 0x40]),
+            Inst::Loop => out.extend_from_slice(&[0x03, 0x40]),
+            Inst::If => out.extend_from_slice(&[0x04, 0x40]),
+            Inst::Else => out.push(0x05),
+            Inst::End => out.push(0x0b),
+            Inst::Br(x) => {
+                out.push(0x0c);
+                uleb(out, *x as u64);
+            }
+            Inst::BrIf(x) => {
+                out.push(0x0d);
+                uleb(out, *x as u64);
+            }
+            Inst::BrTable(targets, default) => {
+                out.push(0x0e);
+                uleb(out, targets.len() as u64);
+                for x in targets.iter().chain([default]) {
+                    uleb(out, *x as u64);
+                }
+            }
+            Inst::Return => out.push(0x0f),
+            Inst::Unreachable => out.push(0x00),
+            Inst::Drop => out.push(0x1a),
+            Inst::Select => out.push(0x1b),
+            Inst::Call(x) => {
+                out.push(0x10);
+                uleb(out, *x as u64);
+            }
+            Inst::CallIndirect(x) => {
+                out.push(0x11);
+                uleb(out, *x as u64);
+                out.push(0x00);
+            }
+            Inst::LocalGet(x) | Inst::LocalSet(x) | Inst::LocalTee(x) => {
+                out.push(match self {
+                    Inst::LocalGet(_) => 0x20,
+                    Inst::LocalSet(_) => 0x21,
+                    _ => 0x22,
+                });
+                uleb(out, *x as u64);
+            }
+            Inst::GlobalGet(x) | Inst::GlobalSet(x) => {
+                out.push(if matches!(self, Inst::GlobalGet(_)) {
+                    0x23
+                } else {
+                    0x24
+                });
+                uleb(out, *x as u64);
+            }
+            Inst::Mem(op) => {
+                out.push(op.opcode());
+                uleb(out, op.align() as u64);
+                uleb(out, 0);
+            }
+            Inst::I32Const(x) => {
+                out.push(0x41);
+                sleb(out, *x as i64);
+            }
+            Inst::I64Const(x) => {
+                out.push(0x42);
+                sleb(out, *x);
+            }
+            Inst::F32Const(x) => {
+                out.push(0x43);
+                out.extend_from_slice(&x.to_le_bytes());
+            }
+            Inst::F64Const(x) => {
+                out.push(0x44);
+                out.extend_from_slice(&x.to_le_bytes());
+            }
+            Inst::Num(op) => out.push(op.opcode()),
+            Inst::MemoryCopy => out.extend_from_slice(&[0xfc, 0x0a, 0x00, 0x00]),
+            Inst::MemoryFill => out.extend_from_slice(&[0xfc, 0x0b, 0x00]),
+        }
+    }
+}
+
+/// 浮点常量的文本，NaN 带上载荷，其余用能精确还原的十进制
+fn wat_float(bits: u64, mantissa: u32, exponent: u32) -> String {
+    let sign = if bits >> (mantissa + exponent) & 1 == 1 {
+        "-"
+    } else {
+        ""
+    };
+    let exp = bits >> mantissa & ((1 << exponent) - 1);
+    let frac = bits & ((1 << mantissa) - 1);
+    if exp == (1 << exponent) - 1 {
+        return match frac {
+            0 => format!("{}inf", sign),
+            _ => format!("{}nan:0x{:x}", sign, frac),
+        };
+    }
+    match mantissa {
+        23 => format!("{:?}", f32::from_bits(bits as u32)),
+        _ => format!("{:?}", f64::from_bits(bits)),
+    }
+}
+
+/// 无符号 LEB128
+pub fn uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// 有符号 LEB128
+pub fn sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
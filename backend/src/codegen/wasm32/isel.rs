@@ -0,0 +1,920 @@
+use crate::codegen::wasm32::emit::{SP, Symbols};
+use crate::codegen::wasm32::inst::{Inst, MemOp, NumOp, ValType};
+use crate::codegen::wasm32::module::{Func, FuncType, WasmModule};
+use crate::codegen::wasm32::structure::Shape;
+use crate::err::codegen_error::CodegenResult;
+use crate::ir::{
+    BinaryOp, BlockId, CastOp, CmpPred, Function, InstId, InstKind, ParamAttr, Signature, Type,
+    Value,
+};
+use slotmap::SecondaryMap;
+
+/// 稠密的 `switch` 用 `br_table`，表项最多这么多
+const MAX_TABLE: u64 = 1024;
+
+///
+/// 控制结构的一层，用来计算 `br` 的相对深度
+/// - `Block`: 结束处是合流点 `b`
+/// - `Loop`: 开头是循环头 `b`
+/// - `Dispatch`: 不可归约时分发标签的循环
+/// - `Other`: `if` 和 `switch` 的块
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frame {
+    Block(BlockId),
+    Loop(BlockId),
+    Dispatch,
+    Other,
+}
+
+///
+/// 把一个 IR 函数翻译为 wasm 函数
+///
+/// 每个有结果的指令对应一个局部变量；不足 32 位的整数在 `i32` 中零扩展保存，
+/// 需要有符号解释的地方（比较、除法、右移、转换）再符号扩展
+///
+/// 栈在线性内存中，由全局变量 `__stack_pointer` 指向栈顶，向下增长；
+/// `alloca`、按值传递的结构体副本和变参区域都在栈帧中，用帧指针加偏移访问
+///
+/// # Members
+/// - `shape`: 结构化控制流需要的分析
+/// - `code`: 函数体
+/// - `locals`: 参数之外的局部变量的类型
+/// - `values`: 有结果的指令对应的局部变量
+/// - `allocas`: `alloca` 在栈帧中的偏移
+/// - `frame`: 栈帧大小
+/// - `fp`: 帧指针，没有栈帧时为 None
+/// - `va_area`: 变参函数最后一个参数，变参区域的地址
+/// - `fixups`: 需要填入栈帧大小的 `i32.const`
+/// - `frames`: 当前所在的控制结构，最内层在最后
+/// - `label`: 不可归约时保存下一个基本块编号的局部变量
+///
+pub struct Lower<'a> {
+    syms: &'a Symbols,
+    wasm: &'a mut WasmModule,
+    func: &'a Function,
+    shape: Shape,
+    code: Vec<Inst>,
+    params: u32,
+    locals: Vec<ValType>,
+    values: SecondaryMap<InstId, u32>,
+    allocas: SecondaryMap<InstId, u32>,
+    frame: u32,
+    fp: Option<u32>,
+    va_area: Option<u32>,
+    fixups: Vec<usize>,
+    frames: Vec<Frame>,
+    label: Option<u32>,
+}
+
+/// IR 类型对应的 wasm 类型，指针和不足 32 位的整数都是 `i32`
+pub fn val_type(ty: Type) -> ValType {
+    match ty {
+        Type::I64 => ValType::I64,
+        Type::F32 => ValType::F32,
+        Type::F64 => ValType::F64,
+        _ => ValType::I32,
+    }
+}
+
+/// 签名对应的函数类型，按值传递的结构体传地址，变参函数多一个变参区域的地址
+pub fn func_type(sig: &Signature) -> FuncType {
+    let mut params: Vec<ValType> = sig.params.iter().map(|x| val_type(x.ty)).collect();
+    if sig.variadic {
+        params.push(ValType::I32);
+    }
+    let results = match sig.ret {
+        Type::Void => vec![],
+        ty => vec![val_type(ty)],
+    };
+    FuncType { params, results }
+}
+
+fn load_op(ty: Type) -> MemOp {
+    match ty {
+        Type::I1 | Type::I8 => MemOp::I32Load8U,
+        Type::I16 => MemOp::I32Load16U,
+        Type::I64 => MemOp::I64Load,
+        Type::F32 => MemOp::F32Load,
+        Type::F64 => MemOp::F64Load,
+        _ => MemOp::I32Load,
+    }
+}
+
+fn store_op(ty: Type) -> MemOp {
+    match ty {
+        Type::I1 | Type::I8 => MemOp::I32Store8,
+        Type::I16 => MemOp::I32Store16,
+        Type::I64 => MemOp::I64Store,
+        Type::F32 => MemOp::F32Store,
+        Type::F64 => MemOp::F64Store,
+        _ => MemOp::I32Store,
+    }
+}
+
+/// 变参区域中一个参数的大小，也是它的对齐
+fn vararg_size(ty: Type) -> u32 {
+    match val_type(ty) {
+        ValType::I64 | ValType::F64 => 8,
+        _ => 4,
+    }
+}
+
+/// 翻译函数，`ty` 是函数类型的下标
+pub fn lower(
+    syms: &Symbols,
+    wasm: &mut WasmModule,
+    func: &Function,
+    ty: u32,
+) -> CodegenResult<Func> {
+    let params = func.sig.params.len() as u32 + func.sig.variadic as u32;
+    let mut lower = Lower {
+        syms,
+        wasm,
+        func,
+        shape: Shape::new(func),
+        code: Vec::new(),
+        params,
+        locals: Vec::new(),
+        values: SecondaryMap::new(),
+        allocas: SecondaryMap::new(),
+        frame: 0,
+        fp: None,
+        va_area: func.sig.variadic.then(|| params - 1),
+        fixups: Vec::new(),
+        frames: Vec::new(),
+        label: None,
+    };
+    lower.run()?;
+    Ok(Func {
+        name: func.name.clone(),
+        ty,
+        locals: lower.locals,
+        body: lower.code,
+    })
+}
+
+impl<'a> Lower<'a> {
+    fn run(&mut self) -> CodegenResult<()> {
+        let func = self.func;
+        if self.needs_frame() {
+            self.fp = Some(self.new_local(ValType::I32));
+        }
+        for (_, inst) in func.inst_iter() {
+            let data = &func.insts[inst];
+            match &data.kind {
+                InstKind::Alloca { size, align } => {
+                    let offset = self.alloc(*size as u32, *align);
+                    self.allocas.insert(inst, offset);
+                }
+                _ if data.ty != Type::Void => {
+                    let local = self.new_local(val_type(data.ty));
+                    self.values.insert(inst, local);
+                }
+                _ => {}
+            }
+        }
+
+        match self.shape.reducible {
+            true => self.tree(func.entry())?,
+            false => self.dispatch()?,
+        }
+        if func.sig.ret != Type::Void && self.code.last() == Some(&Inst::End) {
+            self.code.push(Inst::Unreachable);
+        }
+
+        // 栈帧大小确定后再生成序言
+        let Some(fp) = self.fp else {
+            return Ok(());
+        };
+        self.frame = self.frame.next_multiple_of(16);
+        for pos in self.fixups.iter() {
+            self.code[*pos] = Inst::I32Const(self.frame as i32);
+        }
+        let prologue = [
+            Inst::GlobalGet(SP),
+            Inst::I32Const(self.frame as i32),
+            Inst::Num(NumOp::I32Sub),
+            Inst::LocalTee(fp),
+            Inst::GlobalSet(SP),
+        ];
+        self.code.splice(0..0, prologue);
+        Ok(())
+    }
+
+    /// `alloca`、按值传递的结构体参数和变参调用需要栈帧
+    fn needs_frame(&self) -> bool {
+        self.func
+            .inst_iter()
+            .any(|(_, inst)| match &self.func.insts[inst].kind {
+                InstKind::Alloca { .. } => true,
+                InstKind::Call { sig, .. } => {
+                    sig.variadic
+                        || sig
+                            .params
+                            .iter()
+                            .any(|x| matches!(x.attr, ParamAttr::ByVal { .. }))
+                }
+                _ => false,
+            })
+    }
+
+    fn new_local(&mut self, ty: ValType) -> u32 {
+        self.locals.push(ty);
+        self.params + self.locals.len() as u32 - 1
+    }
+
+    /// 在栈帧中分配，返回相对帧指针的偏移
+    fn alloc(&mut self, size: u32, align: u32) -> u32 {
+        let offset = self.frame.next_multiple_of(align.max(1));
+        self.frame = offset + size;
+        offset
+    }
+
+    fn emit(&mut self, inst: Inst) {
+        self.code.push(inst);
+    }
+
+    fn num(&mut self, op: NumOp) {
+        self.code.push(Inst::Num(op));
+    }
+
+    /// 帧指针加偏移
+    fn frame_addr(&mut self, offset: u32) {
+        self.emit(Inst::LocalGet(self.fp.unwrap()));
+        if offset != 0 {
+            self.emit(Inst::I32Const(offset as i32));
+            self.num(NumOp::I32Add);
+        }
+    }
+
+    fn value_type(&self, value: Value) -> Type {
+        self.func.value_type(value)
+    }
+
+    /// 把值压栈
+    fn push(&mut self, value: Value) {
+        let inst = match value {
+            Value::Inst(x) => {
+                if let Some(offset) = self.allocas.get(x).copied() {
+                    self.frame_addr(offset);
+                    return;
+                }
+                Inst::LocalGet(self.values[x])
+            }
+            Value::Arg(x) => Inst::LocalGet(x),
+            Value::Int {
+                ty: Type::I64,
+                bits,
+            } => Inst::I64Const(bits as i64),
+            Value::Int { bits, .. } => Inst::I32Const(bits as u32 as i32),
+            Value::Float {
+                ty: Type::F32,
+                bits,
+            } => Inst::F32Const(bits as u32),
+            Value::Float { bits, .. } => Inst::F64Const(bits),
+            Value::Global(x) => Inst::I32Const(self.syms.addrs[x] as i32),
+            Value::Func(x) => Inst::I32Const(self.syms.slots[x] as i32),
+            Value::Undef(ty) => match val_type(ty) {
+                ValType::I32 => Inst::I32Const(0),
+                ValType::I64 => Inst::I64Const(0),
+                ValType::F32 => Inst::F32Const(0),
+                ValType::F64 => Inst::F64Const(0),
+            },
+        };
+        self.emit(inst);
+    }
+
+    /// 压栈并符号扩展到 32 位
+    fn push_signed(&mut self, value: Value) {
+        self.push(value);
+        self.sext(self.value_type(value));
+    }
+
+    /// 栈顶的小整数符号扩展到 32 位
+    fn sext(&mut self, ty: Type) {
+        match ty {
+            Type::I1 => {
+                self.emit(Inst::I32Const(31));
+                self.num(NumOp::I32Shl);
+                self.emit(Inst::I32Const(31));
+                self.num(NumOp::I32ShrS);
+            }
+            Type::I8 => self.num(NumOp::I32Extend8S),
+            Type::I16 => self.num(NumOp::I32Extend16S),
+            _ => {}
+        }
+    }
+
+    /// 栈顶的整数截断为 `ty`，保持零扩展
+    fn mask(&mut self, ty: Type) {
+        let mask = match ty {
+            Type::I1 => 1,
+            Type::I8 => 0xff,
+            Type::I16 => 0xffff,
+            _ => return,
+        };
+        self.emit(Inst::I32Const(mask));
+        self.num(NumOp::I32And);
+    }
+
+    /// 控制结构中 `target` 的相对深度
+    fn depth(&self, target: Frame) -> u32 {
+        let pos = self.frames.iter().rposition(|x| *x == target).unwrap();
+        (self.frames.len() - 1 - pos) as u32
+    }
+
+    /// 按支配树生成以 `block` 为根的子树
+    fn tree(&mut self, block: BlockId) -> CodegenResult<()> {
+        let merges = self.shape.merge_children(block);
+        if !self.shape.is_loop_header(block) {
+            return self.within(block, &merges);
+        }
+        self.emit(Inst::Loop);
+        self.frames.push(Frame::Loop(block));
+        self.within(block, &merges)?;
+        self.frames.pop();
+        self.emit(Inst::End);
+        Ok(())
+    }
+
+    /// 每个合流点包一层 `block`，最内层是 `block` 自己的代码，合流点的代码跟在对应的 `end` 后面
+    fn within(&mut self, block: BlockId, merges: &[BlockId]) -> CodegenResult<()> {
+        let Some((first, rest)) = merges.split_first() else {
+            self.body(block)?;
+            return self.terminator(block);
+        };
+        self.emit(Inst::Block);
+        self.frames.push(Frame::Block(*first));
+        self.within(block, rest)?;
+        self.frames.pop();
+        self.emit(Inst::End);
+        self.tree(*first)
+    }
+
+    /// 不可归约的控制流：标签变量保存下一个基本块的编号，循环中用 `br_table` 分发
+    fn dispatch(&mut self) -> CodegenResult<()> {
+        let label = self.new_local(ValType::I32);
+        self.label = Some(label);
+        let blocks = self.shape.dom.rpo().to_vec();
+        self.emit(Inst::I32Const(0));
+        self.emit(Inst::LocalSet(label));
+        self.emit(Inst::Loop);
+        self.frames.push(Frame::Dispatch);
+        for _ in blocks.iter() {
+            self.emit(Inst::Block);
+            self.frames.push(Frame::Other);
+        }
+        self.emit(Inst::LocalGet(label));
+        let n = blocks.len() as u32;
+        self.emit(Inst::BrTable((0..n).collect(), n - 1));
+        for block in blocks {
+            self.frames.pop();
+            self.emit(Inst::End);
+            self.body(block)?;
+            self.terminator(block)?;
+        }
+        self.frames.pop();
+        self.emit(Inst::End);
+        Ok(())
+    }
+
+    /// 跳转：先复制 phi，再跳到循环开头、合流点，或者内联目标
+    fn branch(&mut self, from: BlockId, to: BlockId) -> CodegenResult<()> {
+        self.phi_copies(from, to);
+        if let Some(label) = self.label {
+            let index = self.shape.dom.order(to).unwrap();
+            self.emit(Inst::I32Const(index as i32));
+            self.emit(Inst::LocalSet(label));
+            self.emit(Inst::Br(self.depth(Frame::Dispatch)));
+        } else if self.shape.is_backward(from, to) {
+            self.emit(Inst::Br(self.depth(Frame::Loop(to))));
+        } else if self.shape.is_merge(to) {
+            self.emit(Inst::Br(self.depth(Frame::Block(to))));
+        } else {
+            self.tree(to)?;
+        }
+        Ok(())
+    }
+
+    /// 先把所有入边的值压栈再倒序写入，相当于并行复制
+    fn phi_copies(&mut self, from: BlockId, to: BlockId) {
+        let mut targets = Vec::new();
+        for phi in self.func.phis(to) {
+            let InstKind::Phi { incomings } = &self.func.insts[phi].kind else {
+                unreachable!()
+            };
+            if let Some((_, value)) = incomings.iter().find(|x| x.0 == from) {
+                self.push(*value);
+                targets.push(self.values[phi]);
+            }
+        }
+        for local in targets.into_iter().rev() {
+            self.emit(Inst::LocalSet(local));
+        }
+    }
+
+    fn body(&mut self, block: BlockId) -> CodegenResult<()> {
+        for inst in self.func.blocks[block].insts.iter().cloned() {
+            let kind = &self.func.insts[inst].kind;
+            if !kind.is_terminator() && !kind.is_phi() {
+                self.inst(inst)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn terminator(&mut self, block: BlockId) -> CodegenResult<()> {
+        let Some(term) = self.func.terminator(block) else {
+            self.emit(Inst::Unreachable);
+            return Ok(());
+        };
+        match &self.func.insts[term].kind {
+            InstKind::Br { dest } => self.branch(block, *dest)?,
+            InstKind::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => {
+                self.push(*cond);
+                self.emit(Inst::If);
+                self.frames.push(Frame::Other);
+                self.branch(block, *then_dest)?;
+                self.emit(Inst::Else);
+                self.branch(block, *else_dest)?;
+                self.frames.pop();
+                self.emit(Inst::End);
+            }
+            InstKind::Switch {
+                val,
+                default,
+                cases,
+            } => self.switch(block, *val, *default, cases)?,
+            InstKind::Ret { val } => {
+                if let Some(fp) = self.fp {
+                    self.emit(Inst::LocalGet(fp));
+                    self.fixups.push(self.code.len());
+                    self.emit(Inst::I32Const(0));
+                    self.num(NumOp::I32Add);
+                    self.emit(Inst::GlobalSet(SP));
+                }
+                if let Some(val) = val {
+                    self.push(*val);
+                }
+                self.emit(Inst::Return);
+            }
+            InstKind::Unreachable => self.emit(Inst::Unreachable),
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    ///
+    /// 每个出边一层 `block`，默认目标在最内层；
+    /// 稠密的 case 用 `br_table` 选择跳出几层，稀疏的逐个比较
+    ///
+    fn switch(
+        &mut self,
+        block: BlockId,
+        val: Value,
+        default: BlockId,
+        cases: &[(u64, BlockId)],
+    ) -> CodegenResult<()> {
+        let wide = self.value_type(val) == Type::I64;
+        for _ in 0..=cases.len() {
+            self.emit(Inst::Block);
+            self.frames.push(Frame::Other);
+        }
+        let min = cases.iter().map(|x| x.0).min().unwrap_or(0);
+        let max = cases.iter().map(|x| x.0).max().unwrap_or(0);
+        let range = max.wrapping_sub(min);
+        let dense = !cases.is_empty() && range < MAX_TABLE && range < cases.len() as u64 * 4 + 8;
+        if dense {
+            let mut table = vec![0; range as usize + 1];
+            for (i, (value, _)) in cases.iter().enumerate() {
+                table[(value - min) as usize] = i as u32 + 1;
+            }
+            self.push(val);
+            if wide {
+                let tmp = self.new_local(ValType::I64);
+                self.emit(Inst::I64Const(min as i64));
+                self.num(NumOp::I64Sub);
+                self.emit(Inst::LocalTee(tmp));
+                self.emit(Inst::I64Const(range as i64));
+                self.num(NumOp::I64GtU);
+                self.emit(Inst::BrIf(0));
+                self.emit(Inst::LocalGet(tmp));
+                self.num(NumOp::I32WrapI64);
+            } else if min != 0 {
+                self.emit(Inst::I32Const(min as u32 as i32));
+                self.num(NumOp::I32Sub);
+            }
+            self.emit(Inst::BrTable(table, 0));
+        } else {
+            for (i, (value, _)) in cases.iter().enumerate() {
+                self.push(val);
+                if wide {
+                    self.emit(Inst::I64Const(*value as i64));
+                    self.num(NumOp::I64Eq);
+                } else {
+                    self.emit(Inst::I32Const(*value as u32 as i32));
+                    self.num(NumOp::I32Eq);
+                }
+                self.emit(Inst::BrIf(i as u32 + 1));
+            }
+            self.emit(Inst::Br(0));
+        }
+        let targets = [default].into_iter().chain(cases.iter().map(|x| x.1));
+        for target in targets {
+            self.frames.pop();
+            self.emit(Inst::End);
+            self.branch(block, target)?;
+        }
+        Ok(())
+    }
+
+    fn inst(&mut self, inst: InstId) -> CodegenResult<()> {
+        let data = &self.func.insts[inst];
+        let ty = data.ty;
+        match &data.kind {
+            InstKind::Binary { op, lhs, rhs, .. } => self.binary(*op, *lhs, *rhs, ty),
+            InstKind::FNeg { val } => {
+                self.push(*val);
+                self.num(match ty {
+                    Type::F32 => NumOp::F32Neg,
+                    _ => NumOp::F64Neg,
+                });
+            }
+            InstKind::Cmp { pred, lhs, rhs } => self.compare(*pred, *lhs, *rhs),
+            InstKind::Cast { op, val } => self.cast(*op, *val, ty),
+            InstKind::Select {
+                cond,
+                then_val,
+                else_val,
+            } => {
+                self.push(*then_val);
+                self.push(*else_val);
+                self.push(*cond);
+                self.emit(Inst::Select);
+            }
+            InstKind::Alloca { .. } => return Ok(()),
+            InstKind::Load { ptr, .. } => {
+                self.push(*ptr);
+                self.emit(Inst::Mem(load_op(ty)));
+            }
+            InstKind::Store { ptr, val, .. } => {
+                self.push(*ptr);
+                self.push(*val);
+                self.emit(Inst::Mem(store_op(self.value_type(*val))));
+            }
+            InstKind::Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } => self.gep(*base, *index, *scale, *offset),
+            InstKind::MemCopy { dst, src, size, .. } => {
+                self.push(*dst);
+                self.push(*src);
+                self.emit(Inst::I32Const(*size as i32));
+                self.emit(Inst::MemoryCopy);
+            }
+            InstKind::Call { sig, callee, args } => self.call(sig, *callee, args),
+            InstKind::VaStart { list } => {
+                self.push(*list);
+                self.emit(Inst::LocalGet(self.va_area.unwrap()));
+                self.emit(Inst::Mem(MemOp::I32Store));
+            }
+            InstKind::VaArg { list } => self.va_arg(*list, ty),
+            InstKind::VaEnd { .. } => {}
+            InstKind::VaCopy { dst, src } => {
+                self.push(*dst);
+                self.push(*src);
+                self.emit(Inst::Mem(MemOp::I32Load));
+                self.emit(Inst::Mem(MemOp::I32Store));
+            }
+            _ => unreachable!("terminator or phi in block body"),
+        }
+        if let Some(local) = self.values.get(inst).copied() {
+            self.emit(Inst::LocalSet(local));
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: Value, rhs: Value, ty: Type) {
+        use BinaryOp::*;
+        if op == FRem {
+            let func = match ty {
+                Type::F32 => self.syms.fmodf,
+                _ => self.syms.fmod,
+            };
+            self.push(lhs);
+            self.push(rhs);
+            self.emit(Inst::Call(func.unwrap()));
+            return;
+        }
+        if op.is_float() {
+            let f32 = ty == Type::F32;
+            self.push(lhs);
+            self.push(rhs);
+            self.num(match op {
+                FAdd if f32 => NumOp::F32Add,
+                FSub if f32 => NumOp::F32Sub,
+                FMul if f32 => NumOp::F32Mul,
+                FDiv if f32 => NumOp::F32Div,
+                FAdd => NumOp::F64Add,
+                FSub => NumOp::F64Sub,
+                FMul => NumOp::F64Mul,
+                _ => NumOp::F64Div,
+            });
+            return;
+        }
+        if ty == Type::I64 {
+            self.push(lhs);
+            self.push(rhs);
+            self.num(match op {
+                Add => NumOp::I64Add,
+                Sub => NumOp::I64Sub,
+                Mul => NumOp::I64Mul,
+                SDiv => NumOp::I64DivS,
+                UDiv => NumOp::I64DivU,
+                SRem => NumOp::I64RemS,
+                URem => NumOp::I64RemU,
+                And => NumOp::I64And,
+                Or => NumOp::I64Or,
+                Xor => NumOp::I64Xor,
+                Shl => NumOp::I64Shl,
+                LShr => NumOp::I64ShrU,
+                _ => NumOp::I64ShrS,
+            });
+            return;
+        }
+        let signed = matches!(op, SDiv | SRem | AShr);
+        match signed {
+            true => self.push_signed(lhs),
+            false => self.push(lhs),
+        }
+        match op {
+            SDiv | SRem => self.push_signed(rhs),
+            _ => self.push(rhs),
+        }
+        self.num(match op {
+            Add => NumOp::I32Add,
+            Sub => NumOp::I32Sub,
+            Mul => NumOp::I32Mul,
+            SDiv => NumOp::I32DivS,
+            UDiv => NumOp::I32DivU,
+            SRem => NumOp::I32RemS,
+            URem => NumOp::I32RemU,
+            And => NumOp::I32And,
+            Or => NumOp::I32Or,
+            Xor => NumOp::I32Xor,
+            Shl => NumOp::I32Shl,
+            LShr => NumOp::I32ShrU,
+            _ => NumOp::I32ShrS,
+        });
+        if matches!(op, Add | Sub | Mul | Shl) || signed {
+            self.mask(ty);
+        }
+    }
+
+    fn compare(&mut self, pred: CmpPred, lhs: Value, rhs: Value) {
+        use CmpPred::*;
+        let ty = self.value_type(lhs);
+        if pred.is_float() {
+            self.push(lhs);
+            self.push(rhs);
+            let f32 = ty == Type::F32;
+            self.num(match pred {
+                FOeq if f32 => NumOp::F32Eq,
+                FUne if f32 => NumOp::F32Ne,
+                FOlt if f32 => NumOp::F32Lt,
+                FOle if f32 => NumOp::F32Le,
+                FOgt if f32 => NumOp::F32Gt,
+                FOge if f32 => NumOp::F32Ge,
+                FOeq => NumOp::F64Eq,
+                FUne => NumOp::F64Ne,
+                FOlt => NumOp::F64Lt,
+                FOle => NumOp::F64Le,
+                FOgt => NumOp::F64Gt,
+                _ => NumOp::F64Ge,
+            });
+            return;
+        }
+        if ty == Type::I64 {
+            self.push(lhs);
+            self.push(rhs);
+            self.num(match pred {
+                Eq => NumOp::I64Eq,
+                Ne => NumOp::I64Ne,
+                Slt => NumOp::I64LtS,
+                Sle => NumOp::I64LeS,
+                Sgt => NumOp::I64GtS,
+                Sge => NumOp::I64GeS,
+                Ult => NumOp::I64LtU,
+                Ule => NumOp::I64LeU,
+                Ugt => NumOp::I64GtU,
+                _ => NumOp::I64GeU,
+            });
+            return;
+        }
+        if matches!(pred, Slt | Sle | Sgt | Sge) {
+            self.push_signed(lhs);
+            self.push_signed(rhs);
+        } else {
+            self.push(lhs);
+            self.push(rhs);
+        }
+        self.num(match pred {
+            Eq => NumOp::I32Eq,
+            Ne => NumOp::I32Ne,
+            Slt => NumOp::I32LtS,
+            Sle => NumOp::I32LeS,
+            Sgt => NumOp::I32GtS,
+            Sge => NumOp::I32GeS,
+            Ult => NumOp::I32LtU,
+            Ule => NumOp::I32LeU,
+            Ugt => NumOp::I32GtU,
+            _ => NumOp::I32GeU,
+        });
+    }
+
+    fn cast(&mut self, op: CastOp, val: Value, to: Type) {
+        let from = self.value_type(val);
+        self.push(val);
+        let wide_from = from == Type::I64;
+        let wide_to = to == Type::I64;
+        match op {
+            CastOp::Trunc => {
+                if wide_from {
+                    self.num(NumOp::I32WrapI64);
+                }
+                self.mask(to);
+            }
+            CastOp::ZExt | CastOp::PtrToInt => {
+                if wide_to && !wide_from {
+                    self.num(NumOp::I64ExtendI32U);
+                }
+                self.mask(to);
+            }
+            CastOp::SExt => {
+                self.sext(from);
+                match wide_to {
+                    true => self.num(NumOp::I64ExtendI32S),
+                    false => self.mask(to),
+                }
+            }
+            CastOp::FpToSi | CastOp::FpToUi => {
+                let signed = op == CastOp::FpToSi;
+                self.num(match (from, wide_to, signed) {
+                    (Type::F32, false, true) => NumOp::I32TruncF32S,
+                    (Type::F32, false, false) => NumOp::I32TruncF32U,
+                    (Type::F32, true, true) => NumOp::I64TruncF32S,
+                    (Type::F32, true, false) => NumOp::I64TruncF32U,
+                    (_, false, true) => NumOp::I32TruncF64S,
+                    (_, false, false) => NumOp::I32TruncF64U,
+                    (_, true, true) => NumOp::I64TruncF64S,
+                    (_, true, false) => NumOp::I64TruncF64U,
+                });
+                self.mask(to);
+            }
+            CastOp::SiToFp | CastOp::UiToFp => {
+                let signed = op == CastOp::SiToFp;
+                if signed {
+                    self.sext(from);
+                }
+                self.num(match (to, wide_from, signed) {
+                    (Type::F32, false, true) => NumOp::F32ConvertI32S,
+                    (Type::F32, false, false) => NumOp::F32ConvertI32U,
+                    (Type::F32, true, true) => NumOp::F32ConvertI64S,
+                    (Type::F32, true, false) => NumOp::F32ConvertI64U,
+                    (_, false, true) => NumOp::F64ConvertI32S,
+                    (_, false, false) => NumOp::F64ConvertI32U,
+                    (_, true, true) => NumOp::F64ConvertI64S,
+                    (_, true, false) => NumOp::F64ConvertI64U,
+                });
+            }
+            CastOp::FpExt => self.num(NumOp::F64PromoteF32),
+            CastOp::FpTrunc => self.num(NumOp::F32DemoteF64),
+            CastOp::IntToPtr => {
+                if wide_from {
+                    self.num(NumOp::I32WrapI64);
+                }
+            }
+            CastOp::Bitcast => match (val_type(from), val_type(to)) {
+                (ValType::I32, ValType::F32) => self.num(NumOp::F32ReinterpretI32),
+                (ValType::F32, ValType::I32) => self.num(NumOp::I32ReinterpretF32),
+                (ValType::I64, ValType::F64) => self.num(NumOp::F64ReinterpretI64),
+                (ValType::F64, ValType::I64) => self.num(NumOp::I64ReinterpretF64),
+                (ValType::I64, ValType::I32) => self.num(NumOp::I32WrapI64),
+                (ValType::I32, ValType::I64) => self.num(NumOp::I64ExtendI32U),
+                _ => {}
+            },
+        }
+    }
+
+    /// `base + index * scale + offset`，常数下标合并到偏移中，`i64` 下标截断为 32 位
+    fn gep(&mut self, base: Value, index: Value, scale: u64, offset: i64) {
+        self.push(base);
+        let mut offset = offset;
+        match index.as_int() {
+            Some(x) => offset = offset.wrapping_add(x.wrapping_mul(scale as i64)),
+            None => {
+                match self.value_type(index) {
+                    Type::I64 => {
+                        self.push(index);
+                        self.num(NumOp::I32WrapI64);
+                    }
+                    _ => self.push_signed(index),
+                }
+                if scale != 1 {
+                    self.emit(Inst::I32Const(scale as i32));
+                    self.num(NumOp::I32Mul);
+                }
+                self.num(NumOp::I32Add);
+            }
+        }
+        if offset as i32 != 0 {
+            self.emit(Inst::I32Const(offset as i32));
+            self.num(NumOp::I32Add);
+        }
+    }
+
+    ///
+    /// 调用：按值传递的结构体先复制到栈帧中再传地址；
+    /// 变参依次放在栈帧中的变参区域（按大小对齐，空隙清零），最后传区域的地址
+    ///
+    fn call(&mut self, sig: &Signature, callee: Value, args: &[Value]) {
+        let fixed = sig.params.len();
+        for (param, arg) in sig.params.iter().zip(args.iter()) {
+            let ParamAttr::ByVal { size, align, .. } = param.attr else {
+                self.push(*arg);
+                continue;
+            };
+            let offset = self.alloc(size, align);
+            self.frame_addr(offset);
+            self.push(*arg);
+            self.emit(Inst::I32Const(size as i32));
+            self.emit(Inst::MemoryCopy);
+            self.frame_addr(offset);
+        }
+        if sig.variadic {
+            self.varargs(&args[fixed..]);
+        }
+        match callee {
+            Value::Func(x) => self.emit(Inst::Call(self.syms.funcs[x])),
+            _ => {
+                self.push(callee);
+                let ty = self.wasm.type_index(func_type(sig));
+                self.emit(Inst::CallIndirect(ty));
+            }
+        }
+    }
+
+    fn varargs(&mut self, args: &[Value]) {
+        if args.is_empty() {
+            self.emit(Inst::I32Const(0));
+            return;
+        }
+        let mut offsets = Vec::with_capacity(args.len());
+        let mut size = 0;
+        for arg in args.iter() {
+            let bytes = vararg_size(self.value_type(*arg));
+            size = u32::next_multiple_of(size, bytes);
+            offsets.push(size);
+            size += bytes;
+        }
+        let size = size.next_multiple_of(8);
+        let area = self.alloc(size, 8);
+        self.frame_addr(area);
+        self.emit(Inst::I32Const(0));
+        self.emit(Inst::I32Const(size as i32));
+        self.emit(Inst::MemoryFill);
+        for (arg, offset) in args.iter().zip(offsets) {
+            self.frame_addr(area + offset);
+            self.push(*arg);
+            self.emit(Inst::Mem(store_op(self.value_type(*arg))));
+        }
+        self.frame_addr(area);
+    }
+
+    /// 取出变参区域中下一个参数：按大小对齐，读取后把 `va_list` 中的地址移到它后面
+    fn va_arg(&mut self, list: Value, ty: Type) {
+        let size = vararg_size(ty) as i32;
+        let cursor = self.new_local(ValType::I32);
+        self.push(list);
+        self.emit(Inst::Mem(MemOp::I32Load));
+        self.emit(Inst::I32Const(size - 1));
+        self.num(NumOp::I32Add);
+        self.emit(Inst::I32Const(-size));
+        self.num(NumOp::I32And);
+        self.emit(Inst::LocalSet(cursor));
+        self.push(list);
+        self.emit(Inst::LocalGet(cursor));
+        self.emit(Inst::I32Const(size));
+        self.num(NumOp::I32Add);
+        self.emit(Inst::Mem(MemOp::I32Store));
+        self.emit(Inst::LocalGet(cursor));
+        self.emit(Inst::Mem(load_op(ty)));
+    }
+}
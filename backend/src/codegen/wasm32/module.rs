@@ -0,0 +1,337 @@
+use crate::codegen::wasm32::inst::{Inst, ValType, uleb};
+
+/// 函数类型
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+/// 导入的函数
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub ty: u32,
+}
+
+///
+/// 定义的函数
+///
+/// # Members
+/// - `ty`: 函数类型的下标
+/// - `locals`: 参数之外的局部变量
+/// - `body`: 指令，不含最后的 `end`
+///
+#[derive(Debug, Clone)]
+pub struct Func {
+    pub name: String,
+    pub ty: u32,
+    pub locals: Vec<ValType>,
+    pub body: Vec<Inst>,
+}
+
+/// 全局变量，`init` 是一条常量指令
+#[derive(Debug, Clone)]
+pub struct Global {
+    pub name: String,
+    pub ty: ValType,
+    pub mutable: bool,
+    pub init: Inst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportKind {
+    Func,
+    Memory,
+    Global,
+}
+
+impl ExportKind {
+    fn code(self) -> u8 {
+        match self {
+            ExportKind::Func => 0,
+            ExportKind::Memory => 2,
+            ExportKind::Global => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub name: String,
+    pub kind: ExportKind,
+    pub index: u32,
+}
+
+/// 放在线性内存 `offset` 处的数据段
+#[derive(Debug, Clone)]
+pub struct Data {
+    pub offset: u32,
+    pub bytes: Vec<u8>,
+}
+
+///
+/// wasm 模块，只有一个线性内存和一个函数表
+///
+/// # Members
+/// - `types`: 函数类型，不重复
+/// - `imports` `funcs`: 函数的下标先排导入的函数，再排定义的函数
+/// - `table`: 函数表从下标 1 开始的内容（函数下标），下标 0 留给空指针
+/// - `memory_pages`: 线性内存的初始页数，每页 64 KiB
+///
+#[derive(Debug, Clone, Default)]
+pub struct WasmModule {
+    pub types: Vec<FuncType>,
+    pub imports: Vec<Import>,
+    pub funcs: Vec<Func>,
+    pub table: Vec<u32>,
+    pub memory_pages: u32,
+    pub globals: Vec<Global>,
+    pub exports: Vec<Export>,
+    pub data: Vec<Data>,
+}
+
+impl WasmModule {
+    /// 函数类型的下标，没有时添加
+    pub fn type_index(&mut self, ty: FuncType) -> u32 {
+        match self.types.iter().position(|x| *x == ty) {
+            Some(x) => x as u32,
+            None => {
+                self.types.push(ty);
+                self.types.len() as u32 - 1
+            }
+        }
+    }
+
+    /// 函数下标空间中的名字
+    fn func_names(&self) -> Vec<String> {
+        let imports = self.imports.iter().map(|x| x.name.clone());
+        imports
+            .chain(self.funcs.iter().map(|x| x.name.clone()))
+            .collect()
+    }
+
+    /// 文本格式（`.wat`）
+    pub fn to_wat(&self) -> String {
+        let funcs = self.func_names();
+        let globals: Vec<String> = self.globals.iter().map(|x| x.name.clone()).collect();
+        let mut out = "(module\n".to_string();
+        for (i, ty) in self.types.iter().enumerate() {
+            out.push_str(&format!("  (type (;{};) (func{}))\n", i, signature(ty)));
+        }
+        for import in self.imports.iter() {
+            out.push_str(&format!(
+                "  (import \"{}\" \"{}\" (func ${} (type {})))\n",
+                import.module, import.name, import.name, import.ty
+            ));
+        }
+        for func in self.funcs.iter() {
+            let ty = &self.types[func.ty as usize];
+            out.push_str(&format!(
+                "  (func ${} (type {}){}\n",
+                func.name,
+                func.ty,
+                signature(ty)
+            ));
+            if !func.locals.is_empty() {
+                let locals: Vec<String> = func.locals.iter().map(|x| x.to_string()).collect();
+                out.push_str(&format!("    (local {})\n", locals.join(" ")));
+            }
+            let mut depth = 0;
+            for inst in func.body.iter() {
+                let (leave, enter) = inst.nesting();
+                if leave {
+                    depth -= 1;
+                }
+                let indent = "  ".repeat(depth + 2);
+                out.push_str(&format!("{}{}\n", indent, inst.wat(&funcs, &globals)));
+                if enter {
+                    depth += 1;
+                }
+            }
+            out.push_str("  )\n");
+        }
+        let size = self.table.len() + 1;
+        out.push_str(&format!("  (table (;0;) {} {} funcref)\n", size, size));
+        out.push_str(&format!("  (memory (;0;) {})\n", self.memory_pages));
+        for global in self.globals.iter() {
+            let ty = match global.mutable {
+                true => format!("(mut {})", global.ty),
+                false => global.ty.to_string(),
+            };
+            let init = global.init.wat(&funcs, &globals);
+            out.push_str(&format!("  (global ${} {} ({}))\n", global.name, ty, init));
+        }
+        for export in self.exports.iter() {
+            let target = match export.kind {
+                ExportKind::Func => format!("func ${}", funcs[export.index as usize]),
+                ExportKind::Memory => format!("memory {}", export.index),
+                ExportKind::Global => format!("global ${}", globals[export.index as usize]),
+            };
+            out.push_str(&format!("  (export \"{}\" ({}))\n", export.name, target));
+        }
+        if !self.table.is_empty() {
+            let names: Vec<String> = self
+                .table
+                .iter()
+                .map(|x| format!("${}", funcs[*x as usize]))
+                .collect();
+            out.push_str(&format!(
+                "  (elem (i32.const 1) func {})\n",
+                names.join(" ")
+            ));
+        }
+        for data in self.data.iter() {
+            out.push_str(&format!(
+                "  (data (i32.const {}) \"{}\")\n",
+                data.offset,
+                escape(&data.bytes)
+            ));
+        }
+        out.push_str(")\n");
+        out
+    }
+
+    /// 二进制格式（`.wasm`）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = b"\0asm".to_vec();
+        out.extend_from_slice(&1u32.to_le_bytes());
+
+        section(&mut out, 1, self.types.len(), |buf| {
+            for ty in self.types.iter() {
+                buf.push(0x60);
+                val_types(buf, &ty.params);
+                val_types(buf, &ty.results);
+            }
+        });
+        section(&mut out, 2, self.imports.len(), |buf| {
+            for import in self.imports.iter() {
+                name(buf, &import.module);
+                name(buf, &import.name);
+                buf.push(0x00);
+                uleb(buf, import.ty as u64);
+            }
+        });
+        section(&mut out, 3, self.funcs.len(), |buf| {
+            for func in self.funcs.iter() {
+                uleb(buf, func.ty as u64);
+            }
+        });
+        section(&mut out, 4, 1, |buf| {
+            let size = self.table.len() as u64 + 1;
+            buf.extend_from_slice(&[0x70, 0x01]);
+            uleb(buf, size);
+            uleb(buf, size);
+        });
+        section(&mut out, 5, 1, |buf| {
+            buf.push(0x00);
+            uleb(buf, self.memory_pages as u64);
+        });
+        section(&mut out, 6, self.globals.len(), |buf| {
+            for global in self.globals.iter() {
+                buf.push(global.ty.code());
+                buf.push(global.mutable as u8);
+                global.init.encode(buf);
+                Inst::End.encode(buf);
+            }
+        });
+        section(&mut out, 7, self.exports.len(), |buf| {
+            for export in self.exports.iter() {
+                name(buf, &export.name);
+                buf.push(export.kind.code());
+                uleb(buf, export.index as u64);
+            }
+        });
+        let elems = usize::from(!self.table.is_empty());
+        section(&mut out, 9, elems, |buf| {
+            buf.push(0x00);
+            Inst::I32Const(1).encode(buf);
+            Inst::End.encode(buf);
+            uleb(buf, self.table.len() as u64);
+            for x in self.table.iter() {
+                uleb(buf, *x as u64);
+            }
+        });
+        section(&mut out, 10, self.funcs.len(), |buf| {
+            for func in self.funcs.iter() {
+                let mut code = Vec::new();
+                let mut groups: Vec<(u32, ValType)> = Vec::new();
+                for ty in func.locals.iter() {
+                    match groups.last_mut() {
+                        Some((n, x)) if x == ty => *n += 1,
+                        _ => groups.push((1, *ty)),
+                    }
+                }
+                uleb(&mut code, groups.len() as u64);
+                for (n, ty) in groups {
+                    uleb(&mut code, n as u64);
+                    code.push(ty.code());
+                }
+                for inst in func.body.iter() {
+                    inst.encode(&mut code);
+                }
+                Inst::End.encode(&mut code);
+                uleb(buf, code.len() as u64);
+                buf.extend_from_slice(&code);
+            }
+        });
+        section(&mut out, 11, self.data.len(), |buf| {
+            for data in self.data.iter() {
+                buf.push(0x00);
+                Inst::I32Const(data.offset as i32).encode(buf);
+                Inst::End.encode(buf);
+                uleb(buf, data.bytes.len() as u64);
+                buf.extend_from_slice(&data.bytes);
+            }
+        });
+        out
+    }
+}
+
+/// 函数类型的文本：` (param ...) (result ...)`
+fn signature(ty: &FuncType) -> String {
+    let mut text = String::new();
+    for (kind, types) in [("param", &ty.params), ("result", &ty.results)] {
+        if !types.is_empty() {
+            let names: Vec<String> = types.iter().map(|x| x.to_string()).collect();
+            text.push_str(&format!(" ({} {})", kind, names.join(" ")));
+        }
+    }
+    text
+}
+
+/// 字符串字面量，不可打印的字节转义为 `\hh`
+fn escape(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for byte in bytes.iter().cloned() {
+        match byte {
+            0x20..=0x7e if byte != b'"' && byte != b'\\' => text.push(byte as char),
+            _ => text.push_str(&format!("\\{:02x}", byte)),
+        }
+    }
+    text
+}
+
+/// 段：id、内容长度、元素个数和内容，没有元素时不输出
+fn section(out: &mut Vec<u8>, id: u8, count: usize, f: impl FnOnce(&mut Vec<u8>)) {
+    if count == 0 {
+        return;
+    }
+    let mut buf = Vec::new();
+    uleb(&mut buf, count as u64);
+    f(&mut buf);
+    out.push(id);
+    uleb(out, buf.len() as u64);
+    out.extend_from_slice(&buf);
+}
+
+fn val_types(out: &mut Vec<u8>, types: &[ValType]) {
+    uleb(out, types.len() as u64);
+    out.extend(types.iter().map(|x| x.code()));
+}
+
+fn name(out: &mut Vec<u8>, name: &str) {
+    uleb(out, name.len() as u64);
+    out.extend_from_slice(name.as_bytes());
+}
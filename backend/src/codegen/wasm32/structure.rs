@@ -0,0 +1,82 @@
+use crate::ir::Function;
+use crate::ir::cfg::DomTree;
+use crate::ir::value::BlockId;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+///
+/// 把控制流图转换为结构化控制流需要的分析（Ramsey, "Beyond Relooper"）
+///
+/// 可归约的控制流图按支配树生成代码：循环头包在 `loop` 中，回边是跳到 `loop` 开头的 `br`；
+/// 有多条前向入边的块（合流点）放在它的直接支配者的某个 `block` 之后，前向边是跳出这个 `block` 的 `br`；
+/// 其余的块只有一条前向入边，直接内联在前驱的跳转处
+///
+/// # Members
+/// - `dom`: 支配树
+/// - `loop_headers`: 有回边进入的块
+/// - `merges`: 有两条以上前向入边的块，同一前驱的多条边分别计数
+/// - `reducible`: 每条回边的目标都支配它的起点，不可归约时用标签分发
+///
+pub struct Shape {
+    pub dom: DomTree,
+    loop_headers: FxHashSet<BlockId>,
+    merges: FxHashSet<BlockId>,
+    pub reducible: bool,
+}
+
+impl Shape {
+    pub fn new(func: &Function) -> Self {
+        let dom = DomTree::new(func);
+        let mut loop_headers = FxHashSet::default();
+        let mut forward: FxHashMap<BlockId, usize> = FxHashMap::default();
+        let mut reducible = true;
+        for block in dom.rpo().iter().cloned() {
+            for succ in func.successors(block) {
+                if dom.order(succ) > dom.order(block) {
+                    *forward.entry(succ).or_default() += 1;
+                    continue;
+                }
+                loop_headers.insert(succ);
+                if !dom.dominates(succ, block) {
+                    reducible = false;
+                }
+            }
+        }
+        let merges = forward
+            .into_iter()
+            .filter(|(_, n)| *n >= 2)
+            .map(|(x, _)| x)
+            .collect();
+        Self {
+            dom,
+            loop_headers,
+            merges,
+            reducible,
+        }
+    }
+
+    pub fn is_loop_header(&self, block: BlockId) -> bool {
+        self.loop_headers.contains(&block)
+    }
+
+    pub fn is_merge(&self, block: BlockId) -> bool {
+        self.merges.contains(&block)
+    }
+
+    /// `from -> to` 是回边
+    pub fn is_backward(&self, from: BlockId, to: BlockId) -> bool {
+        self.dom.order(to) <= self.dom.order(from)
+    }
+
+    /// 支配树中是合流点的子节点，逆后序靠后的在前：它的 `block` 在最外层，代码在最后
+    pub fn merge_children(&self, block: BlockId) -> Vec<BlockId> {
+        let mut children: Vec<BlockId> = self
+            .dom
+            .children(block)
+            .iter()
+            .cloned()
+            .filter(|x| self.is_merge(*x))
+            .collect();
+        children.reverse();
+        children
+    }
+}
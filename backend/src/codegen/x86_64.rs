@@ -0,0 +1,42 @@
+/// x86-64 System V 后端，输出 AT&T 语法的 GNU 汇编或 ELF 目标文件
+/// # Contents
+/// - `inst`: 机器指令和寄存器
+/// - `abi`: System V 调用约定，参数和返回值的分类
+/// - `isel`: 指令选择
+/// - `emit`: 栈帧布局，汇编和目标文件输出
+/// - `encode`: 机器码编码
+pub mod abi;
+pub mod emit;
+pub mod encode;
+pub mod inst;
+pub mod isel;
+
+use crate::codegen::TargetIsa;
+use crate::err::codegen_error::CodegenResult;
+use crate::ir::Module;
+use crate::object::elf;
+use crate::target::TargetInfo;
+
+pub struct X86_64 {
+    info: TargetInfo,
+}
+
+impl X86_64 {
+    pub fn new(info: TargetInfo) -> Self {
+        Self { info }
+    }
+}
+
+impl TargetIsa for X86_64 {
+    fn info(&self) -> &TargetInfo {
+        &self.info
+    }
+
+    fn emit_asm(&self, module: &Module) -> CodegenResult<String> {
+        emit::emit_module(module)
+    }
+
+    fn emit_object(&self, module: &Module) -> CodegenResult<Vec<u8>> {
+        Ok(elf::write(&emit::emit_object(module)?))
+    }
+}
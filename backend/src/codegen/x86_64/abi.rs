@@ -0,0 +1,208 @@
+use crate::codegen::x86_64::inst::{FLOAT_ARGS, INT_ARGS, RAX, RDX, XMM0};
+use crate::ir::{AggShape, ParamAttr, Signature, Type};
+
+///
+/// 小的聚合类型的一个 8 字节片段
+///
+/// # Members
+/// - `offset` `size`: 在对象中的位置，最后一个片段可能不足 8 字节
+/// - `reg`: 分到的寄存器
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece {
+    pub offset: u32,
+    pub size: u32,
+    pub reg: u8,
+}
+
+///
+/// 参数的位置
+/// - `Reg`: 标量在寄存器中
+/// - `Stack`: 标量在栈上，偏移相对于参数区域的开头
+/// - `Pieces`: 小的聚合类型拆开放在寄存器中
+/// - `StackAgg`: 聚合类型整体复制到栈上
+/// - `Ignored`: 不传递，通过寄存器返回聚合类型时的 `sret` 参数
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgLoc {
+    Reg(u8),
+    Stack(u64),
+    Pieces(Vec<Piece>),
+    StackAgg(u64),
+    Ignored,
+}
+
+///
+/// 返回值的位置
+/// - `Reg`: 标量在 `%rax` 或 `%xmm0` 中
+/// - `Pieces`: 聚合类型放在 `%rax` `%rdx` `%xmm0` `%xmm1` 中
+/// - `Memory`: 调用者通过 `%rdi` 提供地址，返回时 `%rax` 为这个地址
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetLoc {
+    None,
+    Reg(u8),
+    Pieces(Vec<Piece>),
+    Memory,
+}
+
+///
+/// 一次调用的参数和返回值位置
+///
+/// # Members
+/// - `args`: 每个参数（包括变参）的位置
+/// - `ret`: 返回值的位置
+/// - `stack_size`: 栈上参数区域的大小，对齐到 16 字节
+/// - `gp_used` `fp_used`: 用掉的整数和浮点参数寄存器个数，变参调用时 `%al` 为 `fp_used`
+///
+#[derive(Debug, Clone)]
+pub struct CallConv {
+    pub args: Vec<ArgLoc>,
+    pub ret: RetLoc,
+    pub stack_size: u64,
+    pub gp_used: usize,
+    pub fp_used: usize,
+}
+
+/// 8 字节片段的类别，`true` 为 SSE，`false` 为 INTEGER；需要通过内存传递时返回 None
+fn classify_agg(size: u32, shape: &AggShape) -> Option<Vec<(u32, u32, bool)>> {
+    if size > 16 || shape.is_empty() {
+        return None;
+    }
+    let mut pieces = Vec::new();
+    for i in 0..size.div_ceil(8) {
+        let (start, end) = (i * 8, (i * 8 + 8).min(size));
+        let mut is_float = true;
+        for (offset, ty) in shape.fields() {
+            let offset = *offset as u32;
+            let bytes = ty.bytes(8);
+            if offset < start || offset >= end {
+                continue;
+            }
+            // 跨越 8 字节边界的成员（packed）只能通过内存传递
+            if offset % 8 + bytes > 8 {
+                return None;
+            }
+            is_float &= ty.is_float();
+        }
+        pieces.push((start, end - start, is_float));
+    }
+    Some(pieces)
+}
+
+/// 寄存器分配的状态
+struct Alloc {
+    gp: usize,
+    fp: usize,
+    stack: u64,
+}
+
+impl Alloc {
+    /// 片段都放得进剩下的寄存器时才使用寄存器
+    fn pieces(
+        &mut self,
+        classes: &[(u32, u32, bool)],
+        int_regs: &[u8],
+        float_regs: &[u8],
+    ) -> Option<Vec<Piece>> {
+        let n_float = classes.iter().filter(|x| x.2).count();
+        let n_int = classes.len() - n_float;
+        if self.gp + n_int > int_regs.len() || self.fp + n_float > float_regs.len() {
+            return None;
+        }
+        let pieces = classes
+            .iter()
+            .map(|(offset, size, is_float)| {
+                let reg = match is_float {
+                    true => {
+                        self.fp += 1;
+                        float_regs[self.fp - 1]
+                    }
+                    false => {
+                        self.gp += 1;
+                        int_regs[self.gp - 1]
+                    }
+                };
+                Piece {
+                    offset: *offset,
+                    size: *size,
+                    reg,
+                }
+            })
+            .collect();
+        Some(pieces)
+    }
+
+    fn stack(&mut self, size: u64, align: u64) -> u64 {
+        let offset = self.stack.next_multiple_of(align.max(8));
+        self.stack = offset + size.next_multiple_of(8);
+        offset
+    }
+}
+
+/// 按 System V ABI 计算参数和返回值的位置，`arg_types` 是实际参数（包括变参）的类型
+pub fn classify(sig: &Signature, arg_types: &[Type]) -> CallConv {
+    let mut alloc = Alloc {
+        gp: 0,
+        fp: 0,
+        stack: 0,
+    };
+    let mut args = Vec::new();
+    let mut ret = match sig.ret {
+        Type::Void => RetLoc::None,
+        ty if ty.is_float() => RetLoc::Reg(XMM0),
+        _ => RetLoc::Reg(RAX),
+    };
+
+    for (i, ty) in arg_types.iter().enumerate() {
+        let attr = sig.params.get(i).map(|x| x.attr).unwrap_or_default();
+        let loc = match attr {
+            ParamAttr::SRet { size, shape, .. } => {
+                let mut ret_alloc = Alloc {
+                    gp: 0,
+                    fp: 0,
+                    stack: 0,
+                };
+                let pieces = classify_agg(size, &shape)
+                    .and_then(|x| ret_alloc.pieces(&x, &[RAX, RDX], &[XMM0, XMM0 + 1]));
+                match pieces {
+                    Some(pieces) => {
+                        ret = RetLoc::Pieces(pieces);
+                        ArgLoc::Ignored
+                    }
+                    None => {
+                        ret = RetLoc::Memory;
+                        alloc.gp += 1;
+                        ArgLoc::Reg(INT_ARGS[alloc.gp - 1])
+                    }
+                }
+            }
+            ParamAttr::ByVal { size, align, shape } => {
+                let pieces = classify_agg(size, &shape)
+                    .and_then(|x| alloc.pieces(&x, &INT_ARGS, &FLOAT_ARGS));
+                match pieces {
+                    Some(pieces) => ArgLoc::Pieces(pieces),
+                    None => ArgLoc::StackAgg(alloc.stack(size as u64, align as u64)),
+                }
+            }
+            ParamAttr::None if ty.is_float() && alloc.fp < FLOAT_ARGS.len() => {
+                alloc.fp += 1;
+                ArgLoc::Reg(FLOAT_ARGS[alloc.fp - 1])
+            }
+            ParamAttr::None if !ty.is_float() && alloc.gp < INT_ARGS.len() => {
+                alloc.gp += 1;
+                ArgLoc::Reg(INT_ARGS[alloc.gp - 1])
+            }
+            ParamAttr::None => ArgLoc::Stack(alloc.stack(8, 8)),
+        };
+        args.push(loc);
+    }
+
+    CallConv {
+        args,
+        ret,
+        stack_size: alloc.stack.next_multiple_of(16),
+        gp_used: alloc.gp,
+        fp_used: alloc.fp,
+    }
+}
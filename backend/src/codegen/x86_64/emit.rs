@@ -0,0 +1,290 @@
+use crate::codegen::asm::{emit_data, emit_linkage};
+use crate::codegen::data::{add_globals, binding};
+use crate::codegen::dwarf::{self, FuncCode, FuncDebug};
+use crate::codegen::mir::{MFunction, Reg, RegClassInfo, RegInfo};
+use crate::codegen::regalloc::allocate;
+use crate::codegen::x86_64::inst::*;
+use crate::codegen::x86_64::encode::encode_function;
+use crate::codegen::x86_64::isel::select;
+use crate::err::codegen_error::CodegenResult;
+use crate::ir::debug::DiSubprogram;
+use crate::ir::{Function, Module};
+use crate::object::{Object, Reloc, SectionKind, Symbol, SymbolKind};
+use crate::target::Arch;
+use std::fmt::Write;
+
+///
+/// 寄存器类别：`%rsp` `%rbp` 不参与分配，`%r10` `%r11` 和 `%xmm14` `%xmm15` 留给溢出的虚拟寄存器，
+/// 被调用者保存的整数寄存器是 `%rbx` `%r12` - `%r15`
+///
+pub const REG_INFO: RegInfo = RegInfo {
+    int: RegClassInfo {
+        allocatable: &[RAX, RCX, RDX, RSI, RDI, R8, R9, RBX, 12, 13, 14, 15],
+        scratch: &[R10, R11],
+    },
+    float: RegClassInfo {
+        allocatable: &[16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29],
+        scratch: &[XMM0 + 14, XMM0 + 15],
+    },
+    callee_saved: &[RBX, 12, 13, 14, 15],
+    spill_size: 8,
+};
+
+/// `%rbp` 的 DWARF 寄存器编号，调试信息中的帧基址
+const DWARF_RBP: u8 = 6;
+
+/// 有调试信息的函数，变量的位置是 `alloca` 对应的栈帧对象相对 `%rbp` 的偏移
+fn func_debug<'a>(
+    ir_func: &'a Function,
+    func: &MFunction<X86Inst>,
+    offsets: &[i64],
+    code: Option<FuncCode>,
+) -> Option<FuncDebug<'a>> {
+    let sub = ir_func.debug.as_ref()?;
+    let frame = func
+        .allocas
+        .iter()
+        .map(|(inst, slot)| (*inst, offsets[slot.0 as usize]))
+        .collect();
+    Some(FuncDebug {
+        symbol: &ir_func.name,
+        sub,
+        frame,
+        code,
+    })
+}
+
+/// 生成整个模块的汇编，有调试信息时输出 `.loc` 和调试信息节
+pub fn emit_module(module: &Module) -> CodegenResult<String> {
+    let mut out = String::new();
+    let funcs: Vec<_> = module
+        .func_ids()
+        .into_iter()
+        .filter(|x| !module.funcs[*x].is_declaration())
+        .collect();
+    if let Some(debug) = &module.debug {
+        dwarf::emit_file(&mut out, debug);
+    }
+    if !funcs.is_empty() {
+        writeln!(out, "\t.text").unwrap();
+    }
+    let mut debug_funcs = Vec::new();
+    for id in funcs {
+        let ir_func = &module.funcs[id];
+        let mut func = select(module, ir_func)?;
+        let saved = allocate(&mut func, &REG_INFO);
+        let offsets = finish(&mut func, &saved);
+        let sub = module.debug.as_ref().and(ir_func.debug.as_ref());
+        emit_function(&mut out, &func, sub);
+        if module.debug.is_some() {
+            debug_funcs.extend(func_debug(ir_func, &func, &offsets, None));
+        }
+    }
+    emit_data(&mut out, module);
+    if let Some(debug) = &module.debug {
+        let sections = dwarf::write(module, debug, &debug_funcs, DWARF_RBP);
+        dwarf::emit_sections(&mut out, &sections);
+    }
+    Ok(out)
+}
+
+/// 生成整个模块的目标文件，函数按 16 字节对齐，用 `nop` 填充，有调试信息时加入调试信息节
+pub fn emit_object(module: &Module) -> CodegenResult<Object> {
+    let mut obj = Object::new(Arch::X86_64);
+    let text = obj.section(SectionKind::Text);
+    let mut debug_funcs = Vec::new();
+    for id in module.func_ids() {
+        let ir_func = &module.funcs[id];
+        if ir_func.is_declaration() {
+            continue;
+        }
+        let mut func = select(module, ir_func)?;
+        let saved = allocate(&mut func, &REG_INFO);
+        let offsets = finish(&mut func, &saved);
+        let code = encode_function(&func);
+
+        let start = obj.sections[text].align_to(16, 0x90);
+        if module.debug.is_some() {
+            let code = FuncCode {
+                start,
+                size: code.bytes.len() as u64,
+                lines: code.lines.clone(),
+            };
+            debug_funcs.extend(func_debug(ir_func, &func, &offsets, Some(code)));
+        }
+        obj.define(Symbol {
+            name: func.name.clone(),
+            binding: binding(func.linkage),
+            kind: SymbolKind::Func,
+            visibility: func.visibility,
+            section: Some(text),
+            value: start,
+            size: code.bytes.len() as u64,
+        });
+        for reloc in code.relocs {
+            let symbol = obj.symbol(&reloc.symbol);
+            obj.sections[text].relocs.push(Reloc {
+                offset: start + reloc.offset,
+                symbol,
+                kind: reloc.kind,
+                addend: reloc.addend,
+            });
+        }
+        obj.sections[text].data.extend_from_slice(&code.bytes);
+    }
+    add_globals(&mut obj, module);
+    if let Some(debug) = &module.debug {
+        let sections = dwarf::write(module, debug, &debug_funcs, DWARF_RBP);
+        dwarf::add_sections(&mut obj, sections);
+    }
+    Ok(obj)
+}
+
+///
+/// 栈帧布局：
+///
+/// ```text
+/// 调用者的栈参数       16(%rbp) 开始
+/// 返回地址            8(%rbp)
+/// 保存的 %rbp         0(%rbp)
+/// 被调用者保存的寄存器
+/// 栈帧中的对象         负偏移
+/// 调用时的栈参数       0(%rsp) 开始
+/// ```
+///
+/// 返回 `%rsp` 的调整量和每个栈帧对象相对 `%rbp` 的偏移
+///
+fn layout_frame(func: &mut MFunction<X86Inst>, saved: usize) -> (u64, Vec<i64>) {
+    let mut cursor = saved as u64 * 8;
+    let mut offsets = Vec::with_capacity(func.slots.len());
+    for (size, align) in func.slots.iter() {
+        cursor = (cursor + size).next_multiple_of((*align).clamp(1, 16) as u64);
+        offsets.push(-(cursor as i64));
+    }
+    for inst in func.blocks.iter_mut().flatten() {
+        resolve_slots(inst, &offsets);
+    }
+    let frame = (cursor + func.outgoing).next_multiple_of(16) - saved as u64 * 8;
+    (frame, offsets)
+}
+
+/// 把栈帧对象和栈参数区域替换为 `%rbp` 加偏移
+fn resolve_slots(inst: &mut X86Inst, offsets: &[i64]) {
+    let mem = match inst {
+        X86Inst::Mov {
+            src: Src::Mem(mem), ..
+        }
+        | X86Inst::Alu {
+            src: Src::Mem(mem), ..
+        }
+        | X86Inst::Cmp {
+            rhs: Src::Mem(mem), ..
+        }
+        | X86Inst::FMov {
+            src: Src::Mem(mem), ..
+        }
+        | X86Inst::Store { dst: mem, .. }
+        | X86Inst::FStore { dst: mem, .. }
+        | X86Inst::Lea { mem, .. } => mem,
+        _ => return,
+    };
+    match mem.base {
+        Base::Slot(slot) => {
+            mem.base = Base::Reg(Reg::Phys(RBP));
+            mem.disp += offsets[slot.0 as usize];
+        }
+        Base::Incoming => {
+            mem.base = Base::Reg(Reg::Phys(RBP));
+            mem.disp += 16;
+        }
+        _ => {}
+    }
+}
+
+///
+/// 栈帧布局，并在入口加上函数序言、把每个 `Ret` 展开为函数尾声，得到最终的指令
+///
+/// 汇编输出和机器码编码共用这个结果，返回每个栈帧对象相对 `%rbp` 的偏移
+///
+pub fn finish(func: &mut MFunction<X86Inst>, saved: &[u8]) -> Vec<i64> {
+    let (frame, offsets) = layout_frame(func, saved.len());
+    let rbp = Reg::Phys(RBP);
+    let rsp = Reg::Phys(RSP);
+    let mut prologue = vec![
+        X86Inst::Push { reg: rbp },
+        X86Inst::Mov {
+            size: Size::Q,
+            dst: rbp,
+            src: Src::Reg(rsp),
+        },
+    ];
+    prologue.extend(saved.iter().map(|x| X86Inst::Push { reg: Reg::Phys(*x) }));
+    if frame > 0 {
+        prologue.push(X86Inst::Alu {
+            op: AluOp::Sub,
+            size: Size::Q,
+            dst: rsp,
+            src: Src::Imm(frame as i64),
+        });
+    }
+    let mut epilogue = Vec::new();
+    if saved.is_empty() {
+        epilogue.push(X86Inst::Leave);
+    } else {
+        epilogue.push(X86Inst::Lea {
+            dst: rsp,
+            mem: Mem::reg(rbp, -(saved.len() as i64 * 8)),
+        });
+        epilogue.extend(saved.iter().rev().map(|x| X86Inst::Pop { reg: Reg::Phys(*x) }));
+        epilogue.push(X86Inst::Pop { reg: rbp });
+    }
+
+    for block in func.blocks.iter_mut() {
+        let mut insts = Vec::with_capacity(block.len());
+        for inst in block.drain(..) {
+            if let X86Inst::Ret { .. } = inst {
+                insts.extend(epilogue.iter().cloned());
+            }
+            insts.push(inst);
+        }
+        *block = insts;
+    }
+    func.blocks[0].splice(0..0, prologue);
+    offsets
+}
+
+/// `sub` 不为 None 时函数开头（序言）对应定义所在的行，结尾有调试信息使用的标签
+fn emit_function(out: &mut String, func: &MFunction<X86Inst>, sub: Option<&DiSubprogram>) {
+    let name = &func.name;
+    let label = |x: usize| format!(".LBB_{}_{}", name, x);
+
+    emit_linkage(out, name, func.linkage, func.visibility);
+    writeln!(out, "\t.p2align 4").unwrap();
+    writeln!(out, "\t.type {}, @function", name).unwrap();
+    writeln!(out, "{}:", name).unwrap();
+    if let Some(sub) = sub {
+        writeln!(out, "\t.loc {} {} 0", sub.file, sub.line).unwrap();
+    }
+    for (i, block) in func.blocks.iter().enumerate() {
+        if i > 0 {
+            writeln!(out, "{}:", label(i)).unwrap();
+        }
+        for (j, inst) in block.iter().enumerate() {
+            // 跳转到紧接着的基本块时省略
+            if let X86Inst::Jmp { target } = inst
+                && *target == i + 1
+                && j + 1 == block.len()
+            {
+                continue;
+            }
+            out.push('\t');
+            inst.fmt_att(out, &label).unwrap();
+            out.push('\n');
+        }
+    }
+    if sub.is_some() {
+        writeln!(out, "{}:", dwarf::end_label(name)).unwrap();
+    }
+    writeln!(out, "\t.size {}, .-{}", name, name).unwrap();
+    writeln!(out).unwrap();
+}
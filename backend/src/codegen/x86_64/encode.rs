@@ -0,0 +1,616 @@
+use crate::codegen::mir::{MFunction, Reg};
+use crate::codegen::x86_64::inst::*;
+use crate::ir::debug::DebugLoc;
+use crate::object::RelocKind;
+
+///
+/// 函数内对符号的引用，偏移相对于函数开头
+///
+/// # Members
+/// - `offset`: 被修改的位置
+/// - `symbol`: 符号名
+/// - `kind`: 重定位方式
+/// - `addend`: 加数
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymReloc {
+    pub offset: u64,
+    pub symbol: String,
+    pub kind: RelocKind,
+    pub addend: i64,
+}
+
+///
+/// 一个函数的机器码
+///
+/// # Members
+/// - `bytes`: 指令字节
+/// - `relocs`: 对符号的引用，基本块之间的跳转已经解析
+/// - `lines`: `Loc` 所在的偏移和源码位置
+///
+#[derive(Debug, Clone, Default)]
+pub struct Code {
+    pub bytes: Vec<u8>,
+    pub relocs: Vec<SymReloc>,
+    pub lines: Vec<(u64, DebugLoc)>,
+}
+
+/// ModRM 的 r/m 操作数
+enum Rm<'a> {
+    Reg(Reg),
+    Mem(&'a Mem),
+}
+
+///
+/// 机器码编码器
+///
+/// # Members
+/// - `code`: 输出
+/// - `blocks`: 每个基本块的开始位置
+/// - `branches`: 跳转指令中 rel32 的位置和目标基本块
+/// - `rip`: 当前指令中 RIP 相对寻址的 disp32 位置、符号、偏移和重定位方式，指令结束时才能算出加数
+///
+struct Encoder {
+    code: Code,
+    blocks: Vec<usize>,
+    branches: Vec<(usize, usize)>,
+    rip: Option<(usize, String, i64, RelocKind)>,
+}
+
+/// 把 `finish` 之后的函数编码为机器码，跳转到紧接着的基本块时省略，和汇编输出一致
+pub fn encode_function(func: &MFunction<X86Inst>) -> Code {
+    let mut e = Encoder {
+        code: Code::default(),
+        blocks: Vec::with_capacity(func.blocks.len()),
+        branches: Vec::new(),
+        rip: None,
+    };
+    for (i, block) in func.blocks.iter().enumerate() {
+        e.blocks.push(e.code.bytes.len());
+        for (j, inst) in block.iter().enumerate() {
+            if let X86Inst::Jmp { target } = inst
+                && *target == i + 1
+                && j + 1 == block.len()
+            {
+                continue;
+            }
+            e.inst(inst);
+        }
+    }
+    for (pos, target) in e.branches.iter() {
+        let rel = e.blocks[*target] as i64 - (*pos as i64 + 4);
+        e.code.bytes[*pos..*pos + 4].copy_from_slice(&(rel as i32).to_le_bytes());
+    }
+    e.code
+}
+
+/// 物理寄存器的编号，整数和浮点寄存器都是 0 - 15
+fn num(reg: Reg) -> u8 {
+    match reg {
+        Reg::Phys(x) if x >= XMM0 => x - XMM0,
+        Reg::Phys(x) => x,
+        Reg::Virt(x) => unreachable!("virtual register %v{} after allocation", x),
+    }
+}
+
+fn cond_code(cond: Cond) -> u8 {
+    match cond {
+        Cond::B => 0x2,
+        Cond::Ae => 0x3,
+        Cond::E => 0x4,
+        Cond::Ne => 0x5,
+        Cond::Be => 0x6,
+        Cond::A => 0x7,
+        Cond::S => 0x8,
+        Cond::P => 0xa,
+        Cond::Np => 0xb,
+        Cond::L => 0xc,
+        Cond::Ge => 0xd,
+        Cond::Le => 0xe,
+        Cond::G => 0xf,
+    }
+}
+
+const NO_BYTE: (bool, bool) = (false, false);
+
+fn fits_i8(x: i64) -> bool {
+    i8::try_from(x).is_ok()
+}
+
+fn fits_i32(x: i64) -> bool {
+    i32::try_from(x).is_ok()
+}
+
+/// 浮点指令的强制前缀，`sd` 为 `F2`，`ss` 为 `F3`
+fn fprefix(double: bool) -> u8 {
+    if double { 0xf2 } else { 0xf3 }
+}
+
+impl Encoder {
+    fn byte(&mut self, x: u8) {
+        self.code.bytes.push(x);
+    }
+
+    fn imm(&mut self, x: i64, bytes: usize) {
+        self.code.bytes.extend_from_slice(&x.to_le_bytes()[..bytes]);
+    }
+
+    ///
+    /// 通用的指令格式：前缀、REX、操作码、ModRM（以及 SIB 和偏移），
+    /// `byte` 表示 reg 字段和 r/m 的寄存器是否是 8 位的，`spl` `bpl` `sil` `dil` 需要 REX 前缀
+    ///
+    fn modrm(
+        &mut self,
+        prefixes: &[u8],
+        w: bool,
+        byte: (bool, bool),
+        opcode: &[u8],
+        reg: u8,
+        rm: Rm,
+    ) {
+        // 段前缀要在 REX 之前
+        if let Rm::Mem(Mem { base: Base::Fs, .. }) = &rm {
+            self.byte(0x64);
+        }
+        self.code.bytes.extend_from_slice(prefixes);
+        let (b, low_byte) = match &rm {
+            Rm::Reg(x) => (num(*x), byte.1 && (4..8).contains(&num(*x))),
+            Rm::Mem(mem) => match mem.base {
+                Base::Reg(x) | Base::TpOff(x, _) => (num(x), false),
+                _ => (0, false),
+            },
+        };
+        let force = low_byte || (byte.0 && (4..8).contains(&reg));
+        let rex = 0x40 | (w as u8) << 3 | (reg >> 3) << 2 | b >> 3;
+        if rex != 0x40 || force {
+            self.byte(rex);
+        }
+        self.code.bytes.extend_from_slice(opcode);
+        let reg = (reg & 7) << 3;
+        match rm {
+            Rm::Reg(x) => self.byte(0xc0 | reg | num(x) & 7),
+            Rm::Mem(mem) => self.mem(reg, mem),
+        }
+    }
+
+    fn mem(&mut self, reg: u8, mem: &Mem) {
+        match &mem.base {
+            Base::Reg(base) => {
+                let base = num(*base) & 7;
+                let mode = match mem.disp {
+                    // `%rbp` `%r13` 没有不带偏移的形式
+                    0 if base != 5 => 0x00,
+                    x if fits_i8(x) => 0x40,
+                    _ => 0x80,
+                };
+                self.byte(mode | reg | base);
+                // `%rsp` `%r12` 需要 SIB
+                if base == 4 {
+                    self.byte(0x24);
+                }
+                match mode {
+                    0x40 => self.imm(mem.disp, 1),
+                    0x80 => self.imm(mem.disp, 4),
+                    _ => {}
+                }
+            }
+            // 线程局部变量的偏移总是 32 位，不是 PC 相对的，加数就是偏移
+            Base::TpOff(base, name) => {
+                let base = num(*base) & 7;
+                self.byte(0x80 | reg | base);
+                if base == 4 {
+                    self.byte(0x24);
+                }
+                let pos = self.code.bytes.len();
+                self.imm(0, 4);
+                self.reloc(pos, name.clone(), RelocKind::TpOff32, mem.disp);
+            }
+            // 没有基址和变址的 SIB
+            Base::Fs => {
+                self.byte(reg | 0x04);
+                self.byte(0x25);
+                self.imm(mem.disp, 4);
+            }
+            Base::Sym(name) | Base::Got(name) | Base::GotTpOff(name) => {
+                let kind = match mem.base {
+                    Base::Got(_) => RelocKind::GotPcRel,
+                    Base::GotTpOff(_) => RelocKind::GotTpOff,
+                    _ => RelocKind::Pc32,
+                };
+                self.byte(reg | 0x05);
+                self.rip = Some((self.code.bytes.len(), name.clone(), mem.disp, kind));
+                self.imm(0, 4);
+            }
+            Base::Slot(_) | Base::Incoming => unreachable!("unresolved frame address {}", mem),
+        }
+    }
+
+    /// 指令结束，RIP 相对寻址相对于下一条指令的地址
+    fn end(&mut self) {
+        if let Some((pos, symbol, disp, kind)) = self.rip.take() {
+            let addend = disp - (self.code.bytes.len() - pos) as i64;
+            self.reloc(pos, symbol, kind, addend);
+        }
+    }
+
+    fn reloc(&mut self, pos: usize, symbol: String, kind: RelocKind, addend: i64) {
+        self.code.relocs.push(SymReloc {
+            offset: pos as u64,
+            symbol,
+            kind,
+            addend,
+        });
+    }
+
+    /// 按宽度选择操作码：8 位用 `op8`，16 位加 `66` 前缀，64 位加 REX.W
+    fn sized(&mut self, size: Size, op8: &[u8], op: &[u8], reg: u8, rm: Rm) {
+        self.sized_with(size, op8, op, (reg, true), rm);
+    }
+
+    /// 同 `sized`，reg 字段是操作码扩展
+    fn ext(&mut self, size: Size, op8: &[u8], op: &[u8], ext: u8, rm: Rm) {
+        self.sized_with(size, op8, op, (ext, false), rm);
+    }
+
+    fn sized_with(&mut self, size: Size, op8: &[u8], op: &[u8], reg: (u8, bool), rm: Rm) {
+        match size {
+            Size::B => self.modrm(&[], false, (reg.1, true), op8, reg.0, rm),
+            Size::W => self.modrm(&[0x66], false, NO_BYTE, op, reg.0, rm),
+            Size::L => self.modrm(&[], false, NO_BYTE, op, reg.0, rm),
+            Size::Q => self.modrm(&[], true, NO_BYTE, op, reg.0, rm),
+        }
+    }
+
+    /// 带立即数的整数运算 `80` / `83` / `81`
+    fn alu_imm(&mut self, ext: u8, size: Size, rm: Rm, x: i64) {
+        match size {
+            Size::B => {
+                self.ext(size, &[0x80], &[], ext, rm);
+                self.imm(x, 1);
+            }
+            _ if fits_i8(x) => {
+                self.ext(size, &[], &[0x83], ext, rm);
+                self.imm(x, 1);
+            }
+            Size::W => {
+                self.ext(size, &[], &[0x81], ext, rm);
+                self.imm(x, 2);
+            }
+            _ => {
+                self.ext(size, &[], &[0x81], ext, rm);
+                self.imm(x, 4);
+            }
+        }
+    }
+
+    /// 带立即数的 `mov` 到内存，`C6` / `C7`，64 位的立即数符号扩展
+    fn store_imm(&mut self, size: Size, mem: &Mem, x: i64) {
+        self.ext(size, &[0xc6], &[0xc7], 0, Rm::Mem(mem));
+        self.imm(x, size.bytes().min(4) as usize);
+    }
+
+    fn mov_imm(&mut self, size: Size, dst: Reg, x: i64) {
+        let r = num(dst);
+        let rex = 0x40 | ((size == Size::Q) as u8) << 3 | r >> 3;
+        match size {
+            Size::Q if fits_i32(x) => {
+                self.ext(size, &[], &[0xc7], 0, Rm::Reg(dst));
+                self.imm(x, 4);
+            }
+            Size::B => {
+                if rex != 0x40 || (4..8).contains(&r) {
+                    self.byte(rex);
+                }
+                self.byte(0xb0 + (r & 7));
+                self.imm(x, 1);
+            }
+            _ => {
+                if size == Size::W {
+                    self.byte(0x66);
+                }
+                if rex != 0x40 {
+                    self.byte(rex);
+                }
+                self.byte(0xb8 + (r & 7));
+                self.imm(x, size.bytes() as usize);
+            }
+        }
+    }
+
+    /// `push` / `pop` 的短格式 `50+r` / `58+r`
+    fn push_pop(&mut self, opcode: u8, reg: Reg) {
+        let r = num(reg);
+        if r >= 8 {
+            self.byte(0x41);
+        }
+        self.byte(opcode + (r & 7));
+    }
+
+    fn branch(&mut self, opcode: &[u8], target: usize) {
+        self.code.bytes.extend_from_slice(opcode);
+        self.branches.push((self.code.bytes.len(), target));
+        self.imm(0, 4);
+    }
+
+    fn src(
+        &mut self,
+        size: Size,
+        op_rm: (&[u8], &[u8]),
+        op_r: (&[u8], &[u8]),
+        dst: Reg,
+        src: &Src,
+    ) {
+        match src {
+            // `op r/m, r`：源操作数在 reg 字段
+            Src::Reg(x) => self.sized(size, op_rm.0, op_rm.1, num(*x), Rm::Reg(dst)),
+            // `op r, r/m`
+            Src::Mem(mem) => self.sized(size, op_r.0, op_r.1, num(dst), Rm::Mem(mem)),
+            Src::Imm(_) => unreachable!(),
+        }
+    }
+
+    fn inst(&mut self, inst: &X86Inst) {
+        use X86Inst::*;
+        match inst {
+            Mov { size, dst, src } => match src {
+                Src::Imm(x) => self.mov_imm(*size, *dst, *x),
+                _ => self.src(*size, (&[0x88], &[0x89]), (&[0x8a], &[0x8b]), *dst, src),
+            },
+            Store { size, dst, src } => match src {
+                Src::Imm(x) => self.store_imm(*size, dst, *x),
+                Src::Reg(x) => self.sized(*size, &[0x88], &[0x89], num(*x), Rm::Mem(dst)),
+                Src::Mem(_) => unreachable!("memory to memory move"),
+            },
+            Lea { dst, mem } => self.modrm(&[], true, NO_BYTE, &[0x8d], num(*dst), Rm::Mem(mem)),
+            Alu {
+                op: AluOp::Imul,
+                size,
+                dst,
+                src,
+            } => match src {
+                Src::Imm(x) => {
+                    let rm = Rm::Reg(*dst);
+                    if fits_i8(*x) {
+                        self.sized(*size, &[], &[0x6b], num(*dst), rm);
+                        self.imm(*x, 1);
+                    } else {
+                        self.sized(*size, &[], &[0x69], num(*dst), rm);
+                        self.imm(*x, size.bytes().min(4) as usize);
+                    }
+                }
+                Src::Reg(x) => self.sized(*size, &[], &[0x0f, 0xaf], num(*dst), Rm::Reg(*x)),
+                Src::Mem(mem) => self.sized(*size, &[], &[0x0f, 0xaf], num(*dst), Rm::Mem(mem)),
+            },
+            Alu { op, size, dst, src } => {
+                let ext = match op {
+                    AluOp::Add => 0,
+                    AluOp::Or => 1,
+                    AluOp::And => 4,
+                    AluOp::Sub => 5,
+                    AluOp::Xor => 6,
+                    AluOp::Imul => unreachable!(),
+                };
+                match src {
+                    Src::Imm(x) => self.alu_imm(ext, *size, Rm::Reg(*dst), *x),
+                    _ => {
+                        let base = ext << 3;
+                        let op_rm = ([base], [base + 1]);
+                        let op_r = ([base + 2], [base + 3]);
+                        self.src(*size, (&op_rm.0, &op_rm.1), (&op_r.0, &op_r.1), *dst, src);
+                    }
+                }
+            }
+            Cmp { size, lhs, rhs } => match rhs {
+                Src::Imm(x) => self.alu_imm(7, *size, Rm::Reg(*lhs), *x),
+                _ => self.src(*size, (&[0x38], &[0x39]), (&[0x3a], &[0x3b]), *lhs, rhs),
+            },
+            Test { size, lhs, rhs } => {
+                self.sized(*size, &[0x84], &[0x85], num(*rhs), Rm::Reg(*lhs))
+            }
+            Shift { op, size, dst } => {
+                let ext = match op {
+                    ShiftOp::Shl => 4,
+                    ShiftOp::Shr => 5,
+                    ShiftOp::Sar => 7,
+                };
+                self.ext(*size, &[0xd2], &[0xd3], ext, Rm::Reg(*dst));
+            }
+            Unary { op, size, dst } => {
+                let ext = match op {
+                    UnaryOp::Not => 2,
+                    UnaryOp::Neg => 3,
+                };
+                self.ext(*size, &[0xf6], &[0xf7], ext, Rm::Reg(*dst));
+            }
+            SignExtendRax { size } => match size {
+                Size::Q => self.code.bytes.extend_from_slice(&[0x48, 0x99]),
+                _ => self.byte(0x99),
+            },
+            Div { signed, size, src } => {
+                let ext = if *signed { 7 } else { 6 };
+                self.ext(*size, &[0xf6], &[0xf7], ext, Rm::Reg(*src));
+            }
+            Movx {
+                signed,
+                from,
+                to,
+                dst,
+                src,
+            } => match (signed, from, to) {
+                (false, Size::L, Size::Q) => {
+                    self.sized(Size::L, &[], &[0x89], num(*src), Rm::Reg(*dst))
+                }
+                (true, Size::L, Size::Q) => {
+                    self.modrm(&[], true, NO_BYTE, &[0x63], num(*dst), Rm::Reg(*src))
+                }
+                _ => {
+                    let opcode = match (signed, from) {
+                        (false, Size::B) => 0xb6,
+                        (false, _) => 0xb7,
+                        (true, Size::B) => 0xbe,
+                        (true, _) => 0xbf,
+                    };
+                    let prefixes: &[u8] = if *to == Size::W { &[0x66] } else { &[] };
+                    let w = *to == Size::Q;
+                    let byte = (false, *from == Size::B);
+                    self.modrm(prefixes, w, byte, &[0x0f, opcode], num(*dst), Rm::Reg(*src));
+                }
+            },
+            Setcc { cond, dst } => {
+                let opcode = [0x0f, 0x90 + cond_code(*cond)];
+                self.modrm(&[], false, (false, true), &opcode, 0, Rm::Reg(*dst));
+            }
+            Cmov {
+                cond,
+                size,
+                dst,
+                src,
+            } => {
+                let opcode = [0x0f, 0x40 + cond_code(*cond)];
+                self.sized((*size).max(Size::L), &[], &opcode, num(*dst), Rm::Reg(*src));
+            }
+            FMov { double, dst, src } => match src {
+                Src::Reg(x) => {
+                    self.modrm(&[], false, NO_BYTE, &[0x0f, 0x28], num(*dst), Rm::Reg(*x))
+                }
+                Src::Mem(mem) => {
+                    let prefix = [fprefix(*double)];
+                    self.modrm(
+                        &prefix,
+                        false,
+                        NO_BYTE,
+                        &[0x0f, 0x10],
+                        num(*dst),
+                        Rm::Mem(mem),
+                    );
+                }
+                Src::Imm(_) => unreachable!("float move from immediate"),
+            },
+            FStore { double, dst, src } => {
+                let prefix = [fprefix(*double)];
+                self.modrm(
+                    &prefix,
+                    false,
+                    NO_BYTE,
+                    &[0x0f, 0x11],
+                    num(*src),
+                    Rm::Mem(dst),
+                );
+            }
+            FAlu {
+                op,
+                double,
+                dst,
+                src,
+            } => {
+                let (prefix, opcode) = match op {
+                    FloatOp::Add => (Some(fprefix(*double)), 0x58),
+                    FloatOp::Mul => (Some(fprefix(*double)), 0x59),
+                    FloatOp::Sub => (Some(fprefix(*double)), 0x5c),
+                    FloatOp::Div => (Some(fprefix(*double)), 0x5e),
+                    FloatOp::Xor => (None, 0x57),
+                };
+                let prefix: Vec<u8> = prefix.into_iter().collect();
+                self.modrm(
+                    &prefix,
+                    false,
+                    NO_BYTE,
+                    &[0x0f, opcode],
+                    num(*dst),
+                    Rm::Reg(*src),
+                );
+            }
+            Ucomi { double, lhs, rhs } => {
+                let prefix: &[u8] = if *double { &[0x66] } else { &[] };
+                self.modrm(
+                    prefix,
+                    false,
+                    NO_BYTE,
+                    &[0x0f, 0x2e],
+                    num(*lhs),
+                    Rm::Reg(*rhs),
+                );
+            }
+            CvtIntToFloat {
+                double,
+                size,
+                dst,
+                src,
+            } => {
+                let prefix = [fprefix(*double)];
+                let w = *size == Size::Q;
+                self.modrm(&prefix, w, NO_BYTE, &[0x0f, 0x2a], num(*dst), Rm::Reg(*src));
+            }
+            CvtFloatToInt {
+                double,
+                size,
+                dst,
+                src,
+            } => {
+                let prefix = [fprefix(*double)];
+                let w = *size == Size::Q;
+                self.modrm(&prefix, w, NO_BYTE, &[0x0f, 0x2c], num(*dst), Rm::Reg(*src));
+            }
+            CvtFloat {
+                to_double,
+                dst,
+                src,
+            } => {
+                // `cvtss2sd` 的前缀是 `F3`，`cvtsd2ss` 是 `F2`
+                let prefix = [fprefix(!*to_double)];
+                self.modrm(
+                    &prefix,
+                    false,
+                    NO_BYTE,
+                    &[0x0f, 0x5a],
+                    num(*dst),
+                    Rm::Reg(*src),
+                );
+            }
+            MovToXmm { size, dst, src } => {
+                let w = *size == Size::Q;
+                self.modrm(&[0x66], w, NO_BYTE, &[0x0f, 0x6e], num(*dst), Rm::Reg(*src));
+            }
+            MovFromXmm { size, dst, src } => {
+                let w = *size == Size::Q;
+                self.modrm(&[0x66], w, NO_BYTE, &[0x0f, 0x7e], num(*src), Rm::Reg(*dst));
+            }
+            RepMovsb => self.code.bytes.extend_from_slice(&[0xf3, 0xa4]),
+            Jmp { target } => self.branch(&[0xe9], *target),
+            Jcc { cond, target } => self.branch(&[0x0f, 0x80 + cond_code(*cond)], *target),
+            Call { target, .. } => match target {
+                CallTarget::Sym(name) | CallTarget::Plt(name) => {
+                    self.byte(0xe8);
+                    let pos = self.code.bytes.len();
+                    self.imm(0, 4);
+                    self.reloc(pos, name.clone(), RelocKind::Plt32, -4);
+                }
+                CallTarget::Reg(reg) => self.modrm(&[], false, NO_BYTE, &[0xff], 2, Rm::Reg(*reg)),
+            },
+            Ret { .. } => self.byte(0xc3),
+            TlsGetAddr { name } => {
+                self.code.bytes.extend_from_slice(&[0x66, 0x48, 0x8d, 0x3d]);
+                let pos = self.code.bytes.len();
+                self.imm(0, 4);
+                self.reloc(pos, name.clone(), RelocKind::TlsGd, -4);
+                self.code.bytes.extend_from_slice(&[0x66, 0x66, 0x48, 0xe8]);
+                let pos = self.code.bytes.len();
+                self.imm(0, 4);
+                self.reloc(pos, "__tls_get_addr".to_string(), RelocKind::Plt32, -4);
+            }
+            Ud2 => self.code.bytes.extend_from_slice(&[0x0f, 0x0b]),
+            Push { reg } => self.push_pop(0x50, *reg),
+            Pop { reg } => self.push_pop(0x58, *reg),
+            Leave => self.byte(0xc9),
+            Loc { file, line, col } => {
+                let loc = DebugLoc {
+                    file: *file,
+                    line: *line,
+                    col: *col,
+                };
+                self.code.lines.push((self.code.bytes.len() as u64, loc));
+            }
+        }
+        self.end();
+    }
+}
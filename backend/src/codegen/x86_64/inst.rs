@@ -0,0 +1,916 @@
+use crate::codegen::mir::{MachInst, Reg, RegClass, Role, StackSlot};
+use std::fmt::{Display, Formatter};
+
+pub const RAX: u8 = 0;
+pub const RCX: u8 = 1;
+pub const RDX: u8 = 2;
+pub const RBX: u8 = 3;
+pub const RSP: u8 = 4;
+pub const RBP: u8 = 5;
+pub const RSI: u8 = 6;
+pub const RDI: u8 = 7;
+pub const R8: u8 = 8;
+pub const R9: u8 = 9;
+pub const R10: u8 = 10;
+pub const R11: u8 = 11;
+/// `xmm0`，`xmm{n}` 的编号为 `XMM0 + n`
+pub const XMM0: u8 = 16;
+
+/// 整数参数寄存器
+pub const INT_ARGS: [u8; 6] = [RDI, RSI, RDX, RCX, R8, R9];
+/// 浮点参数寄存器 xmm0 - xmm7
+pub const FLOAT_ARGS: [u8; 8] = [16, 17, 18, 19, 20, 21, 22, 23];
+
+/// 调用者保存的寄存器，调用会破坏它们
+pub const CALLER_SAVED: [u8; 25] = [
+    RAX, RCX, RDX, RSI, RDI, R8, R9, R10, R11, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28,
+    29, 30, 31,
+];
+
+const NAMES: [[&str; 4]; 16] = [
+    ["al", "ax", "eax", "rax"],
+    ["cl", "cx", "ecx", "rcx"],
+    ["dl", "dx", "edx", "rdx"],
+    ["bl", "bx", "ebx", "rbx"],
+    ["spl", "sp", "esp", "rsp"],
+    ["bpl", "bp", "ebp", "rbp"],
+    ["sil", "si", "esi", "rsi"],
+    ["dil", "di", "edi", "rdi"],
+    ["r8b", "r8w", "r8d", "r8"],
+    ["r9b", "r9w", "r9d", "r9"],
+    ["r10b", "r10w", "r10d", "r10"],
+    ["r11b", "r11w", "r11d", "r11"],
+    ["r12b", "r12w", "r12d", "r12"],
+    ["r13b", "r13w", "r13d", "r13"],
+    ["r14b", "r14w", "r14d", "r14"],
+    ["r15b", "r15w", "r15d", "r15"],
+];
+
+/// 操作数宽度，对应 AT&T 后缀 `b` `w` `l` `q`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Size {
+    B,
+    W,
+    L,
+    Q,
+}
+
+impl Size {
+    pub fn bytes(self) -> u32 {
+        1 << self as u32
+    }
+
+    pub fn from_bytes(bytes: u32) -> Size {
+        match bytes {
+            1 => Size::B,
+            2 => Size::W,
+            4 => Size::L,
+            _ => Size::Q,
+        }
+    }
+
+    fn suffix(self) -> char {
+        match self {
+            Size::B => 'b',
+            Size::W => 'w',
+            Size::L => 'l',
+            Size::Q => 'q',
+        }
+    }
+}
+
+/// 条件码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cond {
+    E,
+    Ne,
+    L,
+    Le,
+    G,
+    Ge,
+    B,
+    Be,
+    A,
+    Ae,
+    P,
+    Np,
+    S,
+}
+
+impl Cond {
+    pub fn name(self) -> &'static str {
+        match self {
+            Cond::E => "e",
+            Cond::Ne => "ne",
+            Cond::L => "l",
+            Cond::Le => "le",
+            Cond::G => "g",
+            Cond::Ge => "ge",
+            Cond::B => "b",
+            Cond::Be => "be",
+            Cond::A => "a",
+            Cond::Ae => "ae",
+            Cond::P => "p",
+            Cond::Np => "np",
+            Cond::S => "s",
+        }
+    }
+
+    /// 相反的条件
+    pub fn inverse(self) -> Cond {
+        match self {
+            Cond::E => Cond::Ne,
+            Cond::Ne => Cond::E,
+            Cond::L => Cond::Ge,
+            Cond::Le => Cond::G,
+            Cond::G => Cond::Le,
+            Cond::Ge => Cond::L,
+            Cond::B => Cond::Ae,
+            Cond::Be => Cond::A,
+            Cond::A => Cond::Be,
+            Cond::Ae => Cond::B,
+            Cond::P => Cond::Np,
+            Cond::Np => Cond::P,
+            Cond::S => Cond::Ne,
+        }
+    }
+}
+
+///
+/// 内存操作数的基址
+/// - `Reg`: 寄存器
+/// - `Slot`: 栈帧中的对象，布局后替换为 `%rbp` 加偏移
+/// - `Sym`: 符号，RIP 相对寻址
+/// - `Got`: 符号的 GOT 项，RIP 相对寻址，其中存放符号的地址
+/// - `Incoming`: 调用者通过栈传递的参数区域，`%rbp + 16` 开始
+/// - `Fs`: `%fs` 段中的绝对地址，`%fs:0` 存放线程指针自身
+/// - `TpOff`: 寄存器中的线程指针加线程局部变量的偏移（local-exec）
+/// - `GotTpOff`: 存放线程局部变量偏移的 GOT 项，RIP 相对寻址（initial-exec）
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base {
+    Reg(Reg),
+    Slot(StackSlot),
+    Sym(String),
+    Got(String),
+    Incoming,
+    Fs,
+    TpOff(Reg, String),
+    GotTpOff(String),
+}
+
+/// 内存操作数 `disp(base)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mem {
+    pub base: Base,
+    pub disp: i64,
+}
+
+impl Mem {
+    pub fn reg(reg: Reg, disp: i64) -> Self {
+        Self {
+            base: Base::Reg(reg),
+            disp,
+        }
+    }
+
+    pub fn slot(slot: StackSlot, disp: i64) -> Self {
+        Self {
+            base: Base::Slot(slot),
+            disp,
+        }
+    }
+
+    pub fn sym(name: impl Into<String>, disp: i64) -> Self {
+        Self {
+            base: Base::Sym(name.into()),
+            disp,
+        }
+    }
+
+    pub fn got(name: impl Into<String>) -> Self {
+        Self {
+            base: Base::Got(name.into()),
+            disp: 0,
+        }
+    }
+
+    pub fn fs(disp: i64) -> Self {
+        Self {
+            base: Base::Fs,
+            disp,
+        }
+    }
+
+    pub fn tpoff(tp: Reg, name: impl Into<String>, disp: i64) -> Self {
+        Self {
+            base: Base::TpOff(tp, name.into()),
+            disp,
+        }
+    }
+
+    pub fn gottpoff(name: impl Into<String>) -> Self {
+        Self {
+            base: Base::GotTpOff(name.into()),
+            disp: 0,
+        }
+    }
+
+    pub fn offset(&self, disp: i64) -> Self {
+        Self {
+            base: self.base.clone(),
+            disp: self.disp + disp,
+        }
+    }
+
+    fn visit_regs(&mut self, f: &mut dyn FnMut(&mut Reg, Role)) {
+        if let Base::Reg(reg) | Base::TpOff(reg, _) = &mut self.base {
+            f(reg, Role::Use);
+        }
+    }
+}
+
+/// 源操作数：寄存器、立即数或内存
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Src {
+    Reg(Reg),
+    Imm(i64),
+    Mem(Mem),
+}
+
+impl Src {
+    fn visit_regs(&mut self, f: &mut dyn FnMut(&mut Reg, Role)) {
+        match self {
+            Src::Reg(reg) => f(reg, Role::Use),
+            Src::Mem(mem) => mem.visit_regs(f),
+            Src::Imm(_) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Imul,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftOp {
+    Shl,
+    Shr,
+    Sar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Xor,
+}
+
+/// 调用目标，`Plt` 是位置无关代码中经过 PLT 调用的符号
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallTarget {
+    Sym(String),
+    Plt(String),
+    Reg(Reg),
+}
+
+///
+/// x86-64 机器指令，操作数顺序和 AT&T 语法相反（目标在前）
+///
+/// 浮点指令的 `double` 为 true 时是 `sd` 版本，否则是 `ss` 版本；
+/// `Call` 的 `uses` `defs` 是参数和返回值使用的物理寄存器，栈帧布局后在 `Ret` 前插入函数尾声；
+/// `TlsGetAddr` 是 general-dynamic 模型固定的访问序列，调用 `__tls_get_addr`，结果在 `%rax`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum X86Inst {
+    Mov {
+        size: Size,
+        dst: Reg,
+        src: Src,
+    },
+    Store {
+        size: Size,
+        dst: Mem,
+        src: Src,
+    },
+    Lea {
+        dst: Reg,
+        mem: Mem,
+    },
+    Alu {
+        op: AluOp,
+        size: Size,
+        dst: Reg,
+        src: Src,
+    },
+    Cmp {
+        size: Size,
+        lhs: Reg,
+        rhs: Src,
+    },
+    Test {
+        size: Size,
+        lhs: Reg,
+        rhs: Reg,
+    },
+    Shift {
+        op: ShiftOp,
+        size: Size,
+        dst: Reg,
+    },
+    Unary {
+        op: UnaryOp,
+        size: Size,
+        dst: Reg,
+    },
+    /// `cltd` / `cqto`，把 `%rax` 符号扩展到 `%rdx`
+    SignExtendRax {
+        size: Size,
+    },
+    /// 除数为 `src`，被除数 `%rdx:%rax`，商在 `%rax`，余数在 `%rdx`
+    Div {
+        signed: bool,
+        size: Size,
+        src: Reg,
+    },
+    /// `movzx` / `movsx`，32 位到 64 位的零扩展用 `movl`
+    Movx {
+        signed: bool,
+        from: Size,
+        to: Size,
+        dst: Reg,
+        src: Reg,
+    },
+    Setcc {
+        cond: Cond,
+        dst: Reg,
+    },
+    Cmov {
+        cond: Cond,
+        size: Size,
+        dst: Reg,
+        src: Reg,
+    },
+    FMov {
+        double: bool,
+        dst: Reg,
+        src: Src,
+    },
+    FStore {
+        double: bool,
+        dst: Mem,
+        src: Reg,
+    },
+    FAlu {
+        op: FloatOp,
+        double: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    Ucomi {
+        double: bool,
+        lhs: Reg,
+        rhs: Reg,
+    },
+    /// 整数转浮点 `cvtsi2sd`，`size` 是整数的宽度（`L` 或 `Q`）
+    CvtIntToFloat {
+        double: bool,
+        size: Size,
+        dst: Reg,
+        src: Reg,
+    },
+    /// 浮点截断为整数 `cvttsd2si`
+    CvtFloatToInt {
+        double: bool,
+        size: Size,
+        dst: Reg,
+        src: Reg,
+    },
+    /// `cvtss2sd` / `cvtsd2ss`
+    CvtFloat {
+        to_double: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    /// 整数寄存器和浮点寄存器之间按位复制 `movd` / `movq`
+    MovToXmm {
+        size: Size,
+        dst: Reg,
+        src: Reg,
+    },
+    MovFromXmm {
+        size: Size,
+        dst: Reg,
+        src: Reg,
+    },
+    /// `rep movsb`，使用 `%rdi` `%rsi` `%rcx`
+    RepMovsb,
+    Jmp {
+        target: usize,
+    },
+    Jcc {
+        cond: Cond,
+        target: usize,
+    },
+    Call {
+        target: CallTarget,
+        uses: Vec<u8>,
+        defs: Vec<u8>,
+    },
+    Ret {
+        uses: Vec<u8>,
+    },
+    TlsGetAddr {
+        name: String,
+    },
+    Ud2,
+    /// 函数序言和尾声，只在栈帧布局后出现
+    Push {
+        reg: Reg,
+    },
+    Pop {
+        reg: Reg,
+    },
+    Leave,
+    /// 之后的指令对应的源码位置，不生成代码，汇编输出为 `.loc`
+    Loc {
+        file: u32,
+        line: u32,
+        col: u32,
+    },
+}
+
+fn visit_fixed(regs: &mut [u8], role: Role, f: &mut dyn FnMut(&mut Reg, Role)) {
+    for x in regs.iter_mut() {
+        let mut reg = Reg::Phys(*x);
+        f(&mut reg, role);
+    }
+}
+
+impl MachInst for X86Inst {
+    fn visit_regs(&mut self, f: &mut dyn FnMut(&mut Reg, Role)) {
+        use X86Inst::*;
+        match self {
+            Mov { dst, src, .. } => {
+                src.visit_regs(f);
+                f(dst, Role::Def);
+            }
+            Store { dst, src, .. } => {
+                src.visit_regs(f);
+                dst.visit_regs(f);
+            }
+            Lea { dst, mem } => {
+                mem.visit_regs(f);
+                f(dst, Role::Def);
+            }
+            Alu { dst, src, .. } => {
+                src.visit_regs(f);
+                f(dst, Role::UseDef);
+            }
+            Cmp { lhs, rhs, .. } => {
+                f(lhs, Role::Use);
+                rhs.visit_regs(f);
+            }
+            Test { lhs, rhs, .. } | Ucomi { lhs, rhs, .. } => {
+                f(lhs, Role::Use);
+                f(rhs, Role::Use);
+            }
+            Shift { dst, .. } => {
+                visit_fixed(&mut [RCX], Role::Use, f);
+                f(dst, Role::UseDef);
+            }
+            Unary { dst, .. } => f(dst, Role::UseDef),
+            SignExtendRax { .. } => {
+                visit_fixed(&mut [RAX], Role::Use, f);
+                visit_fixed(&mut [RDX], Role::Def, f);
+            }
+            Div { src, .. } => {
+                f(src, Role::Use);
+                visit_fixed(&mut [RAX, RDX], Role::UseDef, f);
+            }
+            Movx { dst, src, .. }
+            | CvtIntToFloat { dst, src, .. }
+            | CvtFloatToInt { dst, src, .. }
+            | CvtFloat { dst, src, .. }
+            | MovToXmm { dst, src, .. }
+            | MovFromXmm { dst, src, .. } => {
+                f(src, Role::Use);
+                f(dst, Role::Def);
+            }
+            Setcc { dst, .. } => f(dst, Role::Def),
+            Cmov { dst, src, .. } | FAlu { dst, src, .. } => {
+                f(src, Role::Use);
+                f(dst, Role::UseDef);
+            }
+            FMov { dst, src, .. } => {
+                src.visit_regs(f);
+                f(dst, Role::Def);
+            }
+            FStore { dst, src, .. } => {
+                f(src, Role::Use);
+                dst.visit_regs(f);
+            }
+            RepMovsb => visit_fixed(&mut [RDI, RSI, RCX], Role::UseDef, f),
+            Call { target, uses, defs } => {
+                if let CallTarget::Reg(reg) = target {
+                    f(reg, Role::Use);
+                }
+                visit_fixed(uses, Role::Use, f);
+                visit_fixed(defs, Role::Def, f);
+            }
+            Ret { uses } => visit_fixed(uses, Role::Use, f),
+            TlsGetAddr { .. } => visit_fixed(&mut [RAX], Role::Def, f),
+            Push { reg } => f(reg, Role::Use),
+            Pop { reg } => f(reg, Role::Def),
+            Jmp { .. } | Jcc { .. } | Ud2 | Leave | Loc { .. } => {}
+        }
+    }
+
+    fn as_move(&self) -> Option<(Reg, Reg)> {
+        match self {
+            X86Inst::Mov {
+                size: Size::Q,
+                dst,
+                src: Src::Reg(src),
+            }
+            | X86Inst::FMov {
+                double: true,
+                dst,
+                src: Src::Reg(src),
+            } => Some((*dst, *src)),
+            _ => None,
+        }
+    }
+
+    fn successors(&self) -> Vec<usize> {
+        match self {
+            X86Inst::Jmp { target } | X86Inst::Jcc { target, .. } => vec![*target],
+            _ => Vec::new(),
+        }
+    }
+
+    fn clobbers(&self) -> &'static [u8] {
+        match self {
+            X86Inst::Call { .. } | X86Inst::TlsGetAddr { .. } => &CALLER_SAVED,
+            _ => &[],
+        }
+    }
+
+    fn gen_move(dst: Reg, src: Reg, class: RegClass) -> Self {
+        match class {
+            RegClass::Int => X86Inst::Mov {
+                size: Size::Q,
+                dst,
+                src: Src::Reg(src),
+            },
+            RegClass::Float => X86Inst::FMov {
+                double: true,
+                dst,
+                src: Src::Reg(src),
+            },
+        }
+    }
+
+    fn gen_spill(slot: StackSlot, src: Reg, class: RegClass) -> Self {
+        match class {
+            RegClass::Int => X86Inst::Store {
+                size: Size::Q,
+                dst: Mem::slot(slot, 0),
+                src: Src::Reg(src),
+            },
+            RegClass::Float => X86Inst::FStore {
+                double: true,
+                dst: Mem::slot(slot, 0),
+                src,
+            },
+        }
+    }
+
+    fn gen_reload(dst: Reg, slot: StackSlot, class: RegClass) -> Self {
+        match class {
+            RegClass::Int => X86Inst::Mov {
+                size: Size::Q,
+                dst,
+                src: Src::Mem(Mem::slot(slot, 0)),
+            },
+            RegClass::Float => X86Inst::FMov {
+                double: true,
+                dst,
+                src: Src::Mem(Mem::slot(slot, 0)),
+            },
+        }
+    }
+}
+
+/// 寄存器名，浮点寄存器忽略宽度
+pub fn reg_name(reg: Reg, size: Size) -> String {
+    match reg {
+        Reg::Phys(x) if x >= XMM0 => format!("%xmm{}", x - XMM0),
+        Reg::Phys(x) => format!("%{}", NAMES[x as usize][size as usize]),
+        Reg::Virt(x) => format!("%v{}", x),
+    }
+}
+
+struct R(Reg, Size);
+
+impl Display for R {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", reg_name(self.0, self.1))
+    }
+}
+
+impl Display for Mem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.base {
+            Base::Reg(reg) => match self.disp {
+                0 => write!(f, "({})", R(*reg, Size::Q)),
+                disp => write!(f, "{}({})", disp, R(*reg, Size::Q)),
+            },
+            Base::Sym(name) => match self.disp {
+                0 => write!(f, "{}(%rip)", name),
+                disp => write!(f, "{}{:+}(%rip)", name, disp),
+            },
+            Base::Got(name) => write!(f, "{}@GOTPCREL(%rip)", name),
+            Base::Slot(x) => write!(f, "{}(slot{})", self.disp, x.0),
+            Base::Incoming => write!(f, "{}(%rbp)", self.disp + 16),
+            Base::Fs => write!(f, "%fs:{}", self.disp),
+            Base::TpOff(reg, name) => match self.disp {
+                0 => write!(f, "{}@tpoff({})", name, R(*reg, Size::Q)),
+                disp => write!(f, "{}@tpoff{:+}({})", name, disp, R(*reg, Size::Q)),
+            },
+            Base::GotTpOff(name) => write!(f, "{}@gottpoff(%rip)", name),
+        }
+    }
+}
+
+struct S<'a>(&'a Src, Size);
+
+impl Display for S<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Src::Reg(reg) => write!(f, "{}", R(*reg, self.1)),
+            Src::Imm(x) => write!(f, "${}", x),
+            Src::Mem(mem) => write!(f, "{}", mem),
+        }
+    }
+}
+
+fn fsuffix(double: bool) -> &'static str {
+    match double {
+        true => "sd",
+        false => "ss",
+    }
+}
+
+impl X86Inst {
+    /// AT&T 语法，`label` 给出基本块的标签名
+    pub fn fmt_att(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        label: &dyn Fn(usize) -> String,
+    ) -> std::fmt::Result {
+        use X86Inst::*;
+        match self {
+            Mov { size, dst, src } => match src {
+                Src::Imm(x) if *size == Size::Q && i32::try_from(*x).is_err() => {
+                    write!(f, "movabsq ${}, {}", x, R(*dst, *size))
+                }
+                _ => write!(
+                    f,
+                    "mov{} {}, {}",
+                    size.suffix(),
+                    S(src, *size),
+                    R(*dst, *size)
+                ),
+            },
+            Store { size, dst, src } => {
+                write!(f, "mov{} {}, {}", size.suffix(), S(src, *size), dst)
+            }
+            Lea { dst, mem } => write!(f, "leaq {}, {}", mem, R(*dst, Size::Q)),
+            Alu { op, size, dst, src } => {
+                let name = match op {
+                    AluOp::Add => "add",
+                    AluOp::Sub => "sub",
+                    AluOp::And => "and",
+                    AluOp::Or => "or",
+                    AluOp::Xor => "xor",
+                    AluOp::Imul => "imul",
+                };
+                write!(
+                    f,
+                    "{}{} {}, {}",
+                    name,
+                    size.suffix(),
+                    S(src, *size),
+                    R(*dst, *size)
+                )
+            }
+            Cmp { size, lhs, rhs } => {
+                write!(
+                    f,
+                    "cmp{} {}, {}",
+                    size.suffix(),
+                    S(rhs, *size),
+                    R(*lhs, *size)
+                )
+            }
+            Test { size, lhs, rhs } => {
+                write!(
+                    f,
+                    "test{} {}, {}",
+                    size.suffix(),
+                    R(*rhs, *size),
+                    R(*lhs, *size)
+                )
+            }
+            Shift { op, size, dst } => {
+                let name = match op {
+                    ShiftOp::Shl => "shl",
+                    ShiftOp::Shr => "shr",
+                    ShiftOp::Sar => "sar",
+                };
+                write!(f, "{}{} %cl, {}", name, size.suffix(), R(*dst, *size))
+            }
+            Unary { op, size, dst } => {
+                let name = match op {
+                    UnaryOp::Neg => "neg",
+                    UnaryOp::Not => "not",
+                };
+                write!(f, "{}{} {}", name, size.suffix(), R(*dst, *size))
+            }
+            SignExtendRax { size } => match size {
+                Size::Q => write!(f, "cqto"),
+                _ => write!(f, "cltd"),
+            },
+            Div { signed, size, src } => {
+                let name = if *signed { "idiv" } else { "div" };
+                write!(f, "{}{} {}", name, size.suffix(), R(*src, *size))
+            }
+            Movx {
+                signed,
+                from,
+                to,
+                dst,
+                src,
+            } => match (signed, from, to) {
+                (false, Size::L, Size::Q) => {
+                    write!(f, "movl {}, {}", R(*src, Size::L), R(*dst, Size::L))
+                }
+                (true, Size::L, Size::Q) => {
+                    write!(f, "movslq {}, {}", R(*src, Size::L), R(*dst, Size::Q))
+                }
+                _ => {
+                    let name = if *signed { "movs" } else { "movz" };
+                    write!(
+                        f,
+                        "{}{}{} {}, {}",
+                        name,
+                        from.suffix(),
+                        to.suffix(),
+                        R(*src, *from),
+                        R(*dst, *to)
+                    )
+                }
+            },
+            Setcc { cond, dst } => write!(f, "set{} {}", cond.name(), R(*dst, Size::B)),
+            Cmov {
+                cond,
+                size,
+                dst,
+                src,
+            } => {
+                let size = (*size).max(Size::L);
+                write!(
+                    f,
+                    "cmov{}{} {}, {}",
+                    cond.name(),
+                    size.suffix(),
+                    R(*src, size),
+                    R(*dst, size)
+                )
+            }
+            FMov { double, dst, src } => match src {
+                Src::Reg(src) => write!(f, "movaps {}, {}", R(*src, Size::Q), R(*dst, Size::Q)),
+                _ => write!(
+                    f,
+                    "mov{} {}, {}",
+                    fsuffix(*double),
+                    S(src, Size::Q),
+                    R(*dst, Size::Q)
+                ),
+            },
+            FStore { double, dst, src } => {
+                write!(f, "mov{} {}, {}", fsuffix(*double), R(*src, Size::Q), dst)
+            }
+            FAlu {
+                op,
+                double,
+                dst,
+                src,
+            } => {
+                let name = match op {
+                    FloatOp::Add => "add",
+                    FloatOp::Sub => "sub",
+                    FloatOp::Mul => "mul",
+                    FloatOp::Div => "div",
+                    FloatOp::Xor => {
+                        return write!(f, "xorps {}, {}", R(*src, Size::Q), R(*dst, Size::Q));
+                    }
+                };
+                write!(
+                    f,
+                    "{}{} {}, {}",
+                    name,
+                    fsuffix(*double),
+                    R(*src, Size::Q),
+                    R(*dst, Size::Q)
+                )
+            }
+            Ucomi { double, lhs, rhs } => {
+                write!(
+                    f,
+                    "ucomi{} {}, {}",
+                    fsuffix(*double),
+                    R(*rhs, Size::Q),
+                    R(*lhs, Size::Q)
+                )
+            }
+            CvtIntToFloat {
+                double,
+                size,
+                dst,
+                src,
+            } => write!(
+                f,
+                "cvtsi2{}{} {}, {}",
+                fsuffix(*double),
+                size.suffix(),
+                R(*src, *size),
+                R(*dst, Size::Q)
+            ),
+            CvtFloatToInt {
+                double,
+                size,
+                dst,
+                src,
+            } => write!(
+                f,
+                "cvtt{}2si{} {}, {}",
+                fsuffix(*double),
+                size.suffix(),
+                R(*src, Size::Q),
+                R(*dst, *size)
+            ),
+            CvtFloat {
+                to_double,
+                dst,
+                src,
+            } => {
+                let name = if *to_double { "cvtss2sd" } else { "cvtsd2ss" };
+                write!(f, "{} {}, {}", name, R(*src, Size::Q), R(*dst, Size::Q))
+            }
+            MovToXmm { size, dst, src } => {
+                let name = if *size == Size::Q { "movq" } else { "movd" };
+                write!(f, "{} {}, {}", name, R(*src, *size), R(*dst, Size::Q))
+            }
+            MovFromXmm { size, dst, src } => {
+                let name = if *size == Size::Q { "movq" } else { "movd" };
+                write!(f, "{} {}, {}", name, R(*src, Size::Q), R(*dst, *size))
+            }
+            RepMovsb => write!(f, "rep movsb"),
+            Jmp { target } => write!(f, "jmp {}", label(*target)),
+            Jcc { cond, target } => write!(f, "j{} {}", cond.name(), label(*target)),
+            Call { target, .. } => match target {
+                CallTarget::Sym(name) => write!(f, "call {}", name),
+                CallTarget::Plt(name) => write!(f, "call {}@PLT", name),
+                CallTarget::Reg(reg) => write!(f, "call *{}", R(*reg, Size::Q)),
+            },
+            Ret { .. } => write!(f, "ret"),
+            // 填充前缀使序列固定为 16 字节，链接器可以把它松弛为其他模型
+            TlsGetAddr { name } => write!(
+                f,
+                ".byte 0x66\n\tleaq {}@tlsgd(%rip), %rdi\n\t.value 0x6666\n\trex64\n\tcall __tls_get_addr@PLT",
+                name
+            ),
+            Ud2 => write!(f, "ud2"),
+            Push { reg } => write!(f, "pushq {}", R(*reg, Size::Q)),
+            Pop { reg } => write!(f, "popq {}", R(*reg, Size::Q)),
+            Leave => write!(f, "leave"),
+            Loc { file, line, col } => write!(f, ".loc {} {} {}", file, line, col),
+        }
+    }
+}
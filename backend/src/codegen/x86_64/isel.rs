@@ -0,0 +1,1760 @@
+use crate::codegen::mir::{MFunction, Reg, RegClass, StackSlot};
+use crate::codegen::x86_64::abi::{ArgLoc, CallConv, Piece, RetLoc, classify};
+use crate::codegen::x86_64::inst::*;
+use crate::err::codegen_error::{CodegenError, CodegenResult};
+use crate::ir::value::sign_extend;
+use crate::ir::{
+    AbiParam, BinaryOp, BlockId, CastOp, CmpPred, Function, InstId, InstKind, Linkage, Module,
+    ParamAttr, RelocModel, Signature, Type, Value, Visibility,
+};
+use rustc_hash::FxHashSet;
+use slotmap::SecondaryMap;
+
+/// `va_list` 中寄存器保存区域的大小：6 个整数寄存器和 8 个 xmm 寄存器
+const REG_SAVE_AREA: u64 = 6 * 8 + 8 * 16;
+
+/// 内联展开 `memcpy` 的最大字节数，更大的用 `rep movsb`
+const INLINE_COPY: u64 = 128;
+
+///
+/// 比较结果对应的标志位条件
+/// - `Cond`: 一个条件码
+/// - `FOeq` `FUne`: 浮点相等 / 不等还需要检查 PF（无序）
+///
+#[derive(Debug, Clone, Copy)]
+enum Flags {
+    Cond(Cond),
+    FOeq,
+    FUne,
+}
+
+///
+/// 线程局部变量的访问模型
+/// - `LocalExec`: 线程指针加链接时确定的偏移，只能访问可执行文件自己定义的变量
+/// - `InitialExec`: 偏移存放在 GOT 中，由链接器或加载时的动态链接器填写
+/// - `GeneralDynamic`: 调用 `__tls_get_addr`，可以访问任何模块中的变量
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TlsModel {
+    LocalExec,
+    InitialExec,
+    GeneralDynamic,
+}
+
+///
+/// 变参函数的信息
+///
+/// # Members
+/// - `save_area`: 入口处保存参数寄存器的区域
+/// - `gp_used` `fp_used` `stack_used`: 固定参数占用的寄存器和栈空间
+///
+struct VaInfo {
+    save_area: StackSlot,
+    gp_used: usize,
+    fp_used: usize,
+    stack_used: u64,
+}
+
+///
+/// x86-64 指令选择，把 IR 函数转换为使用虚拟寄存器的机器指令
+///
+/// # Members
+/// - `mf`: 生成的机器函数
+/// - `cur`: 当前的机器基本块
+/// - `blocks`: IR 基本块对应的机器基本块
+/// - `values`: 有结果的指令对应的虚拟寄存器
+/// - `args`: 参数对应的虚拟寄存器，聚合类型参数是副本的地址
+/// - `allocas`: `alloca` 对应的栈对象
+/// - `deferred`: 只被条件跳转使用的比较，在跳转处生成
+/// - `ret`: 返回值的位置
+/// - `sret`: 通过寄存器返回聚合类型时的局部缓冲区，或调用者提供的地址
+/// - `va`: 变参函数的信息
+///
+pub struct Isel<'a> {
+    module: &'a Module,
+    func: &'a Function,
+    mf: MFunction<X86Inst>,
+    cur: usize,
+    blocks: SecondaryMap<BlockId, usize>,
+    values: SecondaryMap<InstId, Reg>,
+    args: Vec<Reg>,
+    allocas: SecondaryMap<InstId, StackSlot>,
+    deferred: FxHashSet<InstId>,
+    ret: RetLoc,
+    sret: Option<Reg>,
+    va: Option<VaInfo>,
+}
+
+fn class_of(ty: Type) -> RegClass {
+    match ty.is_float() {
+        true => RegClass::Float,
+        false => RegClass::Int,
+    }
+}
+
+/// 整数类型的操作数宽度，`i1` 按一个字节处理
+pub fn size_of(ty: Type) -> Size {
+    match ty {
+        Type::I1 | Type::I8 => Size::B,
+        Type::I16 => Size::W,
+        Type::I32 | Type::F32 => Size::L,
+        _ => Size::Q,
+    }
+}
+
+fn int_cond(pred: CmpPred) -> Cond {
+    match pred {
+        CmpPred::Eq => Cond::E,
+        CmpPred::Ne => Cond::Ne,
+        CmpPred::Slt => Cond::L,
+        CmpPred::Sle => Cond::Le,
+        CmpPred::Sgt => Cond::G,
+        CmpPred::Sge => Cond::Ge,
+        CmpPred::Ult => Cond::B,
+        CmpPred::Ule => Cond::Be,
+        CmpPred::Ugt => Cond::A,
+        _ => Cond::Ae,
+    }
+}
+
+fn fits_i32(x: i64) -> bool {
+    i32::try_from(x).is_ok()
+}
+
+/// 指令选择，生成的函数还没有分配寄存器
+pub fn select(module: &Module, func: &Function) -> CodegenResult<MFunction<X86Inst>> {
+    let mut isel = Isel {
+        module,
+        func,
+        mf: MFunction::new(func.name.clone(), func.linkage, func.visibility),
+        cur: 0,
+        blocks: SecondaryMap::new(),
+        values: SecondaryMap::new(),
+        args: Vec::new(),
+        allocas: SecondaryMap::new(),
+        deferred: FxHashSet::default(),
+        ret: RetLoc::None,
+        sret: None,
+        va: None,
+    };
+    isel.run()?;
+    isel.mf.allocas = isel.allocas.iter().map(|(k, v)| (k, *v)).collect();
+    Ok(isel.mf)
+}
+
+impl<'a> Isel<'a> {
+    fn run(&mut self) -> CodegenResult<()> {
+        let func = self.func;
+        self.cur = self.mf.new_block();
+        for block in func.layout.iter() {
+            let index = self.mf.new_block();
+            self.blocks.insert(*block, index);
+        }
+        for (_, inst) in func.inst_iter() {
+            let data = &func.insts[inst];
+            match &data.kind {
+                InstKind::Alloca { size, align } => {
+                    let slot = self.mf.new_slot(*size, *align);
+                    self.allocas.insert(inst, slot);
+                }
+                _ if data.ty != Type::Void => {
+                    let reg = self.mf.new_vreg(class_of(data.ty));
+                    self.values.insert(inst, reg);
+                }
+                _ => {}
+            }
+        }
+        self.find_deferred();
+        self.entry();
+        let entry = self.blocks[func.entry()];
+        self.push(X86Inst::Jmp { target: entry });
+
+        // 源码位置变化的地方插入 `Loc`，基本块按输出顺序排列
+        let mut loc = None;
+        for block in func.layout.iter() {
+            self.cur = self.blocks[*block];
+            for inst in func.blocks[*block].insts.iter() {
+                if let Some(x) = func.locs.get(*inst).copied()
+                    && loc != Some(x)
+                {
+                    loc = Some(x);
+                    self.push(X86Inst::Loc {
+                        file: x.file,
+                        line: x.line,
+                        col: x.col,
+                    });
+                }
+                self.inst(*block, *inst)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unsupported<T>(&self, msg: impl Into<String>) -> CodegenResult<T> {
+        Err(CodegenError::Unsupported {
+            arch: "x86-64",
+            func: self.func.name.clone(),
+            msg: msg.into(),
+        })
+    }
+
+    /// 只被同一个基本块的条件跳转使用的比较
+    fn find_deferred(&mut self) {
+        let uses = self.func.use_counts();
+        for block in self.func.layout.iter() {
+            let Some(term) = self.func.terminator(*block) else {
+                continue;
+            };
+            let InstKind::CondBr {
+                cond: Value::Inst(cond),
+                ..
+            } = self.func.insts[term].kind
+            else {
+                continue;
+            };
+            if self.func.inst_block(cond) == Some(*block)
+                && uses.get(cond).copied() == Some(1)
+                && matches!(self.func.insts[cond].kind, InstKind::Cmp { .. })
+            {
+                self.deferred.insert(cond);
+            }
+        }
+    }
+
+    fn push(&mut self, inst: X86Inst) {
+        self.mf.blocks[self.cur].push(inst);
+    }
+
+    fn vreg(&mut self, class: RegClass) -> Reg {
+        self.mf.new_vreg(class)
+    }
+
+    fn sym(&self, value: Value) -> String {
+        self.module.symbol_name(value).to_string()
+    }
+
+    /// 符号的地址是否要从 GOT 中取得：PIC 中可能被其他模块抢占的符号，PIE 中外部声明的符号；
+    /// 其余符号和当前代码在同一个模块中，直接 RIP 相对寻址
+    fn via_got(&self, value: Value) -> bool {
+        let (declaration, linkage, visibility) = match value {
+            Value::Global(x) => {
+                let global = &self.module.globals[x];
+                (global.is_declaration(), global.linkage, global.visibility)
+            }
+            Value::Func(x) => {
+                let func = &self.module.funcs[x];
+                (func.is_declaration(), func.linkage, func.visibility)
+            }
+            _ => return false,
+        };
+        match self.module.reloc_model {
+            RelocModel::Static => false,
+            RelocModel::Pic => linkage != Linkage::Internal && visibility == Visibility::Default,
+            RelocModel::Pie => declaration && visibility == Visibility::Default,
+        }
+    }
+
+    /// 线程局部变量的访问模型，不是线程局部变量时为 None：
+    /// 可执行文件中定义的用 local-exec，外部声明的用 initial-exec，共享库中都用 general-dynamic
+    fn tls_model(&self, value: Value) -> Option<TlsModel> {
+        let Value::Global(x) = value else {
+            return None;
+        };
+        let global = &self.module.globals[x];
+        if !global.thread_local {
+            return None;
+        }
+        Some(match self.module.reloc_model {
+            RelocModel::Pic => TlsModel::GeneralDynamic,
+            _ if global.is_declaration() => TlsModel::InitialExec,
+            _ => TlsModel::LocalExec,
+        })
+    }
+
+    /// 读取线程指针 `%fs:0`
+    fn thread_pointer(&mut self) -> Reg {
+        let dst = self.vreg(RegClass::Int);
+        self.push(X86Inst::Mov {
+            size: Size::Q,
+            dst,
+            src: Src::Mem(Mem::fs(0)),
+        });
+        dst
+    }
+
+    /// 线程局部变量在当前线程中的地址
+    fn tls_addr(&mut self, value: Value, model: TlsModel) -> Reg {
+        let name = self.sym(value);
+        let dst = self.vreg(RegClass::Int);
+        match model {
+            TlsModel::LocalExec => {
+                let tp = self.thread_pointer();
+                let mem = Mem::tpoff(tp, name, 0);
+                self.push(X86Inst::Lea { dst, mem });
+            }
+            TlsModel::InitialExec => {
+                self.push(X86Inst::Mov {
+                    size: Size::Q,
+                    dst,
+                    src: Src::Mem(Mem::gottpoff(name)),
+                });
+                self.push(X86Inst::Alu {
+                    op: AluOp::Add,
+                    size: Size::Q,
+                    dst,
+                    src: Src::Mem(Mem::fs(0)),
+                });
+            }
+            TlsModel::GeneralDynamic => {
+                self.push(X86Inst::TlsGetAddr { name });
+                self.mov(Size::Q, dst, Reg::Phys(RAX));
+            }
+        }
+        dst
+    }
+
+    /// 直接调用的目标，位置无关代码中可能在其他模块的函数经过 PLT
+    fn call_target(&self, name: String, via_plt: bool) -> CallTarget {
+        match via_plt {
+            true => CallTarget::Plt(name),
+            false => CallTarget::Sym(name),
+        }
+    }
+
+    fn value_type(&self, value: Value) -> Type {
+        self.func.value_type(value)
+    }
+
+    /// 整数常量，按有符号解释，`i1` 的真为 1
+    fn const_int(&self, value: Value) -> Option<i64> {
+        match value {
+            Value::Int { ty: Type::I1, bits } => Some(bits as i64),
+            _ => value.as_int(),
+        }
+    }
+
+    /// 值放到一个寄存器中，常量和地址在使用处生成
+    fn reg(&mut self, value: Value) -> Reg {
+        match value {
+            Value::Inst(x) => match self.allocas.get(x).copied() {
+                Some(slot) => {
+                    let dst = self.vreg(RegClass::Int);
+                    let mem = Mem::slot(slot, 0);
+                    self.push(X86Inst::Lea { dst, mem });
+                    dst
+                }
+                None => self.values[x],
+            },
+            Value::Arg(x) => self.args[x as usize],
+            Value::Int { .. } => {
+                let dst = self.vreg(RegClass::Int);
+                let imm = self.const_int(value).unwrap();
+                self.push(X86Inst::Mov {
+                    size: Size::Q,
+                    dst,
+                    src: Src::Imm(imm),
+                });
+                dst
+            }
+            Value::Float { ty, bits } => {
+                let tmp = self.vreg(RegClass::Int);
+                self.push(X86Inst::Mov {
+                    size: Size::Q,
+                    dst: tmp,
+                    src: Src::Imm(bits as i64),
+                });
+                let dst = self.vreg(RegClass::Float);
+                self.push(X86Inst::MovToXmm {
+                    size: size_of(ty),
+                    dst,
+                    src: tmp,
+                });
+                dst
+            }
+            Value::Global(_) if let Some(model) = self.tls_model(value) => {
+                self.tls_addr(value, model)
+            }
+            Value::Global(_) | Value::Func(_) if self.via_got(value) => {
+                let dst = self.vreg(RegClass::Int);
+                self.push(X86Inst::Mov {
+                    size: Size::Q,
+                    dst,
+                    src: Src::Mem(Mem::got(self.sym(value))),
+                });
+                dst
+            }
+            Value::Global(_) | Value::Func(_) => {
+                let dst = self.vreg(RegClass::Int);
+                let mem = Mem::sym(self.sym(value), 0);
+                self.push(X86Inst::Lea { dst, mem });
+                dst
+            }
+            Value::Undef(ty) => {
+                let dst = self.vreg(class_of(ty));
+                match ty.is_float() {
+                    true => self.push(X86Inst::FAlu {
+                        op: FloatOp::Xor,
+                        double: true,
+                        dst,
+                        src: dst,
+                    }),
+                    false => self.push(X86Inst::Mov {
+                        size: Size::L,
+                        dst,
+                        src: Src::Imm(0),
+                    }),
+                }
+                dst
+            }
+        }
+    }
+
+    /// 整数源操作数，能用 32 位立即数表示的常量直接使用
+    fn src(&mut self, value: Value) -> Src {
+        match self.const_int(value) {
+            Some(x) if fits_i32(x) => Src::Imm(x),
+            _ => Src::Reg(self.reg(value)),
+        }
+    }
+
+    /// 指针指向的内存，`alloca` 和不经过 GOT 的全局变量直接寻址，local-exec 的线程局部变量相对线程指针寻址
+    fn mem(&mut self, ptr: Value, disp: i64) -> Mem {
+        match ptr {
+            Value::Global(_) if let Some(model) = self.tls_model(ptr) => match model {
+                TlsModel::LocalExec => {
+                    let tp = self.thread_pointer();
+                    Mem::tpoff(tp, self.sym(ptr), disp)
+                }
+                _ => Mem::reg(self.tls_addr(ptr, model), disp),
+            },
+            Value::Inst(x) if self.allocas.contains_key(x) => Mem::slot(self.allocas[x], disp),
+            Value::Global(_) if !self.via_got(ptr) => Mem::sym(self.sym(ptr), disp),
+            _ => Mem::reg(self.reg(ptr), disp),
+        }
+    }
+
+    fn mov(&mut self, size: Size, dst: Reg, src: Reg) {
+        self.push(X86Inst::Mov {
+            size,
+            dst,
+            src: Src::Reg(src),
+        });
+    }
+
+    /// 同类寄存器之间复制
+    fn copy(&mut self, class: RegClass, dst: Reg, src: Reg) {
+        match class {
+            RegClass::Int => self.mov(Size::Q, dst, src),
+            RegClass::Float => self.push(X86Inst::FMov {
+                double: true,
+                dst,
+                src: Src::Reg(src),
+            }),
+        }
+    }
+
+    /// 整数扩展到 `to`，`from` 不小于 `to` 时直接使用
+    fn extend(&mut self, value: Reg, from: Size, to: Size, signed: bool) -> Reg {
+        if from >= to {
+            return value;
+        }
+        let dst = self.vreg(RegClass::Int);
+        self.push(X86Inst::Movx {
+            signed,
+            from,
+            to,
+            dst,
+            src: value,
+        });
+        dst
+    }
+
+    /// 从内存读取 `size` 字节（1 到 8）到整数寄存器，不是 2 的幂时逐字节拼接
+    fn load_bytes(&mut self, mem: Mem, size: u32) -> Reg {
+        let dst = self.vreg(RegClass::Int);
+        if size.is_power_of_two() {
+            self.push(X86Inst::Mov {
+                size: Size::from_bytes(size),
+                dst,
+                src: Src::Mem(mem),
+            });
+            return dst;
+        }
+        self.push(X86Inst::Mov {
+            size: Size::L,
+            dst,
+            src: Src::Imm(0),
+        });
+        for i in (0..size).rev() {
+            let byte = self.vreg(RegClass::Int);
+            self.push(X86Inst::Mov {
+                size: Size::B,
+                dst: byte,
+                src: Src::Mem(mem.offset(i as i64)),
+            });
+            let byte = self.extend(byte, Size::B, Size::Q, false);
+            self.push(X86Inst::Alu {
+                op: AluOp::Imul,
+                size: Size::Q,
+                dst,
+                src: Src::Imm(256),
+            });
+            self.push(X86Inst::Alu {
+                op: AluOp::Or,
+                size: Size::Q,
+                dst,
+                src: Src::Reg(byte),
+            });
+        }
+        dst
+    }
+
+    /// 把整数寄存器的低 `size` 字节写到内存
+    fn store_bytes(&mut self, mem: Mem, value: Reg, size: u32) {
+        if size.is_power_of_two() {
+            self.push(X86Inst::Store {
+                size: Size::from_bytes(size),
+                dst: mem,
+                src: Src::Reg(value),
+            });
+            return;
+        }
+        let rest = self.vreg(RegClass::Int);
+        self.mov(Size::Q, rest, value);
+        for i in 0..size {
+            self.push(X86Inst::Store {
+                size: Size::B,
+                dst: mem.offset(i as i64),
+                src: Src::Reg(rest),
+            });
+            self.push(X86Inst::Mov {
+                size: Size::L,
+                dst: Reg::Phys(RCX),
+                src: Src::Imm(8),
+            });
+            self.push(X86Inst::Shift {
+                op: ShiftOp::Shr,
+                size: Size::Q,
+                dst: rest,
+            });
+        }
+    }
+
+    /// 聚合类型的一个片段读到寄存器中
+    fn load_piece(&mut self, mem: Mem, piece: &Piece) -> Reg {
+        let mem = mem.offset(piece.offset as i64);
+        match piece.reg >= XMM0 {
+            true => {
+                let dst = self.vreg(RegClass::Float);
+                self.push(X86Inst::FMov {
+                    double: piece.size == 8,
+                    dst,
+                    src: Src::Mem(mem),
+                });
+                dst
+            }
+            false => self.load_bytes(mem, piece.size),
+        }
+    }
+
+    fn store_piece(&mut self, mem: Mem, piece: &Piece, value: Reg) {
+        let mem = mem.offset(piece.offset as i64);
+        match piece.reg >= XMM0 {
+            true => self.push(X86Inst::FStore {
+                double: piece.size == 8,
+                dst: mem,
+                src: value,
+            }),
+            false => self.store_bytes(mem, value, piece.size),
+        }
+    }
+
+    fn piece_class(piece: &Piece) -> RegClass {
+        match piece.reg >= XMM0 {
+            true => RegClass::Float,
+            false => RegClass::Int,
+        }
+    }
+
+    /// 复制内存，小的对象逐 8 字节复制
+    fn memcpy(&mut self, dst: Mem, src: Mem, size: u64) {
+        if size <= INLINE_COPY {
+            let mut offset = 0;
+            while offset < size {
+                let chunk = match size - offset {
+                    8.. => 8,
+                    4..=7 => 4,
+                    2..=3 => 2,
+                    _ => 1,
+                };
+                let tmp = self.vreg(RegClass::Int);
+                self.push(X86Inst::Mov {
+                    size: Size::from_bytes(chunk as u32),
+                    dst: tmp,
+                    src: Src::Mem(src.offset(offset as i64)),
+                });
+                self.push(X86Inst::Store {
+                    size: Size::from_bytes(chunk as u32),
+                    dst: dst.offset(offset as i64),
+                    src: Src::Reg(tmp),
+                });
+                offset += chunk;
+            }
+            return;
+        }
+        self.push(X86Inst::Lea {
+            dst: Reg::Phys(RDI),
+            mem: dst,
+        });
+        self.push(X86Inst::Lea {
+            dst: Reg::Phys(RSI),
+            mem: src,
+        });
+        self.push(X86Inst::Mov {
+            size: Size::Q,
+            dst: Reg::Phys(RCX),
+            src: Src::Imm(size as i64),
+        });
+        self.push(X86Inst::RepMovsb);
+    }
+
+    /// 入口：参数从 ABI 规定的位置复制到虚拟寄存器
+    fn entry(&mut self) {
+        let sig = &self.func.sig;
+        let types: Vec<Type> = sig.params.iter().map(|x| x.ty).collect();
+        let conv = classify(sig, &types);
+
+        // 先把所有参数寄存器复制出来，后面的代码可能会使用这些寄存器
+        let mut incoming = Vec::new();
+        for (loc, ty) in conv.args.iter().zip(types.iter()) {
+            let regs: Vec<Reg> = match loc {
+                ArgLoc::Reg(r) => {
+                    let dst = self.vreg(class_of(*ty));
+                    self.copy(class_of(*ty), dst, Reg::Phys(*r));
+                    vec![dst]
+                }
+                ArgLoc::Pieces(pieces) => pieces
+                    .iter()
+                    .map(|piece| {
+                        let class = Self::piece_class(piece);
+                        let dst = self.vreg(class);
+                        self.copy(class, dst, Reg::Phys(piece.reg));
+                        dst
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            incoming.push(regs);
+        }
+        if sig.variadic {
+            let save_area = self.mf.new_slot(REG_SAVE_AREA, 16);
+            for (i, reg) in INT_ARGS.iter().enumerate() {
+                self.push(X86Inst::Store {
+                    size: Size::Q,
+                    dst: Mem::slot(save_area, i as i64 * 8),
+                    src: Src::Reg(Reg::Phys(*reg)),
+                });
+            }
+            for (i, reg) in FLOAT_ARGS.iter().enumerate() {
+                self.push(X86Inst::FStore {
+                    double: true,
+                    dst: Mem::slot(save_area, 48 + i as i64 * 16),
+                    src: Reg::Phys(*reg),
+                });
+            }
+            self.va = Some(VaInfo {
+                save_area,
+                gp_used: conv.gp_used,
+                fp_used: conv.fp_used,
+                stack_used: conv.stack_size,
+            });
+        }
+
+        for (i, (loc, regs)) in conv.args.iter().zip(incoming).enumerate() {
+            let ty = types[i];
+            let reg = match loc {
+                ArgLoc::Reg(_) => regs[0],
+                ArgLoc::Stack(offset) => {
+                    let dst = self.vreg(class_of(ty));
+                    let src = Src::Mem(Mem {
+                        base: Base::Incoming,
+                        disp: *offset as i64,
+                    });
+                    match ty.is_float() {
+                        true => self.push(X86Inst::FMov {
+                            double: ty == Type::F64,
+                            dst,
+                            src,
+                        }),
+                        false => self.push(X86Inst::Mov {
+                            size: size_of(ty),
+                            dst,
+                            src,
+                        }),
+                    }
+                    dst
+                }
+                ArgLoc::Pieces(pieces) => {
+                    let (size, align) = match sig.params[i].attr {
+                        ParamAttr::ByVal { size, align, .. } => (size, align),
+                        _ => unreachable!(),
+                    };
+                    let slot = self.mf.new_slot(size as u64, align.max(8));
+                    for (piece, reg) in pieces.iter().zip(regs) {
+                        self.store_piece(Mem::slot(slot, 0), piece, reg);
+                    }
+                    let dst = self.vreg(RegClass::Int);
+                    self.push(X86Inst::Lea {
+                        dst,
+                        mem: Mem::slot(slot, 0),
+                    });
+                    dst
+                }
+                ArgLoc::StackAgg(offset) => {
+                    let dst = self.vreg(RegClass::Int);
+                    let mem = Mem {
+                        base: Base::Incoming,
+                        disp: *offset as i64,
+                    };
+                    self.push(X86Inst::Lea { dst, mem });
+                    dst
+                }
+                ArgLoc::Ignored => {
+                    // 聚合类型通过寄存器返回，先写到局部缓冲区
+                    let (size, align) = match sig.params[i].attr {
+                        ParamAttr::SRet { size, align, .. } => (size, align),
+                        _ => unreachable!(),
+                    };
+                    let slot = self.mf.new_slot(size as u64, align.max(8));
+                    let dst = self.vreg(RegClass::Int);
+                    self.push(X86Inst::Lea {
+                        dst,
+                        mem: Mem::slot(slot, 0),
+                    });
+                    dst
+                }
+            };
+            self.args.push(reg);
+        }
+        if matches!(conv.ret, RetLoc::Pieces(_) | RetLoc::Memory) {
+            self.sret = Some(self.args[0]);
+        }
+        self.ret = conv.ret;
+    }
+
+    fn inst(&mut self, block: BlockId, inst: InstId) -> CodegenResult<()> {
+        let data = &self.func.insts[inst];
+        let ty = data.ty;
+        match &data.kind {
+            InstKind::Binary { op, lhs, rhs, .. } => {
+                let dst = self.values[inst];
+                self.binary(*op, ty, dst, *lhs, *rhs)?;
+            }
+            InstKind::FNeg { val } => {
+                let dst = self.values[inst];
+                let mask = self.vreg(RegClass::Int);
+                let bits = match ty {
+                    Type::F32 => 0x8000_0000,
+                    _ => i64::MIN,
+                };
+                self.push(X86Inst::Mov {
+                    size: Size::Q,
+                    dst: mask,
+                    src: Src::Imm(bits),
+                });
+                let mask_xmm = self.vreg(RegClass::Float);
+                self.push(X86Inst::MovToXmm {
+                    size: Size::Q,
+                    dst: mask_xmm,
+                    src: mask,
+                });
+                let val = self.reg(*val);
+                self.copy(RegClass::Float, dst, val);
+                self.push(X86Inst::FAlu {
+                    op: FloatOp::Xor,
+                    double: true,
+                    dst,
+                    src: mask_xmm,
+                });
+            }
+            InstKind::Cmp { .. } if self.deferred.contains(&inst) => {}
+            InstKind::Cmp { pred, lhs, rhs } => {
+                let dst = self.values[inst];
+                let flags = self.compare(*pred, *lhs, *rhs);
+                self.set_flags(dst, flags);
+            }
+            InstKind::Cast { op, val } => {
+                let dst = self.values[inst];
+                self.cast(*op, *val, ty, dst);
+            }
+            InstKind::Select {
+                cond,
+                then_val,
+                else_val,
+            } => {
+                let dst = self.values[inst];
+                let cond = self.reg(*cond);
+                if ty.is_float() {
+                    let then_reg = self.reg(*then_val);
+                    let else_reg = self.reg(*else_val);
+                    let then_block = self.mf.new_block();
+                    let else_block = self.mf.new_block();
+                    let join = self.mf.new_block();
+                    self.push(X86Inst::Test {
+                        size: Size::B,
+                        lhs: cond,
+                        rhs: cond,
+                    });
+                    self.push(X86Inst::Jcc {
+                        cond: Cond::Ne,
+                        target: then_block,
+                    });
+                    self.push(X86Inst::Jmp { target: else_block });
+                    self.cur = then_block;
+                    self.copy(RegClass::Float, dst, then_reg);
+                    self.push(X86Inst::Jmp { target: join });
+                    self.cur = else_block;
+                    self.copy(RegClass::Float, dst, else_reg);
+                    self.push(X86Inst::Jmp { target: join });
+                    self.cur = join;
+                } else {
+                    let then_reg = self.reg(*then_val);
+                    let else_reg = self.reg(*else_val);
+                    self.mov(Size::Q, dst, else_reg);
+                    self.push(X86Inst::Test {
+                        size: Size::B,
+                        lhs: cond,
+                        rhs: cond,
+                    });
+                    self.push(X86Inst::Cmov {
+                        cond: Cond::Ne,
+                        size: Size::Q,
+                        dst,
+                        src: then_reg,
+                    });
+                }
+            }
+            InstKind::Alloca { .. } | InstKind::Phi { .. } | InstKind::VaEnd { .. } => {}
+            InstKind::Load { ptr, .. } => {
+                let dst = self.values[inst];
+                let src = Src::Mem(self.mem(*ptr, 0));
+                match ty.is_float() {
+                    true => self.push(X86Inst::FMov {
+                        double: ty == Type::F64,
+                        dst,
+                        src,
+                    }),
+                    false => self.push(X86Inst::Mov {
+                        size: size_of(ty),
+                        dst,
+                        src,
+                    }),
+                }
+            }
+            InstKind::Store { ptr, val, .. } => {
+                let val_ty = self.value_type(*val);
+                match val_ty.is_float() {
+                    true => {
+                        let src = self.reg(*val);
+                        let dst = self.mem(*ptr, 0);
+                        self.push(X86Inst::FStore {
+                            double: val_ty == Type::F64,
+                            dst,
+                            src,
+                        });
+                    }
+                    false => {
+                        let src = self.src(*val);
+                        let dst = self.mem(*ptr, 0);
+                        self.push(X86Inst::Store {
+                            size: size_of(val_ty),
+                            dst,
+                            src,
+                        });
+                    }
+                }
+            }
+            InstKind::Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } => {
+                let dst = self.values[inst];
+                match self.const_int(*index) {
+                    Some(index) => {
+                        let disp = index.wrapping_mul(*scale as i64).wrapping_add(*offset);
+                        let mem = self.mem(*base, disp);
+                        self.push(X86Inst::Lea { dst, mem });
+                    }
+                    None => {
+                        let index_ty = self.value_type(*index);
+                        let index = self.reg(*index);
+                        let index = self.extend(index, size_of(index_ty), Size::Q, true);
+                        let scaled = self.vreg(RegClass::Int);
+                        self.mov(Size::Q, scaled, index);
+                        if *scale != 1 {
+                            self.push(X86Inst::Alu {
+                                op: AluOp::Imul,
+                                size: Size::Q,
+                                dst: scaled,
+                                src: Src::Imm(*scale as i64),
+                            });
+                        }
+                        let mem = self.mem(*base, *offset);
+                        self.push(X86Inst::Lea { dst, mem });
+                        self.push(X86Inst::Alu {
+                            op: AluOp::Add,
+                            size: Size::Q,
+                            dst,
+                            src: Src::Reg(scaled),
+                        });
+                    }
+                }
+            }
+            InstKind::MemCopy { dst, src, size, .. } => {
+                let dst = self.mem(*dst, 0);
+                let src = self.mem(*src, 0);
+                self.memcpy(dst, src, *size);
+            }
+            InstKind::Call { sig, callee, args } => {
+                let dst = self.values.get(inst).copied();
+                self.call(sig, *callee, args, dst)?;
+            }
+            InstKind::VaStart { list } => self.va_start(*list)?,
+            InstKind::VaArg { list } => {
+                let dst = self.values[inst];
+                self.va_arg(*list, ty, dst);
+            }
+            InstKind::VaCopy { dst, src } => {
+                let dst = self.mem(*dst, 0);
+                let src = self.mem(*src, 0);
+                self.memcpy(dst, src, 24);
+            }
+            InstKind::Br { dest } => {
+                self.phi_copies(block, *dest);
+                let target = self.blocks[*dest];
+                self.push(X86Inst::Jmp { target });
+            }
+            InstKind::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => {
+                let flags = match cond {
+                    Value::Inst(x) if self.deferred.contains(x) => {
+                        let InstKind::Cmp { pred, lhs, rhs } = self.func.insts[*x].kind else {
+                            unreachable!()
+                        };
+                        self.compare(pred, lhs, rhs)
+                    }
+                    _ => {
+                        let cond = self.reg(*cond);
+                        self.push(X86Inst::Test {
+                            size: Size::B,
+                            lhs: cond,
+                            rhs: cond,
+                        });
+                        Flags::Cond(Cond::Ne)
+                    }
+                };
+                let then_target = self.edge(block, *then_dest);
+                let else_target = self.edge(block, *else_dest);
+                match flags {
+                    Flags::Cond(cond) => self.push(X86Inst::Jcc {
+                        cond,
+                        target: then_target,
+                    }),
+                    Flags::FOeq => {
+                        self.push(X86Inst::Jcc {
+                            cond: Cond::P,
+                            target: else_target,
+                        });
+                        self.push(X86Inst::Jcc {
+                            cond: Cond::E,
+                            target: then_target,
+                        });
+                    }
+                    Flags::FUne => {
+                        self.push(X86Inst::Jcc {
+                            cond: Cond::P,
+                            target: then_target,
+                        });
+                        self.push(X86Inst::Jcc {
+                            cond: Cond::Ne,
+                            target: then_target,
+                        });
+                    }
+                }
+                self.push(X86Inst::Jmp {
+                    target: else_target,
+                });
+            }
+            InstKind::Switch {
+                val,
+                default,
+                cases,
+            } => {
+                let val_ty = self.value_type(*val);
+                let size = size_of(val_ty);
+                let val = self.reg(*val);
+                for (case, dest) in cases.iter() {
+                    let case = sign_extend(*case, val_ty.bits(8));
+                    let rhs = match fits_i32(case) {
+                        true => Src::Imm(case),
+                        false => Src::Reg(self.reg(Value::int(Type::I64, case))),
+                    };
+                    let target = self.edge(block, *dest);
+                    self.push(X86Inst::Cmp {
+                        size,
+                        lhs: val,
+                        rhs,
+                    });
+                    self.push(X86Inst::Jcc {
+                        cond: Cond::E,
+                        target,
+                    });
+                }
+                let target = self.edge(block, *default);
+                self.push(X86Inst::Jmp { target });
+            }
+            InstKind::Ret { val } => self.ret(*val),
+            InstKind::Unreachable => self.push(X86Inst::Ud2),
+        }
+        Ok(())
+    }
+
+    /// `from` 到 `to` 的边，`to` 有 phi 时新建一个基本块放置复制
+    fn edge(&mut self, from: BlockId, to: BlockId) -> usize {
+        if self.func.phis(to).is_empty() {
+            return self.blocks[to];
+        }
+        let cur = self.cur;
+        let block = self.mf.new_block();
+        self.cur = block;
+        self.phi_copies(from, to);
+        let target = self.blocks[to];
+        self.push(X86Inst::Jmp { target });
+        self.cur = cur;
+        block
+    }
+
+    /// phi 的并行复制，先复制到临时寄存器，避免互相覆盖
+    fn phi_copies(&mut self, from: BlockId, to: BlockId) {
+        let mut copies = Vec::new();
+        for phi in self.func.phis(to) {
+            let InstKind::Phi { incomings } = &self.func.insts[phi].kind else {
+                unreachable!()
+            };
+            let Some((_, value)) = incomings.iter().find(|(x, _)| *x == from) else {
+                continue;
+            };
+            let class = class_of(self.func.insts[phi].ty);
+            let src = self.reg(*value);
+            let tmp = self.vreg(class);
+            self.copy(class, tmp, src);
+            copies.push((self.values[phi], tmp, class));
+        }
+        for (dst, tmp, class) in copies {
+            self.copy(class, dst, tmp);
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        ty: Type,
+        dst: Reg,
+        lhs: Value,
+        rhs: Value,
+    ) -> CodegenResult<()> {
+        use BinaryOp::*;
+        let size = size_of(ty);
+        match op {
+            Add | Sub | And | Or | Xor | Mul => {
+                let alu = match op {
+                    Add => AluOp::Add,
+                    Sub => AluOp::Sub,
+                    And => AluOp::And,
+                    Or => AluOp::Or,
+                    Xor => AluOp::Xor,
+                    _ => AluOp::Imul,
+                };
+                // 没有 8 位的二操作数乘法，低位的结果相同
+                let size = match alu {
+                    AluOp::Imul => size.max(Size::W),
+                    _ => size,
+                };
+                let lhs = self.reg(lhs);
+                let src = self.src(rhs);
+                self.mov(Size::Q, dst, lhs);
+                self.push(X86Inst::Alu {
+                    op: alu,
+                    size,
+                    dst,
+                    src,
+                });
+            }
+            SDiv | UDiv | SRem | URem => {
+                let signed = matches!(op, SDiv | SRem);
+                let div_size = size.max(Size::L);
+                let lhs = self.reg(lhs);
+                let lhs = self.extend(lhs, size, div_size, signed);
+                let rhs = self.reg(rhs);
+                let rhs = self.extend(rhs, size, div_size, signed);
+                self.mov(Size::Q, Reg::Phys(RAX), lhs);
+                match signed {
+                    true => self.push(X86Inst::SignExtendRax { size: div_size }),
+                    false => self.push(X86Inst::Mov {
+                        size: Size::L,
+                        dst: Reg::Phys(RDX),
+                        src: Src::Imm(0),
+                    }),
+                }
+                self.push(X86Inst::Div {
+                    signed,
+                    size: div_size,
+                    src: rhs,
+                });
+                let result = match op {
+                    SDiv | UDiv => RAX,
+                    _ => RDX,
+                };
+                self.mov(Size::Q, dst, Reg::Phys(result));
+            }
+            Shl | LShr | AShr => {
+                let shift = match op {
+                    Shl => ShiftOp::Shl,
+                    LShr => ShiftOp::Shr,
+                    _ => ShiftOp::Sar,
+                };
+                let lhs = self.reg(lhs);
+                let rhs = self.reg(rhs);
+                self.mov(Size::Q, dst, lhs);
+                self.mov(Size::Q, Reg::Phys(RCX), rhs);
+                self.push(X86Inst::Shift {
+                    op: shift,
+                    size,
+                    dst,
+                });
+            }
+            FAdd | FSub | FMul | FDiv => {
+                let fop = match op {
+                    FAdd => FloatOp::Add,
+                    FSub => FloatOp::Sub,
+                    FMul => FloatOp::Mul,
+                    _ => FloatOp::Div,
+                };
+                let lhs = self.reg(lhs);
+                let rhs = self.reg(rhs);
+                self.copy(RegClass::Float, dst, lhs);
+                self.push(X86Inst::FAlu {
+                    op: fop,
+                    double: ty == Type::F64,
+                    dst,
+                    src: rhs,
+                });
+            }
+            FRem => {
+                let name = match ty {
+                    Type::F32 => "fmodf",
+                    _ => "fmod",
+                };
+                let sig = Signature::new(vec![AbiParam::new(ty), AbiParam::new(ty)], ty, false);
+                self.call_sym(name, &sig, &[lhs, rhs], Some(dst))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compare(&mut self, pred: CmpPred, lhs: Value, rhs: Value) -> Flags {
+        let ty = self.value_type(lhs);
+        if !pred.is_float() {
+            let lhs = self.reg(lhs);
+            let rhs = self.src(rhs);
+            self.push(X86Inst::Cmp {
+                size: size_of(ty),
+                lhs,
+                rhs,
+            });
+            return Flags::Cond(int_cond(pred));
+        }
+        let double = ty == Type::F64;
+        let a = self.reg(lhs);
+        let b = self.reg(rhs);
+        // ucomis 比较 lhs 和 rhs，只有 a / ae 在无序时为假
+        let (lhs, rhs, flags) = match pred {
+            CmpPred::FOeq => (a, b, Flags::FOeq),
+            CmpPred::FUne => (a, b, Flags::FUne),
+            CmpPred::FOgt => (a, b, Flags::Cond(Cond::A)),
+            CmpPred::FOge => (a, b, Flags::Cond(Cond::Ae)),
+            CmpPred::FOlt => (b, a, Flags::Cond(Cond::A)),
+            _ => (b, a, Flags::Cond(Cond::Ae)),
+        };
+        self.push(X86Inst::Ucomi { double, lhs, rhs });
+        flags
+    }
+
+    /// 根据标志位设置 `i1`
+    fn set_flags(&mut self, dst: Reg, flags: Flags) {
+        match flags {
+            Flags::Cond(cond) => self.push(X86Inst::Setcc { cond, dst }),
+            Flags::FOeq | Flags::FUne => {
+                let (first, second, op) = match flags {
+                    Flags::FOeq => (Cond::E, Cond::Np, AluOp::And),
+                    _ => (Cond::Ne, Cond::P, AluOp::Or),
+                };
+                let tmp = self.vreg(RegClass::Int);
+                self.push(X86Inst::Setcc { cond: first, dst });
+                self.push(X86Inst::Setcc {
+                    cond: second,
+                    dst: tmp,
+                });
+                self.push(X86Inst::Alu {
+                    op,
+                    size: Size::B,
+                    dst,
+                    src: Src::Reg(tmp),
+                });
+            }
+        }
+    }
+
+    fn cast(&mut self, op: CastOp, val: Value, to: Type, dst: Reg) {
+        use CastOp::*;
+        let from = self.value_type(val);
+        let src = self.reg(val);
+        let (from_size, to_size) = (size_of(from), size_of(to));
+        match op {
+            Trunc => {
+                self.mov(Size::Q, dst, src);
+                if to == Type::I1 {
+                    self.push(X86Inst::Alu {
+                        op: AluOp::And,
+                        size: Size::B,
+                        dst,
+                        src: Src::Imm(1),
+                    });
+                }
+            }
+            ZExt | PtrToInt | IntToPtr => match from_size < to_size {
+                true => self.push(X86Inst::Movx {
+                    signed: false,
+                    from: from_size,
+                    to: to_size,
+                    dst,
+                    src,
+                }),
+                false => self.mov(Size::Q, dst, src),
+            },
+            SExt if from == Type::I1 => {
+                self.push(X86Inst::Movx {
+                    signed: false,
+                    from: Size::B,
+                    to: to_size.max(Size::L),
+                    dst,
+                    src,
+                });
+                self.push(X86Inst::Unary {
+                    op: UnaryOp::Neg,
+                    size: to_size,
+                    dst,
+                });
+            }
+            SExt => self.push(X86Inst::Movx {
+                signed: true,
+                from: from_size,
+                to: to_size,
+                dst,
+                src,
+            }),
+            FpToSi => self.push(X86Inst::CvtFloatToInt {
+                double: from == Type::F64,
+                size: to_size.max(Size::L),
+                dst,
+                src,
+            }),
+            FpToUi if to != Type::I64 => self.push(X86Inst::CvtFloatToInt {
+                double: from == Type::F64,
+                size: Size::Q,
+                dst,
+                src,
+            }),
+            FpToUi => {
+                // 不小于 2^63 的值先减去 2^63 再转换，然后补上最高位
+                let double = from == Type::F64;
+                let limit = match double {
+                    true => Value::f64((1u64 << 63) as f64),
+                    false => Value::f32((1u64 << 63) as f32),
+                };
+                let limit = self.reg(limit);
+                self.push(X86Inst::CvtFloatToInt {
+                    double,
+                    size: Size::Q,
+                    dst,
+                    src,
+                });
+                let big = self.vreg(RegClass::Float);
+                self.copy(RegClass::Float, big, src);
+                self.push(X86Inst::FAlu {
+                    op: FloatOp::Sub,
+                    double,
+                    dst: big,
+                    src: limit,
+                });
+                let high = self.vreg(RegClass::Int);
+                self.push(X86Inst::CvtFloatToInt {
+                    double,
+                    size: Size::Q,
+                    dst: high,
+                    src: big,
+                });
+                let sign = self.reg(Value::int(Type::I64, i64::MIN));
+                self.push(X86Inst::Alu {
+                    op: AluOp::Xor,
+                    size: Size::Q,
+                    dst: high,
+                    src: Src::Reg(sign),
+                });
+                self.push(X86Inst::Ucomi {
+                    double,
+                    lhs: src,
+                    rhs: limit,
+                });
+                self.push(X86Inst::Cmov {
+                    cond: Cond::Ae,
+                    size: Size::Q,
+                    dst,
+                    src: high,
+                });
+            }
+            SiToFp => {
+                let size = from_size.max(Size::L);
+                let src = match from {
+                    Type::I1 => {
+                        let tmp = self.extend(src, Size::B, Size::L, false);
+                        self.push(X86Inst::Unary {
+                            op: UnaryOp::Neg,
+                            size: Size::L,
+                            dst: tmp,
+                        });
+                        tmp
+                    }
+                    _ => self.extend(src, from_size, size, true),
+                };
+                self.push(X86Inst::CvtIntToFloat {
+                    double: to == Type::F64,
+                    size,
+                    dst,
+                    src,
+                });
+            }
+            UiToFp if from != Type::I64 => {
+                let src = self.extend(src, from_size, Size::Q, false);
+                self.push(X86Inst::CvtIntToFloat {
+                    double: to == Type::F64,
+                    size: Size::Q,
+                    dst,
+                    src,
+                });
+            }
+            UiToFp => {
+                // 最高位为 1 时右移一位（保留最低位用于舍入）转换后乘 2
+                let double = to == Type::F64;
+                let big = self.mf.new_block();
+                let small = self.mf.new_block();
+                let join = self.mf.new_block();
+                self.push(X86Inst::Test {
+                    size: Size::Q,
+                    lhs: src,
+                    rhs: src,
+                });
+                self.push(X86Inst::Jcc {
+                    cond: Cond::S,
+                    target: big,
+                });
+                self.push(X86Inst::Jmp { target: small });
+
+                self.cur = small;
+                self.push(X86Inst::CvtIntToFloat {
+                    double,
+                    size: Size::Q,
+                    dst,
+                    src,
+                });
+                self.push(X86Inst::Jmp { target: join });
+
+                self.cur = big;
+                let half = self.vreg(RegClass::Int);
+                let low = self.vreg(RegClass::Int);
+                self.mov(Size::Q, half, src);
+                self.mov(Size::Q, low, src);
+                self.push(X86Inst::Mov {
+                    size: Size::L,
+                    dst: Reg::Phys(RCX),
+                    src: Src::Imm(1),
+                });
+                self.push(X86Inst::Shift {
+                    op: ShiftOp::Shr,
+                    size: Size::Q,
+                    dst: half,
+                });
+                self.push(X86Inst::Alu {
+                    op: AluOp::And,
+                    size: Size::Q,
+                    dst: low,
+                    src: Src::Imm(1),
+                });
+                self.push(X86Inst::Alu {
+                    op: AluOp::Or,
+                    size: Size::Q,
+                    dst: half,
+                    src: Src::Reg(low),
+                });
+                self.push(X86Inst::CvtIntToFloat {
+                    double,
+                    size: Size::Q,
+                    dst,
+                    src: half,
+                });
+                self.push(X86Inst::FAlu {
+                    op: FloatOp::Add,
+                    double,
+                    dst,
+                    src: dst,
+                });
+                self.push(X86Inst::Jmp { target: join });
+                self.cur = join;
+            }
+            FpExt | FpTrunc => self.push(X86Inst::CvtFloat {
+                to_double: to == Type::F64,
+                dst,
+                src,
+            }),
+            Bitcast => match (from.is_float(), to.is_float()) {
+                (false, true) => self.push(X86Inst::MovToXmm {
+                    size: size_of(from),
+                    dst,
+                    src,
+                }),
+                (true, false) => self.push(X86Inst::MovFromXmm {
+                    size: size_of(to),
+                    dst,
+                    src,
+                }),
+                _ => self.copy(class_of(to), dst, src),
+            },
+        }
+    }
+
+    /// 调用外部的库函数，位置无关代码中经过 PLT
+    fn call_sym(
+        &mut self,
+        name: &str,
+        sig: &Signature,
+        args: &[Value],
+        dst: Option<Reg>,
+    ) -> CodegenResult<()> {
+        let arg_regs: Vec<Reg> = args.iter().map(|x| self.reg(*x)).collect();
+        let types: Vec<Type> = args.iter().map(|x| self.value_type(*x)).collect();
+        let conv = classify(sig, &types);
+        let target = self.call_target(
+            name.to_string(),
+            self.module.reloc_model != RelocModel::Static,
+        );
+        self.call_conv(&conv, sig, target, &arg_regs, &types, dst)
+    }
+
+    fn call(
+        &mut self,
+        sig: &Signature,
+        callee: Value,
+        args: &[Value],
+        dst: Option<Reg>,
+    ) -> CodegenResult<()> {
+        // 先算出所有参数，之后才能设置参数寄存器
+        let arg_regs: Vec<Reg> = args.iter().map(|x| self.reg(*x)).collect();
+        let types: Vec<Type> = args.iter().map(|x| self.value_type(*x)).collect();
+        let target = match callee {
+            Value::Func(_) => self.call_target(self.sym(callee), self.via_got(callee)),
+            _ => CallTarget::Reg(self.reg(callee)),
+        };
+        let conv = classify(sig, &types);
+        self.call_conv(&conv, sig, target, &arg_regs, &types, dst)
+    }
+
+    fn call_conv(
+        &mut self,
+        conv: &CallConv,
+        sig: &Signature,
+        target: CallTarget,
+        args: &[Reg],
+        types: &[Type],
+        dst: Option<Reg>,
+    ) -> CodegenResult<()> {
+        self.mf.outgoing = self.mf.outgoing.max(conv.stack_size);
+        let rsp = Reg::Phys(RSP);
+
+        // 栈上的参数，聚合类型的复制可能用到 rep movsb，要在设置参数寄存器之前
+        for (i, loc) in conv.args.iter().enumerate() {
+            match loc {
+                ArgLoc::StackAgg(offset) => {
+                    let size = match sig.params[i].attr {
+                        ParamAttr::ByVal { size, .. } => size,
+                        _ => unreachable!(),
+                    };
+                    self.memcpy(
+                        Mem::reg(rsp, *offset as i64),
+                        Mem::reg(args[i], 0),
+                        size as u64,
+                    );
+                }
+                ArgLoc::Stack(offset) => {
+                    let dst = Mem::reg(rsp, *offset as i64);
+                    match types[i].is_float() {
+                        true => self.push(X86Inst::FStore {
+                            double: types[i] == Type::F64,
+                            dst,
+                            src: args[i],
+                        }),
+                        false => self.push(X86Inst::Store {
+                            size: Size::Q,
+                            dst,
+                            src: Src::Reg(args[i]),
+                        }),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 聚合类型的片段先读到虚拟寄存器
+        let mut moves = Vec::new();
+        for (i, loc) in conv.args.iter().enumerate() {
+            match loc {
+                ArgLoc::Reg(reg) => moves.push((*reg, args[i], class_of(types[i]))),
+                ArgLoc::Pieces(pieces) => {
+                    for piece in pieces {
+                        let value = self.load_piece(Mem::reg(args[i], 0), piece);
+                        moves.push((piece.reg, value, Self::piece_class(piece)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut uses = Vec::new();
+        for (reg, value, class) in moves {
+            self.copy(class, Reg::Phys(reg), value);
+            uses.push(reg);
+        }
+        if sig.variadic {
+            self.push(X86Inst::Mov {
+                size: Size::L,
+                dst: Reg::Phys(RAX),
+                src: Src::Imm(conv.fp_used as i64),
+            });
+            uses.push(RAX);
+        }
+
+        let defs = match &conv.ret {
+            RetLoc::None => vec![],
+            RetLoc::Reg(reg) => vec![*reg],
+            RetLoc::Pieces(pieces) => pieces.iter().map(|x| x.reg).collect(),
+            RetLoc::Memory => vec![RAX],
+        };
+        self.push(X86Inst::Call { target, uses, defs });
+
+        match &conv.ret {
+            RetLoc::Reg(reg) => {
+                if let Some(dst) = dst {
+                    let class = class_of(sig.ret);
+                    self.copy(class, dst, Reg::Phys(*reg));
+                }
+            }
+            RetLoc::Pieces(pieces) => {
+                let values: Vec<Reg> = pieces
+                    .iter()
+                    .map(|piece| {
+                        let class = Self::piece_class(piece);
+                        let value = self.vreg(class);
+                        self.copy(class, value, Reg::Phys(piece.reg));
+                        value
+                    })
+                    .collect();
+                for (piece, value) in pieces.iter().zip(values) {
+                    self.store_piece(Mem::reg(args[0], 0), piece, value);
+                }
+            }
+            RetLoc::None | RetLoc::Memory => {}
+        }
+        Ok(())
+    }
+
+    fn ret(&mut self, val: Option<Value>) {
+        let uses = match self.ret.clone() {
+            RetLoc::None => vec![],
+            RetLoc::Reg(reg) => {
+                if let Some(val) = val {
+                    let class = class_of(self.value_type(val));
+                    let val = self.reg(val);
+                    self.copy(class, Reg::Phys(reg), val);
+                }
+                vec![reg]
+            }
+            RetLoc::Pieces(pieces) => {
+                let sret = self.sret.unwrap();
+                let values: Vec<_> = pieces
+                    .iter()
+                    .map(|piece| self.load_piece(Mem::reg(sret, 0), piece))
+                    .collect();
+                for (piece, value) in pieces.iter().zip(values) {
+                    self.copy(Self::piece_class(piece), Reg::Phys(piece.reg), value);
+                }
+                pieces.iter().map(|x| x.reg).collect()
+            }
+            RetLoc::Memory => {
+                let sret = self.sret.unwrap();
+                self.mov(Size::Q, Reg::Phys(RAX), sret);
+                vec![RAX]
+            }
+        };
+        self.push(X86Inst::Ret { uses });
+    }
+
+    /// 初始化 `va_list`：`gp_offset` `fp_offset` `overflow_arg_area` `reg_save_area`
+    fn va_start(&mut self, list: Value) -> CodegenResult<()> {
+        let Some(va) = &self.va else {
+            return self.unsupported("va_start in a non-variadic function");
+        };
+        let (save_area, gp, fp, stack) = (va.save_area, va.gp_used, va.fp_used, va.stack_used);
+        let list = self.mem(list, 0);
+        self.push(X86Inst::Store {
+            size: Size::L,
+            dst: list.clone(),
+            src: Src::Imm(gp as i64 * 8),
+        });
+        self.push(X86Inst::Store {
+            size: Size::L,
+            dst: list.offset(4),
+            src: Src::Imm(48 + fp as i64 * 16),
+        });
+        let overflow = self.vreg(RegClass::Int);
+        self.push(X86Inst::Lea {
+            dst: overflow,
+            mem: Mem {
+                base: Base::Incoming,
+                disp: stack as i64,
+            },
+        });
+        self.push(X86Inst::Store {
+            size: Size::Q,
+            dst: list.offset(8),
+            src: Src::Reg(overflow),
+        });
+        let area = self.vreg(RegClass::Int);
+        self.push(X86Inst::Lea {
+            dst: area,
+            mem: Mem::slot(save_area, 0),
+        });
+        self.push(X86Inst::Store {
+            size: Size::Q,
+            dst: list.offset(16),
+            src: Src::Reg(area),
+        });
+        Ok(())
+    }
+
+    /// 寄存器保存区域还有剩余时从中读取，否则从栈上读取
+    fn va_arg(&mut self, list: Value, ty: Type, dst: Reg) {
+        let list = self.mem(list, 0);
+        let (field, limit, step) = match ty.is_float() {
+            true => (4, REG_SAVE_AREA as i64, 16),
+            false => (0, 48, 8),
+        };
+        let offset = self.vreg(RegClass::Int);
+        self.push(X86Inst::Mov {
+            size: Size::L,
+            dst: offset,
+            src: Src::Mem(list.offset(field)),
+        });
+        let reg_block = self.mf.new_block();
+        let stack_block = self.mf.new_block();
+        let join = self.mf.new_block();
+        self.push(X86Inst::Cmp {
+            size: Size::L,
+            lhs: offset,
+            rhs: Src::Imm(limit - step),
+        });
+        self.push(X86Inst::Jcc {
+            cond: Cond::A,
+            target: stack_block,
+        });
+        self.push(X86Inst::Jmp { target: reg_block });
+
+        let load = |isel: &mut Self, addr: Reg| {
+            let src = Src::Mem(Mem::reg(addr, 0));
+            match ty.is_float() {
+                true => isel.push(X86Inst::FMov {
+                    double: ty == Type::F64,
+                    dst,
+                    src,
+                }),
+                false => isel.push(X86Inst::Mov {
+                    size: size_of(ty),
+                    dst,
+                    src,
+                }),
+            }
+        };
+
+        self.cur = reg_block;
+        let addr = self.vreg(RegClass::Int);
+        self.push(X86Inst::Mov {
+            size: Size::Q,
+            dst: addr,
+            src: Src::Mem(list.offset(16)),
+        });
+        let offset64 = self.extend(offset, Size::L, Size::Q, false);
+        self.push(X86Inst::Alu {
+            op: AluOp::Add,
+            size: Size::Q,
+            dst: addr,
+            src: Src::Reg(offset64),
+        });
+        load(self, addr);
+        self.push(X86Inst::Alu {
+            op: AluOp::Add,
+            size: Size::L,
+            dst: offset,
+            src: Src::Imm(step),
+        });
+        self.push(X86Inst::Store {
+            size: Size::L,
+            dst: list.offset(field),
+            src: Src::Reg(offset),
+        });
+        self.push(X86Inst::Jmp { target: join });
+
+        self.cur = stack_block;
+        let addr = self.vreg(RegClass::Int);
+        self.push(X86Inst::Mov {
+            size: Size::Q,
+            dst: addr,
+            src: Src::Mem(list.offset(8)),
+        });
+        load(self, addr);
+        self.push(X86Inst::Alu {
+            op: AluOp::Add,
+            size: Size::Q,
+            dst: addr,
+            src: Src::Imm(8),
+        });
+        self.push(X86Inst::Store {
+            size: Size::Q,
+            dst: list.offset(8),
+            src: Src::Reg(addr),
+        });
+        self.push(X86Inst::Jmp { target: join });
+        self.cur = join;
+    }
+}
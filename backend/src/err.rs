@@ -0,0 +1,4 @@
+pub mod codegen_error;
+pub mod interp_error;
+pub mod ir_error;
+pub mod link_error;
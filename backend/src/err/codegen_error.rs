@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+pub type CodegenResult<T> = Result<T, CodegenError>;
+
+/// 代码生成的错误
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error("unknown target '{0}'")]
+    UnknownTarget(String),
+    #[error("in function '{func}': {msg} is not supported by the {arch} backend")]
+    Unsupported {
+        arch: &'static str,
+        func: String,
+        msg: String,
+    },
+    #[error("external variable '{name}' is not supported by the {arch} backend")]
+    ExternalVariable { arch: &'static str, name: String },
+    #[error("thread-local variable '{name}' is not supported by the {arch} backend")]
+    ThreadLocal { arch: &'static str, name: String },
+    #[error("target '{0}' cannot emit object files directly, use -S and an assembler")]
+    NoObjectWriter(String),
+}
@@ -0,0 +1,50 @@
+use thiserror::Error;
+
+pub type InterpResult<T> = Result<T, InterpError>;
+
+/// IR 解释执行时的错误，大部分是检测到的未定义行为
+#[derive(Debug, Error)]
+pub enum InterpError {
+    #[error("null pointer dereference")]
+    NullDeref,
+    #[error("out-of-bounds access of {size} bytes at offset {offset} of a {alloc_size}-byte object")]
+    OutOfBounds {
+        offset: i64,
+        size: u64,
+        alloc_size: u64,
+    },
+    #[error("access to a dangling pointer")]
+    UseAfterFree,
+    #[error("access through an invalid pointer")]
+    InvalidPointer,
+    #[error("write to read-only memory")]
+    ReadOnly,
+    #[error("use of an uninitialized value in {0}")]
+    Uninit(&'static str),
+    #[error("signed integer overflow in {0}")]
+    SignedOverflow(&'static str),
+    #[error("division by zero")]
+    DivByZero,
+    #[error("shift amount {0} is out of range")]
+    ShiftOutOfRange(u64),
+    #[error("float value is out of the range of the integer type")]
+    FloatToIntOverflow,
+    #[error("invalid free of a pointer not returned by malloc")]
+    InvalidFree,
+    #[error("call through a pointer that is not a function")]
+    NotAFunction,
+    #[error("call to undefined function '{0}'")]
+    UndefinedFunction(String),
+    #[error("{0}")]
+    BadCall(String),
+    #[error("reached unreachable code in '{0}'")]
+    Unreachable(String),
+    #[error("stack overflow (more than {0} nested calls)")]
+    StackOverflow(usize),
+    #[error("{0}-byte pointers are not supported, the interpreter needs 8-byte pointers")]
+    PointerWidth(u32),
+    #[error("program has no 'main' function")]
+    NoMain,
+    #[error("program aborted")]
+    Abort,
+}
@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+pub type IrResult<T> = Result<T, IrError>;
+
+/// IR 文本解析、校验的错误，`Pass` 是优化 pass 之后的校验失败
+#[derive(Debug, Error)]
+pub enum IrError {
+    #[error("{line}:{col}: {msg}")]
+    Parse { line: usize, col: usize, msg: String },
+    #[error("in function '@{func}': {msg}")]
+    Verify { func: String, msg: String },
+    #[error("in global '@{global}': {msg}")]
+    VerifyGlobal { global: String, msg: String },
+    #[error("after pass '{pass}': {source}")]
+    Pass {
+        pass: &'static str,
+        source: Box<IrError>,
+    },
+}
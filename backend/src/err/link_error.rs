@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+pub type LinkResult<T> = Result<T, LinkError>;
+
+/// 读取目标文件和链接时的错误
+#[derive(Debug, Error)]
+pub enum LinkError {
+    #[error("{0}: malformed object file: {1}")]
+    Malformed(String, &'static str),
+    #[error("{0}: unsupported object file: {1}")]
+    Unsupported(String, String),
+    #[error("multiple definition of '{name}': first defined in {first}, again in {second}")]
+    Duplicate {
+        name: String,
+        first: String,
+        second: String,
+    },
+    #[error("{object}: undefined reference to '{name}'")]
+    Undefined { name: String, object: String },
+    #[error("{object}: relocation against '{name}' is out of range")]
+    Overflow { name: String, object: String },
+    #[error(
+        "{object}: relocation against '{name}' cannot be used when making a shared object; recompile with -fPIC"
+    )]
+    NotPic { name: String, object: String },
+    #[error("{object}: dynamic relocation against '{name}' in read-only section")]
+    TextRel { name: String, object: String },
+    #[error("entry symbol '{0}' is not defined")]
+    NoEntry(String),
+}
@@ -0,0 +1,10 @@
+/// IR 解释器，在没有原生后端时直接执行 C 程序
+/// # Contents
+/// - `memory`: 按字节寻址的内存，每个对象独立分配，检测越界和悬空指针
+/// - `machine`: 指令的执行和函数调用
+/// - `libc`: 内置的一小部分 C 标准库（printf、malloc / free、memcpy、strlen、exit、pthread 等）
+pub mod libc;
+pub mod machine;
+pub mod memory;
+
+pub use machine::{Interpreter, RtValue};
@@ -0,0 +1,434 @@
+use crate::err::interp_error::InterpError;
+use crate::interp::machine::{ExecResult, Interpreter, RtValue, Stop};
+use crate::interp::memory::AllocKind;
+use crate::ir::Type;
+use crate::ir::value::sign_extend;
+
+/// `pthread_join` 等待不存在的线程时返回的错误码
+const ESRCH: u64 = 3;
+
+/// 调用 C 标准库函数，只实现运行示例程序需要的一小部分
+pub(crate) fn call(
+    interp: &mut Interpreter,
+    name: &str,
+    args: &[RtValue],
+    varargs: &[(Type, RtValue)],
+) -> ExecResult<RtValue> {
+    let arg = |i: usize| -> ExecResult<u64> {
+        match args.get(i) {
+            Some(x) => Ok(x.expect("argument of a library function")?),
+            None => Err(InterpError::BadCall(format!("too few arguments to '{}'", name)).into()),
+        }
+    };
+    let value = match name {
+        "printf" => {
+            let format = interp.mem.read_cstr(arg(0)?)?;
+            let text = format_printf(interp, &format, varargs)?;
+            interp.output.extend_from_slice(&text);
+            text.len() as u64
+        }
+        "puts" => {
+            let text = interp.mem.read_cstr(arg(0)?)?;
+            interp.output.extend_from_slice(&text);
+            interp.output.push(b'\n');
+            text.len() as u64 + 1
+        }
+        "putchar" => {
+            let c = arg(0)?;
+            interp.output.push(c as u8);
+            c & 0xff
+        }
+        "malloc" => interp.mem.alloc(arg(0)?, AllocKind::Heap),
+        "calloc" => {
+            let size = arg(0)?.wrapping_mul(arg(1)?);
+            let ptr = interp.mem.alloc(size, AllocKind::Heap);
+            interp.mem.write(ptr, &vec![0; size as usize], true)?;
+            ptr
+        }
+        "realloc" => {
+            let (old, size) = (arg(0)?, arg(1)?);
+            let ptr = interp.mem.alloc(size, AllocKind::Heap);
+            if old != 0 {
+                let old_size = match interp.mem.allocation(old) {
+                    Some(x) if x.kind == AllocKind::Heap => x.bytes.len() as u64,
+                    _ => return Err(InterpError::InvalidFree.into()),
+                };
+                interp.mem.copy(ptr, old, old_size.min(size))?;
+                interp.mem.free(old, AllocKind::Heap)?;
+            }
+            ptr
+        }
+        "free" => {
+            let ptr = arg(0)?;
+            if ptr != 0 {
+                interp.mem.free(ptr, AllocKind::Heap)?;
+            }
+            0
+        }
+        "memcpy" | "memmove" => {
+            let dst = arg(0)?;
+            interp.mem.copy(dst, arg(1)?, arg(2)?)?;
+            dst
+        }
+        "memset" => {
+            let (dst, size) = (arg(0)?, arg(2)?);
+            if size != 0 {
+                interp
+                    .mem
+                    .write(dst, &vec![arg(1)? as u8; size as usize], true)?;
+            }
+            dst
+        }
+        "strlen" => interp.mem.read_cstr(arg(0)?)?.len() as u64,
+        "strcmp" => {
+            let a = interp.mem.read_cstr(arg(0)?)?;
+            let b = interp.mem.read_cstr(arg(1)?)?;
+            match a.cmp(&b) {
+                std::cmp::Ordering::Less => -1i64 as u64,
+                std::cmp::Ordering::Equal => 0,
+                std::cmp::Ordering::Greater => 1,
+            }
+        }
+        "strcpy" => {
+            let dst = arg(0)?;
+            let mut text = interp.mem.read_cstr(arg(1)?)?;
+            text.push(0);
+            interp.mem.write(dst, &text, true)?;
+            dst
+        }
+        // 线程在 `pthread_create` 中同步地执行完，互斥锁总是可以直接获得
+        "pthread_create" => {
+            interp.run_thread(arg(0)?, arg(2)?, arg(3)?)?;
+            0
+        }
+        "pthread_join" => {
+            let Some(value) = interp.join_thread(arg(0)?) else {
+                return Ok(RtValue::new(ESRCH));
+            };
+            let retval = arg(1)?;
+            if retval != 0 {
+                interp.mem.write(retval, &value.to_le_bytes(), true)?;
+            }
+            0
+        }
+        "pthread_self" => interp.current_thread(),
+        "pthread_equal" => (arg(0)? == arg(1)?) as u64,
+        "pthread_mutex_init"
+        | "pthread_mutex_destroy"
+        | "pthread_mutex_lock"
+        | "pthread_mutex_unlock" => 0,
+        "exit" => return Err(Stop::Exit(arg(0)? as i32)),
+        "abort" => return Err(InterpError::Abort.into()),
+        _ => return Err(InterpError::UndefinedFunction(name.to_string()).into()),
+    };
+    Ok(RtValue::new(value))
+}
+
+///
+/// printf 的一个转换说明 `%[flags][width][.precision][length]conversion`
+///
+/// # Members
+/// - `left` `plus` `space` `alt` `zero`: 标志 `-` `+` ` ` `#` `0`
+/// - `width` `precision`: 宽度和精度
+/// - `length`: 长度修饰对应的位宽，为 0 时没有修饰
+///
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alt: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    length: u32,
+}
+
+/// 依次取出变参，`ptr_bytes` 是模块的指针宽度
+struct Args<'a> {
+    args: &'a [(Type, RtValue)],
+    next: usize,
+    ptr_bytes: u32,
+}
+
+impl Args<'_> {
+    fn next(&mut self) -> ExecResult<(Type, u64)> {
+        let Some((ty, value)) = self.args.get(self.next) else {
+            return Err(InterpError::BadCall("too few arguments to 'printf'".to_string()).into());
+        };
+        self.next += 1;
+        Ok((*ty, value.expect("argument of printf")?))
+    }
+
+    /// 整数参数按自身类型符号扩展后截断到 `bits` 位
+    fn int(&mut self, bits: u32) -> ExecResult<i64> {
+        let (ty, value) = self.next()?;
+        let value = sign_extend(value, ty.bits(self.ptr_bytes));
+        Ok(sign_extend(value as u64, bits))
+    }
+
+    fn float(&mut self) -> ExecResult<f64> {
+        let (ty, value) = self.next()?;
+        Ok(match ty {
+            Type::F32 => f32::from_bits(value as u32) as f64,
+            _ => f64::from_bits(value),
+        })
+    }
+}
+
+/// 按宽度填充，`prefix` 是符号或 `0x`，补 0 时 0 在前缀之后
+fn pad(spec: &Spec, prefix: &str, body: &str, zero: bool) -> String {
+    let len = prefix.len() + body.len();
+    if len >= spec.width {
+        return format!("{}{}", prefix, body);
+    }
+    let fill = spec.width - len;
+    if spec.left {
+        format!("{}{}{}", prefix, body, " ".repeat(fill))
+    } else if zero {
+        format!("{}{}{}", prefix, "0".repeat(fill), body)
+    } else {
+        format!("{}{}{}", " ".repeat(fill), prefix, body)
+    }
+}
+
+fn sign(spec: &Spec, negative: bool) -> &'static str {
+    if negative {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    }
+}
+
+/// 按精度补足最少的数字位数，精度为 0 且值为 0 时没有数字
+fn digits(spec: &Spec, text: String) -> String {
+    match spec.precision {
+        Some(0) if text == "0" => String::new(),
+        Some(p) if text.len() < p => format!("{}{}", "0".repeat(p - text.len()), text),
+        _ => text,
+    }
+}
+
+/// `%e`，指数至少两位
+fn format_exp(value: f64, precision: usize, upper: bool) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let (mantissa, exp) = text.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let sign = if exp < 0 { '-' } else { '+' };
+    let text = format!("{}e{}{:02}", mantissa, sign, exp.abs());
+    match upper {
+        true => text.to_uppercase(),
+        false => text,
+    }
+}
+
+/// `%g`，根据指数选择 `%f` 或 `%e`，没有 `#` 时去掉末尾的 0
+fn format_general(value: f64, precision: usize, alt: bool, upper: bool) -> String {
+    let precision = precision.max(1);
+    let exp = match value {
+        0.0 => 0,
+        _ => {
+            let text = format!("{:.*e}", precision - 1, value);
+            text.split_once('e').unwrap().1.parse::<i32>().unwrap()
+        }
+    };
+    let mut text = if exp < -4 || exp >= precision as i32 {
+        format_exp(value, precision - 1, upper)
+    } else {
+        format!("{:.*}", (precision as i32 - 1 - exp) as usize, value)
+    };
+    if !alt && text.contains('.') {
+        let (mantissa, exp) = match text.find(['e', 'E']) {
+            Some(i) => text.split_at(i),
+            None => (text.as_str(), ""),
+        };
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        text = format!("{}{}", mantissa, exp);
+    }
+    text
+}
+
+fn format_float(spec: &Spec, conv: u8, value: f64) -> String {
+    let upper = conv.is_ascii_uppercase();
+    let negative = value.is_sign_negative() && !value.is_nan();
+    let value = value.abs();
+    let body = if !value.is_finite() {
+        let text = if value.is_nan() { "nan" } else { "inf" };
+        let text = match upper {
+            true => text.to_uppercase(),
+            false => text.to_string(),
+        };
+        return pad(spec, sign(spec, negative), &text, false);
+    } else {
+        let precision = spec.precision.unwrap_or(6);
+        match conv.to_ascii_lowercase() {
+            b'f' => {
+                let text = format!("{:.*}", precision, value);
+                match spec.alt && precision == 0 {
+                    true => format!("{}.", text),
+                    false => text,
+                }
+            }
+            b'e' => format_exp(value, precision, upper),
+            _ => format_general(value, precision, spec.alt, upper),
+        }
+    };
+    pad(spec, sign(spec, negative), &body, spec.zero && !spec.left)
+}
+
+/// 格式化 printf 的输出
+fn format_printf(
+    interp: &Interpreter,
+    format: &[u8],
+    varargs: &[(Type, RtValue)],
+) -> ExecResult<Vec<u8>> {
+    let mut args = Args {
+        args: varargs,
+        next: 0,
+        ptr_bytes: interp.module().ptr_bytes,
+    };
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < format.len() {
+        if format[i] != b'%' {
+            out.push(format[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        let mut spec = Spec::default();
+        while let Some(c) = format.get(i) {
+            match c {
+                b'-' => spec.left = true,
+                b'+' => spec.plus = true,
+                b' ' => spec.space = true,
+                b'#' => spec.alt = true,
+                b'0' => spec.zero = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        if format.get(i) == Some(&b'*') {
+            let width = args.int(32)?;
+            spec.left |= width < 0;
+            spec.width = width.unsigned_abs() as usize;
+            i += 1;
+        } else {
+            while let Some(c) = format.get(i).filter(|x| x.is_ascii_digit()) {
+                spec.width = spec.width * 10 + (c - b'0') as usize;
+                i += 1;
+            }
+        }
+        if format.get(i) == Some(&b'.') {
+            i += 1;
+            let mut precision = 0;
+            if format.get(i) == Some(&b'*') {
+                // 负的精度视为没有精度
+                let value = args.int(32)?;
+                i += 1;
+                spec.precision = (value >= 0).then_some(value as usize);
+            } else {
+                while let Some(c) = format.get(i).filter(|x| x.is_ascii_digit()) {
+                    precision = precision * 10 + (c - b'0') as usize;
+                    i += 1;
+                }
+                spec.precision = Some(precision);
+            }
+        }
+        let rest = &format[i.min(format.len())..];
+        let (length, len) = match rest {
+            [b'h', b'h', ..] => (8, 2),
+            [b'h', ..] => (16, 1),
+            [b'l', b'l', ..] => (64, 2),
+            [b'l' | b'z' | b'j' | b't' | b'L', ..] => (64, 1),
+            _ => (0, 0),
+        };
+        spec.length = length;
+        i += len;
+        let Some(conv) = format.get(i).copied() else {
+            break;
+        };
+        i += 1;
+
+        let bits = match spec.length {
+            0 => 32,
+            x => x,
+        };
+        let text = match conv {
+            b'd' | b'i' => {
+                let value = args.int(bits)?;
+                let body = digits(&spec, value.unsigned_abs().to_string());
+                let zero = spec.zero && !spec.left && spec.precision.is_none();
+                pad(&spec, sign(&spec, value < 0), &body, zero)
+            }
+            b'u' | b'x' | b'X' | b'o' => {
+                let value = args.int(bits)? as u64 & (u64::MAX >> (64 - bits));
+                let body = match conv {
+                    b'u' => value.to_string(),
+                    b'x' => format!("{:x}", value),
+                    b'X' => format!("{:X}", value),
+                    _ => format!("{:o}", value),
+                };
+                let mut body = digits(&spec, body);
+                let mut prefix = "";
+                if spec.alt && value != 0 {
+                    match conv {
+                        b'x' => prefix = "0x",
+                        b'X' => prefix = "0X",
+                        b'o' if !body.starts_with('0') => body.insert(0, '0'),
+                        _ => {}
+                    }
+                }
+                let zero = spec.zero && !spec.left && spec.precision.is_none();
+                pad(&spec, prefix, &body, zero)
+            }
+            b'c' => {
+                let c = args.int(32)? as u8;
+                let text = pad(&spec, "", "\0", false);
+                out.extend(text.bytes().map(|x| if x == 0 { c } else { x }));
+                continue;
+            }
+            b's' => {
+                let (_, ptr) = args.next()?;
+                let mut bytes = interp.mem.read_cstr(ptr)?;
+                if let Some(p) = spec.precision {
+                    bytes.truncate(p);
+                }
+                let fill = spec.width.saturating_sub(bytes.len());
+                if !spec.left {
+                    out.extend(std::iter::repeat_n(b' ', fill));
+                }
+                out.extend_from_slice(&bytes);
+                if spec.left {
+                    out.extend(std::iter::repeat_n(b' ', fill));
+                }
+                continue;
+            }
+            b'p' => {
+                let (_, ptr) = args.next()?;
+                match ptr {
+                    0 => pad(&spec, "", "(nil)", false),
+                    _ => pad(&spec, "0x", &format!("{:x}", ptr), false),
+                }
+            }
+            b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                let value = args.float()?;
+                format_float(&spec, conv, value)
+            }
+            b'%' => "%".to_string(),
+            c => {
+                return Err(InterpError::BadCall(format!(
+                    "unsupported printf conversion '%{}'",
+                    c as char
+                ))
+                .into());
+            }
+        };
+        out.extend_from_slice(text.as_bytes());
+    }
+    Ok(out)
+}
@@ -0,0 +1,798 @@
+use crate::err::interp_error::{InterpError, InterpResult};
+use crate::interp::libc;
+use crate::interp::memory::{AllocKind, Memory};
+use crate::ir::value::{sign_extend, truncate};
+use crate::ir::{
+    BinaryOp, BlockId, CastOp, CmpPred, FuncId, Function, GlobalId, InitItem, InstData, InstKind,
+    InstId, Module, ParamAttr, Type, Value,
+};
+use rustc_hash::FxHashMap;
+use slotmap::SecondaryMap;
+
+/// 调用嵌套的最大深度，超过时报告栈溢出
+const MAX_DEPTH: usize = 1000;
+
+///
+/// 运行时的值
+///
+/// # Members
+/// - `bits`: 整数按类型截断后零扩展，浮点数是位模式（`f32` 在低 32 位），指针是地址
+/// - `defined`: 已经初始化的位，类型宽度以上的位总是置 1；
+///   位域只写入存储单元的一部分，按位记录才能读回已经写入的位域
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtValue {
+    pub bits: u64,
+    pub defined: u64,
+}
+
+impl RtValue {
+    pub fn new(bits: u64) -> Self {
+        Self {
+            bits,
+            defined: u64::MAX,
+        }
+    }
+
+    pub fn undef() -> Self {
+        Self {
+            bits: 0,
+            defined: 0,
+        }
+    }
+
+    /// 部分位已经初始化的 `width` 位的值
+    fn partial(bits: u64, defined: u64, width: u32) -> Self {
+        Self {
+            bits: truncate(bits, width),
+            defined: defined | !truncate(u64::MAX, width),
+        }
+    }
+
+    /// 所有位都已经初始化
+    pub fn is_defined(self) -> bool {
+        self.defined == u64::MAX
+    }
+
+    pub fn from_f32(value: f32) -> Self {
+        Self::new(value.to_bits() as u64)
+    }
+
+    pub fn from_f64(value: f64) -> Self {
+        Self::new(value.to_bits())
+    }
+
+    pub fn as_f32(self) -> f32 {
+        f32::from_bits(self.bits as u32)
+    }
+
+    pub fn as_f64(self) -> f64 {
+        f64::from_bits(self.bits)
+    }
+
+    /// 要求值已经初始化，`what` 用于错误信息
+    pub fn expect(self, what: &'static str) -> InterpResult<u64> {
+        match self.is_defined() {
+            true => Ok(self.bits),
+            false => Err(InterpError::Uninit(what)),
+        }
+    }
+
+    /// 两个操作数的所有位都初始化时结果才初始化
+    fn with(self, other: RtValue, bits: u64) -> RtValue {
+        match self.is_defined() && other.is_defined() {
+            true => RtValue::new(bits),
+            false => RtValue { bits, defined: 0 },
+        }
+    }
+}
+
+/// 执行中止的原因，`exit` 不是错误
+pub(crate) enum Stop {
+    Exit(i32),
+    Error(InterpError),
+}
+
+impl From<InterpError> for Stop {
+    fn from(value: InterpError) -> Self {
+        Stop::Error(value)
+    }
+}
+
+pub(crate) type ExecResult<T> = Result<T, Stop>;
+
+///
+/// 一次函数调用的状态
+///
+/// # Members
+/// - `values`: 指令的结果
+/// - `args`: 固定参数
+/// - `varargs`: `...` 部分的参数和类型
+/// - `allocas`: 栈上分配的对象，返回时释放
+///
+struct Frame {
+    values: SecondaryMap<InstId, RtValue>,
+    args: Vec<RtValue>,
+    varargs: Vec<(Type, RtValue)>,
+    allocas: Vec<u64>,
+}
+
+///
+/// IR 解释器，直接执行模块中的函数，用于在没有原生后端时运行 C 程序
+///
+/// 内存按字节寻址，每个对象（全局变量、`alloca`、`malloc`）独立分配，
+/// 能检测越界、空指针、悬空指针、未初始化的值、`nsw` 溢出等未定义行为
+///
+/// # Members
+/// - `module`: 执行的模块
+/// - `mem`: 内存
+/// - `globals` `funcs`: 全局变量和函数的地址
+/// - `output`: 程序的标准输出
+/// - `depth`: 当前调用深度
+/// - `thread` `next_thread`: 当前线程和下一个新线程的编号，主线程为 1
+/// - `finished`: 已经结束、还没有被 `pthread_join` 的线程和它的返回值
+///
+pub struct Interpreter<'m> {
+    module: &'m Module,
+    pub(crate) mem: Memory,
+    globals: SecondaryMap<GlobalId, u64>,
+    funcs: SecondaryMap<FuncId, u64>,
+    pub output: Vec<u8>,
+    depth: usize,
+    thread: u64,
+    next_thread: u64,
+    finished: FxHashMap<u64, u64>,
+}
+
+/// 从内存中的小端字节得到值
+fn from_bytes(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, x| (acc << 8) | *x as u64)
+}
+
+fn float_binary(op: BinaryOp, a: f64, b: f64) -> f64 {
+    match op {
+        BinaryOp::FAdd => a + b,
+        BinaryOp::FSub => a - b,
+        BinaryOp::FMul => a * b,
+        BinaryOp::FDiv => a / b,
+        _ => a % b,
+    }
+}
+
+/// 浮点数转换为 `bits` 位整数，超出范围是未定义行为
+fn float_to_int(value: f64, bits: u32, signed: bool) -> InterpResult<u64> {
+    let value = value.trunc();
+    let (min, max) = match signed {
+        true => (-(2f64.powi(bits as i32 - 1)), 2f64.powi(bits as i32 - 1)),
+        false => (0.0, 2f64.powi(bits as i32)),
+    };
+    if value.is_nan() || value < min || value >= max {
+        return Err(InterpError::FloatToIntOverflow);
+    }
+    let bits_value = match signed {
+        true => value as i64 as u64,
+        false => value as u64,
+    };
+    Ok(truncate(bits_value, bits))
+}
+
+impl<'m> Interpreter<'m> {
+    /// 为函数和全局变量分配地址并写入全局变量的初始值
+    pub fn new(module: &'m Module) -> InterpResult<Self> {
+        // 地址的高 32 位是对象编号，需要 64 位指针
+        if module.ptr_bytes != 8 {
+            return Err(InterpError::PointerWidth(module.ptr_bytes));
+        }
+        let mut interp = Self {
+            module,
+            mem: Memory::new(),
+            globals: SecondaryMap::new(),
+            funcs: SecondaryMap::new(),
+            output: Vec::new(),
+            depth: 0,
+            thread: 1,
+            next_thread: 2,
+            finished: FxHashMap::default(),
+        };
+        for id in module.func_ids() {
+            let ptr = interp.mem.alloc(0, AllocKind::Func(id));
+            interp.funcs.insert(id, ptr);
+        }
+        for id in module.global_ids() {
+            let ptr = interp.mem.alloc(module.globals[id].size, AllocKind::Global);
+            interp.globals.insert(id, ptr);
+        }
+        for id in module.global_ids() {
+            interp.init_global(id)?;
+        }
+        Ok(interp)
+    }
+
+    /// 外部声明的全局变量保持未初始化
+    fn init_global(&mut self, id: GlobalId) -> InterpResult<()> {
+        let global = &self.module.globals[id];
+        let Some(items) = &global.init else {
+            return Ok(());
+        };
+        let base = self.globals[id];
+        let mut offset = 0;
+        for item in items {
+            let ptr = base.wrapping_add(offset);
+            match item {
+                InitItem::Bytes(bytes) => {
+                    self.mem.write(ptr, bytes, true)?;
+                    offset += bytes.len() as u64;
+                }
+                InitItem::Zero(n) => {
+                    self.mem.write(ptr, &vec![0; *n as usize], true)?;
+                    offset += n;
+                }
+                InitItem::Addr { target, addend } => {
+                    let addr = self.constant(*target).bits.wrapping_add(*addend as u64);
+                    self.mem.write(ptr, &addr.to_le_bytes(), true)?;
+                    offset += self.module.ptr_bytes as u64;
+                }
+            }
+        }
+        self.mem.allocation_mut(base).unwrap().readonly = global.constant;
+        Ok(())
+    }
+
+    pub fn module(&self) -> &'m Module {
+        self.module
+    }
+
+    /// 全局变量的地址
+    pub fn global_addr(&self, id: GlobalId) -> u64 {
+        self.globals[id]
+    }
+
+    /// 执行 `main`，返回退出码，`args` 是 `argv`（包括程序名）
+    pub fn run_main(&mut self, args: &[&str]) -> InterpResult<i32> {
+        let id = self.module.func_by_name("main").ok_or(InterpError::NoMain)?;
+        let func = &self.module.funcs[id];
+        let mut main_args = Vec::new();
+        if func.sig.params.len() >= 2 {
+            let ptr_bytes = self.module.ptr_bytes as u64;
+            let argv = self
+                .mem
+                .alloc((args.len() as u64 + 1) * ptr_bytes, AllocKind::Heap);
+            for (i, arg) in args.iter().enumerate() {
+                let mut bytes = arg.as_bytes().to_vec();
+                bytes.push(0);
+                let ptr = self.mem.alloc(bytes.len() as u64, AllocKind::Heap);
+                self.mem.write(ptr, &bytes, true)?;
+                let slot = argv + i as u64 * ptr_bytes;
+                self.mem.write(slot, &ptr.to_le_bytes(), true)?;
+            }
+            let slot = argv + args.len() as u64 * ptr_bytes;
+            self.mem.write(slot, &0u64.to_le_bytes(), true)?;
+            main_args.push(RtValue::new(args.len() as u64));
+            main_args.push(RtValue::new(argv));
+        }
+        main_args.resize(func.sig.params.len(), RtValue::new(0));
+
+        match self.exec(id, main_args, Vec::new()) {
+            Ok(_) if !func.sig.ret.is_int() => Ok(0),
+            Ok(value) => {
+                let bits = value.expect("return value of main")?;
+                Ok(sign_extend(bits, func.sig.ret.bits(self.module.ptr_bytes)) as i32)
+            }
+            Err(Stop::Exit(code)) => Ok(code),
+            Err(Stop::Error(e)) => Err(e),
+        }
+    }
+
+    /// 调用函数，`args` 是固定参数，`exit` 也作为错误返回
+    pub fn call(&mut self, id: FuncId, args: &[RtValue]) -> InterpResult<RtValue> {
+        match self.exec(id, args.to_vec(), Vec::new()) {
+            Ok(value) => Ok(value),
+            Err(Stop::Exit(code)) => Err(InterpError::BadCall(format!(
+                "program exited with code {}",
+                code
+            ))),
+            Err(Stop::Error(e)) => Err(e),
+        }
+    }
+
+    ///
+    /// 创建线程执行 `start(arg)`，线程编号写入 `handle` 指向的 `pthread_t`
+    ///
+    /// 线程在创建时同步地执行完，不会和其他线程交错。执行期间线程局部变量换成新分配、
+    /// 重新初始化的副本，结束后释放副本并换回原来的，返回值留给 `join_thread`
+    ///
+    pub(crate) fn run_thread(&mut self, handle: u64, start: u64, arg: u64) -> ExecResult<()> {
+        let id = self.mem.func_of(start).ok_or(InterpError::NotAFunction)?;
+        let thread = self.next_thread;
+        self.next_thread += 1;
+        self.mem.write(handle, &thread.to_le_bytes(), true)?;
+
+        let saved: Vec<(GlobalId, u64)> = self
+            .module
+            .global_ids()
+            .into_iter()
+            .filter(|x| self.module.globals[*x].thread_local)
+            .map(|x| (x, self.globals[x]))
+            .collect();
+        for (x, _) in saved.iter() {
+            let size = self.module.globals[*x].size;
+            let ptr = self.mem.alloc(size, AllocKind::ThreadLocal);
+            self.globals.insert(*x, ptr);
+        }
+        for (x, _) in saved.iter() {
+            self.init_global(*x)?;
+        }
+
+        // 线程中的错误和 `exit` 结束整个程序，不需要恢复
+        let parent = std::mem::replace(&mut self.thread, thread);
+        let value = self.exec(id, vec![RtValue::new(arg)], Vec::new())?;
+        self.thread = parent;
+        for (x, ptr) in saved {
+            self.mem.free(self.globals[x], AllocKind::ThreadLocal)?;
+            self.globals.insert(x, ptr);
+        }
+        let value = value.expect("return value of a thread")?;
+        self.finished.insert(thread, value);
+        Ok(())
+    }
+
+    /// 等待线程结束，返回它的返回值，线程不存在或已经被等待过时为 None
+    pub(crate) fn join_thread(&mut self, thread: u64) -> Option<u64> {
+        self.finished.remove(&thread)
+    }
+
+    /// 当前线程的编号
+    pub(crate) fn current_thread(&self) -> u64 {
+        self.thread
+    }
+
+    /// 没有函数体的函数由 libc 实现
+    fn exec(
+        &mut self,
+        id: FuncId,
+        mut args: Vec<RtValue>,
+        varargs: Vec<(Type, RtValue)>,
+    ) -> ExecResult<RtValue> {
+        let func = &self.module.funcs[id];
+        if func.is_declaration() {
+            return libc::call(self, &func.name, &args, &varargs);
+        }
+        if self.depth >= MAX_DEPTH {
+            return Err(InterpError::StackOverflow(MAX_DEPTH).into());
+        }
+
+        // byval 参数由被调用者拥有一份拷贝
+        let mut allocas = Vec::new();
+        for (param, arg) in func.sig.params.iter().zip(args.iter_mut()) {
+            if let ParamAttr::ByVal { size, .. } = param.attr {
+                let copy = self.mem.alloc(size as u64, AllocKind::Stack);
+                self.mem.copy(copy, arg.expect("byval argument")?, size as u64)?;
+                allocas.push(copy);
+                *arg = RtValue::new(copy);
+            }
+        }
+        let mut frame = Frame {
+            values: SecondaryMap::new(),
+            args,
+            varargs,
+            allocas,
+        };
+        self.depth += 1;
+        let result = self.run(func, &mut frame);
+        self.depth -= 1;
+        for ptr in frame.allocas {
+            let _ = self.mem.free(ptr, AllocKind::Stack);
+        }
+        result
+    }
+
+    fn run(&mut self, func: &'m Function, frame: &mut Frame) -> ExecResult<RtValue> {
+        let mut block = func.entry();
+        let mut prev: Option<BlockId> = None;
+        loop {
+            let insts = &func.blocks[block].insts;
+            // phi 在块的入口同时求值
+            let phis: Vec<_> = insts
+                .iter()
+                .take_while(|x| func.insts[**x].kind.is_phi())
+                .collect();
+            let mut values = Vec::with_capacity(phis.len());
+            for inst in phis.iter() {
+                let InstKind::Phi { incomings } = &func.insts[**inst].kind else {
+                    unreachable!()
+                };
+                let value = incomings
+                    .iter()
+                    .find(|(pred, _)| Some(*pred) == prev)
+                    .map(|(_, value)| self.value(frame, *value))
+                    .unwrap_or(RtValue::undef());
+                values.push(value);
+            }
+            for (inst, value) in phis.iter().zip(values) {
+                frame.values.insert(**inst, value);
+            }
+
+            let mut next = None;
+            for inst in insts[phis.len()..].iter() {
+                let data = &func.insts[*inst];
+                match &data.kind {
+                    InstKind::Br { dest } => next = Some(*dest),
+                    InstKind::CondBr {
+                        cond,
+                        then_dest,
+                        else_dest,
+                    } => {
+                        let cond = self.value(frame, *cond).expect("branch condition")?;
+                        next = Some(if cond != 0 { *then_dest } else { *else_dest });
+                    }
+                    InstKind::Switch {
+                        val,
+                        default,
+                        cases,
+                    } => {
+                        let val = self.value(frame, *val).expect("switch")?;
+                        let dest = cases.iter().find(|(x, _)| *x == val).map(|(_, x)| *x);
+                        next = Some(dest.unwrap_or(*default));
+                    }
+                    InstKind::Ret { val } => {
+                        return Ok(match val {
+                            Some(val) => self.value(frame, *val),
+                            None => RtValue::undef(),
+                        });
+                    }
+                    InstKind::Unreachable => {
+                        return Err(InterpError::Unreachable(func.name.clone()).into());
+                    }
+                    _ => {
+                        let value = self.eval(func, frame, data)?;
+                        frame.values.insert(*inst, value);
+                    }
+                }
+            }
+            prev = Some(block);
+            block = next.expect("block without terminator");
+        }
+    }
+
+    /// 常量和全局符号的值
+    fn constant(&self, value: Value) -> RtValue {
+        match value {
+            Value::Int { bits, .. } | Value::Float { bits, .. } => RtValue::new(bits),
+            Value::Global(x) => RtValue::new(self.globals[x]),
+            Value::Func(x) => RtValue::new(self.funcs[x]),
+            Value::Undef(_) | Value::Inst(_) | Value::Arg(_) => RtValue::undef(),
+        }
+    }
+
+    fn value(&self, frame: &Frame, value: Value) -> RtValue {
+        match value {
+            Value::Inst(x) => frame.values.get(x).copied().unwrap_or(RtValue::undef()),
+            Value::Arg(x) => frame.args[x as usize],
+            _ => self.constant(value),
+        }
+    }
+
+    fn eval(&mut self, func: &'m Function, frame: &mut Frame, data: &InstData) -> ExecResult<RtValue> {
+        let ty = data.ty;
+        let value = match &data.kind {
+            InstKind::Binary { op, lhs, rhs, nsw } => {
+                let lhs = self.value(frame, *lhs);
+                let rhs = self.value(frame, *rhs);
+                self.binary(*op, ty, lhs, rhs, *nsw)?
+            }
+            InstKind::FNeg { val } => {
+                let val = self.value(frame, *val);
+                let bits = match ty {
+                    Type::F32 => val.bits ^ (1 << 31),
+                    _ => val.bits ^ (1 << 63),
+                };
+                RtValue { bits, ..val }
+            }
+            InstKind::Cmp { pred, lhs, rhs } => {
+                let from = func.value_type(*lhs);
+                let lhs = self.value(frame, *lhs);
+                let rhs = self.value(frame, *rhs);
+                lhs.with(rhs, self.compare(*pred, from, lhs, rhs) as u64)
+            }
+            InstKind::Cast { op, val } => {
+                let from = func.value_type(*val);
+                let val = self.value(frame, *val);
+                self.cast_value(*op, from, ty, val)?
+            }
+            InstKind::Select {
+                cond,
+                then_val,
+                else_val,
+            } => match self.value(frame, *cond).expect("select")? {
+                0 => self.value(frame, *else_val),
+                _ => self.value(frame, *then_val),
+            },
+            InstKind::Alloca { size, .. } => {
+                let ptr = self.mem.alloc(*size, AllocKind::Stack);
+                frame.allocas.push(ptr);
+                RtValue::new(ptr)
+            }
+            InstKind::Load { ptr, .. } => {
+                let ptr = self.value(frame, *ptr).expect("address")?;
+                self.load(ptr, ty)?
+            }
+            InstKind::Store { ptr, val, .. } => {
+                let ptr = self.value(frame, *ptr).expect("address")?;
+                let val_ty = func.value_type(*val);
+                let val = self.value(frame, *val);
+                self.store(ptr, val_ty, val)?;
+                RtValue::undef()
+            }
+            InstKind::Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } => {
+                let index_ty = func.value_type(*index);
+                let base = self.value(frame, *base);
+                let index = self.value(frame, *index);
+                let index = sign_extend(index.bits, index_ty.bits(self.module.ptr_bytes));
+                let bits = base
+                    .bits
+                    .wrapping_add((index as u64).wrapping_mul(*scale))
+                    .wrapping_add(*offset as u64);
+                base.with(RtValue::new(0), bits)
+            }
+            InstKind::MemCopy { dst, src, size, .. } => {
+                let dst = self.value(frame, *dst).expect("address")?;
+                let src = self.value(frame, *src).expect("address")?;
+                self.mem.copy(dst, src, *size)?;
+                RtValue::undef()
+            }
+            InstKind::Call { sig, callee, args } => {
+                let id = match *callee {
+                    Value::Func(id) => id,
+                    callee => {
+                        let ptr = self.value(frame, callee).expect("function pointer")?;
+                        self.mem.func_of(ptr).ok_or(InterpError::NotAFunction)?
+                    }
+                };
+                let fixed = sig.params.len().min(args.len());
+                let values: Vec<_> = args[..fixed].iter().map(|x| self.value(frame, *x)).collect();
+                let varargs: Vec<_> = args[fixed..]
+                    .iter()
+                    .map(|x| (func.value_type(*x), self.value(frame, *x)))
+                    .collect();
+                let value = self.exec(id, values, varargs)?;
+                match ty {
+                    Type::Void => RtValue::undef(),
+                    _ => RtValue {
+                        bits: truncate(value.bits, ty.bits(self.module.ptr_bytes)),
+                        ..value
+                    },
+                }
+            }
+            InstKind::VaStart { list } => {
+                let list = self.value(frame, *list).expect("va_list")?;
+                // 每个变参占 8 字节，va_list 的前 8 字节保存下一个参数的地址
+                let area = self
+                    .mem
+                    .alloc(frame.varargs.len() as u64 * 8, AllocKind::Stack);
+                frame.allocas.push(area);
+                for (i, (arg_ty, arg)) in frame.varargs.iter().enumerate() {
+                    let bits = match arg_ty {
+                        Type::F32 => (arg.as_f32() as f64).to_bits(),
+                        _ => arg.bits,
+                    };
+                    self.mem
+                        .write(area + i as u64 * 8, &bits.to_le_bytes(), arg.is_defined())?;
+                }
+                self.mem.write(list, &area.to_le_bytes(), true)?;
+                RtValue::undef()
+            }
+            InstKind::VaArg { list } => {
+                let list = self.value(frame, *list).expect("va_list")?;
+                let cursor = self.load(list, Type::Ptr)?.expect("va_list")?;
+                let value = self.load(cursor, Type::I64)?;
+                self.mem
+                    .write(list, &cursor.wrapping_add(8).to_le_bytes(), true)?;
+                let bits = match ty {
+                    Type::F32 => (f64::from_bits(value.bits) as f32).to_bits() as u64,
+                    _ => truncate(value.bits, ty.bits(self.module.ptr_bytes)),
+                };
+                RtValue { bits, ..value }
+            }
+            InstKind::VaEnd { .. } => RtValue::undef(),
+            InstKind::VaCopy { dst, src } => {
+                let dst = self.value(frame, *dst).expect("va_list")?;
+                let src = self.value(frame, *src).expect("va_list")?;
+                self.mem.copy(dst, src, 8)?;
+                RtValue::undef()
+            }
+            kind => unreachable!("unexpected instruction {:?}", kind),
+        };
+        Ok(value)
+    }
+
+    fn binary(
+        &self,
+        op: BinaryOp,
+        ty: Type,
+        lhs: RtValue,
+        rhs: RtValue,
+        nsw: bool,
+    ) -> InterpResult<RtValue> {
+        use BinaryOp::*;
+        if op.is_float() {
+            let bits = match ty {
+                Type::F32 => {
+                    let value = float_binary(op, lhs.as_f32() as f64, rhs.as_f32() as f64);
+                    (value as f32).to_bits() as u64
+                }
+                _ => float_binary(op, lhs.as_f64(), rhs.as_f64()).to_bits(),
+            };
+            return Ok(lhs.with(rhs, bits));
+        }
+
+        let width = ty.bits(self.module.ptr_bytes);
+        // 除数和移位量未初始化时无法判断是否合法
+        if matches!(op, SDiv | UDiv | SRem | URem | Shl | LShr | AShr) {
+            rhs.expect(op.name())?;
+        }
+        let (ua, ub) = (lhs.bits, rhs.bits);
+        let (sa, sb) = (sign_extend(ua, width), sign_extend(ub, width));
+        let overflow = |x: i128| {
+            let min = -(1i128 << (width - 1));
+            let max = (1i128 << (width - 1)) - 1;
+            match nsw && lhs.is_defined() && rhs.is_defined() && (x < min || x > max) {
+                true => Err(InterpError::SignedOverflow(op.name())),
+                false => Ok(x as u64),
+            }
+        };
+        let bits = match op {
+            Add => overflow(sa as i128 + sb as i128)?,
+            Sub => overflow(sa as i128 - sb as i128)?,
+            Mul => overflow(sa as i128 * sb as i128)?,
+            SDiv | SRem => {
+                if sb == 0 {
+                    return Err(InterpError::DivByZero);
+                }
+                if lhs.is_defined() && width > 1 && sa == i64::MIN >> (64 - width) && sb == -1 {
+                    return Err(InterpError::SignedOverflow(op.name()));
+                }
+                match op {
+                    SDiv => sa.wrapping_div(sb) as u64,
+                    _ => sa.wrapping_rem(sb) as u64,
+                }
+            }
+            UDiv | URem => {
+                if ub == 0 {
+                    return Err(InterpError::DivByZero);
+                }
+                match op {
+                    UDiv => ua / ub,
+                    _ => ua % ub,
+                }
+            }
+            // 位运算按位传播初始化状态，确定的 0 与任何值都是 0，确定的 1 或任何值都是 1
+            And | Or | Xor => {
+                let (da, db) = (lhs.defined, rhs.defined);
+                let both = da & db;
+                let value = match op {
+                    And => RtValue::partial(ua & ub, both | (da & !ua) | (db & !ub), width),
+                    Or => RtValue::partial(ua | ub, both | (da & ua) | (db & ub), width),
+                    _ => RtValue::partial(ua ^ ub, both, width),
+                };
+                return Ok(value);
+            }
+            // 移入的位是确定的，移位量已经检查过初始化
+            Shl | LShr | AShr => {
+                if ub >= width as u64 {
+                    return Err(InterpError::ShiftOutOfRange(ub));
+                }
+                let da = lhs.defined;
+                let (bits, defined) = match op {
+                    Shl => (ua << ub, (da << ub) | truncate(u64::MAX, ub as u32)),
+                    LShr => (ua >> ub, (da >> ub) | !(u64::MAX >> ub)),
+                    _ => ((sa >> ub) as u64, (sign_extend(da, width) >> ub) as u64),
+                };
+                return Ok(RtValue::partial(bits, defined, width));
+            }
+            _ => unreachable!(),
+        };
+        Ok(lhs.with(rhs, truncate(bits, width)))
+    }
+
+    fn compare(&self, pred: CmpPred, ty: Type, lhs: RtValue, rhs: RtValue) -> bool {
+        use CmpPred::*;
+        if pred.is_float() {
+            let (a, b) = match ty {
+                Type::F32 => (lhs.as_f32() as f64, rhs.as_f32() as f64),
+                _ => (lhs.as_f64(), rhs.as_f64()),
+            };
+            return match pred {
+                FOeq => a == b,
+                FUne => a != b,
+                FOlt => a < b,
+                FOle => a <= b,
+                FOgt => a > b,
+                _ => a >= b,
+            };
+        }
+        let width = ty.bits(self.module.ptr_bytes);
+        let (ua, ub) = (lhs.bits, rhs.bits);
+        let (sa, sb) = (sign_extend(ua, width), sign_extend(ub, width));
+        match pred {
+            Eq => ua == ub,
+            Ne => ua != ub,
+            Slt => sa < sb,
+            Sle => sa <= sb,
+            Sgt => sa > sb,
+            Sge => sa >= sb,
+            Ult => ua < ub,
+            Ule => ua <= ub,
+            Ugt => ua > ub,
+            _ => ua >= ub,
+        }
+    }
+
+    /// 整数的截断和扩展按位保留初始化状态，其他转换要求所有位都已经初始化
+    fn cast_value(&self, op: CastOp, from: Type, to: Type, val: RtValue) -> InterpResult<RtValue> {
+        let from_bits = from.bits(self.module.ptr_bytes);
+        let to_bits = to.bits(self.module.ptr_bytes);
+        let defined = match op {
+            CastOp::Trunc | CastOp::ZExt => val.defined,
+            CastOp::SExt => sign_extend(val.defined, from_bits) as u64,
+            _ if val.is_defined() => u64::MAX,
+            _ => return Ok(RtValue::undef()),
+        };
+        let bits = self.cast(op, from, to, val.bits)?;
+        Ok(RtValue::partial(bits, defined, to_bits))
+    }
+
+    fn cast(&self, op: CastOp, from: Type, to: Type, bits: u64) -> InterpResult<u64> {
+        use CastOp::*;
+        let from_bits = from.bits(self.module.ptr_bytes);
+        let to_bits = to.bits(self.module.ptr_bytes);
+        let as_f64 = |bits: u64| match from {
+            Type::F32 => f32::from_bits(bits as u32) as f64,
+            _ => f64::from_bits(bits),
+        };
+        let to_float = |value: f64| match to {
+            Type::F32 => (value as f32).to_bits() as u64,
+            _ => value.to_bits(),
+        };
+        let bits = match op {
+            Trunc | ZExt | PtrToInt | IntToPtr => truncate(bits, to_bits),
+            SExt => truncate(sign_extend(bits, from_bits) as u64, to_bits),
+            FpToSi => float_to_int(as_f64(bits), to_bits, true)?,
+            FpToUi => float_to_int(as_f64(bits), to_bits, false)?,
+            SiToFp => match to {
+                Type::F32 => (sign_extend(bits, from_bits) as f32).to_bits() as u64,
+                _ => (sign_extend(bits, from_bits) as f64).to_bits(),
+            },
+            UiToFp => match to {
+                Type::F32 => (bits as f32).to_bits() as u64,
+                _ => (bits as f64).to_bits(),
+            },
+            FpExt | FpTrunc => to_float(as_f64(bits)),
+            Bitcast => bits,
+        };
+        Ok(bits)
+    }
+
+    /// 从内存读取 `ty` 类型的值，未初始化的位在结果中也未初始化
+    pub(crate) fn load(&self, ptr: u64, ty: Type) -> InterpResult<RtValue> {
+        let (bytes, init) = self
+            .mem
+            .read_bits(ptr, ty.bytes(self.module.ptr_bytes) as u64)?;
+        let width = ty.bits(self.module.ptr_bytes);
+        Ok(RtValue::partial(from_bytes(bytes), from_bytes(init), width))
+    }
+
+    /// 存储未初始化的位会把内存中对应的位标记为未初始化
+    pub(crate) fn store(&mut self, ptr: u64, ty: Type, value: RtValue) -> InterpResult<()> {
+        let size = ty.bytes(self.module.ptr_bytes) as usize;
+        let (bits, defined) = (value.bits.to_le_bytes(), value.defined.to_le_bytes());
+        self.mem.write_bits(ptr, &bits[..size], &defined[..size])
+    }
+}
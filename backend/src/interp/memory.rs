@@ -0,0 +1,211 @@
+use crate::err::interp_error::{InterpError, InterpResult};
+use crate::ir::FuncId;
+
+/// 指针的高 32 位是对象编号（从 1 开始），低 32 位是对象内的偏移，空指针为 0
+const OFFSET_BITS: u32 = 32;
+
+///
+/// 对象的种类
+/// - `Stack`: `alloca`，函数返回时释放
+/// - `Heap`: `malloc`，`free` 释放
+/// - `Global`: 全局变量，`constant` 的全局变量只读
+/// - `ThreadLocal`: 线程局部变量在新线程中的副本，线程结束时释放
+/// - `Func`: 函数，只用于得到函数指针，不能读写
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocKind {
+    Stack,
+    Heap,
+    Global,
+    ThreadLocal,
+    Func(FuncId),
+}
+
+///
+/// 一个对象
+///
+/// # Members
+/// - `bytes`: 内容
+/// - `init`: 每个字节中已经初始化的位，位域只写入存储单元的一部分
+/// - `live`: 释放后为 false，保留对象以检测悬空指针
+/// - `readonly`: 只读
+///
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    pub kind: AllocKind,
+    pub bytes: Vec<u8>,
+    pub init: Vec<u8>,
+    pub live: bool,
+    pub readonly: bool,
+}
+
+/// 按字节寻址的内存，每个对象独立，越界访问都能检测到
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    allocs: Vec<Allocation>,
+}
+
+pub fn make_ptr(id: usize, offset: u64) -> u64 {
+    ((id as u64) << OFFSET_BITS).wrapping_add(offset)
+}
+
+/// 指针拆分为对象下标和偏移，偏移按有符号解释以便报告负的越界
+fn split_ptr(ptr: u64) -> (usize, i64) {
+    let id = (ptr >> OFFSET_BITS) as usize;
+    let offset = ptr as u32 as i32 as i64;
+    // 负偏移会借位到对象编号
+    match offset < 0 {
+        true => (id + 1, offset),
+        false => (id, offset),
+    }
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 分配 `size` 字节，内容未初始化
+    pub fn alloc(&mut self, size: u64, kind: AllocKind) -> u64 {
+        self.allocs.push(Allocation {
+            kind,
+            bytes: vec![0; size as usize],
+            init: vec![0; size as usize],
+            live: true,
+            readonly: false,
+        });
+        make_ptr(self.allocs.len(), 0)
+    }
+
+    pub fn allocation(&self, ptr: u64) -> Option<&Allocation> {
+        let (id, _) = split_ptr(ptr);
+        self.allocs.get(id.checked_sub(1)?)
+    }
+
+    pub fn allocation_mut(&mut self, ptr: u64) -> Option<&mut Allocation> {
+        let (id, _) = split_ptr(ptr);
+        self.allocs.get_mut(id.checked_sub(1)?)
+    }
+
+    /// 释放对象，`kind` 必须匹配并且指针指向对象开头
+    pub fn free(&mut self, ptr: u64, kind: AllocKind) -> InterpResult<()> {
+        let (_, offset) = split_ptr(ptr);
+        let alloc = self.allocation_mut(ptr).ok_or(InterpError::InvalidFree)?;
+        if alloc.kind != kind || offset != 0 {
+            return Err(InterpError::InvalidFree);
+        }
+        if !alloc.live {
+            return Err(InterpError::UseAfterFree);
+        }
+        alloc.live = false;
+        Ok(())
+    }
+
+    /// 检查访问 `[ptr, ptr + size)` 是否合法，返回对象和偏移
+    fn check(&self, ptr: u64, size: u64) -> InterpResult<(usize, usize)> {
+        if ptr == 0 {
+            return Err(InterpError::NullDeref);
+        }
+        let (id, offset) = split_ptr(ptr);
+        let alloc = match id.checked_sub(1).and_then(|x| self.allocs.get(x)) {
+            Some(x) => x,
+            None if id == 0 => return Err(InterpError::NullDeref),
+            None => return Err(InterpError::InvalidPointer),
+        };
+        if !alloc.live {
+            return Err(InterpError::UseAfterFree);
+        }
+        if matches!(alloc.kind, AllocKind::Func(_)) {
+            return Err(InterpError::InvalidPointer);
+        }
+        let alloc_size = alloc.bytes.len() as u64;
+        if offset < 0 || offset as u64 + size > alloc_size {
+            return Err(InterpError::OutOfBounds {
+                offset,
+                size,
+                alloc_size,
+            });
+        }
+        Ok((id - 1, offset as usize))
+    }
+
+    /// 读取 `size` 字节，同时返回是否全部已经初始化
+    pub fn read(&self, ptr: u64, size: u64) -> InterpResult<(&[u8], bool)> {
+        let (bytes, init) = self.read_bits(ptr, size)?;
+        Ok((bytes, init.iter().all(|x| *x == u8::MAX)))
+    }
+
+    /// 读取 `size` 字节，同时返回每个字节中已经初始化的位
+    pub fn read_bits(&self, ptr: u64, size: u64) -> InterpResult<(&[u8], &[u8])> {
+        let (id, offset) = self.check(ptr, size)?;
+        let alloc = &self.allocs[id];
+        let range = offset..offset + size as usize;
+        Ok((&alloc.bytes[range.clone()], &alloc.init[range]))
+    }
+
+    pub fn write(&mut self, ptr: u64, data: &[u8], init: bool) -> InterpResult<()> {
+        let init = match init {
+            true => u8::MAX,
+            false => 0,
+        };
+        self.write_bits(ptr, data, &vec![init; data.len()])
+    }
+
+    /// 写入 `data`，`init` 是每个字节中已经初始化的位
+    pub fn write_bits(&mut self, ptr: u64, data: &[u8], init: &[u8]) -> InterpResult<()> {
+        let (id, offset) = self.check(ptr, data.len() as u64)?;
+        let alloc = &mut self.allocs[id];
+        if alloc.readonly {
+            return Err(InterpError::ReadOnly);
+        }
+        let range = offset..offset + data.len();
+        alloc.bytes[range.clone()].copy_from_slice(data);
+        alloc.init[range].copy_from_slice(init);
+        Ok(())
+    }
+
+    /// 复制内存，初始化状态一起复制，允许重叠
+    pub fn copy(&mut self, dst: u64, src: u64, size: u64) -> InterpResult<()> {
+        if size == 0 {
+            return Ok(());
+        }
+        let (src_id, src_offset) = self.check(src, size)?;
+        let range = src_offset..src_offset + size as usize;
+        let bytes = self.allocs[src_id].bytes[range.clone()].to_vec();
+        let init = self.allocs[src_id].init[range].to_vec();
+
+        let (dst_id, dst_offset) = self.check(dst, size)?;
+        let alloc = &mut self.allocs[dst_id];
+        if alloc.readonly {
+            return Err(InterpError::ReadOnly);
+        }
+        let range = dst_offset..dst_offset + size as usize;
+        alloc.bytes[range.clone()].copy_from_slice(&bytes);
+        alloc.init[range].copy_from_slice(&init);
+        Ok(())
+    }
+
+    /// 读取以 0 结尾的字符串，不包含结尾的 0
+    pub fn read_cstr(&self, ptr: u64) -> InterpResult<Vec<u8>> {
+        let mut out = Vec::new();
+        loop {
+            let (bytes, init) = self.read(ptr.wrapping_add(out.len() as u64), 1)?;
+            if !init {
+                return Err(InterpError::Uninit("string"));
+            }
+            match bytes[0] {
+                0 => return Ok(out),
+                x => out.push(x),
+            }
+        }
+    }
+
+    /// 函数指针对应的函数
+    pub fn func_of(&self, ptr: u64) -> Option<FuncId> {
+        let (_, offset) = split_ptr(ptr);
+        match self.allocation(ptr)?.kind {
+            AllocKind::Func(x) if offset == 0 => Some(x),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+/// SSA IR
+/// # Contents
+/// - `types`: 标量类型、函数签名
+/// - `value`: 指令操作数，指令 / 基本块 / 全局变量 / 函数的 key
+/// - `inst`: 指令
+/// - `function`: 函数，基本块和指令都存放在函数中
+/// - `module`: 模块，包含全局变量和函数
+/// - `builder`: 指令构建器
+/// - `debug`: 调试信息：源码位置、类型、变量，由代码生成输出为 DWARF
+/// - `cfg`: 控制流图分析：逆后序、支配树和支配边界
+/// - `loops`: 自然循环和循环嵌套，preheader 的插入
+/// - `printer` `parser`: 文本格式的输出和解析，用于调试和 IR 文件测试
+/// - `verifier`: 结构校验
+pub mod builder;
+pub mod cfg;
+pub mod debug;
+pub mod function;
+pub mod inst;
+pub mod loops;
+pub mod module;
+pub mod parser;
+pub mod printer;
+pub mod types;
+pub mod value;
+pub mod verifier;
+
+pub use function::{Function, InlineAttr};
+pub use inst::{BinaryOp, CastOp, CmpPred, InstData, InstKind};
+pub use module::{Global, InitItem, Linkage, Module, RelocModel, Visibility};
+pub use types::{AbiParam, AggShape, ParamAttr, Signature, Type};
+pub use value::{BlockId, FuncId, GlobalId, InstId, Value};
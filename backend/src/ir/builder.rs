@@ -0,0 +1,223 @@
+use crate::ir::debug::DebugLoc;
+use crate::ir::function::Function;
+use crate::ir::inst::{BinaryOp, CastOp, CmpPred, InstData, InstKind};
+use crate::ir::types::{Signature, Type};
+use crate::ir::value::{BlockId, Value};
+
+///
+/// 指令构建器，在当前基本块末尾追加指令
+///
+/// # Members
+/// - `func`: 正在构建的函数
+/// - `block`: 当前基本块
+/// - `loc`: 追加的指令的源码位置
+///
+pub struct FuncBuilder<'a> {
+    pub func: &'a mut Function,
+    block: Option<BlockId>,
+    loc: Option<DebugLoc>,
+}
+
+impl<'a> FuncBuilder<'a> {
+    pub fn new(func: &'a mut Function) -> Self {
+        let block = func.layout.last().cloned();
+        Self {
+            func,
+            block,
+            loc: None,
+        }
+    }
+
+    pub fn create_block(&mut self) -> BlockId {
+        self.func.add_block()
+    }
+
+    pub fn switch_to(&mut self, block: BlockId) {
+        self.block = Some(block);
+    }
+
+    pub fn set_loc(&mut self, loc: Option<DebugLoc>) {
+        self.loc = loc;
+    }
+
+    pub fn current_block(&self) -> BlockId {
+        self.block.expect("builder has no current block")
+    }
+
+    /// 当前块是否已经有终结指令，有的话后续的指令不可达
+    pub fn is_terminated(&self) -> bool {
+        self.func.terminator(self.current_block()).is_some()
+    }
+
+    pub fn value_type(&self, value: Value) -> Type {
+        self.func.value_type(value)
+    }
+
+    pub fn ins(&mut self, kind: InstKind, ty: Type) -> Value {
+        let block = self.current_block();
+        let inst = self.func.append_inst(block, InstData { kind, ty });
+        if let Some(loc) = self.loc {
+            self.func.locs.insert(inst, loc);
+        }
+        Value::Inst(inst)
+    }
+
+    pub fn binary(&mut self, op: BinaryOp, lhs: Value, rhs: Value) -> Value {
+        let ty = self.value_type(lhs);
+        let nsw = false;
+        self.ins(InstKind::Binary { op, lhs, rhs, nsw }, ty)
+    }
+
+    /// 有符号溢出是未定义行为的 `add` `sub` `mul`
+    pub fn binary_nsw(&mut self, op: BinaryOp, lhs: Value, rhs: Value) -> Value {
+        debug_assert!(op.can_be_nsw());
+        let ty = self.value_type(lhs);
+        let nsw = true;
+        self.ins(InstKind::Binary { op, lhs, rhs, nsw }, ty)
+    }
+
+    pub fn fneg(&mut self, val: Value) -> Value {
+        let ty = self.value_type(val);
+        self.ins(InstKind::FNeg { val }, ty)
+    }
+
+    pub fn cmp(&mut self, pred: CmpPred, lhs: Value, rhs: Value) -> Value {
+        self.ins(InstKind::Cmp { pred, lhs, rhs }, Type::I1)
+    }
+
+    pub fn cast(&mut self, op: CastOp, val: Value, ty: Type) -> Value {
+        self.ins(InstKind::Cast { op, val }, ty)
+    }
+
+    pub fn select(&mut self, cond: Value, then_val: Value, else_val: Value) -> Value {
+        let ty = self.value_type(then_val);
+        let kind = InstKind::Select {
+            cond,
+            then_val,
+            else_val,
+        };
+        self.ins(kind, ty)
+    }
+
+    pub fn alloca(&mut self, size: u64, align: u32) -> Value {
+        self.ins(InstKind::Alloca { size, align }, Type::Ptr)
+    }
+
+    /// 在入口块开头分配栈空间，这样 alloca 不会在循环中重复执行，mem2reg 也只处理入口块的 alloca
+    pub fn entry_alloca(&mut self, size: u64, align: u32) -> Value {
+        let entry = self.func.entry();
+        let pos = self.func.blocks[entry]
+            .insts
+            .iter()
+            .take_while(|x| matches!(self.func.insts[**x].kind, InstKind::Alloca { .. }))
+            .count();
+        let data = InstData {
+            kind: InstKind::Alloca { size, align },
+            ty: Type::Ptr,
+        };
+        Value::Inst(self.func.insert_inst(entry, pos, data))
+    }
+
+    pub fn load(&mut self, ty: Type, ptr: Value) -> Value {
+        let kind = InstKind::Load {
+            ptr,
+            volatile: false,
+            tbaa: 0,
+        };
+        self.ins(kind, ty)
+    }
+
+    pub fn store(&mut self, ptr: Value, val: Value) {
+        let kind = InstKind::Store {
+            ptr,
+            val,
+            volatile: false,
+            tbaa: 0,
+        };
+        self.ins(kind, Type::Void);
+    }
+
+    /// `base + index * scale + offset`
+    pub fn gep(&mut self, base: Value, index: Value, scale: u64, offset: i64) -> Value {
+        let kind = InstKind::Gep {
+            base,
+            index,
+            scale,
+            offset,
+        };
+        self.ins(kind, Type::Ptr)
+    }
+
+    /// `base + offset`，偏移为 0 时直接返回 `base`
+    pub fn offset(&mut self, base: Value, offset: i64) -> Value {
+        if offset == 0 {
+            return base;
+        }
+        self.gep(base, Value::int(Type::I64, 0), 1, offset)
+    }
+
+    pub fn memcpy(&mut self, dst: Value, src: Value, size: u64, align: u32) {
+        let kind = InstKind::MemCopy {
+            dst,
+            src,
+            size,
+            align,
+        };
+        self.ins(kind, Type::Void);
+    }
+
+    pub fn call(&mut self, sig: Signature, callee: Value, args: Vec<Value>) -> Value {
+        let ty = sig.ret;
+        self.ins(InstKind::Call { sig, callee, args }, ty)
+    }
+
+    pub fn phi(&mut self, ty: Type, incomings: Vec<(BlockId, Value)>) -> Value {
+        self.ins(InstKind::Phi { incomings }, ty)
+    }
+
+    pub fn va_start(&mut self, list: Value) {
+        self.ins(InstKind::VaStart { list }, Type::Void);
+    }
+
+    pub fn va_arg(&mut self, list: Value, ty: Type) -> Value {
+        self.ins(InstKind::VaArg { list }, ty)
+    }
+
+    pub fn va_end(&mut self, list: Value) {
+        self.ins(InstKind::VaEnd { list }, Type::Void);
+    }
+
+    pub fn va_copy(&mut self, dst: Value, src: Value) {
+        self.ins(InstKind::VaCopy { dst, src }, Type::Void);
+    }
+
+    pub fn br(&mut self, dest: BlockId) {
+        self.ins(InstKind::Br { dest }, Type::Void);
+    }
+
+    pub fn cond_br(&mut self, cond: Value, then_dest: BlockId, else_dest: BlockId) {
+        let kind = InstKind::CondBr {
+            cond,
+            then_dest,
+            else_dest,
+        };
+        self.ins(kind, Type::Void);
+    }
+
+    pub fn switch(&mut self, val: Value, default: BlockId, cases: Vec<(u64, BlockId)>) {
+        let kind = InstKind::Switch {
+            val,
+            default,
+            cases,
+        };
+        self.ins(kind, Type::Void);
+    }
+
+    pub fn ret(&mut self, val: Option<Value>) {
+        self.ins(InstKind::Ret { val }, Type::Void);
+    }
+
+    pub fn unreachable(&mut self) {
+        self.ins(InstKind::Unreachable, Type::Void);
+    }
+}
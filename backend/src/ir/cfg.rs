@@ -0,0 +1,190 @@
+use crate::ir::function::Function;
+use crate::ir::value::BlockId;
+use slotmap::SecondaryMap;
+
+///
+/// 支配树，不可达的基本块不在树中
+///
+/// # Members
+/// - `rpo`: 可达基本块的逆后序，第一个是入口块
+/// - `order`: 基本块在逆后序中的位置
+/// - `idom`: 直接支配者，入口块没有
+/// - `children`: 支配树的子节点，按逆后序排列
+///
+#[derive(Debug, Clone)]
+pub struct DomTree {
+    rpo: Vec<BlockId>,
+    order: SecondaryMap<BlockId, usize>,
+    idom: SecondaryMap<BlockId, BlockId>,
+    children: SecondaryMap<BlockId, Vec<BlockId>>,
+}
+
+impl DomTree {
+    /// Cooper、Harvey、Kennedy 的迭代算法
+    pub fn new(func: &Function) -> Self {
+        let rpo = reverse_postorder(func);
+        let mut order = SecondaryMap::new();
+        for (i, block) in rpo.iter().enumerate() {
+            order.insert(*block, i);
+        }
+        let preds = func.predecessors();
+
+        // 按逆后序的位置计算，`doms[i]` 是第 i 个块的直接支配者的位置
+        let mut doms: Vec<Option<usize>> = vec![None; rpo.len()];
+        if !rpo.is_empty() {
+            doms[0] = Some(0);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 1..rpo.len() {
+                let mut new_idom: Option<usize> = None;
+                for pred in preds[rpo[i]].iter() {
+                    let Some(p) = order.get(*pred).copied() else {
+                        continue;
+                    };
+                    if doms[p].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => p,
+                        Some(x) => intersect(&doms, p, x),
+                    });
+                }
+                if new_idom.is_some() && doms[i] != new_idom {
+                    doms[i] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut idom = SecondaryMap::new();
+        let mut children: SecondaryMap<BlockId, Vec<BlockId>> = SecondaryMap::new();
+        for block in rpo.iter() {
+            children.insert(*block, Vec::new());
+        }
+        for (i, block) in rpo.iter().enumerate().skip(1) {
+            let parent = rpo[doms[i].unwrap()];
+            idom.insert(*block, parent);
+            children[parent].push(*block);
+        }
+        Self {
+            rpo,
+            order,
+            idom,
+            children,
+        }
+    }
+
+    /// 可达基本块的逆后序
+    pub fn rpo(&self) -> &[BlockId] {
+        &self.rpo
+    }
+
+    /// 在逆后序中的位置，不可达的块为 None
+    pub fn order(&self, block: BlockId) -> Option<usize> {
+        self.order.get(block).copied()
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.order.contains_key(block)
+    }
+
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        self.idom.get(block).copied()
+    }
+
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        self.children
+            .get(block)
+            .map(|x| x.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// 支配边界：`b` 支配 `x` 的某个前驱但不严格支配 `x` 时，`x` 在 `b` 的支配边界中
+    pub fn frontiers(&self, func: &Function) -> SecondaryMap<BlockId, Vec<BlockId>> {
+        let mut frontiers: SecondaryMap<BlockId, Vec<BlockId>> = SecondaryMap::new();
+        for block in self.rpo.iter() {
+            frontiers.insert(*block, Vec::new());
+        }
+        let preds = func.predecessors();
+        for block in self.rpo.iter().cloned() {
+            let preds: Vec<BlockId> = preds[block]
+                .iter()
+                .cloned()
+                .filter(|x| self.is_reachable(*x))
+                .collect();
+            if preds.len() < 2 {
+                continue;
+            }
+            for pred in preds {
+                let mut runner = pred;
+                while Some(runner) != self.idom(block) {
+                    if !frontiers[runner].contains(&block) {
+                        frontiers[runner].push(block);
+                    }
+                    match self.idom(runner) {
+                        Some(x) => runner = x,
+                        None => break,
+                    }
+                }
+            }
+        }
+        frontiers
+    }
+
+    /// `a` 是否支配 `b`，每个块支配自己
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        let mut cur = b;
+        loop {
+            if cur == a {
+                return true;
+            }
+            match self.idom(cur) {
+                Some(x) if self.order[x] >= self.order[a] => cur = x,
+                _ => return false,
+            }
+        }
+    }
+}
+
+fn intersect(doms: &[Option<usize>], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while a > b {
+            a = doms[a].unwrap();
+        }
+        while b > a {
+            b = doms[b].unwrap();
+        }
+    }
+    a
+}
+
+/// 从入口块开始的逆后序，后继按终结指令中的顺序访问
+pub fn reverse_postorder(func: &Function) -> Vec<BlockId> {
+    if func.is_declaration() {
+        return Vec::new();
+    }
+    let mut visited: SecondaryMap<BlockId, ()> = SecondaryMap::new();
+    let mut post = Vec::with_capacity(func.layout.len());
+    let entry = func.entry();
+    visited.insert(entry, ());
+    let mut stack = vec![(entry, func.successors(entry), 0)];
+    while let Some((block, succs, next)) = stack.last_mut() {
+        if let Some(succ) = succs.get(*next).copied() {
+            *next += 1;
+            if visited.insert(succ, ()).is_none() {
+                let succs = func.successors(succ);
+                stack.push((succ, succs, 0));
+            }
+        } else {
+            post.push(*block);
+            stack.pop();
+        }
+    }
+    post.reverse();
+    post
+}
@@ -0,0 +1,200 @@
+use crate::ir::value::{GlobalId, InstId};
+
+/// 源码位置，文件编号见 `DebugInfo::files`，行和列都从 1 开始，列为 0 表示不知道列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DebugLoc {
+    pub file: u32,
+    pub line: u32,
+    pub col: u32,
+}
+
+/// 调试信息中类型的下标，指向 `DebugInfo::types`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DiTypeId(pub u32);
+
+/// 基本类型的编码，对应 DWARF 的 `DW_ATE_*`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiEncoding {
+    Signed,
+    Unsigned,
+    SignedChar,
+    UnsignedChar,
+    Float,
+    Boolean,
+}
+
+///
+/// struct / union 的成员
+///
+/// # Members
+/// - `name`: 成员名，匿名的 struct / union 成员为 None
+/// - `ty`: 类型
+/// - `offset`: 字节偏移
+/// - `bit_field`: 位域在 `offset` 开始的存储单元中的起始位（从低位开始）和位宽
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiMember {
+    pub name: Option<String>,
+    pub ty: DiTypeId,
+    pub offset: u64,
+    pub bit_field: Option<(u32, u32)>,
+}
+
+///
+/// 调试信息中的类型，类型之间通过 `DiTypeId` 引用，可以有环（指向自身的 struct）；
+/// 引用的类型为 None 时表示 `void`
+///
+/// - `Base`: 整数、浮点等基本类型
+/// - `Pointer`: 指针
+/// - `Array`: 数组，`count` 为 None 时是不完整数组
+/// - `Record`: struct / union，`size` 为 None 时是不完整类型，只有声明
+/// - `Enum`: 枚举和它的枚举常量
+/// - `Typedef`: 类型别名
+/// - `Function`: 函数类型
+/// - `Const` `Volatile`: 限定符
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiType {
+    Base {
+        name: String,
+        size: u64,
+        encoding: DiEncoding,
+    },
+    Pointer {
+        pointee: Option<DiTypeId>,
+        size: u64,
+    },
+    Array {
+        elem: DiTypeId,
+        count: Option<u64>,
+    },
+    Record {
+        is_union: bool,
+        name: Option<String>,
+        size: Option<u64>,
+        members: Vec<DiMember>,
+    },
+    Enum {
+        name: Option<String>,
+        size: u64,
+        enumerators: Vec<(String, i64)>,
+    },
+    Typedef {
+        name: String,
+        ty: Option<DiTypeId>,
+        file: u32,
+        line: u32,
+    },
+    Function {
+        ret: Option<DiTypeId>,
+        params: Vec<DiTypeId>,
+        is_variadic: bool,
+    },
+    Const(Option<DiTypeId>),
+    Volatile(Option<DiTypeId>),
+}
+
+///
+/// 局部变量或参数
+///
+/// # Members
+/// - `name` `ty`: 名字、类型
+/// - `file` `line`: 声明所在的文件编号和行
+/// - `arg`: 参数的序号，从 1 开始，局部变量为 None
+/// - `addr`: 变量所在的 `alloca`，被优化掉后变量没有位置
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiVariable {
+    pub name: String,
+    pub ty: DiTypeId,
+    pub file: u32,
+    pub line: u32,
+    pub arg: Option<u32>,
+    pub addr: InstId,
+}
+
+///
+/// 函数的调试信息
+///
+/// # Members
+/// - `name`: 源码中的名字
+/// - `file` `line`: 定义所在的文件编号和行
+/// - `ty`: 函数类型，是 `DiType::Function`
+/// - `external`: 是否是外部可见的函数
+/// - `vars`: 参数和局部变量，参数在前
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiSubprogram {
+    pub name: String,
+    pub file: u32,
+    pub line: u32,
+    pub ty: DiTypeId,
+    pub external: bool,
+    pub vars: Vec<DiVariable>,
+}
+
+///
+/// 全局变量或块作用域的 `static` 变量
+///
+/// # Members
+/// - `name` `ty`: 源码中的名字、类型
+/// - `file` `line`: 声明所在的文件编号和行
+/// - `global`: 对应的全局变量
+/// - `external`: 是否是外部可见的变量
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiGlobal {
+    pub name: String,
+    pub ty: DiTypeId,
+    pub file: u32,
+    pub line: u32,
+    pub global: GlobalId,
+    pub external: bool,
+}
+
+///
+/// 模块（编译单元）的调试信息，函数的调试信息和指令的源码位置保存在各个函数中
+///
+/// 文本格式的 IR 不包含调试信息
+///
+/// # Members
+/// - `producer`: 编译器标识
+/// - `file` `dir`: 源文件名和编译时的工作目录
+/// - `files`: 其余的源文件（头文件等），编号从 2 开始，1 号是 `file`
+/// - `version`: DWARF 版本，4 或 5
+/// - `types`: 类型表，其中的类型全部输出
+/// - `globals`: 全局变量
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub producer: String,
+    pub file: String,
+    pub dir: String,
+    pub files: Vec<String>,
+    pub version: u16,
+    pub types: Vec<DiType>,
+    pub globals: Vec<DiGlobal>,
+}
+
+impl DebugInfo {
+    pub fn new(
+        producer: impl Into<String>,
+        file: impl Into<String>,
+        dir: impl Into<String>,
+    ) -> Self {
+        Self {
+            producer: producer.into(),
+            file: file.into(),
+            dir: dir.into(),
+            files: Vec::new(),
+            version: 5,
+            types: Vec::new(),
+            globals: Vec::new(),
+        }
+    }
+
+    pub fn add_type(&mut self, ty: DiType) -> DiTypeId {
+        self.types.push(ty);
+        DiTypeId(self.types.len() as u32 - 1)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::ir::debug::{DebugLoc, DiSubprogram};
+use crate::ir::inst::{InstData, InstKind};
+use crate::ir::module::{Linkage, Visibility};
+use crate::ir::types::{Signature, Type};
+use crate::ir::value::{BlockId, InstId, Value};
+use slotmap::{SecondaryMap, SlotMap};
+
+/// 函数的内联属性，`Hint` 对应 C 的 `inline`，`Always` `Never` 对应 GNU 的 `always_inline` `noinline`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum InlineAttr {
+    #[default]
+    None,
+    Hint,
+    Always,
+    Never,
+}
+
+/// 基本块，只保存指令顺序，指令本身在 `Function::insts` 中
+#[derive(Debug, Clone, Default)]
+pub struct BlockData {
+    pub insts: Vec<InstId>,
+}
+
+///
+/// 函数
+///
+/// # Members
+/// - `name`: 符号名
+/// - `sig`: 签名
+/// - `linkage`: 链接属性
+/// - `visibility`: 可见性
+/// - `inline`: 内联属性
+/// - `blocks` `insts`: 基本块和指令池，删除后 key 失效
+/// - `layout`: 基本块顺序，第一个是入口块，为空时是函数声明
+/// - `inst_block`: 指令所在的基本块
+/// - `debug`: 函数的调试信息
+/// - `locs`: 指令对应的源码位置，优化新建的指令没有位置
+///
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub sig: Signature,
+    pub linkage: Linkage,
+    pub visibility: Visibility,
+    pub inline: InlineAttr,
+    pub blocks: SlotMap<BlockId, BlockData>,
+    pub insts: SlotMap<InstId, InstData>,
+    pub layout: Vec<BlockId>,
+    inst_block: SecondaryMap<InstId, BlockId>,
+    pub debug: Option<DiSubprogram>,
+    pub locs: SecondaryMap<InstId, DebugLoc>,
+}
+
+impl Function {
+    pub fn new(name: impl Into<String>, sig: Signature, linkage: Linkage) -> Self {
+        Self {
+            name: name.into(),
+            sig,
+            linkage,
+            visibility: Visibility::Default,
+            inline: InlineAttr::None,
+            blocks: SlotMap::with_key(),
+            insts: SlotMap::with_key(),
+            layout: Vec::new(),
+            inst_block: SecondaryMap::new(),
+            debug: None,
+            locs: SecondaryMap::new(),
+        }
+    }
+
+    /// 没有函数体的是声明
+    pub fn is_declaration(&self) -> bool {
+        self.layout.is_empty()
+    }
+
+    pub fn entry(&self) -> BlockId {
+        *self.layout.first().expect("function declaration has no entry")
+    }
+
+    /// 创建基本块并追加到末尾
+    pub fn add_block(&mut self) -> BlockId {
+        let block = self.blocks.insert(BlockData::default());
+        self.layout.push(block);
+        block
+    }
+
+    /// 删除基本块及其中的指令，调用者负责先删掉对它的引用
+    pub fn remove_block(&mut self, block: BlockId) {
+        let data = self.blocks.remove(block).expect("block not exist");
+        for inst in data.insts {
+            self.insts.remove(inst);
+            self.inst_block.remove(inst);
+        }
+        self.layout.retain(|x| *x != block);
+    }
+
+    pub fn inst(&self, inst: InstId) -> &InstData {
+        &self.insts[inst]
+    }
+
+    pub fn inst_mut(&mut self, inst: InstId) -> &mut InstData {
+        &mut self.insts[inst]
+    }
+
+    /// 指令所在的基本块，已经从块中移除的指令返回 None
+    pub fn inst_block(&self, inst: InstId) -> Option<BlockId> {
+        self.inst_block.get(inst).cloned()
+    }
+
+    /// 追加到基本块末尾
+    pub fn append_inst(&mut self, block: BlockId, data: InstData) -> InstId {
+        let inst = self.insts.insert(data);
+        self.blocks[block].insts.push(inst);
+        self.inst_block.insert(inst, block);
+        inst
+    }
+
+    /// 插入到基本块的 `pos` 位置
+    pub fn insert_inst(&mut self, block: BlockId, pos: usize, data: InstData) -> InstId {
+        let inst = self.insts.insert(data);
+        self.blocks[block].insts.insert(pos, inst);
+        self.inst_block.insert(inst, block);
+        inst
+    }
+
+    /// 插入到 `before` 之前
+    pub fn insert_inst_before(&mut self, before: InstId, data: InstData) -> InstId {
+        let block = self.inst_block[before];
+        let pos = self.inst_pos(before);
+        self.insert_inst(block, pos, data)
+    }
+
+    /// 指令在所在块中的下标
+    pub fn inst_pos(&self, inst: InstId) -> usize {
+        let block = self.inst_block[inst];
+        self.blocks[block]
+            .insts
+            .iter()
+            .position(|x| *x == inst)
+            .expect("inst not in its block")
+    }
+
+    /// 从基本块中移除并删除指令，调用者负责先替换掉它的使用
+    pub fn remove_inst(&mut self, inst: InstId) {
+        if let Some(block) = self.inst_block.remove(inst) {
+            self.blocks[block].insts.retain(|x| *x != inst);
+        }
+        self.insts.remove(inst);
+        self.locs.remove(inst);
+    }
+
+    /// 把指令从所在块中摘下（不删除），用于移动指令
+    pub fn detach_inst(&mut self, inst: InstId) {
+        if let Some(block) = self.inst_block.remove(inst) {
+            self.blocks[block].insts.retain(|x| *x != inst);
+        }
+    }
+
+    /// 把摘下的指令插入到 `block` 的 `pos` 位置
+    pub fn attach_inst(&mut self, block: BlockId, pos: usize, inst: InstId) {
+        debug_assert!(!self.inst_block.contains_key(inst));
+        self.blocks[block].insts.insert(pos, inst);
+        self.inst_block.insert(inst, block);
+    }
+
+    /// 值的类型
+    pub fn value_type(&self, value: Value) -> Type {
+        match value {
+            Value::Inst(x) => self.insts[x].ty,
+            Value::Arg(x) => self.sig.params[x as usize].ty,
+            Value::Int { ty, .. } | Value::Float { ty, .. } | Value::Undef(ty) => ty,
+            Value::Global(_) | Value::Func(_) => Type::Ptr,
+        }
+    }
+
+    /// 基本块的终结指令
+    pub fn terminator(&self, block: BlockId) -> Option<InstId> {
+        let last = *self.blocks[block].insts.last()?;
+        self.insts[last].kind.is_terminator().then_some(last)
+    }
+
+    pub fn successors(&self, block: BlockId) -> Vec<BlockId> {
+        match self.terminator(block) {
+            Some(x) => self.insts[x].kind.successors(),
+            None => vec![],
+        }
+    }
+
+    /// 所有基本块的前驱，重复边只记录一次
+    pub fn predecessors(&self) -> SecondaryMap<BlockId, Vec<BlockId>> {
+        let mut preds: SecondaryMap<BlockId, Vec<BlockId>> = SecondaryMap::new();
+        for block in self.layout.iter().cloned() {
+            preds.entry(block).unwrap().or_default();
+        }
+        for block in self.layout.iter().cloned() {
+            for succ in self.successors(block) {
+                let list = preds.entry(succ).unwrap().or_default();
+                if !list.contains(&block) {
+                    list.push(block);
+                }
+            }
+        }
+        preds
+    }
+
+    /// 块中开头的 phi
+    pub fn phis(&self, block: BlockId) -> Vec<InstId> {
+        self.blocks[block]
+            .insts
+            .iter()
+            .cloned()
+            .take_while(|x| self.insts[*x].kind.is_phi())
+            .collect()
+    }
+
+    /// 把所有对 `old` 的使用替换成 `new`
+    pub fn replace_all_uses(&mut self, old: Value, new: Value) {
+        for (_, data) in self.insts.iter_mut() {
+            for operand in data.kind.operands_mut() {
+                if *operand == old {
+                    *operand = new;
+                }
+            }
+        }
+    }
+
+    /// 按 layout 顺序遍历所有指令
+    pub fn inst_iter(&self) -> impl Iterator<Item = (BlockId, InstId)> + '_ {
+        self.layout
+            .iter()
+            .flat_map(|b| self.blocks[*b].insts.iter().map(move |i| (*b, *i)))
+    }
+
+    /// 每个值被使用的次数
+    pub fn use_counts(&self) -> SecondaryMap<InstId, usize> {
+        let mut counts = SecondaryMap::new();
+        for (_, inst) in self.inst_iter() {
+            for operand in self.insts[inst].kind.operands() {
+                if let Value::Inst(x) = operand {
+                    *counts.entry(x).unwrap().or_default() += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// phi 中把来自 `old` 的入边改成来自 `new`
+    pub fn replace_phi_pred(&mut self, block: BlockId, old: BlockId, new: BlockId) {
+        for phi in self.phis(block) {
+            if let InstKind::Phi { incomings } = &mut self.insts[phi].kind {
+                for incoming in incomings.iter_mut() {
+                    if incoming.0 == old {
+                        incoming.0 = new;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 删除 phi 中来自 `pred` 的入边
+    pub fn remove_phi_pred(&mut self, block: BlockId, pred: BlockId) {
+        for phi in self.phis(block) {
+            if let InstKind::Phi { incomings } = &mut self.insts[phi].kind {
+                incomings.retain(|x| x.0 != pred);
+            }
+        }
+    }
+}
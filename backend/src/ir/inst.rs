@@ -0,0 +1,399 @@
+use crate::ir::types::{Signature, Type};
+use crate::ir::value::{BlockId, Value};
+use std::fmt::{Display, Formatter};
+
+macro_rules! name_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $text:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => $text),+
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($text => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.name())
+            }
+        }
+    };
+}
+
+name_enum!(
+    /// 二元运算，两个操作数与结果类型相同
+    BinaryOp {
+        Add => "add",
+        Sub => "sub",
+        Mul => "mul",
+        SDiv => "sdiv",
+        UDiv => "udiv",
+        SRem => "srem",
+        URem => "urem",
+        And => "and",
+        Or => "or",
+        Xor => "xor",
+        Shl => "shl",
+        LShr => "lshr",
+        AShr => "ashr",
+        FAdd => "fadd",
+        FSub => "fsub",
+        FMul => "fmul",
+        FDiv => "fdiv",
+        FRem => "frem",
+    }
+);
+
+name_enum!(
+    /// 比较，结果为 `i1`，`o*` 为有序比较（任一为 NaN 时为假），`une` 为无序不等
+    CmpPred {
+        Eq => "eq",
+        Ne => "ne",
+        Slt => "slt",
+        Sle => "sle",
+        Sgt => "sgt",
+        Sge => "sge",
+        Ult => "ult",
+        Ule => "ule",
+        Ugt => "ugt",
+        Uge => "uge",
+        FOeq => "oeq",
+        FUne => "une",
+        FOlt => "olt",
+        FOle => "ole",
+        FOgt => "ogt",
+        FOge => "oge",
+    }
+);
+
+name_enum!(
+    /// 类型转换
+    CastOp {
+        Trunc => "trunc",
+        ZExt => "zext",
+        SExt => "sext",
+        FpToSi => "fptosi",
+        FpToUi => "fptoui",
+        SiToFp => "sitofp",
+        UiToFp => "uitofp",
+        FpExt => "fpext",
+        FpTrunc => "fptrunc",
+        PtrToInt => "ptrtoint",
+        IntToPtr => "inttoptr",
+        Bitcast => "bitcast",
+    }
+);
+
+impl BinaryOp {
+    pub fn is_float(self) -> bool {
+        use BinaryOp::*;
+        matches!(self, FAdd | FSub | FMul | FDiv | FRem)
+    }
+
+    /// 交换律
+    /// 可以带 `nsw` 标记
+    pub fn can_be_nsw(self) -> bool {
+        use BinaryOp::*;
+        matches!(self, Add | Sub | Mul)
+    }
+
+    pub fn is_commutative(self) -> bool {
+        use BinaryOp::*;
+        matches!(self, Add | Mul | And | Or | Xor | FAdd | FMul)
+    }
+}
+
+impl CmpPred {
+    pub fn is_float(self) -> bool {
+        use CmpPred::*;
+        matches!(self, FOeq | FUne | FOlt | FOle | FOgt | FOge)
+    }
+
+    /// 交换操作数后的谓词，`a < b` 等价于 `b > a`
+    pub fn swap(self) -> Self {
+        use CmpPred::*;
+        match self {
+            Slt => Sgt,
+            Sle => Sge,
+            Sgt => Slt,
+            Sge => Sle,
+            Ult => Ugt,
+            Ule => Uge,
+            Ugt => Ult,
+            Uge => Ule,
+            FOlt => FOgt,
+            FOle => FOge,
+            FOgt => FOlt,
+            FOge => FOle,
+            x => x,
+        }
+    }
+
+    /// 取反后的谓词，浮点比较取反后变成无序比较，不能简单取反
+    pub fn inverse(self) -> Option<Self> {
+        use CmpPred::*;
+        let pred = match self {
+            Eq => Ne,
+            Ne => Eq,
+            Slt => Sge,
+            Sle => Sgt,
+            Sgt => Sle,
+            Sge => Slt,
+            Ult => Uge,
+            Ule => Ugt,
+            Ugt => Ule,
+            Uge => Ult,
+            FOeq => FUne,
+            FUne => FOeq,
+            _ => return None,
+        };
+        Some(pred)
+    }
+}
+
+///
+/// 指令
+///
+/// # Members
+/// - `kind`: 指令种类和操作数
+/// - `ty`: 结果类型，没有结果的指令为 `void`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstData {
+    pub kind: InstKind,
+    pub ty: Type,
+}
+
+///
+/// 指令种类
+///
+/// `Binary` 的 `nsw` 表示有符号溢出是未定义行为，只用于整数的 `add` `sub` `mul`
+///
+/// 聚合类型没有 IR 类型，内存操作都以字节为单位：
+/// - `Alloca`: 在栈上分配 `size` 字节，结果为 `ptr`
+/// - `Gep`: 地址计算 `base + index * scale + offset`，`index` 为整数
+/// - `MemCopy`: 复制 `size` 字节，用于结构体赋值 / 传参
+///
+/// `Load` `Store` 的 `tbaa` 是前端按 C 的有效类型规则给出的类型标签，两个非 0 的标签不同时
+/// 访问的不是同一个对象；`0` 表示没有类型信息（字符类型、联合体的成员），可能和任何访问重叠
+///
+/// 变参：`va_list` 是指向一块由目标决定大小的内存的指针（由前端 alloca），
+/// `VaStart` `VaArg` `VaEnd` `VaCopy` 都操作这个指针
+///
+/// 终结指令：`Br` `CondBr` `Switch` `Ret` `Unreachable`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstKind {
+    Binary {
+        op: BinaryOp,
+        lhs: Value,
+        rhs: Value,
+        nsw: bool,
+    },
+    FNeg {
+        val: Value,
+    },
+    Cmp {
+        pred: CmpPred,
+        lhs: Value,
+        rhs: Value,
+    },
+    Cast {
+        op: CastOp,
+        val: Value,
+    },
+    Select {
+        cond: Value,
+        then_val: Value,
+        else_val: Value,
+    },
+    Alloca {
+        size: u64,
+        align: u32,
+    },
+    Load {
+        ptr: Value,
+        volatile: bool,
+        tbaa: u32,
+    },
+    Store {
+        ptr: Value,
+        val: Value,
+        volatile: bool,
+        tbaa: u32,
+    },
+    Gep {
+        base: Value,
+        index: Value,
+        scale: u64,
+        offset: i64,
+    },
+    MemCopy {
+        dst: Value,
+        src: Value,
+        size: u64,
+        align: u32,
+    },
+    Call {
+        sig: Signature,
+        callee: Value,
+        args: Vec<Value>,
+    },
+    Phi {
+        incomings: Vec<(BlockId, Value)>,
+    },
+    VaStart {
+        list: Value,
+    },
+    VaArg {
+        list: Value,
+    },
+    VaEnd {
+        list: Value,
+    },
+    VaCopy {
+        dst: Value,
+        src: Value,
+    },
+    Br {
+        dest: BlockId,
+    },
+    CondBr {
+        cond: Value,
+        then_dest: BlockId,
+        else_dest: BlockId,
+    },
+    Switch {
+        val: Value,
+        default: BlockId,
+        cases: Vec<(u64, BlockId)>,
+    },
+    Ret {
+        val: Option<Value>,
+    },
+    Unreachable,
+}
+
+impl InstKind {
+    pub fn is_terminator(&self) -> bool {
+        use InstKind::*;
+        matches!(
+            self,
+            Br { .. } | CondBr { .. } | Switch { .. } | Ret { .. } | Unreachable
+        )
+    }
+
+    pub fn is_phi(&self) -> bool {
+        matches!(self, InstKind::Phi { .. })
+    }
+
+    /// 是否有副作用（写内存、调用、控制流），没有副作用且结果无人使用的指令可以删除
+    pub fn has_side_effects(&self) -> bool {
+        use InstKind::*;
+        match self {
+            Store { .. } | MemCopy { .. } | Call { .. } => true,
+            VaStart { .. } | VaArg { .. } | VaEnd { .. } | VaCopy { .. } => true,
+            Load { volatile, .. } => *volatile,
+            _ => self.is_terminator(),
+        }
+    }
+
+    /// 是否读内存
+    pub fn reads_memory(&self) -> bool {
+        use InstKind::*;
+        matches!(
+            self,
+            Load { .. } | MemCopy { .. } | Call { .. } | VaArg { .. } | VaCopy { .. }
+        )
+    }
+
+    /// 后继基本块
+    pub fn successors(&self) -> Vec<BlockId> {
+        use InstKind::*;
+        match self {
+            Br { dest } => vec![*dest],
+            CondBr {
+                then_dest,
+                else_dest,
+                ..
+            } => vec![*then_dest, *else_dest],
+            Switch { default, cases, .. } => {
+                let mut succs = vec![*default];
+                succs.extend(cases.iter().map(|x| x.1));
+                succs
+            }
+            _ => vec![],
+        }
+    }
+
+    /// 所有后继的可变引用，用于修改跳转目标
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        use InstKind::*;
+        match self {
+            Br { dest } => vec![dest],
+            CondBr {
+                then_dest,
+                else_dest,
+                ..
+            } => vec![then_dest, else_dest],
+            Switch { default, cases, .. } => {
+                let mut succs = vec![default];
+                succs.extend(cases.iter_mut().map(|x| &mut x.1));
+                succs
+            }
+            _ => vec![],
+        }
+    }
+
+    /// 所有操作数，按照文本格式中出现的顺序
+    pub fn operands(&self) -> Vec<Value> {
+        let mut this = self.clone();
+        this.operands_mut().into_iter().map(|x| *x).collect()
+    }
+
+    /// 所有操作数的可变引用，用于替换值
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        use InstKind::*;
+        match self {
+            Binary { lhs, rhs, .. } | Cmp { lhs, rhs, .. } => vec![lhs, rhs],
+            FNeg { val } | Cast { val, .. } => vec![val],
+            Select {
+                cond,
+                then_val,
+                else_val,
+            } => vec![cond, then_val, else_val],
+            Alloca { .. } => vec![],
+            Load { ptr, .. } => vec![ptr],
+            Store { ptr, val, .. } => vec![val, ptr],
+            Gep { base, index, .. } => vec![base, index],
+            MemCopy { dst, src, .. } => vec![dst, src],
+            Call { callee, args, .. } => {
+                let mut ops = vec![callee];
+                ops.extend(args.iter_mut());
+                ops
+            }
+            Phi { incomings } => incomings.iter_mut().map(|x| &mut x.1).collect(),
+            VaStart { list } | VaArg { list } | VaEnd { list } => vec![list],
+            VaCopy { dst, src } => vec![dst, src],
+            CondBr { cond, .. } => vec![cond],
+            Switch { val, .. } => vec![val],
+            Ret { val } => val.iter_mut().collect(),
+            Br { .. } | Unreachable => vec![],
+        }
+    }
+}
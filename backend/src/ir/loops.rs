@@ -0,0 +1,232 @@
+use crate::ir::cfg::DomTree;
+use crate::ir::function::Function;
+use crate::ir::inst::{InstData, InstKind};
+use crate::ir::types::Type;
+use crate::ir::value::{BlockId, Value};
+use slotmap::SecondaryMap;
+
+///
+/// 自然循环，由指向循环头的回边（起点被循环头支配的边）确定，同一个循环头的回边属于同一个循环
+///
+/// # Members
+/// - `header`: 循环头，支配循环中所有的块
+/// - `latches`: 回边的起点
+/// - `blocks`: 循环中的块，包括内层循环的块，按逆后序排列，第一个是 `header`
+/// - `parent`: 直接外层循环在 `LoopInfo::loops` 中的下标
+/// - `children`: 直接内层循环
+/// - `depth`: 嵌套深度，最外层为 1
+///
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub header: BlockId,
+    pub latches: Vec<BlockId>,
+    pub blocks: Vec<BlockId>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub depth: u32,
+}
+
+impl Loop {
+    pub fn contains(&self, block: BlockId) -> bool {
+        self.blocks.contains(&block)
+    }
+
+    /// 循环外唯一的前驱，并且它只有循环头一个后继
+    pub fn preheader(&self, func: &Function) -> Option<BlockId> {
+        let preds = func.predecessors();
+        let outside: Vec<BlockId> = preds[self.header]
+            .iter()
+            .cloned()
+            .filter(|x| !self.contains(*x))
+            .collect();
+        match outside.as_slice() {
+            [x] if func.successors(*x) == [self.header] => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// 离开循环的边 `(循环中的块, 循环外的块)`，按 `blocks` 的顺序
+    pub fn exits(&self, func: &Function) -> Vec<(BlockId, BlockId)> {
+        let mut exits = Vec::new();
+        for block in self.blocks.iter().cloned() {
+            for succ in func.successors(block) {
+                if !self.contains(succ) && !exits.contains(&(block, succ)) {
+                    exits.push((block, succ));
+                }
+            }
+        }
+        exits
+    }
+}
+
+///
+/// 函数中的循环和嵌套关系，不可达的块和不可规约的环不构成循环
+///
+/// # Members
+/// - `loops`: 所有循环，外层循环在内层之前
+/// - `innermost`: 每个块所在的最内层循环
+///
+#[derive(Debug, Clone)]
+pub struct LoopInfo {
+    loops: Vec<Loop>,
+    innermost: SecondaryMap<BlockId, usize>,
+}
+
+impl LoopInfo {
+    pub fn new(func: &Function, dom: &DomTree) -> Self {
+        let preds = func.predecessors();
+        let mut loops: Vec<Loop> = Vec::new();
+        let mut innermost: SecondaryMap<BlockId, usize> = SecondaryMap::new();
+        // 外层循环的循环头支配内层的，在逆后序中先出现
+        for header in dom.rpo().iter().cloned() {
+            let latches: Vec<BlockId> = preds[header]
+                .iter()
+                .cloned()
+                .filter(|x| dom.dominates(header, *x))
+                .collect();
+            if latches.is_empty() {
+                continue;
+            }
+
+            // 从回边的起点反向走到循环头
+            let mut body: SecondaryMap<BlockId, ()> = SecondaryMap::new();
+            body.insert(header, ());
+            let mut work = latches.clone();
+            while let Some(block) = work.pop() {
+                if body.insert(block, ()).is_none() {
+                    work.extend(
+                        preds[block]
+                            .iter()
+                            .cloned()
+                            .filter(|x| dom.is_reachable(*x)),
+                    );
+                }
+            }
+            let blocks: Vec<BlockId> = dom
+                .rpo()
+                .iter()
+                .cloned()
+                .filter(|x| body.contains_key(*x))
+                .collect();
+
+            let id = loops.len();
+            let parent = innermost.get(header).copied();
+            let depth = match parent {
+                Some(x) => {
+                    loops[x].children.push(id);
+                    loops[x].depth + 1
+                }
+                None => 1,
+            };
+            for block in blocks.iter() {
+                innermost.insert(*block, id);
+            }
+            loops.push(Loop {
+                header,
+                latches,
+                blocks,
+                parent,
+                children: Vec::new(),
+                depth,
+            });
+        }
+        Self { loops, innermost }
+    }
+
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// 块所在的最内层循环
+    pub fn innermost(&self, block: BlockId) -> Option<usize> {
+        self.innermost.get(block).copied()
+    }
+
+    /// 块的循环嵌套深度，不在循环中为 0
+    pub fn depth(&self, block: BlockId) -> u32 {
+        self.innermost(block)
+            .map(|x| self.loops[x].depth)
+            .unwrap_or(0)
+    }
+
+    /// 内层循环在外层之前的顺序
+    pub fn postorder(&self) -> Vec<usize> {
+        (0..self.loops.len()).rev().collect()
+    }
+}
+
+///
+/// 给循环插入 preheader：循环外的前驱都改为跳到新的块，新的块再跳到循环头
+///
+/// 循环头的 phi 中来自循环外的入边合并为一条，值不同时在 preheader 中用 phi 合并。
+/// 循环头是入口块时不能插入，返回 None
+///
+pub fn insert_preheader(func: &mut Function, lp: &Loop) -> Option<BlockId> {
+    let header = lp.header;
+    if header == func.entry() {
+        return None;
+    }
+    let preds = func.predecessors();
+    let outside: Vec<BlockId> = preds[header]
+        .iter()
+        .cloned()
+        .filter(|x| !lp.contains(*x))
+        .collect();
+
+    let preheader = func.add_block();
+    func.layout.pop();
+    let at = func.layout.iter().position(|x| *x == header).unwrap();
+    func.layout.insert(at, preheader);
+
+    for phi in func.phis(header) {
+        let InstKind::Phi { incomings } = func.insts[phi].kind.clone() else {
+            unreachable!()
+        };
+        let (outer, mut inner): (Vec<_>, Vec<_>) =
+            incomings.into_iter().partition(|x| outside.contains(&x.0));
+        let value = match outer.first() {
+            None => continue,
+            Some(first) if outer.iter().all(|x| x.1 == first.1) => first.1,
+            Some(_) => {
+                let data = InstData {
+                    kind: InstKind::Phi { incomings: outer },
+                    ty: func.insts[phi].ty,
+                };
+                let pos = func.blocks[preheader].insts.len();
+                Value::Inst(func.insert_inst(preheader, pos, data))
+            }
+        };
+        inner.push((preheader, value));
+        func.insts[phi].kind = InstKind::Phi { incomings: inner };
+    }
+
+    for pred in outside {
+        let term = func.terminator(pred).unwrap();
+        for succ in func.insts[term].kind.successors_mut() {
+            if *succ == header {
+                *succ = preheader;
+            }
+        }
+    }
+    func.append_inst(
+        preheader,
+        InstData {
+            kind: InstKind::Br { dest: header },
+            ty: Type::Void,
+        },
+    );
+    Some(preheader)
+}
+
+/// 给所有没有 preheader 的循环插入 preheader，返回是否修改了函数
+pub fn insert_preheaders(func: &mut Function) -> bool {
+    let dom = DomTree::new(func);
+    let info = LoopInfo::new(func, &dom);
+    let mut changed = false;
+    for lp in info.loops() {
+        if lp.preheader(func).is_none() {
+            changed |= insert_preheader(func, lp).is_some();
+        }
+    }
+    changed
+}
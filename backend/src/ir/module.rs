@@ -0,0 +1,192 @@
+use crate::ir::debug::DebugInfo;
+use crate::ir::function::Function;
+use crate::ir::value::{FuncId, GlobalId, Value};
+use rustc_hash::FxHashMap;
+use slotmap::SlotMap;
+
+/// 链接属性，`Internal` 对应 C 的 `static`，`Weak` 是可以被其他定义覆盖的外部符号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Linkage {
+    #[default]
+    External,
+    Internal,
+    Weak,
+}
+
+/// 符号可见性，对应 ELF 的 `STV_*`；`Hidden` 不导出到共享库之外，`Protected` 导出但不可被抢占
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Visibility {
+    #[default]
+    Default,
+    Hidden,
+    Protected,
+}
+
+/// 重定位模型：`Static` 只能链接成固定地址的可执行文件，`Pie` 生成位置无关的可执行文件，
+/// `Pic` 可以链接进共享库，外部符号经 GOT / PLT 访问
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RelocModel {
+    #[default]
+    Static,
+    Pic,
+    Pie,
+}
+
+///
+/// 全局变量初始值的一段
+/// - `Bytes`: 原始字节（小端，由前端按目标布局生成）
+/// - `Zero`: `n` 个零字节
+/// - `Addr`: 符号地址加偏移，宽度为指针宽度，目标是 `Value::Global` 或 `Value::Func`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitItem {
+    Bytes(Vec<u8>),
+    Zero(u64),
+    Addr { target: Value, addend: i64 },
+}
+
+///
+/// 全局变量
+///
+/// # Members
+/// - `name`: 符号名
+/// - `size` `align`: 大小和对齐
+/// - `init`: 初始值，为 None 时是外部声明
+/// - `linkage`: 链接属性
+/// - `visibility`: 可见性
+/// - `thread_local`: 线程局部变量，每个线程有自己的一份，初始值相同
+/// - `constant`: 只读，放在只读段
+///
+#[derive(Debug, Clone)]
+pub struct Global {
+    pub name: String,
+    pub size: u64,
+    pub align: u32,
+    pub init: Option<Vec<InitItem>>,
+    pub linkage: Linkage,
+    pub visibility: Visibility,
+    pub thread_local: bool,
+    pub constant: bool,
+}
+
+impl Global {
+    pub fn is_declaration(&self) -> bool {
+        self.init.is_none()
+    }
+}
+
+///
+/// 模块，一个翻译单元对应一个模块
+///
+/// # Members
+/// - `globals` `funcs`: 全局变量和函数，删除后 key 失效
+/// - `global_order` `func_order`: 输出顺序，保证输出稳定
+/// - `symbols`: 名字到符号，全局变量和函数共用一个名字空间
+/// - `debug`: 调试信息，为 None 时不生成
+/// - `reloc_model`: 重定位模型
+/// - `ptr_bytes`: 指针的字节数，默认 8
+///
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub globals: SlotMap<GlobalId, Global>,
+    pub funcs: SlotMap<FuncId, Function>,
+    global_order: Vec<GlobalId>,
+    func_order: Vec<FuncId>,
+    symbols: FxHashMap<String, Value>,
+    pub debug: Option<DebugInfo>,
+    pub reloc_model: RelocModel,
+    pub ptr_bytes: u32,
+}
+
+impl Default for Module {
+    fn default() -> Self {
+        Self {
+            globals: SlotMap::default(),
+            funcs: SlotMap::default(),
+            global_order: Vec::new(),
+            func_order: Vec::new(),
+            symbols: FxHashMap::default(),
+            debug: None,
+            reloc_model: RelocModel::default(),
+            ptr_bytes: 8,
+        }
+    }
+}
+
+impl Module {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加全局变量，名字必须唯一
+    pub fn add_global(&mut self, global: Global) -> GlobalId {
+        debug_assert!(!self.symbols.contains_key(&global.name), "{}", global.name);
+        let name = global.name.clone();
+        let id = self.globals.insert(global);
+        self.global_order.push(id);
+        self.symbols.insert(name, Value::Global(id));
+        id
+    }
+
+    /// 添加函数，名字必须唯一
+    pub fn add_func(&mut self, func: Function) -> FuncId {
+        debug_assert!(!self.symbols.contains_key(&func.name), "{}", func.name);
+        let name = func.name.clone();
+        let id = self.funcs.insert(func);
+        self.func_order.push(id);
+        self.symbols.insert(name, Value::Func(id));
+        id
+    }
+
+    pub fn remove_global(&mut self, id: GlobalId) -> Global {
+        let global = self.globals.remove(id).expect("global not exist");
+        self.global_order.retain(|x| *x != id);
+        self.symbols.remove(&global.name);
+        global
+    }
+
+    pub fn remove_func(&mut self, id: FuncId) -> Function {
+        let func = self.funcs.remove(id).expect("function not exist");
+        self.func_order.retain(|x| *x != id);
+        self.symbols.remove(&func.name);
+        func
+    }
+
+    /// 按名字查找符号，结果为 `Value::Global` 或 `Value::Func`
+    pub fn symbol(&self, name: &str) -> Option<Value> {
+        self.symbols.get(name).cloned()
+    }
+
+    pub fn func_by_name(&self, name: &str) -> Option<FuncId> {
+        match self.symbol(name)? {
+            Value::Func(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn global_by_name(&self, name: &str) -> Option<GlobalId> {
+        match self.symbol(name)? {
+            Value::Global(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// 符号名，`value` 必须是 `Value::Global` 或 `Value::Func`
+    pub fn symbol_name(&self, value: Value) -> &str {
+        match value {
+            Value::Global(x) => &self.globals[x].name,
+            Value::Func(x) => &self.funcs[x].name,
+            _ => unreachable!("not a symbol {:?}", value),
+        }
+    }
+
+    /// 按添加顺序的全局变量
+    pub fn global_ids(&self) -> Vec<GlobalId> {
+        self.global_order.clone()
+    }
+
+    /// 按添加顺序的函数
+    pub fn func_ids(&self) -> Vec<FuncId> {
+        self.func_order.clone()
+    }
+}
@@ -0,0 +1,1045 @@
+//!
+//! IR 文本格式的解析
+//!
+//! ```text
+//! @s = internal constant 6, align 1 { bytes [104, 101, 108, 108, 111, 0] }
+//! @p = global 8, align 8 { addr @s +1 }
+//! @e = external global
+//!
+//! declare i32 @printf(ptr, ...)
+//!
+//! define i32 @main(i32 %a0, ptr %a1) {
+//! bb0:
+//!     %0 = alloca 4, align 4
+//!     store i32 %a0, ptr %0
+//!     %1 = load i32, ptr %0
+//!     %2 = icmp slt i32 %1, 10
+//!     br i1 %2, bb1, bb2
+//! bb1:
+//!     %3 = call i32 (ptr, ...) @printf(ptr @s, i32 %1)
+//!     br bb2
+//! bb2:
+//!     %4 = phi i32 [0, bb0], [1, bb1]
+//!     ret i32 %4
+//! }
+//! ```
+//!
+//! 值和基本块可以使用任意名字，也可以先使用后定义（phi 中常见），
+//! `;` 到行尾是注释
+//!
+
+use crate::err::ir_error::{IrError, IrResult};
+use crate::ir::function::{Function, InlineAttr};
+use crate::ir::inst::{BinaryOp, CastOp, CmpPred, InstData, InstKind};
+use crate::ir::module::{Global, InitItem, Linkage, Module, Visibility};
+use crate::ir::types::{AbiParam, AggShape, ParamAttr, Signature, Type};
+use crate::ir::value::{BlockId, Value};
+use rustc_hash::FxHashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Local(String),
+    Global(String),
+    Num(String),
+    Punct(char),
+    Ellipsis,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    line: usize,
+    col: usize,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')
+}
+
+fn lex(text: &str) -> IrResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or_default();
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let (line, col) = (line_no + 1, i + 1);
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let take_name = |start: usize| {
+                let mut end = start;
+                while end < chars.len() && is_name_char(chars[end]) {
+                    end += 1;
+                }
+                (chars[start..end].iter().collect::<String>(), end)
+            };
+            let tok = match c {
+                '%' | '@' => {
+                    let (name, end) = take_name(i + 1);
+                    if name.is_empty() {
+                        return Err(IrError::Parse {
+                            line,
+                            col,
+                            msg: format!("expected name after '{}'", c),
+                        });
+                    }
+                    i = end;
+                    match c {
+                        '%' => Tok::Local(name),
+                        _ => Tok::Global(name),
+                    }
+                }
+                '.' if chars[i..].starts_with(&['.', '.', '.']) => {
+                    i += 3;
+                    Tok::Ellipsis
+                }
+                '-' | '+' | '0'..='9' => {
+                    // 数字，包括浮点数 `1.5e-3` `-inf` `NaN`
+                    let start = i;
+                    i += 1;
+                    while i < chars.len() {
+                        let c = chars[i];
+                        let exp_sign = matches!(c, '-' | '+') && matches!(chars[i - 1], 'e' | 'E');
+                        if is_name_char(c) || exp_sign {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    Tok::Num(chars[start..i].iter().collect())
+                }
+                c if is_name_char(c) => {
+                    let (name, end) = take_name(i);
+                    i = end;
+                    if name == "NaN" || name == "inf" {
+                        Tok::Num(name)
+                    } else {
+                        Tok::Ident(name)
+                    }
+                }
+                '=' | ',' | ':' | '(' | ')' | '[' | ']' | '{' | '}' => {
+                    i += 1;
+                    Tok::Punct(c)
+                }
+                _ => {
+                    return Err(IrError::Parse {
+                        line,
+                        col,
+                        msg: format!("unexpected character '{}'", c),
+                    });
+                }
+            };
+            tokens.push(Token { tok, line, col });
+        }
+    }
+    let line = text.lines().count() + 1;
+    tokens.push(Token {
+        tok: Tok::Eof,
+        line,
+        col: 1,
+    });
+    Ok(tokens)
+}
+
+/// 解析文本格式的模块
+pub fn parse_module(text: &str) -> IrResult<Module> {
+    let tokens = lex(text)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        module: Module::new(),
+    };
+    // 第一遍只登记符号，函数体和初始值可以引用后面定义的符号
+    parser.declare_symbols()?;
+    parser.pos = 0;
+    parser.parse_items()?;
+    Ok(parser.module)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    module: Module,
+}
+
+/// 函数体内的名字
+struct Locals {
+    values: FxHashMap<String, Value>,
+    blocks: FxHashMap<String, BlockId>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Tok {
+        &self.tokens[self.pos].tok
+    }
+
+    fn peek_at(&self, n: usize) -> &Tok {
+        let pos = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[pos].tok
+    }
+
+    fn next(&mut self) -> Tok {
+        let tok = self.tokens[self.pos].tok.clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn error<T>(&self, msg: impl Into<String>) -> IrResult<T> {
+        let token = &self.tokens[self.pos];
+        Err(IrError::Parse {
+            line: token.line,
+            col: token.col,
+            msg: msg.into(),
+        })
+    }
+
+    fn expect_punct(&mut self, c: char) -> IrResult<()> {
+        match self.peek() {
+            Tok::Punct(x) if *x == c => {
+                self.next();
+                Ok(())
+            }
+            x => self.error(format!("expected '{}', found {:?}", c, x)),
+        }
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        if *self.peek() == Tok::Punct(c) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_ident(&mut self, name: &str) -> bool {
+        match self.peek() {
+            Tok::Ident(x) if x == name => {
+                self.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_ident(&mut self, name: &str) -> IrResult<()> {
+        match self.eat_ident(name) {
+            true => Ok(()),
+            false => self.error(format!("expected '{}', found {:?}", name, self.peek())),
+        }
+    }
+
+    fn ident(&mut self) -> IrResult<String> {
+        match self.next() {
+            Tok::Ident(x) => Ok(x),
+            x => {
+                self.pos -= 1;
+                self.error(format!("expected identifier, found {:?}", x))
+            }
+        }
+    }
+
+    fn global_name(&mut self) -> IrResult<String> {
+        match self.next() {
+            Tok::Global(x) => Ok(x),
+            x => {
+                self.pos -= 1;
+                self.error(format!("expected '@name', found {:?}", x))
+            }
+        }
+    }
+
+    fn int(&mut self) -> IrResult<i64> {
+        match self.next() {
+            Tok::Num(x) => match parse_int(&x) {
+                Some(x) => Ok(x as i64),
+                None => {
+                    self.pos -= 1;
+                    self.error(format!("invalid integer '{}'", x))
+                }
+            },
+            x => {
+                self.pos -= 1;
+                self.error(format!("expected integer, found {:?}", x))
+            }
+        }
+    }
+
+    fn uint(&mut self) -> IrResult<u64> {
+        let value = self.int()?;
+        if value < 0 {
+            self.pos -= 1;
+            return self.error("expected unsigned integer");
+        }
+        Ok(value as u64)
+    }
+
+    fn ty(&mut self) -> IrResult<Type> {
+        let name = self.ident()?;
+        match Type::from_name(&name) {
+            Some(x) => Ok(x),
+            None => {
+                self.pos -= 1;
+                self.error(format!("unknown type '{}'", name))
+            }
+        }
+    }
+
+    fn peek_is_type(&self) -> bool {
+        matches!(self.peek(), Tok::Ident(x) if Type::from_name(x).is_some())
+    }
+
+    fn linkage(&mut self) -> Linkage {
+        if self.eat_ident("internal") {
+            Linkage::Internal
+        } else if self.eat_ident("weak") {
+            Linkage::Weak
+        } else {
+            Linkage::External
+        }
+    }
+
+    fn visibility(&mut self) -> Visibility {
+        if self.eat_ident("hidden") {
+            Visibility::Hidden
+        } else if self.eat_ident("protected") {
+            Visibility::Protected
+        } else {
+            Visibility::Default
+        }
+    }
+
+    fn inline_attr(&mut self) -> InlineAttr {
+        if self.eat_ident("inlinehint") {
+            InlineAttr::Hint
+        } else if self.eat_ident("alwaysinline") {
+            InlineAttr::Always
+        } else if self.eat_ident("noinline") {
+            InlineAttr::Never
+        } else {
+            InlineAttr::None
+        }
+    }
+
+    /// `load` `store` 末尾可选的 `, tbaa n`
+    fn tbaa(&mut self) -> IrResult<u32> {
+        if !self.eat_punct(',') {
+            return Ok(0);
+        }
+        self.expect_ident("tbaa")?;
+        Ok(self.uint()? as u32)
+    }
+
+    /// 参数：`ty [byval(size, align[, shape]) | sret(size, align[, shape])] [%name]`
+    fn param(&mut self) -> IrResult<(AbiParam, Option<String>)> {
+        let ty = self.ty()?;
+        let attr = match self.peek() {
+            Tok::Ident(x) if x == "byval" || x == "sret" => {
+                let byval = x == "byval";
+                self.next();
+                self.expect_punct('(')?;
+                let size = self.uint()? as u32;
+                self.expect_punct(',')?;
+                let align = self.uint()? as u32;
+                let shape = self.agg_shape()?;
+                self.expect_punct(')')?;
+                match byval {
+                    true => ParamAttr::ByVal { size, align, shape },
+                    false => ParamAttr::SRet { size, align, shape },
+                }
+            }
+            _ => ParamAttr::None,
+        };
+        let name = match self.peek().clone() {
+            Tok::Local(x) => {
+                self.next();
+                Some(x)
+            }
+            _ => None,
+        };
+        Ok((AbiParam::with_attr(ty, attr), name))
+    }
+
+    /// 聚合类型的标量成员 `, {ty offset, ...}`，可以省略
+    fn agg_shape(&mut self) -> IrResult<AggShape> {
+        if !self.eat_punct(',') {
+            return Ok(AggShape::default());
+        }
+        self.expect_punct('{')?;
+        let mut fields = Vec::new();
+        loop {
+            let ty = self.ty()?;
+            fields.push((self.uint()?, ty));
+            if !self.eat_punct(',') {
+                break;
+            }
+        }
+        self.expect_punct('}')?;
+        match AggShape::new(&fields) {
+            Some(x) => Ok(x),
+            None => self.error("too many fields in aggregate shape"),
+        }
+    }
+
+    /// 参数列表 `(params, ...)`，返回参数、参数名、是否变参
+    #[allow(clippy::type_complexity)]
+    fn params(&mut self) -> IrResult<(Vec<AbiParam>, Vec<Option<String>>, bool)> {
+        self.expect_punct('(')?;
+        let mut params = Vec::new();
+        let mut names = Vec::new();
+        let mut variadic = false;
+        if !self.eat_punct(')') {
+            loop {
+                if *self.peek() == Tok::Ellipsis {
+                    self.next();
+                    variadic = true;
+                    self.expect_punct(')')?;
+                    break;
+                }
+                let (param, name) = self.param()?;
+                params.push(param);
+                names.push(name);
+                if self.eat_punct(')') {
+                    break;
+                }
+                self.expect_punct(',')?;
+            }
+        }
+        Ok((params, names, variadic))
+    }
+
+    /// 函数头 `ret @name(params)`
+    fn func_head(&mut self) -> IrResult<(String, Signature, Vec<Option<String>>)> {
+        let ret = self.ty()?;
+        let name = self.global_name()?;
+        let (params, names, variadic) = self.params()?;
+        Ok((name, Signature::new(params, ret, variadic), names))
+    }
+
+    fn define_symbol(&mut self, name: &str, value: impl FnOnce(&mut Module)) -> IrResult<()> {
+        if self.module.symbol(name).is_some() {
+            return self.error(format!("redefinition of '@{}'", name));
+        }
+        value(&mut self.module);
+        Ok(())
+    }
+
+    /// 跳过 `{ ... }`
+    fn skip_braces(&mut self) -> IrResult<()> {
+        self.expect_punct('{')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Tok::Punct('{') => depth += 1,
+                Tok::Punct('}') => depth -= 1,
+                Tok::Eof => return self.error("unexpected end of file, expected '}'"),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn declare_symbols(&mut self) -> IrResult<()> {
+        loop {
+            match self.peek().clone() {
+                Tok::Eof => return Ok(()),
+                Tok::Global(name) => {
+                    self.next();
+                    self.expect_punct('=')?;
+                    let global = self.global_head(name.clone())?;
+                    let has_init = global.init.is_some();
+                    self.define_symbol(&name, |m| {
+                        m.add_global(global);
+                    })?;
+                    if has_init {
+                        self.skip_braces()?;
+                    }
+                }
+                Tok::Ident(x) if x == "declare" || x == "define" => {
+                    self.next();
+                    let linkage = self.linkage();
+                    let visibility = self.visibility();
+                    let inline = self.inline_attr();
+                    let (name, sig, _) = self.func_head()?;
+                    let mut func = Function::new(name.clone(), sig, linkage);
+                    func.visibility = visibility;
+                    func.inline = inline;
+                    self.define_symbol(&name, |m| {
+                        m.add_func(func);
+                    })?;
+                    if x == "define" {
+                        self.skip_braces()?;
+                    }
+                }
+                x => return self.error(format!("expected global or function, found {:?}", x)),
+            }
+        }
+    }
+
+    /// `[external] [internal] [hidden] [thread_local] (global | constant) [size, align n]`，有初始值时 init 为空 Vec
+    fn global_head(&mut self, name: String) -> IrResult<Global> {
+        let external = self.eat_ident("external");
+        let linkage = self.linkage();
+        let visibility = self.visibility();
+        let thread_local = self.eat_ident("thread_local");
+        let constant = match self.ident()?.as_str() {
+            "global" => false,
+            "constant" => true,
+            x => {
+                let msg = format!("expected 'global' or 'constant', found '{}'", x);
+                self.pos -= 1;
+                return self.error(msg);
+            }
+        };
+        let mut global = Global {
+            name,
+            size: 0,
+            align: 1,
+            init: None,
+            linkage,
+            visibility,
+            thread_local,
+            constant,
+        };
+        if !external {
+            global.size = self.uint()?;
+            self.expect_punct(',')?;
+            self.expect_ident("align")?;
+            global.align = self.uint()? as u32;
+            global.init = Some(Vec::new());
+        }
+        Ok(global)
+    }
+
+    fn parse_items(&mut self) -> IrResult<()> {
+        loop {
+            match self.peek().clone() {
+                Tok::Eof => return Ok(()),
+                Tok::Global(name) => {
+                    self.next();
+                    self.expect_punct('=')?;
+                    let head = self.global_head(name.clone())?;
+                    if head.init.is_some() {
+                        let init = self.global_init()?;
+                        let id = self.module.global_by_name(&name).expect("declared");
+                        self.module.globals[id].init = Some(init);
+                    }
+                }
+                Tok::Ident(x) if x == "declare" => {
+                    self.next();
+                    self.linkage();
+                    self.visibility();
+                    self.inline_attr();
+                    self.func_head()?;
+                }
+                Tok::Ident(x) if x == "define" => {
+                    self.next();
+                    self.linkage();
+                    self.visibility();
+                    self.inline_attr();
+                    let (name, _, names) = self.func_head()?;
+                    let id = self.module.func_by_name(&name).expect("declared");
+                    let mut func = std::mem::replace(
+                        &mut self.module.funcs[id],
+                        Function::new("", Signature::new(vec![], Type::Void, false), Linkage::External),
+                    );
+                    let result = self.func_body(&mut func, names);
+                    self.module.funcs[id] = func;
+                    result?;
+                }
+                x => return self.error(format!("expected global or function, found {:?}", x)),
+            }
+        }
+    }
+
+    /// `{ item, ... }`
+    fn global_init(&mut self) -> IrResult<Vec<InitItem>> {
+        self.expect_punct('{')?;
+        let mut items = Vec::new();
+        if self.eat_punct('}') {
+            return Ok(items);
+        }
+        loop {
+            let kind = self.ident()?;
+            let item = match kind.as_str() {
+                "bytes" => {
+                    self.expect_punct('[')?;
+                    let mut bytes = Vec::new();
+                    if !self.eat_punct(']') {
+                        loop {
+                            let byte = self.int()?;
+                            if !(-128..=255).contains(&byte) {
+                                self.pos -= 1;
+                                return self.error(format!("byte out of range: {}", byte));
+                            }
+                            bytes.push(byte as u8);
+                            if self.eat_punct(']') {
+                                break;
+                            }
+                            self.expect_punct(',')?;
+                        }
+                    }
+                    InitItem::Bytes(bytes)
+                }
+                "zero" => InitItem::Zero(self.uint()?),
+                "addr" => {
+                    let name = self.global_name()?;
+                    let Some(target) = self.module.symbol(&name) else {
+                        self.pos -= 1;
+                        return self.error(format!("use of undefined symbol '@{}'", name));
+                    };
+                    let addend = match self.peek() {
+                        Tok::Num(_) => self.int()?,
+                        _ => 0,
+                    };
+                    InitItem::Addr { target, addend }
+                }
+                x => {
+                    let msg = format!("unknown initializer '{}'", x);
+                    self.pos -= 1;
+                    return self.error(msg);
+                }
+            };
+            items.push(item);
+            if self.eat_punct('}') {
+                return Ok(items);
+            }
+            self.expect_punct(',')?;
+        }
+    }
+
+    /// 预扫描函数体，登记基本块和有名字的指令，使得它们可以先使用后定义
+    fn prescan_body(&mut self, func: &mut Function, locals: &mut Locals) -> IrResult<()> {
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            match self.peek().clone() {
+                Tok::Punct('{') => depth += 1,
+                Tok::Punct('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                Tok::Eof => return self.error("unexpected end of file, expected '}'"),
+                Tok::Ident(label) if *self.peek_at(1) == Tok::Punct(':') => {
+                    if locals.blocks.contains_key(&label) {
+                        return self.error(format!("redefinition of block '{}'", label));
+                    }
+                    locals.blocks.insert(label, func.add_block());
+                }
+                Tok::Local(name) if *self.peek_at(1) == Tok::Punct('=') => {
+                    if locals.values.contains_key(&name) {
+                        return self.error(format!("redefinition of '%{}'", name));
+                    }
+                    // 占位，解析到定义时再填入真正的内容
+                    let placeholder = InstData {
+                        kind: InstKind::Unreachable,
+                        ty: Type::Void,
+                    };
+                    let inst = func.insts.insert(placeholder);
+                    locals.values.insert(name, Value::Inst(inst));
+                }
+                _ => {}
+            }
+            self.next();
+        }
+        self.pos = start;
+        Ok(())
+    }
+
+    fn func_body(&mut self, func: &mut Function, names: Vec<Option<String>>) -> IrResult<()> {
+        let mut locals = Locals {
+            values: FxHashMap::default(),
+            blocks: FxHashMap::default(),
+        };
+        for (i, name) in names.into_iter().enumerate() {
+            let Some(name) = name else {
+                return self.error("parameter of a function definition must have a name");
+            };
+            locals.values.insert(name, Value::Arg(i as u32));
+        }
+        self.prescan_body(func, &mut locals)?;
+        self.expect_punct('{')?;
+
+        let mut block = None;
+        loop {
+            match self.peek().clone() {
+                Tok::Punct('}') => {
+                    self.next();
+                    break;
+                }
+                Tok::Ident(label) if *self.peek_at(1) == Tok::Punct(':') => {
+                    self.next();
+                    self.next();
+                    block = Some(locals.blocks[&label]);
+                }
+                _ => {
+                    let Some(block) = block else {
+                        return self.error("instruction outside of a basic block");
+                    };
+                    let result = match self.peek().clone() {
+                        Tok::Local(name) if *self.peek_at(1) == Tok::Punct('=') => {
+                            self.next();
+                            self.next();
+                            Some(name)
+                        }
+                        _ => None,
+                    };
+                    let data = self.inst(&locals)?;
+                    match result {
+                        Some(name) => {
+                            if data.ty.is_void() {
+                                return self.error(format!("'%{}' names an instruction without result", name));
+                            }
+                            let inst = locals.values[&name].as_inst().expect("reserved inst");
+                            func.insts[inst] = data;
+                            let pos = func.blocks[block].insts.len();
+                            func.attach_inst(block, pos, inst);
+                        }
+                        None => {
+                            func.append_inst(block, data);
+                        }
+                    }
+                }
+            }
+        }
+
+        if func.layout.is_empty() {
+            return self.error(format!("function '@{}' has no basic block", func.name));
+        }
+        // 只使用未定义的名字
+        let undefined: Vec<_> = locals
+            .values
+            .iter()
+            .filter(|(_, v)| matches!(v, Value::Inst(x) if func.inst_block(*x).is_none()))
+            .map(|(k, _)| k.clone())
+            .collect();
+        if let Some(name) = undefined.into_iter().min() {
+            return self.error(format!("use of undefined value '%{}'", name));
+        }
+        Ok(())
+    }
+
+    fn block_ref(&mut self, locals: &Locals) -> IrResult<BlockId> {
+        let label = self.ident()?;
+        match locals.blocks.get(&label) {
+            Some(x) => Ok(*x),
+            None => {
+                self.pos -= 1;
+                self.error(format!("use of undefined block '{}'", label))
+            }
+        }
+    }
+
+    /// 解析类型为 `ty` 的值
+    fn value(&mut self, ty: Type, locals: &Locals) -> IrResult<Value> {
+        let value = match self.next() {
+            Tok::Local(name) => match locals.values.get(&name) {
+                Some(x) => *x,
+                None => {
+                    self.pos -= 1;
+                    return self.error(format!("use of undefined value '%{}'", name));
+                }
+            },
+            Tok::Global(name) => match self.module.symbol(&name) {
+                Some(x) => x,
+                None => {
+                    self.pos -= 1;
+                    return self.error(format!("use of undefined symbol '@{}'", name));
+                }
+            },
+            Tok::Ident(x) if x == "null" => Value::null(),
+            Tok::Ident(x) if x == "undef" => Value::Undef(ty),
+            Tok::Ident(x) if x == "true" || x == "false" => Value::bool(x == "true"),
+            Tok::Num(text) if ty.is_float() => {
+                let value = match ty {
+                    Type::F32 => text.parse::<f32>().ok().map(Value::f32),
+                    _ => text.parse::<f64>().ok().map(Value::f64),
+                };
+                match value {
+                    Some(x) => x,
+                    None => {
+                        self.pos -= 1;
+                        return self.error(format!("invalid float '{}'", text));
+                    }
+                }
+            }
+            Tok::Num(text) if ty.is_int() || ty.is_ptr() => match parse_int(&text) {
+                Some(x) if ty.is_ptr() => Value::Int { ty, bits: x },
+                Some(x) => Value::int(ty, x as i64),
+                None => {
+                    self.pos -= 1;
+                    return self.error(format!("invalid integer '{}'", text));
+                }
+            },
+            x => {
+                self.pos -= 1;
+                return self.error(format!("expected value of type {}, found {:?}", ty, x));
+            }
+        };
+        Ok(value)
+    }
+
+    fn typed_value(&mut self, locals: &Locals) -> IrResult<Value> {
+        let ty = self.ty()?;
+        self.value(ty, locals)
+    }
+
+    fn sig(&mut self) -> IrResult<Signature> {
+        let ret = self.ty()?;
+        let (params, _, variadic) = self.params()?;
+        Ok(Signature::new(params, ret, variadic))
+    }
+
+    fn inst(&mut self, locals: &Locals) -> IrResult<InstData> {
+        let opcode = self.ident()?;
+        let name = opcode.as_str();
+        let (kind, ty) = if let Some(op) = BinaryOp::from_name(name) {
+            let nsw = self.eat_ident("nsw");
+            let ty = self.ty()?;
+            let lhs = self.value(ty, locals)?;
+            self.expect_punct(',')?;
+            let rhs = self.value(ty, locals)?;
+            (InstKind::Binary { op, lhs, rhs, nsw }, ty)
+        } else if let Some(op) = CastOp::from_name(name) {
+            let val = self.typed_value(locals)?;
+            self.expect_ident("to")?;
+            let ty = self.ty()?;
+            (InstKind::Cast { op, val }, ty)
+        } else {
+            match name {
+                "fneg" => {
+                    let ty = self.ty()?;
+                    let val = self.value(ty, locals)?;
+                    (InstKind::FNeg { val }, ty)
+                }
+                "icmp" | "fcmp" => {
+                    let pred_name = self.ident()?;
+                    let pred = match CmpPred::from_name(&pred_name) {
+                        Some(x) if x.is_float() == (name == "fcmp") => x,
+                        _ => {
+                            self.pos -= 1;
+                            return self.error(format!("invalid predicate '{}' for {}", pred_name, name));
+                        }
+                    };
+                    let ty = self.ty()?;
+                    let lhs = self.value(ty, locals)?;
+                    self.expect_punct(',')?;
+                    let rhs = self.value(ty, locals)?;
+                    (InstKind::Cmp { pred, lhs, rhs }, Type::I1)
+                }
+                "select" => {
+                    let cond = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    let ty = self.ty()?;
+                    let then_val = self.value(ty, locals)?;
+                    self.expect_punct(',')?;
+                    let else_val = self.value(ty, locals)?;
+                    let kind = InstKind::Select {
+                        cond,
+                        then_val,
+                        else_val,
+                    };
+                    (kind, ty)
+                }
+                "alloca" => {
+                    let size = self.uint()?;
+                    self.expect_punct(',')?;
+                    self.expect_ident("align")?;
+                    let align = self.uint()? as u32;
+                    (InstKind::Alloca { size, align }, Type::Ptr)
+                }
+                "load" => {
+                    let volatile = self.eat_ident("volatile");
+                    let ty = self.ty()?;
+                    self.expect_punct(',')?;
+                    let ptr = self.typed_value(locals)?;
+                    let tbaa = self.tbaa()?;
+                    let kind = InstKind::Load {
+                        ptr,
+                        volatile,
+                        tbaa,
+                    };
+                    (kind, ty)
+                }
+                "store" => {
+                    let volatile = self.eat_ident("volatile");
+                    let val = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    let ptr = self.typed_value(locals)?;
+                    let tbaa = self.tbaa()?;
+                    let kind = InstKind::Store {
+                        ptr,
+                        val,
+                        volatile,
+                        tbaa,
+                    };
+                    (kind, Type::Void)
+                }
+                "gep" => {
+                    let base = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    let index = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    self.expect_ident("scale")?;
+                    let scale = self.uint()?;
+                    self.expect_punct(',')?;
+                    self.expect_ident("offset")?;
+                    let offset = self.int()?;
+                    let kind = InstKind::Gep {
+                        base,
+                        index,
+                        scale,
+                        offset,
+                    };
+                    (kind, Type::Ptr)
+                }
+                "memcpy" => {
+                    let dst = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    let src = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    let size = self.uint()?;
+                    self.expect_punct(',')?;
+                    self.expect_ident("align")?;
+                    let align = self.uint()? as u32;
+                    let kind = InstKind::MemCopy {
+                        dst,
+                        src,
+                        size,
+                        align,
+                    };
+                    (kind, Type::Void)
+                }
+                "call" => {
+                    let sig = self.sig()?;
+                    let callee = self.value(Type::Ptr, locals)?;
+                    self.expect_punct('(')?;
+                    let mut args = Vec::new();
+                    if !self.eat_punct(')') {
+                        loop {
+                            args.push(self.typed_value(locals)?);
+                            if self.eat_punct(')') {
+                                break;
+                            }
+                            self.expect_punct(',')?;
+                        }
+                    }
+                    let ty = sig.ret;
+                    (InstKind::Call { sig, callee, args }, ty)
+                }
+                "phi" => {
+                    let ty = self.ty()?;
+                    let mut incomings = Vec::new();
+                    loop {
+                        self.expect_punct('[')?;
+                        let value = self.value(ty, locals)?;
+                        self.expect_punct(',')?;
+                        let block = self.block_ref(locals)?;
+                        self.expect_punct(']')?;
+                        incomings.push((block, value));
+                        if !self.eat_punct(',') {
+                            break;
+                        }
+                    }
+                    (InstKind::Phi { incomings }, ty)
+                }
+                "va_start" => (InstKind::VaStart { list: self.typed_value(locals)? }, Type::Void),
+                "va_end" => (InstKind::VaEnd { list: self.typed_value(locals)? }, Type::Void),
+                "va_arg" => {
+                    let ty = self.ty()?;
+                    self.expect_punct(',')?;
+                    let list = self.typed_value(locals)?;
+                    (InstKind::VaArg { list }, ty)
+                }
+                "va_copy" => {
+                    let dst = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    let src = self.typed_value(locals)?;
+                    (InstKind::VaCopy { dst, src }, Type::Void)
+                }
+                "br" if self.peek_is_type() => {
+                    let cond = self.typed_value(locals)?;
+                    self.expect_punct(',')?;
+                    let then_dest = self.block_ref(locals)?;
+                    self.expect_punct(',')?;
+                    let else_dest = self.block_ref(locals)?;
+                    let kind = InstKind::CondBr {
+                        cond,
+                        then_dest,
+                        else_dest,
+                    };
+                    (kind, Type::Void)
+                }
+                "br" => (InstKind::Br { dest: self.block_ref(locals)? }, Type::Void),
+                "switch" => {
+                    let ty = self.ty()?;
+                    let val = self.value(ty, locals)?;
+                    self.expect_punct(',')?;
+                    let default = self.block_ref(locals)?;
+                    self.expect_punct('[')?;
+                    let mut cases = Vec::new();
+                    if !self.eat_punct(']') {
+                        loop {
+                            let case = match self.value(ty, locals)? {
+                                Value::Int { bits, .. } => bits,
+                                _ => return self.error("switch case must be an integer"),
+                            };
+                            self.expect_punct(':')?;
+                            cases.push((case, self.block_ref(locals)?));
+                            if self.eat_punct(']') {
+                                break;
+                            }
+                            self.expect_punct(',')?;
+                        }
+                    }
+                    let kind = InstKind::Switch {
+                        val,
+                        default,
+                        cases,
+                    };
+                    (kind, Type::Void)
+                }
+                "ret" if self.eat_ident("void") => (InstKind::Ret { val: None }, Type::Void),
+                "ret" => {
+                    let val = self.typed_value(locals)?;
+                    (InstKind::Ret { val: Some(val) }, Type::Void)
+                }
+                "unreachable" => (InstKind::Unreachable, Type::Void),
+                _ => {
+                    self.pos -= 1;
+                    return self.error(format!("unknown instruction '{}'", opcode));
+                }
+            }
+        };
+        Ok(InstData { kind, ty })
+    }
+}
+
+/// 十进制或 `0x` 十六进制整数，负数按补码保存
+fn parse_int(text: &str) -> Option<u64> {
+    let (neg, digits) = match text.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<u64>().ok()?,
+    };
+    if neg {
+        if value > i64::MAX as u64 + 1 {
+            return None;
+        }
+        Some((value as i64).wrapping_neg() as u64)
+    } else {
+        Some(value)
+    }
+}
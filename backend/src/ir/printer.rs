@@ -0,0 +1,356 @@
+use crate::ir::function::{Function, InlineAttr};
+use crate::ir::inst::{InstKind, InstData};
+use crate::ir::module::{Global, InitItem, Linkage, Module, Visibility};
+use crate::ir::types::Type;
+use crate::ir::value::{sign_extend, BlockId, InstId, Value};
+use slotmap::SecondaryMap;
+use std::fmt::{Display, Formatter, Write};
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", print_module(self))
+    }
+}
+
+/// 输出文本格式的模块，格式见 `ir::parser`
+pub fn print_module(module: &Module) -> String {
+    let mut out = String::new();
+    for id in module.global_ids() {
+        print_global(&mut out, module, &module.globals[id]);
+    }
+    for id in module.func_ids() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        print_func(&mut out, module, &module.funcs[id]);
+    }
+    out
+}
+
+fn inline_attr(attr: InlineAttr) -> &'static str {
+    match attr {
+        InlineAttr::None => "",
+        InlineAttr::Hint => "inlinehint ",
+        InlineAttr::Always => "alwaysinline ",
+        InlineAttr::Never => "noinline ",
+    }
+}
+
+fn linkage(linkage: Linkage) -> &'static str {
+    match linkage {
+        Linkage::External => "",
+        Linkage::Internal => "internal ",
+        Linkage::Weak => "weak ",
+    }
+}
+
+fn visibility(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Default => "",
+        Visibility::Hidden => "hidden ",
+        Visibility::Protected => "protected ",
+    }
+}
+
+/// 类型标签，`0` 不输出
+fn tbaa_suffix(tbaa: u32) -> String {
+    match tbaa {
+        0 => String::new(),
+        x => format!(", tbaa {}", x),
+    }
+}
+
+fn print_global(out: &mut String, module: &Module, global: &Global) {
+    let kind = if global.constant { "constant" } else { "global" };
+    let tls = if global.thread_local {
+        "thread_local "
+    } else {
+        ""
+    };
+    let _ = write!(out, "@{} = ", global.name);
+    let Some(init) = &global.init else {
+        let _ = writeln!(
+            out,
+            "external {}{}{}",
+            visibility(global.visibility),
+            tls,
+            kind
+        );
+        return;
+    };
+    let _ = write!(
+        out,
+        "{}{}{}{} {}, align {} {{",
+        linkage(global.linkage),
+        visibility(global.visibility),
+        tls,
+        kind,
+        global.size,
+        global.align
+    );
+    for (i, item) in init.iter().enumerate() {
+        out.push_str(if i == 0 { " " } else { ", " });
+        match item {
+            InitItem::Bytes(bytes) => {
+                let bytes: Vec<_> = bytes.iter().map(|x| x.to_string()).collect();
+                let _ = write!(out, "bytes [{}]", bytes.join(", "));
+            }
+            InitItem::Zero(n) => {
+                let _ = write!(out, "zero {}", n);
+            }
+            InitItem::Addr { target, addend } => {
+                let _ = write!(out, "addr @{}", module.symbol_name(*target));
+                if *addend != 0 {
+                    let _ = write!(out, " {:+}", addend);
+                }
+            }
+        }
+    }
+    if !init.is_empty() {
+        out.push(' ');
+    }
+    out.push_str("}\n");
+}
+
+///
+/// 函数内的命名：参数为 `%aN`，有结果的指令按出现顺序为 `%N`，基本块为 `bbN`
+///
+pub struct FuncPrinter<'a> {
+    module: &'a Module,
+    func: &'a Function,
+    values: SecondaryMap<InstId, usize>,
+    blocks: SecondaryMap<BlockId, usize>,
+}
+
+impl<'a> FuncPrinter<'a> {
+    pub fn new(module: &'a Module, func: &'a Function) -> Self {
+        let mut values = SecondaryMap::new();
+        let mut blocks = SecondaryMap::new();
+        for (i, block) in func.layout.iter().enumerate() {
+            blocks.insert(*block, i);
+        }
+        for (_, inst) in func.inst_iter() {
+            if !func.insts[inst].ty.is_void() {
+                let n = values.len();
+                values.insert(inst, n);
+            }
+        }
+        Self {
+            module,
+            func,
+            values,
+            blocks,
+        }
+    }
+
+    pub fn block(&self, block: BlockId) -> String {
+        match self.blocks.get(block) {
+            Some(x) => format!("bb{}", x),
+            None => format!("<invalid block {:?}>", block),
+        }
+    }
+
+    /// 不带类型的值
+    pub fn value(&self, value: Value) -> String {
+        match value {
+            Value::Inst(x) => match self.values.get(x) {
+                Some(n) => format!("%{}", n),
+                None => format!("<invalid inst {:?}>", x),
+            },
+            Value::Arg(x) => format!("%a{}", x),
+            Value::Int { ty, bits } => match ty {
+                Type::I1 => (bits != 0).to_string(),
+                Type::Ptr if bits == 0 => "null".to_owned(),
+                Type::Ptr => bits.to_string(),
+                _ => sign_extend(bits, ty.bits(8)).to_string(),
+            },
+            Value::Float { ty, bits } => match ty {
+                Type::F32 => format!("{:?}", f32::from_bits(bits as u32)),
+                _ => format!("{:?}", f64::from_bits(bits)),
+            },
+            Value::Global(_) | Value::Func(_) => format!("@{}", self.module.symbol_name(value)),
+            Value::Undef(_) => "undef".to_owned(),
+        }
+    }
+
+    /// 带类型的值，如 `i32 %0`
+    pub fn typed(&self, value: Value) -> String {
+        format!("{} {}", self.func.value_type(value), self.value(value))
+    }
+
+    pub fn inst(&self, inst: InstId) -> String {
+        let data = &self.func.insts[inst];
+        let body = self.inst_body(data);
+        match self.values.get(inst) {
+            Some(n) => format!("%{} = {}", n, body),
+            None => body,
+        }
+    }
+
+    fn inst_body(&self, data: &InstData) -> String {
+        use InstKind::*;
+        let ty = data.ty;
+        match &data.kind {
+            Binary { op, lhs, rhs, nsw } => {
+                let nsw = if *nsw { "nsw " } else { "" };
+                format!("{} {}{} {}, {}", op, nsw, ty, self.value(*lhs), self.value(*rhs))
+            }
+            FNeg { val } => format!("fneg {}", self.typed(*val)),
+            Cmp { pred, lhs, rhs } => {
+                let cmp = if pred.is_float() { "fcmp" } else { "icmp" };
+                format!(
+                    "{} {} {}, {}",
+                    cmp,
+                    pred,
+                    self.typed(*lhs),
+                    self.value(*rhs)
+                )
+            }
+            Cast { op, val } => format!("{} {} to {}", op, self.typed(*val), ty),
+            Select {
+                cond,
+                then_val,
+                else_val,
+            } => format!(
+                "select {}, {}, {}",
+                self.typed(*cond),
+                self.typed(*then_val),
+                self.value(*else_val)
+            ),
+            Alloca { size, align } => format!("alloca {}, align {}", size, align),
+            Load {
+                ptr,
+                volatile,
+                tbaa,
+            } => {
+                let volatile = if *volatile { "volatile " } else { "" };
+                let ptr = self.typed(*ptr);
+                format!("load {}{}, {}{}", volatile, ty, ptr, tbaa_suffix(*tbaa))
+            }
+            Store {
+                ptr,
+                val,
+                volatile,
+                tbaa,
+            } => {
+                let volatile = if *volatile { "volatile " } else { "" };
+                let (val, ptr) = (self.typed(*val), self.typed(*ptr));
+                format!("store {}{}, {}{}", volatile, val, ptr, tbaa_suffix(*tbaa))
+            }
+            Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } => format!(
+                "gep {}, {}, scale {}, offset {}",
+                self.typed(*base),
+                self.typed(*index),
+                scale,
+                offset
+            ),
+            MemCopy {
+                dst,
+                src,
+                size,
+                align,
+            } => format!(
+                "memcpy {}, {}, {}, align {}",
+                self.typed(*dst),
+                self.typed(*src),
+                size,
+                align
+            ),
+            Call { sig, callee, args } => {
+                let args: Vec<_> = args.iter().map(|x| self.typed(*x)).collect();
+                format!("call {} {}({})", sig, self.value(*callee), args.join(", "))
+            }
+            Phi { incomings } => {
+                let incomings: Vec<_> = incomings
+                    .iter()
+                    .map(|(block, value)| format!("[{}, {}]", self.value(*value), self.block(*block)))
+                    .collect();
+                format!("phi {} {}", ty, incomings.join(", "))
+            }
+            VaStart { list } => format!("va_start {}", self.typed(*list)),
+            VaArg { list } => format!("va_arg {}, {}", ty, self.typed(*list)),
+            VaEnd { list } => format!("va_end {}", self.typed(*list)),
+            VaCopy { dst, src } => format!("va_copy {}, {}", self.typed(*dst), self.typed(*src)),
+            Br { dest } => format!("br {}", self.block(*dest)),
+            CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => format!(
+                "br {}, {}, {}",
+                self.typed(*cond),
+                self.block(*then_dest),
+                self.block(*else_dest)
+            ),
+            Switch {
+                val,
+                default,
+                cases,
+            } => {
+                let bits = self.func.value_type(*val).bits(8);
+                let cases: Vec<_> = cases
+                    .iter()
+                    .map(|(x, block)| format!("{}: {}", sign_extend(*x, bits), self.block(*block)))
+                    .collect();
+                format!(
+                    "switch {}, {} [{}]",
+                    self.typed(*val),
+                    self.block(*default),
+                    cases.join(", ")
+                )
+            }
+            Ret { val: Some(val) } => format!("ret {}", self.typed(*val)),
+            Ret { val: None } => "ret void".to_owned(),
+            Unreachable => "unreachable".to_owned(),
+        }
+    }
+}
+
+fn print_func(out: &mut String, module: &Module, func: &Function) {
+    let params: Vec<_> = func
+        .sig
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, x)| match func.is_declaration() {
+            true => x.to_string(),
+            false => format!("{} %a{}", x, i),
+        })
+        .chain(func.sig.variadic.then(|| "...".to_owned()))
+        .collect();
+    let head = format!("{} @{}({})", func.sig.ret, func.name, params.join(", "));
+
+    if func.is_declaration() {
+        let _ = writeln!(out, "declare {}{}", visibility(func.visibility), head);
+        return;
+    }
+
+    let _ = writeln!(
+        out,
+        "define {}{}{}{} {{",
+        linkage(func.linkage),
+        visibility(func.visibility),
+        inline_attr(func.inline),
+        head
+    );
+    let printer = FuncPrinter::new(module, func);
+    for block in func.layout.iter().cloned() {
+        let _ = writeln!(out, "{}:", printer.block(block));
+        for inst in func.blocks[block].insts.iter().cloned() {
+            let _ = writeln!(out, "    {}", printer.inst(inst));
+        }
+    }
+    out.push_str("}\n");
+}
+
+/// 单个函数的文本，调试用
+pub fn print_func_to_string(module: &Module, func: &Function) -> String {
+    let mut out = String::new();
+    print_func(&mut out, module, func);
+    out
+}
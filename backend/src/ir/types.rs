@@ -0,0 +1,274 @@
+use std::fmt::{Display, Formatter};
+
+///
+/// IR 中的标量类型，聚合类型（struct / array）在 IR 中只是一段内存，
+/// 通过 `alloca` 的大小、`gep` 的偏移和 `memcpy` 表达
+///
+/// `Ptr` 是不透明指针，宽度由目标决定
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    Void,
+    I1,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Ptr,
+}
+
+impl Type {
+    pub fn is_int(self) -> bool {
+        matches!(self, Type::I1 | Type::I8 | Type::I16 | Type::I32 | Type::I64)
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, Type::F32 | Type::F64)
+    }
+
+    pub fn is_ptr(self) -> bool {
+        self == Type::Ptr
+    }
+
+    pub fn is_void(self) -> bool {
+        self == Type::Void
+    }
+
+    /// 位宽，`ptr_bytes` 为目标指针宽度
+    pub fn bits(self, ptr_bytes: u32) -> u32 {
+        match self {
+            Type::Void => 0,
+            Type::I1 => 1,
+            Type::I8 => 8,
+            Type::I16 => 16,
+            Type::I32 | Type::F32 => 32,
+            Type::I64 | Type::F64 => 64,
+            Type::Ptr => ptr_bytes * 8,
+        }
+    }
+
+    /// 在内存中占用的字节数，`i1` 占一个字节
+    pub fn bytes(self, ptr_bytes: u32) -> u32 {
+        self.bits(ptr_bytes).div_ceil(8)
+    }
+
+    /// 给定位宽的整数类型
+    pub fn int(bits: u32) -> Option<Type> {
+        match bits {
+            1 => Some(Type::I1),
+            8 => Some(Type::I8),
+            16 => Some(Type::I16),
+            32 => Some(Type::I32),
+            64 => Some(Type::I64),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Type> {
+        let ty = match name {
+            "void" => Type::Void,
+            "i1" => Type::I1,
+            "i8" => Type::I8,
+            "i16" => Type::I16,
+            "i32" => Type::I32,
+            "i64" => Type::I64,
+            "f32" => Type::F32,
+            "f64" => Type::F64,
+            "ptr" => Type::Ptr,
+            _ => return None,
+        };
+        Some(ty)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Type::Void => "void",
+            Type::I1 => "i1",
+            Type::I8 => "i8",
+            Type::I16 => "i16",
+            Type::I32 => "i32",
+            Type::I64 => "i64",
+            Type::F32 => "f32",
+            Type::F64 => "f64",
+            Type::Ptr => "ptr",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// `AggShape` 最多记录的标量成员数
+pub const AGG_SHAPE_MAX: usize = 16;
+
+///
+/// 不超过 16 字节的聚合类型展开后的标量成员，按偏移排序，目标 ABI 据此决定用哪些寄存器传递
+///
+/// 为空时表示不知道成员的类型（或聚合类型太大），只能按内存传递；
+/// union 的成员会重叠，数组展开为每个元素
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AggShape {
+    len: u8,
+    fields: [(u8, Type); AGG_SHAPE_MAX],
+}
+
+impl Default for AggShape {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            fields: [(0, Type::Void); AGG_SHAPE_MAX],
+        }
+    }
+}
+
+impl AggShape {
+    /// 成员过多或偏移超过 255 时返回 None
+    pub fn new(fields: &[(u64, Type)]) -> Option<Self> {
+        let mut shape = Self::default();
+        if fields.len() > AGG_SHAPE_MAX {
+            return None;
+        }
+        let mut fields = fields.to_vec();
+        fields.sort_by_key(|x| x.0);
+        for (i, (offset, ty)) in fields.into_iter().enumerate() {
+            shape.fields[i] = (u8::try_from(offset).ok()?, ty);
+        }
+        shape.len = shape_len(&shape.fields);
+        Some(shape)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 成员的偏移和类型
+    pub fn fields(&self) -> &[(u8, Type)] {
+        &self.fields[..self.len as usize]
+    }
+}
+
+fn shape_len(fields: &[(u8, Type)]) -> u8 {
+    fields.iter().take_while(|x| x.1 != Type::Void).count() as u8
+}
+
+///
+/// 参数属性，描述聚合类型按值传递，ABI lowering 时使用
+/// - `ByVal`: 参数是指向一份拷贝的指针，被调用者拥有这份拷贝
+/// - `SRet`: 隐藏的返回值指针，返回聚合类型时使用
+///
+/// `shape` 是聚合类型的标量成员，目标可以据此把小的聚合类型放在寄存器中传递 / 返回
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ParamAttr {
+    #[default]
+    None,
+    ByVal {
+        size: u32,
+        align: u32,
+        shape: AggShape,
+    },
+    SRet {
+        size: u32,
+        align: u32,
+        shape: AggShape,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AbiParam {
+    pub ty: Type,
+    pub attr: ParamAttr,
+}
+
+impl AbiParam {
+    pub fn new(ty: Type) -> Self {
+        Self {
+            ty,
+            attr: ParamAttr::None,
+        }
+    }
+
+    pub fn with_attr(ty: Type, attr: ParamAttr) -> Self {
+        Self { ty, attr }
+    }
+}
+
+///
+/// 函数签名
+///
+/// # Members
+/// - `params`: 固定参数
+/// - `ret`: 返回类型，返回聚合类型时为 `void` 并且第一个参数为 `sret`
+/// - `variadic`: 是否是变参函数
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Signature {
+    pub params: Vec<AbiParam>,
+    pub ret: Type,
+    pub variadic: bool,
+}
+
+impl Signature {
+    pub fn new(params: Vec<AbiParam>, ret: Type, variadic: bool) -> Self {
+        Self {
+            params,
+            ret,
+            variadic,
+        }
+    }
+}
+
+impl Display for AbiParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ty)?;
+        match self.attr {
+            ParamAttr::None => Ok(()),
+            ParamAttr::ByVal { size, align, shape } => {
+                write!(f, " byval({}, {}{})", size, align, shape)
+            }
+            ParamAttr::SRet { size, align, shape } => {
+                write!(f, " sret({}, {}{})", size, align, shape)
+            }
+        }
+    }
+}
+
+impl Display for Signature {
+    /// `ret (params, ...)`
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (", self.ret)?;
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", param)?;
+        }
+        if self.variadic {
+            if !self.params.is_empty() {
+                write!(f, ", ")?;
+            }
+            write!(f, "...")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Display for AggShape {
+    /// `, {f64 0, i32 8}`，为空时不输出
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        write!(f, ", {{")?;
+        for (i, (offset, ty)) in self.fields().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {}", ty, offset)?;
+        }
+        write!(f, "}}")
+    }
+}
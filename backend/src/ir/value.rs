@@ -0,0 +1,114 @@
+use crate::ir::types::Type;
+use slotmap::new_key_type;
+
+new_key_type! {
+    pub struct InstId;
+    pub struct BlockId;
+    pub struct GlobalId;
+    pub struct FuncId;
+}
+
+///
+/// 指令的操作数
+///
+/// - `Inst`: 指令的结果
+/// - `Arg`: 当前函数的第 n 个参数
+/// - `Int`: 整数常量（包括 `ptr null`），`bits` 按类型截断后零扩展保存
+/// - `Float`: 浮点常量，`bits` 是 IEEE 754 位模式，`f32` 保存在低 32 位
+/// - `Global` `Func`: 全局变量 / 函数的地址，类型都是 `ptr`
+/// - `Undef`: 未定义值
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Value {
+    Inst(InstId),
+    Arg(u32),
+    Int { ty: Type, bits: u64 },
+    Float { ty: Type, bits: u64 },
+    Global(GlobalId),
+    Func(FuncId),
+    Undef(Type),
+}
+
+impl Value {
+    /// 整数常量，超出位宽的部分被截断
+    pub fn int(ty: Type, value: i64) -> Value {
+        debug_assert!(ty.is_int() || ty.is_ptr());
+        Value::Int {
+            ty,
+            bits: truncate(value as u64, ty.bits(8)),
+        }
+    }
+
+    pub fn bool(value: bool) -> Value {
+        Value::int(Type::I1, value as i64)
+    }
+
+    pub fn null() -> Value {
+        Value::Int {
+            ty: Type::Ptr,
+            bits: 0,
+        }
+    }
+
+    pub fn f32(value: f32) -> Value {
+        Value::Float {
+            ty: Type::F32,
+            bits: value.to_bits() as u64,
+        }
+    }
+
+    pub fn f64(value: f64) -> Value {
+        Value::Float {
+            ty: Type::F64,
+            bits: value.to_bits(),
+        }
+    }
+
+    pub fn is_const(&self) -> bool {
+        matches!(self, Value::Int { .. } | Value::Float { .. } | Value::Undef(_))
+    }
+
+    /// 整数常量按有符号解释
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            Value::Int { ty, bits } => Some(sign_extend(bits, ty.bits(8))),
+            _ => None,
+        }
+    }
+
+    /// 整数常量按无符号解释
+    pub fn as_uint(&self) -> Option<u64> {
+        match *self {
+            Value::Int { bits, .. } => Some(bits),
+            _ => None,
+        }
+    }
+
+    pub fn as_inst(&self) -> Option<InstId> {
+        match *self {
+            Value::Inst(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+/// 截断到 `bits` 位
+pub fn truncate(value: u64, bits: u32) -> u64 {
+    match bits {
+        0 => 0,
+        64.. => value,
+        _ => value & ((1u64 << bits) - 1),
+    }
+}
+
+/// 把低 `bits` 位符号扩展到 64 位
+pub fn sign_extend(value: u64, bits: u32) -> i64 {
+    match bits {
+        0 => 0,
+        64.. => value as i64,
+        _ => {
+            let shift = 64 - bits;
+            ((value << shift) as i64) >> shift
+        }
+    }
+}
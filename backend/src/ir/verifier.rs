@@ -0,0 +1,302 @@
+use crate::err::ir_error::{IrError, IrResult};
+use crate::ir::function::Function;
+use crate::ir::inst::{CastOp, InstKind};
+use crate::ir::module::{InitItem, Module};
+use crate::ir::printer::FuncPrinter;
+use crate::ir::types::Type;
+use crate::ir::value::{BlockId, InstId, Value};
+use rustc_hash::FxHashSet;
+use slotmap::SecondaryMap;
+
+/// 校验整个模块
+pub fn verify_module(module: &Module) -> IrResult<()> {
+    for id in module.global_ids() {
+        let global = &module.globals[id];
+        let error = |msg: String| IrError::VerifyGlobal {
+            global: global.name.clone(),
+            msg,
+        };
+        if !global.align.is_power_of_two() {
+            return Err(error(format!("alignment {} is not a power of two", global.align)));
+        }
+        for item in global.init.iter().flatten() {
+            if let InitItem::Addr { target, .. } = item
+                && !symbol_exists(module, *target)
+            {
+                return Err(error(format!("initializer refers to unknown symbol {:?}", target)));
+            }
+        }
+    }
+    for id in module.func_ids() {
+        verify_function(module, &module.funcs[id])?;
+    }
+    Ok(())
+}
+
+fn symbol_exists(module: &Module, value: Value) -> bool {
+    match value {
+        Value::Global(x) => module.globals.contains_key(x),
+        Value::Func(x) => module.funcs.contains_key(x),
+        _ => false,
+    }
+}
+
+///
+/// 结构校验：
+/// - 每个基本块以且仅以一个终结指令结尾，phi 都在块的开头
+/// - 操作数都已定义，同一个块中先定义后使用，类型与指令匹配
+/// - phi 的入边与前驱一一对应
+/// - 跳转目标都在函数中
+///
+/// 不校验跨基本块的支配关系（定义支配使用），这需要支配树
+///
+pub fn verify_function(module: &Module, func: &Function) -> IrResult<()> {
+    if func.is_declaration() {
+        return Ok(());
+    }
+    Verifier {
+        module,
+        func,
+        printer: FuncPrinter::new(module, func),
+        blocks: func.layout.iter().cloned().collect(),
+        preds: func.predecessors(),
+    }
+    .verify()
+}
+
+struct Verifier<'a> {
+    module: &'a Module,
+    func: &'a Function,
+    printer: FuncPrinter<'a>,
+    blocks: FxHashSet<BlockId>,
+    preds: SecondaryMap<BlockId, Vec<BlockId>>,
+}
+
+impl Verifier<'_> {
+    fn error(&self, inst: Option<InstId>, msg: impl Into<String>) -> IrError {
+        let msg = match inst {
+            Some(inst) => format!("'{}': {}", self.printer.inst(inst), msg.into()),
+            None => msg.into(),
+        };
+        IrError::Verify {
+            func: self.func.name.clone(),
+            msg,
+        }
+    }
+
+    fn verify(&self) -> IrResult<()> {
+        let func = self.func;
+        let mut seen = FxHashSet::default();
+        for block in func.layout.iter().cloned() {
+            if !seen.insert(block) {
+                return Err(self.error(None, format!("{} appears twice in layout", self.printer.block(block))));
+            }
+            let insts = &func.blocks[block].insts;
+            let Some(last) = insts.last() else {
+                return Err(self.error(None, format!("{} is empty", self.printer.block(block))));
+            };
+            if !func.insts[*last].kind.is_terminator() {
+                return Err(self.error(Some(*last), "block does not end with a terminator"));
+            }
+
+            let mut defined = FxHashSet::default();
+            let mut phi_area = true;
+            for (i, inst) in insts.iter().cloned().enumerate() {
+                let kind = &func.insts[inst].kind;
+                if func.inst_block(inst) != Some(block) {
+                    return Err(self.error(Some(inst), "instruction block mismatch"));
+                }
+                if kind.is_terminator() && i + 1 != insts.len() {
+                    return Err(self.error(Some(inst), "terminator in the middle of a block"));
+                }
+                if kind.is_phi() && !phi_area {
+                    return Err(self.error(Some(inst), "phi is not at the beginning of the block"));
+                }
+                phi_area &= kind.is_phi();
+                self.verify_operands(block, inst, &defined)?;
+                self.verify_inst(block, inst)?;
+                defined.insert(inst);
+            }
+        }
+        Ok(())
+    }
+
+    /// 操作数存在，同一块中的非 phi 指令只能使用之前定义的值
+    fn verify_operands(&self, block: BlockId, inst: InstId, defined: &FxHashSet<InstId>) -> IrResult<()> {
+        let func = self.func;
+        let kind = &func.insts[inst].kind;
+        for operand in kind.operands() {
+            match operand {
+                Value::Inst(x) => {
+                    let Some(def_block) = func.insts.get(x).and(func.inst_block(x)) else {
+                        return Err(self.error(Some(inst), "use of a deleted instruction"));
+                    };
+                    if func.insts[x].ty.is_void() {
+                        return Err(self.error(Some(inst), "use of an instruction without result"));
+                    }
+                    if x == inst && !kind.is_phi() {
+                        return Err(self.error(Some(inst), "instruction uses itself"));
+                    }
+                    if def_block == block && !kind.is_phi() && !defined.contains(&x) {
+                        return Err(self.error(Some(inst), "use before definition"));
+                    }
+                }
+                Value::Arg(x) if x as usize >= func.sig.params.len() => {
+                    return Err(self.error(Some(inst), format!("argument %a{} out of range", x)));
+                }
+                Value::Global(_) | Value::Func(_) if !symbol_exists(self.module, operand) => {
+                    return Err(self.error(Some(inst), "use of an unknown symbol"));
+                }
+                _ => {}
+            }
+        }
+        for succ in kind.successors() {
+            if !self.blocks.contains(&succ) {
+                return Err(self.error(Some(inst), "branch to a block not in the function"));
+            }
+        }
+        Ok(())
+    }
+
+    fn ty(&self, value: Value) -> Type {
+        self.func.value_type(value)
+    }
+
+    fn verify_inst(&self, block: BlockId, inst: InstId) -> IrResult<()> {
+        use InstKind::*;
+        let data = &self.func.insts[inst];
+        let ty = data.ty;
+        let check = |ok: bool, msg: &str| match ok {
+            true => Ok(()),
+            false => Err(self.error(Some(inst), msg)),
+        };
+
+        match &data.kind {
+            Binary { op, lhs, rhs, nsw } => {
+                check(self.ty(*lhs) == ty && self.ty(*rhs) == ty, "operand type mismatch")?;
+                check(!nsw || op.can_be_nsw(), "nsw is only allowed on add, sub and mul")?;
+                match op.is_float() {
+                    true => check(ty.is_float(), "float operation on non-float type"),
+                    false => check(ty.is_int(), "integer operation on non-integer type"),
+                }
+            }
+            FNeg { val } => check(ty.is_float() && self.ty(*val) == ty, "fneg requires float"),
+            Cmp { pred, lhs, rhs } => {
+                let op_ty = self.ty(*lhs);
+                check(op_ty == self.ty(*rhs), "operand type mismatch")?;
+                check(ty == Type::I1, "compare result must be i1")?;
+                match pred.is_float() {
+                    true => check(op_ty.is_float(), "fcmp on non-float type"),
+                    false => check(op_ty.is_int() || op_ty.is_ptr(), "icmp on non-integer type"),
+                }
+            }
+            Cast { op, val } => {
+                let from = self.ty(*val);
+                check(cast_is_valid(*op, from, ty), &format!("invalid cast from {} to {}", from, ty))
+            }
+            Select {
+                cond,
+                then_val,
+                else_val,
+            } => {
+                check(self.ty(*cond) == Type::I1, "select condition must be i1")?;
+                check(
+                    self.ty(*then_val) == ty && self.ty(*else_val) == ty,
+                    "select operand type mismatch",
+                )
+            }
+            Alloca { align, .. } => {
+                check(ty == Type::Ptr, "alloca result must be ptr")?;
+                check(align.is_power_of_two(), "alignment must be a power of two")
+            }
+            Load { ptr, .. } => {
+                check(self.ty(*ptr) == Type::Ptr, "load address must be ptr")?;
+                check(!ty.is_void(), "load of void")
+            }
+            Store { ptr, val, .. } => {
+                check(self.ty(*ptr) == Type::Ptr, "store address must be ptr")?;
+                check(!self.ty(*val).is_void(), "store of void")
+            }
+            Gep { base, index, .. } => {
+                check(self.ty(*base) == Type::Ptr, "gep base must be ptr")?;
+                check(self.ty(*index).is_int(), "gep index must be an integer")
+            }
+            MemCopy { dst, src, align, .. } => {
+                check(self.ty(*dst) == Type::Ptr && self.ty(*src) == Type::Ptr, "memcpy operands must be ptr")?;
+                check(align.is_power_of_two(), "alignment must be a power of two")
+            }
+            Call { sig, callee, args } => {
+                check(self.ty(*callee) == Type::Ptr, "callee must be ptr")?;
+                check(ty == sig.ret, "call result type mismatch")?;
+                let fixed = sig.params.len();
+                match sig.variadic {
+                    true => check(args.len() >= fixed, "too few arguments")?,
+                    false => check(args.len() == fixed, "argument count mismatch")?,
+                }
+                for (arg, param) in args.iter().zip(sig.params.iter()) {
+                    check(self.ty(*arg) == param.ty, "argument type mismatch")?;
+                }
+                for arg in args.iter().skip(fixed) {
+                    check(!self.ty(*arg).is_void(), "void variadic argument")?;
+                }
+                if let Value::Func(f) = callee {
+                    check(self.module.funcs[*f].sig == *sig, "call signature does not match callee")?;
+                }
+                Ok(())
+            }
+            Phi { incomings } => {
+                let preds = &self.preds[block];
+                let mut seen = FxHashSet::default();
+                for (pred, value) in incomings {
+                    check(self.ty(*value) == ty, "phi incoming type mismatch")?;
+                    check(preds.contains(pred), "phi incoming block is not a predecessor")?;
+                    check(seen.insert(*pred), "duplicate phi incoming block")?;
+                }
+                check(seen.len() == preds.len(), "phi is missing an incoming block")
+            }
+            VaStart { list } => {
+                check(self.func.sig.variadic, "va_start in non-variadic function")?;
+                check(self.ty(*list) == Type::Ptr, "va_list must be ptr")
+            }
+            VaArg { list } => {
+                check(!ty.is_void(), "va_arg of void")?;
+                check(self.ty(*list) == Type::Ptr, "va_list must be ptr")
+            }
+            VaEnd { list } => check(self.ty(*list) == Type::Ptr, "va_list must be ptr"),
+            VaCopy { dst, src } => check(
+                self.ty(*dst) == Type::Ptr && self.ty(*src) == Type::Ptr,
+                "va_list must be ptr",
+            ),
+            Br { .. } | Unreachable => Ok(()),
+            CondBr { cond, .. } => check(self.ty(*cond) == Type::I1, "branch condition must be i1"),
+            Switch { val, cases, .. } => {
+                check(self.ty(*val).is_int(), "switch value must be an integer")?;
+                let mut seen = FxHashSet::default();
+                for (case, _) in cases {
+                    check(seen.insert(*case), "duplicate switch case")?;
+                }
+                Ok(())
+            }
+            Ret { val } => {
+                let ret = val.map(|x| self.ty(x)).unwrap_or(Type::Void);
+                check(ret == self.func.sig.ret, "return type mismatch")
+            }
+        }
+    }
+}
+
+fn cast_is_valid(op: CastOp, from: Type, to: Type) -> bool {
+    use CastOp::*;
+    let size = |ty: Type| ty.bits(8);
+    match op {
+        Trunc => from.is_int() && to.is_int() && size(from) > size(to),
+        ZExt | SExt => from.is_int() && to.is_int() && size(from) < size(to),
+        FpToSi | FpToUi => from.is_float() && to.is_int(),
+        SiToFp | UiToFp => from.is_int() && to.is_float(),
+        FpExt => from == Type::F32 && to == Type::F64,
+        FpTrunc => from == Type::F64 && to == Type::F32,
+        PtrToInt => from.is_ptr() && to.is_int(),
+        IntToPtr => from.is_int() && to.is_ptr(),
+        Bitcast => !from.is_void() && !to.is_void() && !from.is_ptr() && !to.is_ptr() && size(from) == size(to),
+    }
+}
@@ -1,14 +1,19 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+/// rcc 的中端与后端，前端（rcc）把 AST lowering 到这里的 IR
+/// # Contents
+/// - `ir`: SSA IR，包括模块、函数、基本块、指令、全局变量，文本格式的 parser 和 printer，以及 verifier
+/// - `interp`: IR 解释器
+/// - `opt`: IR 优化 pass 和 pass manager
+/// - `target`: 目标平台的数据模型
+/// - `codegen`: 代码生成
+/// - `object`: 与格式无关的目标文件，ELF 的读写和静态链接
+/// - `err`: 错误类型
+pub mod codegen;
+pub mod err;
+pub mod interp;
+pub mod ir;
+pub mod object;
+pub mod opt;
+pub mod target;
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+mod tests;
@@ -0,0 +1,294 @@
+/// 与文件格式无关的目标文件：节、符号、重定位，以及静态链接
+/// # Contents
+/// - `elf`: ELF64 可重定位目标文件的读写
+/// - `crt`: 不依赖 C 库的启动代码
+/// - `link`: 静态链接器，输出 ELF 可执行文件或共享库
+pub mod crt;
+pub mod elf;
+pub mod link;
+
+use crate::ir::Visibility;
+use crate::target::Arch;
+
+/// 节的种类，决定 ELF 的节类型和标志
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+    /// 代码，可读可执行
+    Text,
+    /// 可读写的数据
+    Data,
+    /// 只读数据
+    ReadOnly,
+    /// 全零的可读写数据，不占文件空间
+    Bss,
+    /// 线程局部变量的初始值 `.tdata`，每个线程复制一份
+    Tls,
+    /// 全零的线程局部变量 `.tbss`
+    TlsBss,
+    /// 调试信息，不加载到内存
+    Debug,
+}
+
+impl SectionKind {
+    /// 没有内容、只有大小的节
+    pub fn is_bss(self) -> bool {
+        matches!(self, SectionKind::Bss | SectionKind::TlsBss)
+    }
+
+    /// 线程局部变量的节
+    pub fn is_tls(self) -> bool {
+        matches!(self, SectionKind::Tls | SectionKind::TlsBss)
+    }
+}
+
+///
+/// 节
+///
+/// # Members
+/// - `name`: 节名，如 `.text`
+/// - `kind`: 种类
+/// - `align`: 对齐
+/// - `data`: 内容，`Bss` `TlsBss` 没有内容
+/// - `bss_size`: `Bss` `TlsBss` 的大小
+/// - `relocs`: 这个节中的重定位
+///
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    pub kind: SectionKind,
+    pub align: u64,
+    pub data: Vec<u8>,
+    pub bss_size: u64,
+    pub relocs: Vec<Reloc>,
+}
+
+impl Section {
+    pub fn new(name: impl Into<String>, kind: SectionKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            align: 1,
+            data: Vec::new(),
+            bss_size: 0,
+            relocs: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        match self.kind.is_bss() {
+            true => self.bss_size,
+            false => self.data.len() as u64,
+        }
+    }
+
+    /// 对齐当前位置，代码用 `fill` 填充，返回对齐后的位置
+    pub fn align_to(&mut self, align: u64, fill: u8) -> u64 {
+        self.align = self.align.max(align);
+        let size = self.size().next_multiple_of(align);
+        match self.kind.is_bss() {
+            true => self.bss_size = size,
+            false => self.data.resize(size as usize, fill),
+        }
+        size
+    }
+}
+
+/// 符号的绑定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Local,
+    Global,
+    Weak,
+}
+
+/// 符号的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    NoType,
+    Func,
+    Object,
+    /// 节符号，名字是节名，用于引用节内的偏移
+    Section,
+    /// 线程局部变量，值是在线程局部存储块中的偏移
+    Tls,
+}
+
+///
+/// 符号
+///
+/// # Members
+/// - `name`: 符号名
+/// - `binding` `kind`: 绑定和类型
+/// - `visibility`: 可见性，只对链接共享库有意义
+/// - `section`: 定义所在的节，None 表示未定义
+/// - `value`: 在节中的偏移
+/// - `size`: 大小
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub binding: Binding,
+    pub kind: SymbolKind,
+    pub visibility: Visibility,
+    pub section: Option<usize>,
+    pub value: u64,
+    pub size: u64,
+}
+
+///
+/// 重定位的计算方式，S 是符号地址，A 是加数，P 是被修改的位置
+/// - `Abs64`: S + A，8 字节
+/// - `Abs32`: S + A，4 字节，结果必须能零扩展回原值，用于调试信息中节内的偏移
+/// - `Pc32`: S + A - P，4 字节
+/// - `Plt32`: 调用，静态链接时和 `Pc32` 相同，链接共享库时外部符号经过 PLT
+/// - `GotPcRel`: G + GOT + A - P，4 字节，G + GOT 是存放符号地址的 GOT 项
+/// - `TpOff32`: 线程局部变量相对线程指针的偏移加 A，4 字节，local-exec 模型
+/// - `GotTpOff`: G + GOT + A - P，4 字节，GOT 项存放相对线程指针的偏移，initial-exec 模型
+/// - `TlsGd`: G + GOT + A - P，4 字节，GOT 中连续两项是模块编号和块内偏移，作为 `__tls_get_addr`
+///   的参数，general-dynamic 模型
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RelocKind {
+    Abs64,
+    Abs32,
+    Pc32,
+    Plt32,
+    GotPcRel,
+    TpOff32,
+    GotTpOff,
+    TlsGd,
+}
+
+impl RelocKind {
+    /// 被修改的字节数
+    pub fn size(self) -> usize {
+        match self {
+            RelocKind::Abs64 => 8,
+            _ => 4,
+        }
+    }
+}
+
+///
+/// 重定位
+///
+/// # Members
+/// - `offset`: 在所在节中的偏移
+/// - `symbol`: 符号的下标
+/// - `kind`: 计算方式
+/// - `addend`: 加数
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reloc {
+    pub offset: u64,
+    pub symbol: usize,
+    pub kind: RelocKind,
+    pub addend: i64,
+}
+
+///
+/// 目标文件
+///
+/// # Members
+/// - `arch`: 目标架构
+/// - `sections`: 节
+/// - `symbols`: 符号，生成的目标文件中名字唯一，读入的目标文件中局部符号可能重名
+/// - `comment`: 写入 `.comment` 的编译器标识
+///
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub arch: Arch,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub comment: String,
+}
+
+impl Object {
+    /// 包含 `.text` `.data` `.bss` `.rodata` 四个节，线程局部变量的节用到时再添加
+    pub fn new(arch: Arch) -> Self {
+        let sections = vec![
+            Section::new(".text", SectionKind::Text),
+            Section::new(".data", SectionKind::Data),
+            Section::new(".bss", SectionKind::Bss),
+            Section::new(".rodata", SectionKind::ReadOnly),
+        ];
+        Self {
+            arch,
+            sections,
+            symbols: Vec::new(),
+            comment: format!("rcc {}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// 某种节的下标，没有时新建，调试信息的节按名字查找，见 `debug_section`
+    pub fn section(&mut self, kind: SectionKind) -> usize {
+        if let Some(index) = self.sections.iter().position(|x| x.kind == kind) {
+            return index;
+        }
+        let name = match kind {
+            SectionKind::Text => ".text",
+            SectionKind::Data => ".data",
+            SectionKind::ReadOnly => ".rodata",
+            SectionKind::Bss => ".bss",
+            SectionKind::Tls => ".tdata",
+            SectionKind::TlsBss => ".tbss",
+            SectionKind::Debug => unreachable!("debug sections are looked up by name"),
+        };
+        self.sections.push(Section::new(name, kind));
+        self.sections.len() - 1
+    }
+
+    /// 名为 `name` 的调试信息节的下标，没有时新建
+    pub fn debug_section(&mut self, name: &str) -> usize {
+        if let Some(index) = self.sections.iter().position(|x| x.name == name) {
+            return index;
+        }
+        self.sections.push(Section::new(name, SectionKind::Debug));
+        self.sections.len() - 1
+    }
+
+    /// 节符号的下标，没有时添加
+    pub fn section_symbol(&mut self, section: usize) -> usize {
+        let found = self
+            .symbols
+            .iter()
+            .position(|x| x.kind == SymbolKind::Section && x.section == Some(section));
+        if let Some(index) = found {
+            return index;
+        }
+        self.symbols.push(Symbol {
+            name: self.sections[section].name.clone(),
+            binding: Binding::Local,
+            kind: SymbolKind::Section,
+            visibility: Visibility::Default,
+            section: Some(section),
+            value: 0,
+            size: 0,
+        });
+        self.symbols.len() - 1
+    }
+
+    /// 符号的下标，没有时添加一个未定义的全局符号
+    pub fn symbol(&mut self, name: &str) -> usize {
+        if let Some(index) = self.symbols.iter().position(|x| x.name == name) {
+            return index;
+        }
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            binding: Binding::Global,
+            kind: SymbolKind::NoType,
+            visibility: Visibility::Default,
+            section: None,
+            value: 0,
+            size: 0,
+        });
+        self.symbols.len() - 1
+    }
+
+    /// 定义符号，之前作为未定义符号引用过时更新它
+    pub fn define(&mut self, symbol: Symbol) -> usize {
+        let index = self.symbol(&symbol.name);
+        self.symbols[index] = symbol;
+        index
+    }
+}
@@ -0,0 +1,118 @@
+use crate::ir::Visibility;
+use crate::object::{Binding, Object, Reloc, RelocKind, SectionKind, Symbol, SymbolKind};
+use crate::target::Arch;
+
+///
+/// x86-64 Linux 的启动代码，不依赖 C 库。有 `PT_TLS` 段时在栈上分配主线程的线程局部存储块，
+/// 复制初始值、清零其余部分，并用 `arch_prctl(ARCH_SET_FS)` 设置线程指针，
+/// 线程指针指向块的末尾，那里存放线程指针自身（`%fs:0`）：
+///
+/// ```text
+/// _start:
+///     xorl %ebp, %ebp
+///     movq %rsp, %r12                 ; argc 所在的位置
+///     andq $-16, %rsp
+///     leaq __ehdr_start(%rip), %rax   ; 链接器定义，加载的文件头
+///     movq 32(%rax), %rbx             ; e_phoff
+///     addq %rax, %rbx
+///     movzwl 56(%rax), %ecx           ; e_phnum
+/// 1:  testl %ecx, %ecx
+///     jz 3f
+///     cmpl $7, (%rbx)                 ; PT_TLS
+///     je 2f
+///     addq $56, %rbx
+///     decl %ecx
+///     jmp 1b
+/// 2:  movq 48(%rbx), %r8              ; p_align，至少 1
+///     movl $1, %eax
+///     cmpq %rax, %r8
+///     cmovbq %rax, %r8
+///     leaq -1(%r8), %r9
+///     notq %r9
+///     movq 40(%rbx), %rcx             ; p_memsz 按对齐向上取整
+///     leaq -1(%r8,%rcx), %rcx
+///     andq %r9, %rcx
+///     leaq -16(%rsp), %r13            ; 线程指针
+///     andq %r9, %r13
+///     movq %r13, %rdi
+///     subq %rcx, %rdi
+///     movq %rdi, %rsp
+///     xorl %eax, %eax
+///     rep stosb
+///     movq %rsp, %rdi
+///     movq 16(%rbx), %rsi             ; p_vaddr
+///     movq 32(%rbx), %rcx             ; p_filesz
+///     rep movsb
+///     movq %r13, (%r13)
+///     movl $158, %eax                 ; arch_prctl
+///     movl $0x1002, %edi              ; ARCH_SET_FS
+///     movq %r13, %rsi
+///     syscall
+/// 3:  andq $-16, %rsp
+///     movq (%r12), %rdi               ; argc
+///     leaq 8(%r12), %rsi              ; argv
+///     leaq 8(%rsi,%rdi,8), %rdx       ; envp
+///     call main
+///     movl %eax, %edi
+///     call exit
+/// exit:                               ; 弱符号，exit_group
+///     movl $231, %eax
+///     syscall
+///     hlt
+/// write:                              ; 弱符号，write(fd, buf, len)
+///     movl $1, %eax
+///     syscall
+///     ret
+/// ```
+///
+const X86_64_START: [u8; 178] = [
+    0x31, 0xed, 0x49, 0x89, 0xe4, 0x48, 0x83, 0xe4, 0xf0, 0x48, 0x8d, 0x05, 0x00, 0x00, 0x00, 0x00,
+    0x48, 0x8b, 0x58, 0x20, 0x48, 0x01, 0xc3, 0x0f, 0xb7, 0x48, 0x38, 0x85, 0xc9, 0x74, 0x65, 0x83,
+    0x3b, 0x07, 0x74, 0x08, 0x48, 0x83, 0xc3, 0x38, 0xff, 0xc9, 0xeb, 0xef, 0x4c, 0x8b, 0x43, 0x30,
+    0xb8, 0x01, 0x00, 0x00, 0x00, 0x49, 0x39, 0xc0, 0x4c, 0x0f, 0x42, 0xc0, 0x4d, 0x8d, 0x48, 0xff,
+    0x49, 0xf7, 0xd1, 0x48, 0x8b, 0x4b, 0x28, 0x49, 0x8d, 0x4c, 0x08, 0xff, 0x4c, 0x21, 0xc9, 0x4c,
+    0x8d, 0x6c, 0x24, 0xf0, 0x4d, 0x21, 0xcd, 0x4c, 0x89, 0xef, 0x48, 0x29, 0xcf, 0x48, 0x89, 0xfc,
+    0x31, 0xc0, 0xf3, 0xaa, 0x48, 0x89, 0xe7, 0x48, 0x8b, 0x73, 0x10, 0x48, 0x8b, 0x4b, 0x20, 0xf3,
+    0xa4, 0x4d, 0x89, 0x6d, 0x00, 0xb8, 0x9e, 0x00, 0x00, 0x00, 0xbf, 0x02, 0x10, 0x00, 0x00, 0x4c,
+    0x89, 0xee, 0x0f, 0x05, 0x48, 0x83, 0xe4, 0xf0, 0x49, 0x8b, 0x3c, 0x24, 0x49, 0x8d, 0x74, 0x24,
+    0x08, 0x48, 0x8d, 0x54, 0xfe, 0x08, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x89, 0xc7, 0xe8, 0x00, 0x00,
+    0x00, 0x00, 0xb8, 0xe7, 0x00, 0x00, 0x00, 0x0f, 0x05, 0xf4, 0xb8, 0x01, 0x00, 0x00, 0x00, 0x0f,
+    0x05, 0xc3,
+];
+
+/// x86-64 Linux 的启动目标文件，定义 `_start` 和弱符号 `exit` `write`，引用 `main` 和 `__ehdr_start`
+pub fn x86_64_linux() -> Object {
+    let mut obj = Object::new(Arch::X86_64);
+    let text = obj.section(SectionKind::Text);
+    obj.sections[text].align = 16;
+    obj.sections[text].data = X86_64_START.to_vec();
+    for (name, binding, value, size) in [
+        ("_start", Binding::Global, 0, 162),
+        ("exit", Binding::Weak, 162, 8),
+        ("write", Binding::Weak, 170, 8),
+    ] {
+        obj.define(Symbol {
+            name: name.to_string(),
+            binding,
+            kind: SymbolKind::Func,
+            visibility: Visibility::Default,
+            section: Some(text),
+            value,
+            size,
+        });
+    }
+    for (offset, name, kind) in [
+        (12, "__ehdr_start", RelocKind::Pc32),
+        (151, "main", RelocKind::Plt32),
+        (158, "exit", RelocKind::Plt32),
+    ] {
+        let symbol = obj.symbol(name);
+        obj.sections[text].relocs.push(Reloc {
+            offset,
+            symbol,
+            kind,
+            addend: -4,
+        });
+    }
+    obj
+}
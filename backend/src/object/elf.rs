@@ -0,0 +1,681 @@
+use crate::err::link_error::{LinkError, LinkResult};
+use crate::ir::Visibility;
+use crate::object::{Binding, Object, Reloc, RelocKind, Section, SectionKind, Symbol, SymbolKind};
+use crate::target::Arch;
+
+pub const ET_REL: u16 = 1;
+pub const ET_EXEC: u16 = 2;
+pub const ET_DYN: u16 = 3;
+pub const EM_X86_64: u16 = 62;
+pub const EM_RISCV: u16 = 243;
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_HASH: u32 = 5;
+pub const SHT_DYNAMIC: u32 = 6;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_DYNSYM: u32 = 11;
+
+pub const SHF_WRITE: u64 = 0x1;
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_EXECINSTR: u64 = 0x4;
+pub const SHF_MERGE: u64 = 0x10;
+pub const SHF_STRINGS: u64 = 0x20;
+pub const SHF_INFO_LINK: u64 = 0x40;
+pub const SHF_TLS: u64 = 0x400;
+
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STB_WEAK: u8 = 2;
+
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+pub const STT_FILE: u8 = 4;
+pub const STT_TLS: u8 = 6;
+
+pub const STV_DEFAULT: u8 = 0;
+pub const STV_HIDDEN: u8 = 2;
+pub const STV_PROTECTED: u8 = 3;
+
+pub const SHN_ABS: u16 = 0xfff1;
+pub const SHN_COMMON: u16 = 0xfff2;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_TLS: u32 = 7;
+pub const PT_GNU_STACK: u32 = 0x6474e551;
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+pub const R_X86_64_64: u32 = 1;
+pub const R_X86_64_PC32: u32 = 2;
+pub const R_X86_64_PLT32: u32 = 4;
+pub const R_X86_64_GLOB_DAT: u32 = 6;
+pub const R_X86_64_JUMP_SLOT: u32 = 7;
+pub const R_X86_64_RELATIVE: u32 = 8;
+pub const R_X86_64_GOTPCREL: u32 = 9;
+pub const R_X86_64_32: u32 = 10;
+pub const R_X86_64_DTPMOD64: u32 = 16;
+pub const R_X86_64_DTPOFF64: u32 = 17;
+pub const R_X86_64_TPOFF64: u32 = 18;
+pub const R_X86_64_TLSGD: u32 = 19;
+pub const R_X86_64_GOTTPOFF: u32 = 22;
+pub const R_X86_64_TPOFF32: u32 = 23;
+pub const R_X86_64_GOTPCRELX: u32 = 41;
+pub const R_X86_64_REX_GOTPCRELX: u32 = 42;
+
+pub const DT_NULL: u64 = 0;
+pub const DT_HASH: u64 = 4;
+pub const DT_STRTAB: u64 = 5;
+pub const DT_SYMTAB: u64 = 6;
+pub const DT_RELA: u64 = 7;
+pub const DT_RELASZ: u64 = 8;
+pub const DT_RELAENT: u64 = 9;
+pub const DT_STRSZ: u64 = 10;
+pub const DT_SYMENT: u64 = 11;
+pub const DT_SONAME: u64 = 14;
+pub const DT_FLAGS: u64 = 30;
+pub const DF_STATIC_TLS: u64 = 0x10;
+
+pub const EHDR_SIZE: u64 = 64;
+pub const PHDR_SIZE: u64 = 56;
+pub const SHDR_SIZE: u64 = 64;
+pub const SYM_SIZE: u64 = 24;
+pub const RELA_SIZE: u64 = 24;
+
+///
+/// ELF 文件头
+///
+/// # Members
+/// - `kind`: `ET_REL`、`ET_EXEC` 或 `ET_DYN`
+/// - `arch`: 目标架构
+/// - `entry`: 入口地址
+/// - `phnum`: 程序头的个数，程序头紧接着文件头
+/// - `shoff` `shnum`: 节头的位置和个数，最后一个节是节名字符串表
+///
+#[derive(Debug, Clone)]
+pub struct FileHeader {
+    pub kind: u16,
+    pub arch: Arch,
+    pub entry: u64,
+    pub phnum: u16,
+    pub shoff: u64,
+    pub shnum: u16,
+}
+
+impl FileHeader {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(EHDR_SIZE as usize);
+        // ELFCLASS64, ELFDATA2LSB, EV_CURRENT, ELFOSABI_NONE
+        out.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        out.extend_from_slice(&self.kind.to_le_bytes());
+        out.extend_from_slice(&machine(self.arch).to_le_bytes());
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&self.entry.to_le_bytes());
+        let phoff = if self.phnum > 0 { EHDR_SIZE } else { 0 };
+        out.extend_from_slice(&phoff.to_le_bytes());
+        out.extend_from_slice(&self.shoff.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        out.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+        let phentsize = if self.phnum > 0 { PHDR_SIZE as u16 } else { 0 };
+        out.extend_from_slice(&phentsize.to_le_bytes());
+        out.extend_from_slice(&self.phnum.to_le_bytes());
+        out.extend_from_slice(&(SHDR_SIZE as u16).to_le_bytes());
+        out.extend_from_slice(&self.shnum.to_le_bytes());
+        out.extend_from_slice(&self.shnum.saturating_sub(1).to_le_bytes()); // e_shstrndx
+        out
+    }
+}
+
+/// 节头
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Shdr {
+    pub name: u32,
+    pub kind: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+    pub align: u64,
+    pub entsize: u64,
+}
+
+impl Shdr {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(SHDR_SIZE as usize);
+        out.extend_from_slice(&self.name.to_le_bytes());
+        out.extend_from_slice(&self.kind.to_le_bytes());
+        out.extend_from_slice(&self.flags.to_le_bytes());
+        out.extend_from_slice(&self.addr.to_le_bytes());
+        out.extend_from_slice(&self.offset.to_le_bytes());
+        out.extend_from_slice(&self.size.to_le_bytes());
+        out.extend_from_slice(&self.link.to_le_bytes());
+        out.extend_from_slice(&self.info.to_le_bytes());
+        out.extend_from_slice(&self.align.to_le_bytes());
+        out.extend_from_slice(&self.entsize.to_le_bytes());
+        out
+    }
+}
+
+/// 字符串表，下标 0 是空字符串
+pub(crate) struct StrTab(pub Vec<u8>);
+
+impl StrTab {
+    pub fn new() -> Self {
+        Self(vec![0])
+    }
+
+    pub fn add(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        let index = self.0.len() as u32;
+        self.0.extend_from_slice(s.as_bytes());
+        self.0.push(0);
+        index
+    }
+}
+
+fn machine(arch: Arch) -> u16 {
+    match arch {
+        Arch::X86_64 => EM_X86_64,
+        Arch::Riscv64 => EM_RISCV,
+        Arch::Wasm32 => unreachable!("wasm32 has no ELF objects"),
+    }
+}
+
+fn reloc_type(arch: Arch, kind: RelocKind) -> u32 {
+    match (arch, kind) {
+        (Arch::X86_64, RelocKind::Abs64) => R_X86_64_64,
+        (Arch::X86_64, RelocKind::Abs32) => R_X86_64_32,
+        (Arch::X86_64, RelocKind::Pc32) => R_X86_64_PC32,
+        (Arch::X86_64, RelocKind::Plt32) => R_X86_64_PLT32,
+        (Arch::X86_64, RelocKind::GotPcRel) => R_X86_64_GOTPCREL,
+        (Arch::X86_64, RelocKind::TpOff32) => R_X86_64_TPOFF32,
+        (Arch::X86_64, RelocKind::GotTpOff) => R_X86_64_GOTTPOFF,
+        (Arch::X86_64, RelocKind::TlsGd) => R_X86_64_TLSGD,
+        (arch, kind) => unreachable!("no {:?} relocation for {:?}", kind, arch),
+    }
+}
+
+/// `st_other` 中的可见性
+pub(crate) fn visibility_bits(visibility: Visibility) -> u8 {
+    match visibility {
+        Visibility::Default => STV_DEFAULT,
+        Visibility::Hidden => STV_HIDDEN,
+        Visibility::Protected => STV_PROTECTED,
+    }
+}
+
+/// `st_info` 中的绑定和类型
+pub(crate) fn symbol_info(symbol: &Symbol) -> u8 {
+    let binding = match symbol.binding {
+        Binding::Local => STB_LOCAL,
+        Binding::Global => STB_GLOBAL,
+        Binding::Weak => STB_WEAK,
+    };
+    let kind = match symbol.kind {
+        SymbolKind::NoType => STT_NOTYPE,
+        SymbolKind::Object => STT_OBJECT,
+        SymbolKind::Func => STT_FUNC,
+        SymbolKind::Section => STT_SECTION,
+        SymbolKind::Tls => STT_TLS,
+    };
+    binding << 4 | kind
+}
+
+/// 追加一个符号表项
+pub(crate) fn push_symbol(
+    out: &mut Vec<u8>,
+    name: u32,
+    info: u8,
+    other: u8,
+    shndx: u16,
+    value: u64,
+    size: u64,
+) {
+    out.extend_from_slice(&name.to_le_bytes());
+    out.push(info);
+    out.push(other);
+    out.extend_from_slice(&shndx.to_le_bytes());
+    out.extend_from_slice(&value.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes());
+}
+
+/// 追加一个 `Elf64_Rela`
+pub(crate) fn push_rela(out: &mut Vec<u8>, offset: u64, symbol: u32, kind: u32, addend: i64) {
+    let info = (symbol as u64) << 32 | kind as u64;
+    out.extend_from_slice(&offset.to_le_bytes());
+    out.extend_from_slice(&info.to_le_bytes());
+    out.extend_from_slice(&addend.to_le_bytes());
+}
+
+/// 按对齐追加到文件中，返回文件偏移
+fn place(out: &mut Vec<u8>, data: &[u8], align: u64) -> u64 {
+    out.resize(
+        (out.len() as u64).next_multiple_of(align.max(1)) as usize,
+        0,
+    );
+    let offset = out.len() as u64;
+    out.extend_from_slice(data);
+    offset
+}
+
+///
+/// 写出 ELF64 小端的可重定位目标文件
+///
+/// 节的顺序：空节、目标文件中的节、`.comment`、`.note.GNU-stack`、各节的 `.rela`、
+/// `.symtab`、`.strtab`、`.shstrtab`；符号表中局部符号在前
+///
+pub fn write(obj: &Object) -> Vec<u8> {
+    let mut out = vec![0; EHDR_SIZE as usize];
+    let mut shstrtab = StrTab::new();
+    let mut headers = vec![Shdr::default()];
+
+    for section in obj.sections.iter() {
+        let (kind, flags) = match section.kind {
+            SectionKind::Text => (SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR),
+            SectionKind::Data => (SHT_PROGBITS, SHF_ALLOC | SHF_WRITE),
+            SectionKind::ReadOnly => (SHT_PROGBITS, SHF_ALLOC),
+            SectionKind::Bss => (SHT_NOBITS, SHF_ALLOC | SHF_WRITE),
+            SectionKind::Tls => (SHT_PROGBITS, SHF_ALLOC | SHF_WRITE | SHF_TLS),
+            SectionKind::TlsBss => (SHT_NOBITS, SHF_ALLOC | SHF_WRITE | SHF_TLS),
+            SectionKind::Debug => (SHT_PROGBITS, 0),
+        };
+        let offset = place(&mut out, &section.data, section.align);
+        headers.push(Shdr {
+            name: shstrtab.add(&section.name),
+            kind,
+            flags,
+            offset,
+            size: section.size(),
+            align: section.align,
+            ..Default::default()
+        });
+    }
+
+    let mut comment = obj.comment.as_bytes().to_vec();
+    comment.push(0);
+    let offset = place(&mut out, &comment, 1);
+    headers.push(Shdr {
+        name: shstrtab.add(".comment"),
+        kind: SHT_PROGBITS,
+        flags: SHF_MERGE | SHF_STRINGS,
+        offset,
+        size: comment.len() as u64,
+        align: 1,
+        entsize: 1,
+        ..Default::default()
+    });
+    headers.push(Shdr {
+        name: shstrtab.add(".note.GNU-stack"),
+        kind: SHT_PROGBITS,
+        offset: out.len() as u64,
+        align: 1,
+        ..Default::default()
+    });
+
+    // 符号表：空符号、局部符号、全局和弱符号
+    let mut strtab = StrTab::new();
+    let mut symtab = vec![0; SYM_SIZE as usize];
+    let mut indices = vec![0u32; obj.symbols.len()];
+    let locals: Vec<usize> = (0..obj.symbols.len())
+        .filter(|x| obj.symbols[*x].binding == Binding::Local)
+        .collect();
+    let globals: Vec<usize> = (0..obj.symbols.len())
+        .filter(|x| obj.symbols[*x].binding != Binding::Local)
+        .collect();
+    for (i, index) in locals.iter().chain(globals.iter()).enumerate() {
+        let symbol = &obj.symbols[*index];
+        indices[*index] = i as u32 + 1;
+        // 节符号没有名字，由所在的节表示
+        let name = match symbol.kind {
+            SymbolKind::Section => "",
+            _ => &symbol.name,
+        };
+        // 节头下标比节的下标多一个空节
+        let shndx = symbol.section.map_or(0, |x| x as u16 + 1);
+        push_symbol(
+            &mut symtab,
+            strtab.add(name),
+            symbol_info(symbol),
+            visibility_bits(symbol.visibility),
+            shndx,
+            symbol.value,
+            symbol.size,
+        );
+    }
+
+    let symtab_index =
+        headers.len() as u32 + obj.sections.iter().filter(|x| !x.relocs.is_empty()).count() as u32;
+    for (i, section) in obj.sections.iter().enumerate() {
+        if section.relocs.is_empty() {
+            continue;
+        }
+        let mut data = Vec::with_capacity(section.relocs.len() * RELA_SIZE as usize);
+        for reloc in section.relocs.iter() {
+            let kind = reloc_type(obj.arch, reloc.kind);
+            push_rela(
+                &mut data,
+                reloc.offset,
+                indices[reloc.symbol],
+                kind,
+                reloc.addend,
+            );
+        }
+        let offset = place(&mut out, &data, 8);
+        headers.push(Shdr {
+            name: shstrtab.add(&format!(".rela{}", section.name)),
+            kind: SHT_RELA,
+            flags: SHF_INFO_LINK,
+            offset,
+            size: data.len() as u64,
+            link: symtab_index,
+            info: i as u32 + 1,
+            align: 8,
+            entsize: RELA_SIZE,
+            ..Default::default()
+        });
+    }
+
+    let offset = place(&mut out, &symtab, 8);
+    headers.push(Shdr {
+        name: shstrtab.add(".symtab"),
+        kind: SHT_SYMTAB,
+        offset,
+        size: symtab.len() as u64,
+        link: symtab_index + 1,
+        info: locals.len() as u32 + 1,
+        align: 8,
+        entsize: SYM_SIZE,
+        ..Default::default()
+    });
+    let offset = place(&mut out, &strtab.0, 1);
+    headers.push(Shdr {
+        name: shstrtab.add(".strtab"),
+        kind: SHT_STRTAB,
+        offset,
+        size: strtab.0.len() as u64,
+        align: 1,
+        ..Default::default()
+    });
+    let name = shstrtab.add(".shstrtab");
+    let offset = place(&mut out, &shstrtab.0, 1);
+    headers.push(Shdr {
+        name,
+        kind: SHT_STRTAB,
+        offset,
+        size: shstrtab.0.len() as u64,
+        align: 1,
+        ..Default::default()
+    });
+
+    let shoff = place(&mut out, &[], 8);
+    for h in headers.iter() {
+        out.extend_from_slice(&h.to_bytes());
+    }
+
+    let ehdr = FileHeader {
+        kind: ET_REL,
+        arch: obj.arch,
+        entry: 0,
+        phnum: 0,
+        shoff,
+        shnum: headers.len() as u16,
+    }
+    .to_bytes();
+    out[..EHDR_SIZE as usize].copy_from_slice(&ehdr);
+    out
+}
+
+/// 小端读取，越界时返回 `Malformed`
+struct Reader<'a> {
+    name: &'a str,
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn bytes(&self, offset: u64, len: u64) -> LinkResult<&[u8]> {
+        let end = offset.checked_add(len);
+        match end {
+            Some(end) if end <= self.data.len() as u64 => {
+                Ok(&self.data[offset as usize..end as usize])
+            }
+            _ => Err(LinkError::Malformed(
+                self.name.to_string(),
+                "truncated file",
+            )),
+        }
+    }
+
+    fn u8(&self, offset: u64) -> LinkResult<u8> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: u64) -> LinkResult<u16> {
+        Ok(u16::from_le_bytes(
+            self.bytes(offset, 2)?.try_into().unwrap(),
+        ))
+    }
+
+    fn u32(&self, offset: u64) -> LinkResult<u32> {
+        Ok(u32::from_le_bytes(
+            self.bytes(offset, 4)?.try_into().unwrap(),
+        ))
+    }
+
+    fn u64(&self, offset: u64) -> LinkResult<u64> {
+        Ok(u64::from_le_bytes(
+            self.bytes(offset, 8)?.try_into().unwrap(),
+        ))
+    }
+
+    /// 字符串表中的字符串
+    fn str(&self, table: &Shdr, offset: u32) -> LinkResult<String> {
+        let bytes = self.bytes(table.offset, table.size)?;
+        let bytes = bytes.get(offset as usize..).unwrap_or_default();
+        let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    fn shdr(&self, offset: u64) -> LinkResult<Shdr> {
+        Ok(Shdr {
+            name: self.u32(offset)?,
+            kind: self.u32(offset + 4)?,
+            flags: self.u64(offset + 8)?,
+            addr: self.u64(offset + 16)?,
+            offset: self.u64(offset + 24)?,
+            size: self.u64(offset + 32)?,
+            link: self.u32(offset + 40)?,
+            info: self.u32(offset + 44)?,
+            align: self.u64(offset + 48)?,
+            entsize: self.u64(offset + 56)?,
+        })
+    }
+}
+
+fn reloc_kind(arch: Arch, kind: u32) -> Option<RelocKind> {
+    match (arch, kind) {
+        (Arch::X86_64, R_X86_64_64) => Some(RelocKind::Abs64),
+        (Arch::X86_64, R_X86_64_32) => Some(RelocKind::Abs32),
+        (Arch::X86_64, R_X86_64_PC32) => Some(RelocKind::Pc32),
+        (Arch::X86_64, R_X86_64_PLT32) => Some(RelocKind::Plt32),
+        // 可以松弛的 GOT 访问，不做松弛时和 `R_X86_64_GOTPCREL` 相同
+        (Arch::X86_64, R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX) => {
+            Some(RelocKind::GotPcRel)
+        }
+        (Arch::X86_64, R_X86_64_TPOFF32) => Some(RelocKind::TpOff32),
+        (Arch::X86_64, R_X86_64_GOTTPOFF) => Some(RelocKind::GotTpOff),
+        (Arch::X86_64, R_X86_64_TLSGD) => Some(RelocKind::TlsGd),
+        _ => None,
+    }
+}
+
+///
+/// 读取 ELF64 小端的可重定位目标文件，`name` 用于错误信息
+///
+/// 只保留占用内存的节（代码、数据、只读数据、`.bss`、线程局部变量）、`.debug_` 开头的调试信息节和它们的重定位，
+/// 节符号转换为以节名命名的局部符号，文件符号被忽略
+///
+pub fn read(name: &str, data: &[u8]) -> LinkResult<Object> {
+    let r = Reader { name, data };
+    let malformed = |msg| LinkError::Malformed(name.to_string(), msg);
+    let unsupported = |msg: String| LinkError::Unsupported(name.to_string(), msg);
+    if r.bytes(0, 4)? != b"\x7fELF" {
+        return Err(malformed("bad magic"));
+    }
+    if r.u8(4)? != 2 || r.u8(5)? != 1 {
+        return Err(unsupported(
+            "not a 64-bit little-endian ELF file".to_string(),
+        ));
+    }
+    if r.u16(16)? != ET_REL {
+        return Err(unsupported("not a relocatable object".to_string()));
+    }
+    let arch = match r.u16(18)? {
+        EM_X86_64 => Arch::X86_64,
+        EM_RISCV => Arch::Riscv64,
+        x => return Err(unsupported(format!("machine {}", x))),
+    };
+    let shoff = r.u64(40)?;
+    let shnum = r.u16(60)? as u64;
+    let shstrndx = r.u16(62)? as usize;
+    let headers = (0..shnum)
+        .map(|i| r.shdr(shoff + i * SHDR_SIZE))
+        .collect::<LinkResult<Vec<_>>>()?;
+    let shstrtab = headers
+        .get(shstrndx)
+        .ok_or(malformed("bad section name table"))?;
+
+    let mut obj = Object::new(arch);
+    obj.sections.clear();
+    // 节头下标到节的下标
+    let mut sections: Vec<Option<usize>> = vec![None; headers.len()];
+    for (i, h) in headers.iter().enumerate() {
+        let section_name = r.str(shstrtab, h.name)?;
+        if section_name == ".comment" {
+            let comment = r.str(h, 0)?;
+            obj.comment = comment;
+            continue;
+        }
+        let is_debug = h.kind == SHT_PROGBITS && section_name.starts_with(".debug_");
+        if !is_debug && (h.flags & SHF_ALLOC == 0 || !matches!(h.kind, SHT_PROGBITS | SHT_NOBITS)) {
+            continue;
+        }
+        let kind = match (h.kind, h.flags & SHF_WRITE != 0) {
+            _ if h.flags & SHF_ALLOC == 0 => SectionKind::Debug,
+            _ if h.flags & SHF_EXECINSTR != 0 => SectionKind::Text,
+            (SHT_NOBITS, _) if h.flags & SHF_TLS != 0 => SectionKind::TlsBss,
+            _ if h.flags & SHF_TLS != 0 => SectionKind::Tls,
+            (SHT_NOBITS, _) => SectionKind::Bss,
+            (_, true) => SectionKind::Data,
+            (_, false) => SectionKind::ReadOnly,
+        };
+        let mut section = Section::new(section_name, kind);
+        section.align = h.align.max(1);
+        match kind.is_bss() {
+            true => section.bss_size = h.size,
+            false => section.data = r.bytes(h.offset, h.size)?.to_vec(),
+        }
+        sections[i] = Some(obj.sections.len());
+        obj.sections.push(section);
+    }
+
+    // 符号表下标到符号的下标
+    let mut symbols: Vec<Option<usize>> = Vec::new();
+    if let Some(symtab) = headers.iter().find(|x| x.kind == SHT_SYMTAB) {
+        let strtab = headers
+            .get(symtab.link as usize)
+            .ok_or(malformed("bad string table"))?;
+        for i in 0..symtab.size / SYM_SIZE {
+            let offset = symtab.offset + i * SYM_SIZE;
+            let info = r.u8(offset + 4)?;
+            let other = r.u8(offset + 5)?;
+            let shndx = r.u16(offset + 6)?;
+            let section = match shndx {
+                0 => None,
+                SHN_ABS | SHN_COMMON => {
+                    let msg = "absolute and common symbols are not supported";
+                    return Err(unsupported(msg.to_string()));
+                }
+                x => sections.get(x as usize).copied().flatten(),
+            };
+            let (binding, kind) = (info >> 4, info & 0xf);
+            let symbol_name = match kind {
+                _ if i == 0 => {
+                    symbols.push(None);
+                    continue;
+                }
+                STT_FILE => {
+                    symbols.push(None);
+                    continue;
+                }
+                STT_SECTION => match section {
+                    Some(x) => obj.sections[x].name.clone(),
+                    None => {
+                        symbols.push(None);
+                        continue;
+                    }
+                },
+                _ => r.str(strtab, r.u32(offset)?)?,
+            };
+            symbols.push(Some(obj.symbols.len()));
+            obj.symbols.push(Symbol {
+                name: symbol_name,
+                binding: match binding {
+                    STB_LOCAL => Binding::Local,
+                    STB_WEAK => Binding::Weak,
+                    _ => Binding::Global,
+                },
+                kind: match kind {
+                    STT_FUNC => SymbolKind::Func,
+                    STT_OBJECT => SymbolKind::Object,
+                    STT_SECTION => SymbolKind::Section,
+                    STT_TLS => SymbolKind::Tls,
+                    _ => SymbolKind::NoType,
+                },
+                visibility: match other & 3 {
+                    STV_HIDDEN => Visibility::Hidden,
+                    STV_PROTECTED => Visibility::Protected,
+                    // STV_INTERNAL 按 hidden 处理
+                    1 => Visibility::Hidden,
+                    _ => Visibility::Default,
+                },
+                section,
+                value: r.u64(offset + 8)?,
+                size: r.u64(offset + 16)?,
+            });
+        }
+    }
+
+    for h in headers.iter().filter(|x| x.kind == SHT_RELA) {
+        let Some(Some(target)) = sections.get(h.info as usize) else {
+            continue;
+        };
+        for i in 0..h.size / RELA_SIZE {
+            let offset = h.offset + i * RELA_SIZE;
+            let info = r.u64(offset + 8)?;
+            let kind = reloc_kind(arch, info as u32)
+                .ok_or_else(|| unsupported(format!("relocation type {}", info as u32)))?;
+            let symbol = symbols
+                .get((info >> 32) as usize)
+                .copied()
+                .flatten()
+                .ok_or(malformed("relocation against an unknown symbol"))?;
+            obj.sections[*target].relocs.push(Reloc {
+                offset: r.u64(offset)?,
+                symbol,
+                kind,
+                addend: r.u64(offset + 16)? as i64,
+            });
+        }
+    }
+    Ok(obj)
+}
@@ -0,0 +1,973 @@
+use crate::err::link_error::{LinkError, LinkResult};
+use crate::ir::Visibility;
+use crate::object::elf::{
+    DF_STATIC_TLS, DT_FLAGS, DT_HASH, DT_NULL, DT_RELA, DT_RELAENT, DT_RELASZ, DT_SONAME, DT_STRSZ,
+    DT_STRTAB, DT_SYMENT, DT_SYMTAB, EHDR_SIZE, ET_DYN, ET_EXEC, FileHeader, PF_R, PF_W, PF_X,
+    PHDR_SIZE, PT_DYNAMIC, PT_GNU_STACK, PT_LOAD, PT_TLS, R_X86_64_64, R_X86_64_DTPMOD64,
+    R_X86_64_DTPOFF64, R_X86_64_GLOB_DAT, R_X86_64_RELATIVE, R_X86_64_TPOFF64, RELA_SIZE,
+    SHF_ALLOC, SHF_EXECINSTR, SHF_TLS, SHF_WRITE, SHT_DYNAMIC, SHT_DYNSYM, SHT_HASH, SHT_NOBITS,
+    SHT_PROGBITS, SHT_RELA, SHT_STRTAB, SYM_SIZE, Shdr, StrTab, push_rela, push_symbol,
+    symbol_info, visibility_bits,
+};
+use crate::object::{Binding, Object, Reloc, RelocKind, Section, SectionKind, SymbolKind};
+use crate::target::Arch;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// 可执行文件的加载地址
+pub const BASE: u64 = 0x400000;
+const PAGE: u64 = 0x1000;
+/// PLT 项的大小：`jmp *got(%rip)` 和两字节的 `nop`
+const PLT_SIZE: u64 = 8;
+/// general-dynamic 模型的访问序列共 16 字节，以带填充前缀的 `leaq x@tlsgd(%rip), %rdi` 开头，
+/// 之后是 8 字节的 `call __tls_get_addr`
+const TLS_GD_LEA: [u8; 4] = [0x66, 0x48, 0x8d, 0x3d];
+/// 静态链接时松弛成的 local-exec 序列：`movq %fs:0, %rax` 和 `leaq x@tpoff(%rax), %rax`
+const TLS_LE: [u8; 12] = [0x64, 0x48, 0x8b, 0x04, 0x25, 0, 0, 0, 0, 0x48, 0x8d, 0x80];
+
+///
+/// 合并后的节，链接器生成的节（`.got` `.dynsym` 等）也用它表示
+///
+/// # Members
+/// - `name` `kind`: 节名和种类，种类决定所在的段
+/// - `sh_type` `link` `info` `entsize`: 节头中的字段，`link` 是另一个输出节的下标
+/// - `data` `size`: 内容和大小，`.bss` `.tbss` 只有大小
+/// - `align`: 输入节的最大对齐
+/// - `offset` `addr`: 文件偏移和虚拟地址，调试信息的节地址为 0
+///
+#[derive(Debug, Clone)]
+struct OutSection {
+    name: String,
+    kind: SectionKind,
+    sh_type: u32,
+    link: Option<usize>,
+    info: u32,
+    entsize: u64,
+    data: Vec<u8>,
+    size: u64,
+    align: u64,
+    offset: u64,
+    addr: u64,
+}
+
+impl OutSection {
+    fn new(name: &str, kind: SectionKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            sh_type: match kind.is_bss() {
+                true => SHT_NOBITS,
+                false => SHT_PROGBITS,
+            },
+            link: None,
+            info: 0,
+            entsize: 0,
+            data: Vec::new(),
+            size: 0,
+            align: 1,
+            offset: 0,
+            addr: 0,
+        }
+    }
+
+    fn with_type(mut self, sh_type: u32, entsize: u64) -> Self {
+        self.sh_type = sh_type;
+        self.entsize = entsize;
+        self
+    }
+}
+
+/// 输入节合并到的输出节，调试信息的节按名字合并
+fn out_name(section: &Section) -> &str {
+    match section.kind {
+        SectionKind::Text => ".text",
+        SectionKind::ReadOnly => ".rodata",
+        SectionKind::Data => ".data",
+        SectionKind::Bss => ".bss",
+        SectionKind::Tls => ".tdata",
+        SectionKind::TlsBss => ".tbss",
+        SectionKind::Debug => &section.name,
+    }
+}
+
+/// 可读写的节，线程局部变量的初始值也在可读写的段中
+fn is_writable(kind: SectionKind) -> bool {
+    !matches!(
+        kind,
+        SectionKind::Text | SectionKind::ReadOnly | SectionKind::Debug
+    )
+}
+
+/// 是否有可读写的段
+fn writable(outs: &[OutSection]) -> bool {
+    outs.iter().any(|x| is_writable(x.kind) && x.size > 0)
+}
+
+/// 是否有线程局部变量
+fn has_tls(outs: &[OutSection]) -> bool {
+    outs.iter().any(|x| x.kind.is_tls() && x.size > 0)
+}
+
+/// 程序头：只读可执行的段、可读写的段（有时）、`PT_DYNAMIC`（共享库）、`PT_TLS`（有时）、`PT_GNU_STACK`
+fn phnum(outs: &[OutSection], dynamic: bool) -> u16 {
+    2 + writable(outs) as u16 + dynamic as u16 + has_tls(outs) as u16
+}
+
+///
+/// 线程局部存储的模板，也就是 `PT_TLS` 段
+///
+/// # Members
+/// - `offset` `addr`: 文件偏移和虚拟地址
+/// - `filesz`: 初始值（`.tdata`）的大小
+/// - `memsz`: 包括 `.tbss` 的大小
+/// - `align`: 对齐
+///
+#[derive(Debug, Clone, Copy, Default)]
+struct TlsTemplate {
+    offset: u64,
+    addr: u64,
+    filesz: u64,
+    memsz: u64,
+    align: u64,
+}
+
+impl TlsTemplate {
+    /// `.tbss` 跟在 `.tdata` 后面，没有线程局部变量时为 None
+    fn new(outs: &[OutSection]) -> Option<Self> {
+        let mut sections = outs.iter().filter(|x| x.kind.is_tls() && x.size > 0);
+        let first = sections.next()?;
+        let mut template = Self {
+            offset: first.offset,
+            addr: first.addr,
+            filesz: 0,
+            memsz: 0,
+            align: 1,
+        };
+        for x in std::iter::once(first).chain(sections) {
+            if x.kind == SectionKind::Tls {
+                template.filesz = x.addr + x.size - template.addr;
+            }
+            template.memsz = x.addr + x.size - template.addr;
+            template.align = template.align.max(x.align);
+        }
+        Some(template)
+    }
+
+    /// 相对线程指针的偏移，x86-64 的线程指针指向线程局部存储块的末尾，块的大小按对齐向上取整
+    fn tp_offset(&self, addr: u64) -> u64 {
+        let size = self.memsz.next_multiple_of(self.align);
+        addr.wrapping_sub(self.addr).wrapping_sub(size)
+    }
+
+    /// 在线程局部存储块中的偏移
+    fn dtp_offset(&self, addr: u64) -> u64 {
+        addr.wrapping_sub(self.addr)
+    }
+}
+
+/// 每个输出节的节头下标，空的节不输出节头，下标为 0
+fn header_indices(outs: &[OutSection]) -> Vec<u16> {
+    let mut next = 0;
+    outs.iter()
+        .map(|x| match x.size {
+            0 => 0,
+            _ => {
+                next += 1;
+                next
+            }
+        })
+        .collect()
+}
+
+/// 重定位的目标：定义所在的目标文件和符号下标，或者未定义的符号名，GOT 项按它去重
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SymRef<'a> {
+    Defined(usize, usize),
+    Undefined(&'a str),
+}
+
+///
+/// 合并节、解析符号之后的状态，静态链接和链接共享库共用
+///
+/// # Members
+/// - `inputs`: 目标文件名和目标文件
+/// - `outs`: 输出节，只读的节在前，可读写的节在后，调试信息的节在最后
+/// - `placement`: 每个输入节所在的输出节和在其中的偏移
+/// - `globals`: 全局符号名到定义所在的目标文件和符号下标
+///
+struct Linker<'a> {
+    inputs: &'a [(String, Object)],
+    outs: Vec<OutSection>,
+    placement: Vec<Vec<(usize, u64)>>,
+    globals: FxHashMap<&'a str, (usize, usize)>,
+}
+
+impl<'a> Linker<'a> {
+    /// `outs` 是排好顺序的输出节，调试信息的节追加在后面
+    ///
+    /// 同名的强符号重复定义时报错，弱符号被强符号覆盖
+    fn new(inputs: &'a [(String, Object)], mut outs: Vec<OutSection>) -> LinkResult<Self> {
+        for (name, obj) in inputs {
+            if obj.arch != Arch::X86_64 {
+                let msg = "only x86-64 objects can be linked".to_string();
+                return Err(LinkError::Unsupported(name.clone(), msg));
+            }
+        }
+
+        // 合并同类的节，记录每个输入节在输出节中的位置
+        let mut placement: Vec<Vec<(usize, u64)>> = Vec::with_capacity(inputs.len());
+        for (_, obj) in inputs {
+            let mut places = Vec::with_capacity(obj.sections.len());
+            for section in obj.sections.iter() {
+                let name = out_name(section);
+                let k = match outs.iter().position(|x| x.name == name) {
+                    Some(k) => k,
+                    None => {
+                        outs.push(OutSection::new(name, section.kind));
+                        outs.len() - 1
+                    }
+                };
+                let out = &mut outs[k];
+                out.align = out.align.max(section.align);
+                let offset = out.size.next_multiple_of(section.align.max(1));
+                out.data.resize(offset as usize, 0);
+                if !section.kind.is_bss() {
+                    out.data.extend_from_slice(&section.data);
+                }
+                out.size = offset + section.size();
+                places.push((k, offset));
+            }
+            placement.push(places);
+        }
+        for out in outs.iter_mut().filter(|x| x.kind.is_bss()) {
+            out.data.clear();
+        }
+
+        // 全局符号表
+        let mut globals: FxHashMap<&str, (usize, usize)> = FxHashMap::default();
+        for (i, (name, obj)) in inputs.iter().enumerate() {
+            for (j, symbol) in obj.symbols.iter().enumerate() {
+                if symbol.binding == Binding::Local || symbol.section.is_none() {
+                    continue;
+                }
+                let Some(prev) = globals.get(symbol.name.as_str()).copied() else {
+                    globals.insert(&symbol.name, (i, j));
+                    continue;
+                };
+                let prev_binding = inputs[prev.0].1.symbols[prev.1].binding;
+                match (prev_binding, symbol.binding) {
+                    (Binding::Weak, Binding::Global) => {
+                        globals.insert(&symbol.name, (i, j));
+                    }
+                    (Binding::Global, Binding::Global) => {
+                        return Err(LinkError::Duplicate {
+                            name: symbol.name.clone(),
+                            first: inputs[prev.0].0.clone(),
+                            second: name.clone(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Self {
+            inputs,
+            outs,
+            placement,
+            globals,
+        })
+    }
+
+    /// 输出节的下标
+    fn index(&self, name: &str) -> usize {
+        self.outs.iter().position(|x| x.name == name).unwrap()
+    }
+
+    /// 设置链接器生成的节的内容
+    fn set(&mut self, name: &str, data: Vec<u8>, align: u64) {
+        let k = self.index(name);
+        self.outs[k].size = data.len() as u64;
+        self.outs[k].data = data;
+        self.outs[k].align = align;
+    }
+
+    ///
+    /// 布局：文件头和程序头、只读的节在第一个段，可读写的节从新的一页开始，调试信息的节不加载
+    ///
+    /// `.tbss` 只是线程局部存储模板的一部分，运行时不会访问它的地址，因此和后面的节重叠
+    ///
+    fn layout(&mut self, base: u64, dynamic: bool) {
+        let mut cursor = EHDR_SIZE + PHDR_SIZE * phnum(&self.outs, dynamic) as u64;
+        let mut writable = false;
+        for out in self.outs.iter_mut() {
+            if is_writable(out.kind) && !writable {
+                writable = true;
+                cursor = cursor.next_multiple_of(PAGE);
+            }
+            out.align = out.align.max(1);
+            cursor = cursor.next_multiple_of(out.align);
+            out.offset = cursor;
+            if out.kind == SectionKind::Debug {
+                cursor += out.size;
+                continue;
+            }
+            out.addr = base + cursor;
+            // `.bss` `.tbss` 不占文件空间
+            if !out.kind.is_bss() {
+                cursor += out.size;
+            }
+        }
+    }
+
+    /// 所有重定位：所在的目标文件、输出节、输入节在输出节中的偏移和重定位本身
+    fn relocs(&self) -> Vec<(usize, usize, u64, Reloc)> {
+        let mut relocs = Vec::new();
+        for (i, (_, obj)) in self.inputs.iter().enumerate() {
+            for (s, section) in obj.sections.iter().enumerate() {
+                let (k, base) = self.placement[i][s];
+                relocs.extend(section.relocs.iter().map(|x| (i, k, base, *x)));
+            }
+        }
+        relocs
+    }
+
+    /// 符号的定义，局部符号就是它自己
+    fn symref(&self, input: usize, symbol: usize) -> SymRef<'a> {
+        let inputs = self.inputs;
+        let sym = &inputs[input].1.symbols[symbol];
+        match sym.binding {
+            Binding::Local => SymRef::Defined(input, symbol),
+            _ => match self.globals.get(sym.name.as_str()) {
+                Some((x, y)) => SymRef::Defined(*x, *y),
+                None => SymRef::Undefined(&sym.name),
+            },
+        }
+    }
+
+    /// 定义的地址，需要先布局，符号所在的节没有被保留时为 None
+    fn address(&self, input: usize, symbol: usize) -> Option<u64> {
+        let symbol = &self.inputs[input].1.symbols[symbol];
+        let (k, offset) = self.placement[input][symbol.section?];
+        Some(self.outs[k].addr + offset + symbol.value)
+    }
+
+    fn undefined(&self, input: usize, symbol: usize) -> LinkError {
+        let (name, obj) = &self.inputs[input];
+        LinkError::Undefined {
+            name: obj.symbols[symbol].name.clone(),
+            object: name.clone(),
+        }
+    }
+
+    /// 把重定位的结果 `value` 写入输出节 `k` 的 `offset` 处
+    fn patch(&mut self, k: usize, offset: u64, kind: RelocKind, value: u64) -> Result<(), ()> {
+        let place = self.outs[k].addr + offset;
+        let offset = offset as usize;
+        let data = &mut self.outs[k].data;
+        match kind {
+            RelocKind::Abs64 => {
+                data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+            }
+            RelocKind::Abs32 => {
+                let value = u32::try_from(value).map_err(|_| ())?;
+                data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            RelocKind::Pc32
+            | RelocKind::Plt32
+            | RelocKind::GotPcRel
+            | RelocKind::GotTpOff
+            | RelocKind::TlsGd => {
+                let rel = i32::try_from(value.wrapping_sub(place) as i64).map_err(|_| ())?;
+                data[offset..offset + 4].copy_from_slice(&rel.to_le_bytes());
+            }
+            RelocKind::TpOff32 => {
+                let value = i32::try_from(value as i64).map_err(|_| ())?;
+                data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    fn overflow(&self, input: usize, symbol: usize) -> LinkError {
+        let (name, obj) = &self.inputs[input];
+        LinkError::Overflow {
+            name: obj.symbols[symbol].name.clone(),
+            object: name.clone(),
+        }
+    }
+}
+
+/// GOT 项的内容
+/// - `Addr`: 符号的地址
+/// - `TpOff`: 线程局部变量相对线程指针的偏移
+/// - `TlsModule` `TlsOffset`: `__tls_get_addr` 的参数，模块编号和在模块的线程局部存储块中的偏移，
+///   总是相邻的两项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum GotKind {
+    Addr,
+    TpOff,
+    TlsModule,
+    TlsOffset,
+}
+
+/// 经 GOT 访问的重定位使用的 GOT 项
+fn got_kind(kind: RelocKind) -> Option<GotKind> {
+    match kind {
+        RelocKind::GotPcRel => Some(GotKind::Addr),
+        RelocKind::GotTpOff => Some(GotKind::TpOff),
+        RelocKind::TlsGd => Some(GotKind::TlsModule),
+        _ => None,
+    }
+}
+
+/// GOT 项的下标，没有时分配一个，`TlsModule` 同时分配后面的 `TlsOffset`
+fn got_entry<'a>(got: &mut Vec<(SymRef<'a>, GotKind)>, target: SymRef<'a>, kind: GotKind) -> usize {
+    match got.iter().position(|x| *x == (target, kind)) {
+        Some(index) => index,
+        None => {
+            got.push((target, kind));
+            if kind == GotKind::TlsModule {
+                got.push((target, GotKind::TlsOffset));
+            }
+            got.len() - 1 - (kind == GotKind::TlsModule) as usize
+        }
+    }
+}
+
+/// general-dynamic 访问序列中对 `__tls_get_addr` 的调用：`TlsGd` 重定位之后 8 字节处的重定位
+fn tls_calls(relocs: &[(usize, usize, u64, Reloc)]) -> FxHashSet<(usize, u64)> {
+    relocs
+        .iter()
+        .filter(|(_, _, _, x)| x.kind == RelocKind::TlsGd)
+        .map(|(_, k, base, x)| (*k, base + x.offset + 8))
+        .collect()
+}
+
+///
+/// 静态链接为 x86-64 Linux 的可执行文件，`inputs` 是目标文件名和目标文件，
+/// 启动代码（见 `crt`）也作为普通的输入
+///
+/// 未定义的弱符号地址为 0，其余未定义的符号报错，`__ehdr_start` 是加载的文件头；位置无关代码经 GOT 的访问
+/// 在链接时就填好了 GOT 项，不需要动态链接器。线程局部变量的 general-dynamic 访问松弛为 local-exec，
+/// 不再调用 `__tls_get_addr`
+///
+pub fn link(inputs: &[(String, Object)], entry: &str) -> LinkResult<Vec<u8>> {
+    let outs = vec![
+        OutSection::new(".text", SectionKind::Text),
+        OutSection::new(".rodata", SectionKind::ReadOnly),
+        OutSection::new(".tdata", SectionKind::Tls),
+        OutSection::new(".tbss", SectionKind::TlsBss),
+        OutSection::new(".got", SectionKind::Data).with_type(SHT_PROGBITS, 8),
+        OutSection::new(".data", SectionKind::Data),
+        OutSection::new(".bss", SectionKind::Bss),
+    ];
+    let mut linker = Linker::new(inputs, outs)?;
+    let relocs = linker.relocs();
+    let tls_calls = tls_calls(&relocs);
+
+    let mut got = Vec::new();
+    for (i, k, base, reloc) in relocs.iter() {
+        if let Some(kind) = got_kind(reloc.kind)
+            && kind != GotKind::TlsModule
+            && !tls_calls.contains(&(*k, base + reloc.offset))
+        {
+            got_entry(&mut got, linker.symref(*i, reloc.symbol), kind);
+        }
+    }
+    linker.set(".got", vec![0; got.len() * 8], 8);
+    linker.layout(BASE, false);
+
+    let got_index = linker.index(".got");
+    let got_addr = linker.outs[got_index].addr;
+    let tls = TlsTemplate::new(&linker.outs).unwrap_or_default();
+    for (i, k, base, reloc) in relocs {
+        let offset = base + reloc.offset;
+        if tls_calls.contains(&(k, offset)) {
+            continue;
+        }
+        let symbol = &inputs[i].1.symbols[reloc.symbol];
+        let target = match linker.symref(i, reloc.symbol) {
+            SymRef::Defined(x, y) => linker.address(x, y),
+            SymRef::Undefined("__ehdr_start") => Some(BASE),
+            SymRef::Undefined(_) if symbol.binding == Binding::Weak => Some(0),
+            SymRef::Undefined(_) => None,
+        };
+        let Some(mut target) = target else {
+            return Err(linker.undefined(i, reloc.symbol));
+        };
+        match reloc.kind {
+            RelocKind::GotPcRel | RelocKind::GotTpOff => {
+                let got_kind = got_kind(reloc.kind).unwrap();
+                let index = got_entry(&mut got, linker.symref(i, reloc.symbol), got_kind);
+                let value = match got_kind {
+                    GotKind::TpOff => tls.tp_offset(target),
+                    _ => target,
+                };
+                let entry = &mut linker.outs[got_index].data[index * 8..index * 8 + 8];
+                entry.copy_from_slice(&value.to_le_bytes());
+                target = got_addr + index as u64 * 8;
+            }
+            RelocKind::TpOff32 => target = tls.tp_offset(target),
+            RelocKind::TlsGd => {
+                // 重定位在 `leaq` 的位移处，松弛后偏移在 `leaq x@tpoff(%rax)` 的位移处
+                let start = (offset as usize).wrapping_sub(TLS_GD_LEA.len());
+                let code = linker.outs[k].data.get_mut(start..start + 16);
+                let Some(code) = code.filter(|x| x.starts_with(&TLS_GD_LEA)) else {
+                    let msg = format!("unexpected TLS sequence for '{}'", symbol.name);
+                    return Err(LinkError::Unsupported(inputs[i].0.clone(), msg));
+                };
+                code[..TLS_LE.len()].copy_from_slice(&TLS_LE);
+                let offset = (start + TLS_LE.len()) as u64;
+                let value = tls.tp_offset(target);
+                if linker.patch(k, offset, RelocKind::TpOff32, value).is_err() {
+                    return Err(linker.overflow(i, reloc.symbol));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let value = target.wrapping_add(reloc.addend as u64);
+        if linker.patch(k, offset, reloc.kind, value).is_err() {
+            return Err(linker.overflow(i, reloc.symbol));
+        }
+    }
+
+    let entry = linker
+        .globals
+        .get(entry)
+        .and_then(|(x, y)| linker.address(*x, *y))
+        .ok_or_else(|| LinkError::NoEntry(entry.to_string()))?;
+    Ok(write_image(&linker.outs, ET_EXEC, BASE, entry, None))
+}
+
+/// SysV 的符号名哈希，`.hash` 使用
+fn elf_hash(name: &str) -> u32 {
+    let mut h: u32 = 0;
+    for c in name.bytes() {
+        h = (h << 4).wrapping_add(c as u32);
+        let g = h & 0xf000_0000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+/// `.hash` 的内容：桶的个数、链的个数、桶、链，`names` 从动态符号表的下标 1 开始
+fn hash_table(names: &[&str]) -> Vec<u8> {
+    let nchain = names.len() + 1;
+    let nbucket = names.len().max(1);
+    let mut buckets = vec![0u32; nbucket];
+    let mut chains = vec![0u32; nchain];
+    for (i, name) in names.iter().enumerate() {
+        let bucket = elf_hash(name) as usize % nbucket;
+        chains[i + 1] = buckets[bucket];
+        buckets[bucket] = i as u32 + 1;
+    }
+    [nbucket as u32, nchain as u32]
+        .iter()
+        .chain(buckets.iter())
+        .chain(chains.iter())
+        .flat_map(|x| x.to_le_bytes())
+        .collect()
+}
+
+///
+/// 链接为 x86-64 Linux 的共享库，`soname` 写入 `DT_SONAME`
+///
+/// 非 hidden 的全局符号和弱符号导出到动态符号表。调用库内的函数直接跳转（相当于 `-Bsymbolic-functions`），
+/// 其余对导出符号和未定义符号的引用留给动态链接器在加载时解析，可执行文件中的定义（包括复制重定位）可以覆盖库中的定义：
+/// 数据和函数地址经 GOT 访问，调用未定义的函数经 PLT 跳转，GOT 项在加载时一次填好。
+/// 未定义的符号不能被 `Pc32` 引用，`Abs32` 和 `TpOff32` 不能出现在加载的节中，只读的节中也不能有动态重定位，
+/// 它们都说明目标文件不是位置无关代码。线程局部变量的 GOT 项由动态链接器填写，
+/// 使用 initial-exec 模型时设置 `DF_STATIC_TLS`
+///
+pub fn link_shared(inputs: &[(String, Object)], soname: Option<&str>) -> LinkResult<Vec<u8>> {
+    let outs = vec![
+        OutSection::new(".hash", SectionKind::ReadOnly).with_type(SHT_HASH, 4),
+        OutSection::new(".dynsym", SectionKind::ReadOnly).with_type(SHT_DYNSYM, SYM_SIZE),
+        OutSection::new(".dynstr", SectionKind::ReadOnly).with_type(SHT_STRTAB, 0),
+        OutSection::new(".rela.dyn", SectionKind::ReadOnly).with_type(SHT_RELA, RELA_SIZE),
+        OutSection::new(".text", SectionKind::Text),
+        OutSection::new(".plt", SectionKind::Text),
+        OutSection::new(".rodata", SectionKind::ReadOnly),
+        OutSection::new(".tdata", SectionKind::Tls),
+        OutSection::new(".tbss", SectionKind::TlsBss),
+        OutSection::new(".dynamic", SectionKind::Data).with_type(SHT_DYNAMIC, 16),
+        OutSection::new(".got", SectionKind::Data).with_type(SHT_PROGBITS, 8),
+        OutSection::new(".data", SectionKind::Data),
+        OutSection::new(".bss", SectionKind::Bss),
+    ];
+    let mut linker = Linker::new(inputs, outs)?;
+    let relocs = linker.relocs();
+    let [
+        hash,
+        dynsym,
+        dynstr,
+        rela_dyn,
+        plt_index,
+        dynamic,
+        got_index,
+    ] = [
+        ".hash",
+        ".dynsym",
+        ".dynstr",
+        ".rela.dyn",
+        ".plt",
+        ".dynamic",
+        ".got",
+    ]
+    .map(|x| linker.index(x));
+    linker.outs[hash].link = Some(dynsym);
+    linker.outs[dynsym].link = Some(dynstr);
+    linker.outs[dynsym].info = 1;
+    linker.outs[rela_dyn].link = Some(dynsym);
+    linker.outs[dynamic].link = Some(dynstr);
+
+    // 导出的符号，记录定义到动态符号表下标
+    let mut dynsyms: Vec<(usize, usize)> = Vec::new();
+    let mut exports: FxHashMap<(usize, usize), u32> = FxHashMap::default();
+    for (i, (_, obj)) in inputs.iter().enumerate() {
+        for (j, symbol) in obj.symbols.iter().enumerate() {
+            let exported = symbol.binding != Binding::Local
+                && symbol.visibility != Visibility::Hidden
+                && linker.globals.get(symbol.name.as_str()) == Some(&(i, j))
+                && linker.outs[linker.placement[i][symbol.section.unwrap()].0].kind
+                    != SectionKind::Debug;
+            if exported {
+                dynsyms.push((i, j));
+                exports.insert((i, j), dynsyms.len() as u32);
+            }
+        }
+    }
+
+    // 确定 GOT 项、PLT 项、未定义的动态符号和动态重定位的个数，动态符号记录第一个引用它的符号
+    let mut got = Vec::new();
+    let mut plt: Vec<&str> = Vec::new();
+    let mut undefined: FxHashMap<&str, u32> = FxHashMap::default();
+    let mut rela_count = 0;
+    let mut static_tls = false;
+    for (i, k, _, reloc) in relocs.iter() {
+        let section = linker.outs[*k].kind;
+        if section == SectionKind::Debug {
+            continue;
+        }
+        let target = linker.symref(*i, reloc.symbol);
+        let (name, obj) = &inputs[*i];
+        let symbol = &obj.symbols[reloc.symbol];
+        match (reloc.kind, target) {
+            (RelocKind::Abs64, _)
+                if matches!(section, SectionKind::Text | SectionKind::ReadOnly) =>
+            {
+                return Err(LinkError::TextRel {
+                    name: symbol.name.clone(),
+                    object: name.clone(),
+                });
+            }
+            (RelocKind::Abs64, _) => rela_count += 1,
+            (RelocKind::Abs32 | RelocKind::TpOff32, _)
+            | (RelocKind::Pc32, SymRef::Undefined(_)) => {
+                return Err(LinkError::NotPic {
+                    name: symbol.name.clone(),
+                    object: name.clone(),
+                });
+            }
+            (RelocKind::Pc32 | RelocKind::Plt32, SymRef::Defined(..)) => {}
+            (RelocKind::Plt32, SymRef::Undefined(x)) => {
+                got_entry(&mut got, target, GotKind::Addr);
+                if !plt.contains(&x) {
+                    plt.push(x);
+                }
+            }
+            (RelocKind::GotPcRel | RelocKind::GotTpOff | RelocKind::TlsGd, _) => {
+                static_tls |= reloc.kind == RelocKind::GotTpOff;
+                got_entry(&mut got, target, got_kind(reloc.kind).unwrap());
+            }
+        }
+        if let SymRef::Undefined(x) = target
+            && !undefined.contains_key(x)
+        {
+            dynsyms.push((*i, reloc.symbol));
+            undefined.insert(x, dynsyms.len() as u32);
+        }
+    }
+    // 需要动态链接器解析的符号：未定义的和导出的，导出的符号可以被可执行文件中的定义覆盖
+    let dynamic_symbol = |target: SymRef| match target {
+        SymRef::Defined(x, y) => exports.get(&(x, y)).copied(),
+        SymRef::Undefined(x) => Some(undefined[x]),
+    };
+    // 库内的线程局部变量在块中的偏移在链接时就知道，不需要动态重定位
+    rela_count += got
+        .iter()
+        .filter(|(x, kind)| *kind != GotKind::TlsOffset || dynamic_symbol(*x).is_some())
+        .count();
+
+    let mut strtab = StrTab::new();
+    let soname = soname.map(|x| strtab.add(x));
+    let names: Vec<&str> = dynsyms
+        .iter()
+        .map(|(i, j)| inputs[*i].1.symbols[*j].name.as_str())
+        .collect();
+    let name_offsets: Vec<u32> = names.iter().map(|x| strtab.add(x)).collect();
+    linker.set(".hash", hash_table(&names), 8);
+    linker.set(".dynsym", vec![0; (names.len() + 1) * SYM_SIZE as usize], 8);
+    linker.set(".dynstr", strtab.0, 1);
+    linker.set(".rela.dyn", vec![0; rela_count * RELA_SIZE as usize], 8);
+    linker.set(".plt", vec![0; plt.len() * PLT_SIZE as usize], 16);
+    linker.set(".got", vec![0; got.len() * 8], 8);
+    let mut tags = vec![DT_HASH, DT_STRTAB, DT_SYMTAB, DT_STRSZ, DT_SYMENT];
+    if rela_count > 0 {
+        tags.extend([DT_RELA, DT_RELASZ, DT_RELAENT]);
+    }
+    if soname.is_some() {
+        tags.push(DT_SONAME);
+    }
+    if static_tls {
+        tags.push(DT_FLAGS);
+    }
+    tags.push(DT_NULL);
+    linker.set(".dynamic", vec![0; tags.len() * 16], 8);
+    linker.layout(0, true);
+
+    // GOT 项：动态符号由动态链接器查找，库内的符号是加载地址加偏移，
+    // 库内的线程局部变量以块中的偏移作为加数，模块编号为 0 表示这个库自己
+    let got_addr = linker.outs[got_index].addr;
+    let tls = TlsTemplate::new(&linker.outs).unwrap_or_default();
+    let mut rela = Vec::with_capacity(rela_count * RELA_SIZE as usize);
+    for (index, (target, kind)) in got.iter().enumerate() {
+        let place = got_addr + index as u64 * 8;
+        let symbol = dynamic_symbol(*target);
+        let addr = match (*target, symbol) {
+            (SymRef::Defined(x, y), None) => {
+                linker.address(x, y).ok_or_else(|| linker.undefined(x, y))?
+            }
+            _ => 0,
+        };
+        let (value, dynamic_reloc) = match (kind, symbol) {
+            (GotKind::Addr, Some(symbol)) => (0, Some((symbol, R_X86_64_GLOB_DAT, 0))),
+            (GotKind::Addr, None) => (addr, Some((0, R_X86_64_RELATIVE, addr as i64))),
+            (GotKind::TpOff, Some(symbol)) => (0, Some((symbol, R_X86_64_TPOFF64, 0))),
+            (GotKind::TpOff, None) => {
+                let offset = tls.dtp_offset(addr) as i64;
+                (0, Some((0, R_X86_64_TPOFF64, offset)))
+            }
+            (GotKind::TlsModule, symbol) => (0, Some((symbol.unwrap_or(0), R_X86_64_DTPMOD64, 0))),
+            (GotKind::TlsOffset, Some(symbol)) => (0, Some((symbol, R_X86_64_DTPOFF64, 0))),
+            (GotKind::TlsOffset, None) => (tls.dtp_offset(addr), None),
+        };
+        let entry = &mut linker.outs[got_index].data[index * 8..index * 8 + 8];
+        entry.copy_from_slice(&value.to_le_bytes());
+        if let Some((symbol, r_type, addend)) = dynamic_reloc {
+            push_rela(&mut rela, place, symbol, r_type, addend);
+        }
+    }
+
+    // PLT 项：`jmp *got(%rip)`，`xchg %ax, %ax`
+    let plt_addr = linker.outs[plt_index].addr;
+    for (index, name) in plt.iter().enumerate() {
+        let entry = got_entry(&mut got, SymRef::Undefined(name), GotKind::Addr);
+        let place = plt_addr + index as u64 * PLT_SIZE;
+        let rel = (got_addr + entry as u64 * 8).wrapping_sub(place + 6) as i32;
+        let code = &mut linker.outs[plt_index].data[index * PLT_SIZE as usize..];
+        code[..2].copy_from_slice(&[0xff, 0x25]);
+        code[2..6].copy_from_slice(&rel.to_le_bytes());
+        code[6..8].copy_from_slice(&[0x66, 0x90]);
+    }
+
+    // 调用库内的函数直接跳转，不经过 PLT
+    for (i, k, base, reloc) in relocs {
+        let symbol = &inputs[i].1.symbols[reloc.symbol];
+        let loaded = linker.outs[k].kind != SectionKind::Debug;
+        let place = linker.outs[k].addr + base + reloc.offset;
+        let target = linker.symref(i, reloc.symbol);
+        let addr = match (reloc.kind, target) {
+            (RelocKind::GotPcRel | RelocKind::GotTpOff | RelocKind::TlsGd, _) => {
+                let kind = got_kind(reloc.kind).unwrap();
+                got_addr + got_entry(&mut got, target, kind) as u64 * 8
+            }
+            (RelocKind::Plt32, SymRef::Undefined(x)) if loaded => {
+                let index = plt.iter().position(|y| *y == x).unwrap();
+                plt_addr + index as u64 * PLT_SIZE
+            }
+            (RelocKind::Abs64, _) if loaded && dynamic_symbol(target).is_some() => {
+                let symbol = dynamic_symbol(target).unwrap();
+                push_rela(&mut rela, place, symbol, R_X86_64_64, reloc.addend);
+                continue;
+            }
+            (_, SymRef::Defined(x, y)) => linker
+                .address(x, y)
+                .ok_or_else(|| linker.undefined(i, reloc.symbol))?,
+            // 只剩下调试信息中对未定义符号的引用
+            (_, SymRef::Undefined(_)) if symbol.binding == Binding::Weak => 0,
+            (_, SymRef::Undefined(_)) => return Err(linker.undefined(i, reloc.symbol)),
+        };
+        let value = addr.wrapping_add(reloc.addend as u64);
+        if reloc.kind == RelocKind::Abs64 && loaded {
+            push_rela(&mut rela, place, 0, R_X86_64_RELATIVE, value as i64);
+        }
+        if linker
+            .patch(k, base + reloc.offset, reloc.kind, value)
+            .is_err()
+        {
+            return Err(linker.overflow(i, reloc.symbol));
+        }
+    }
+    debug_assert_eq!(rela.len(), rela_count * RELA_SIZE as usize);
+    linker.outs[rela_dyn].data = rela;
+
+    // 动态符号表，未定义的符号沿用引用它的符号的绑定，线程局部变量的值是在块中的偏移
+    let shndx = header_indices(&linker.outs);
+    let mut symtab = vec![0; SYM_SIZE as usize];
+    for ((i, j), name) in dynsyms.iter().zip(name_offsets) {
+        let symbol = &inputs[*i].1.symbols[*j];
+        let info = symbol_info(symbol);
+        match symbol.section {
+            Some(s) => {
+                let mut addr = linker.address(*i, *j).unwrap();
+                if symbol.kind == SymbolKind::Tls {
+                    addr = tls.dtp_offset(addr);
+                }
+                let index = shndx[linker.placement[*i][s].0];
+                let other = visibility_bits(symbol.visibility);
+                push_symbol(&mut symtab, name, info, other, index, addr, symbol.size);
+            }
+            None => push_symbol(&mut symtab, name, info, 0, 0, 0, 0),
+        }
+    }
+    linker.outs[dynsym].data = symtab;
+
+    let mut data = Vec::with_capacity(tags.len() * 16);
+    for tag in tags {
+        let value = match tag {
+            DT_HASH => linker.outs[hash].addr,
+            DT_STRTAB => linker.outs[dynstr].addr,
+            DT_SYMTAB => linker.outs[dynsym].addr,
+            DT_STRSZ => linker.outs[dynstr].size,
+            DT_SYMENT => SYM_SIZE,
+            DT_RELA => linker.outs[rela_dyn].addr,
+            DT_RELASZ => linker.outs[rela_dyn].size,
+            DT_RELAENT => RELA_SIZE,
+            DT_SONAME => soname.unwrap() as u64,
+            DT_FLAGS => DF_STATIC_TLS,
+            _ => 0,
+        };
+        data.extend_from_slice(&tag.to_le_bytes());
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    linker.outs[dynamic].data = data;
+
+    Ok(write_image(&linker.outs, ET_DYN, 0, 0, Some(dynamic)))
+}
+
+///
+/// 写出文件头、程序头、各个节的内容和节头，节头供调试器和 `objdump` 等工具使用，不影响加载
+///
+/// 段的文件偏移和虚拟地址相差 `base`，`dynamic` 是共享库的 `.dynamic`
+///
+fn write_image(
+    outs: &[OutSection],
+    kind: u16,
+    base: u64,
+    entry: u64,
+    dynamic: Option<usize>,
+) -> Vec<u8> {
+    let phnum = phnum(outs, dynamic.is_some());
+    let mut out = vec![0; EHDR_SIZE as usize];
+
+    let mut phdr = |kind: u32, flags: u32, offset: u64, filesz: u64, memsz: u64, align: u64| {
+        let addr = if kind == PT_GNU_STACK {
+            0
+        } else {
+            base + offset
+        };
+        out.extend_from_slice(&kind.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
+        out.extend_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&addr.to_le_bytes());
+        out.extend_from_slice(&addr.to_le_bytes());
+        out.extend_from_slice(&filesz.to_le_bytes());
+        out.extend_from_slice(&memsz.to_le_bytes());
+        out.extend_from_slice(&align.to_le_bytes());
+    };
+    let end = |kinds: &[SectionKind]| {
+        outs.iter()
+            .filter(|x| kinds.contains(&x.kind))
+            .map(|x| x.offset + x.size)
+            .max()
+            .unwrap_or(0)
+    };
+    let text_end = end(&[SectionKind::Text, SectionKind::ReadOnly]);
+    phdr(PT_LOAD, PF_R | PF_X, 0, text_end, text_end, PAGE);
+    if writable(outs) {
+        let start = outs.iter().find(|x| is_writable(x.kind)).unwrap().offset;
+        let filesz = end(&[SectionKind::Data, SectionKind::Tls]).saturating_sub(start);
+        let memsz = end(&[SectionKind::Data, SectionKind::Tls, SectionKind::Bss]) - start;
+        phdr(PT_LOAD, PF_R | PF_W, start, filesz, memsz, PAGE);
+    }
+    if let Some(k) = dynamic {
+        let size = outs[k].size;
+        phdr(PT_DYNAMIC, PF_R | PF_W, outs[k].offset, size, size, 8);
+    }
+    if let Some(tls) = TlsTemplate::new(outs) {
+        phdr(PT_TLS, PF_R, tls.offset, tls.filesz, tls.memsz, tls.align);
+    }
+    phdr(PT_GNU_STACK, PF_R | PF_W, 0, 0, 0, PAGE);
+
+    // 空的节不输出节头
+    let indices = header_indices(outs);
+    let mut shstrtab = StrTab::new();
+    let mut headers = vec![Shdr::default()];
+    for section in outs.iter().filter(|x| x.size > 0) {
+        let flags = match section.kind {
+            SectionKind::Text => SHF_ALLOC | SHF_EXECINSTR,
+            SectionKind::ReadOnly => SHF_ALLOC,
+            SectionKind::Data | SectionKind::Bss => SHF_ALLOC | SHF_WRITE,
+            SectionKind::Tls | SectionKind::TlsBss => SHF_ALLOC | SHF_WRITE | SHF_TLS,
+            SectionKind::Debug => 0,
+        };
+        if !section.kind.is_bss() {
+            out.resize(section.offset as usize, 0);
+            out.extend_from_slice(&section.data);
+        }
+        headers.push(Shdr {
+            name: shstrtab.add(&section.name),
+            kind: section.sh_type,
+            flags,
+            addr: section.addr,
+            offset: section.offset,
+            size: section.size,
+            link: section.link.map_or(0, |x| indices[x] as u32),
+            info: section.info,
+            align: section.align,
+            entsize: section.entsize,
+        });
+    }
+    let name = shstrtab.add(".shstrtab");
+    headers.push(Shdr {
+        name,
+        kind: SHT_STRTAB,
+        offset: out.len() as u64,
+        size: shstrtab.0.len() as u64,
+        align: 1,
+        ..Default::default()
+    });
+    out.extend_from_slice(&shstrtab.0);
+
+    let shoff = (out.len() as u64).next_multiple_of(8);
+    out.resize(shoff as usize, 0);
+    for h in headers.iter() {
+        out.extend_from_slice(&h.to_bytes());
+    }
+    let ehdr = FileHeader {
+        kind,
+        arch: Arch::X86_64,
+        entry,
+        phnum,
+        shoff,
+        shnum: headers.len() as u16,
+    }
+    .to_bytes();
+    out[..EHDR_SIZE as usize].copy_from_slice(&ehdr);
+    out
+}
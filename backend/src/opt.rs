@@ -0,0 +1,165 @@
+/// IR 优化，每个 pass 读入 IR 并原地修改，pass manager 按优化级别组织流水线
+/// # Contents
+/// - `fold`: 常量折叠，整数按位宽回绕，`f32` 按单精度计算，和前端 `APInt` / `APFloat` 的语义一致
+/// - `mem2reg`: 把只被直接 load / store 的 `alloca` 提升为 SSA 值
+/// - `sccp`: 稀疏条件常量传播
+/// - `dce`: 死代码删除和死存储删除
+/// - `simplify_cfg`: 控制流图化简：常量分支、不可达块、块合并、跳转串联
+/// - `call_graph`: 模块的调用图和强连通分量
+/// - `inline`: 按代价模型内联函数
+/// - `global_dce`: 删除没有被引用的 `static` 函数
+/// - `alias`: 别名分析：基址、常量偏移、地址逃逸和类型标签
+/// - `gvn`: 基于支配树的全局值编号，删除公共子表达式和冗余的 load
+/// - `licm`: 循环不变量外提
+/// - `indvars`: 归纳变量化简和强度削弱，循环次数的计算
+/// - `unroll`: 完全展开循环次数确定的小循环
+pub mod alias;
+pub mod call_graph;
+pub mod dce;
+pub mod fold;
+pub mod global_dce;
+pub mod gvn;
+pub mod indvars;
+pub mod inline;
+pub mod licm;
+pub mod mem2reg;
+pub mod sccp;
+pub mod simplify_cfg;
+pub mod unroll;
+
+use crate::err::ir_error::{IrError, IrResult};
+use crate::ir::verifier::verify_module;
+use crate::ir::{Function, Module};
+
+///
+/// 优化 pass，默认在每个有函数体的函数上分别运行
+///
+pub trait Pass {
+    /// 名字，`PassManager::parse` 按这个名字查找
+    fn name(&self) -> &'static str;
+
+    /// 在一个函数上运行，返回是否修改了函数，`ptr_bytes` 是模块的指针宽度
+    fn run_on_function(&mut self, _func: &mut Function, _ptr_bytes: u32) -> bool {
+        false
+    }
+
+    /// 在整个模块上运行，返回是否修改了模块
+    fn run_on_module(&mut self, module: &mut Module) -> bool {
+        let mut changed = false;
+        for id in module.func_ids() {
+            let func = &mut module.funcs[id];
+            if !func.is_declaration() {
+                changed |= self.run_on_function(func, module.ptr_bytes);
+            }
+        }
+        changed
+    }
+}
+
+/// 按名字创建 pass
+pub fn create_pass(name: &str) -> Option<Box<dyn Pass>> {
+    let pass: Box<dyn Pass> = match name {
+        "mem2reg" => Box::new(mem2reg::Mem2Reg),
+        "sccp" => Box::new(sccp::Sccp),
+        "dce" => Box::new(dce::Dce),
+        "simplify-cfg" => Box::new(simplify_cfg::SimplifyCfg),
+        "inline" => Box::new(inline::Inliner::default()),
+        "globaldce" => Box::new(global_dce::GlobalDce),
+        "gvn" => Box::new(gvn::Gvn),
+        "licm" => Box::new(licm::Licm),
+        "indvars" => Box::new(indvars::IndVars),
+        "unroll" => Box::new(unroll::Unroll::default()),
+        _ => return None,
+    };
+    Some(pass)
+}
+
+///
+/// 依次运行一组 pass
+///
+/// # Members
+/// - `passes`: 按顺序运行的 pass
+/// - `verify`: 每个 pass 之后校验模块，出错时报告是哪个 pass 破坏了 IR
+///
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+    pub verify: bool,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    /// 优化级别对应的流水线，`0` 不做优化，`1` 为函数内的优化，`2` 及以上在其中加入内联、删除死函数和循环展开
+    pub fn for_level(level: u32) -> Self {
+        let mut pm = Self::new();
+        let names: &[&str] = match level {
+            0 => &[],
+            1 => &[
+                "mem2reg",
+                "sccp",
+                "simplify-cfg",
+                "dce",
+                "gvn",
+                "licm",
+                "indvars",
+                "sccp",
+                "simplify-cfg",
+                "dce",
+            ],
+            _ => &[
+                "mem2reg",
+                "sccp",
+                "simplify-cfg",
+                "dce",
+                "inline",
+                "globaldce",
+                "sccp",
+                "simplify-cfg",
+                "dce",
+                "gvn",
+                "licm",
+                "indvars",
+                "unroll",
+                "gvn",
+                "sccp",
+                "simplify-cfg",
+                "dce",
+            ],
+        };
+        for name in names {
+            pm.add(create_pass(name).unwrap());
+        }
+        pm
+    }
+
+    /// 逗号或空白分隔的 pass 名字，有未知的名字时返回这个名字
+    pub fn parse(names: &str) -> Result<Self, String> {
+        let mut pm = Self::new();
+        for name in names.split([',', ' ']).filter(|x| !x.is_empty()) {
+            pm.add(create_pass(name).ok_or_else(|| name.to_string())?);
+        }
+        Ok(pm)
+    }
+
+    /// 运行所有 pass，返回是否修改了模块
+    pub fn run(&mut self, module: &mut Module) -> IrResult<bool> {
+        let mut changed = false;
+        for pass in self.passes.iter_mut() {
+            changed |= pass.run_on_module(module);
+            if self.verify {
+                verify_module(module).map_err(|e| IrError::Pass {
+                    pass: pass.name(),
+                    source: Box::new(e),
+                })?;
+            }
+        }
+        Ok(changed)
+    }
+}
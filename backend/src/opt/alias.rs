@@ -0,0 +1,203 @@
+use crate::ir::{Function, InstId, InstKind, Value};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// 两次访问的关系：不重叠、可能重叠、起始地址相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasResult {
+    No,
+    May,
+    Must,
+}
+
+///
+/// 一次内存访问
+///
+/// # Members
+/// - `ptr`: 起始地址
+/// - `size`: 访问的字节数
+/// - `tbaa`: 类型标签，`0` 表示没有类型信息
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub ptr: Value,
+    pub size: u64,
+    pub tbaa: u32,
+}
+
+impl Location {
+    /// `load` / `store` 访问的位置，`ptr_bytes` 是模块的指针宽度
+    pub fn of(func: &Function, inst: InstId, ptr_bytes: u32) -> Option<Self> {
+        let data = &func.insts[inst];
+        let (ptr, ty, tbaa) = match data.kind {
+            InstKind::Load { ptr, tbaa, .. } => (ptr, data.ty, tbaa),
+            InstKind::Store { ptr, val, tbaa, .. } => (ptr, func.value_type(val), tbaa),
+            _ => return None,
+        };
+        Some(Self {
+            ptr,
+            size: ty.bytes(ptr_bytes) as u64,
+            tbaa,
+        })
+    }
+}
+
+/// 地址指向的对象：`alloca`，其他可以确定是哪个对象的（全局变量、函数），不能确定的
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Object {
+    Alloca(InstId),
+    Identified(Value),
+    Unknown(Value),
+}
+
+///
+/// 函数内的别名分析
+///
+/// 地址沿 `gep` 追溯：
+/// - 去掉常量偏移后基址相同时，按偏移和访问的范围判断
+/// - 不同的 `alloca`、全局变量、函数是不同的对象
+/// - 地址没有逃逸的 `alloca` 只能通过它自己派生的地址访问，和其他对象、调用都不重叠
+///
+/// 以上不能确定时，两个访问的类型标签都不为 `0` 并且不同时不重叠
+///
+/// # Members
+/// - `escaped`: 地址逃逸的 `alloca`：地址被存到内存、传给调用、参与 `gep` 以外的运算
+/// - `ptr_bytes`: 模块的指针宽度
+///
+pub struct AliasAnalysis {
+    escaped: FxHashSet<InstId>,
+    ptr_bytes: u32,
+}
+
+impl AliasAnalysis {
+    pub fn new(func: &Function, ptr_bytes: u32) -> Self {
+        let mut users: FxHashMap<InstId, Vec<InstId>> = FxHashMap::default();
+        for (_, inst) in func.inst_iter() {
+            for operand in func.insts[inst].kind.operands() {
+                if let Value::Inst(x) = operand {
+                    users.entry(x).or_default().push(inst);
+                }
+            }
+        }
+
+        // `alloca` 和从它派生的地址，只作为 load / store / memcpy 的地址使用时没有逃逸
+        let mut work: Vec<(InstId, InstId)> = Vec::new();
+        for (_, inst) in func.inst_iter() {
+            if let InstKind::Alloca { .. } = func.insts[inst].kind {
+                work.push((inst, inst));
+            }
+        }
+        let mut escaped = FxHashSet::default();
+        while let Some((alloca, derived)) = work.pop() {
+            if escaped.contains(&alloca) {
+                continue;
+            }
+            let value = Value::Inst(derived);
+            for user in users.get(&derived).into_iter().flatten() {
+                match &func.insts[*user].kind {
+                    InstKind::Load { .. } | InstKind::MemCopy { .. } => {}
+                    InstKind::Store { val, .. } if *val != value => {}
+                    InstKind::Gep { base, index, .. } if *base == value && *index != value => {
+                        work.push((alloca, *user));
+                    }
+                    _ => {
+                        escaped.insert(alloca);
+                    }
+                }
+            }
+        }
+        Self { escaped, ptr_bytes }
+    }
+
+    /// 地址的基址和相对基址的常量偏移，沿索引是常量的 `gep` 追溯
+    fn decompose(func: &Function, mut ptr: Value) -> (Value, i64) {
+        let mut total = 0i64;
+        while let Value::Inst(x) = ptr
+            && let InstKind::Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } = func.insts[x].kind
+            && let Some(index) = index.as_int()
+        {
+            total = total
+                .wrapping_add(index.wrapping_mul(scale as i64))
+                .wrapping_add(offset);
+            ptr = base;
+        }
+        (ptr, total)
+    }
+
+    /// 地址指向的对象，沿所有的 `gep` 追溯
+    fn object(func: &Function, mut ptr: Value) -> Object {
+        while let Value::Inst(x) = ptr {
+            match func.insts[x].kind {
+                InstKind::Gep { base, .. } => ptr = base,
+                InstKind::Alloca { .. } => return Object::Alloca(x),
+                _ => return Object::Unknown(ptr),
+            }
+        }
+        match ptr {
+            Value::Global(_) | Value::Func(_) => Object::Identified(ptr),
+            _ => Object::Unknown(ptr),
+        }
+    }
+
+    /// 没有逃逸的 `alloca`
+    fn is_local(&self, object: Object) -> bool {
+        matches!(object, Object::Alloca(x) if !self.escaped.contains(&x))
+    }
+
+    pub fn alias(&self, func: &Function, a: Location, b: Location) -> AliasResult {
+        let (base_a, offset_a) = Self::decompose(func, a.ptr);
+        let (base_b, offset_b) = Self::decompose(func, b.ptr);
+        if base_a == base_b {
+            if offset_a == offset_b {
+                return AliasResult::Must;
+            }
+            let disjoint = match offset_a < offset_b {
+                true => offset_b.wrapping_sub(offset_a) as u64 >= a.size,
+                false => offset_a.wrapping_sub(offset_b) as u64 >= b.size,
+            };
+            if disjoint {
+                return AliasResult::No;
+            }
+        }
+        let x = Self::object(func, base_a);
+        let y = Self::object(func, base_b);
+        if x != y {
+            let identified = !matches!(x, Object::Unknown(_)) && !matches!(y, Object::Unknown(_));
+            if identified || self.is_local(x) || self.is_local(y) {
+                return AliasResult::No;
+            }
+        }
+        if a.tbaa != 0 && b.tbaa != 0 && a.tbaa != b.tbaa {
+            return AliasResult::No;
+        }
+        AliasResult::May
+    }
+
+    /// 指令是否可能修改这个位置，调用和变参指令可能修改除了没有逃逸的 `alloca` 以外的内存
+    pub fn may_write(&self, func: &Function, inst: InstId, loc: Location) -> bool {
+        match func.insts[inst].kind {
+            InstKind::Store { .. } => {
+                let store = Location::of(func, inst, self.ptr_bytes).unwrap();
+                self.alias(func, store, loc) != AliasResult::No
+            }
+            InstKind::MemCopy { dst, size, .. } => {
+                let dst = Location {
+                    ptr: dst,
+                    size,
+                    tbaa: 0,
+                };
+                self.alias(func, dst, loc) != AliasResult::No
+            }
+            InstKind::Call { .. }
+            | InstKind::VaStart { .. }
+            | InstKind::VaArg { .. }
+            | InstKind::VaEnd { .. }
+            | InstKind::VaCopy { .. } => !self.is_local(Self::object(func, loc.ptr)),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::ir::{FuncId, InitItem, InstKind, Module, Value};
+use slotmap::SecondaryMap;
+
+///
+/// 模块的调用图，只记录直接调用
+///
+/// # Members
+/// - `callees`: 每个函数直接调用的函数，去重，按第一次出现的顺序
+/// - `callers`: 直接调用每个函数的函数
+/// - `address_taken`: 地址被取走的函数（出现在调用目标以外的位置或全局变量的初始值中），可能被间接调用
+///
+#[derive(Debug, Clone)]
+pub struct CallGraph {
+    callees: SecondaryMap<FuncId, Vec<FuncId>>,
+    callers: SecondaryMap<FuncId, Vec<FuncId>>,
+    address_taken: SecondaryMap<FuncId, ()>,
+}
+
+impl CallGraph {
+    pub fn new(module: &Module) -> Self {
+        let mut callees: SecondaryMap<FuncId, Vec<FuncId>> = SecondaryMap::new();
+        let mut callers: SecondaryMap<FuncId, Vec<FuncId>> = SecondaryMap::new();
+        let mut address_taken = SecondaryMap::new();
+        for id in module.func_ids() {
+            callees.insert(id, Vec::new());
+            callers.insert(id, Vec::new());
+        }
+        for id in module.func_ids() {
+            let func = &module.funcs[id];
+            for (_, inst) in func.inst_iter() {
+                let kind = &func.insts[inst].kind;
+                let mut operands = kind.operands();
+                if let InstKind::Call {
+                    callee: Value::Func(callee),
+                    ..
+                } = kind
+                {
+                    if !callees[id].contains(callee) {
+                        callees[id].push(*callee);
+                        callers[*callee].push(id);
+                    }
+                    operands.remove(0);
+                }
+                for operand in operands {
+                    if let Value::Func(x) = operand {
+                        address_taken.insert(x, ());
+                    }
+                }
+            }
+        }
+        for (_, global) in module.globals.iter() {
+            for item in global.init.iter().flatten() {
+                if let InitItem::Addr {
+                    target: Value::Func(x),
+                    ..
+                } = item
+                {
+                    address_taken.insert(*x, ());
+                }
+            }
+        }
+        Self {
+            callees,
+            callers,
+            address_taken,
+        }
+    }
+
+    pub fn callees(&self, func: FuncId) -> &[FuncId] {
+        &self.callees[func]
+    }
+
+    pub fn callers(&self, func: FuncId) -> &[FuncId] {
+        &self.callers[func]
+    }
+
+    pub fn is_address_taken(&self, func: FuncId) -> bool {
+        self.address_taken.contains_key(func)
+    }
+
+    ///
+    /// 强连通分量（Tarjan 算法），按自底向上的顺序：被调用的分量在调用者之前
+    ///
+    /// 同一个分量中的函数互相递归；只有一个函数时，它调用自己才是递归
+    ///
+    pub fn sccs(&self, module: &Module) -> Vec<Vec<FuncId>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: SecondaryMap::new(),
+            low: SecondaryMap::new(),
+            stack: Vec::new(),
+            on_stack: SecondaryMap::new(),
+            next: 0,
+            sccs: Vec::new(),
+        };
+        for id in module.func_ids() {
+            if !tarjan.index.contains_key(id) {
+                tarjan.visit(id);
+            }
+        }
+        tarjan.sccs
+    }
+
+    /// `func` 是否在递归中：在多于一个函数的强连通分量中，或者调用自己
+    pub fn is_recursive(&self, sccs: &[Vec<FuncId>], func: FuncId) -> bool {
+        let scc = sccs.iter().find(|x| x.contains(&func)).unwrap();
+        scc.len() > 1 || self.callees[func].contains(&func)
+    }
+}
+
+///
+/// Tarjan 算法的状态
+///
+/// # Members
+/// - `index` `low`: 访问的序号和能回到的最小序号
+/// - `stack` `on_stack`: 还没有归入分量的函数
+/// - `next`: 下一个序号
+/// - `sccs`: 已经找到的分量
+///
+struct Tarjan<'a> {
+    graph: &'a CallGraph,
+    index: SecondaryMap<FuncId, usize>,
+    low: SecondaryMap<FuncId, usize>,
+    stack: Vec<FuncId>,
+    on_stack: SecondaryMap<FuncId, ()>,
+    next: usize,
+    sccs: Vec<Vec<FuncId>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, func: FuncId) {
+        self.index.insert(func, self.next);
+        self.low.insert(func, self.next);
+        self.next += 1;
+        self.stack.push(func);
+        self.on_stack.insert(func, ());
+
+        for callee in self.graph.callees[func].iter().cloned() {
+            if !self.index.contains_key(callee) {
+                self.visit(callee);
+                self.low[func] = self.low[func].min(self.low[callee]);
+            } else if self.on_stack.contains_key(callee) {
+                self.low[func] = self.low[func].min(self.index[callee]);
+            }
+        }
+
+        if self.low[func] == self.index[func] {
+            let mut scc = Vec::new();
+            loop {
+                let x = self.stack.pop().unwrap();
+                self.on_stack.remove(x);
+                scc.push(x);
+                if x == func {
+                    break;
+                }
+            }
+            scc.reverse();
+            self.sccs.push(scc);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use crate::ir::{Function, InstId, InstKind, Type, Value};
+use crate::opt::Pass;
+use rustc_hash::FxHashMap;
+use slotmap::SecondaryMap;
+
+///
+/// 死代码删除和死存储删除
+///
+/// 先删除死存储：基本块内被覆盖之前没有被读过的 `store`，以及只被写、从不被读也没有逃逸的 `alloca`；
+/// 再从有副作用的指令出发标记活跃的指令，删除其余的指令（包括只互相使用的 phi 环）。
+/// volatile 的访问总是保留
+///
+pub struct Dce;
+
+impl Pass for Dce {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, ptr_bytes: u32) -> bool {
+        let mut changed = overwritten_stores(func, ptr_bytes);
+        changed |= write_only_allocas(func);
+        changed |= sweep(func);
+        changed
+    }
+}
+
+/// 删除同一个块中在被读之前又被写入同一地址的 `store`
+fn overwritten_stores(func: &mut Function, ptr_bytes: u32) -> bool {
+    let mut dead = Vec::new();
+    for block in func.layout.iter().cloned() {
+        // 地址上还没有被读过的 store 和写入的类型
+        let mut pending: FxHashMap<Value, (InstId, Type)> = FxHashMap::default();
+        for inst in func.blocks[block].insts.iter().cloned() {
+            let data = &func.insts[inst];
+            match data.kind {
+                InstKind::Store {
+                    ptr,
+                    val,
+                    volatile: false,
+                    ..
+                } => {
+                    let ty = func.value_type(val);
+                    if let Some((prev, prev_ty)) = pending.insert(ptr, (inst, ty))
+                        && prev_ty.bytes(ptr_bytes) <= ty.bytes(ptr_bytes)
+                    {
+                        dead.push(prev);
+                    }
+                }
+                InstKind::Store { .. } => {}
+                ref kind if kind.reads_memory() || kind.has_side_effects() => pending.clear(),
+                _ => {}
+            }
+        }
+    }
+    let changed = !dead.is_empty();
+    for inst in dead {
+        func.remove_inst(inst);
+    }
+    changed
+}
+
+/// 删除只作为非 volatile `store` 的地址使用的 `alloca` 和这些 `store`
+fn write_only_allocas(func: &mut Function) -> bool {
+    let mut stores: FxHashMap<InstId, Vec<InstId>> = FxHashMap::default();
+    for (_, inst) in func.inst_iter() {
+        if let InstKind::Alloca { .. } = func.insts[inst].kind {
+            stores.insert(inst, Vec::new());
+        }
+    }
+    let mut escaped = Vec::new();
+    for (_, inst) in func.inst_iter() {
+        match func.insts[inst].kind {
+            InstKind::Store {
+                ptr: Value::Inst(ptr),
+                val,
+                volatile: false,
+                ..
+            } if stores.contains_key(&ptr) => {
+                stores.get_mut(&ptr).unwrap().push(inst);
+                if let Value::Inst(x) = val {
+                    escaped.push(x);
+                }
+            }
+            ref kind => escaped.extend(kind.operands().iter().filter_map(|x| x.as_inst())),
+        }
+    }
+    for inst in escaped {
+        stores.remove(&inst);
+    }
+    // 删掉 store 之后没有使用的 alloca 留给后面的清扫
+    let mut changed = false;
+    for store in stores.into_values().flatten() {
+        func.remove_inst(store);
+        changed = true;
+    }
+    changed
+}
+
+/// 标记从有副作用的指令可达的指令，删除其余的
+fn sweep(func: &mut Function) -> bool {
+    let mut live: SecondaryMap<InstId, ()> = SecondaryMap::new();
+    let mut work: Vec<InstId> = func
+        .inst_iter()
+        .map(|x| x.1)
+        .filter(|x| func.insts[*x].kind.has_side_effects())
+        .collect();
+    while let Some(inst) = work.pop() {
+        if live.insert(inst, ()).is_some() {
+            continue;
+        }
+        for operand in func.insts[inst].kind.operands() {
+            if let Value::Inst(x) = operand
+                && !live.contains_key(x)
+            {
+                work.push(x);
+            }
+        }
+    }
+    let dead: Vec<InstId> = func
+        .inst_iter()
+        .map(|x| x.1)
+        .filter(|x| !live.contains_key(*x))
+        .collect();
+    let changed = !dead.is_empty();
+    for inst in dead {
+        func.remove_inst(inst);
+    }
+    changed
+}
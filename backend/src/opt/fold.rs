@@ -0,0 +1,198 @@
+//! IR 常量的折叠，语义和前端的 `APInt` / `APFloat` 一致，但没有复用它们：
+//! rcc 依赖 backend，反过来依赖会形成循环；移到单独的 crate 也不合适，前端的 `APInt` 用大整数保存带符号的值，IR 常量是截断到类型位宽的 `u64` 位模式，
+//! 运算规则由 `BinaryOp` / `CastOp` 决定而不是由值本身的符号决定。
+//! 修改任何一边的回绕、舍入规则时两边要一起改
+
+use crate::ir::value::{sign_extend, truncate};
+use crate::ir::{BinaryOp, CastOp, CmpPred, Type, Value};
+
+/// 浮点常量的值，`f32` 先转成 `f64`（精确）
+fn float_value(value: Value) -> Option<f64> {
+    match value {
+        Value::Float {
+            ty: Type::F32,
+            bits,
+        } => Some(f32::from_bits(bits as u32) as f64),
+        Value::Float { bits, .. } => Some(f64::from_bits(bits)),
+        _ => None,
+    }
+}
+
+/// `ty` 类型的浮点常量，`f32` 的结果舍入到单精度
+fn float_const(ty: Type, value: f64) -> Value {
+    match ty {
+        Type::F32 => Value::f32(value as f32),
+        _ => Value::f64(value),
+    }
+}
+
+fn int_const(ty: Type, bits: u64, ptr_bytes: u32) -> Value {
+    Value::Int {
+        ty,
+        bits: truncate(bits, ty.bits(ptr_bytes)),
+    }
+}
+
+///
+/// 二元运算
+///
+/// 整数运算按位宽回绕；除零、`INT_MIN / -1`、移位量超出位宽、带 `nsw` 的有符号溢出是未定义行为，不折叠，
+/// 留给运行时（解释器会报告）；浮点运算按操作数的精度计算，`f32` 的每一步都舍入到单精度；
+/// `ptr_bytes` 是模块的指针宽度
+///
+pub fn binary(
+    op: BinaryOp,
+    ty: Type,
+    lhs: Value,
+    rhs: Value,
+    nsw: bool,
+    ptr_bytes: u32,
+) -> Option<Value> {
+    use BinaryOp::*;
+    if op.is_float() {
+        let (a, b) = (float_value(lhs)?, float_value(rhs)?);
+        let value = match op {
+            FAdd => a + b,
+            FSub => a - b,
+            FMul => a * b,
+            FDiv => a / b,
+            _ => a % b,
+        };
+        return Some(float_const(ty, value));
+    }
+
+    let width = ty.bits(ptr_bytes);
+    let (ua, ub) = (lhs.as_uint()?, rhs.as_uint()?);
+    let (sa, sb) = (sign_extend(ua, width), sign_extend(ub, width));
+    let checked = |x: i128| {
+        let min = -(1i128 << (width - 1));
+        let max = (1i128 << (width - 1)) - 1;
+        match nsw && (x < min || x > max) {
+            true => None,
+            false => Some(x as u64),
+        }
+    };
+    let bits = match op {
+        Add => checked(sa as i128 + sb as i128)?,
+        Sub => checked(sa as i128 - sb as i128)?,
+        Mul => checked(sa as i128 * sb as i128)?,
+        SDiv | SRem => {
+            if sb == 0 || (width > 1 && sa == i64::MIN >> (64 - width) && sb == -1) {
+                return None;
+            }
+            match op {
+                SDiv => sa.wrapping_div(sb) as u64,
+                _ => sa.wrapping_rem(sb) as u64,
+            }
+        }
+        UDiv | URem => {
+            if ub == 0 {
+                return None;
+            }
+            match op {
+                UDiv => ua / ub,
+                _ => ua % ub,
+            }
+        }
+        And => ua & ub,
+        Or => ua | ub,
+        Xor => ua ^ ub,
+        Shl | LShr | AShr => {
+            if ub >= width as u64 {
+                return None;
+            }
+            match op {
+                Shl => ua << ub,
+                LShr => ua >> ub,
+                _ => (sa >> ub) as u64,
+            }
+        }
+        _ => return None,
+    };
+    Some(int_const(ty, bits, ptr_bytes))
+}
+
+/// 浮点取负
+pub fn fneg(val: Value) -> Option<Value> {
+    match val {
+        Value::Float { ty, .. } => Some(float_const(ty, -float_value(val)?)),
+        _ => None,
+    }
+}
+
+/// 比较，结果为 `i1`；浮点的有序比较在任一操作数为 NaN 时为假
+pub fn compare(pred: CmpPred, lhs: Value, rhs: Value) -> Option<Value> {
+    use CmpPred::*;
+    if pred.is_float() {
+        let (a, b) = (float_value(lhs)?, float_value(rhs)?);
+        let result = match pred {
+            FOeq => a == b,
+            FUne => a != b,
+            FOlt => a < b,
+            FOle => a <= b,
+            FOgt => a > b,
+            _ => a >= b,
+        };
+        return Some(Value::bool(result));
+    }
+    let (ua, ub) = (lhs.as_uint()?, rhs.as_uint()?);
+    let (sa, sb) = (lhs.as_int()?, rhs.as_int()?);
+    let result = match pred {
+        Eq => ua == ub,
+        Ne => ua != ub,
+        Slt => sa < sb,
+        Sle => sa <= sb,
+        Sgt => sa > sb,
+        Sge => sa >= sb,
+        Ult => ua < ub,
+        Ule => ua <= ub,
+        Ugt => ua > ub,
+        _ => ua >= ub,
+    };
+    Some(Value::bool(result))
+}
+
+/// 类型转换，浮点转整数超出范围时不折叠
+pub fn cast(op: CastOp, val: Value, to: Type, ptr_bytes: u32) -> Option<Value> {
+    use CastOp::*;
+    let to_bits = to.bits(ptr_bytes);
+    let value = match (op, val) {
+        (Trunc | ZExt | PtrToInt | IntToPtr, Value::Int { bits, .. }) => {
+            int_const(to, bits, ptr_bytes)
+        }
+        (SExt, Value::Int { .. }) => int_const(to, val.as_int()? as u64, ptr_bytes),
+        (FpToSi | FpToUi, Value::Float { .. }) => {
+            let value = float_value(val)?.trunc();
+            let signed = op == FpToSi;
+            let (min, max) = match signed {
+                true => (
+                    -(2f64.powi(to_bits as i32 - 1)),
+                    2f64.powi(to_bits as i32 - 1),
+                ),
+                false => (0.0, 2f64.powi(to_bits as i32)),
+            };
+            if value.is_nan() || value < min || value >= max {
+                return None;
+            }
+            match signed {
+                true => int_const(to, value as i64 as u64, ptr_bytes),
+                false => int_const(to, value as u64, ptr_bytes),
+            }
+        }
+        (SiToFp, Value::Int { .. }) => match to {
+            Type::F32 => Value::f32(val.as_int()? as f32),
+            _ => Value::f64(val.as_int()? as f64),
+        },
+        (UiToFp, Value::Int { bits, .. }) => match to {
+            Type::F32 => Value::f32(bits as f32),
+            _ => Value::f64(bits as f64),
+        },
+        (FpExt | FpTrunc, Value::Float { .. }) => float_const(to, float_value(val)?),
+        (Bitcast, Value::Int { bits, .. } | Value::Float { bits, .. }) => match to.is_float() {
+            true => Value::Float { ty: to, bits },
+            false => int_const(to, bits, ptr_bytes),
+        },
+        _ => return None,
+    };
+    Some(value)
+}
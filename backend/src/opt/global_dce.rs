@@ -0,0 +1,63 @@
+use crate::ir::{FuncId, InitItem, Linkage, Module, Value};
+use crate::opt::Pass;
+use slotmap::SecondaryMap;
+
+///
+/// 删除没有被引用的 `static` 函数
+///
+/// 从外部可见的函数和全局变量的初始值出发，沿函数体中出现的函数（调用或取地址）标记，
+/// 删除没有标记到的内部函数；只被其他死函数引用的函数也会被删除
+///
+pub struct GlobalDce;
+
+impl Pass for GlobalDce {
+    fn name(&self) -> &'static str {
+        "globaldce"
+    }
+
+    fn run_on_module(&mut self, module: &mut Module) -> bool {
+        let mut work: Vec<FuncId> = module
+            .func_ids()
+            .into_iter()
+            .filter(|x| module.funcs[*x].linkage != Linkage::Internal)
+            .collect();
+        for (_, global) in module.globals.iter() {
+            for item in global.init.iter().flatten() {
+                if let InitItem::Addr {
+                    target: Value::Func(x),
+                    ..
+                } = item
+                {
+                    work.push(*x);
+                }
+            }
+        }
+
+        let mut live: SecondaryMap<FuncId, ()> = SecondaryMap::new();
+        while let Some(id) = work.pop() {
+            if live.insert(id, ()).is_some() {
+                continue;
+            }
+            let func = &module.funcs[id];
+            for (_, inst) in func.inst_iter() {
+                for operand in func.insts[inst].kind.operands() {
+                    if let Value::Func(x) = operand
+                        && !live.contains_key(x)
+                    {
+                        work.push(x);
+                    }
+                }
+            }
+        }
+
+        let dead: Vec<FuncId> = module
+            .func_ids()
+            .into_iter()
+            .filter(|x| !live.contains_key(*x))
+            .collect();
+        for id in dead.iter().cloned() {
+            module.remove_func(id);
+        }
+        !dead.is_empty()
+    }
+}
@@ -0,0 +1,219 @@
+use crate::ir::cfg::DomTree;
+use crate::ir::{BinaryOp, BlockId, CastOp, CmpPred, Function, InstId, InstKind, Type, Value};
+use crate::opt::Pass;
+use crate::opt::alias::{AliasAnalysis, AliasResult, Location};
+use rustc_hash::FxHashMap;
+use slotmap::SecondaryMap;
+
+///
+/// 基于支配树的全局值编号和公共子表达式删除
+///
+/// 沿支配树先序遍历，支配当前块的块中算过的表达式都可用：
+/// - 运算、比较、类型转换、`select`、地址计算按操作码和操作数编号，相同的表达式只保留第一个，
+///   可交换的运算和比较交换操作数后也相同；`nsw` 不参与比较，先执行的那个没有溢出时结果相同
+/// - 非 volatile 的 `load` 从之前的 `store` 或 `load` 得到值：地址一定相同并且类型相同时替换，
+///   中间可能写这个位置的指令（由别名分析判断）使它失效
+///
+/// 内存中的值只在块唯一的前驱是支配树的父节点时带入，否则从空开始
+///
+pub struct Gvn;
+
+impl Pass for Gvn {
+    fn name(&self) -> &'static str {
+        "gvn"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, ptr_bytes: u32) -> bool {
+        let dom = DomTree::new(func);
+        let aa = AliasAnalysis::new(func, ptr_bytes);
+        let preds = func.predecessors();
+        let mut numbering = Numbering {
+            aa: &aa,
+            ptr_bytes,
+            preds: &preds,
+            exprs: FxHashMap::default(),
+            memory: Vec::new(),
+            replace: SecondaryMap::new(),
+            dead: Vec::new(),
+        };
+        numbering.visit(func, &dom, func.entry());
+        if numbering.dead.is_empty() {
+            return false;
+        }
+
+        // phi 的操作数可能来自回边，遍历时还没有替换
+        let Numbering { replace, dead, .. } = numbering;
+        for (_, inst) in func.inst_iter().collect::<Vec<_>>() {
+            for operand in func.insts[inst].kind.operands_mut() {
+                *operand = resolve(&replace, *operand);
+            }
+        }
+        for inst in dead {
+            func.remove_inst(inst);
+        }
+        true
+    }
+}
+
+/// 参与编号的表达式，指令的结果类型也是编号的一部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Expr {
+    Binary(BinaryOp, Value, Value),
+    FNeg(Value),
+    Cmp(CmpPred, Value, Value),
+    Cast(CastOp, Value),
+    Select(Value, Value, Value),
+    Gep(Value, Value, u64, i64),
+}
+
+impl Expr {
+    fn of(kind: &InstKind) -> Option<Self> {
+        let expr = match *kind {
+            InstKind::Binary { op, lhs, rhs, .. } => Expr::Binary(op, lhs, rhs),
+            InstKind::FNeg { val } => Expr::FNeg(val),
+            InstKind::Cmp { pred, lhs, rhs } => Expr::Cmp(pred, lhs, rhs),
+            InstKind::Cast { op, val } => Expr::Cast(op, val),
+            InstKind::Select {
+                cond,
+                then_val,
+                else_val,
+            } => Expr::Select(cond, then_val, else_val),
+            InstKind::Gep {
+                base,
+                index,
+                scale,
+                offset,
+            } => Expr::Gep(base, index, scale, offset),
+            _ => return None,
+        };
+        Some(expr)
+    }
+
+    /// 交换操作数后相同的表达式
+    fn swapped(self) -> Option<Self> {
+        match self {
+            Expr::Binary(op, lhs, rhs) if op.is_commutative() => Some(Expr::Binary(op, rhs, lhs)),
+            Expr::Cmp(pred, lhs, rhs) => Some(Expr::Cmp(pred.swap(), rhs, lhs)),
+            _ => None,
+        }
+    }
+}
+
+/// 内存中已知的值：位置、读写的类型、值
+#[derive(Debug, Clone, Copy)]
+struct Available {
+    loc: Location,
+    ty: Type,
+    value: Value,
+}
+
+/// 替换链的终点
+fn resolve(replace: &SecondaryMap<InstId, Value>, mut value: Value) -> Value {
+    while let Value::Inst(x) = value {
+        match replace.get(x) {
+            Some(next) => value = *next,
+            None => break,
+        }
+    }
+    value
+}
+
+///
+/// 沿支配树编号
+///
+/// # Members
+/// - `exprs`: 支配当前位置的表达式和它的值
+/// - `memory`: 当前位置内存中已知的值
+/// - `replace`: 被删除的指令替换为的值
+/// - `dead`: 要删除的指令
+///
+struct Numbering<'a> {
+    aa: &'a AliasAnalysis,
+    ptr_bytes: u32,
+    preds: &'a SecondaryMap<BlockId, Vec<BlockId>>,
+    exprs: FxHashMap<(Expr, Type), Value>,
+    memory: Vec<Available>,
+    replace: SecondaryMap<InstId, Value>,
+    dead: Vec<InstId>,
+}
+
+impl Numbering<'_> {
+    fn visit(&mut self, func: &mut Function, dom: &DomTree, block: BlockId) {
+        let saved = (self.exprs.clone(), self.memory.clone());
+        let preds = &self.preds[block];
+        if preds.is_empty() || preds.iter().any(|x| Some(*x) != dom.idom(block)) {
+            self.memory.clear();
+        }
+
+        for inst in func.blocks[block].insts.clone() {
+            if func.insts[inst].kind.is_phi() {
+                continue;
+            }
+            for operand in func.insts[inst].kind.operands_mut() {
+                *operand = resolve(&self.replace, *operand);
+            }
+            if let Some(value) = self.number(func, inst) {
+                self.replace.insert(inst, value);
+                self.dead.push(inst);
+            }
+        }
+
+        for child in dom.children(block).to_vec() {
+            self.visit(func, dom, child);
+        }
+        (self.exprs, self.memory) = saved;
+    }
+
+    /// 记录指令的值，返回之前算过的相同的值
+    fn number(&mut self, func: &Function, inst: InstId) -> Option<Value> {
+        let data = &func.insts[inst];
+        if let Some(expr) = Expr::of(&data.kind) {
+            let found = [Some(expr), expr.swapped()]
+                .into_iter()
+                .flatten()
+                .find_map(|x| self.exprs.get(&(x, data.ty)).copied());
+            if found.is_none() {
+                self.exprs.insert((expr, data.ty), Value::Inst(inst));
+            }
+            return found;
+        }
+
+        match data.kind {
+            InstKind::Load {
+                volatile: false, ..
+            } => {
+                let loc = Location::of(func, inst, self.ptr_bytes).unwrap();
+                let found = self.memory.iter().rev().find(|x| {
+                    x.ty == data.ty && self.aa.alias(func, x.loc, loc) == AliasResult::Must
+                });
+                if let Some(x) = found {
+                    return Some(x.value);
+                }
+                self.memory.push(Available {
+                    loc,
+                    ty: data.ty,
+                    value: Value::Inst(inst),
+                });
+            }
+            InstKind::Load { .. } => {}
+            ref kind if kind.has_side_effects() => {
+                let aa = self.aa;
+                self.memory.retain(|x| !aa.may_write(func, inst, x.loc));
+                if let InstKind::Store {
+                    val,
+                    volatile: false,
+                    ..
+                } = *kind
+                {
+                    self.memory.push(Available {
+                        loc: Location::of(func, inst, self.ptr_bytes).unwrap(),
+                        ty: func.value_type(val),
+                        value: val,
+                    });
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
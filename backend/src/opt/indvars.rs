@@ -0,0 +1,447 @@
+use crate::ir::cfg::DomTree;
+use crate::ir::loops::{Loop, LoopInfo, insert_preheaders};
+use crate::ir::{BinaryOp, BlockId, Function, InstData, InstId, InstKind, Value};
+use crate::opt::Pass;
+use crate::opt::fold;
+
+/// 计算循环次数时最多模拟的次数
+const MAX_TRIP: u64 = 1 << 16;
+
+///
+/// 基本归纳变量：`phi = [init, preheader], [next, latch]`，`next = phi + step`
+///
+/// # Members
+/// - `phi`: 循环头中的 phi
+/// - `init`: 进入循环时的值
+/// - `step`: 每次迭代增加的常量
+/// - `next`: 下一次迭代的值，`add` 或 `sub`
+///
+#[derive(Debug, Clone, Copy)]
+pub struct Induction {
+    pub phi: InstId,
+    pub init: Value,
+    pub step: Value,
+    pub next: InstId,
+}
+
+/// 循环中的基本归纳变量，循环需要有 preheader 和唯一的 latch，`ptr_bytes` 是模块的指针宽度
+pub fn inductions(
+    func: &Function,
+    lp: &Loop,
+    preheader: BlockId,
+    ptr_bytes: u32,
+) -> Vec<Induction> {
+    let [latch] = lp.latches.as_slice() else {
+        return Vec::new();
+    };
+    let mut ivs = Vec::new();
+    for phi in func.phis(lp.header) {
+        let ty = func.insts[phi].ty;
+        let InstKind::Phi { incomings } = &func.insts[phi].kind else {
+            unreachable!()
+        };
+        let value = |block: BlockId| incomings.iter().find(|x| x.0 == block).map(|x| x.1);
+        let (Some(init), Some(Value::Inst(next))) = (value(preheader), value(*latch)) else {
+            continue;
+        };
+        if !ty.is_int() || incomings.len() != 2 {
+            continue;
+        }
+        let step = match func.insts[next].kind {
+            InstKind::Binary {
+                op: BinaryOp::Add,
+                lhs,
+                rhs,
+                ..
+            } => match (lhs, rhs) {
+                (Value::Inst(x), c @ Value::Int { .. })
+                | (c @ Value::Int { .. }, Value::Inst(x))
+                    if x == phi =>
+                {
+                    c
+                }
+                _ => continue,
+            },
+            InstKind::Binary {
+                op: BinaryOp::Sub,
+                lhs: Value::Inst(x),
+                rhs: c @ Value::Int { .. },
+                ..
+            } if x == phi => {
+                fold::binary(BinaryOp::Sub, ty, Value::int(ty, 0), c, false, ptr_bytes).unwrap()
+            }
+            _ => continue,
+        };
+        ivs.push(Induction {
+            phi,
+            init,
+            step,
+            next,
+        });
+    }
+    ivs
+}
+
+///
+/// 循环头执行的次数
+///
+/// 只处理这样的循环：只有一条离开循环的边，出口在循环头或唯一的 latch，出口的条件是归纳变量
+/// （`phi` 或 `next`）和常量的比较，这些归纳变量的初始值是常量。循环中不能有 `ret`；
+/// 按位宽回绕模拟执行，`nsw` 溢出或超过 `MAX_TRIP` 次时返回 None
+///
+pub fn trip_count(func: &Function, lp: &Loop, ivs: &[Induction], ptr_bytes: u32) -> Option<u64> {
+    let exits = lp.exits(func);
+    let [(exiting, exit)] = exits.as_slice() else {
+        return None;
+    };
+    if *exiting != lp.header && lp.latches != [*exiting] {
+        return None;
+    }
+    if lp.blocks.iter().any(|x| func.successors(*x).is_empty()) {
+        return None;
+    }
+    let InstKind::CondBr {
+        cond: Value::Inst(cond),
+        then_dest,
+        ..
+    } = func.insts[func.terminator(*exiting)?].kind
+    else {
+        return None;
+    };
+    let InstKind::Cmp { pred, lhs, rhs } = func.insts[cond].kind else {
+        return None;
+    };
+    let exit_when = then_dest == *exit;
+    // 只模拟条件中出现的归纳变量
+    let ivs: Vec<&Induction> = ivs
+        .iter()
+        .filter(|iv| {
+            [lhs, rhs]
+                .iter()
+                .any(|x| *x == Value::Inst(iv.phi) || *x == Value::Inst(iv.next))
+        })
+        .collect();
+    if ivs.iter().any(|x| !x.init.is_const()) {
+        return None;
+    }
+
+    // 第 k 次执行循环头时每个归纳变量的值
+    let mut values: Vec<Value> = ivs.iter().map(|x| x.init).collect();
+    let next_value = |iv: &Induction, value: Value| {
+        let InstKind::Binary { op, lhs, rhs, nsw } = func.insts[iv.next].kind else {
+            unreachable!()
+        };
+        let operand = |x: Value| if x == Value::Inst(iv.phi) { value } else { x };
+        let ty = func.insts[iv.next].ty;
+        fold::binary(op, ty, operand(lhs), operand(rhs), nsw, ptr_bytes)
+    };
+    for k in 0..MAX_TRIP {
+        let eval = |x: Value| -> Option<Value> {
+            if x.is_const() {
+                return Some(x);
+            }
+            let pos = ivs.iter().position(|iv| x == Value::Inst(iv.phi));
+            if let Some(i) = pos {
+                return Some(values[i]);
+            }
+            let i = ivs.iter().position(|iv| x == Value::Inst(iv.next))?;
+            next_value(ivs[i], values[i])
+        };
+        let taken = fold::compare(pred, eval(lhs)?, eval(rhs)?)?.as_uint()? == 1;
+        if taken == exit_when {
+            return Some(k + 1);
+        }
+        for (i, iv) in ivs.iter().enumerate() {
+            values[i] = next_value(iv, values[i])?;
+        }
+    }
+    None
+}
+
+///
+/// 归纳变量化简和强度削弱
+///
+/// - 初始值和步长都相同的归纳变量合并为一个
+/// - 循环次数确定时，循环外对归纳变量的使用替换为离开循环时的常量
+/// - 循环中的 `mul iv, c` `shl iv, c` 改为每次迭代加 `step * c` 的新归纳变量
+/// - 循环中以归纳变量为下标的 `gep` 改为每次迭代前进 `step * scale` 的指针归纳变量，
+///   归纳变量不是指针宽度时要求 `next` 带 `nsw`（下标不回绕）
+///
+pub struct IndVars;
+
+impl Pass for IndVars {
+    fn name(&self) -> &'static str {
+        "indvars"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, ptr_bytes: u32) -> bool {
+        let mut changed = insert_preheaders(func);
+        let dom = DomTree::new(func);
+        let info = LoopInfo::new(func, &dom);
+        for id in info.postorder() {
+            let lp = &info.loops()[id];
+            let Some(preheader) = lp.preheader(func) else {
+                continue;
+            };
+            changed |= merge_duplicates(func, &dom, lp, preheader, ptr_bytes);
+            let ivs = inductions(func, lp, preheader, ptr_bytes);
+            if let Some(count) = trip_count(func, lp, &ivs, ptr_bytes) {
+                changed |= replace_exit_values(func, lp, &ivs, count, ptr_bytes);
+            }
+            changed |= strength_reduce(func, lp, preheader, &ivs, ptr_bytes);
+        }
+        changed
+    }
+}
+
+/// 合并相同的归纳变量，保留 `next` 支配另一个 `next` 的那个，这样替换后的使用仍然被定义支配
+fn merge_duplicates(
+    func: &mut Function,
+    dom: &DomTree,
+    lp: &Loop,
+    preheader: BlockId,
+    ptr_bytes: u32,
+) -> bool {
+    let ivs = inductions(func, lp, preheader, ptr_bytes);
+    let mut changed = false;
+    let mut kept: Vec<Induction> = Vec::new();
+    for iv in ivs {
+        let ty = func.insts[iv.phi].ty;
+        let same = kept
+            .iter()
+            .position(|x| x.init == iv.init && x.step == iv.step && func.insts[x.phi].ty == ty);
+        let Some(i) = same else {
+            kept.push(iv);
+            continue;
+        };
+        let (keep, dead) = if inst_dominates(func, dom, kept[i].next, iv.next) {
+            (kept[i], iv)
+        } else if inst_dominates(func, dom, iv.next, kept[i].next) {
+            let old = kept[i];
+            kept[i] = iv;
+            (iv, old)
+        } else {
+            continue;
+        };
+        func.replace_all_uses(Value::Inst(dead.phi), Value::Inst(keep.phi));
+        func.replace_all_uses(Value::Inst(dead.next), Value::Inst(keep.next));
+        func.remove_inst(dead.phi);
+        func.remove_inst(dead.next);
+        changed = true;
+    }
+    changed
+}
+
+fn inst_dominates(func: &Function, dom: &DomTree, a: InstId, b: InstId) -> bool {
+    let (x, y) = (func.inst_block(a).unwrap(), func.inst_block(b).unwrap());
+    if x == y {
+        func.inst_pos(a) <= func.inst_pos(b)
+    } else {
+        dom.dominates(x, y)
+    }
+}
+
+/// 离开循环时 `phi` 为 `init + (count - 1) * step`，`next` 为 `init + count * step`
+fn replace_exit_values(
+    func: &mut Function,
+    lp: &Loop,
+    ivs: &[Induction],
+    count: u64,
+    ptr_bytes: u32,
+) -> bool {
+    let mut changed = false;
+    for iv in ivs {
+        let ty = func.insts[iv.phi].ty;
+        let value = |n: u64| {
+            let n = Value::int(ty, n as i64);
+            let offset = fold::binary(BinaryOp::Mul, ty, iv.step, n, false, ptr_bytes)?;
+            fold::binary(BinaryOp::Add, ty, iv.init, offset, false, ptr_bytes)
+        };
+        for (old, new) in [(iv.phi, value(count - 1)), (iv.next, value(count))] {
+            let Some(new) = new else {
+                continue;
+            };
+            for block in func.layout.clone() {
+                if lp.contains(block) {
+                    continue;
+                }
+                for inst in func.blocks[block].insts.clone() {
+                    for operand in func.insts[inst].kind.operands_mut() {
+                        if *operand == Value::Inst(old) {
+                            *operand = new;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// 可以强度削弱的指令：`iv * factor`，或者 `gep base, iv, scale, offset`
+enum Recurrence {
+    Mul(Value),
+    Gep {
+        base: Value,
+        scale: u64,
+        offset: i64,
+    },
+}
+
+fn recurrence(
+    func: &Function,
+    lp: &Loop,
+    iv: &Induction,
+    inst: InstId,
+    ptr_bytes: u32,
+) -> Option<Recurrence> {
+    let ty = func.insts[iv.phi].ty;
+    let phi = Value::Inst(iv.phi);
+    match func.insts[inst].kind {
+        InstKind::Binary {
+            op: BinaryOp::Mul,
+            lhs,
+            rhs,
+            ..
+        } => match (lhs, rhs) {
+            (x, c @ Value::Int { .. }) | (c @ Value::Int { .. }, x) if x == phi => {
+                Some(Recurrence::Mul(c))
+            }
+            _ => None,
+        },
+        InstKind::Binary {
+            op: BinaryOp::Shl,
+            lhs,
+            rhs: Value::Int { bits, .. },
+            ..
+        } if lhs == phi && bits < ty.bits(ptr_bytes) as u64 => {
+            Some(Recurrence::Mul(Value::int(ty, 1i64 << bits)))
+        }
+        InstKind::Gep {
+            base,
+            index,
+            scale,
+            offset,
+        } if index == phi && is_invariant(func, lp, base) => {
+            // 下标回绕时指针不会跟着回绕
+            let no_wrap = ty.bits(ptr_bytes) == ptr_bytes * 8
+                || matches!(func.insts[iv.next].kind, InstKind::Binary { nsw: true, .. });
+            no_wrap.then_some(Recurrence::Gep {
+                base,
+                scale,
+                offset,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn strength_reduce(
+    func: &mut Function,
+    lp: &Loop,
+    preheader: BlockId,
+    ivs: &[Induction],
+    ptr_bytes: u32,
+) -> bool {
+    let latch = lp.latches[0];
+    let mut changed = false;
+    for iv in ivs {
+        let ty = func.insts[iv.phi].ty;
+        for block in lp.blocks.iter().cloned() {
+            for inst in func.blocks[block].insts.clone() {
+                if inst == iv.next {
+                    continue;
+                }
+                let Some(rec) = recurrence(func, lp, iv, inst, ptr_bytes) else {
+                    continue;
+                };
+                let inst_ty = func.insts[inst].ty;
+                let new_phi = func.insert_inst(
+                    lp.header,
+                    0,
+                    InstData {
+                        kind: InstKind::Phi {
+                            incomings: Vec::new(),
+                        },
+                        ty: inst_ty,
+                    },
+                );
+
+                // 进入循环时的值，不是常量时在 preheader 中计算
+                let (start, next) = match rec {
+                    Recurrence::Mul(factor) => {
+                        let start =
+                            fold::binary(BinaryOp::Mul, ty, iv.init, factor, false, ptr_bytes);
+                        let start = start.ok_or(InstKind::Binary {
+                            op: BinaryOp::Mul,
+                            lhs: iv.init,
+                            rhs: factor,
+                            nsw: false,
+                        });
+                        let step =
+                            fold::binary(BinaryOp::Mul, ty, iv.step, factor, false, ptr_bytes);
+                        let step = step.unwrap();
+                        let next = InstKind::Binary {
+                            op: BinaryOp::Add,
+                            lhs: Value::Inst(new_phi),
+                            rhs: step,
+                            nsw: false,
+                        };
+                        (start, next)
+                    }
+                    Recurrence::Gep {
+                        base,
+                        scale,
+                        offset,
+                    } => {
+                        let start = match (iv.init.as_uint(), offset) {
+                            (Some(0), 0) => Ok(base),
+                            _ => Err(InstKind::Gep {
+                                base,
+                                index: iv.init,
+                                scale,
+                                offset,
+                            }),
+                        };
+                        let next = InstKind::Gep {
+                            base: Value::Inst(new_phi),
+                            index: iv.step,
+                            scale,
+                            offset: 0,
+                        };
+                        (start, next)
+                    }
+                };
+                let start = match start {
+                    Ok(x) => x,
+                    Err(kind) => {
+                        let term = func.terminator(preheader).unwrap();
+                        let data = InstData { kind, ty: inst_ty };
+                        Value::Inst(func.insert_inst_before(term, data))
+                    }
+                };
+                let term = func.terminator(latch).unwrap();
+                let data = InstData {
+                    kind: next,
+                    ty: inst_ty,
+                };
+                let new_next = func.insert_inst_before(term, data);
+                func.insts[new_phi].kind = InstKind::Phi {
+                    incomings: vec![(preheader, start), (latch, Value::Inst(new_next))],
+                };
+                func.replace_all_uses(Value::Inst(inst), Value::Inst(new_phi));
+                func.remove_inst(inst);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+fn is_invariant(func: &Function, lp: &Loop, value: Value) -> bool {
+    match value {
+        Value::Inst(x) => func.inst_block(x).is_some_and(|b| !lp.contains(b)),
+        _ => true,
+    }
+}
@@ -0,0 +1,341 @@
+use crate::ir::{
+    BlockId, FuncId, Function, InlineAttr, InstData, InstId, InstKind, Linkage, Module, ParamAttr,
+    Type, Value,
+};
+use crate::opt::Pass;
+use crate::opt::call_graph::CallGraph;
+use slotmap::SecondaryMap;
+
+///
+/// 函数内联
+///
+/// 按调用图自底向上处理，被调用者先完成内联；调用点是否内联由代价模型决定：
+/// - `noinline` 的函数、声明、变参函数、签名和调用不一致的调用从不内联
+/// - `always_inline` 的函数总是内联
+/// - 只有这一个调用点、地址没有被取走的 `static` 函数总是内联，之后函数本身可以删除
+/// - 其他函数的指令数不超过阈值时内联，`inline` 的函数使用更高的阈值
+///
+/// 每个调用点记录它是由哪些函数的内联带进来的；被调用者在这条链中（包括调用者自己）已经出现
+/// `recursion_limit` 次时不再内联，递归函数不会无限展开
+///
+/// # Members
+/// - `threshold`: 普通函数的指令数阈值
+/// - `hint_threshold`: `inline` 函数的指令数阈值
+/// - `recursion_limit`: 递归函数展开的最大次数，`0` 表示不展开递归调用
+/// - `max_size`: 调用者超过这个指令数后只内联 `always_inline` 的函数
+///
+pub struct Inliner {
+    pub threshold: usize,
+    pub hint_threshold: usize,
+    pub recursion_limit: u32,
+    pub max_size: usize,
+}
+
+impl Default for Inliner {
+    fn default() -> Self {
+        Self {
+            threshold: 40,
+            hint_threshold: 120,
+            recursion_limit: 0,
+            max_size: 10000,
+        }
+    }
+}
+
+impl Pass for Inliner {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    fn run_on_module(&mut self, module: &mut Module) -> bool {
+        let graph = CallGraph::new(module);
+        let sccs = graph.sccs(module);
+        let mut call_counts = call_counts(module);
+        let mut changed = false;
+        for scc in sccs.iter() {
+            for caller in scc.iter().cloned() {
+                if module.funcs[caller].is_declaration() {
+                    continue;
+                }
+                // 调用点和带来它的内联链
+                let mut work: Vec<(InstId, Vec<FuncId>)> = calls(&module.funcs[caller])
+                    .into_iter()
+                    .rev()
+                    .map(|x| (x, vec![caller]))
+                    .collect();
+                while let Some((call, history)) = work.pop() {
+                    let func = &module.funcs[caller];
+                    let InstKind::Call {
+                        callee: Value::Func(callee),
+                        ..
+                    } = func.insts[call].kind
+                    else {
+                        continue;
+                    };
+                    let expanded = history.iter().filter(|x| **x == callee).count();
+                    if expanded > self.recursion_limit as usize {
+                        continue;
+                    }
+                    let recursive = scc.contains(&callee);
+                    let single = call_counts.get(callee) == Some(&1)
+                        && module.funcs[callee].linkage == Linkage::Internal
+                        && !graph.is_address_taken(callee)
+                        && !recursive;
+                    if !self.should_inline(func, call, &module.funcs[callee], single) {
+                        continue;
+                    }
+
+                    let body = module.funcs[callee].clone();
+                    let new_calls = inline_call(&mut module.funcs[caller], call, &body);
+                    *call_counts.entry(callee).unwrap().or_default() -= 1;
+                    let mut history = history;
+                    history.push(callee);
+                    for inst in new_calls.iter().rev() {
+                        if let InstKind::Call {
+                            callee: Value::Func(x),
+                            ..
+                        } = module.funcs[caller].insts[*inst].kind
+                        {
+                            *call_counts.entry(x).unwrap().or_default() += 1;
+                        }
+                        work.push((*inst, history.clone()));
+                    }
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+}
+
+impl Inliner {
+    fn should_inline(
+        &self,
+        caller: &Function,
+        call: InstId,
+        callee: &Function,
+        single: bool,
+    ) -> bool {
+        let InstKind::Call { sig, .. } = &caller.insts[call].kind else {
+            unreachable!()
+        };
+        if callee.is_declaration()
+            || callee.inline == InlineAttr::Never
+            || callee.sig.variadic
+            || *sig != callee.sig
+        {
+            return false;
+        }
+        if callee.inline == InlineAttr::Always {
+            return true;
+        }
+        if caller.insts.len() > self.max_size {
+            return false;
+        }
+        if single {
+            return true;
+        }
+        let threshold = match callee.inline {
+            InlineAttr::Hint => self.hint_threshold,
+            _ => self.threshold,
+        };
+        cost(callee) <= threshold
+    }
+}
+
+/// 内联后增加的指令数，不计 `ret` 和 `alloca`
+fn cost(func: &Function) -> usize {
+    func.inst_iter()
+        .filter(|(_, x)| {
+            !matches!(
+                func.insts[*x].kind,
+                InstKind::Ret { .. } | InstKind::Alloca { .. }
+            )
+        })
+        .count()
+}
+
+/// 函数中的直接调用，按 layout 顺序
+fn calls(func: &Function) -> Vec<InstId> {
+    func.inst_iter()
+        .map(|x| x.1)
+        .filter(|x| {
+            matches!(
+                func.insts[*x].kind,
+                InstKind::Call {
+                    callee: Value::Func(_),
+                    ..
+                }
+            )
+        })
+        .collect()
+}
+
+/// 模块中每个函数的直接调用点个数
+fn call_counts(module: &Module) -> SecondaryMap<FuncId, usize> {
+    let mut counts: SecondaryMap<FuncId, usize> = SecondaryMap::new();
+    for (_, func) in module.funcs.iter() {
+        for call in calls(func) {
+            if let InstKind::Call {
+                callee: Value::Func(x),
+                ..
+            } = func.insts[call].kind
+            {
+                *counts.entry(x).unwrap().or_default() += 1;
+            }
+        }
+    }
+    counts
+}
+
+///
+/// 把 `callee` 的函数体复制到调用点，返回复制进来的调用指令
+///
+/// 调用所在的块在调用处拆开，调用之前的部分跳到复制的入口块，`ret` 改为跳到拆出的后半部分，
+/// 多个返回值在后半部分开头用 phi 合并；复制的块放在两部分之间。
+/// `byval` 参数在调用者中复制一份；被调用者入口块中的 `alloca` 移到调用者的入口块，避免在循环中重复分配
+///
+pub fn inline_call(caller: &mut Function, call: InstId, callee: &Function) -> Vec<InstId> {
+    let block = caller.inst_block(call).unwrap();
+    let pos = caller.inst_pos(call);
+    let InstKind::Call { args, .. } = caller.insts[call].kind.clone() else {
+        unreachable!()
+    };
+    let ret_ty = caller.insts[call].ty;
+
+    // 拆开调用所在的块
+    let cont = caller.add_block();
+    let tail: Vec<InstId> = caller.blocks[block].insts.drain(pos + 1..).collect();
+    for inst in tail {
+        caller.detach_inst(inst);
+        let len = caller.blocks[cont].insts.len();
+        caller.attach_inst(cont, len, inst);
+    }
+    for succ in caller.successors(cont) {
+        caller.replace_phi_pred(succ, block, cont);
+    }
+
+    // 实参，`byval` 的参数传递副本的地址
+    let entry = caller.entry();
+    let mut alloca_pos = caller.phis(entry).len();
+    let mut actuals = Vec::with_capacity(args.len());
+    for (i, arg) in args.iter().cloned().enumerate() {
+        match callee.sig.params[i].attr {
+            ParamAttr::ByVal { size, align, .. } => {
+                let data = InstData {
+                    kind: InstKind::Alloca {
+                        size: size as u64,
+                        align,
+                    },
+                    ty: Type::Ptr,
+                };
+                let copy = caller.insert_inst(entry, alloca_pos, data);
+                alloca_pos += 1;
+                let data = InstData {
+                    kind: InstKind::MemCopy {
+                        dst: Value::Inst(copy),
+                        src: arg,
+                        size: size as u64,
+                        align,
+                    },
+                    ty: Type::Void,
+                };
+                caller.insert_inst_before(call, data);
+                actuals.push(Value::Inst(copy));
+            }
+            _ => actuals.push(arg),
+        }
+    }
+
+    // 复制基本块和指令，再替换操作数和跳转目标
+    let mut block_map: SecondaryMap<BlockId, BlockId> = SecondaryMap::new();
+    let mut inst_map: SecondaryMap<InstId, InstId> = SecondaryMap::new();
+    let mut new_blocks = Vec::with_capacity(callee.layout.len());
+    for old in callee.layout.iter().cloned() {
+        let new = caller.add_block();
+        block_map.insert(old, new);
+        new_blocks.push(new);
+    }
+    let mut new_insts = Vec::new();
+    for old in callee.layout.iter().cloned() {
+        for inst in callee.blocks[old].insts.iter().cloned() {
+            let new = caller.append_inst(block_map[old], callee.insts[inst].clone());
+            inst_map.insert(inst, new);
+            new_insts.push(new);
+        }
+    }
+    let mut returns: Vec<(BlockId, Option<Value>)> = Vec::new();
+    for inst in new_insts.iter().cloned() {
+        let inst_block = caller.inst_block(inst).unwrap();
+        let kind = &mut caller.insts[inst].kind;
+        for operand in kind.operands_mut() {
+            *operand = match *operand {
+                Value::Inst(x) => Value::Inst(inst_map[x]),
+                Value::Arg(x) => actuals[x as usize],
+                x => x,
+            };
+        }
+        for succ in kind.successors_mut() {
+            *succ = block_map[*succ];
+        }
+        if let InstKind::Phi { incomings } = kind {
+            for incoming in incomings.iter_mut() {
+                incoming.0 = block_map[incoming.0];
+            }
+        }
+        if let InstKind::Ret { val } = *kind {
+            *kind = InstKind::Br { dest: cont };
+            returns.push((inst_block, val));
+        }
+    }
+
+    // 被调用者入口块中的 alloca 移到调用者的入口块
+    let callee_entry = new_blocks[0];
+    for inst in caller.blocks[callee_entry].insts.clone() {
+        if let InstKind::Alloca { .. } = caller.insts[inst].kind {
+            caller.detach_inst(inst);
+            caller.attach_inst(entry, alloca_pos, inst);
+            alloca_pos += 1;
+        }
+    }
+
+    // 返回值
+    if ret_ty != Type::Void {
+        let result = match returns.as_slice() {
+            [] => Value::Undef(ret_ty),
+            [(_, val)] => val.unwrap(),
+            _ => {
+                let incomings = returns.iter().map(|(b, v)| (*b, v.unwrap())).collect();
+                let data = InstData {
+                    kind: InstKind::Phi { incomings },
+                    ty: ret_ty,
+                };
+                Value::Inst(caller.insert_inst(cont, 0, data))
+            }
+        };
+        caller.replace_all_uses(Value::Inst(call), result);
+    }
+    caller.remove_inst(call);
+    caller.append_inst(
+        block,
+        InstData {
+            kind: InstKind::Br { dest: callee_entry },
+            ty: Type::Void,
+        },
+    );
+
+    // 复制的块和后半部分紧跟在调用所在的块之后
+    caller
+        .layout
+        .retain(|x| !new_blocks.contains(x) && *x != cont);
+    let at = caller.layout.iter().position(|x| *x == block).unwrap() + 1;
+    caller
+        .layout
+        .splice(at..at, new_blocks.into_iter().chain([cont]));
+
+    new_insts
+        .into_iter()
+        .filter(|x| caller.insts.contains_key(*x))
+        .filter(|x| matches!(caller.insts[*x].kind, InstKind::Call { .. }))
+        .collect()
+}
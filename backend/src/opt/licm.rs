@@ -0,0 +1,121 @@
+use crate::ir::cfg::DomTree;
+use crate::ir::loops::{Loop, LoopInfo, insert_preheaders};
+use crate::ir::{BinaryOp, BlockId, CastOp, Function, InstKind, Value};
+use crate::opt::Pass;
+
+///
+/// 循环不变量外提
+///
+/// 由内向外处理每个循环，操作数都在循环外定义的指令移到 preheader：
+/// - 没有副作用的运算、比较、地址计算可以直接外提，`nsw` 的运算在不保证执行时去掉 `nsw`
+/// - 可能出错的运算（除法、移位量不是常量的移位、浮点转整数）只在保证执行时外提
+/// - `load` 只在循环中没有写内存的指令并且保证执行时外提
+///
+/// 保证执行：循环中没有调用（调用可能不返回），指令所在的块支配所有离开循环的块
+///
+pub struct Licm;
+
+impl Pass for Licm {
+    fn name(&self) -> &'static str {
+        "licm"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, ptr_bytes: u32) -> bool {
+        let mut changed = insert_preheaders(func);
+        let dom = DomTree::new(func);
+        let info = LoopInfo::new(func, &dom);
+        for id in info.postorder() {
+            let lp = &info.loops()[id];
+            if let Some(preheader) = lp.preheader(func) {
+                changed |= hoist(func, &dom, lp, preheader, ptr_bytes);
+            }
+        }
+        changed
+    }
+}
+
+fn hoist(
+    func: &mut Function,
+    dom: &DomTree,
+    lp: &Loop,
+    preheader: BlockId,
+    ptr_bytes: u32,
+) -> bool {
+    let mut writes = false;
+    let mut calls = false;
+    for block in lp.blocks.iter() {
+        for inst in func.blocks[*block].insts.iter() {
+            let kind = &func.insts[*inst].kind;
+            writes |= kind.has_side_effects() && !kind.is_terminator();
+            calls |= matches!(kind, InstKind::Call { .. });
+        }
+    }
+    let exiting: Vec<BlockId> = lp.exits(func).into_iter().map(|x| x.0).collect();
+    let guaranteed = |block: BlockId| {
+        !calls && !exiting.is_empty() && exiting.iter().all(|x| dom.dominates(block, *x))
+    };
+
+    let mut changed = false;
+    for block in lp.blocks.iter().cloned() {
+        for inst in func.blocks[block].insts.clone() {
+            let kind = &func.insts[inst].kind;
+            let invariant = kind.operands().into_iter().all(|x| match x {
+                Value::Inst(x) => func.inst_block(x).is_some_and(|b| !lp.contains(b)),
+                _ => true,
+            });
+            if !invariant {
+                continue;
+            }
+            let hoistable = match kind {
+                InstKind::Binary { op, rhs, .. } => match op {
+                    BinaryOp::SDiv | BinaryOp::SRem => {
+                        safe_divisor(*rhs, true, ptr_bytes) || guaranteed(block)
+                    }
+                    BinaryOp::UDiv | BinaryOp::URem => {
+                        safe_divisor(*rhs, false, ptr_bytes) || guaranteed(block)
+                    }
+                    BinaryOp::Shl | BinaryOp::LShr | BinaryOp::AShr => {
+                        let width = func.insts[inst].ty.bits(ptr_bytes) as u64;
+                        rhs.as_uint().is_some_and(|x| x < width) || guaranteed(block)
+                    }
+                    _ => true,
+                },
+                InstKind::Cast { op, .. } => {
+                    !matches!(op, CastOp::FpToSi | CastOp::FpToUi) || guaranteed(block)
+                }
+                InstKind::FNeg { .. }
+                | InstKind::Cmp { .. }
+                | InstKind::Select { .. }
+                | InstKind::Gep { .. } => true,
+                InstKind::Load {
+                    volatile: false, ..
+                } => !writes && guaranteed(block),
+                _ => false,
+            };
+            if !hoistable {
+                continue;
+            }
+
+            if let InstKind::Binary { nsw, .. } = &mut func.insts[inst].kind
+                && !guaranteed(block)
+            {
+                *nsw = false;
+            }
+            func.detach_inst(inst);
+            let pos = func.blocks[preheader].insts.len() - 1;
+            func.attach_inst(preheader, pos, inst);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// 除数是不会出错的常量：不为 0，有符号除法时不为 -1（`INT_MIN / -1` 溢出）
+fn safe_divisor(rhs: Value, signed: bool, ptr_bytes: u32) -> bool {
+    match rhs {
+        Value::Int { ty, bits } => {
+            bits != 0 && !(signed && bits == u64::MAX >> (64 - ty.bits(ptr_bytes)))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,278 @@
+use crate::ir::cfg::DomTree;
+use crate::ir::{BlockId, Function, InstData, InstId, InstKind, Type, Value};
+use crate::opt::Pass;
+use rustc_hash::FxHashMap;
+use slotmap::SecondaryMap;
+
+///
+/// 把 `alloca` 提升为 SSA 值（Cytron 等人的算法）
+///
+/// 可以提升的 `alloca`：所有使用都是以它为地址的非 volatile `load` / `store`，并且访问的类型相同；
+/// 在存储块的迭代支配边界上放置 phi，然后沿支配树重命名，`load` 替换为当前的值，`store` 删除；
+/// 读到未写过的值时为 `undef`
+///
+pub struct Mem2Reg;
+
+impl Pass for Mem2Reg {
+    fn name(&self) -> &'static str {
+        "mem2reg"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, _ptr_bytes: u32) -> bool {
+        let allocas = promotable(func);
+        if allocas.is_empty() {
+            return false;
+        }
+        let dom = DomTree::new(func);
+        let phis = place_phis(func, &dom, &allocas);
+        let mut renamer = Renamer {
+            allocas: &allocas,
+            phis: &phis,
+            current: allocas
+                .keys()
+                .map(|x| (*x, Value::Undef(allocas[x])))
+                .collect(),
+            replace: SecondaryMap::new(),
+            dead: Vec::new(),
+        };
+        renamer.rename(func, &dom, func.entry());
+
+        // 来自不可达前驱的边没有经过重命名；入边按前驱的顺序排列
+        let preds = func.predecessors();
+        for phi in phis.keys() {
+            let block = func.inst_block(*phi).unwrap();
+            let ty = func.insts[*phi].ty;
+            if let InstKind::Phi { incomings } = &mut func.insts[*phi].kind {
+                *incomings = preds[block]
+                    .iter()
+                    .map(|pred| match incomings.iter().find(|x| x.0 == *pred) {
+                        Some(x) => *x,
+                        None => (*pred, Value::Undef(ty)),
+                    })
+                    .collect();
+            }
+        }
+
+        // 不可达块中的访问不在支配树中，load 得到 undef
+        for block in func.layout.clone() {
+            if dom.is_reachable(block) {
+                continue;
+            }
+            for inst in func.blocks[block].insts.clone() {
+                match access(func, inst) {
+                    Some((alloca, true)) if allocas.contains_key(&alloca) => {
+                        let ty = allocas[&alloca];
+                        renamer.replace.insert(inst, Value::Undef(ty));
+                        renamer.dead.push(inst);
+                    }
+                    Some((alloca, false)) if allocas.contains_key(&alloca) => {
+                        renamer.dead.push(inst)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let Renamer { replace, dead, .. } = renamer;
+        for (_, data) in func.insts.iter_mut() {
+            for operand in data.kind.operands_mut() {
+                *operand = resolve(&replace, *operand);
+            }
+        }
+        for inst in dead {
+            func.remove_inst(inst);
+        }
+        for alloca in allocas.keys() {
+            func.remove_inst(*alloca);
+        }
+        true
+    }
+}
+
+/// `inst` 以 `alloca` 为地址访问内存时，返回 `alloca` 和是否是 load
+fn access(func: &Function, inst: InstId) -> Option<(InstId, bool)> {
+    match func.insts[inst].kind {
+        InstKind::Load {
+            ptr: Value::Inst(x),
+            ..
+        } => Some((x, true)),
+        InstKind::Store {
+            ptr: Value::Inst(x),
+            ..
+        } => Some((x, false)),
+        _ => None,
+    }
+}
+
+/// 可以提升的 `alloca` 和访问它的类型
+fn promotable(func: &Function) -> FxHashMap<InstId, Type> {
+    let mut candidates: FxHashMap<InstId, Option<Type>> = FxHashMap::default();
+    for (_, inst) in func.inst_iter() {
+        if let InstKind::Alloca { .. } = func.insts[inst].kind {
+            candidates.insert(inst, None);
+        }
+    }
+    let mut rejected = Vec::new();
+    for (_, inst) in func.inst_iter() {
+        let data = &func.insts[inst];
+        let (ty, direct) = match &data.kind {
+            InstKind::Load { volatile, .. } => (data.ty, !*volatile),
+            InstKind::Store { val, volatile, .. } => {
+                // 地址本身被存到内存中时逃逸
+                if let Value::Inst(x) = val {
+                    rejected.push(*x);
+                }
+                (func.value_type(*val), !*volatile)
+            }
+            _ => {
+                rejected.extend(data.kind.operands().iter().filter_map(|x| x.as_inst()));
+                continue;
+            }
+        };
+        let Some((alloca, _)) = access(func, inst) else {
+            continue;
+        };
+        let Some(seen) = candidates.get_mut(&alloca) else {
+            continue;
+        };
+        if !direct || seen.is_some_and(|x| x != ty) {
+            rejected.push(alloca);
+        }
+        *seen = Some(ty);
+    }
+    for inst in rejected {
+        candidates.remove(&inst);
+    }
+    // 从未访问的 alloca 留给 DCE
+    candidates
+        .into_iter()
+        .filter_map(|(inst, ty)| Some((inst, ty?)))
+        .collect()
+}
+
+/// 在每个 `alloca` 的存储块的迭代支配边界上放置 phi，返回 phi 对应的 `alloca`
+fn place_phis(
+    func: &mut Function,
+    dom: &DomTree,
+    allocas: &FxHashMap<InstId, Type>,
+) -> FxHashMap<InstId, InstId> {
+    let frontiers = dom.frontiers(func);
+    let mut defs: FxHashMap<InstId, Vec<BlockId>> = FxHashMap::default();
+    for (block, inst) in func.inst_iter() {
+        if let Some((alloca, false)) = access(func, inst)
+            && allocas.contains_key(&alloca)
+            && dom.is_reachable(block)
+        {
+            defs.entry(alloca).or_default().push(block);
+        }
+    }
+
+    // 按 alloca 在函数中出现的顺序处理，保证输出稳定
+    let order: Vec<InstId> = func
+        .inst_iter()
+        .map(|x| x.1)
+        .filter(|x| allocas.contains_key(x))
+        .collect();
+    let mut phis = FxHashMap::default();
+    for alloca in order {
+        let mut work = defs.remove(&alloca).unwrap_or_default();
+        let mut placed: Vec<BlockId> = Vec::new();
+        while let Some(block) = work.pop() {
+            for x in frontiers[block].iter().cloned() {
+                if placed.contains(&x) {
+                    continue;
+                }
+                placed.push(x);
+                let phi = func.insert_inst(
+                    x,
+                    0,
+                    InstData {
+                        kind: InstKind::Phi {
+                            incomings: Vec::new(),
+                        },
+                        ty: allocas[&alloca],
+                    },
+                );
+                phis.insert(phi, alloca);
+                work.push(x);
+            }
+        }
+    }
+    phis
+}
+
+/// 替换链的终点
+fn resolve(replace: &SecondaryMap<InstId, Value>, mut value: Value) -> Value {
+    while let Value::Inst(x) = value {
+        match replace.get(x) {
+            Some(next) => value = *next,
+            None => break,
+        }
+    }
+    value
+}
+
+///
+/// 沿支配树重命名
+///
+/// # Members
+/// - `allocas`: 提升的 `alloca` 和类型
+/// - `phis`: 放置的 phi 对应的 `alloca`
+/// - `current`: 每个 `alloca` 当前的值
+/// - `replace`: 被删除的 load 替换为的值
+/// - `dead`: 要删除的 load 和 store
+///
+struct Renamer<'a> {
+    allocas: &'a FxHashMap<InstId, Type>,
+    phis: &'a FxHashMap<InstId, InstId>,
+    current: FxHashMap<InstId, Value>,
+    replace: SecondaryMap<InstId, Value>,
+    dead: Vec<InstId>,
+}
+
+impl Renamer<'_> {
+    fn rename(&mut self, func: &mut Function, dom: &DomTree, block: BlockId) {
+        let saved = self.current.clone();
+        for inst in func.blocks[block].insts.clone() {
+            if let Some(alloca) = self.phis.get(&inst) {
+                self.current.insert(*alloca, Value::Inst(inst));
+                continue;
+            }
+            match access(func, inst) {
+                Some((alloca, true)) if self.allocas.contains_key(&alloca) => {
+                    self.replace.insert(inst, self.current[&alloca]);
+                    self.dead.push(inst);
+                }
+                Some((alloca, false)) if self.allocas.contains_key(&alloca) => {
+                    let InstKind::Store { val, .. } = func.insts[inst].kind else {
+                        unreachable!()
+                    };
+                    self.current.insert(alloca, resolve(&self.replace, val));
+                    self.dead.push(inst);
+                }
+                _ => {}
+            }
+        }
+
+        let mut succs = func.successors(block);
+        succs.dedup();
+        for succ in succs {
+            for phi in func.phis(succ) {
+                let Some(alloca) = self.phis.get(&phi) else {
+                    continue;
+                };
+                let value = self.current[alloca];
+                if let InstKind::Phi { incomings } = &mut func.insts[phi].kind
+                    && !incomings.iter().any(|x| x.0 == block)
+                {
+                    incomings.push((block, value));
+                }
+            }
+        }
+
+        for child in dom.children(block).to_vec() {
+            self.rename(func, dom, child);
+        }
+        self.current = saved;
+    }
+}
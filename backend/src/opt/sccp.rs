@@ -0,0 +1,345 @@
+use crate::ir::{BlockId, Function, InstId, InstKind, Value};
+use crate::opt::{Pass, fold};
+use rustc_hash::FxHashSet;
+use slotmap::SecondaryMap;
+
+///
+/// 稀疏条件常量传播（Wegman、Zadeck）
+///
+/// 只沿可执行的边传播，常量条件的分支只有一边可执行；结束后常量替换为字面值，
+/// 常量条件的分支改为无条件跳转，从未执行的基本块删除
+///
+pub struct Sccp;
+
+impl Pass for Sccp {
+    fn name(&self) -> &'static str {
+        "sccp"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, ptr_bytes: u32) -> bool {
+        let mut solver = Solver::new(func, ptr_bytes);
+        solver.solve(func);
+        rewrite(func, &solver)
+    }
+}
+
+///
+/// 格上的值
+/// - `Top`: 还没有确定
+/// - `Const`: 总是这个常量
+/// - `Bottom`: 不是常量
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lattice {
+    Top,
+    Const(Value),
+    Bottom,
+}
+
+impl Lattice {
+    fn meet(self, other: Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Top, x) | (x, Lattice::Top) => x,
+            (Lattice::Const(a), Lattice::Const(b)) if a == b => self,
+            _ => Lattice::Bottom,
+        }
+    }
+}
+
+///
+/// 求解器
+///
+/// # Members
+/// - `states`: 指令结果在格上的值，不在表中的为 `Top`
+/// - `blocks`: 可执行的基本块
+/// - `edges`: 可执行的边
+/// - `users`: 每条指令的使用者
+/// - `flow_work` `ssa_work`: 新的可执行边和值改变了的指令
+/// - `ptr_bytes`: 模块的指针宽度
+///
+struct Solver {
+    states: SecondaryMap<InstId, Lattice>,
+    blocks: FxHashSet<BlockId>,
+    edges: FxHashSet<(BlockId, BlockId)>,
+    users: SecondaryMap<InstId, Vec<InstId>>,
+    flow_work: Vec<(Option<BlockId>, BlockId)>,
+    ssa_work: Vec<InstId>,
+    ptr_bytes: u32,
+}
+
+impl Solver {
+    fn new(func: &Function, ptr_bytes: u32) -> Self {
+        let mut users: SecondaryMap<InstId, Vec<InstId>> = SecondaryMap::new();
+        for (_, inst) in func.inst_iter() {
+            for operand in func.insts[inst].kind.operands() {
+                if let Value::Inst(x) = operand {
+                    users.entry(x).unwrap().or_default().push(inst);
+                }
+            }
+        }
+        Self {
+            states: SecondaryMap::new(),
+            blocks: FxHashSet::default(),
+            edges: FxHashSet::default(),
+            users,
+            flow_work: vec![(None, func.entry())],
+            ssa_work: Vec::new(),
+            ptr_bytes,
+        }
+    }
+
+    fn state(&self, value: Value) -> Lattice {
+        match value {
+            Value::Inst(x) => self.states.get(x).copied().unwrap_or(Lattice::Top),
+            Value::Arg(_) | Value::Undef(_) => Lattice::Bottom,
+            _ => Lattice::Const(value),
+        }
+    }
+
+    fn solve(&mut self, func: &Function) {
+        loop {
+            self.propagate(func);
+            // 条件仍然未确定的分支（只依赖自身的 phi 环）当作两边都可执行
+            let mut forced = false;
+            for block in func.layout.iter().cloned() {
+                if !self.blocks.contains(&block) {
+                    continue;
+                }
+                let Some(term) = func.terminator(block) else {
+                    continue;
+                };
+                let cond = match func.insts[term].kind {
+                    InstKind::CondBr { cond, .. } => cond,
+                    InstKind::Switch { val, .. } => val,
+                    _ => continue,
+                };
+                if let Value::Inst(x) = cond
+                    && self.state(cond) == Lattice::Top
+                {
+                    self.update(x, Lattice::Bottom);
+                    forced = true;
+                }
+            }
+            if !forced {
+                break;
+            }
+        }
+    }
+
+    fn propagate(&mut self, func: &Function) {
+        loop {
+            if let Some((from, to)) = self.flow_work.pop() {
+                if let Some(from) = from
+                    && !self.edges.insert((from, to))
+                {
+                    continue;
+                }
+                if self.blocks.insert(to) {
+                    for inst in func.blocks[to].insts.iter().cloned() {
+                        self.visit(func, inst);
+                    }
+                } else {
+                    // 新的入边只影响 phi
+                    for phi in func.phis(to) {
+                        self.visit(func, phi);
+                    }
+                }
+            } else if let Some(inst) = self.ssa_work.pop() {
+                let block = func.inst_block(inst).unwrap();
+                if self.blocks.contains(&block) {
+                    self.visit(func, inst);
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn update(&mut self, inst: InstId, state: Lattice) {
+        let old = self.states.get(inst).copied().unwrap_or(Lattice::Top);
+        let new = old.meet(state);
+        if new != old {
+            self.states.insert(inst, new);
+            if let Some(users) = self.users.get(inst) {
+                self.ssa_work.extend(users.iter().cloned());
+            }
+        }
+    }
+
+    fn visit(&mut self, func: &Function, inst: InstId) {
+        let block = func.inst_block(inst).unwrap();
+        let data = &func.insts[inst];
+        let state = match &data.kind {
+            InstKind::Phi { incomings } => incomings
+                .iter()
+                .filter(|(pred, _)| self.edges.contains(&(*pred, block)))
+                .fold(Lattice::Top, |acc, (_, x)| acc.meet(self.state(*x))),
+            InstKind::Binary { op, lhs, rhs, nsw } => self.fold(&[*lhs, *rhs], |x| {
+                fold::binary(*op, data.ty, x[0], x[1], *nsw, self.ptr_bytes)
+            }),
+            InstKind::FNeg { val } => self.fold(&[*val], |x| fold::fneg(x[0])),
+            InstKind::Cmp { pred, lhs, rhs } => {
+                self.fold(&[*lhs, *rhs], |x| fold::compare(*pred, x[0], x[1]))
+            }
+            InstKind::Cast { op, val } => {
+                self.fold(&[*val], |x| fold::cast(*op, x[0], data.ty, self.ptr_bytes))
+            }
+            InstKind::Select {
+                cond,
+                then_val,
+                else_val,
+            } => match self.state(*cond) {
+                Lattice::Top => Lattice::Top,
+                Lattice::Const(x) if x.as_uint() == Some(1) => self.state(*then_val),
+                Lattice::Const(_) => self.state(*else_val),
+                Lattice::Bottom => self.state(*then_val).meet(self.state(*else_val)),
+            },
+            InstKind::Br { dest } => {
+                self.flow_work.push((Some(block), *dest));
+                return;
+            }
+            InstKind::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => {
+                match self.state(*cond) {
+                    Lattice::Top => {}
+                    Lattice::Const(x) if x.as_uint() == Some(1) => {
+                        self.flow_work.push((Some(block), *then_dest))
+                    }
+                    Lattice::Const(_) => self.flow_work.push((Some(block), *else_dest)),
+                    Lattice::Bottom => {
+                        self.flow_work.push((Some(block), *then_dest));
+                        self.flow_work.push((Some(block), *else_dest));
+                    }
+                }
+                return;
+            }
+            InstKind::Switch { val, .. } => {
+                match self.state(*val) {
+                    Lattice::Top => {}
+                    Lattice::Const(x) => {
+                        let dest = switch_target(&data.kind, x);
+                        self.flow_work.push((Some(block), dest));
+                    }
+                    Lattice::Bottom => {
+                        for succ in data.kind.successors() {
+                            self.flow_work.push((Some(block), succ));
+                        }
+                    }
+                }
+                return;
+            }
+            _ => Lattice::Bottom,
+        };
+        self.update(inst, state);
+    }
+
+    /// 操作数都是常量时折叠，不能折叠时不是常量
+    fn fold(&self, operands: &[Value], f: impl FnOnce(&[Value]) -> Option<Value>) -> Lattice {
+        let mut values = Vec::with_capacity(operands.len());
+        for operand in operands {
+            match self.state(*operand) {
+                Lattice::Bottom => return Lattice::Bottom,
+                Lattice::Top => return Lattice::Top,
+                Lattice::Const(x) => values.push(x),
+            }
+        }
+        match f(&values) {
+            Some(x) => Lattice::Const(x),
+            None => Lattice::Bottom,
+        }
+    }
+}
+
+/// `switch` 在值为常量 `val` 时的目标
+fn switch_target(kind: &InstKind, val: Value) -> BlockId {
+    let InstKind::Switch { default, cases, .. } = kind else {
+        unreachable!()
+    };
+    let bits = val.as_uint();
+    cases
+        .iter()
+        .find(|(x, _)| Some(*x) == bits)
+        .map(|x| x.1)
+        .unwrap_or(*default)
+}
+
+fn rewrite(func: &mut Function, solver: &Solver) -> bool {
+    let mut changed = false;
+
+    // 常量替换为字面值
+    let mut replace: SecondaryMap<InstId, Value> = SecondaryMap::new();
+    for (inst, state) in solver.states.iter() {
+        if let Lattice::Const(x) = state {
+            replace.insert(inst, *x);
+        }
+    }
+    if !replace.is_empty() {
+        changed = true;
+        for (_, data) in func.insts.iter_mut() {
+            for operand in data.kind.operands_mut() {
+                if let Value::Inst(x) = *operand
+                    && let Some(value) = replace.get(x)
+                {
+                    *operand = *value;
+                }
+            }
+        }
+        for inst in replace.keys() {
+            func.remove_inst(inst);
+        }
+    }
+
+    // 只有一边可执行的分支改为无条件跳转
+    for block in func.layout.clone() {
+        if !solver.blocks.contains(&block) {
+            continue;
+        }
+        let Some(term) = func.terminator(block) else {
+            continue;
+        };
+        let succs = func.insts[term].kind.successors();
+        if succs.len() < 2 {
+            continue;
+        }
+        let mut live: Vec<BlockId> = succs
+            .iter()
+            .cloned()
+            .filter(|x| solver.edges.contains(&(block, *x)))
+            .collect();
+        live.sort();
+        live.dedup();
+        if live.len() != 1 {
+            continue;
+        }
+        func.insts[term].kind = InstKind::Br { dest: live[0] };
+        for succ in succs {
+            if succ != live[0] {
+                func.remove_phi_pred(succ, block);
+            }
+        }
+        changed = true;
+    }
+
+    // 删除从未执行的基本块
+    let dead: Vec<BlockId> = func
+        .layout
+        .iter()
+        .cloned()
+        .filter(|x| !solver.blocks.contains(x))
+        .collect();
+    for block in dead.iter().cloned() {
+        for succ in func.successors(block) {
+            if solver.blocks.contains(&succ) {
+                func.remove_phi_pred(succ, block);
+            }
+        }
+    }
+    for block in dead.iter().cloned() {
+        func.remove_block(block);
+        changed = true;
+    }
+    changed
+}
@@ -0,0 +1,274 @@
+use crate::ir::cfg::reverse_postorder;
+use crate::ir::{BlockId, Function, InstKind, Value};
+use crate::opt::Pass;
+use rustc_hash::FxHashSet;
+
+///
+/// 控制流图化简，反复进行直到不再变化：
+/// - 常量条件或两边相同的分支改为无条件跳转
+/// - 删除不可达的基本块
+/// - 删除所有入边的值都相同的 phi
+/// - 唯一后继的唯一前驱是自己时合并两个块
+/// - 只有一条 `br` 的空块让前驱直接跳到它的目标
+/// - 跳转串联：条件是 phi 的分支，前驱传入常量时前驱直接跳到确定的目标
+///
+pub struct SimplifyCfg;
+
+impl Pass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplify-cfg"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, _ptr_bytes: u32) -> bool {
+        let mut changed = false;
+        loop {
+            let mut round = fold_branches(func);
+            round |= remove_unreachable(func);
+            round |= remove_trivial_phis(func);
+            round |= merge_blocks(func);
+            round |= forward_empty_blocks(func);
+            round |= thread_jumps(func);
+            if !round {
+                return changed;
+            }
+            changed = true;
+        }
+    }
+}
+
+/// 把 `block` 的终结指令改为跳到 `dest`，其他后继的 phi 删除来自 `block` 的入边
+fn set_jump(func: &mut Function, block: BlockId, dest: BlockId) {
+    let term = func.terminator(block).unwrap();
+    for succ in func.insts[term].kind.successors() {
+        if succ != dest {
+            func.remove_phi_pred(succ, block);
+        }
+    }
+    func.insts[term].kind = InstKind::Br { dest };
+}
+
+fn fold_branches(func: &mut Function) -> bool {
+    let mut changed = false;
+    for block in func.layout.clone() {
+        let Some(term) = func.terminator(block) else {
+            continue;
+        };
+        let dest = match &func.insts[term].kind {
+            InstKind::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => match cond.as_uint() {
+                Some(1) => *then_dest,
+                Some(_) => *else_dest,
+                None if then_dest == else_dest => *then_dest,
+                None => continue,
+            },
+            InstKind::Switch {
+                val,
+                default,
+                cases,
+            } => match val.as_uint() {
+                Some(x) => cases
+                    .iter()
+                    .find(|c| c.0 == x)
+                    .map(|c| c.1)
+                    .unwrap_or(*default),
+                None if cases.iter().all(|c| c.1 == *default) => *default,
+                None => continue,
+            },
+            _ => continue,
+        };
+        set_jump(func, block, dest);
+        changed = true;
+    }
+    changed
+}
+
+/// 删除从入口不可达的基本块，返回是否删除了
+pub fn remove_unreachable(func: &mut Function) -> bool {
+    let reachable: FxHashSet<BlockId> = reverse_postorder(func).into_iter().collect();
+    let dead: Vec<BlockId> = func
+        .layout
+        .iter()
+        .cloned()
+        .filter(|x| !reachable.contains(x))
+        .collect();
+    for block in dead.iter().cloned() {
+        for succ in func.successors(block) {
+            if reachable.contains(&succ) {
+                func.remove_phi_pred(succ, block);
+            }
+        }
+    }
+    for block in dead.iter().cloned() {
+        func.remove_block(block);
+    }
+    !dead.is_empty()
+}
+
+/// 除自身外所有入边的值都相同的 phi 替换为这个值
+fn remove_trivial_phis(func: &mut Function) -> bool {
+    let mut changed = false;
+    for block in func.layout.clone() {
+        for phi in func.phis(block) {
+            let InstKind::Phi { incomings } = &func.insts[phi].kind else {
+                unreachable!()
+            };
+            let mut values = incomings
+                .iter()
+                .map(|x| x.1)
+                .filter(|x| *x != Value::Inst(phi));
+            let Some(first) = values.next() else {
+                continue;
+            };
+            if values.all(|x| x == first) {
+                func.replace_all_uses(Value::Inst(phi), first);
+                func.remove_inst(phi);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// 唯一后继 `b` 的唯一前驱是 `a` 时，把 `b` 并入 `a`
+fn merge_blocks(func: &mut Function) -> bool {
+    let mut changed = false;
+    let mut preds = func.predecessors();
+    for a in func.layout.clone() {
+        if !func.blocks.contains_key(a) {
+            continue;
+        }
+        let Some(term) = func.terminator(a) else {
+            continue;
+        };
+        let InstKind::Br { dest: b } = func.insts[term].kind else {
+            continue;
+        };
+        if b == a || b == func.entry() || preds[b].as_slice() != [a] {
+            continue;
+        }
+        for phi in func.phis(b) {
+            let InstKind::Phi { incomings } = &func.insts[phi].kind else {
+                unreachable!()
+            };
+            let value = incomings[0].1;
+            func.replace_all_uses(Value::Inst(phi), value);
+            func.remove_inst(phi);
+        }
+        func.remove_inst(term);
+        for inst in func.blocks[b].insts.clone() {
+            func.detach_inst(inst);
+            let pos = func.blocks[a].insts.len();
+            func.attach_inst(a, pos, inst);
+        }
+        for succ in func.successors(a) {
+            func.replace_phi_pred(succ, b, a);
+        }
+        func.remove_block(b);
+        preds = func.predecessors();
+        changed = true;
+    }
+    changed
+}
+
+/// 只有一条 `br` 的块，让前驱直接跳到它的目标；前驱已经是目标的前驱并且目标有 phi 时不能这样做
+fn forward_empty_blocks(func: &mut Function) -> bool {
+    let mut changed = false;
+    for block in func.layout.clone() {
+        if block == func.entry() || func.blocks[block].insts.len() != 1 {
+            continue;
+        }
+        let Some(term) = func.terminator(block) else {
+            continue;
+        };
+        let InstKind::Br { dest } = func.insts[term].kind else {
+            continue;
+        };
+        if dest == block {
+            continue;
+        }
+        let preds = func.predecessors();
+        let has_phis = !func.phis(dest).is_empty();
+        for pred in preds[block].iter().cloned() {
+            if has_phis && preds[dest].contains(&pred) {
+                continue;
+            }
+            redirect(func, pred, block, dest);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// 把 `pred` 到 `old` 的边改到 `new`，`new` 的 phi 中来自 `pred` 的值和来自 `old` 的相同
+fn redirect(func: &mut Function, pred: BlockId, old: BlockId, new: BlockId) {
+    let term = func.terminator(pred).unwrap();
+    for succ in func.insts[term].kind.successors_mut() {
+        if *succ == old {
+            *succ = new;
+        }
+    }
+    for phi in func.phis(new) {
+        if let InstKind::Phi { incomings } = &mut func.insts[phi].kind
+            && let Some(value) = incomings.iter().find(|x| x.0 == old).map(|x| x.1)
+        {
+            incomings.push((pred, value));
+        }
+    }
+    func.remove_phi_pred(old, pred);
+    // `old` 失去所有前驱后由删除不可达块处理
+}
+
+///
+/// 跳转串联
+///
+/// 块中只有一个 phi 和以它为条件的 `condbr` 时，对以 `br` 跳到这里并传入常量的前驱，
+/// 让它直接跳到条件确定的目标
+///
+fn thread_jumps(func: &mut Function) -> bool {
+    let mut changed = false;
+    let uses = func.use_counts();
+    for block in func.layout.clone() {
+        let insts = &func.blocks[block].insts;
+        if block == func.entry() || insts.len() != 2 {
+            continue;
+        }
+        let (phi, term) = (insts[0], insts[1]);
+        let InstKind::Phi { incomings } = &func.insts[phi].kind else {
+            continue;
+        };
+        let InstKind::CondBr {
+            cond: Value::Inst(cond),
+            then_dest,
+            else_dest,
+        } = func.insts[term].kind
+        else {
+            continue;
+        };
+        if cond != phi || uses.get(phi) != Some(&1) {
+            continue;
+        }
+        let incomings = incomings.clone();
+        let preds = func.predecessors();
+        for (pred, value) in incomings {
+            let Some(bits) = value.as_uint() else {
+                continue;
+            };
+            let dest = if bits == 1 { then_dest } else { else_dest };
+            let Some(pred_term) = func.terminator(pred) else {
+                continue;
+            };
+            if dest == block
+                || !matches!(func.insts[pred_term].kind, InstKind::Br { .. })
+                || preds[dest].contains(&pred)
+            {
+                continue;
+            }
+            redirect(func, pred, block, dest);
+            changed = true;
+        }
+    }
+    changed
+}
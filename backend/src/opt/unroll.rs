@@ -0,0 +1,187 @@
+use crate::ir::cfg::DomTree;
+use crate::ir::loops::{Loop, LoopInfo, insert_preheaders};
+use crate::ir::{BlockId, Function, InstId, InstKind, Value};
+use crate::opt::Pass;
+use crate::opt::indvars::{inductions, trip_count};
+use crate::opt::simplify_cfg::remove_unreachable;
+use rustc_hash::FxHashMap;
+
+///
+/// 完全展开循环次数确定的小循环
+///
+/// 只展开最内层循环，展开后外层循环可能成为最内层循环，继续展开。
+/// 循环体复制 `count` 份依次连接，每份中循环头的 phi 替换为上一份的值，
+/// 出口的条件分支改为确定的方向
+///
+/// # Members
+/// - `max_count`: 循环次数的上限
+/// - `max_size`: 展开后的指令数上限
+///
+pub struct Unroll {
+    pub max_count: u64,
+    pub max_size: usize,
+}
+
+impl Default for Unroll {
+    fn default() -> Self {
+        Self {
+            max_count: 16,
+            max_size: 128,
+        }
+    }
+}
+
+impl Pass for Unroll {
+    fn name(&self) -> &'static str {
+        "unroll"
+    }
+
+    fn run_on_function(&mut self, func: &mut Function, ptr_bytes: u32) -> bool {
+        let mut changed = insert_preheaders(func);
+        // 每次展开一个循环后重新分析
+        'outer: loop {
+            let dom = DomTree::new(func);
+            let info = LoopInfo::new(func, &dom);
+            for lp in info.loops().iter().filter(|x| x.children.is_empty()) {
+                let Some(preheader) = lp.preheader(func) else {
+                    continue;
+                };
+                let ivs = inductions(func, lp, preheader, ptr_bytes);
+                let Some(count) = trip_count(func, lp, &ivs, ptr_bytes) else {
+                    continue;
+                };
+                let size: usize = lp.blocks.iter().map(|x| func.blocks[*x].insts.len()).sum();
+                if count > self.max_count || count as usize * size > self.max_size {
+                    continue;
+                }
+                unroll(func, lp, preheader, count);
+                changed = true;
+                continue 'outer;
+            }
+            return changed;
+        }
+    }
+}
+
+/// 展开循环，`count` 为循环头执行的次数，调用者保证循环只有一条出口边，出口在循环头或唯一的 latch
+fn unroll(func: &mut Function, lp: &Loop, preheader: BlockId, count: u64) {
+    let header = lp.header;
+    let latch = lp.latches[0];
+    let (exiting, exit) = lp.exits(func)[0];
+    let phis = func.phis(header);
+    let incoming = |func: &Function, phi: InstId, block: BlockId| {
+        let InstKind::Phi { incomings } = &func.insts[phi].kind else {
+            unreachable!()
+        };
+        incomings.iter().find(|x| x.0 == block).unwrap().1
+    };
+    let map = |values: &FxHashMap<InstId, Value>, value: Value| match value {
+        Value::Inst(x) => values.get(&x).copied().unwrap_or(value),
+        _ => value,
+    };
+
+    // 每一份中原来的值和块对应的值和块
+    let mut values: FxHashMap<InstId, Value> = FxHashMap::default();
+    let mut copies: Vec<FxHashMap<BlockId, BlockId>> = Vec::new();
+    for k in 0..count {
+        let mut current: FxHashMap<InstId, Value> = FxHashMap::default();
+        for phi in phis.iter().cloned() {
+            let value = match k {
+                0 => incoming(func, phi, preheader),
+                _ => map(&values, incoming(func, phi, latch)),
+            };
+            current.insert(phi, value);
+        }
+        let mut blocks: FxHashMap<BlockId, BlockId> = FxHashMap::default();
+        for block in lp.blocks.iter().cloned() {
+            blocks.insert(block, func.add_block());
+        }
+        let mut new_insts = Vec::new();
+        for block in lp.blocks.iter().cloned() {
+            for inst in func.blocks[block].insts.clone() {
+                if phis.contains(&inst) {
+                    continue;
+                }
+                let new = func.append_inst(blocks[&block], func.insts[inst].clone());
+                current.insert(inst, Value::Inst(new));
+                new_insts.push(new);
+            }
+        }
+        for inst in new_insts {
+            let kind = &mut func.insts[inst].kind;
+            for operand in kind.operands_mut() {
+                *operand = map(&current, *operand);
+            }
+            for succ in kind.successors_mut() {
+                *succ = blocks.get(succ).copied().unwrap_or(*succ);
+            }
+            if let InstKind::Phi { incomings } = kind {
+                for incoming in incomings.iter_mut() {
+                    incoming.0 = blocks[&incoming.0];
+                }
+            }
+        }
+
+        // 出口的分支改为确定的方向，最后一份离开循环
+        let term = func.terminator(blocks[&exiting]).unwrap();
+        let dest = match func.insts[term].kind {
+            _ if k + 1 == count => exit,
+            InstKind::CondBr {
+                then_dest,
+                else_dest,
+                ..
+            } => {
+                if then_dest == exit {
+                    else_dest
+                } else {
+                    then_dest
+                }
+            }
+            _ => unreachable!(),
+        };
+        func.insts[term].kind = InstKind::Br { dest };
+        values = current;
+        copies.push(blocks);
+    }
+
+    // 回边接到下一份的循环头，最后一份的回边不可达，留给删除不可达块
+    for k in 0..copies.len() - 1 {
+        let term = func.terminator(copies[k][&latch]).unwrap();
+        for succ in func.insts[term].kind.successors_mut() {
+            if *succ == copies[k][&header] {
+                *succ = copies[k + 1][&header];
+            }
+        }
+    }
+    let term = func.terminator(preheader).unwrap();
+    func.insts[term].kind = InstKind::Br {
+        dest: copies[0][&header],
+    };
+
+    // 循环外的使用改为最后一份的值
+    let last_exiting = copies[copies.len() - 1][&exiting];
+    func.replace_phi_pred(exit, exiting, last_exiting);
+    for block in func.layout.clone() {
+        if lp.contains(block) {
+            continue;
+        }
+        for inst in func.blocks[block].insts.clone() {
+            for operand in func.insts[inst].kind.operands_mut() {
+                *operand = map(&values, *operand);
+            }
+        }
+    }
+
+    // 复制的块放在原来循环的位置
+    let new_blocks: Vec<BlockId> = copies
+        .iter()
+        .flat_map(|blocks| lp.blocks.iter().map(|x| blocks[x]))
+        .collect();
+    func.layout.retain(|x| !new_blocks.contains(x));
+    let at = func.layout.iter().position(|x| *x == header).unwrap();
+    func.layout.splice(at..at, new_blocks);
+    for block in lp.blocks.iter().cloned() {
+        func.remove_block(block);
+    }
+    remove_unreachable(func);
+}
@@ -0,0 +1,123 @@
+use std::fmt::{Display, Formatter};
+
+/// 目标架构
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arch {
+    X86_64,
+    Riscv64,
+    Wasm32,
+}
+
+///
+/// 目标平台的数据模型和 ABI 参数，前端的类型布局和后端的代码生成共用
+///
+/// # Members
+/// - `arch`: 架构
+/// - `triple`: 目标三元组
+/// - `data_layout`: LLVM 的 `datalayout` 字符串，输出 `.ll` 时使用
+/// - `ptr_bytes`: 指针宽度
+/// - `long_bytes`: `long` 的宽度，LP64 为 8，ILP32 为 4
+/// - `long_double_bytes` `long_double_align`: `long double` 的大小和对齐
+/// - `char_signed`: `char` 是否有符号
+/// - `max_align`: 基本类型的最大对齐
+/// - `stack_align`: 调用时栈的对齐
+/// - `va_list_bytes` `va_list_align`: `va_list` 指向的对象的大小和对齐
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetInfo {
+    pub arch: Arch,
+    pub triple: &'static str,
+    pub data_layout: &'static str,
+    pub ptr_bytes: u32,
+    pub long_bytes: u32,
+    pub long_double_bytes: u32,
+    pub long_double_align: u32,
+    pub char_signed: bool,
+    pub max_align: u32,
+    pub stack_align: u32,
+    pub va_list_bytes: u32,
+    pub va_list_align: u32,
+}
+
+impl TargetInfo {
+    /// x86-64 Linux，System V ABI，LP64
+    pub fn x86_64_linux() -> Self {
+        Self {
+            arch: Arch::X86_64,
+            triple: "x86_64-unknown-linux-gnu",
+            data_layout: "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128",
+            ptr_bytes: 8,
+            long_bytes: 8,
+            long_double_bytes: 16,
+            long_double_align: 16,
+            char_signed: true,
+            max_align: 16,
+            stack_align: 16,
+            va_list_bytes: 24,
+            va_list_align: 8,
+        }
+    }
+
+    /// RISC-V 64 Linux，RV64GC，LP64D，`char` 无符号
+    pub fn riscv64_linux() -> Self {
+        Self {
+            arch: Arch::Riscv64,
+            triple: "riscv64-unknown-linux-gnu",
+            data_layout: "e-m:e-p:64:64-i64:64-i128:128-n64-S128",
+            ptr_bytes: 8,
+            long_bytes: 8,
+            long_double_bytes: 16,
+            long_double_align: 16,
+            char_signed: false,
+            max_align: 16,
+            stack_align: 16,
+            va_list_bytes: 8,
+            va_list_align: 8,
+        }
+    }
+
+    /// WebAssembly，ILP32，`long double` 按 `double` 处理，`va_list` 是指向变参区域的指针
+    pub fn wasm32() -> Self {
+        Self {
+            arch: Arch::Wasm32,
+            triple: "wasm32-unknown-unknown",
+            data_layout: "e-m:e-p:32:32-i64:64-n32:64-S128",
+            ptr_bytes: 4,
+            long_bytes: 4,
+            long_double_bytes: 8,
+            long_double_align: 8,
+            char_signed: true,
+            max_align: 8,
+            stack_align: 16,
+            va_list_bytes: 4,
+            va_list_align: 4,
+        }
+    }
+
+    /// 按三元组查找，只看架构部分，`x86_64` 和 `amd64` 等价
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        let arch = triple.split('-').next()?;
+        match arch {
+            "x86_64" | "amd64" => Some(Self::x86_64_linux()),
+            "riscv64" | "riscv64gc" => Some(Self::riscv64_linux()),
+            "wasm32" => Some(Self::wasm32()),
+            _ => None,
+        }
+    }
+
+    /// 宿主平台对应的目标，默认目标
+    pub fn host() -> Self {
+        Self::x86_64_linux()
+    }
+}
+
+impl Display for Arch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Arch::X86_64 => "x86_64",
+            Arch::Riscv64 => "riscv64",
+            Arch::Wasm32 => "wasm32",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,6 @@
+mod test_codegen;
+mod test_debug;
+mod test_interp;
+mod test_ir;
+mod test_link;
+mod test_opt;
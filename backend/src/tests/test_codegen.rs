@@ -0,0 +1,390 @@
+//! 依赖外部工具（`cc` `readelf` `node` `lli` `llvm-as` `llvm-mc`）的测试标记为 `#[ignore]`，
+//! 用 `cargo test -p backend -- --include-ignored` 运行，工具不存在时失败而不是跳过
+
+use crate::codegen::{isa_by_triple, llvm};
+use crate::interp::Interpreter;
+use crate::ir::parser::parse_module;
+use crate::target::TargetInfo;
+use std::fs;
+use std::process::{Command, Output};
+
+fn emit(text: &str) -> String {
+    emit_for("x86_64-unknown-linux-gnu", text)
+}
+
+fn emit_for(triple: &str, text: &str) -> String {
+    let module = parse_module(text).unwrap();
+    let isa = isa_by_triple(triple).unwrap();
+    isa.emit_asm(&module).unwrap()
+}
+
+fn emit_object(text: &str) -> Vec<u8> {
+    let module = parse_module(text).unwrap();
+    let isa = isa_by_triple("x86_64-unknown-linux-gnu").unwrap();
+    isa.emit_object(&module).unwrap()
+}
+
+/// 运行外部工具，工具不存在时失败
+fn run_tool(cmd: &mut Command) -> Output {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    cmd.output()
+        .unwrap_or_else(|err| panic!("failed to run `{}`: {}", program, err))
+}
+
+/// 运行外部工具，要求成功并且没有诊断信息，返回标准输出
+fn check_tool(cmd: &mut Command, what: &str) -> String {
+    let output = run_tool(cmd);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && stderr.is_empty(),
+        "{}: {}",
+        what,
+        stderr
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// 汇编（`.s`）或直接链接（`.o`）并运行，返回退出码和输出
+fn run_native(name: &str, ext: &str, input: &[u8]) -> (i32, String) {
+    let dir = std::env::temp_dir();
+    let src = dir.join(format!("rcc-{}.{}", name, ext));
+    let exe = dir.join(format!("rcc-{}-{}", name, ext));
+    fs::write(&src, input).unwrap();
+    let mut cmd = Command::new("cc");
+    cmd.arg("-no-pie").arg("-o").arg(&exe).arg(&src).arg("-lm");
+    check_tool(&mut cmd, &format!("failed to assemble {}", src.display()));
+    let output = run_tool(&mut Command::new(&exe));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    (output.status.code().unwrap(), stdout)
+}
+
+#[test]
+fn test_x86_64_asm() {
+    let asm = emit(
+        r#"
+@counter = global 4, align 4 { zero 4 }
+@table = internal constant 8, align 8 { addr @counter }
+define i32 @main() {
+bb0:
+    ret i32 0
+}
+"#,
+    );
+    assert!(asm.contains("\t.globl main\n"));
+    assert!(asm.contains("\t.bss\n\t.globl counter\n"));
+    assert!(asm.contains("\t.section .rodata\n\t.p2align 3\n"));
+    assert!(asm.contains("\t.quad counter\n"));
+    assert!(asm.ends_with("\t.section .note.GNU-stack,\"\",@progbits\n"));
+}
+
+/// 原生执行的结果和解释器相同
+#[test]
+#[ignore = "needs `cc`"]
+fn test_x86_64_run() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/codegen");
+    for name in ["ops", "abi", "pressure"] {
+        let text = fs::read_to_string(format!("{}/{}.ir", dir, name)).unwrap();
+        let module = parse_module(&text).unwrap();
+        let mut interp = Interpreter::new(&module).unwrap();
+        let code = interp.run_main(&["prog"]).unwrap();
+        let output = String::from_utf8(interp.output).unwrap();
+
+        let native = run_native(name, "s", emit(&text).as_bytes());
+        assert_eq!(native, (code, output.clone()), "{}", name);
+        let native = run_native(name, "o", &emit_object(&text));
+        assert_eq!(native, (code, output), "{} (object file)", name);
+    }
+}
+
+const OBJECT_SAMPLE: &str = r#"
+@counter = global 4, align 4 { zero 4 }
+@table = internal constant 8, align 8 { addr @counter }
+@fmt = internal constant 4, align 1 { bytes [37, 100, 10, 0] }
+declare i32 @printf(ptr, ...)
+define weak i32 @hook() {
+bb0:
+    ret i32 7
+}
+define i32 @main() {
+bb0:
+    %0 = call i32 () @hook()
+    %1 = call i32 (ptr, ...) @printf(ptr @fmt, i32 %0)
+    ret i32 0
+}
+"#;
+
+#[test]
+fn test_x86_64_object() {
+    let obj = emit_object(OBJECT_SAMPLE);
+    assert_eq!(&obj[..4], b"\x7fELF");
+    assert!(
+        isa_by_triple("riscv64-unknown-linux-gnu")
+            .unwrap()
+            .emit_object(&parse_module("").unwrap())
+            .is_err()
+    );
+}
+
+/// 目标文件的节、符号绑定和重定位
+#[test]
+#[ignore = "needs `readelf`"]
+fn test_x86_64_object_readelf() {
+    let path = std::env::temp_dir().join("rcc-object.o");
+    fs::write(&path, emit_object(OBJECT_SAMPLE)).unwrap();
+    let text = check_tool(Command::new("readelf").arg("-SsrW").arg(&path), "readelf");
+    let has = |words: &[&str]| {
+        text.lines()
+            .any(|line| words.iter().all(|x| line.split_whitespace().any(|y| y == *x)))
+    };
+    assert!(has(&[".text", "PROGBITS", "AX"]), "{}", text);
+    assert!(has(&[".bss", "NOBITS", "WA"]), "{}", text);
+    assert!(has(&[".rodata", "PROGBITS", "A"]), "{}", text);
+    assert!(has(&[".comment", "MS"]), "{}", text);
+    assert!(has(&[".note.GNU-stack"]), "{}", text);
+    assert!(has(&["OBJECT", "LOCAL", "table"]), "{}", text);
+    assert!(has(&["OBJECT", "GLOBAL", "counter"]), "{}", text);
+    assert!(has(&["FUNC", "WEAK", "hook"]), "{}", text);
+    assert!(has(&["FUNC", "GLOBAL", "main"]), "{}", text);
+    assert!(has(&["NOTYPE", "GLOBAL", "UND", "printf"]), "{}", text);
+    assert!(has(&["R_X86_64_PLT32", "printf", "-", "4"]), "{}", text);
+    assert!(has(&["R_X86_64_PC32", "fmt", "-", "4"]), "{}", text);
+    assert!(has(&["R_X86_64_64", "counter", "+", "0"]), "{}", text);
+}
+
+/// LP64D：浮点参数用浮点寄存器，小结构体按成员拆开，大常数和大栈帧用 `lui` 展开
+#[test]
+fn test_riscv64_asm() {
+    let asm = emit_for(
+        "riscv64-unknown-linux-gnu",
+        r#"
+define i64 @f(f64 %a0, ptr byval(16, 8, {f64 0, i32 8}) %a1, i64 %a2) {
+bb0:
+    %0 = alloca 4096, align 8
+    %1 = fptosi f64 %a0 to i64
+    %2 = add i64 %1, %a2
+    %3 = add i64 %2, 305419896
+    store i64 %3, ptr %0
+    %4 = load i64, ptr %0
+    ret i64 %4
+}
+"#,
+    );
+    // %a1 在 fa1 和 a0 中，%a2 在 a1 中，参数寄存器合并后不再复制
+    assert!(asm.contains("\tfsd fa1, 0(t0)\n"));
+    assert!(asm.contains("\tsw a0, 0(t0)\n"));
+    assert!(asm.contains("\tfcvt.l.d a0, fa0, rtz\n\tadd a0, a0, a1\n"));
+    assert!(asm.contains("\tlui a1, 74565\n\taddiw a1, a1, 1656\n"));
+    assert!(!asm.contains("\tmv "));
+    assert!(asm.contains("\tadd sp, sp, t0\n"));
+    assert!(asm.contains("\taddi sp, s0, -16\n\tld ra, 8(sp)\n"));
+    assert!(asm.ends_with("\t.section .note.GNU-stack,\"\",@progbits\n"));
+}
+
+/// RISC-V 汇编能被 `llvm-mc` 汇编，反汇编后每个函数都在，没有无法识别的指令
+#[test]
+#[ignore = "needs `llvm-mc` and `llvm-objdump`"]
+fn test_riscv64_assemble() {
+    const FEATURES: &str = "+m,+a,+f,+d";
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/codegen");
+    for name in ["ops", "abi", "pressure"] {
+        let text = fs::read_to_string(format!("{}/{}.ir", dir, name)).unwrap();
+        let module = parse_module(&text).unwrap();
+        let tmp = std::env::temp_dir();
+        let src = tmp.join(format!("rcc-{}.riscv64.s", name));
+        let obj = tmp.join(format!("rcc-{}.riscv64.o", name));
+        fs::write(&src, emit_for("riscv64-unknown-linux-gnu", &text)).unwrap();
+        let mut cmd = Command::new("llvm-mc");
+        cmd.arg("-triple=riscv64-unknown-linux-gnu");
+        cmd.arg(format!("-mattr={}", FEATURES));
+        cmd.arg("-filetype=obj").arg("-o").arg(&obj).arg(&src);
+        check_tool(&mut cmd, name);
+
+        let mut cmd = Command::new("llvm-objdump");
+        cmd.arg("-d").arg(format!("--mattr={}", FEATURES)).arg(&obj);
+        let disasm = check_tool(&mut cmd, name);
+        assert!(!disasm.contains("<unknown>"), "{}: {}", name, disasm);
+        for func in module.funcs.values().filter(|x| !x.is_declaration()) {
+            let label = format!("<{}>:", func.name);
+            assert!(disasm.contains(&label), "{}: missing {}", name, label);
+        }
+    }
+}
+
+/// 用 node 运行 `.wasm`，返回退出码和输出
+fn run_wasm(name: &str, wasm: &[u8]) -> (i32, String) {
+    let path = std::env::temp_dir().join(format!("rcc-{}.wasm", name));
+    fs::write(&path, wasm).unwrap();
+    let runner = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/codegen/wasm_run.js");
+    let output = run_tool(Command::new("node").arg(runner).arg(&path));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.is_empty(), "{}: {}", name, stderr);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    (output.status.code().unwrap(), stdout)
+}
+
+/// wasm 模块的运行结果和解释器相同
+#[test]
+#[ignore = "needs `node`"]
+fn test_wasm32_run() {
+    let isa = isa_by_triple("wasm32-unknown-unknown").unwrap();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/codegen");
+    for name in ["ops", "abi", "pressure"] {
+        let text = fs::read_to_string(format!("{}/{}.ir", dir, name)).unwrap();
+        let module = parse_module(&text).unwrap();
+        let mut interp = Interpreter::new(&module).unwrap();
+        let code = interp.run_main(&["prog"]).unwrap();
+        let output = String::from_utf8(interp.output).unwrap();
+
+        let result = run_wasm(name, &isa.emit_object(&module).unwrap());
+        assert_eq!(result, (code, output), "{}", name);
+    }
+}
+
+/// 循环、合流点和 `switch` 的结构化控制流，不可归约的控制流用 `br_table` 分发
+#[test]
+fn test_wasm32_wat() {
+    let wat = emit_for(
+        "wasm32-unknown-unknown",
+        r#"
+@n = global 4, align 4 { bytes [5, 0, 0, 0] }
+declare i32 @printf(ptr, ...)
+define i32 @sum(i32 %a0) {
+bb0:
+    br bb1
+bb1:
+    %0 = phi i32 [0, bb0], [%2, bb2]
+    %1 = phi i32 [0, bb0], [%3, bb2]
+    %4 = icmp slt i32 %1, %a0
+    br i1 %4, bb2, bb3
+bb2:
+    %2 = add i32 %0, %1
+    %3 = add i32 %1, 1
+    br bb1
+bb3:
+    ret i32 %0
+}
+define internal i32 @pick(i32 %a0) {
+bb0:
+    switch i32 %a0, bb3 [1: bb1, 2: bb2, 3: bb1]
+bb1:
+    ret i32 10
+bb2:
+    ret i32 20
+bb3:
+    ret i32 0
+}
+define internal i32 @tangle(i1 %a0) {
+bb0:
+    br i1 %a0, bb1, bb2
+bb1:
+    %0 = phi i32 [0, bb0], [%1, bb2]
+    %2 = add i32 %0, 1
+    %3 = icmp slt i32 %2, 10
+    br i1 %3, bb2, bb3
+bb2:
+    %1 = phi i32 [1, bb0], [%2, bb1]
+    br bb1
+bb3:
+    ret i32 %2
+}
+"#,
+    );
+    assert!(wat.contains("(import \"env\" \"printf\" (func $printf (type 0)))"));
+    assert!(wat.contains("(global $__stack_pointer (mut i32) (i32.const 66576))"));
+    assert!(wat.contains("(data (i32.const 1024) \"\\05\\00\\00\\00\")"));
+    assert!(wat.contains("(export \"sum\" (func $sum))"));
+    assert!(!wat.contains("(export \"pick\""));
+    assert!(wat.contains("(elem (i32.const 1) func $printf $sum $pick $tangle)"));
+    // 循环头在 `loop` 中，回边跳到 `loop` 开头，出口内联在 `if` 的 `else` 中
+    assert!(wat.contains("    loop\n"));
+    assert!(wat.contains("        br 1\n      else\n        local.get 1\n        return\n"));
+    // case 1 和 3 跳到同一个合流点 bb1，它的 `block` 在最外层
+    assert!(wat.contains("br_table 1 2 3 0\n"));
+    assert!(wat.contains("      end\n      br 0\n    end\n    i32.const 10\n"));
+    // bb1 和 bb2 互相跳转，都有来自入口的边
+    assert!(wat.contains("br_table 0 1 2 3 3\n"));
+}
+
+/// LLVM 工具的命令，LLVM 14 需要显式打开不透明指针，更新的版本默认打开
+fn llvm_tool(tool: &str) -> Command {
+    let version = run_tool(Command::new(tool).arg("--version"));
+    let mut cmd = Command::new(tool);
+    if String::from_utf8_lossy(&version.stdout).contains("version 14") {
+        cmd.arg("-opaque-pointers");
+    }
+    cmd
+}
+
+/// 用 `lli` 执行 `.ll`，返回退出码和输出
+fn run_llvm(name: &str, text: &str) -> (i32, String) {
+    let path = std::env::temp_dir().join(format!("rcc-{}.ll", name));
+    fs::write(&path, text).unwrap();
+    let output = run_tool(llvm_tool("lli").arg(&path));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.is_empty(), "{}: {}", name, stderr);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    (output.status.code().unwrap(), stdout)
+}
+
+/// `.ll` 的执行结果和解释器相同
+#[test]
+#[ignore = "needs `lli`"]
+fn test_llvm_run() {
+    let info = TargetInfo::x86_64_linux();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/codegen");
+    for name in ["ops", "abi", "pressure"] {
+        let text = fs::read_to_string(format!("{}/{}.ir", dir, name)).unwrap();
+        let module = parse_module(&text).unwrap();
+        let mut interp = Interpreter::new(&module).unwrap();
+        let code = interp.run_main(&["prog"]).unwrap();
+        let output = String::from_utf8(interp.output).unwrap();
+
+        let result = run_llvm(name, &llvm::emit_module(&module, &info));
+        assert_eq!(result, (code, output), "{}", name);
+    }
+}
+
+/// 每个目标生成的 `.ll` 都能通过 `llvm-as` 的解析和校验
+#[test]
+#[ignore = "needs `llvm-as`"]
+fn test_llvm_verify() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/resources");
+    let inputs = [
+        "codegen/ops",
+        "codegen/abi",
+        "codegen/pressure",
+        "llvm/sample",
+    ];
+    for input in inputs {
+        let text = fs::read_to_string(format!("{}/{}.ir", dir, input)).unwrap();
+        let module = parse_module(&text).unwrap();
+        for info in [
+            TargetInfo::x86_64_linux(),
+            TargetInfo::riscv64_linux(),
+            TargetInfo::wasm32(),
+        ] {
+            let name = format!("{}.{}", input.replace('/', "-"), info.arch);
+            let path = std::env::temp_dir().join(format!("rcc-{}.ll", name));
+            fs::write(&path, llvm::emit_module(&module, &info)).unwrap();
+            let mut cmd = llvm_tool("llvm-as");
+            check_tool(cmd.arg("-o").arg("/dev/null").arg(&path), &name);
+        }
+    }
+}
+
+/// 和 `resources/llvm` 中的 `.ll` 逐字比较，生成时不需要安装 LLVM
+#[test]
+fn test_llvm_golden() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/llvm");
+    let text = fs::read_to_string(format!("{}/sample.ir", dir)).unwrap();
+    let module = parse_module(&text).unwrap();
+    for info in [
+        TargetInfo::x86_64_linux(),
+        TargetInfo::riscv64_linux(),
+        TargetInfo::wasm32(),
+    ] {
+        let path = format!("{}/sample.{}.ll", dir, info.arch);
+        let actual = llvm::emit_module(&module, &info);
+        assert_eq!(actual, fs::read_to_string(&path).unwrap(), "{}", path);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::codegen::isa_by_triple;
+use crate::codegen::x86_64::emit::emit_object;
+use crate::ir::Module;
+use crate::ir::debug::{
+    DebugInfo, DebugLoc, DiEncoding, DiGlobal, DiMember, DiSubprogram, DiType, DiTypeId, DiVariable,
+};
+use crate::ir::parser::parse_module;
+use crate::object::crt;
+use crate::object::link::link;
+use gimli::{
+    AttributeValue, DebuggingInformationEntry, EndianSlice, LittleEndian, Operation, Reader,
+};
+use std::fs;
+use std::process::Command;
+
+type Slice<'a> = EndianSlice<'a, LittleEndian>;
+
+///
+/// 对应的源码：
+///
+/// ```c
+/// #include "node.h"                          // typedef struct node node_t; 在 node.h 第 2 行
+/// enum color { RED, GREEN = 5 };
+/// struct node origin;                         // 7
+/// struct node { int value; struct node *next; unsigned flag : 3; };
+///
+/// int main(int argc, char **argv) {           // 10
+///     struct node p;
+///     p.value = 7;
+/// #line 40 "gen.y"
+///     return p.value + argc;                  // gen.y:40
+/// }
+/// ```
+///
+const PROGRAM: &str = r#"
+@origin = global 24, align 8 { zero 24 }
+define i32 @main(i32 %a0, ptr %a1) {
+bb0:
+    %0 = alloca 4, align 4
+    %1 = alloca 24, align 8
+    store i32 %a0, ptr %0
+    store i32 7, ptr %1
+    %2 = load i32, ptr %1
+    %3 = load i32, ptr %0
+    %4 = add i32 %2, %3
+    ret i32 %4
+}
+"#;
+
+fn program(version: u16) -> Module {
+    let mut module = parse_module(PROGRAM).unwrap();
+    let mut debug = DebugInfo::new("rcc test", "test.c", "/tmp");
+    debug.version = version;
+    debug.files = vec!["node.h".to_string(), "gen.y".to_string()];
+    let id = DiTypeId;
+    let base = |name: &str, size, encoding| DiType::Base {
+        name: name.to_string(),
+        size,
+        encoding,
+    };
+    let member = |name: &str, ty, offset, bit_field| DiMember {
+        name: Some(name.to_string()),
+        ty,
+        offset,
+        bit_field,
+    };
+    debug.types = vec![
+        base("int", 4, DiEncoding::Signed),
+        base("char", 1, DiEncoding::SignedChar),
+        DiType::Pointer {
+            pointee: Some(id(1)),
+            size: 8,
+        },
+        DiType::Pointer {
+            pointee: Some(id(2)),
+            size: 8,
+        },
+        DiType::Record {
+            is_union: false,
+            name: Some("node".to_string()),
+            size: Some(24),
+            members: vec![
+                member("value", id(0), 0, None),
+                member("next", id(5), 8, None),
+                member("flag", id(6), 16, Some((0, 3))),
+            ],
+        },
+        DiType::Pointer {
+            pointee: Some(id(4)),
+            size: 8,
+        },
+        base("unsigned int", 4, DiEncoding::Unsigned),
+        DiType::Enum {
+            name: Some("color".to_string()),
+            size: 4,
+            enumerators: vec![("RED".to_string(), 0), ("GREEN".to_string(), 5)],
+        },
+        DiType::Typedef {
+            name: "node_t".to_string(),
+            ty: Some(id(4)),
+            file: 2,
+            line: 2,
+        },
+        DiType::Function {
+            ret: Some(id(0)),
+            params: vec![id(0), id(3)],
+            is_variadic: false,
+        },
+    ];
+    debug.globals.push(DiGlobal {
+        name: "origin".to_string(),
+        ty: id(4),
+        file: 1,
+        line: 7,
+        global: module.global_by_name("origin").unwrap(),
+        external: true,
+    });
+
+    let main = module.func_by_name("main").unwrap();
+    let func = &mut module.funcs[main];
+    let insts = func.blocks[func.entry()].insts.clone();
+    let loc = |file, line, col| DebugLoc { file, line, col };
+    func.locs.insert(insts[2], loc(1, 10, 14));
+    func.locs.insert(insts[3], loc(1, 12, 13));
+    for inst in insts[4..].iter() {
+        func.locs.insert(*inst, loc(3, 40, 5));
+    }
+    let var = |name: &str, ty, line, arg, addr| DiVariable {
+        name: name.to_string(),
+        ty,
+        file: 1,
+        line,
+        arg,
+        addr,
+    };
+    func.debug = Some(DiSubprogram {
+        name: "main".to_string(),
+        file: 1,
+        line: 10,
+        ty: id(9),
+        external: true,
+        vars: vec![
+            var("argc", id(0), 10, Some(1), insts[0]),
+            var("p", id(4), 11, None, insts[1]),
+        ],
+    });
+    module.debug = Some(debug);
+    module
+}
+
+/// 可执行文件中的节，按节头查找
+fn section<'a>(exe: &'a [u8], name: &str) -> &'a [u8] {
+    let u16_at = |x: usize| u16::from_le_bytes(exe[x..x + 2].try_into().unwrap()) as usize;
+    let u32_at = |x: usize| u32::from_le_bytes(exe[x..x + 4].try_into().unwrap()) as usize;
+    let u64_at = |x: usize| u64::from_le_bytes(exe[x..x + 8].try_into().unwrap()) as usize;
+    let (shoff, shnum, shstrndx) = (u64_at(40), u16_at(60), u16_at(62));
+    let names = u64_at(shoff + shstrndx * 64 + 24);
+    for i in 0..shnum {
+        let header = shoff + i * 64;
+        let start = names + u32_at(header);
+        let end = start + exe[start..].iter().position(|x| *x == 0).unwrap();
+        if &exe[start..end] == name.as_bytes() {
+            let offset = u64_at(header + 24);
+            return &exe[offset..offset + u64_at(header + 32)];
+        }
+    }
+    &[]
+}
+
+fn attr<'a>(
+    entry: &DebuggingInformationEntry<Slice<'a>>,
+    at: gimli::DwAt,
+) -> AttributeValue<Slice<'a>> {
+    entry
+        .attr_value(at)
+        .unwrap()
+        .unwrap_or_else(|| panic!("no {}", at))
+}
+
+fn udata(entry: &DebuggingInformationEntry<Slice>, at: gimli::DwAt) -> u64 {
+    attr(entry, at).udata_value().unwrap()
+}
+
+/// 位置表达式中唯一的操作
+fn location<'a>(
+    entry: &DebuggingInformationEntry<Slice<'a>>,
+    encoding: gimli::Encoding,
+) -> Operation<Slice<'a>> {
+    let AttributeValue::Exprloc(expr) = attr(entry, gimli::DW_AT_location) else {
+        panic!("location is not an expression");
+    };
+    let mut ops = expr.operations(encoding);
+    let op = ops.next().unwrap().unwrap();
+    assert!(ops.next().unwrap().is_none());
+    op
+}
+
+/// 链接后的可执行文件中的类型、变量、函数和行号表
+#[test]
+fn test_dwarf_object() {
+    for version in [4, 5] {
+        let module = program(version);
+        let inputs = vec![
+            ("crt1.o".to_string(), crt::x86_64_linux()),
+            ("test.o".to_string(), emit_object(&module).unwrap()),
+        ];
+        let exe = link(&inputs, "_start").unwrap();
+        let dwarf = gimli::Dwarf::load(|id| -> gimli::Result<Slice> {
+            Ok(EndianSlice::new(section(&exe, id.name()), LittleEndian))
+        })
+        .unwrap();
+        let header = dwarf.units().next().unwrap().unwrap();
+        assert_eq!(header.version(), version);
+        let unit = dwarf.unit(header).unwrap();
+        let name = |entry: &DebuggingInformationEntry<Slice>| {
+            let value = entry.attr_value(gimli::DW_AT_name).unwrap()?;
+            let name = dwarf.attr_string(&unit, value).unwrap();
+            Some(name.to_string_lossy().into_owned())
+        };
+
+        // 深度优先的 DIE 和它的父节点
+        let mut dies = Vec::new();
+        let mut parents = Vec::new();
+        let mut entries = unit.entries();
+        while let Some((delta, entry)) = entries.next_dfs().unwrap() {
+            parents.truncate((parents.len() as isize + delta - 1).max(0) as usize);
+            dies.push((entry.clone(), parents.last().copied()));
+            parents.push(entry.offset());
+        }
+        let find = |tag, want: &str| {
+            dies.iter()
+                .find(|(x, _)| x.tag() == tag && name(x).as_deref() == Some(want))
+                .unwrap_or_else(|| panic!("no {}", want))
+                .0
+                .clone()
+        };
+        let children = |parent: gimli::UnitOffset| {
+            dies.iter()
+                .filter(move |(_, x)| *x == Some(parent))
+                .map(|(x, _)| x.clone())
+        };
+
+        let cu = &dies[0].0;
+        assert_eq!(cu.tag(), gimli::DW_TAG_compile_unit);
+        assert_eq!(name(cu).as_deref(), Some("test.c"));
+
+        // struct 成员的偏移，指针指回 struct 自身
+        let node = find(gimli::DW_TAG_structure_type, "node");
+        assert_eq!(udata(&node, gimli::DW_AT_byte_size), 24);
+        let members: Vec<_> = children(node.offset()).collect();
+        assert_eq!(members.len(), 3);
+        assert_eq!(udata(&members[0], gimli::DW_AT_data_member_location), 0);
+        assert_eq!(udata(&members[1], gimli::DW_AT_data_member_location), 8);
+        let AttributeValue::UnitRef(ptr) = attr(&members[1], gimli::DW_AT_type) else {
+            panic!("member type is not a reference");
+        };
+        let ptr = unit.entry(ptr).unwrap();
+        assert_eq!(ptr.tag(), gimli::DW_TAG_pointer_type);
+        assert_eq!(
+            attr(&ptr, gimli::DW_AT_type),
+            AttributeValue::UnitRef(node.offset())
+        );
+        assert_eq!(udata(&members[2], gimli::DW_AT_data_bit_offset), 128);
+        assert_eq!(udata(&members[2], gimli::DW_AT_bit_size), 3);
+
+        let color = find(gimli::DW_TAG_enumeration_type, "color");
+        let enumerators: Vec<_> = children(color.offset())
+            .map(|x| (name(&x).unwrap(), attr(&x, gimli::DW_AT_const_value)))
+            .collect();
+        assert_eq!(
+            enumerators[1],
+            ("GREEN".to_string(), AttributeValue::Sdata(5))
+        );
+        let typedef = find(gimli::DW_TAG_typedef, "node_t");
+        assert_eq!(
+            attr(&typedef, gimli::DW_AT_type),
+            AttributeValue::UnitRef(node.offset())
+        );
+        assert_eq!(
+            attr(&typedef, gimli::DW_AT_decl_file),
+            AttributeValue::FileIndex(2)
+        );
+        assert_eq!(udata(&typedef, gimli::DW_AT_decl_line), 2);
+
+        // 全局变量的地址在可执行文件中，局部变量相对帧基址 `%rbp`
+        let origin = find(gimli::DW_TAG_variable, "origin");
+        let Operation::Address { address } = location(&origin, unit.encoding()) else {
+            panic!("global location is not an address");
+        };
+        assert!(address >= crate::object::link::BASE);
+        let main = find(gimli::DW_TAG_subprogram, "main");
+        let AttributeValue::Addr(low_pc) = attr(&main, gimli::DW_AT_low_pc) else {
+            panic!("low_pc is not an address");
+        };
+        let size = udata(&main, gimli::DW_AT_high_pc);
+        assert!(size > 0);
+        let AttributeValue::Exprloc(frame_base) = attr(&main, gimli::DW_AT_frame_base) else {
+            panic!("frame base is not an expression");
+        };
+        assert_eq!(frame_base.0.to_slice().unwrap().as_ref(), &[0x56]);
+        let argc = find(gimli::DW_TAG_formal_parameter, "argc");
+        let p = find(gimli::DW_TAG_variable, "p");
+        let offset = |x| match location(x, unit.encoding()) {
+            Operation::FrameOffset { offset } => offset,
+            op => panic!("unexpected location {:?}", op),
+        };
+        assert!(offset(&argc) < 0 && offset(&p) < 0 && offset(&argc) != offset(&p));
+        assert_eq!(offset(&p) % 8, 0);
+
+        // 函数开头是定义所在的行，之后是语句的行，最后结束序列；文件表的 2 号之后是其余的文件
+        let program = unit.line_program.clone().unwrap();
+        let file_name = |index| {
+            let file = program.header().file(index).unwrap();
+            let name = dwarf.attr_string(&unit, file.path_name()).unwrap();
+            name.to_string_lossy().into_owned()
+        };
+        assert_eq!(file_name(1), "test.c");
+        assert_eq!(file_name(2), "node.h");
+        assert_eq!(file_name(3), "gen.y");
+        let mut rows = program.rows();
+        let mut lines = Vec::new();
+        while let Some((_, row)) = rows.next_row().unwrap() {
+            let line = row.line().map_or(0, |x| x.get());
+            lines.push((row.address() - low_pc, line, row.end_sequence()));
+            if line == 40 {
+                assert_eq!(row.file_index(), 3);
+            } else if !row.end_sequence() {
+                assert_eq!(row.file_index(), 1);
+            }
+        }
+        assert_eq!(lines[0], (0, 10, false));
+        assert!(lines.iter().any(|x| x.1 == 12) && lines.iter().any(|x| x.1 == 40));
+        assert!(lines.windows(2).all(|x| x[0].0 <= x[1].0));
+        assert_eq!(lines.last().map(|x| (x.0, x.2)), Some((size, true)));
+    }
+}
+
+/// 汇编输出的 `.file` `.loc` 和调试信息节；有 `cc` 时检查能否汇编
+#[test]
+fn test_dwarf_asm() {
+    let isa = isa_by_triple("x86_64-unknown-linux-gnu").unwrap();
+    let asm = isa.emit_asm(&program(5)).unwrap();
+    assert!(asm.starts_with("\t.file 0 \"/tmp\" \"test.c\"\n\t.file 1 \"test.c\"\n"));
+    assert!(asm.contains("\t.file 2 \"node.h\"\n\t.file 3 \"gen.y\"\n"));
+    assert!(asm.contains("main:\n\t.loc 1 10 0\n"));
+    assert!(asm.contains("\t.loc 3 40 5\n"));
+    assert!(asm.contains("\t.section .debug_info,\"\",@progbits\n.Ldebug_info0:\n"));
+    assert!(asm.contains("\t.long .Ldebug_abbrev0\n"));
+    assert!(asm.contains("\t.quad .Lfunc_end_main-main\n"));
+    assert!(asm.contains("\t.quad origin\n"));
+
+    let path = std::env::temp_dir().join("rcc-debug.s");
+    fs::write(&path, &asm).unwrap();
+    let Ok(status) = Command::new("cc")
+        .arg("-c")
+        .arg("-o")
+        .arg(path.with_extension("o"))
+        .arg(&path)
+        .status()
+    else {
+        return;
+    };
+    assert!(status.success(), "failed to assemble {}", path.display());
+}
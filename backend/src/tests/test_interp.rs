@@ -0,0 +1,154 @@
+use crate::err::interp_error::InterpError;
+use crate::interp::Interpreter;
+use crate::ir::parser::parse_module;
+use std::fs;
+
+fn run(text: &str) -> (Result<i32, InterpError>, String) {
+    let module = parse_module(text).unwrap();
+    let mut interp = Interpreter::new(&module).unwrap();
+    let result = interp.run_main(&["prog"]);
+    (result, String::from_utf8(interp.output).unwrap())
+}
+
+#[test]
+fn test_run_basic() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/ir/basic.ir");
+    let (result, output) = run(&fs::read_to_string(path).unwrap());
+    assert_eq!(result.unwrap(), 0);
+    assert_eq!(output, "3 1.500000\n");
+}
+
+#[test]
+fn test_printf() {
+    let text = r#"
+@fmt = internal constant 30, align 1 { bytes [37, 53, 100, 124, 37, 45, 52, 120, 124, 37, 48, 56, 46, 51, 102, 124, 37, 103, 124, 37, 115, 124, 37, 99, 124, 37, 37, 10, 0, 0] }
+@s = internal constant 3, align 1 { bytes [104, 105, 0] }
+declare i32 @printf(ptr, ...)
+declare void @exit(i32)
+define i32 @main() {
+bb0:
+    %0 = call i32 (ptr, ...) @printf(ptr @fmt, i32 -42, i32 255, f64 3.14159, f64 0.0001, ptr @s, i32 65)
+    call void (i32) @exit(i32 7)
+    ret i32 0
+}
+"#;
+    let (result, output) = run(text);
+    assert_eq!(result.unwrap(), 7);
+    assert_eq!(output, "  -42|ff  |0003.142|0.0001|hi|A|%\n");
+}
+
+#[test]
+fn test_undefined_behavior() {
+    let cases = [
+        (
+            "%0 = add nsw i32 2147483647, 1\n    ret i32 %0",
+            "signed integer overflow in add",
+        ),
+        (
+            "%0 = alloca 4, align 4\n    %1 = gep ptr %0, i64 1, scale 4, offset 0\n    %2 = load i32, ptr %1\n    ret i32 %2",
+            "out-of-bounds access of 4 bytes at offset 4 of a 4-byte object",
+        ),
+        (
+            "%0 = alloca 4, align 4\n    %1 = load i32, ptr %0\n    %2 = icmp eq i32 %1, 0\n    br i1 %2, bb1, bb1\nbb1:\n    ret i32 0",
+            "use of an uninitialized value in branch condition",
+        ),
+        (
+            "%0 = load i32, ptr null\n    ret i32 %0",
+            "null pointer dereference",
+        ),
+        (
+            "%0 = sdiv i32 1, 0\n    ret i32 %0",
+            "division by zero",
+        ),
+    ];
+    for (body, expected) in cases {
+        let text = format!("define i32 @main() {{\nbb0:\n    {}\n}}\n", body);
+        let (result, _) = run(&text);
+        assert_eq!(result.unwrap_err().to_string(), expected, "{}", body);
+    }
+}
+
+/// 位域只写入存储单元的一部分，写入的位可以读回，没有写入的位仍然未初始化
+#[test]
+fn test_partial_init() {
+    let store = "%0 = alloca 4, align 4
+    %1 = load i32, ptr %0
+    %2 = and i32 %1, -8
+    %3 = or i32 %2, 3
+    store i32 %3, ptr %0
+    %4 = load i32, ptr %0";
+    // (指令, 返回值, 期望结果)
+    let cases = [
+        ("%5 = shl i32 %4, 29\n    %6 = ashr i32 %5, 29", 6, Ok(3)),
+        ("%5 = and i32 %4, 7", 5, Ok(3)),
+        (
+            "%5 = trunc i32 %4 to i8\n    %6 = zext i8 %5 to i32",
+            6,
+            Err(()),
+        ),
+        (
+            "%5 = trunc i32 %4 to i8\n    %6 = zext i8 %5 to i32\n    %7 = and i32 %6, 7",
+            7,
+            Ok(3),
+        ),
+        ("%5 = lshr i32 %4, 3\n    %6 = and i32 %5, 31", 6, Err(())),
+        ("%5 = and i32 %4, 15", 5, Err(())),
+        ("%5 = add i32 %4, 0\n    %6 = and i32 %5, 7", 6, Err(())),
+    ];
+    for (body, ret, expected) in cases {
+        let text = format!(
+            "define i32 @main() {{\nbb0:\n    {}\n    {}\n    ret i32 %{}\n}}\n",
+            store, body, ret
+        );
+        let (result, _) = run(&text);
+        match expected {
+            Ok(value) => assert_eq!(result.unwrap(), value, "{}", body),
+            Err(()) => assert_eq!(
+                result.unwrap_err().to_string(),
+                "use of an uninitialized value in return value of main",
+                "{}",
+                body
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_thread_local() {
+    let text = r#"
+@value = thread_local global 4, align 4 { bytes [10, 0, 0, 0] }
+declare i32 @pthread_create(ptr, ptr, ptr, ptr)
+declare i32 @pthread_join(i64, ptr)
+define ptr @worker(ptr %0) {
+bb0:
+    %1 = load i32, ptr @value
+    %2 = ptrtoint ptr %0 to i64
+    %3 = trunc i64 %2 to i32
+    %4 = add i32 %1, %3
+    store i32 %4, ptr @value
+    %5 = sext i32 %4 to i64
+    %6 = inttoptr i64 %5 to ptr
+    ret ptr %6
+}
+define i32 @main() {
+bb0:
+    store i32 1, ptr @value
+    %0 = alloca 8, align 8
+    %1 = alloca 8, align 8
+    %2 = inttoptr i64 5 to ptr
+    %3 = call i32 (ptr, ptr, ptr, ptr) @pthread_create(ptr %0, ptr null, ptr @worker, ptr %2)
+    %4 = load i64, ptr %0
+    %5 = call i32 (i64, ptr) @pthread_join(i64 %4, ptr %1)
+    %6 = load ptr, ptr %1
+    %7 = ptrtoint ptr %6 to i64
+    %8 = trunc i64 %7 to i32
+    %9 = load i32, ptr @value
+    %10 = mul i32 %8, 100
+    %11 = add i32 %10, %9
+    ret i32 %11
+}
+"#;
+    // 新线程看到初始值 10，主线程的 1 不受影响
+    let (result, _) = run(text);
+    assert_eq!(result.unwrap(), 1501);
+}
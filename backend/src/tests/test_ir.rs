@@ -0,0 +1,49 @@
+use crate::ir::parser::parse_module;
+use crate::ir::verifier::verify_module;
+use std::fs;
+use std::path::Path;
+
+fn resources() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/ir"))
+}
+
+fn ir_files(dir: &Path) -> Vec<(String, String)> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .filter(|x| x.extension().is_some_and(|x| x == "ir"))
+        .map(|x| (x.display().to_string(), fs::read_to_string(&x).unwrap()))
+        .collect();
+    files.sort();
+    files
+}
+
+/// print(parse(print(parse(src)))) == print(parse(src))
+#[test]
+fn test_round_trip() {
+    let files = ir_files(resources());
+    assert!(!files.is_empty());
+    for (path, text) in files {
+        let module = parse_module(&text).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        verify_module(&module).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        let first = module.to_string();
+        let module = parse_module(&first).unwrap_or_else(|e| panic!("{}: {}\n{}", path, e, first));
+        verify_module(&module).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        assert_eq!(first, module.to_string(), "{}", path);
+    }
+}
+
+/// 每个文件第一行为 `; ERROR: <期望的错误信息>`
+#[test]
+fn test_invalid() {
+    let files = ir_files(&resources().join("invalid"));
+    assert!(!files.is_empty());
+    for (path, text) in files {
+        let expected = text.lines().next().and_then(|x| x.strip_prefix("; ERROR: ")).unwrap();
+        let error = parse_module(&text)
+            .and_then(|module| verify_module(&module))
+            .expect_err(&path)
+            .to_string();
+        assert!(error.contains(expected), "{}: {}", path, error);
+    }
+}
@@ -0,0 +1,466 @@
+use crate::codegen::isa_by_triple;
+use crate::codegen::x86_64::emit::emit_object;
+use crate::err::link_error::LinkError;
+use crate::ir::parser::parse_module;
+use crate::ir::printer::print_module;
+use crate::ir::{Module, RelocModel, Visibility};
+use crate::object::link::{link, link_shared};
+use crate::object::{Binding, Object, RelocKind, crt, elf};
+use std::fs;
+use std::process::Command;
+
+fn object(text: &str) -> Object {
+    emit_object(&parse_module(text).unwrap()).unwrap()
+}
+
+/// 和启动代码一起链接
+fn link_with_crt(objects: &[(&str, &str)]) -> Result<Vec<u8>, LinkError> {
+    let mut inputs = vec![("crt1.o".to_string(), crt::x86_64_linux())];
+    for (name, text) in objects {
+        inputs.push((name.to_string(), object(text)));
+    }
+    link(&inputs, "_start")
+}
+
+/// 运行链接出的可执行文件，返回退出码和输出；不是 x86-64 Linux 时返回 None
+fn run(name: &str, exe: &[u8]) -> Option<(i32, String)> {
+    if !cfg!(all(target_arch = "x86_64", target_os = "linux")) {
+        return None;
+    }
+    let path = std::env::temp_dir().join(format!("rcc-link-{}", name));
+    fs::write(&path, exe).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let output = Command::new(&path).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    Some((output.status.code().unwrap(), stdout))
+}
+
+const HELLO: &str = r#"
+@msg = internal constant 6, align 1 { bytes [104, 101, 108, 108, 111, 10] }
+@base = global 4, align 4 { bytes [40, 0, 0, 0] }
+@counter = global 4, align 4 { zero 4 }
+@self = internal global 8, align 8 { addr @counter }
+declare i64 @write(i32, ptr, i64)
+define i32 @main(i32 %a0, ptr %a1) {
+bb0:
+    %0 = call i64 (i32, ptr, i64) @write(i32 1, ptr @msg, i64 6)
+    %1 = load ptr, ptr @self
+    store i32 2, ptr %1
+    %2 = load i32, ptr @base
+    %3 = load i32, ptr @counter
+    %4 = add i32 %2, %3
+    %5 = add i32 %4, %a0
+    ret i32 %5
+}
+"#;
+
+/// 不依赖外部工具链，链接出的程序可以直接运行
+#[test]
+fn test_link_run() {
+    let exe = link_with_crt(&[("hello.o", HELLO)]).unwrap();
+    assert_eq!(&exe[..4], b"\x7fELF");
+    if let Some(result) = run("hello", &exe) {
+        assert_eq!(result, (43, "hello\n".to_string()));
+    }
+}
+
+/// 强符号覆盖弱符号，不同目标文件中的局部符号互不影响
+#[test]
+fn test_link_weak() {
+    let a = r#"
+define weak i32 @hook() {
+bb0:
+    ret i32 1
+}
+define internal i32 @helper() {
+bb0:
+    ret i32 10
+}
+define i32 @main() {
+bb0:
+    %0 = call i32 () @hook()
+    %1 = call i32 () @helper()
+    %2 = add i32 %0, %1
+    ret i32 %2
+}
+"#;
+    let b = r#"
+define i32 @hook() {
+bb0:
+    %0 = call i32 () @helper()
+    ret i32 %0
+}
+define internal i32 @helper() {
+bb0:
+    ret i32 20
+}
+"#;
+    let exe = link_with_crt(&[("a.o", a), ("b.o", b)]).unwrap();
+    if let Some(result) = run("weak", &exe) {
+        assert_eq!(result, (30, String::new()));
+    }
+    let exe = link_with_crt(&[("a.o", a)]).unwrap();
+    if let Some(result) = run("weak-only", &exe) {
+        assert_eq!(result, (11, String::new()));
+    }
+}
+
+#[test]
+fn test_link_errors() {
+    let main = r#"
+define i32 @main() {
+bb0:
+    ret i32 0
+}
+"#;
+    let err = link_with_crt(&[("a.o", main), ("b.o", main)]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "multiple definition of 'main': first defined in a.o, again in b.o"
+    );
+
+    let missing = r#"
+declare i32 @missing()
+define i32 @main() {
+bb0:
+    %0 = call i32 () @missing()
+    ret i32 %0
+}
+"#;
+    let err = link_with_crt(&[("a.o", missing)]).unwrap_err();
+    assert_eq!(err.to_string(), "a.o: undefined reference to 'missing'");
+
+    let err = link_with_crt(&[]).unwrap_err();
+    assert_eq!(err.to_string(), "crt1.o: undefined reference to 'main'");
+}
+
+/// 写出再读入的目标文件和原来相同
+#[test]
+fn test_elf_read() {
+    let obj = object(HELLO);
+    let read = elf::read("hello.o", &elf::write(&obj)).unwrap();
+    assert_eq!(read.comment, obj.comment);
+    for symbol in obj.symbols.iter() {
+        let other = read.symbols.iter().find(|x| x.name == symbol.name).unwrap();
+        assert_eq!(other, symbol);
+    }
+    for section in obj.sections.iter() {
+        let other = read
+            .sections
+            .iter()
+            .find(|x| x.name == section.name)
+            .unwrap();
+        assert_eq!(other.data, section.data);
+        assert_eq!(other.size(), section.size());
+        assert_eq!(other.relocs.len(), section.relocs.len());
+        for (x, y) in other.relocs.iter().zip(section.relocs.iter()) {
+            let name = |o: &Object, i: usize| o.symbols[i].name.clone();
+            assert_eq!((x.offset, x.kind, x.addend), (y.offset, y.kind, y.addend));
+            assert_eq!(name(&read, x.symbol), name(&obj, y.symbol));
+        }
+    }
+    assert!(read.symbols.iter().any(|x| x.binding == Binding::Local));
+    assert!(elf::read("bad.o", b"\x7fELF").is_err());
+}
+
+/// 插件：导出 `plugin` 和 `counter`，`host` `base` 由加载它的程序提供
+const PLUGIN: &str = r#"
+@counter = global 4, align 4 { bytes [5, 0, 0, 0] }
+@secret = hidden global 4, align 4 { bytes [2, 0, 0, 0] }
+@table = constant 16, align 8 { addr @counter, addr @helper }
+@base = external global
+declare i32 @host(i32)
+define hidden i32 @helper(i32 %a0) {
+bb0:
+    %0 = add i32 %a0, 100
+    ret i32 %0
+}
+define i32 @plugin(i32 %a0) {
+bb0:
+    %0 = load ptr, ptr @table
+    %1 = load i32, ptr %0
+    %2 = load i32, ptr @secret
+    %3 = call i32 (i32) @host(i32 %a0)
+    %4 = gep ptr @table, i64 1, scale 8, offset 0
+    %5 = load ptr, ptr %4
+    %6 = call i32 (i32) %5(i32 %a0)
+    %7 = load i32, ptr @base
+    %8 = add i32 %1, %2
+    %9 = add i32 %8, %3
+    %10 = add i32 %9, %6
+    %11 = add i32 %10, %7
+    store i32 %11, ptr @counter
+    ret i32 %11
+}
+"#;
+
+fn pic_module(text: &str, model: RelocModel) -> Module {
+    let mut module = parse_module(text).unwrap();
+    module.reloc_model = model;
+    module
+}
+
+/// 位置无关代码经 GOT 访问可能被抢占的符号，链接出的共享库可以被系统的工具链使用
+#[test]
+fn test_link_shared() {
+    let module = pic_module(PLUGIN, RelocModel::Pic);
+    let secret = module.global_by_name("secret").unwrap();
+    assert_eq!(module.globals[secret].visibility, Visibility::Hidden);
+    assert_eq!(
+        print_module(&parse_module(&print_module(&module)).unwrap()),
+        print_module(&module)
+    );
+
+    let obj = emit_object(&module).unwrap();
+    let kind = |name: &str| {
+        let relocs = obj.sections.iter().flat_map(|x| x.relocs.iter());
+        let mut kinds: Vec<RelocKind> = relocs
+            .filter(|x| obj.symbols[x.symbol].name == name)
+            .map(|x| x.kind)
+            .collect();
+        kinds.dedup();
+        kinds
+    };
+    assert_eq!(kind("counter"), [RelocKind::GotPcRel, RelocKind::Abs64]);
+    assert_eq!(kind("secret"), [RelocKind::Pc32]);
+    assert_eq!(kind("base"), [RelocKind::GotPcRel]);
+    assert_eq!(kind("host"), [RelocKind::Plt32]);
+    // 含有地址的常量需要加载时重定位，不能放在只读数据中
+    let table = obj.symbols.iter().find(|x| x.name == "table").unwrap();
+    assert_eq!(obj.sections[table.section.unwrap()].name, ".data");
+
+    let isa = isa_by_triple("x86_64-unknown-linux-gnu").unwrap();
+    let asm = isa.emit_asm(&module).unwrap();
+    assert!(asm.contains("movq base@GOTPCREL(%rip), "));
+    assert!(asm.contains("call host@PLT\n"));
+    assert!(asm.contains("\t.hidden secret\n"));
+
+    let lib = link_shared(
+        &[("plugin.o".to_string(), obj.clone())],
+        Some("libplugin.so"),
+    )
+    .unwrap();
+    assert_eq!(u16::from_le_bytes([lib[16], lib[17]]), elf::ET_DYN);
+
+    // 不是位置无关代码时报错
+    let static_obj = emit_object(&pic_module(PLUGIN, RelocModel::Static)).unwrap();
+    let err = link_shared(&[("plugin.o".to_string(), static_obj)], None).unwrap_err();
+    assert!(matches!(err, LinkError::NotPic { .. }), "{}", err);
+
+    if !cfg!(all(target_arch = "x86_64", target_os = "linux")) {
+        return;
+    }
+    let dir = std::env::temp_dir().join("rcc-link-shared");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("libplugin.so"), &lib).unwrap();
+    fs::write(dir.join("plugin.o"), elf::write(&obj)).unwrap();
+    let main = r#"
+#include <stdio.h>
+int base = 30;
+extern int counter;
+int plugin(int);
+int host(int x) { return x * 2; }
+int main(void) {
+    int result = plugin(3);
+    printf("%d %d\n", result, counter);
+    return 0;
+}
+"#;
+    fs::write(dir.join("main.c"), main).unwrap();
+
+    // 用我们的链接器和系统的链接器分别生成共享库
+    for (exe, lib) in [("main", "libplugin.so"), ("main-ld", "libplugin-ld.so")] {
+        if lib == "libplugin-ld.so" {
+            let Ok(status) = Command::new("cc")
+                .current_dir(&dir)
+                .args(["-shared", "-o", lib, "plugin.o"])
+                .status()
+            else {
+                return;
+            };
+            assert!(status.success());
+        }
+        let Ok(status) = Command::new("cc")
+            .current_dir(&dir)
+            .args(["-o", exe, "main.c", lib])
+            .status()
+        else {
+            return;
+        };
+        assert!(status.success());
+        let output = Command::new(dir.join(exe))
+            .env("LD_LIBRARY_PATH", &dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        // 5 + 2 + 6 + 103 + 30，可执行文件读到的是插件写入的 `counter`
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "146 146\n");
+    }
+}
+
+/// PIE 的代码经 GOT 访问外部声明的符号，静态链接时 GOT 项在链接时填好
+#[test]
+fn test_link_pie() {
+    let a = r#"
+@value = external global
+declare i32 @get()
+define i32 @main() {
+bb0:
+    %0 = load i32, ptr @value
+    %1 = call i32 () @get()
+    %2 = add i32 %0, %1
+    ret i32 %2
+}
+"#;
+    let b = r#"
+@value = global 4, align 4 { bytes [7, 0, 0, 0] }
+define i32 @get() {
+bb0:
+    %0 = load i32, ptr @value
+    ret i32 %0
+}
+"#;
+    let inputs = vec![
+        ("crt1.o".to_string(), crt::x86_64_linux()),
+        (
+            "a.o".to_string(),
+            emit_object(&pic_module(a, RelocModel::Pie)).unwrap(),
+        ),
+        (
+            "b.o".to_string(),
+            emit_object(&pic_module(b, RelocModel::Pie)).unwrap(),
+        ),
+    ];
+    let relocs = inputs[1].1.sections.iter().flat_map(|x| x.relocs.iter());
+    assert!(relocs.map(|x| x.kind).any(|x| x == RelocKind::GotPcRel));
+    let exe = link(&inputs, "_start").unwrap();
+    if let Some(result) = run("pie", &exe) {
+        assert_eq!(result, (14, String::new()));
+    }
+}
+
+/// 线程局部变量：可执行文件自己的用 local-exec，外部的用 initial-exec，
+/// 位置无关代码用 general-dynamic，静态链接时松弛为 local-exec
+#[test]
+fn test_link_tls() {
+    let a = r#"
+@counter = thread_local global 4, align 4 { bytes [5, 0, 0, 0] }
+@zeroed = internal thread_local global 4, align 4 { zero 4 }
+@shared = external thread_local global
+declare i32 @bump()
+define i32 @main() {
+bb0:
+    %0 = load i32, ptr @counter
+    store i32 7, ptr @zeroed
+    %1 = load i32, ptr @zeroed
+    %2 = call i32 () @bump()
+    %3 = load i32, ptr @shared
+    %4 = add i32 %0, %1
+    %5 = add i32 %4, %2
+    %6 = add i32 %5, %3
+    ret i32 %6
+}
+"#;
+    let b = r#"
+@shared = thread_local global 4, align 4 { bytes [30, 0, 0, 0] }
+define i32 @bump() {
+bb0:
+    %0 = load i32, ptr @shared
+    %1 = add i32 %0, 1
+    store i32 %1, ptr @shared
+    ret i32 %1
+}
+"#;
+    let a_module = pic_module(a, RelocModel::Static);
+    let b_module = pic_module(b, RelocModel::Pic);
+    assert_eq!(
+        print_module(&parse_module(&print_module(&a_module)).unwrap()),
+        print_module(&a_module)
+    );
+    let isa = isa_by_triple("x86_64-unknown-linux-gnu").unwrap();
+    let asm = isa.emit_asm(&a_module).unwrap();
+    assert!(asm.contains("movq %fs:0, "));
+    assert!(asm.contains("counter@tpoff("));
+    assert!(asm.contains("movq shared@gottpoff(%rip), "));
+    assert!(asm.contains("\t.section .tbss,\"awT\",@nobits\n"));
+    assert!(asm.contains("\t.type counter, @tls_object\n"));
+    let asm = isa.emit_asm(&b_module).unwrap();
+    assert!(asm.contains("leaq shared@tlsgd(%rip), %rdi\n\t.value 0x6666\n\trex64\n"));
+
+    let a_obj = emit_object(&a_module).unwrap();
+    let b_obj = emit_object(&b_module).unwrap();
+    let kinds = |obj: &Object| -> Vec<RelocKind> {
+        let mut kinds: Vec<RelocKind> = obj.sections[0].relocs.iter().map(|x| x.kind).collect();
+        kinds.sort_by_key(|x| *x as u8);
+        kinds.dedup();
+        kinds
+    };
+    assert_eq!(
+        kinds(&a_obj),
+        [RelocKind::Plt32, RelocKind::TpOff32, RelocKind::GotTpOff]
+    );
+    assert_eq!(kinds(&b_obj), [RelocKind::Plt32, RelocKind::TlsGd]);
+
+    let inputs = vec![
+        ("crt1.o".to_string(), crt::x86_64_linux()),
+        ("a.o".to_string(), a_obj),
+        ("b.o".to_string(), b_obj.clone()),
+    ];
+    let exe = link(&inputs, "_start").unwrap();
+    // 5 + 7 + 31 + 31
+    if let Some(result) = run("tls", &exe) {
+        assert_eq!(result, (74, String::new()));
+    }
+
+    // local-exec 不能用于共享库
+    let static_obj = emit_object(&a_module).unwrap();
+    let err = link_shared(&[("a.o".to_string(), static_obj)], None).unwrap_err();
+    assert!(matches!(err, LinkError::NotPic { .. }), "{}", err);
+
+    // general-dynamic 的共享库由系统的动态链接器分配线程局部存储
+    let lib = link_shared(&[("b.o".to_string(), b_obj)], Some("libtls.so")).unwrap();
+    if !cfg!(all(target_arch = "x86_64", target_os = "linux")) {
+        return;
+    }
+    let dir = std::env::temp_dir().join("rcc-link-tls-shared");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("libtls.so"), &lib).unwrap();
+    let main = r#"
+#include <pthread.h>
+#include <stdio.h>
+extern __thread int shared;
+int bump(void);
+static void *run(void *arg) {
+    bump();
+    return (void *)(long)bump();
+}
+int main(void) {
+    pthread_t thread;
+    void *result;
+    bump();
+    pthread_create(&thread, 0, run, 0);
+    pthread_join(thread, &result);
+    printf("%d %d\n", shared, (int)(long)result);
+    return 0;
+}
+"#;
+    fs::write(dir.join("main.c"), main).unwrap();
+    let Ok(status) = Command::new("cc")
+        .current_dir(&dir)
+        .args(["-o", "main", "main.c", "libtls.so", "-pthread"])
+        .status()
+    else {
+        return;
+    };
+    assert!(status.success());
+    let output = Command::new(dir.join("main"))
+        .env("LD_LIBRARY_PATH", &dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "31 32\n");
+}
@@ -0,0 +1,161 @@
+use crate::interp::Interpreter;
+use crate::ir::FuncId;
+use crate::ir::cfg::DomTree;
+use crate::ir::loops::LoopInfo;
+use crate::ir::parser::parse_module;
+use crate::ir::verifier::verify_module;
+use crate::opt::PassManager;
+use crate::opt::call_graph::CallGraph;
+use std::fs;
+use std::path::Path;
+
+fn resources() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/opt"))
+}
+
+/// 每个 `.ir` 第一行为 `; PASSES: <pass 列表>`，运行后打印的模块和同名的 `.out` 逐字比较
+#[test]
+fn test_passes() {
+    let mut files: Vec<_> = fs::read_dir(resources())
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .filter(|x| x.extension().is_some_and(|x| x == "ir"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+    for path in files {
+        let name = path.display().to_string();
+        let text = fs::read_to_string(&path).unwrap();
+        let passes = text
+            .lines()
+            .next()
+            .and_then(|x| x.strip_prefix("; PASSES: "))
+            .unwrap();
+        let mut module = parse_module(&text).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        verify_module(&module).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let mut pm = PassManager::parse(passes).unwrap();
+        pm.verify = true;
+        pm.run(&mut module)
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let expected = fs::read_to_string(path.with_extension("out")).unwrap();
+        assert_eq!(module.to_string(), expected, "{}", name);
+    }
+}
+
+/// `-O1` `-O2` 的流水线不改变程序的输出
+#[test]
+fn test_pipeline_semantics() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/codegen");
+    for name in ["ops", "abi", "pressure"] {
+        let text = fs::read_to_string(format!("{}/{}.ir", dir, name)).unwrap();
+        let module = parse_module(&text).unwrap();
+        let mut interp = Interpreter::new(&module).unwrap();
+        let expected = interp.run_main(&["prog"]).unwrap();
+        let expected_output = interp.output.clone();
+
+        for level in [1, 2] {
+            let mut optimized = module.clone();
+            let mut pm = PassManager::for_level(level);
+            pm.verify = true;
+            pm.run(&mut optimized)
+                .unwrap_or_else(|e| panic!("{} -O{}: {}", name, level, e));
+            let mut interp = Interpreter::new(&optimized).unwrap();
+            let code = interp.run_main(&["prog"]).unwrap();
+            assert_eq!(code, expected, "{} -O{}", name, level);
+            assert_eq!(interp.output, expected_output, "{} -O{}", name, level);
+        }
+    }
+}
+
+#[test]
+fn test_call_graph() {
+    let text = fs::read_to_string(resources().join("inline.ir")).unwrap();
+    let module = parse_module(&text).unwrap();
+    let id = |name: &str| module.func_by_name(name).unwrap();
+    let graph = CallGraph::new(&module);
+    let sccs = graph.sccs(&module);
+    let order: Vec<FuncId> = sccs.iter().flatten().cloned().collect();
+    let pos = |name: &str| order.iter().position(|x| *x == id(name)).unwrap();
+    assert!(pos("get") < pos("sum") && pos("fact") < pos("sum"));
+    assert!(graph.is_recursive(&sccs, id("fact")));
+    assert!(!graph.is_recursive(&sccs, id("sum")));
+    assert_eq!(graph.callers(id("get")), [id("sum")]);
+    assert_eq!(graph.callees(id("sum")).len(), 5);
+    assert!(graph.is_address_taken(id("callback")));
+    assert!(!graph.is_address_taken(id("get")));
+}
+
+/// `loops/` 中的程序在循环优化前后由解释器运行，输出和返回值相同
+#[test]
+fn test_loop_corpus() {
+    let mut files: Vec<_> = fs::read_dir(resources().join("loops"))
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+    for path in files {
+        let name = path.display().to_string();
+        let module = parse_module(&fs::read_to_string(&path).unwrap()).unwrap();
+        let mut interp = Interpreter::new(&module).unwrap();
+        let expected = interp.run_main(&["prog"]).unwrap();
+        let expected_output = interp.output.clone();
+
+        let mut pipelines: Vec<(String, PassManager)> = [
+            "licm",
+            "indvars",
+            "unroll",
+            "mem2reg licm",
+            "mem2reg indvars",
+            "mem2reg unroll",
+            "mem2reg gvn licm",
+            "mem2reg sccp licm indvars unroll sccp simplify-cfg dce",
+        ]
+        .iter()
+        .map(|x| (x.to_string(), PassManager::parse(x).unwrap()))
+        .collect();
+        for level in [1, 2] {
+            pipelines.push((format!("-O{}", level), PassManager::for_level(level)));
+        }
+        for (passes, mut pm) in pipelines {
+            let mut optimized = module.clone();
+            pm.verify = true;
+            pm.run(&mut optimized)
+                .unwrap_or_else(|e| panic!("{} {}: {}", name, passes, e));
+            let mut interp = Interpreter::new(&optimized).unwrap();
+            let code = interp.run_main(&["prog"]).unwrap();
+            assert_eq!(code, expected, "{} {}", name, passes);
+            assert_eq!(interp.output, expected_output, "{} {}", name, passes);
+        }
+    }
+}
+
+#[test]
+fn test_loop_info() {
+    let text = fs::read_to_string(resources().join("licm.ir")).unwrap();
+    let module = parse_module(&text).unwrap();
+    let func = &module.funcs[module.func_by_name("nest").unwrap()];
+    let dom = DomTree::new(func);
+    let info = LoopInfo::new(func, &dom);
+    let [outer, inner] = info.loops() else {
+        panic!("expect 2 loops");
+    };
+    assert_eq!(outer.depth, 1);
+    assert_eq!(inner.depth, 2);
+    assert_eq!(inner.parent, Some(0));
+    assert_eq!(outer.children, [1]);
+    assert_eq!(outer.blocks.len(), 4);
+    assert_eq!(inner.blocks.len(), 2);
+    assert!(inner.blocks.iter().all(|x| outer.contains(*x)));
+    assert_eq!(info.innermost(inner.latches[0]), Some(1));
+    assert_eq!(info.depth(func.entry()), 0);
+    // 外层循环头有两个循环外的前驱，内层循环头循环外的前驱是条件分支
+    assert_eq!(outer.preheader(func), None);
+    assert_eq!(inner.preheader(func), None);
+    assert_eq!(info.postorder(), [1, 0]);
+}
+
+#[test]
+fn test_unknown_pass() {
+    assert_eq!(PassManager::parse("mem2reg,sroa").err().unwrap(), "sroa");
+}
@@ -0,0 +1,10 @@
+#define N 3
+typedef struct P { int x : 4; char *s; } P;
+enum E { A, B = N };
+static int arr[N] = {1, 2, 3};
+int f(int a, ...);
+int main(void) {
+    P p = {1, "s"};
+    for (int i = 0; i < N; i++) p.x += arr[i];
+    return p.x > 2 ? f(p.x, sizeof(P)) : (int)B;
+}
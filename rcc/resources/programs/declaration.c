@@ -0,0 +1,71 @@
+/* 声明测试，main 的返回值是各项结果的校验和 */
+
+// 函数声明
+int factorial(int n);
+static int add(int a, int b);
+
+// typedef 与类型组合
+typedef int type1;
+typedef unsigned long size_type;
+typedef struct point point_t;
+
+// 前向声明的 struct 与定义
+struct point {
+    int x, y;
+};
+
+struct person {
+    char name[20];
+    unsigned age : 7;
+    point_t pos;
+    struct person *next;
+};
+
+union number {
+    int i;
+    char bytes[4];
+};
+
+enum color { RED, GREEN = 5, BLUE };
+
+// 全局变量与暂定定义
+type1 counter = 10;
+int tentative;
+int tentative;
+static const int table[] = {1, 2, 3, 4};
+const char *name = "declaration";
+int (*operation)(int, int) = add;
+int *pointers[3];
+int matrix[2][3] = {{1, 2, 3}, {4, 5, 6}};
+
+int factorial(int n) {
+    return (n <= 1) ? 1 : n * factorial(n - 1);
+}
+
+static int add(int a, int b) {
+    return a + b;
+}
+
+int main(void) {
+    struct person p = {"rcc", 30, {1, 2}, 0};
+    union number num;
+    enum color c = BLUE;
+    register int i;
+    size_type len = sizeof(struct person);
+    int sum = 0;
+
+    num.i = 0;
+    num.bytes[0] = 7;
+    pointers[0] = &counter;
+
+    for (i = 0; i < 4; i++) {
+        sum += table[i];
+    }
+    sum += factorial(4);
+    sum += operation(p.pos.x, p.pos.y);
+    sum += p.age + c + num.i + *pointers[0] + tentative;
+    sum += matrix[1][2];
+    sum += p.name[0] == 'r';
+    sum += len > 0;
+    return sum;
+}
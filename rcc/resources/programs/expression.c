@@ -0,0 +1,56 @@
+/* 表达式测试，main 的返回值是所有结果的校验和 */
+
+int printf(const char *fmt, ...);
+
+int main(void) {
+    int x, y, z, result;
+    int flags, mask, combined;
+    double d;
+    int n;
+    char c;
+    double mixed;
+    int arr[6] = {1, 3, 5, 7, 9, 11};
+    int *ptr;
+    int arr_val;
+    int a, b;
+    int sum = 0;
+
+    // 1. 嵌套三元运算符
+    x = 10, y = 20, z = 30;
+    result = (x > y) ? (y > z ? y : z) : (x > z ? x : z);
+    printf("1. 嵌套三元运算: %d\n", result);
+    sum += result;
+
+    // 2. 复杂位运算表达式
+    flags = 0x00F0;
+    mask = 0x0F0F;
+    combined = (flags & mask) | ((~flags) & (mask << 1)) ^ 0xAAAA;
+    printf("2. 复杂位运算: 0x%04X\n", combined & 0xFFFF);
+    sum += combined & 0xFF;
+
+    // 3. 混合类型表达式
+    d = 3.5;
+    n = 10;
+    c = 'A';
+    mixed = (d * n) + (c / (d - 3)) - (n % (int)(d * 10));
+    printf("3. 混合类型计算: %.4f\n", mixed);
+    sum += (int)mixed;
+
+    // 4. 指针算术与数组访问
+    ptr = arr + 2;
+    arr_val = *(ptr + 1) + ptr[-1] * (*ptr % 4);
+    printf("4. 指针算术: %d\n", arr_val);
+    sum += arr_val;
+
+    // 5. 复合赋值与副作用
+    a = 5, b = 7;
+    a += (b += 3) - 2;
+    printf("5. 复合赋值链: a=%d, b=%d\n", a, b);
+    sum += a + b;
+
+    // 6. 逻辑与比较
+    sum += (x < y && y < z) + (x == 10 || y / 0) + !(z != 30);
+    sum += sizeof(int) + sizeof arr / sizeof arr[0];
+
+    return sum % 256;
+}
@@ -0,0 +1,154 @@
+/* 语句测试，main 的返回值是各项测试的校验和 */
+
+int printf(const char *fmt, ...);
+
+/* 1. 基本语句测试 */
+int test_basic_statements(void) {
+    int a, b = 0;
+    printf("\n=== 基本语句测试 ===\n");
+
+    // 表达式语句
+    a = 10;
+    a++;  // 后缀自增
+    ++a;  // 前缀自增
+    printf("1.1 表达式语句: a = %d\n", a);
+
+    // 空语句
+    ; // 这是一个空语句
+
+    // 复合语句(块)
+    {
+        int b = 20;
+        printf("1.3 复合语句: b = %d\n", b);
+        a += b;
+    }
+    return a + b;
+}
+
+/* 2. 控制流语句测试 */
+int test_control_flow(void) {
+    int x = 5, count, total = 0;
+    printf("\n=== 控制流语句测试 ===\n");
+
+    // if-else 语句
+    if (x > 10) {
+        total += 100;
+    } else if (x > 0) {
+        total += 1;
+    } else {
+        total += 200;
+    }
+
+    // switch 语句
+    switch (x) {
+        case 1:
+            total += 100;
+            break;
+        case 5:
+            total += 2;
+            // 故意不加break测试fall-through
+        case 6:
+            total += 3;
+            break;
+        default:
+            total += 100;
+    }
+
+    // while 循环
+    count = 0;
+    while (count < 3) {
+        count++;
+    }
+    total += count;
+
+    // do-while 循环
+    count = 0;
+    do {
+        count++;
+    } while (count < 3);
+    total += count;
+
+    // for 循环
+    for (int i = 0; i < 3; i++) {
+        total += i;
+    }
+
+    // for循环中的复杂表达式
+    for (int i = 0, j = 10; i < j; i++, j--) {
+        total++;
+    }
+    return total;
+}
+
+/* 3. 跳转语句测试 */
+int test_jump_statements(void) {
+    int n = 0, i, total = 0;
+    printf("\n=== 跳转语句测试 ===\n");
+
+    // goto 语句
+LOOP_LABEL:
+    if (n < 3) {
+        n++;
+        goto LOOP_LABEL;
+    }
+    total += n;
+
+    // break 语句
+    for (i = 0; i < 5; i++) {
+        if (i == 3) break;
+    }
+    total += i;
+
+    // continue 语句
+    for (i = 0; i < 5; i++) {
+        if (i % 2 == 0) continue;
+        total += i;
+    }
+
+    // return 语句
+    return total;
+    total = 0;
+}
+
+/* 4. 语句组合测试 */
+int test_statement_combinations(void) {
+    int i, j, total = 0;
+    char c = 'A';
+    printf("\n=== 语句组合测试 ===\n");
+
+    // 嵌套控制流
+    for (i = 0; i < 3; i++) {
+        for (j = 0; j < 2; j++) {
+            if (j == 1) {
+                total += i;
+            }
+        }
+    }
+
+    // switch 嵌套在循环中
+    while (c <= 'C') {
+        switch (c) {
+            case 'A':
+                total += 10;
+                break;
+            case 'B':
+                c++;
+                continue;
+            default:
+                total += 20;
+        }
+        c++;
+    }
+    return total;
+}
+
+int main(void) {
+    int sum = 0;
+    sum += test_basic_statements();
+    sum += test_control_flow();
+    sum += test_jump_statements();
+    sum += test_statement_combinations();
+
+    printf("\n=== 语句测试完成 ===\n");
+    return sum;
+}
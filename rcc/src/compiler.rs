@@ -1 +1,2 @@
-pub mod c_compiler;
\ No newline at end of file
+pub mod c_compiler;
+pub mod options;
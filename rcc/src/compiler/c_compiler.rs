@@ -1,59 +1,357 @@
+use crate::compiler::options::{Action, CompilerOptions, MacroArg};
 use crate::content_manager::ContentManager;
+use crate::err::driver_error::{DriverError, DriverResult};
 use crate::lex::lex_core::{Lex, run_lexer};
 use crate::lex::token_stream::TokenStream;
+use crate::lower::lower_debug::DebugLower;
+use crate::lower::{lower_unit, lower_unit_debug};
+use crate::parser::ast::func::TranslationUnit;
 use crate::parser::ast::visitor::Visitor;
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::parse_translation_unit;
+use crate::preprocess::Preprocessor;
+use crate::preprocess::source_map::SourceMap;
+use crate::writer::ast_dump::AstDumper;
 use crate::writer::ast_graph::AstGraph;
-use petgraph::dot::{Config, Dot};
+use crate::writer::ast_json;
+use crate::writer::c_printer::{CPrinter, ParenStyle};
+use backend::codegen::{TargetIsa, isa, isa_by_triple, llvm};
+use backend::interp::Interpreter;
+use backend::ir::{Module, RelocModel};
+use backend::object::link::{link, link_shared};
+use backend::opt::PassManager;
+use backend::object::{crt, elf};
+use backend::target::{Arch, TargetInfo};
+use std::cell::OnceCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, mpsc};
 
 ///
 /// 编译器主流程
 ///
 /// # Members
-/// - `input`: 输入
-/// - `token_bound`: token有界队列大小
+/// - `code`: 输入代码
+/// - `options`: 命令行选项
+/// - `source`: 预处理后的代码和它到源文件位置的映射，第一次使用时生成
 ///
 pub struct CCompiler {
     code: String,
+    options: CompilerOptions,
+    source: OnceCell<(String, SourceMap)>,
 }
 
 impl CCompiler {
-    pub fn new(code: String) -> Self {
-        Self { code }
-    }
-
-    // ///
-    // /// 编译代码，lexer --> parser --> AST
-    // /// 1. 前端部分lexer parser相互协作，parser提供基础的typedef定义（临时符号表），parser需要在构建CST的同时最小
-    // ///    限度解析作用域和typedef符号定义，lexer使用临时符号表，查询ID是否为TYPE_NAME
-    // /// 2. AST
-    // ///
-    // ///
-    // ///
-    // pub fn compile(self) {
-    //     let content_manager = Arc::new(ContentManager::new(self.code));
-    //
-    //     let (error_tx, error_rx) = mpsc::channel();
-    //
-    //     // 执行lexer
-    //     let lex = Lex::new(Arc::clone(&content_manager));
-    //     let tokens = run_lexer(lex, error_tx);
-    //
-    //
-    //     let sema = Sema::new();
-    //     let token_stream = TokenStream::new(tokens);
-    //     let mut parser = Parser::new(token_stream, sema);
-    //
-    //     let mut trans_unit = parser.parse_translation_unit().unwrap();
-    //     // println!("{:#?}", trans_unit);
-    //
-    //     let mut graph = AstGraph::new();
-    //     graph.walk_translation_unit(&mut trans_unit);
-    //
-    //     let dot = Dot::with_config(&graph.tree, &[Config::EdgeNoLabel]);
-    //     println!("{:?}", dot);
-    //     // for x in error_rx {
-    //     //     eprintln!("{x:?}")
-    //     // }
-    // }
+    pub fn new(code: String, options: CompilerOptions) -> Self {
+        Self {
+            code,
+            options,
+            source: OnceCell::new(),
+        }
+    }
+
+    ///
+    /// 编译代码，预处理 --> lexer --> parser --> AST --> IR
+    /// 1. 前端部分lexer parser相互协作，parser 在构建 AST 的同时完成 sema
+    /// 2. 根据 `options.action` 决定输出，返回进程的退出码
+    ///
+    pub fn compile(self) -> DriverResult<i32> {
+        if self.options.action == Action::Preprocess {
+            self.emit_preprocessed()?;
+            return Ok(0);
+        }
+        let (content_manager, ctx, unit) = self.parse()?;
+
+        match self.options.action {
+            Action::Compile => {
+                if let Some(path) = self.options.output.as_deref() {
+                    let exe = self.link(&ctx, &unit)?;
+                    write_output(path, &exe)?;
+                    if !self.is_wasm() {
+                        make_executable(path)?;
+                    }
+                }
+            }
+            Action::AstDump => self.ast_dump(&ctx, &content_manager, &unit),
+            Action::AstDot => self.ast_dot(&ctx, &unit),
+            Action::EmitC => self.emit_c(&ctx, &unit),
+            Action::AstJson => {
+                println!("{}", ast_json::to_json(&ctx, &content_manager, &unit))
+            }
+            Action::EmitIr => print!("{}", self.lower(&ctx, &unit)?),
+            Action::Run => return self.run(&ctx, &unit),
+            Action::EmitAsm | Action::EmitLlvm => {
+                let text = match self.options.action {
+                    Action::EmitAsm => self.emit_asm(&ctx, &unit)?,
+                    _ => self.emit_llvm(&ctx, &unit)?,
+                };
+                match self.options.output.as_deref() {
+                    Some(path) => write_output(path, text.as_bytes())?,
+                    None => print!("{}", text),
+                }
+            }
+            Action::EmitObj => {
+                let obj = self.emit_obj(&ctx, &unit)?;
+                write_output(&self.object_path(), &obj)?;
+            }
+            Action::Preprocess => unreachable!("handled before parsing"),
+        }
+
+        Ok(0)
+    }
+
+    /// 按命令行配置的预处理器：`-I` 目录、预定义宏，然后按顺序处理 `-D` `-U`
+    fn preprocessor(&self) -> DriverResult<Preprocessor> {
+        let dirs = self
+            .options
+            .include_dirs
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut preprocessor = Preprocessor::new(self.target()).with_include_dirs(dirs);
+        preprocessor.predefine(self.options.std.version())?;
+        for arg in self.options.macros.iter() {
+            match arg {
+                MacroArg::Define(def) => preprocessor.define(def)?,
+                MacroArg::Undef(name) => preprocessor.undef(name)?,
+            }
+        }
+        Ok(preprocessor)
+    }
+
+    /// 预处理后的代码和源码映射，lexer 的位置基于预处理后的代码
+    fn preprocessed(&self) -> DriverResult<&(String, SourceMap)> {
+        if let Some(source) = self.source.get() {
+            return Ok(source);
+        }
+        let file = self.options.input.as_deref().unwrap_or("a.c");
+        let mut preprocessor = self.preprocessor()?;
+        let source = preprocessor.preprocess(file, &self.code)?;
+        let map = preprocessor.take_source_map();
+        Ok(self.source.get_or_init(|| (source, map)))
+    }
+
+    /// 预处理后的代码
+    pub fn source(&self) -> DriverResult<&str> {
+        Ok(&self.preprocessed()?.0)
+    }
+
+    /// 预处理后的代码，位置通过源码映射转换为源文件的行列号
+    pub fn content(&self) -> DriverResult<ContentManager> {
+        let (source, map) = self.preprocessed()?;
+        Ok(ContentManager::with_source_map(source.clone(), map.clone()))
+    }
+
+    /// `-E` 输出预处理后的代码，加上 `-dM` 时输出预处理结束时定义的所有宏
+    fn emit_preprocessed(&self) -> DriverResult<()> {
+        let text = match self.options.dump_macros {
+            true => {
+                let mut preprocessor = self.preprocessor()?;
+                let file = self.options.input.as_deref().unwrap_or("a.c");
+                preprocessor.preprocess(file, &self.code)?;
+                preprocessor.dump_macros()
+            }
+            false => self.source()?.to_owned(),
+        };
+        match self.options.output.as_deref() {
+            Some(path) => write_output(path, text.as_bytes()),
+            None => {
+                print!("{}", text);
+                Ok(())
+            }
+        }
+    }
+
+    /// 前端：预处理 --> lexer --> parser(sema) --> AST，出错时输出诊断
+    pub fn parse(&self) -> DriverResult<(Arc<ContentManager>, CompCtx, TranslationUnit)> {
+        let content_manager = Arc::new(self.content()?);
+
+        let (error_tx, error_rx) = mpsc::channel();
+
+        // 执行lexer
+        let lex = Lex::new(Arc::clone(&content_manager));
+        let tokens = run_lexer(lex, error_tx);
+        let lex_errors: Vec<_> = error_rx.into_iter().collect();
+        for x in lex_errors.iter() {
+            eprintln!("{x:?}")
+        }
+
+        let token_stream = TokenStream::new(tokens);
+        let mut ctx = CompCtx::new(token_stream);
+        ctx.target = self.target();
+        let result = parse_translation_unit(&mut ctx);
+
+        for x in ctx.errors.iter() {
+            eprintln!("{}: {}", x.level, x.error_kind)
+        }
+        let unit = match result {
+            Ok(x) => x,
+            Err(err) => {
+                eprintln!("{}: {}", err.level, err.error_kind);
+                return Err(DriverError::CompileFailed(ctx.errors.len() + 1));
+            }
+        };
+        if !lex_errors.is_empty() {
+            return Err(DriverError::CompileFailed(lex_errors.len()));
+        }
+
+        Ok((content_manager, ctx, unit))
+    }
+
+    /// AST --> IR，出错时输出诊断；再按 `-O` 的级别优化
+    pub fn lower(&self, ctx: &CompCtx, unit: &TranslationUnit) -> DriverResult<Module> {
+        let result = match self.options.debug {
+            Some(version) => {
+                // 行列号通过源码映射计算，文件名和目录记录在调试信息中
+                let content = self.content()?;
+                let file = self.options.input.as_deref().unwrap_or("a.c");
+                let dir = std::env::current_dir().unwrap_or_default();
+                let debug = DebugLower::new(&content, file, &dir.to_string_lossy(), version);
+                lower_unit_debug(ctx, unit, debug)
+            }
+            None => lower_unit(ctx, unit),
+        };
+        let mut module = result.map_err(|err| {
+            eprintln!("error: {}", err);
+            DriverError::CompileFailed(1)
+        })?;
+        module.reloc_model = self.reloc_model();
+        PassManager::for_level(self.options.opt_level).run(&mut module)?;
+        Ok(module)
+    }
+
+    /// `-shared` 时至少是 `Pic`
+    fn reloc_model(&self) -> RelocModel {
+        match self.options.reloc_model {
+            RelocModel::Static | RelocModel::Pie if self.options.shared => RelocModel::Pic,
+            model => model,
+        }
+    }
+
+    /// 解释执行 `main`，出错前的输出也会写到标准输出
+    fn run(&self, ctx: &CompCtx, unit: &TranslationUnit) -> DriverResult<i32> {
+        let module = self.lower(ctx, unit)?;
+        let mut interp = Interpreter::new(&module)?;
+        let result = interp.run_main(&["a.out"]);
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(&interp.output);
+        let _ = stdout.flush();
+        Ok(result?)
+    }
+
+    /// 目标由 `-target` 决定
+    fn isa(&self) -> DriverResult<Box<dyn TargetIsa>> {
+        Ok(match self.options.target.as_deref() {
+            Some(triple) => isa_by_triple(triple)?,
+            None => isa(TargetInfo::host()),
+        })
+    }
+
+    /// IR --> 汇编
+    fn emit_asm(&self, ctx: &CompCtx, unit: &TranslationUnit) -> DriverResult<String> {
+        let isa = self.isa()?;
+        let module = self.lower(ctx, unit)?;
+        Ok(isa.emit_asm(&module)?)
+    }
+
+    /// IR --> LLVM IR 文本，数据布局和三元组来自目标
+    fn emit_llvm(&self, ctx: &CompCtx, unit: &TranslationUnit) -> DriverResult<String> {
+        let isa = self.isa()?;
+        let module = self.lower(ctx, unit)?;
+        Ok(llvm::emit_module(&module, isa.info()))
+    }
+
+    /// IR --> 目标文件
+    fn emit_obj(&self, ctx: &CompCtx, unit: &TranslationUnit) -> DriverResult<Vec<u8>> {
+        let isa = self.isa()?;
+        let module = self.lower(ctx, unit)?;
+        Ok(isa.emit_object(&module)?)
+    }
+
+    /// `-target` 对应的目标信息，没有指定或无法识别时为宿主
+    fn target(&self) -> TargetInfo {
+        let triple = self.options.target.as_deref();
+        triple
+            .and_then(TargetInfo::from_triple)
+            .unwrap_or_else(TargetInfo::host)
+    }
+
+    /// 目标是否为 wasm32，它的“目标文件”已经是完整的模块，不需要链接
+    fn is_wasm(&self) -> bool {
+        self.target().arch == Arch::Wasm32
+    }
+
+    /// IR --> 目标文件 --> 和启动代码静态链接为可执行文件；`-shared` 时不带启动代码链接为共享库，
+    /// soname 为输出文件名
+    fn link(&self, ctx: &CompCtx, unit: &TranslationUnit) -> DriverResult<Vec<u8>> {
+        let obj = self.emit_obj(ctx, unit)?;
+        if self.is_wasm() {
+            return Ok(obj);
+        }
+        let name = self.object_path();
+        let obj = elf::read(&name, &obj)?;
+        if self.options.shared {
+            let soname = Path::new(&name).file_name().map(|x| x.to_string_lossy());
+            return Ok(link_shared(&[(name.clone(), obj)], soname.as_deref())?);
+        }
+        let inputs = [("crt1.o".to_string(), crt::x86_64_linux()), (name, obj)];
+        Ok(link(&inputs, "_start")?)
+    }
+
+    /// `-c` 的输出文件：`-o` 指定的文件，或者输入文件名换成 `.o`（wasm32 为 `.wasm`）
+    fn object_path(&self) -> String {
+        if let Some(path) = &self.options.output {
+            return path.clone();
+        }
+        let input = self.options.input.as_deref().unwrap_or("a.c");
+        let stem = Path::new(input).file_stem().unwrap_or_default();
+        let ext = if self.is_wasm() { "wasm" } else { "o" };
+        format!("{}.{}", stem.to_string_lossy(), ext)
+    }
+
+    fn ast_dump(&self, ctx: &CompCtx, content: &ContentManager, unit: &TranslationUnit) {
+        let filter = self.options.ast_dump_filter.as_deref();
+        let mut dumper = AstDumper::new(ctx, content, filter);
+        dumper.walk_translation_unit(unit);
+        print!("{}", dumper.finish());
+    }
+
+    fn emit_c(&self, ctx: &CompCtx, unit: &TranslationUnit) {
+        let parens = match self.options.c_full_parens {
+            true => ParenStyle::Full,
+            false => ParenStyle::Minimal,
+        };
+        print!("{}", CPrinter::new(ctx, parens).print_unit(unit));
+    }
+
+    fn ast_dot(&self, ctx: &CompCtx, unit: &TranslationUnit) {
+        let filter = self.options.ast_dump_filter.as_deref();
+        let mut graph = AstGraph::new(ctx)
+            .with_filter(filter)
+            .with_decl_refs(self.options.ast_dot_decl_refs);
+        graph.walk_translation_unit(unit);
+        println!("{}", graph.to_dot());
+    }
+}
+
+fn write_output(path: &str, data: &[u8]) -> DriverResult<()> {
+    std::fs::write(path, data).map_err(|err| DriverError::Io {
+        path: path.to_string(),
+        err,
+    })
+}
+
+#[cfg(unix)]
+fn make_executable(path: &str) -> DriverResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let permissions = std::fs::Permissions::from_mode(0o755);
+    std::fs::set_permissions(path, permissions).map_err(|err| DriverError::Io {
+        path: path.to_string(),
+        err,
+    })
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &str) -> DriverResult<()> {
+    Ok(())
 }
@@ -0,0 +1,207 @@
+use crate::err::driver_error::{DriverError, DriverResult};
+use backend::ir::RelocModel;
+
+/// 编译器要执行的动作，互斥，后出现的覆盖前面的
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Action {
+    /// 只检查代码；指定 `-o` 时用内置的静态链接器和启动代码生成可执行文件，不依赖外部工具链；
+    /// 加上 `-shared` 时生成共享库
+    #[default]
+    Compile,
+    /// `-ast-dump` 打印带类型的 AST
+    AstDump,
+    /// `-emit-ast-dot` 输出 Graphviz 格式的 AST
+    AstDot,
+    /// `-emit-c` 从 AST 还原 C 代码
+    EmitC,
+    /// `-ast-json` 输出 JSON 格式的 AST
+    AstJson,
+    /// `-emit-ir` 输出 IR
+    EmitIr,
+    /// `--run` 用 IR 解释器执行程序，退出码为程序的退出码
+    Run,
+    /// `-S` 输出目标机器的汇编，wasm32 为 `.wat`
+    EmitAsm,
+    /// `-c` 输出 ELF 可重定位目标文件，wasm32 为 `.wasm` 模块
+    EmitObj,
+    /// `-emit-llvm` 输出 LLVM IR 文本（`.ll`），交给 LLVM 的工具继续编译
+    EmitLlvm,
+    /// `-E` 只预处理，输出到标准输出或 `-o`；加上 `-dM` 时输出预处理结束时定义的所有宏
+    Preprocess,
+}
+
+/// `-std` 指定的 C 标准，GNU 方言和对应的 ISO 标准相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CStd {
+    C89,
+    C99,
+    C11,
+    #[default]
+    C17,
+    C23,
+}
+
+impl CStd {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "c89" | "c90" | "gnu89" | "gnu90" | "iso9899:1990" => CStd::C89,
+            "c99" | "gnu99" | "iso9899:1999" => CStd::C99,
+            "c11" | "gnu11" | "iso9899:2011" => CStd::C11,
+            "c17" | "c18" | "gnu17" | "gnu18" | "iso9899:2017" | "iso9899:2018" => CStd::C17,
+            "c23" | "c2x" | "gnu23" | "gnu2x" => CStd::C23,
+            _ => return None,
+        })
+    }
+
+    /// `__STDC_VERSION__` 的值，C89 没有这个宏
+    pub fn version(self) -> Option<u32> {
+        match self {
+            CStd::C89 => None,
+            CStd::C99 => Some(199901),
+            CStd::C11 => Some(201112),
+            CStd::C17 => Some(201710),
+            CStd::C23 => Some(202311),
+        }
+    }
+}
+
+/// 命令行的 `-D` `-U`，按出现的顺序处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroArg {
+    /// `-D name` 定义为 1，`-D name=value` 定义为 `value`
+    Define(String),
+    /// `-U name`
+    Undef(String),
+}
+
+///
+/// 命令行选项
+///
+/// # Members
+/// - `input`: 输入文件
+/// - `action`: 执行的动作
+/// - `ast_dump_filter`: 只输出名字匹配的顶层声明，`-ast-dump` `-emit-ast-dot` 共用
+/// - `ast_dot_decl_refs`: `-emit-ast-dot` 是否输出引用边
+/// - `c_full_parens`: `-emit-c` 是否给所有子表达式加括号
+/// - `target`: `-target` 指定的目标三元组，默认为宿主平台
+/// - `output`: `-o` 指定的输出文件，`-S` `-emit-llvm` 默认输出到标准输出，`-c` 默认为输入文件名换成 `.o`，
+///   没有 `-S` `-c` 时是可执行文件
+/// - `opt_level`: `-O` 指定的优化级别，`-O0` 为默认，`-O` 等同于 `-O1`，`-O1` 包括全局值编号、循环不变量外提和归纳变量化简，`-O2` 及以上加入函数内联和循环展开
+/// - `debug`: 生成的 DWARF 调试信息的版本，`-g` `-gdwarf-5` 为 5，`-gdwarf-4` 为 4，`-g0` 不生成；
+///   目前只有 x86-64 输出调试信息
+/// - `reloc_model`: 重定位模型，`-fPIC` `-fpic` 为 `Pic`，`-fPIE` `-fpie` 为 `Pie`，`-fno-pic` `-fno-pie` 为 `Static`；
+///   目前只有 x86-64 支持位置无关代码
+/// - `shared`: `-shared` 输出共享库，隐含 `-fPIC`
+/// - `include_dirs`: `-I` 指定的头文件目录，按出现的顺序在内置头文件之前查找
+/// - `macros`: `-D` `-U` 定义和取消的宏，在预定义宏之后处理
+/// - `std`: `-std` 指定的 C 标准，决定 `__STDC_VERSION__`
+/// - `dump_macros`: `-dM`，和 `-E` 一起使用
+///
+#[derive(Debug, Clone, Default)]
+pub struct CompilerOptions {
+    pub input: Option<String>,
+    pub action: Action,
+    pub ast_dump_filter: Option<String>,
+    pub ast_dot_decl_refs: bool,
+    pub c_full_parens: bool,
+    pub target: Option<String>,
+    pub output: Option<String>,
+    pub opt_level: u32,
+    pub debug: Option<u16>,
+    pub reloc_model: RelocModel,
+    pub shared: bool,
+    pub include_dirs: Vec<String>,
+    pub macros: Vec<MacroArg>,
+    pub std: CStd,
+    pub dump_macros: bool,
+}
+
+impl CompilerOptions {
+    /// 解析命令行参数，不包含程序名
+    pub fn parse(args: impl IntoIterator<Item = String>) -> DriverResult<Self> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-ast-dump" => options.action = Action::AstDump,
+                "-emit-ast-dot" => options.action = Action::AstDot,
+                "-ast-dot-decl-refs" => options.ast_dot_decl_refs = true,
+                "-emit-c" => options.action = Action::EmitC,
+                "-ast-json" => options.action = Action::AstJson,
+                "-emit-ir" => options.action = Action::EmitIr,
+                "--run" => options.action = Action::Run,
+                "-S" => options.action = Action::EmitAsm,
+                "-c" => options.action = Action::EmitObj,
+                "-emit-llvm" => options.action = Action::EmitLlvm,
+                "-E" => options.action = Action::Preprocess,
+                "-dM" => options.dump_macros = true,
+                "-emit-c-full-parens" => options.c_full_parens = true,
+                "-O" => options.opt_level = 1,
+                "-g" | "-gdwarf-5" => options.debug = Some(5),
+                "-gdwarf-4" => options.debug = Some(4),
+                "-g0" => options.debug = None,
+                "-fPIC" | "-fpic" => options.reloc_model = RelocModel::Pic,
+                "-fPIE" | "-fpie" => options.reloc_model = RelocModel::Pie,
+                "-fno-PIC" | "-fno-pic" | "-fno-PIE" | "-fno-pie" => {
+                    options.reloc_model = RelocModel::Static
+                }
+                "-shared" => options.shared = true,
+                _ if arg.starts_with("-O") && arg[2..].parse::<u32>().is_ok() => {
+                    options.opt_level = arg[2..].parse().unwrap();
+                }
+                "-ast-dump-filter" => {
+                    let value = args.next().ok_or(DriverError::MissingValue(arg))?;
+                    options.ast_dump_filter = Some(value);
+                }
+                _ if arg.starts_with("-ast-dump-filter=") => {
+                    let value = &arg["-ast-dump-filter=".len()..];
+                    options.ast_dump_filter = Some(value.to_owned());
+                }
+                "-o" => {
+                    let value = args.next().ok_or(DriverError::MissingValue(arg))?;
+                    options.output = Some(value);
+                }
+                "-I" => {
+                    let value = args.next().ok_or(DriverError::MissingValue(arg))?;
+                    options.include_dirs.push(value);
+                }
+                _ if arg.starts_with("-I") => options.include_dirs.push(arg[2..].to_owned()),
+                "-D" => {
+                    let value = args.next().ok_or(DriverError::MissingValue(arg))?;
+                    options.macros.push(MacroArg::Define(value));
+                }
+                "-U" => {
+                    let value = args.next().ok_or(DriverError::MissingValue(arg))?;
+                    options.macros.push(MacroArg::Undef(value));
+                }
+                _ if arg.starts_with("-D") => {
+                    let value = arg[2..].to_owned();
+                    options.macros.push(MacroArg::Define(value));
+                }
+                _ if arg.starts_with("-U") => {
+                    let value = arg[2..].to_owned();
+                    options.macros.push(MacroArg::Undef(value));
+                }
+                _ if arg.starts_with("-std=") => {
+                    let value = &arg["-std=".len()..];
+                    options.std = CStd::parse(value).ok_or_else(|| DriverError::InvalidValue {
+                        value: value.to_owned(),
+                        arg: arg.clone(),
+                    })?;
+                }
+                "-target" => {
+                    let value = args.next().ok_or(DriverError::MissingValue(arg))?;
+                    options.target = Some(value);
+                }
+                _ if arg.starts_with("--target=") => {
+                    options.target = Some(arg["--target=".len()..].to_owned());
+                }
+                _ if arg.starts_with('-') => return Err(DriverError::UnknownArgument(arg)),
+                _ => options.input = Some(arg),
+            }
+        }
+
+        Ok(options)
+    }
+}
@@ -1,4 +1,7 @@
 pub const DECL_SPEC: &str = "declaration specifier";
 pub const TYPEDEF_REQUIRE_NAME: &str = "Typedef require a name";
+pub const THREAD_LOCAL_IN_BLOCK: &str =
+    "_Thread_local in block scope requires 'static' or 'extern'";
+pub const NORETURN_NOT_FUNC: &str = "'_Noreturn' can only appear on functions";
 /// 用于 Expect 错误
 pub const EXPECT_IDENT_OR_LB: &str = "identifier or '{'";
\ No newline at end of file
@@ -5,6 +5,8 @@ pub const MAX_UINT:usize = u32::MAX as usize;
 /// 默认的对齐大小
 pub const DEFAULT_ALIGN:usize = 1;
 pub const DEFAULT_SIZE:usize = 1;
+/// 自动变量支持的最大对齐，后端的栈帧只保证 16 字节对齐
+pub const MAX_STACK_ALIGN:usize = 16;
 
 /// 默认类型位宽
 pub const CHAR_BITWIDTH:usize = 8;
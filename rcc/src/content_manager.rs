@@ -1,3 +1,5 @@
+use crate::preprocess::source_map::{SourceLoc, SourceMap};
+use crate::types::span::Span;
 use std::ops::Range;
 use std::str::Chars;
 
@@ -6,36 +8,92 @@ use std::str::Chars;
 /// # Members
 /// - `content`: 代码
 /// - `line_ranges`: 每个行对应的区间，索引+1是行号
+/// - `source_map`: 代码是预处理的输出时，到源文件位置的映射
 ///
 pub struct ContentManager {
     content: String,
     line_ranges: Vec<(usize, usize)>,
+    source_map: Option<SourceMap>,
 }
 
 impl ContentManager {
     pub fn new(content: String) -> ContentManager {
         let mut line_ranges = Vec::new();
         let mut beg = 0;
-        for line in content.lines() {
-            let end = beg + line.len(); // 计算行结束偏移
+        for line in content.split_inclusive('\n') {
+            let end = beg + line.trim_end_matches(['\n', '\r']).len(); // 计算行结束偏移
             line_ranges.push((beg, end)); // 索引 + 1 就是行号
-            beg = end; // 作为下一行开始偏移
+            beg += line.len(); // 跳过换行符，作为下一行开始偏移
         }
 
         Self {
             content,
             line_ranges,
+            source_map: None,
         }
     }
 
+    /// 预处理的输出和它的源码映射
+    pub fn with_source_map(content: String, source_map: SourceMap) -> ContentManager {
+        let mut manager = Self::new(content);
+        manager.source_map = Some(source_map);
+        manager
+    }
+
     /// [beg, end)
     pub fn str(&self, range: Range<usize>) -> &str {
         &self.content[range]
     }
 
-    pub fn chars(&self, pos: usize) -> Chars {
+    pub fn chars(&self, pos: usize) -> Chars<'_> {
         self.content[pos..].chars()
     }
 
+    /// 字节偏移转换为 (行, 列)，均从 1 开始，列按字节计算；有源码映射时是源文件中的行列
+    pub fn line_col(&self, pos: usize) -> (usize, usize) {
+        let loc = self.location(pos);
+        (loc.line, loc.col)
+    }
+
+    /// 字节偏移对应的源文件位置；没有源码映射时文件为 0，行列是代码本身的
+    pub fn location(&self, pos: usize) -> SourceLoc {
+        if let Some(loc) = self.source_map.as_ref().and_then(|x| x.lookup(pos)) {
+            return loc;
+        }
+        let idx = self
+            .line_ranges
+            .partition_point(|(beg, _)| *beg <= pos)
+            .saturating_sub(1);
+        let beg = self.line_ranges.get(idx).map(|(beg, _)| *beg).unwrap_or(0);
+        SourceLoc {
+            file: 0,
+            line: idx + 1,
+            col: pos - beg + 1,
+        }
+    }
+
+    /// `span` 的结束位置（不包含），由最后一个字节的位置得到，宏展开的结果不会对应到下一个记号
+    pub fn end_location(&self, span: Span) -> SourceLoc {
+        if span.end == span.start {
+            return self.location(span.end);
+        }
+        let loc = self.location(span.end - 1);
+        SourceLoc {
+            col: loc.col + 1,
+            ..loc
+        }
+    }
+
+    /// 源码映射中的文件，`location` 中的文件是它的下标
+    pub fn files(&self) -> &[String] {
+        self.source_map.as_ref().map_or(&[], |x| &x.files)
+    }
+
+    /// `location` 中的文件名，没有源码映射时为 None
+    pub fn file_name(&self, file: usize) -> Option<&str> {
+        let map = self.source_map.as_ref()?;
+        map.files.get(file).map(String::as_str)
+    }
+
 }
 
@@ -1,5 +1,8 @@
+pub mod driver_error;
 pub mod global_err;
 pub mod lex_error;
+pub mod lower_error;
 pub mod parser_error;
+pub mod preprocess_error;
 pub mod scope_error;
 pub mod type_error;
@@ -0,0 +1,38 @@
+use crate::err::preprocess_error::PreprocessError;
+use backend::err::codegen_error::CodegenError;
+use backend::err::interp_error::InterpError;
+use backend::err::ir_error::IrError;
+use backend::err::link_error::LinkError;
+use thiserror::Error;
+
+pub type DriverResult<T> = Result<T, DriverError>;
+
+/// 命令行 / 驱动层面的错误
+#[derive(Debug, Error)]
+pub enum DriverError {
+    #[error("unknown argument: '{0}'")]
+    UnknownArgument(String),
+    #[error("argument to '{0}' is missing (expected 1 value)")]
+    MissingValue(String),
+    #[error("invalid value '{value}' in '{arg}'")]
+    InvalidValue { value: String, arg: String },
+    #[error("no input files")]
+    NoInput,
+    #[error("cannot open '{path}': {err}")]
+    Io {
+        path: String,
+        err: std::io::Error,
+    },
+    #[error("{0}")]
+    Preprocess(#[from] PreprocessError),
+    #[error("{0} error(s) generated")]
+    CompileFailed(usize),
+    #[error("runtime error: {0}")]
+    Runtime(#[from] InterpError),
+    #[error("{0}")]
+    Ir(#[from] IrError),
+    #[error("{0}")]
+    Codegen(#[from] CodegenError),
+    #[error("{0}")]
+    Link(#[from] LinkError),
+}
@@ -0,0 +1,84 @@
+use crate::err::parser_error::ParserError;
+use crate::types::span::Span;
+use backend::err::ir_error::IrError;
+use thiserror::Error;
+
+pub type LowerResult<T> = Result<T, LowerError>;
+
+/// AST 转换为 IR 时的错误，sema 没有检查到的问题在这里报告
+#[derive(Debug, Error)]
+pub enum LowerError {
+    #[error("{what} is not supported")]
+    Unsupported { what: String, span: Span },
+    #[error("use of undeclared identifier '{name}'")]
+    Unresolved { name: String, span: Span },
+    #[error("use of undeclared label '{name}'")]
+    UndefinedLabel { name: String, span: Span },
+    #[error("redefinition of label '{name}'")]
+    DuplicateLabel { name: String, span: Span },
+    #[error("'break' statement not in loop or switch statement")]
+    BreakOutside { span: Span },
+    #[error("'continue' statement not in loop statement")]
+    ContinueOutside { span: Span },
+    #[error("'{stmt}' statement not in switch statement")]
+    CaseOutside { stmt: &'static str, span: Span },
+    #[error("duplicate case value '{value}'")]
+    DuplicateCase { value: i128, span: Span },
+    #[error("multiple default labels in one switch")]
+    DuplicateDefault { span: Span },
+    #[error("expression is not an integer constant expression")]
+    NotIntConstant { span: Span },
+    #[error("initializer element is not a compile-time constant")]
+    NotConstant { span: Span },
+    #[error("excess elements in initializer")]
+    ExcessInit { span: Span },
+    #[error("variable has incomplete type")]
+    IncompleteType { span: Span },
+    #[error("no member named '{field}'")]
+    NoMember { field: String, span: Span },
+    #[error("'va_start' used in function with fixed parameters")]
+    VaStartOutside { span: Span },
+    #[error("{}", .0.error_kind)]
+    Sema(ParserError),
+    #[error("generated invalid IR: {0}")]
+    Verify(#[from] IrError),
+}
+
+/// sema 已经检查过的错误，例如初始值
+impl From<ParserError> for LowerError {
+    fn from(value: ParserError) -> Self {
+        Self::Sema(value)
+    }
+}
+
+impl LowerError {
+    pub fn unsupported(what: impl Into<String>, span: Span) -> Self {
+        Self::Unsupported {
+            what: what.into(),
+            span,
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        use LowerError::*;
+        match self {
+            Unsupported { span, .. }
+            | Unresolved { span, .. }
+            | UndefinedLabel { span, .. }
+            | DuplicateLabel { span, .. }
+            | BreakOutside { span }
+            | ContinueOutside { span }
+            | CaseOutside { span, .. }
+            | DuplicateCase { span, .. }
+            | DuplicateDefault { span }
+            | NotIntConstant { span }
+            | NotConstant { span }
+            | ExcessInit { span }
+            | IncompleteType { span }
+            | VaStartOutside { span }
+            | NoMember { span, .. } => Some(*span),
+            Sema(err) => Some(err.span),
+            Verify(_) => None,
+        }
+    }
+}
@@ -68,6 +68,8 @@ pub enum ErrorKind {
     ConflictingType { prev: DeclKey, name: &'static str },
     #[error("{storage} '{name}' is initialized")]
     IllegalInit { storage: String, name: &'static str },
+    #[error("Invalid argument to '{builtin}': {msg}")]
+    BuiltinArg { builtin: &'static str, msg: String },
     #[error("{msg}")]
     WarningMsg { msg: String },
     #[error("{msg}")]
@@ -87,8 +89,8 @@ impl ErrorLevel {
         use ErrorLevel::*;
         match kind {
             ExpectButFound { .. }
-            | NonSubscripted { .. }
-            | UnCallable { .. }
+            | NonSubscripted
+            | UnCallable
             | Expect { .. }
             | NotAssignable { .. }
             | TypeSpecifierMissing
@@ -107,6 +109,7 @@ impl ErrorLevel {
             | DeclNotMatch { .. }
             | ConflictingType { .. }
             | IllegalInit { .. }
+            | BuiltinArg { .. }
             | UndefinedLabel { .. }
             | RedefinitionLabel { .. } => Error,
             Duplicate { .. } | WarningMsg { .. } => Warning,
@@ -244,6 +247,11 @@ impl ParserError {
         Self::new(kind, span)
     }
 
+    pub fn builtin_arg(builtin: &'static str, msg: String, span: Span) -> Self {
+        let kind = ErrorKind::BuiltinArg { builtin, msg };
+        Self::new(kind, span)
+    }
+
     pub fn expect(msg: &str, span: Span) -> Self {
         let kind = ErrorKind::Expect { expect: msg.to_owned() };
         Self::new(kind, span)
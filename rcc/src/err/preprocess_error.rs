@@ -0,0 +1,59 @@
+use thiserror::Error;
+
+pub type PreprocessResult<T> = Result<T, PreprocessError>;
+
+///
+/// 预处理错误，带出错的文件和行号
+///
+/// # Members
+/// - `file`: 文件路径，内置头文件为 `<rcc>/include/xxx.h`
+/// - `line`: 行号，从 1 开始
+/// - `kind`: 错误种类
+///
+#[derive(Debug, Error)]
+#[error("{file}:{line}: {kind}")]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: usize,
+    pub kind: PreprocessErrorKind,
+}
+
+#[derive(Debug, Error)]
+pub enum PreprocessErrorKind {
+    #[error("'{0}' file not found")]
+    IncludeNotFound(String),
+    #[error("#include nested too deeply")]
+    IncludeDepth,
+    #[error("expected \"FILENAME\" or <FILENAME>")]
+    IncludeName,
+    #[error("invalid preprocessing directive '#{0}'")]
+    UnknownDirective(String),
+    #[error("#{0} without #if")]
+    UnmatchedDirective(&'static str),
+    #[error("#{0} after #else")]
+    AfterElse(&'static str),
+    #[error("unterminated conditional directive")]
+    UnterminatedConditional,
+    #[error("macro name must be an identifier")]
+    MacroName,
+    #[error("invalid macro definition: {0}")]
+    InvalidMacro(String),
+    #[error("unterminated argument list invoking macro '{0}'")]
+    UnterminatedArgs(String),
+    #[error("macro '{name}' requires {expected} arguments, but {found} given")]
+    ArgCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("invalid expression in preprocessor directive: {0}")]
+    InvalidExpr(String),
+    #[error("#error {0}")]
+    ErrorDirective(String),
+    #[error("#line directive requires a simple digit sequence")]
+    LineNumber,
+    #[error("invalid filename for #line directive")]
+    LineFile,
+    #[error("extra tokens at end of #{0} directive")]
+    ExtraTokens(&'static str),
+}
@@ -4,4 +4,6 @@ use thiserror::Error;
 pub enum TypeError {
     #[error("Restrict requires a pointer or reference, ('{invalid}' is invalid)")]
     RestrictError{ invalid: String },
+    #[error("Pointer to function type may not be 'restrict' qualified")]
+    RestrictFunction,
 }
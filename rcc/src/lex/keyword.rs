@@ -40,6 +40,13 @@ pub static KEYWORDS: phf::Map<&'static str, Keyword> = phf_map! {
     "_Bool" => Bool,
     "_Complex" => Complex,
     "_Imaginary" => Imaginary,
+    "__attribute__" => Attribute,
+    "_Thread_local" => ThreadLocal,
+    "__thread" => ThreadLocal,
+    "_Alignas" => Alignas,
+    "_Alignof" => Alignof,
+    "__alignof__" => Alignof,
+    "_Noreturn" => Noreturn,
 
 
 
@@ -62,8 +62,7 @@ impl Lex {
 
     /// 取出patten
     fn get_patten(&self) -> &str {
-        let patten = self.content_manager.str(self.last_pos..self.curr_pos);
-        patten
+        self.content_manager.str(self.last_pos..self.curr_pos)
     }
 
     fn clear_patten(&mut self) {
@@ -103,7 +102,7 @@ impl Lex {
                 continue
             } else if chr.is_ascii_digit() || (chr == '.' && self.peek_next_is_digit()) {
                 self.maybe_number_constant()?
-            } else if is_xid_start(chr) {
+            } else if is_xid_start(chr) || chr == '_' {
                 self.maybe_keyword_or_ident()?
             } else if chr == '"' || chr == '\'' {
                 self.maybe_string_or_char()?
@@ -181,7 +180,7 @@ impl Lex {
             // 检测浮点标志
             if chr == '.' || chr == 'e' || chr == 'E' {
                 return Ok(false); // 转为浮点
-            } else if !chr.is_digit(16) {
+            } else if !chr.is_ascii_hexdigit() {
                 break;
             }
 
@@ -241,7 +240,7 @@ impl Lex {
         use FloatSuffix::*;
         let beg = self.curr_pos;
         let chr = match self.peek() {
-            Some(x) if x.is_ascii_digit() => x, // 是后缀字符
+            Some(x) if is_xid_continue(x) => x, // 是后缀字符
             Some(_) | None => return Ok(None), // 不是后缀字符
         };
 
@@ -263,7 +262,7 @@ impl Lex {
         };
 
         // 如果后缀是非后缀字符，继续匹配。
-        if self.peek().map(|x| is_xid_continue(x)).unwrap_or(false) {
+        if self.peek().map(is_xid_continue).unwrap_or(false) {
             self.skip_word();
             let end = self.curr_pos;
             let content = self.content_manager.str(beg..end).to_owned();
@@ -404,9 +403,7 @@ impl Lex {
             };
         }
 
-        self.curr_pos = pos;
-
-        let kind = match operator::STATES[last_state].clone() {
+        let kind = match operator::STATES[last_state] {
             None => return Err(LexError::UnknownSymbol {pos: self.curr_pos, symbol: self.peek().unwrap()}),
             Some(x) => x,
         };
@@ -503,7 +500,7 @@ pub fn make_char(patten: &str) -> TokenKind {
 /// # Returns
 /// 解析后的Token
 /// 
-pub fn run_lexer<'a>(mut lex: Lex, error_rx: mpsc::Sender<GlobalError>) -> Vec<Token> {
+pub fn run_lexer(mut lex: Lex, error_rx: mpsc::Sender<GlobalError>) -> Vec<Token> {
     let mut tokens = Vec::new();
     loop {
         let tok = match lex.next_token() {
@@ -37,9 +37,9 @@ static ROW_ID: [usize; 48] = [
 
 pub static STATES: [Option<TokenKind>; 48] = [
     Some(Ne),
-    Some(Amp),
+    Some(Bang),
     Some(Percent),
-    Some(Pipe),
+    Some(Amp),
     Some(LParen),
     Some(RParen),
     Some(Star),
@@ -58,7 +58,7 @@ pub static STATES: [Option<TokenKind>; 48] = [
     Some(RBracket),
     Some(Caret),
     Some(LBrace),
-    Some(Caret),
+    Some(Pipe),
     Some(RBrace),
     Some(Tilde),
     Some(PipeEq),
@@ -94,7 +94,7 @@ pub fn find_next(state_id: usize, chr: char) -> Option<usize> {
 
     let idx = base + class_id;
 
-    if idx > CHECK.len() {
+    if idx >= CHECK.len() {
         return None;
     }
 
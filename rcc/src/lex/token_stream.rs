@@ -7,27 +7,19 @@ use crate::types::span::Span;
 /// # Members
 /// - `pos`: 当前指针位置，可以保证永远不越界
 /// - `tokens`: lexer输出的token数组
-/// - `last_pos`: 上次计算span的位置
-/// - `mark_pos`: 保存回溯位置的栈
 /// 
 pub struct TokenStream {
     pos: usize,
     tokens: Vec<Token>,
-    last_pos: usize,
-    mark_pos: Vec<usize>,
 }
 
 impl TokenStream {
     
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self {
-            pos: 0,
-            last_pos: 0,
-            tokens,
-            mark_pos: Vec::new(),
-        }
+        Self { pos: 0, tokens }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Token {
         let token = self.tokens[self.pos];
         if self.pos < self.tokens.len() - 1 { // 非Eof移动 
@@ -6,12 +6,18 @@ use std::fmt::{Debug, Display, Formatter};
 thread_local! {
     static SYMBOL_INTERNER: RefCell<Interner> = RefCell::new(Interner::new());
 }
-///
+
 pub struct Interner {
     names: Vec<&'static str>,
     indices: FxHashMap<&'static str, Symbol>,
 }
 
+impl Default for Interner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Interner {
     pub fn new() -> Self {
         Self {
@@ -162,9 +168,14 @@ pub enum Keyword {
     Void,
     Volatile,
     While,
-    Bool,      // _Bool
-    Complex,   // _Complex
-    Imaginary, // _Imaginary
+    Bool,        // _Bool
+    Complex,     // _Complex
+    Imaginary,   // _Imaginary
+    Attribute,   // __attribute__
+    ThreadLocal, // _Thread_local __thread
+    Alignas,     // _Alignas
+    Alignof,     // _Alignof __alignof__
+    Noreturn,    // _Noreturn
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, EnumAsInner)]
@@ -299,6 +310,11 @@ impl Display for Keyword {
             Keyword::Bool => "_Bool",
             Keyword::Complex => "_Complex",
             Keyword::Imaginary => "_Imaginary",
+            Keyword::Attribute => "__attribute__",
+            Keyword::ThreadLocal => "_Thread_local",
+            Keyword::Alignas => "_Alignas",
+            Keyword::Alignof => "_Alignof",
+            Keyword::Noreturn => "_Noreturn",
         };
         write!(f, "{}", msg)
     }
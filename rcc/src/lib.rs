@@ -1,7 +1,9 @@
 pub mod lex;
+pub mod preprocess;
 pub mod err;
 pub mod util;
 pub mod parser;
+pub mod lower;
 pub mod compiler;
 pub mod types;
 pub mod content_manager;
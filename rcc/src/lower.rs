@@ -0,0 +1,22 @@
+//!
+//! AST --> IR
+//!
+//! - 局部变量都放在入口块的 `alloca` 中，读写都是 `load` `store`，由后续的 mem2reg 提升为 SSA
+//! - 表达式按 `ValueType` 分为左值和右值，左值得到地址，右值得到值；
+//!   struct / union / 数组的右值是对象的地址
+//! - sema 没有插入隐式类型转换，这里按 C 的规则（整数提升，usual arithmetic conversion，
+//!   赋值转换，变参的默认提升）自己完成
+//! - struct / union 参数按 `byval` 传地址，返回值通过 `sret` 参数返回
+//! - 开启调试信息时给指令记录源码位置，并把类型、变量、函数转换为 `DebugInfo`
+//!
+
+pub mod lower_builtin;
+pub mod lower_core;
+pub mod lower_debug;
+pub mod lower_expr;
+pub mod lower_func;
+pub mod lower_init;
+pub mod lower_stmt;
+pub mod lower_ty;
+
+pub use lower_core::{lower_unit, lower_unit_debug};
@@ -0,0 +1,166 @@
+use crate::err::lower_error::{LowerError, LowerResult};
+use crate::lower::lower_func::FuncLower;
+use crate::lower::lower_ty::{TyClass, classify, intptr_type};
+use crate::parser::ast::ExprKey;
+use crate::parser::ast::exprs::{Builtin, BuiltinArg};
+use crate::parser::ast::types::IntegerSize;
+use crate::parser::sema::expr::const_eval::{eval_const, eval_int};
+use backend::ir::{AbiParam, BinaryOp, Function, Linkage, Signature, Type, Value};
+use backend::target::Arch;
+
+fn arg(args: &[BuiltinArg], index: usize) -> ExprKey {
+    *args[index]
+        .as_expr()
+        .expect("parser guarantees an expression argument")
+}
+
+impl FuncLower<'_, '_> {
+    /// 内建函数调用，能在编译期求值的直接使用常量
+    pub(crate) fn builtin(
+        &mut self,
+        key: ExprKey,
+        builtin: Builtin,
+        args: &[BuiltinArg],
+    ) -> LowerResult<Value> {
+        use Builtin::*;
+        let expr = self.ctx.get_expr(key);
+        let (ty, span) = (expr.ty, expr.span);
+        if let Some(value) = eval_const(self.ctx, key) {
+            return Ok(self.const_value(value, ty));
+        }
+
+        let void = Value::Undef(Type::Void);
+        match builtin {
+            VaStart => {
+                if !self.func.sig.variadic {
+                    return Err(LowerError::VaStartOutside { span });
+                }
+                let list = self.va_list(arg(args, 0))?;
+                self.ins().va_start(list);
+                Ok(void)
+            }
+            VaArg => {
+                let list = self.va_list(arg(args, 0))?;
+                match classify(self.ctx, ty) {
+                    TyClass::Scalar(t) => Ok(self.ins().va_arg(list, t)),
+                    _ => Err(LowerError::unsupported("va_arg of struct or union", span)),
+                }
+            }
+            VaEnd => {
+                let list = self.va_list(arg(args, 0))?;
+                self.ins().va_end(list);
+                Ok(void)
+            }
+            VaCopy => {
+                let dst = self.va_list(arg(args, 0))?;
+                let src = self.va_list(arg(args, 1))?;
+                self.ins().va_copy(dst, src);
+                Ok(void)
+            }
+            Expect => {
+                let value = self.rvalue_as(arg(args, 0), ty)?;
+                self.effect(arg(args, 1))?;
+                Ok(value)
+            }
+            Unreachable => {
+                self.ins().unreachable();
+                Ok(void)
+            }
+            Popcount(size) | Clz(size) | Ctz(size) => {
+                let arg_ty = self.ctx.type_ctx.get_int_type(size, false);
+                let value = self.rvalue_as(arg(args, 0), arg_ty)?;
+                let t = classify(self.ctx, arg_ty);
+                let TyClass::Scalar(t) = t else {
+                    unreachable!("integer is scalar")
+                };
+                let value = match builtin {
+                    Clz(_) => self.clz(value, t),
+                    Ctz(_) => self.ctz(value, t),
+                    _ => self.popcount(value, t),
+                };
+                Ok(self.convert(value, arg_ty, ty))
+            }
+            Memcpy => {
+                let dst = self.pointer(arg(args, 0))?;
+                let src = self.pointer(arg(args, 1))?;
+                match eval_int(self.ctx, arg(args, 2)) {
+                    Some(size) if size >= 0 => self.ins().memcpy(dst, src, size as u64, 1),
+                    _ => {
+                        let size_ty = self.ctx.type_ctx.get_int_type(IntegerSize::Long, false);
+                        let size = self.rvalue_as(arg(args, 2), size_ty)?;
+                        let (sig, callee) = self.memcpy_func();
+                        self.ins().call(sig, callee, vec![dst, src, size]);
+                    }
+                }
+                Ok(dst)
+            }
+            Offsetof | TypesCompatibleP | ConstantP => unreachable!("always folded by sema"),
+        }
+    }
+
+    /// va_list 对象的地址；x86-64 的 va_list 是数组，作为参数时已经调整为指针，两种情况的右值都是地址
+    fn va_list(&mut self, key: ExprKey) -> LowerResult<Value> {
+        match self.ctx.target.arch {
+            Arch::X86_64 => self.rvalue(key),
+            Arch::Riscv64 | Arch::Wasm32 => Ok(self.lvalue(key)?.addr),
+        }
+    }
+
+    /// 大小不是常量的 `__builtin_memcpy` 调用 C 库的 `memcpy`
+    fn memcpy_func(&mut self) -> (Signature, Value) {
+        let params = vec![
+            AbiParam::new(Type::Ptr),
+            AbiParam::new(Type::Ptr),
+            AbiParam::new(intptr_type(self.ctx)),
+        ];
+        let sig = Signature::new(params, Type::Ptr, false);
+        let module = &mut self.m.module;
+        let id = match module.func_by_name("memcpy") {
+            Some(id) => id,
+            None => module.add_func(Function::new("memcpy", sig.clone(), Linkage::External)),
+        };
+        (sig, Value::Func(id))
+    }
+
+    /// 并行计数 1 的个数
+    fn popcount(&mut self, x: Value, t: Type) -> Value {
+        use BinaryOp::*;
+        let c = |x: u64| Value::int(t, x as i64);
+        let s = self.ins().binary(LShr, x, c(1));
+        let s = self.ins().binary(And, s, c(0x5555_5555_5555_5555));
+        let x = self.ins().binary(Sub, x, s);
+        let a = self.ins().binary(And, x, c(0x3333_3333_3333_3333));
+        let b = self.ins().binary(LShr, x, c(2));
+        let b = self.ins().binary(And, b, c(0x3333_3333_3333_3333));
+        let x = self.ins().binary(Add, a, b);
+        let s = self.ins().binary(LShr, x, c(4));
+        let x = self.ins().binary(Add, x, s);
+        let x = self.ins().binary(And, x, c(0x0f0f_0f0f_0f0f_0f0f));
+        // 每个字节的计数累加到最高字节
+        let x = self.ins().binary(Mul, x, c(0x0101_0101_0101_0101));
+        self.ins().binary(LShr, x, c(t.bits(8) as u64 - 8))
+    }
+
+    /// 最高位的 1 向右扩散后，0 的个数就是前导 0 的个数
+    fn clz(&mut self, x: Value, t: Type) -> Value {
+        use BinaryOp::*;
+        let mut x = x;
+        let mut shift = 1;
+        while shift < t.bits(8) {
+            let s = self.ins().binary(LShr, x, Value::int(t, shift as i64));
+            x = self.ins().binary(Or, x, s);
+            shift *= 2;
+        }
+        let x = self.ins().binary(Xor, x, Value::int(t, -1));
+        self.popcount(x, t)
+    }
+
+    /// `~x & (x - 1)` 只保留末尾的 0 并变为 1
+    fn ctz(&mut self, x: Value, t: Type) -> Value {
+        use BinaryOp::*;
+        let m = self.ins().binary(Sub, x, Value::int(t, 1));
+        let n = self.ins().binary(Xor, x, Value::int(t, -1));
+        let x = self.ins().binary(And, n, m);
+        self.popcount(x, t)
+    }
+}
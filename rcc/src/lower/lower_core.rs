@@ -0,0 +1,439 @@
+use crate::err::lower_error::{LowerError, LowerResult};
+use crate::lower::lower_debug::DebugLower;
+use crate::lower::lower_func::FuncLower;
+use crate::lower::lower_init::StaticImage;
+use crate::lower::lower_ty::{check_value, decl_size_align, func_parts, signature, size_align};
+use crate::parser::ast::decls::decl::{Decl, DeclKind};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::ExprKind;
+use crate::parser::ast::func::{ExternalDecl, FuncDef, TranslationUnit};
+use crate::parser::ast::types::TypeKind;
+use crate::parser::ast::{DeclKey, ExprKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::decl_spec::{FuncSpecKind, StorageSpecKind, VisibilityKind};
+use crate::parser::sema::decl::initializer::flatten_init;
+use backend::ir::debug::{DiGlobal, DiSubprogram};
+use backend::ir::verifier::verify_module;
+use backend::ir::{Function, Global, GlobalId, InlineAttr, Linkage, Module, Value, Visibility};
+use rustc_hash::FxHashMap;
+
+/// 把翻译单元转换为 IR 模块，返回的模块已经通过校验
+pub fn lower_unit(ctx: &CompCtx, unit: &TranslationUnit) -> LowerResult<Module> {
+    lower(ModuleLower::new(ctx), unit)
+}
+
+/// 同 `lower_unit`，同时生成调试信息
+pub fn lower_unit_debug(
+    ctx: &CompCtx,
+    unit: &TranslationUnit,
+    debug: DebugLower,
+) -> LowerResult<Module> {
+    let mut lower = ModuleLower::new(ctx);
+    lower.debug = Some(debug);
+    self::lower(lower, unit)
+}
+
+fn lower(mut lower: ModuleLower, unit: &TranslationUnit) -> LowerResult<Module> {
+    for ext_decl in unit {
+        match ext_decl {
+            ExternalDecl::Declaration(group) => {
+                for decl in group.decls.iter() {
+                    lower.global_decl(*decl)?;
+                }
+            }
+            ExternalDecl::FunctionDefinition(def) => lower.func_def(def)?,
+        }
+    }
+    verify_module(&lower.module)?;
+    lower.module.debug = lower.debug.map(|x| x.info);
+    Ok(lower.module)
+}
+
+///
+/// # Members
+/// - `module`: 生成的模块
+/// - `globals`: 文件作用域的变量和块作用域的 `extern` 变量，同名的声明对应同一个全局变量
+/// - `statics`: 块作用域的 `static` 变量
+/// - `strings`: 字符串字面量，内容相同的共用一个全局变量
+/// - `counter`: 生成匿名全局变量的名字
+/// - `debug`: 调试信息，为 None 时不生成
+///
+pub struct ModuleLower<'a> {
+    pub ctx: &'a CompCtx,
+    pub module: Module,
+    pub debug: Option<DebugLower<'a>>,
+    globals: FxHashMap<DeclKey, GlobalId>,
+    statics: FxHashMap<DeclKey, GlobalId>,
+    strings: FxHashMap<Vec<u8>, GlobalId>,
+    literals: FxHashMap<ExprKey, GlobalId>,
+    counter: usize,
+}
+
+fn linkage(decl: &Decl) -> Linkage {
+    match &decl.storage {
+        Some(x) if x.kind == StorageSpecKind::Static => Linkage::Internal,
+        _ => Linkage::External,
+    }
+}
+
+fn inline_attr(decl: &Decl) -> InlineAttr {
+    match decl.func_spec.as_ref().map(|x| x.kind) {
+        Some(FuncSpecKind::Inline) => InlineAttr::Hint,
+        Some(FuncSpecKind::AlwaysInline) => InlineAttr::Always,
+        Some(FuncSpecKind::NoInline) => InlineAttr::Never,
+        // `_Noreturn` 放在 `noreturn` 中，不会出现在这里
+        Some(FuncSpecKind::Noreturn) | None => InlineAttr::None,
+    }
+}
+
+/// `__attribute__((visibility(...)))`，没有写的为 None
+fn visibility(decl: &Decl) -> Option<Visibility> {
+    decl.visibility.as_ref().map(|x| match x.kind {
+        VisibilityKind::Default => Visibility::Default,
+        VisibilityKind::Hidden => Visibility::Hidden,
+        VisibilityKind::Protected => Visibility::Protected,
+    })
+}
+
+fn decl_name(decl: &Decl) -> &'static str {
+    decl.name.as_ref().map(|x| x.symbol.get()).unwrap_or("")
+}
+
+impl<'a> ModuleLower<'a> {
+    pub fn new(ctx: &'a CompCtx) -> Self {
+        let mut module = Module::new();
+        module.ptr_bytes = ctx.target.ptr_bytes;
+        Self {
+            ctx,
+            module,
+            debug: None,
+            globals: FxHashMap::default(),
+            statics: FxHashMap::default(),
+            strings: FxHashMap::default(),
+            literals: FxHashMap::default(),
+            counter: 0,
+        }
+    }
+
+    /// 文件作用域的声明，typedef、record、enum 不生成代码
+    fn global_decl(&mut self, key: DeclKey) -> LowerResult<()> {
+        let decl = self.ctx.get_decl(key);
+        match &decl.kind {
+            DeclKind::VarDecl { .. } => {
+                self.declare_global(key)?;
+            }
+            DeclKind::VarDef { init } => self.define_global(key, init.as_ref())?,
+            DeclKind::FuncDecl { .. } => {
+                self.declare_func(key);
+            }
+            DeclKind::TypeDef => self.debug_typedef(key),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 调试信息中的 typedef
+    pub fn debug_typedef(&mut self, key: DeclKey) {
+        if let Some(debug) = &mut self.debug {
+            debug.typedef(self.ctx, key);
+        }
+    }
+
+    /// 调试信息中的全局变量，同一个变量只记录一次
+    fn debug_global(&mut self, id: GlobalId, key: DeclKey) {
+        let Some(debug) = &mut self.debug else {
+            return;
+        };
+        if debug.info.globals.iter().any(|x| x.global == id) {
+            return;
+        }
+        let decl = self.ctx.get_decl(key);
+        let Some(ty) = debug.ty(self.ctx, decl.ty) else {
+            return;
+        };
+        let loc = debug.loc(decl.span);
+        let global = DiGlobal {
+            name: decl_name(decl).to_string(),
+            ty,
+            file: loc.file,
+            line: loc.line,
+            global: id,
+            external: self.module.globals[id].linkage == Linkage::External,
+        };
+        debug.info.globals.push(global);
+    }
+
+    /// 生成一个没有重名的符号
+    fn unique_name(&mut self, prefix: &str) -> String {
+        loop {
+            let name = format!("{}.{}", prefix, self.counter);
+            self.counter += 1;
+            if self.module.symbol(&name).is_none() {
+                return name;
+            }
+        }
+    }
+
+    /// 声明全局变量，已经声明过的返回原来的变量；`static`、`_Thread_local` 和可见性属性在任何一个声明上出现都生效
+    pub fn declare_global(&mut self, key: DeclKey) -> LowerResult<GlobalId> {
+        if let Some(id) = self.globals.get(&key) {
+            return Ok(*id);
+        }
+        let decl = self.ctx.get_decl(key);
+        let name = decl_name(decl);
+        let id = match self.module.global_by_name(name) {
+            Some(id) => id,
+            None if self.module.func_by_name(name).is_some() => {
+                return Err(LowerError::unsupported(
+                    format!("variable '{}' with the same name as a function", name),
+                    decl.span,
+                ));
+            }
+            None => {
+                let (size, align) = decl_size_align(self.ctx, decl);
+                self.module.add_global(Global {
+                    name: name.to_string(),
+                    size,
+                    align,
+                    init: None,
+                    linkage: linkage(decl),
+                    visibility: Visibility::Default,
+                    thread_local: false,
+                    constant: false,
+                })
+            }
+        };
+        if linkage(decl) == Linkage::Internal {
+            self.module.globals[id].linkage = Linkage::Internal;
+        }
+        if decl.thread_local.is_some() {
+            self.module.globals[id].thread_local = true;
+        }
+        if let Some(visibility) = visibility(decl) {
+            self.module.globals[id].visibility = visibility;
+        }
+        self.globals.insert(key, id);
+        Ok(id)
+    }
+
+    /// 定义全局变量，没有初始值的是试探性定义，初始化为 0
+    fn define_global(&mut self, key: DeclKey, init: Option<&Initializer>) -> LowerResult<()> {
+        let id = self.declare_global(key)?;
+        let decl = self.ctx.get_decl(key);
+        let is_extern = matches!(&decl.storage, Some(x) if x.kind == StorageSpecKind::Extern);
+        if init.is_none() && (is_extern || self.module.globals[id].init.is_some()) {
+            return Ok(());
+        }
+        self.init_global(id, key, init)
+    }
+
+    /// 生成全局变量的初始值
+    fn init_global(
+        &mut self,
+        id: GlobalId,
+        key: DeclKey,
+        init: Option<&Initializer>,
+    ) -> LowerResult<()> {
+        let decl = self.ctx.get_decl(key);
+        let (size, align) = decl_size_align(self.ctx, decl);
+        let image = match init {
+            Some(init) => {
+                let (elems, _) = flatten_init(self.ctx, decl.ty, init, decl.span)?;
+                let mut image = StaticImage::new(size);
+                for elem in elems.iter() {
+                    if !self.write_static(&mut image, elem)? {
+                        return Err(LowerError::NotConstant {
+                            span: self.ctx.get_expr(elem.expr().unwrap()).span,
+                        });
+                    }
+                }
+                image
+            }
+            None => StaticImage::new(size),
+        };
+        if size == 0
+            && !matches!(
+                self.ctx.type_ctx.get_type(decl.ty).kind,
+                TypeKind::Array { .. }
+            )
+        {
+            return Err(LowerError::IncompleteType { span: decl.span });
+        }
+
+        let global = &mut self.module.globals[id];
+        global.size = size;
+        global.align = align;
+        global.init = Some(image.into_items());
+        global.constant = self.ctx.type_ctx.get_type(decl.ty).qual.is_const;
+        self.debug_global(id, key);
+        Ok(())
+    }
+
+    /// 块作用域的 `static` 变量，名字为 `函数名.变量名`
+    pub fn static_local(&mut self, func: &str, key: DeclKey) -> LowerResult<GlobalId> {
+        if let Some(id) = self.statics.get(&key) {
+            return Ok(*id);
+        }
+        let decl = self.ctx.get_decl(key);
+        let name = format!("{}.{}", func, decl_name(decl));
+        let name = match self.module.symbol(&name) {
+            Some(_) => self.unique_name(&name),
+            None => name,
+        };
+        let (size, align) = decl_size_align(self.ctx, decl);
+        let id = self.module.add_global(Global {
+            name,
+            size,
+            align,
+            init: None,
+            linkage: Linkage::Internal,
+            visibility: Visibility::Default,
+            thread_local: decl.thread_local.is_some(),
+            constant: false,
+        });
+        // 先登记再生成初始值，初始值可以引用变量自身 `static void *p = &p;`
+        self.statics.insert(key, id);
+        let init = decl.kind.as_var_def().and_then(|x| x.as_ref());
+        self.init_global(id, key, init)?;
+        Ok(id)
+    }
+
+    /// 声明函数，已经声明过的返回原来的函数；`static`、内联和可见性属性在任何一个声明上出现都生效
+    pub fn declare_func(&mut self, key: DeclKey) -> Value {
+        let decl = self.ctx.get_decl(key);
+        let name = decl_name(decl);
+        if let Some(id) = self.module.func_by_name(name) {
+            let func = &mut self.module.funcs[id];
+            if linkage(decl) == Linkage::Internal {
+                func.linkage = Linkage::Internal;
+            }
+            if inline_attr(decl) != InlineAttr::None {
+                func.inline = inline_attr(decl);
+            }
+            if let Some(visibility) = visibility(decl) {
+                func.visibility = visibility;
+            }
+            return Value::Func(id);
+        }
+        let mut func = Function::new(name, signature(self.ctx, decl.ty), linkage(decl));
+        func.inline = inline_attr(decl);
+        func.visibility = visibility(decl).unwrap_or_default();
+        Value::Func(self.module.add_func(func))
+    }
+
+    fn func_def(&mut self, def: &FuncDef) -> LowerResult<()> {
+        let Value::Func(id) = self.declare_func(def.decl) else {
+            unreachable!()
+        };
+        let decl = self.ctx.get_decl(def.decl);
+        if let Some((ret_ty, param_tys, _)) = func_parts(self.ctx, decl.ty) {
+            for ty in std::iter::once(&ret_ty).chain(param_tys.iter()) {
+                check_value(self.ctx, *ty, decl.span)?;
+            }
+        }
+        let (params, body) = match &decl.kind {
+            DeclKind::FuncDef { params, body, .. } => (params.as_slice(), body.as_ref()),
+            _ => (&[][..], self.ctx.get_stmt(def.body)),
+        };
+
+        let prev = &self.module.funcs[id];
+        let mut func = Function::new(prev.name.clone(), prev.sig.clone(), prev.linkage);
+        func.inline = prev.inline;
+        func.visibility = prev.visibility;
+        if let Some(debug) = &mut self.debug {
+            let loc = debug.loc(decl.span);
+            func.debug = debug.ty(self.ctx, decl.ty).map(|ty| DiSubprogram {
+                name: decl_name(decl).to_string(),
+                file: loc.file,
+                line: loc.line,
+                ty,
+                external: func.linkage == Linkage::External,
+                vars: Vec::new(),
+            });
+        }
+        let func = FuncLower::new(self, func, decl.ty).lower(params, body)?;
+        self.module.funcs[id] = func;
+        Ok(())
+    }
+
+    /// 全局的变量或函数，局部变量返回 None
+    pub fn symbol_of(&mut self, key: DeclKey) -> Option<Value> {
+        if let Some(id) = self.statics.get(&key).or_else(|| self.globals.get(&key)) {
+            return Some(Value::Global(*id));
+        }
+        match &self.ctx.get_decl(key).kind {
+            DeclKind::FuncDecl { .. } | DeclKind::FuncDef { .. } => Some(self.declare_func(key)),
+            _ => None,
+        }
+    }
+
+    /// 字符串字面量的全局变量，`bytes` 包含结尾的 0
+    pub fn string_literal(&mut self, bytes: Vec<u8>) -> Value {
+        if let Some(id) = self.strings.get(&bytes) {
+            return Value::Global(*id);
+        }
+        let name = self.unique_name(".str");
+        let id = self.module.add_global(Global {
+            name,
+            size: bytes.len() as u64,
+            align: 1,
+            init: Some(StaticImage::from_bytes(bytes.clone()).into_items()),
+            linkage: Linkage::Internal,
+            visibility: Visibility::Default,
+            thread_local: false,
+            constant: true,
+        });
+        self.strings.insert(bytes, id);
+        Value::Global(id)
+    }
+
+    /// 文件作用域的复合字面量，静态存储期的匿名对象，初始值不是常量时返回 None
+    pub fn compound_literal(&mut self, key: ExprKey) -> Option<Value> {
+        if let Some(id) = self.literals.get(&key) {
+            return Some(Value::Global(*id));
+        }
+        let ctx = self.ctx;
+        let expr = ctx.get_expr(key);
+        let ExprKind::CompoundLiteral { ty, init, .. } = &expr.kind else {
+            return None;
+        };
+        let (elems, _) = flatten_init(ctx, *ty, init, expr.span).ok()?;
+        let (size, align) = size_align(ctx, *ty);
+        let mut image = StaticImage::new(size);
+        for elem in elems.iter() {
+            if !self.write_static(&mut image, elem).ok()? {
+                return None;
+            }
+        }
+        let name = self.unique_name(".compoundliteral");
+        let id = self.module.add_global(Global {
+            name,
+            size,
+            align,
+            init: Some(image.into_items()),
+            linkage: Linkage::Internal,
+            visibility: Visibility::Default,
+            thread_local: false,
+            constant: ctx.type_ctx.get_type(*ty).qual.is_const,
+        });
+        self.literals.insert(key, id);
+        Some(Value::Global(id))
+    }
+
+    /// 局部变量初始值的常量部分，先整体复制再写入非常量的部分
+    pub fn init_template(&mut self, image: StaticImage, align: u32) -> Value {
+        let name = self.unique_name(".init");
+        let size = image.size();
+        let id = self.module.add_global(Global {
+            name,
+            size,
+            align,
+            init: Some(image.into_items()),
+            linkage: Linkage::Internal,
+            visibility: Visibility::Default,
+            thread_local: false,
+            constant: true,
+        });
+        Value::Global(id)
+    }
+}
@@ -0,0 +1,207 @@
+use crate::content_manager::ContentManager;
+use crate::parser::ast::common::RecordKind;
+use crate::parser::ast::decls::decl::DeclKind;
+use crate::parser::ast::types::{
+    ArraySize, IntegerSize, RecordID, RecordLayout, TypeKind, TypeLayout,
+};
+use crate::parser::ast::{DeclKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::sema::expr::const_eval::enum_value;
+use crate::types::span::Span;
+use backend::ir::debug::{DebugInfo, DebugLoc, DiEncoding, DiMember, DiType, DiTypeId};
+use rustc_hash::FxHashMap;
+
+///
+/// 生成调试信息：源码位置和 `TypeCtx` 中的类型
+///
+/// # Members
+/// - `content`: 源码，用于把字节偏移转换为行列号
+/// - `info`: 生成的模块调试信息
+/// - `types`: 已经转换的类型
+/// - `records`: 已经转换的 struct / union，在转换成员之前登记，使指向自身的指针可以引用它；
+///   带限定符的 record 是不同的 `TypeKey`，但共用同一个 record
+///
+pub struct DebugLower<'a> {
+    content: &'a ContentManager,
+    pub info: DebugInfo,
+    types: FxHashMap<TypeKey, Option<DiTypeId>>,
+    records: FxHashMap<RecordID, DiTypeId>,
+}
+
+/// 名字，匿名时为 None
+fn decl_name(ctx: &CompCtx, key: DeclKey) -> Option<String> {
+    let decl = ctx.get_decl(key);
+    decl.name.as_ref().map(|x| x.symbol.get().to_string())
+}
+
+/// enum 的成员，`def` 可以是 `EnumDecl` 或 `EnumDef`
+fn enumerators(ctx: &CompCtx, def: DeclKey) -> Vec<(String, i64)> {
+    match &ctx.get_decl(def).kind {
+        DeclKind::EnumDef { enums: Some(enums) } => enums
+            .iter()
+            .map(|x| {
+                let name = decl_name(ctx, *x).unwrap_or_default();
+                (name, enum_value(ctx, *x).unwrap_or(0) as i64)
+            })
+            .collect(),
+        DeclKind::EnumDecl { def: Some(x) } => enumerators(ctx, *x),
+        _ => Vec::new(),
+    }
+}
+
+impl<'a> DebugLower<'a> {
+    /// `version` 是 DWARF 版本；源码映射中主文件之外的文件依次是 2 号之后的文件
+    pub fn new(content: &'a ContentManager, file: &str, dir: &str, version: u16) -> Self {
+        let producer = format!("rcc {}", env!("CARGO_PKG_VERSION"));
+        let mut info = DebugInfo::new(producer, file, dir);
+        info.version = version;
+        info.files = content.files().iter().skip(1).cloned().collect();
+        Self {
+            content,
+            info,
+            types: FxHashMap::default(),
+            records: FxHashMap::default(),
+        }
+    }
+
+    /// `span` 开始处的文件和行列号，文件编号是源码映射中的下标加 1
+    pub fn loc(&self, span: Span) -> DebugLoc {
+        let loc = self.content.location(span.start);
+        DebugLoc {
+            file: loc.file as u32 + 1,
+            line: loc.line as u32,
+            col: loc.col as u32,
+        }
+    }
+
+    /// 转换类型，`void` 和出错的类型为 None
+    pub fn ty(&mut self, ctx: &CompCtx, key: TypeKey) -> Option<DiTypeId> {
+        if let Some(id) = self.types.get(&key) {
+            return *id;
+        }
+        let ty = ctx.type_ctx.get_type(key);
+        let mut id = match &ty.kind {
+            TypeKind::Void | TypeKind::Unknown => None,
+            TypeKind::Integer { is_signed, size } => {
+                let (name, encoding) = match (size, is_signed) {
+                    (IntegerSize::Bool, _) => (size.to_string(), DiEncoding::Boolean),
+                    (IntegerSize::Char, true) => ("char".to_string(), DiEncoding::SignedChar),
+                    (IntegerSize::Char, false) => {
+                        ("unsigned char".to_string(), DiEncoding::UnsignedChar)
+                    }
+                    (_, true) => (size.to_string(), DiEncoding::Signed),
+                    (_, false) => (format!("unsigned {}", size), DiEncoding::Unsigned),
+                };
+                Some(self.info.add_type(DiType::Base {
+                    name,
+                    size: size.sizeof(&ctx.target) as u64,
+                    encoding,
+                }))
+            }
+            TypeKind::Floating { size } => Some(self.info.add_type(DiType::Base {
+                name: size.to_string(),
+                size: size.sizeof(&ctx.target) as u64,
+                encoding: DiEncoding::Float,
+            })),
+            TypeKind::Pointer { elem_ty } => {
+                let pointee = self.ty(ctx, *elem_ty);
+                Some(self.info.add_type(DiType::Pointer {
+                    pointee,
+                    size: ctx.target.ptr_bytes as u64,
+                }))
+            }
+            TypeKind::Array { elem_ty, size } => self.ty(ctx, *elem_ty).map(|elem| {
+                let count = match size {
+                    ArraySize::Static(n) => Some(*n as u64),
+                    ArraySize::Incomplete | ArraySize::VLA => None,
+                };
+                self.info.add_type(DiType::Array { elem, count })
+            }),
+            TypeKind::Function {
+                ret_ty,
+                params,
+                is_variadic,
+                ..
+            } => {
+                let ret = self.ty(ctx, *ret_ty);
+                let params = params.iter().filter_map(|x| self.ty(ctx, *x)).collect();
+                Some(self.info.add_type(DiType::Function {
+                    ret,
+                    params,
+                    is_variadic: *is_variadic,
+                }))
+            }
+            TypeKind::Record { id, .. } if self.records.contains_key(id) => Some(self.records[id]),
+            TypeKind::Record {
+                kind,
+                id: record,
+                def,
+            } => {
+                // 先登记不完整的类型，成员中指向自身的指针引用它
+                let id = self.info.add_type(DiType::Record {
+                    is_union: *kind == RecordKind::Union,
+                    name: def.and_then(|x| decl_name(ctx, x)),
+                    size: None,
+                    members: Vec::new(),
+                });
+                self.records.insert(*record, id);
+                if let Some(layout) = def.and_then(|x| RecordLayout::new(ctx, x)) {
+                    let members = layout
+                        .fields
+                        .iter()
+                        .filter_map(|field| {
+                            Some(DiMember {
+                                name: field.name.map(|x| x.get().to_string()),
+                                ty: self.ty(ctx, field.ty)?,
+                                offset: field.offset as u64,
+                                bit_field: field
+                                    .bit_field
+                                    .map(|x| (x.bit_offset as u32, x.width as u32)),
+                            })
+                        })
+                        .collect();
+                    let DiType::Record {
+                        size, members: x, ..
+                    } = &mut self.info.types[id.0 as usize]
+                    else {
+                        unreachable!()
+                    };
+                    *size = Some(layout.size as u64);
+                    *x = members;
+                }
+                Some(id)
+            }
+            TypeKind::Enum { def, .. } => Some(self.info.add_type(DiType::Enum {
+                name: def.and_then(|x| decl_name(ctx, x)),
+                size: TypeLayout::of(ctx, key).size as u64,
+                enumerators: def.map(|x| enumerators(ctx, x)).unwrap_or_default(),
+            })),
+        };
+
+        // 限定符包在外面，`const volatile int` 为 const -> volatile -> int
+        if ty.qual.is_volatile {
+            id = Some(self.info.add_type(DiType::Volatile(id)));
+        }
+        if ty.qual.is_const {
+            id = Some(self.info.add_type(DiType::Const(id)));
+        }
+        self.types.insert(key, id);
+        id
+    }
+
+    /// typedef 声明
+    pub fn typedef(&mut self, ctx: &CompCtx, key: DeclKey) {
+        let decl = ctx.get_decl(key);
+        let Some(name) = decl_name(ctx, key) else {
+            return;
+        };
+        let ty = self.ty(ctx, decl.ty);
+        let DebugLoc { file, line, .. } = self.loc(decl.span);
+        self.info.add_type(DiType::Typedef {
+            name,
+            ty,
+            file,
+            line,
+        });
+    }
+}
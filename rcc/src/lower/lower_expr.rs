@@ -0,0 +1,964 @@
+use crate::err::lower_error::{LowerError, LowerResult};
+use crate::lex::types::token_kind::LiteralKind;
+use crate::lower::lower_func::FuncLower;
+use crate::lower::lower_ty::*;
+use crate::parser::ast::common::RecordKind;
+use crate::parser::ast::exprs::{AssignOpKind, BinOpKind, ExprKind, MemberAccessKind, UnaryOpKind};
+use crate::parser::ast::types::{BitFieldLayout, IntegerSize, RecordLayout, TypeKind};
+use crate::parser::ast::{DeclKey, ExprKey, TypeKey};
+use crate::parser::common::Ident;
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::sema::expr::const_eval::{ConstValue, eval_const};
+use crate::types::span::Span;
+use crate::util::literal;
+use backend::ir::{BinaryOp, BlockId, CastOp, CmpPred, InstKind, Type, Value};
+use std::cmp::Ordering;
+
+///
+/// 左值
+///
+/// # Members
+/// - `addr`: 对象的地址
+/// - `ty`: 对象的类型
+/// - `bit_field`: 位域在存储单元中的位置，`addr` 是存储单元的地址
+/// - `volatile`: 读写不能被优化
+/// - `tbaa`: 访问的类型标签，经过联合体成员访问得到的左值为 `0`
+///
+#[derive(Debug, Clone, Copy)]
+pub struct LValue {
+    pub addr: Value,
+    pub ty: TypeKey,
+    pub bit_field: Option<BitFieldLayout>,
+    pub volatile: bool,
+    pub tbaa: u32,
+}
+
+impl LValue {
+    pub fn new(ctx: &CompCtx, addr: Value, ty: TypeKey) -> Self {
+        Self {
+            addr,
+            ty,
+            bit_field: None,
+            volatile: ctx.type_ctx.get_type(ty).qual.is_volatile,
+            tbaa: alias_tag(ctx, ty),
+        }
+    }
+
+    /// 实际读写使用的类型标签，位域的存储单元可能和相邻的其他类型的位域重叠
+    fn tbaa(&self) -> u32 {
+        match self.bit_field {
+            Some(_) => 0,
+            None => self.tbaa,
+        }
+    }
+}
+
+/// 低 `width` 位为 1
+fn low_mask(width: usize) -> u64 {
+    match width {
+        64.. => u64::MAX,
+        _ => (1u64 << width) - 1,
+    }
+}
+
+impl FuncLower<'_, '_> {
+    fn expr_ty(&self, key: ExprKey) -> TypeKey {
+        self.ctx.get_expr(key).ty
+    }
+
+    fn span(&self, key: ExprKey) -> Span {
+        self.ctx.get_expr(key).span
+    }
+
+    /// 求值并丢弃结果
+    pub fn effect(&mut self, key: ExprKey) -> LowerResult<()> {
+        self.rvalue(key)?;
+        Ok(())
+    }
+
+    /// 求值并转换为 `ty` 类型
+    pub fn rvalue_as(&mut self, key: ExprKey, ty: TypeKey) -> LowerResult<Value> {
+        check_value(self.ctx, ty, self.span(key))?;
+        let value = self.rvalue(key)?;
+        Ok(self.convert(value, self.expr_ty(key), ty))
+    }
+
+    /// 右值，struct / union / 数组 / 函数得到地址
+    pub fn rvalue(&mut self, key: ExprKey) -> LowerResult<Value> {
+        use ExprKind::*;
+        let expr = self.ctx.get_expr(key);
+        let ty = expr.ty;
+        check_value(self.ctx, ty, expr.span)?;
+
+        match &expr.kind {
+            Literal(LiteralKind::String { .. }) => self.lvalue_load(key),
+            Literal(_) | SizeofExpr { .. } | SizeofType { .. } | AlignofType { .. } => {
+                self.constant(key)
+            }
+            DeclRef {
+                decl: Some(decl), ..
+            } if self.ctx.get_decl(*decl).kind.is_enum_field() => self.constant(key),
+            DeclRef { .. }
+            | ArraySubscript { .. }
+            | MemberAccess { .. }
+            | CompoundLiteral { .. } => self.lvalue_load(key),
+            Unary { op, rhs } => self.unary(key, op.kind, *rhs),
+            Binary { lhs, op, rhs } => self.binary(key, op.kind, *lhs, *rhs),
+            Assign { lhs, op, rhs } => self.assign(op.kind, *lhs, *rhs),
+            Call { base, params } => self.call(key, *base, &params.exprs),
+            Cast { expr, .. } => match classify(self.ctx, ty) {
+                TyClass::Void => {
+                    self.effect(*expr)?;
+                    Ok(Value::Undef(Type::Void))
+                }
+                _ => self.rvalue_as(*expr, ty),
+            },
+            Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => self.ternary(ty, *cond, *then_expr, *else_expr),
+            BuiltinCall { builtin, args } => self.builtin(key, *builtin, args),
+        }
+    }
+
+    fn lvalue_load(&mut self, key: ExprKey) -> LowerResult<Value> {
+        let lvalue = self.lvalue(key)?;
+        Ok(self.load(lvalue))
+    }
+
+    /// 常量表达式，`sizeof` 不支持变长数组
+    fn constant(&mut self, key: ExprKey) -> LowerResult<Value> {
+        let expr = self.ctx.get_expr(key);
+        match eval_const(self.ctx, key) {
+            Some(value) => Ok(self.const_value(value, expr.ty)),
+            None if matches!(expr.kind, ExprKind::Literal(_)) => {
+                Err(LowerError::unsupported("malformed literal", expr.span))
+            }
+            None => Err(LowerError::unsupported(
+                "sizeof of variable length array",
+                expr.span,
+            )),
+        }
+    }
+
+    /// 已经转换为 `ty` 类型的常量
+    pub fn const_value(&self, value: ConstValue, ty: TypeKey) -> Value {
+        match ir_type(self.ctx, ty) {
+            Type::F32 => Value::f32(value.as_float() as f32),
+            Type::F64 => Value::f64(value.as_float()),
+            Type::Ptr => Value::Int {
+                ty: Type::Ptr,
+                bits: value.as_int() as u64,
+            },
+            Type::Void => Value::Undef(Type::Void),
+            ty => Value::int(ty, value.as_int() as i64),
+        }
+    }
+
+    /// 变量或函数的地址
+    fn decl_addr(&mut self, ident: &Ident, decl: Option<DeclKey>) -> LowerResult<Value> {
+        let value = match decl {
+            Some(key) => match self.locals.get(&key) {
+                Some(x) => Some(*x),
+                None => self.m.symbol_of(key),
+            },
+            None => self.m.module.symbol(ident.symbol.get()),
+        };
+        value.ok_or_else(|| LowerError::Unresolved {
+            name: ident.symbol.get().to_string(),
+            span: ident.span,
+        })
+    }
+
+    /// 表达式是不是经过联合体的成员访问得到的对象，通过联合体读写其他成员的类型双关是允许的
+    fn through_union(&self, key: ExprKey) -> bool {
+        use ExprKind::*;
+        let is_union = |ty: TypeKey| {
+            matches!(
+                self.ctx.type_ctx.get_type(ty).kind,
+                TypeKind::Record {
+                    kind: RecordKind::Union,
+                    ..
+                }
+            )
+        };
+        match &self.ctx.get_expr(key).kind {
+            MemberAccess { kind, base, .. } => {
+                let base_ty = self.expr_ty(*base);
+                match kind {
+                    MemberAccessKind::Dot => is_union(base_ty) || self.through_union(*base),
+                    MemberAccessKind::Arrow => pointee(self.ctx, base_ty).is_some_and(is_union),
+                }
+            }
+            // 数组成员的元素，指针指向的对象不在联合体中
+            ArraySubscript { base, index } => {
+                let base = match is_pointer_like(self.ctx, self.expr_ty(*base)) {
+                    true => *base,
+                    false => *index,
+                };
+                let is_array = self.ctx.type_ctx.get_type(self.expr_ty(base)).kind.is_array();
+                is_array && self.through_union(base)
+            }
+            _ => false,
+        }
+    }
+
+    /// 左值，得到对象的地址
+    pub fn lvalue(&mut self, key: ExprKey) -> LowerResult<LValue> {
+        use ExprKind::*;
+        let expr = self.ctx.get_expr(key);
+        let ty = expr.ty;
+
+        match &expr.kind {
+            DeclRef { ident, decl } => {
+                let addr = self.decl_addr(ident, *decl)?;
+                Ok(LValue::new(self.ctx, addr, ty))
+            }
+            Literal(LiteralKind::String { value }) => {
+                let addr = self.m.string_literal(literal::string_bytes(value.get()));
+                Ok(LValue::new(self.ctx, addr, ty))
+            }
+            ArraySubscript { base, index } => {
+                // `a[i]` 和 `i[a]` 等价
+                let (base, index) = match is_pointer_like(self.ctx, self.expr_ty(*base)) {
+                    true => (*base, *index),
+                    false => (*index, *base),
+                };
+                let ptr = self.rvalue(base)?;
+                let index_value = self.rvalue(index)?;
+                let index_value = self.index(index_value, self.expr_ty(index));
+                let scale = stride(self.ctx, self.expr_ty(base));
+                let addr = self.ins().gep(ptr, index_value, scale, 0);
+                let mut lvalue = LValue::new(self.ctx, addr, ty);
+                if self.through_union(key) {
+                    lvalue.tbaa = 0;
+                }
+                Ok(lvalue)
+            }
+            MemberAccess { kind, base, field } => {
+                let base_ty = self.expr_ty(*base);
+                let record_ty = match kind {
+                    MemberAccessKind::Dot => base_ty,
+                    MemberAccessKind::Arrow => pointee(self.ctx, base_ty).unwrap_or(base_ty),
+                };
+                let base_addr = self.rvalue(*base)?;
+                let Some(layout) = RecordLayout::of(self.ctx, record_ty) else {
+                    return Err(LowerError::IncompleteType { span: expr.span });
+                };
+                let Some(member) = layout.find_field(self.ctx, *field) else {
+                    return Err(LowerError::NoMember {
+                        field: field.get().to_string(),
+                        span: expr.span,
+                    });
+                };
+                let addr = self.ins().offset(base_addr, member.offset as i64);
+                let mut lvalue = LValue::new(self.ctx, addr, member.ty);
+                lvalue.bit_field = member.bit_field;
+                lvalue.volatile |= self.ctx.type_ctx.get_type(record_ty).qual.is_volatile;
+                if self.through_union(key) {
+                    lvalue.tbaa = 0;
+                }
+                Ok(lvalue)
+            }
+            Unary { op, rhs } if op.kind == UnaryOpKind::Deref => {
+                let addr = self.rvalue(*rhs)?;
+                Ok(LValue::new(self.ctx, addr, ty))
+            }
+            // 块作用域的复合字面量每次求值都重新初始化
+            CompoundLiteral {
+                init,
+                file_scope: false,
+                ..
+            } => {
+                let addr = self.alloca(ty);
+                self.local_init(addr, ty, init, expr.span)?;
+                Ok(LValue::new(self.ctx, addr, ty))
+            }
+            // 函数返回值、赋值、条件表达式得到的 struct / union 是临时对象
+            _ if classify(self.ctx, ty) == TyClass::Memory => {
+                let addr = self.rvalue(key)?;
+                Ok(LValue::new(self.ctx, addr, ty))
+            }
+            _ => Err(LowerError::unsupported(
+                "taking the address of an rvalue",
+                expr.span,
+            )),
+        }
+    }
+
+    /// 读取左值，struct / union / 数组 / 函数得到地址
+    pub fn load(&mut self, lvalue: LValue) -> Value {
+        let ty = match classify(self.ctx, lvalue.ty) {
+            TyClass::Memory => return lvalue.addr,
+            TyClass::Void => return Value::Undef(Type::Void),
+            TyClass::Scalar(x) => x,
+        };
+        let kind = InstKind::Load {
+            ptr: lvalue.addr,
+            volatile: lvalue.volatile,
+            tbaa: lvalue.tbaa(),
+        };
+        let unit = self.ins().ins(kind, ty);
+        let Some(bf) = lvalue.bit_field else {
+            return unit;
+        };
+        let signed = is_signed(self.ctx, lvalue.ty);
+        self.extract_bits(unit, ty, signed, bf.bit_offset, bf.width)
+    }
+
+    /// 取出 `unit` 从 `offset` 开始的 `width` 位，有符号的位域先左移到最高位再算术右移
+    fn extract_bits(
+        &mut self,
+        unit: Value,
+        ty: Type,
+        signed: bool,
+        offset: usize,
+        width: usize,
+    ) -> Value {
+        let bits = ty.bits(8) as usize;
+        let mut builder = self.ins();
+        match signed {
+            true => {
+                let shl = Value::int(ty, (bits - offset - width) as i64);
+                let value = builder.binary(BinaryOp::Shl, unit, shl);
+                builder.binary(BinaryOp::AShr, value, Value::int(ty, (bits - width) as i64))
+            }
+            false => {
+                let value = match offset {
+                    0 => unit,
+                    _ => builder.binary(BinaryOp::LShr, unit, Value::int(ty, offset as i64)),
+                };
+                builder.binary(BinaryOp::And, value, Value::int(ty, low_mask(width) as i64))
+            }
+        }
+    }
+
+    /// 写入位域后再读出的值，赋值表达式的值要截断到位域的宽度
+    fn bit_field_value(&mut self, lvalue: LValue, value: Value) -> Value {
+        let Some(bf) = lvalue.bit_field else {
+            return value;
+        };
+        let ty = ir_type(self.ctx, lvalue.ty);
+        let signed = is_signed(self.ctx, lvalue.ty);
+        self.extract_bits(value, ty, signed, 0, bf.width)
+    }
+
+    /// 写入左值，`value` 已经转换为左值的类型，struct / union 的 `value` 是源对象的地址
+    pub fn store(&mut self, lvalue: LValue, value: Value) {
+        let ty = match classify(self.ctx, lvalue.ty) {
+            TyClass::Memory => {
+                let (size, align) = size_align(self.ctx, lvalue.ty);
+                self.ins().memcpy(lvalue.addr, value, size, align);
+                return;
+            }
+            TyClass::Void => return,
+            TyClass::Scalar(x) => x,
+        };
+
+        // 位域先读出存储单元，替换对应的位再写回
+        let value = match lvalue.bit_field {
+            None => value,
+            Some(bf) => {
+                let kind = InstKind::Load {
+                    ptr: lvalue.addr,
+                    volatile: lvalue.volatile,
+                    tbaa: 0,
+                };
+                let mask = low_mask(bf.width) << bf.bit_offset;
+                let mut builder = self.ins();
+                let unit = builder.ins(kind, ty);
+                let value =
+                    builder.binary(BinaryOp::Shl, value, Value::int(ty, bf.bit_offset as i64));
+                let value = builder.binary(BinaryOp::And, value, Value::int(ty, mask as i64));
+                let unit = builder.binary(BinaryOp::And, unit, Value::int(ty, !mask as i64));
+                builder.binary(BinaryOp::Or, unit, value)
+            }
+        };
+        let kind = InstKind::Store {
+            ptr: lvalue.addr,
+            val: value,
+            volatile: lvalue.volatile,
+            tbaa: lvalue.tbaa(),
+        };
+        self.ins().ins(kind, Type::Void);
+    }
+
+    /// 把 `from` 类型的值转换为 `to` 类型，数组和函数转换为指针时值不变
+    pub fn convert(&mut self, value: Value, from: TypeKey, to: TypeKey) -> Value {
+        if from != to && self.ctx.type_ctx.get_type(to).is_bool() {
+            return self.bool_convert(value, from, to);
+        }
+        let from_signed = is_signed(self.ctx, from);
+        let to_signed = is_signed(self.ctx, to);
+        match (classify(self.ctx, from), classify(self.ctx, to)) {
+            (_, TyClass::Void) => Value::Undef(Type::Void),
+            (TyClass::Void, TyClass::Scalar(t)) => Value::Undef(t),
+            (TyClass::Memory, TyClass::Scalar(t)) => {
+                self.cast(value, Type::Ptr, false, t, to_signed)
+            }
+            (TyClass::Scalar(f), TyClass::Scalar(t)) => {
+                self.cast(value, f, from_signed, t, to_signed)
+            }
+            _ => value,
+        }
+    }
+
+    /// 转换为 `_Bool`：不为 0 时为 1，整数常量直接折叠
+    fn bool_convert(&mut self, value: Value, from: TypeKey, to: TypeKey) -> Value {
+        if let Value::Int { bits, .. } = value {
+            return Value::int(ir_type(self.ctx, to), (bits != 0) as i64);
+        }
+        let value = self.is_nonzero(value, from);
+        self.bool_value(value, to)
+    }
+
+    /// 标量之间的转换，整数常量直接折叠
+    fn cast(
+        &mut self,
+        value: Value,
+        from: Type,
+        from_signed: bool,
+        to: Type,
+        to_signed: bool,
+    ) -> Value {
+        use CastOp::*;
+        if from == to {
+            return value;
+        }
+        if let Value::Int { bits, .. } = value
+            && from.is_int()
+        {
+            let bits = match from_signed {
+                true => backend::ir::value::sign_extend(bits, from.bits(8)),
+                false => bits as i64,
+            };
+            match to {
+                Type::F32 if from_signed => return Value::f32(bits as f32),
+                Type::F32 => return Value::f32(bits as u64 as f32),
+                Type::F64 if from_signed => return Value::f64(bits as f64),
+                Type::F64 => return Value::f64(bits as u64 as f64),
+                Type::Ptr => {
+                    return Value::Int {
+                        ty: Type::Ptr,
+                        bits: bits as u64,
+                    };
+                }
+                _ if to.is_int() => return Value::int(to, bits),
+                _ => {}
+            }
+        }
+
+        let intptr = intptr_type(self.ctx);
+        let mut builder = self.ins();
+        match (from, to) {
+            (Type::Ptr, _) if to.is_int() => {
+                let value = builder.cast(PtrToInt, value, intptr);
+                match to.bits(8).cmp(&intptr.bits(8)) {
+                    Ordering::Equal => value,
+                    Ordering::Less => builder.cast(Trunc, value, to),
+                    Ordering::Greater => builder.cast(ZExt, value, to),
+                }
+            }
+            (_, Type::Ptr) if from.is_int() => {
+                let value = match (from.bits(8).cmp(&intptr.bits(8)), from_signed) {
+                    (Ordering::Equal, _) => value,
+                    (Ordering::Greater, _) => builder.cast(Trunc, value, intptr),
+                    (Ordering::Less, true) => builder.cast(SExt, value, intptr),
+                    (Ordering::Less, false) => builder.cast(ZExt, value, intptr),
+                };
+                builder.cast(IntToPtr, value, Type::Ptr)
+            }
+            _ if from.is_int() && to.is_int() => {
+                let op = match from.bits(8) > to.bits(8) {
+                    true => Trunc,
+                    false if from_signed => SExt,
+                    false => ZExt,
+                };
+                builder.cast(op, value, to)
+            }
+            _ if from.is_int() && to.is_float() => {
+                let op = if from_signed { SiToFp } else { UiToFp };
+                builder.cast(op, value, to)
+            }
+            _ if from.is_float() && to.is_int() => {
+                let op = if to_signed { FpToSi } else { FpToUi };
+                builder.cast(op, value, to)
+            }
+            (Type::F32, Type::F64) => builder.cast(FpExt, value, to),
+            (Type::F64, Type::F32) => builder.cast(FpTrunc, value, to),
+            _ => builder.cast(Bitcast, value, to),
+        }
+    }
+
+    /// 数组下标、指针偏移转换为 `long`，和指针同宽
+    fn index(&mut self, value: Value, ty: TypeKey) -> Value {
+        let long = self
+            .ctx
+            .type_ctx
+            .get_int_type(IntegerSize::Long, is_signed(self.ctx, ty));
+        self.convert(value, ty, long)
+    }
+
+    /// 整数常量、空指针转换为指针
+    pub(crate) fn pointer(&mut self, key: ExprKey) -> LowerResult<Value> {
+        let value = self.rvalue(key)?;
+        let ty = self.expr_ty(key);
+        match classify(self.ctx, ty) {
+            TyClass::Scalar(t) if t.is_int() => {
+                Ok(self.cast(value, t, is_signed(self.ctx, ty), Type::Ptr, false))
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// 标量是否不为 0，结果为 `i1`
+    fn is_nonzero(&mut self, value: Value, ty: TypeKey) -> Value {
+        match classify(self.ctx, ty) {
+            TyClass::Scalar(Type::F32) => self.ins().cmp(CmpPred::FUne, value, Value::f32(0.0)),
+            TyClass::Scalar(Type::F64) => self.ins().cmp(CmpPred::FUne, value, Value::f64(0.0)),
+            TyClass::Scalar(t) => self.ins().cmp(CmpPred::Ne, value, Value::int(t, 0)),
+            // 数组和函数的地址不为空
+            _ => Value::bool(true),
+        }
+    }
+
+    /// 条件表达式的值，结果为 `i1`
+    pub fn cond(&mut self, key: ExprKey) -> LowerResult<Value> {
+        let expr = self.ctx.get_expr(key);
+        match &expr.kind {
+            ExprKind::Binary { lhs, op, rhs } => match op.kind {
+                BinOpKind::And | BinOpKind::Or => self.logical(op.kind, *lhs, *rhs),
+                BinOpKind::Lt
+                | BinOpKind::Gt
+                | BinOpKind::Eq
+                | BinOpKind::Ne
+                | BinOpKind::Le
+                | BinOpKind::Ge => self.compare(op.kind, *lhs, *rhs),
+                _ => {
+                    let value = self.rvalue(key)?;
+                    Ok(self.is_nonzero(value, expr.ty))
+                }
+            },
+            ExprKind::Unary { op, rhs } if op.kind == UnaryOpKind::Not => {
+                let value = self.cond(*rhs)?;
+                Ok(self.ins().binary(BinaryOp::Xor, value, Value::bool(true)))
+            }
+            _ => {
+                let value = self.rvalue(key)?;
+                Ok(self.is_nonzero(value, expr.ty))
+            }
+        }
+    }
+
+    /// 按条件跳转，`&&` `||` `!` 直接转换为控制流
+    pub fn branch(
+        &mut self,
+        key: ExprKey,
+        then_block: BlockId,
+        else_block: BlockId,
+    ) -> LowerResult<()> {
+        if let Some(value) = eval_const(self.ctx, key) {
+            let dest = if value.is_true() {
+                then_block
+            } else {
+                else_block
+            };
+            self.ins().br(dest);
+            return Ok(());
+        }
+
+        let expr = self.ctx.get_expr(key);
+        match &expr.kind {
+            ExprKind::Binary { lhs, op, rhs } if op.kind == BinOpKind::And => {
+                let mid = self.create_block();
+                self.branch(*lhs, mid, else_block)?;
+                self.switch_to(mid);
+                self.branch(*rhs, then_block, else_block)
+            }
+            ExprKind::Binary { lhs, op, rhs } if op.kind == BinOpKind::Or => {
+                let mid = self.create_block();
+                self.branch(*lhs, then_block, mid)?;
+                self.switch_to(mid);
+                self.branch(*rhs, then_block, else_block)
+            }
+            ExprKind::Unary { op, rhs } if op.kind == UnaryOpKind::Not => {
+                self.branch(*rhs, else_block, then_block)
+            }
+            _ => {
+                let value = self.cond(key)?;
+                self.ins().cond_br(value, then_block, else_block);
+                Ok(())
+            }
+        }
+    }
+
+    /// `&&` `||` 的值，右边只在需要时求值
+    fn logical(&mut self, op: BinOpKind, lhs: ExprKey, rhs: ExprKey) -> LowerResult<Value> {
+        let rhs_block = self.create_block();
+        let end = self.create_block();
+        let lhs_value = self.cond(lhs)?;
+        let lhs_from = self.block;
+        match op {
+            BinOpKind::And => self.ins().cond_br(lhs_value, rhs_block, end),
+            _ => self.ins().cond_br(lhs_value, end, rhs_block),
+        }
+
+        self.switch_to(rhs_block);
+        let rhs_value = self.cond(rhs)?;
+        let rhs_from = self.block;
+        self.ins().br(end);
+
+        self.switch_to(end);
+        let short = Value::bool(op == BinOpKind::Or);
+        Ok(self
+            .ins()
+            .phi(Type::I1, vec![(lhs_from, short), (rhs_from, rhs_value)]))
+    }
+
+    /// 比较，指针按无符号比较，结果为 `i1`
+    fn compare(&mut self, op: BinOpKind, lhs: ExprKey, rhs: ExprKey) -> LowerResult<Value> {
+        let lhs_ty = self.expr_ty(lhs);
+        let rhs_ty = self.expr_ty(rhs);
+        let (a, b, float, signed) =
+            if is_pointer_like(self.ctx, lhs_ty) || is_pointer_like(self.ctx, rhs_ty) {
+                (self.pointer(lhs)?, self.pointer(rhs)?, false, false)
+            } else {
+                let common = arith_type(self.ctx, lhs_ty, rhs_ty);
+                let a = self.rvalue_as(lhs, common)?;
+                let b = self.rvalue_as(rhs, common)?;
+                (
+                    a,
+                    b,
+                    is_float(self.ctx, common),
+                    is_signed(self.ctx, common),
+                )
+            };
+
+        use CmpPred::*;
+        let pred = match (op, float, signed) {
+            (BinOpKind::Eq, true, _) => FOeq,
+            (BinOpKind::Ne, true, _) => FUne,
+            (BinOpKind::Lt, true, _) => FOlt,
+            (BinOpKind::Le, true, _) => FOle,
+            (BinOpKind::Gt, true, _) => FOgt,
+            (BinOpKind::Ge, true, _) => FOge,
+            (BinOpKind::Eq, false, _) => Eq,
+            (BinOpKind::Ne, false, _) => Ne,
+            (BinOpKind::Lt, false, true) => Slt,
+            (BinOpKind::Le, false, true) => Sle,
+            (BinOpKind::Gt, false, true) => Sgt,
+            (BinOpKind::Ge, false, true) => Sge,
+            (BinOpKind::Lt, false, false) => Ult,
+            (BinOpKind::Le, false, false) => Ule,
+            (BinOpKind::Gt, false, false) => Ugt,
+            (BinOpKind::Ge, false, false) => Uge,
+            _ => unreachable!("{:?} is not a comparison", op),
+        };
+        Ok(self.ins().cmp(pred, a, b))
+    }
+
+    /// `i1` 扩展为表达式的类型
+    fn bool_value(&mut self, value: Value, ty: TypeKey) -> Value {
+        let ty = ir_type(self.ctx, ty);
+        self.ins().cast(CastOp::ZExt, value, ty)
+    }
+
+    fn binary(
+        &mut self,
+        key: ExprKey,
+        op: BinOpKind,
+        lhs: ExprKey,
+        rhs: ExprKey,
+    ) -> LowerResult<Value> {
+        use BinOpKind::*;
+        let ty = self.expr_ty(key);
+        match op {
+            Comma => {
+                self.effect(lhs)?;
+                self.rvalue(rhs)
+            }
+            And | Or | Lt | Gt | Eq | Ne | Le | Ge => {
+                let value = self.cond(key)?;
+                Ok(self.bool_value(value, ty))
+            }
+            _ => {
+                let a = self.rvalue(lhs)?;
+                let b = self.rvalue(rhs)?;
+                let (lhs_ty, rhs_ty) = (self.expr_ty(lhs), self.expr_ty(rhs));
+                Ok(self.arith(op, a, lhs_ty, b, rhs_ty, ty))
+            }
+        }
+    }
+
+    /// 算术运算和指针运算，结果转换为 `ty` 类型
+    fn arith(
+        &mut self,
+        op: BinOpKind,
+        a: Value,
+        a_ty: TypeKey,
+        b: Value,
+        b_ty: TypeKey,
+        ty: TypeKey,
+    ) -> Value {
+        use BinOpKind::*;
+        let ctx = self.ctx;
+        match (is_pointer_like(ctx, a_ty), is_pointer_like(ctx, b_ty), op) {
+            // 指针相减得到元素个数
+            (true, true, Minus) => {
+                let intptr = intptr_type(ctx);
+                let mut builder = self.ins();
+                let a = builder.cast(CastOp::PtrToInt, a, intptr);
+                let b = builder.cast(CastOp::PtrToInt, b, intptr);
+                let mut diff = builder.binary(BinaryOp::Sub, a, b);
+                let size = stride(ctx, a_ty);
+                if size > 1 {
+                    diff = builder.binary(BinaryOp::SDiv, diff, Value::int(intptr, size as i64));
+                }
+                let long = ctx.type_ctx.get_int_type(IntegerSize::Long, true);
+                return self.convert(diff, long, ty);
+            }
+            (true, false, Plus | Minus) => {
+                let mut index = self.index(b, b_ty);
+                if op == Minus {
+                    let zero = Value::int(intptr_type(ctx), 0);
+                    index = self.ins().binary(BinaryOp::Sub, zero, index);
+                }
+                return self.ins().gep(a, index, stride(ctx, a_ty), 0);
+            }
+            (false, true, Plus) => {
+                let index = self.index(a, a_ty);
+                return self.ins().gep(b, index, stride(ctx, b_ty), 0);
+            }
+            _ => {}
+        }
+
+        // 移位的结果是左操作数提升后的类型
+        let common = match op {
+            Shl | Shr => promote(ctx, a_ty),
+            _ => arith_type(ctx, a_ty, b_ty),
+        };
+        let a = self.convert(a, a_ty, common);
+        let b = self.convert(b, b_ty, common);
+        let float = is_float(ctx, common);
+        let signed = is_signed(ctx, common);
+
+        use BinaryOp as B;
+        let op = match (op, float, signed) {
+            (Plus, true, _) => B::FAdd,
+            (Minus, true, _) => B::FSub,
+            (Mul, true, _) => B::FMul,
+            (Div, true, _) => B::FDiv,
+            (Mod, true, _) => B::FRem,
+            (Plus, false, _) => B::Add,
+            (Minus, false, _) => B::Sub,
+            (Mul, false, _) => B::Mul,
+            (Div, false, true) => B::SDiv,
+            (Div, false, false) => B::UDiv,
+            (Mod, false, true) => B::SRem,
+            (Mod, false, false) => B::URem,
+            (BitAnd, ..) => B::And,
+            (BitOr, ..) => B::Or,
+            (BitXor | Xor, ..) => B::Xor,
+            (Shl, ..) => B::Shl,
+            (Shr, _, true) => B::AShr,
+            (Shr, _, false) => B::LShr,
+            _ => unreachable!("{:?} is not an arithmetic operator", op),
+        };
+        // 提升后的有符号整数溢出是未定义行为
+        let value = match signed && op.can_be_nsw() {
+            true => self.ins().binary_nsw(op, a, b),
+            false => self.ins().binary(op, a, b),
+        };
+        self.convert(value, common, ty)
+    }
+
+    fn unary(&mut self, key: ExprKey, op: UnaryOpKind, rhs: ExprKey) -> LowerResult<Value> {
+        use UnaryOpKind::*;
+        let ty = self.expr_ty(key);
+        let rhs_ty = self.expr_ty(rhs);
+        match op {
+            AddrOf => {
+                let lvalue = self.lvalue(rhs)?;
+                if lvalue.bit_field.is_some() {
+                    return Err(LowerError::unsupported(
+                        "taking the address of a bit-field",
+                        self.span(key),
+                    ));
+                }
+                Ok(lvalue.addr)
+            }
+            Deref => self.lvalue_load(key),
+            Plus => self.rvalue_as(rhs, ty),
+            Minus => {
+                let promoted = promote(self.ctx, rhs_ty);
+                let value = self.rvalue_as(rhs, promoted)?;
+                let value = match ir_type(self.ctx, promoted) {
+                    Type::F32 | Type::F64 => self.ins().fneg(value),
+                    t if is_signed(self.ctx, promoted) => {
+                        self.ins().binary_nsw(BinaryOp::Sub, Value::int(t, 0), value)
+                    }
+                    t => self.ins().binary(BinaryOp::Sub, Value::int(t, 0), value),
+                };
+                Ok(self.convert(value, promoted, ty))
+            }
+            BitNot => {
+                let promoted = promote(self.ctx, rhs_ty);
+                let value = self.rvalue_as(rhs, promoted)?;
+                let all = Value::int(ir_type(self.ctx, promoted), -1);
+                let value = self.ins().binary(BinaryOp::Xor, value, all);
+                Ok(self.convert(value, promoted, ty))
+            }
+            Not => {
+                let value = self.cond(key)?;
+                Ok(self.bool_value(value, ty))
+            }
+            PreInc => self.inc_dec(rhs, 1, false),
+            PreDec => self.inc_dec(rhs, -1, false),
+            PostInc => self.inc_dec(rhs, 1, true),
+            PostDec => self.inc_dec(rhs, -1, true),
+        }
+    }
+
+    /// `++` `--`，后缀形式返回原来的值
+    fn inc_dec(&mut self, target: ExprKey, delta: i64, post: bool) -> LowerResult<Value> {
+        let lvalue = self.lvalue(target)?;
+        let ty = lvalue.ty;
+        let old = self.load(lvalue);
+        let new = match ir_type(self.ctx, ty) {
+            _ if is_pointer_like(self.ctx, ty) => {
+                let scale = stride(self.ctx, ty);
+                let delta = Value::int(intptr_type(self.ctx), delta);
+                self.ins().gep(old, delta, scale, 0)
+            }
+            Type::F32 => self
+                .ins()
+                .binary(BinaryOp::FAdd, old, Value::f32(delta as f32)),
+            Type::F64 => self
+                .ins()
+                .binary(BinaryOp::FAdd, old, Value::f64(delta as f64)),
+            // 比 int 小的类型先提升再截断，不会溢出
+            t if is_signed(self.ctx, ty) && promote(self.ctx, ty) == ty => {
+                self.ins().binary_nsw(BinaryOp::Add, old, Value::int(t, delta))
+            }
+            t => self.ins().binary(BinaryOp::Add, old, Value::int(t, delta)),
+        };
+        // `_Bool` 的值只有 0 和 1，加减后重新判断是否为 0
+        let new = match self.ctx.type_ctx.get_type(ty).is_bool() {
+            true => self.bool_convert(new, ty, ty),
+            false => new,
+        };
+        self.store(lvalue, new);
+        Ok(match post {
+            true => old,
+            false => self.bit_field_value(lvalue, new),
+        })
+    }
+
+    /// 赋值和复合赋值，值为赋值后左边的值
+    fn assign(&mut self, op: AssignOpKind, lhs: ExprKey, rhs: ExprKey) -> LowerResult<Value> {
+        use AssignOpKind::*;
+        let lvalue = self.lvalue(lhs)?;
+        let value = match op {
+            Assign => self.rvalue_as(rhs, lvalue.ty)?,
+            _ => {
+                let op = match op {
+                    PlusEq => BinOpKind::Plus,
+                    MinusEq => BinOpKind::Minus,
+                    StarEq => BinOpKind::Mul,
+                    SlashEq => BinOpKind::Div,
+                    PercentEq => BinOpKind::Mod,
+                    ShlEq => BinOpKind::Shl,
+                    ShrEq => BinOpKind::Shr,
+                    AmpEq => BinOpKind::BitAnd,
+                    CaretEq => BinOpKind::BitXor,
+                    PipeEq => BinOpKind::BitOr,
+                    Assign => unreachable!(),
+                };
+                let old = self.load(lvalue);
+                let b = self.rvalue(rhs)?;
+                self.arith(op, old, lvalue.ty, b, self.expr_ty(rhs), lvalue.ty)
+            }
+        };
+        self.store(lvalue, value);
+        match classify(self.ctx, lvalue.ty) {
+            TyClass::Memory => Ok(lvalue.addr),
+            _ => Ok(self.bit_field_value(lvalue, value)),
+        }
+    }
+
+    /// 函数调用，变参按默认提升传递，返回 struct / union 时结果在临时对象中
+    fn call(&mut self, key: ExprKey, base: ExprKey, args: &[ExprKey]) -> LowerResult<Value> {
+        let callee_ty = self.expr_ty(base);
+        let Some((ret_ty, params, is_variadic)) = func_parts(self.ctx, callee_ty) else {
+            return Err(LowerError::unsupported(
+                "calling a non-function",
+                self.span(key),
+            ));
+        };
+        if params.is_empty() && !is_variadic && !args.is_empty() {
+            return Err(LowerError::unsupported(
+                "calling a function without a prototype",
+                self.span(key),
+            ));
+        }
+        let params = params.clone();
+        let sig = signature(self.ctx, callee_ty);
+        let callee = self.rvalue(base)?;
+
+        let mut values = Vec::new();
+        let sret = match is_record(self.ctx, ret_ty) {
+            true => {
+                let tmp = self.alloca(ret_ty);
+                values.push(tmp);
+                Some(tmp)
+            }
+            false => None,
+        };
+        for (i, arg) in args.iter().enumerate() {
+            let ty = match params.get(i) {
+                Some(x) => *x,
+                None => default_promote(self.ctx, self.expr_ty(*arg)),
+            };
+            let value = self.rvalue_as(*arg, ty)?;
+            values.push(value);
+        }
+        let result = self.ins().call(sig, callee, values);
+        Ok(sret.unwrap_or(result))
+    }
+
+    /// 条件表达式，两边都求值为结果类型再合并
+    fn ternary(
+        &mut self,
+        ty: TypeKey,
+        cond: ExprKey,
+        then_expr: ExprKey,
+        else_expr: ExprKey,
+    ) -> LowerResult<Value> {
+        let then_block = self.create_block();
+        let else_block = self.create_block();
+        let end = self.create_block();
+        self.branch(cond, then_block, else_block)?;
+
+        let mut incomings = Vec::new();
+        for (block, expr) in [(then_block, then_expr), (else_block, else_expr)] {
+            self.switch_to(block);
+            let value = match classify(self.ctx, ty) {
+                TyClass::Void => {
+                    self.effect(expr)?;
+                    Value::Undef(Type::Void)
+                }
+                _ if is_pointer_like(self.ctx, ty) => self.pointer(expr)?,
+                _ => self.rvalue_as(expr, ty)?,
+            };
+            incomings.push((self.block, value));
+            self.ins().br(end);
+        }
+
+        self.switch_to(end);
+        match ir_type(self.ctx, ty) {
+            Type::Void => Ok(Value::Undef(Type::Void)),
+            t => Ok(self.ins().phi(t, incomings)),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::err::lower_error::{LowerError, LowerResult};
+use crate::lex::types::token_kind::Symbol;
+use crate::lower::lower_core::ModuleLower;
+use crate::lower::lower_ty::{func_parts, is_record, size_align};
+use crate::parser::ast::stmt::Stmt;
+use crate::parser::ast::{DeclKey, StmtKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::types::span::Span;
+use backend::ir::builder::FuncBuilder;
+use backend::ir::debug::{DebugLoc, DiVariable};
+use backend::ir::{BlockId, Function, Type, Value};
+use rustc_hash::FxHashMap;
+
+/// 标签对应的基本块，`defined` 为 false 时只被 `goto` 引用过，`span` 是第一次引用的位置
+pub(crate) struct Label {
+    pub block: BlockId,
+    pub defined: bool,
+    pub span: Span,
+}
+
+///
+/// 把一个函数定义转换为 IR
+///
+/// # Members
+/// - `m`: 模块的转换状态，用于引用全局变量、字符串字面量
+/// - `func`: 正在生成的函数
+/// - `block`: 当前基本块
+/// - `locals`: 局部变量和参数的地址
+/// - `labels`: 函数中的标签
+/// - `breaks` `continues`: `break` `continue` 的目标，循环和 switch 压栈
+/// - `switches`: 正在转换的 switch 中 case / default 语句对应的基本块
+/// - `ret_ty`: 返回值类型
+/// - `sret`: 返回 struct / union 时调用者提供的地址
+/// - `loc`: 之后生成的指令对应的源码位置，没有调试信息时为 None
+///
+pub struct FuncLower<'a, 'm> {
+    pub(crate) ctx: &'a CompCtx,
+    pub(crate) m: &'m mut ModuleLower<'a>,
+    pub(crate) func: Function,
+    pub(crate) block: BlockId,
+    pub(crate) locals: FxHashMap<DeclKey, Value>,
+    pub(crate) labels: FxHashMap<Symbol, Label>,
+    pub(crate) breaks: Vec<BlockId>,
+    pub(crate) continues: Vec<BlockId>,
+    pub(crate) switches: Vec<FxHashMap<StmtKey, BlockId>>,
+    pub(crate) ret_ty: TypeKey,
+    pub(crate) sret: Option<Value>,
+    pub(crate) loc: Option<DebugLoc>,
+}
+
+impl<'a, 'm> FuncLower<'a, 'm> {
+    /// `func` 是只有签名的空函数，`ty` 是函数类型
+    pub fn new(m: &'m mut ModuleLower<'a>, mut func: Function, ty: TypeKey) -> Self {
+        let ctx = m.ctx;
+        let (ret_ty, _, _) = func_parts(ctx, ty).expect("not a function type");
+        let block = func.add_block();
+        Self {
+            ctx,
+            m,
+            func,
+            block,
+            locals: FxHashMap::default(),
+            labels: FxHashMap::default(),
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            switches: Vec::new(),
+            ret_ty,
+            sret: None,
+            loc: None,
+        }
+    }
+
+    /// 参数保存到栈上，`byval` 参数本身就是副本的地址
+    pub fn lower(mut self, params: &[DeclKey], body: &Stmt) -> LowerResult<Function> {
+        let mut arg = 0;
+        if is_record(self.ctx, self.ret_ty) {
+            self.sret = Some(Value::Arg(0));
+            arg = 1;
+        }
+        // 参数的保存对应函数定义所在的行
+        self.loc = self.func.debug.as_ref().map(|x| DebugLoc {
+            file: x.file,
+            line: x.line,
+            col: 0,
+        });
+        for (i, key) in params.iter().enumerate() {
+            let ty = self.ctx.get_decl(*key).ty;
+            let value = Value::Arg(arg + i as u32);
+            if is_record(self.ctx, ty) {
+                self.locals.insert(*key, value);
+            } else {
+                let addr = self.alloca(ty);
+                self.ins().store(addr, value);
+                self.locals.insert(*key, addr);
+                self.debug_var(*key, addr, Some(i as u32 + 1));
+            }
+        }
+
+        self.stmt(body)?;
+        // 补上的 `ret` 对应函数体结尾的 `}`
+        self.set_loc(Span::new(body.span.end.saturating_sub(1), body.span.end));
+        self.finish()
+    }
+
+    /// 检查未定义的标签，没有终结指令的基本块补上 `ret`
+    fn finish(mut self) -> LowerResult<Function> {
+        let mut undefined: Vec<_> = self.labels.iter().filter(|(_, x)| !x.defined).collect();
+        undefined.sort_by_key(|(_, x)| (x.span.start, x.span.end));
+        if let Some((name, label)) = undefined.first() {
+            return Err(LowerError::UndefinedLabel {
+                name: name.get().to_string(),
+                span: label.span,
+            });
+        }
+
+        // 到达 main 结尾时返回 0，其余函数返回未定义的值
+        let ret = self.func.sig.ret;
+        let is_main = self.func.name == "main";
+        for block in self.func.layout.clone() {
+            if self.func.terminator(block).is_some() {
+                continue;
+            }
+            let mut builder = FuncBuilder::new(&mut self.func);
+            builder.switch_to(block);
+            builder.set_loc(self.loc);
+            match ret {
+                Type::Void => builder.ret(None),
+                _ if is_main && ret.is_int() => builder.ret(Some(Value::int(ret, 0))),
+                _ => builder.ret(Some(Value::Undef(ret))),
+            }
+        }
+        Ok(self.func)
+    }
+
+    /// 在当前基本块追加指令，当前块已经结束时（后面是不可达代码）新建一个基本块
+    pub(crate) fn ins(&mut self) -> FuncBuilder<'_> {
+        if self.is_terminated() {
+            self.block = self.func.add_block();
+        }
+        let mut builder = FuncBuilder::new(&mut self.func);
+        builder.switch_to(self.block);
+        builder.set_loc(self.loc);
+        builder
+    }
+
+    /// 有调试信息时，之后生成的指令对应 `span` 的开头
+    pub(crate) fn set_loc(&mut self, span: Span) {
+        if let Some(debug) = &self.m.debug {
+            self.loc = Some(debug.loc(span));
+        }
+    }
+
+    /// 调试信息中的参数或局部变量，`addr` 是变量的 `alloca`，`arg` 是参数的序号
+    pub(crate) fn debug_var(&mut self, key: DeclKey, addr: Value, arg: Option<u32>) {
+        let (Some(debug), Some(sub), Value::Inst(addr)) =
+            (&mut self.m.debug, &mut self.func.debug, addr)
+        else {
+            return;
+        };
+        let decl = self.ctx.get_decl(key);
+        let (Some(name), Some(ty)) = (&decl.name, debug.ty(self.ctx, decl.ty)) else {
+            return;
+        };
+        let loc = debug.loc(decl.span);
+        sub.vars.push(DiVariable {
+            name: name.symbol.get().to_string(),
+            ty,
+            file: loc.file,
+            line: loc.line,
+            arg,
+            addr,
+        });
+    }
+
+    pub(crate) fn is_terminated(&self) -> bool {
+        self.func.terminator(self.block).is_some()
+    }
+
+    pub(crate) fn create_block(&mut self) -> BlockId {
+        self.func.add_block()
+    }
+
+    /// 切换到 `block`，并把它移到最后，使基本块的顺序和源码一致
+    pub(crate) fn switch_to(&mut self, block: BlockId) {
+        self.func.layout.retain(|x| *x != block);
+        self.func.layout.push(block);
+        self.block = block;
+    }
+
+    /// 当前块没有结束时跳转到 `block`
+    pub(crate) fn br_to(&mut self, block: BlockId) {
+        if !self.is_terminated() {
+            self.ins().br(block);
+        }
+    }
+
+    /// 在入口块为 `ty` 类型的对象分配栈空间
+    pub(crate) fn alloca(&mut self, ty: TypeKey) -> Value {
+        let (size, align) = size_align(self.ctx, ty);
+        self.alloca_size(size, align)
+    }
+
+    pub(crate) fn alloca_size(&mut self, size: u64, align: u32) -> Value {
+        FuncBuilder::new(&mut self.func).entry_alloca(size.max(1), align.max(1))
+    }
+
+    /// 标签对应的基本块，第一次引用时创建
+    pub(crate) fn label_block(&mut self, name: Symbol, span: Span) -> BlockId {
+        if let Some(label) = self.labels.get(&name) {
+            return label.block;
+        }
+        let block = self.create_block();
+        let label = Label {
+            block,
+            defined: false,
+            span,
+        };
+        self.labels.insert(name, label);
+        block
+    }
+}
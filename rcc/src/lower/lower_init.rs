@@ -0,0 +1,345 @@
+use crate::err::lower_error::LowerResult;
+use crate::lex::types::token_kind::LiteralKind;
+use crate::lower::lower_core::ModuleLower;
+use crate::lower::lower_expr::LValue;
+use crate::lower::lower_func::FuncLower;
+use crate::lower::lower_ty::{
+    TyClass, check_value, classify, intptr_type, is_pointer_like, is_record, pointee, size_align,
+    stride,
+};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{BinOpKind, ExprKind, MemberAccessKind, UnaryOpKind};
+use crate::parser::ast::types::{BitFieldLayout, RecordLayout};
+use crate::parser::ast::{ExprKey, TypeKey};
+use crate::parser::sema::decl::initializer::{InitElem, flatten_init, is_aggregate};
+use crate::parser::sema::expr::const_eval::{self, ConstValue, eval_const, eval_int};
+use crate::types::span::Span;
+use crate::util::literal;
+use backend::ir::{InitItem, Type, Value};
+
+///
+/// 全局变量初始值的内存映像
+///
+/// # Members
+/// - `bytes`: 小端的字节
+/// - `relocs`: 需要填入符号地址的位置，`(偏移, 符号, 加数)`
+///
+pub struct StaticImage {
+    bytes: Vec<u8>,
+    relocs: Vec<(u64, Value, i64)>,
+}
+
+/// 连续这么多个 0 时使用 `InitItem::Zero`
+const ZERO_RUN: usize = 8;
+
+impl StaticImage {
+    pub fn new(size: u64) -> Self {
+        Self {
+            bytes: vec![0; size as usize],
+            relocs: Vec::new(),
+        }
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            relocs: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.bytes.len() as u64
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) {
+        let offset = offset as usize;
+        let end = (offset + data.len()).min(self.bytes.len());
+        if offset < end {
+            self.bytes[offset..end].copy_from_slice(&data[..end - offset]);
+        }
+    }
+
+    /// 已经转换为 `ty` 类型的常量
+    fn write_const(&mut self, offset: u64, ty: Type, value: ConstValue) {
+        match ty {
+            Type::F32 => self.write(offset, &(value.as_float() as f32).to_le_bytes()),
+            Type::F64 => self.write(offset, &value.as_float().to_le_bytes()),
+            _ => {
+                let size = ty.bits(8) as usize / 8;
+                self.write(offset, &(value.as_int() as u64).to_le_bytes()[..size]);
+            }
+        }
+    }
+
+    /// 修改存储单元中位域对应的位
+    fn write_bits(&mut self, offset: u64, ty: Type, bit_field: BitFieldLayout, value: i128) {
+        let size = ty.bits(8) as usize / 8;
+        let start = offset as usize;
+        let mut unit = [0u8; 8];
+        unit[..size].copy_from_slice(&self.bytes[start..start + size]);
+        let unit = u64::from_le_bytes(unit);
+
+        let width = bit_field.width;
+        let mask = match width {
+            64.. => u64::MAX,
+            _ => (1u64 << width) - 1,
+        } << bit_field.bit_offset;
+        let unit = unit & !mask | (value as u64) << bit_field.bit_offset & mask;
+        self.bytes[start..start + size].copy_from_slice(&unit.to_le_bytes()[..size]);
+    }
+
+    fn reloc(&mut self, offset: u64, target: Value, addend: i64) {
+        self.relocs.push((offset, target, addend));
+    }
+
+    /// 转换为 `InitItem`，较长的连续 0 压缩为 `Zero`
+    pub fn into_items(mut self) -> Vec<InitItem> {
+        self.relocs.sort_by_key(|x| x.0);
+        let mut items = Vec::new();
+        let mut pos = 0;
+        for (offset, target, addend) in self.relocs.iter() {
+            push_bytes(&mut items, &self.bytes[pos..*offset as usize]);
+            items.push(InitItem::Addr {
+                target: *target,
+                addend: *addend,
+            });
+            pos = *offset as usize + 8;
+        }
+        push_bytes(&mut items, &self.bytes[pos..]);
+        items
+    }
+}
+
+fn push_bytes(items: &mut Vec<InitItem>, bytes: &[u8]) {
+    let zeros = |x: &[u8]| x.iter().take_while(|x| **x == 0).count();
+    let mut start = 0;
+    while start < bytes.len() {
+        let n = zeros(&bytes[start..]);
+        if n >= ZERO_RUN {
+            items.push(InitItem::Zero(n as u64));
+            start += n;
+            continue;
+        }
+
+        // 一直延伸到下一段足够长的 0
+        let mut end = start;
+        while end < bytes.len() {
+            match zeros(&bytes[end..]) {
+                0 => end += 1,
+                n if n >= ZERO_RUN => break,
+                n => end += n,
+            }
+        }
+        items.push(InitItem::Bytes(bytes[start..end].to_vec()));
+        start = end;
+    }
+}
+
+///
+/// 静态初始值
+/// - `Const`: 算术常量
+/// - `Addr`: 地址常量，符号地址加偏移
+///
+enum StaticValue {
+    Const(ConstValue),
+    Addr(Value, i64),
+}
+
+impl ModuleLower<'_> {
+    /// 地址常量中的左值
+    fn static_lvalue(&mut self, key: ExprKey) -> Option<(Value, i64)> {
+        let ctx = self.ctx;
+        let expr = ctx.get_expr(key);
+        match &expr.kind {
+            ExprKind::DeclRef { decl, .. } => Some((self.symbol_of((*decl)?)?, 0)),
+            ExprKind::Literal(LiteralKind::String { value }) => {
+                Some((self.string_literal(literal::string_bytes(value.get())), 0))
+            }
+            ExprKind::MemberAccess { kind, base, field } => {
+                let base_ty = ctx.get_expr(*base).ty;
+                let (addr, record_ty) = match kind {
+                    MemberAccessKind::Dot => (self.static_lvalue(*base)?, base_ty),
+                    MemberAccessKind::Arrow => (self.static_addr(*base)?, pointee(ctx, base_ty)?),
+                };
+                let field = RecordLayout::of(ctx, record_ty)?.find_field(ctx, *field)?;
+                if field.bit_field.is_some() {
+                    return None;
+                }
+                Some((addr.0, addr.1 + field.offset as i64))
+            }
+            ExprKind::ArraySubscript { base, index } => {
+                let (base, index) = match is_pointer_like(ctx, ctx.get_expr(*base).ty) {
+                    true => (*base, *index),
+                    false => (*index, *base),
+                };
+                let (target, addend) = self.static_addr(base)?;
+                let scale = stride(ctx, ctx.get_expr(base).ty) as i64;
+                Some((target, addend + eval_int(ctx, index)? as i64 * scale))
+            }
+            ExprKind::Unary { op, rhs } if op.kind == UnaryOpKind::Deref => self.static_addr(*rhs),
+            ExprKind::CompoundLiteral {
+                file_scope: true, ..
+            } => Some((self.compound_literal(key)?, 0)),
+            _ => None,
+        }
+    }
+
+    /// 地址常量
+    fn static_addr(&mut self, key: ExprKey) -> Option<(Value, i64)> {
+        match self.static_value(key)? {
+            StaticValue::Addr(target, addend) => Some((target, addend)),
+            StaticValue::Const(_) => None,
+        }
+    }
+
+    /// 静态初始值，不是常量时返回 None
+    fn static_value(&mut self, key: ExprKey) -> Option<StaticValue> {
+        let ctx = self.ctx;
+        let expr = ctx.get_expr(key);
+        if let Some(value) = eval_const(ctx, key) {
+            return Some(StaticValue::Const(value));
+        }
+
+        match &expr.kind {
+            // 数组和函数转换为地址
+            ExprKind::DeclRef { .. }
+            | ExprKind::Literal(LiteralKind::String { .. })
+            | ExprKind::MemberAccess { .. }
+            | ExprKind::ArraySubscript { .. }
+            | ExprKind::CompoundLiteral { .. }
+                if classify(ctx, expr.ty) == TyClass::Memory =>
+            {
+                let (target, addend) = self.static_lvalue(key)?;
+                Some(StaticValue::Addr(target, addend))
+            }
+            ExprKind::Unary { op, rhs } if op.kind == UnaryOpKind::AddrOf => {
+                let (target, addend) = self.static_lvalue(*rhs)?;
+                Some(StaticValue::Addr(target, addend))
+            }
+            ExprKind::Cast { expr, .. } => self.static_value(*expr),
+            ExprKind::Binary { lhs, op, rhs }
+                if matches!(op.kind, BinOpKind::Plus | BinOpKind::Minus) =>
+            {
+                let (ptr, index) = match is_pointer_like(ctx, ctx.get_expr(*lhs).ty) {
+                    true => (*lhs, *rhs),
+                    false if op.kind == BinOpKind::Plus => (*rhs, *lhs),
+                    false => return None,
+                };
+                let (target, addend) = self.static_addr(ptr)?;
+                let offset =
+                    eval_int(ctx, index)? as i64 * stride(ctx, ctx.get_expr(ptr).ty) as i64;
+                match op.kind {
+                    BinOpKind::Plus => Some(StaticValue::Addr(target, addend + offset)),
+                    _ => Some(StaticValue::Addr(target, addend - offset)),
+                }
+            }
+            ExprKind::Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => match eval_const(ctx, *cond)?.is_true() {
+                true => self.static_value(*then_expr),
+                false => self.static_value(*else_expr),
+            },
+            _ => None,
+        }
+    }
+
+    /// 把常量初始值写入映像，不是常量时返回 false
+    pub fn write_static(&mut self, image: &mut StaticImage, elem: &InitElem) -> LowerResult<bool> {
+        let ctx = self.ctx;
+        match elem {
+            InitElem::String {
+                offset, bytes, len, ..
+            } => {
+                let len = (*len as usize).min(bytes.len());
+                image.write(*offset, &bytes[..len]);
+            }
+            InitElem::Scalar { offset, ty, expr } => {
+                check_value(ctx, *ty, ctx.get_expr(*expr).span)?;
+                let TyClass::Scalar(ir_ty) = classify(ctx, *ty) else {
+                    return Ok(false);
+                };
+                match self.static_value(*expr) {
+                    Some(StaticValue::Const(value)) => {
+                        image.write_const(*offset, ir_ty, const_eval::convert(ctx, value, *ty));
+                    }
+                    // 地址只能放在指针宽度的对象中
+                    Some(StaticValue::Addr(target, addend))
+                        if ir_ty == Type::Ptr || ir_ty == intptr_type(ctx) =>
+                    {
+                        image.reloc(*offset, target, addend);
+                    }
+                    _ => return Ok(false),
+                }
+            }
+            InitElem::BitField {
+                offset,
+                ty,
+                bit_field,
+                expr,
+            } => {
+                let Some(value) = eval_const(ctx, *expr) else {
+                    return Ok(false);
+                };
+                let value = const_eval::convert(ctx, value, *ty).as_int();
+                let TyClass::Scalar(ir_ty) = classify(ctx, *ty) else {
+                    return Ok(false);
+                };
+                image.write_bits(*offset, ir_ty, *bit_field, value);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl FuncLower<'_, '_> {
+    /// 局部变量的初始值：标量和 struct 拷贝直接赋值；
+    /// 聚合类型先复制常量部分的模板，再逐个写入非常量的成员
+    pub fn local_init(
+        &mut self,
+        addr: Value,
+        ty: TypeKey,
+        init: &Initializer,
+        span: Span,
+    ) -> LowerResult<()> {
+        if let Initializer::Expr(expr) = init
+            && (!is_aggregate(self.ctx, ty) || is_record(self.ctx, self.ctx.get_expr(*expr).ty))
+        {
+            let value = self.rvalue_as(*expr, ty)?;
+            self.store(LValue::new(self.ctx, addr, ty), value);
+            return Ok(());
+        }
+
+        let (elems, size) = flatten_init(self.ctx, ty, init, span)?;
+        let mut image = StaticImage::new(size);
+        let mut dynamic = Vec::new();
+        for elem in elems {
+            if !self.m.write_static(&mut image, &elem)? {
+                dynamic.push(elem);
+            }
+        }
+        let align = size_align(self.ctx, ty).1;
+        let template = self.m.init_template(image, align);
+        self.ins().memcpy(addr, template, size, align);
+
+        for elem in dynamic {
+            let (offset, ty, bit_field, expr) = match elem {
+                InitElem::Scalar { offset, ty, expr } => (offset, ty, None, expr),
+                InitElem::BitField {
+                    offset,
+                    ty,
+                    bit_field,
+                    expr,
+                } => (offset, ty, Some(bit_field), expr),
+                InitElem::String { .. } => unreachable!("string initializer is always constant"),
+            };
+            let addr = self.ins().offset(addr, offset as i64);
+            let mut lvalue = LValue::new(self.ctx, addr, ty);
+            lvalue.bit_field = bit_field;
+            let value = self.rvalue_as(expr, ty)?;
+            self.store(lvalue, value);
+        }
+        Ok(())
+    }
+}
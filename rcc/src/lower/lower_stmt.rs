@@ -0,0 +1,347 @@
+use crate::err::lower_error::{LowerError, LowerResult};
+use crate::lower::lower_func::FuncLower;
+use crate::lower::lower_ty::{decl_size_align, ir_type, is_vla, promote, size_align};
+use crate::parser::ast::decls::decl::DeclKind;
+use crate::parser::ast::stmt::{Stmt, StmtKind};
+use crate::parser::ast::{DeclKey, StmtKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::decl_spec::StorageSpecKind;
+use crate::parser::sema::expr::const_eval::{self, ConstValue, eval_int};
+use backend::ir::value::truncate;
+use backend::ir::{BlockId, Type, Value};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// switch 中的 case / default 语句，不进入嵌套的 switch
+fn collect_cases(ctx: &CompCtx, key: StmtKey, cases: &mut Vec<StmtKey>) {
+    use StmtKind::*;
+    match &ctx.get_stmt(key).kind {
+        Case { stmt, .. } | Default { stmt, .. } => {
+            cases.push(key);
+            collect_cases(ctx, *stmt, cases);
+        }
+        Label { stmt, .. } => collect_cases(ctx, *stmt, cases),
+        IfElse {
+            then_stmt,
+            else_stmt,
+            ..
+        } => {
+            collect_cases(ctx, *then_stmt, cases);
+            if let Some(x) = else_stmt {
+                collect_cases(ctx, *x, cases);
+            }
+        }
+        While { body, .. } | DoWhile { body, .. } | For { body, .. } => {
+            collect_cases(ctx, *body, cases)
+        }
+        Compound { stmts, .. } => {
+            for x in stmts.iter() {
+                collect_cases(ctx, *x, cases);
+            }
+        }
+        Expr { .. }
+        | Decl { .. }
+        | Switch { .. }
+        | Goto { .. }
+        | Continue { .. }
+        | Break { .. }
+        | Return { .. } => {}
+    }
+}
+
+impl FuncLower<'_, '_> {
+    pub fn stmt(&mut self, stmt: &Stmt) -> LowerResult<()> {
+        self.lower_stmt(None, stmt)
+    }
+
+    fn stmt_key(&mut self, key: StmtKey) -> LowerResult<()> {
+        let stmt = self.ctx.get_stmt(key);
+        self.lower_stmt(Some(key), stmt)
+    }
+
+    /// `key` 用于查找 case / default 对应的基本块
+    fn lower_stmt(&mut self, key: Option<StmtKey>, stmt: &Stmt) -> LowerResult<()> {
+        use StmtKind::*;
+        if !matches!(stmt.kind, Compound { .. }) {
+            self.set_loc(stmt.span);
+        }
+        match &stmt.kind {
+            Expr { expr, .. } => {
+                if let Some(expr) = expr {
+                    self.effect(*expr)?;
+                }
+            }
+            Decl { decl } => {
+                for key in decl.decls.iter() {
+                    self.local_decl(*key)?;
+                }
+            }
+            Label { ident, stmt } => {
+                let block = self.label_block(ident.symbol, ident.span);
+                let label = self.labels.get_mut(&ident.symbol).unwrap();
+                if label.defined {
+                    return Err(LowerError::DuplicateLabel {
+                        name: ident.symbol.get().to_string(),
+                        span: ident.span,
+                    });
+                }
+                label.defined = true;
+                self.br_to(block);
+                self.switch_to(block);
+                self.stmt_key(*stmt)?;
+            }
+            Case { stmt: body, .. } | Default { stmt: body, .. } => {
+                let block = key.and_then(|x| self.switches.last()?.get(&x).cloned());
+                let Some(block) = block else {
+                    let name = if stmt.kind.is_case() {
+                        "case"
+                    } else {
+                        "default"
+                    };
+                    return Err(LowerError::CaseOutside {
+                        stmt: name,
+                        span: stmt.span,
+                    });
+                };
+                self.br_to(block);
+                self.switch_to(block);
+                self.stmt_key(*body)?;
+            }
+            IfElse {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let then_block = self.create_block();
+                let end = self.create_block();
+                let else_block = match else_stmt {
+                    Some(_) => self.create_block(),
+                    None => end,
+                };
+                self.branch(*cond, then_block, else_block)?;
+
+                self.switch_to(then_block);
+                self.stmt_key(*then_stmt)?;
+                self.br_to(end);
+                if let Some(else_stmt) = else_stmt {
+                    self.switch_to(else_block);
+                    self.stmt_key(*else_stmt)?;
+                    self.br_to(end);
+                }
+                self.switch_to(end);
+            }
+            Switch { expr, body, .. } => self.switch(*expr, *body)?,
+            While { cond, body, .. } => {
+                let cond_block = self.create_block();
+                let body_block = self.create_block();
+                let end = self.create_block();
+                self.br_to(cond_block);
+                self.switch_to(cond_block);
+                self.set_loc(self.ctx.get_expr(*cond).span);
+                self.branch(*cond, body_block, end)?;
+
+                self.switch_to(body_block);
+                self.loop_body(*body, end, cond_block)?;
+                self.br_to(cond_block);
+                self.switch_to(end);
+            }
+            DoWhile { body, cond, .. } => {
+                let body_block = self.create_block();
+                let cond_block = self.create_block();
+                let end = self.create_block();
+                self.br_to(body_block);
+                self.switch_to(body_block);
+                self.loop_body(*body, end, cond_block)?;
+                self.br_to(cond_block);
+
+                self.switch_to(cond_block);
+                self.set_loc(self.ctx.get_expr(*cond).span);
+                self.branch(*cond, body_block, end)?;
+                self.switch_to(end);
+            }
+            For {
+                init,
+                cond,
+                step,
+                body,
+                ..
+            } => {
+                if let Some(init) = init {
+                    self.stmt(self.ctx.get_stmt(*init))?;
+                }
+                let cond_block = self.create_block();
+                let body_block = self.create_block();
+                let step_block = self.create_block();
+                let end = self.create_block();
+                self.br_to(cond_block);
+                self.switch_to(cond_block);
+                match cond {
+                    Some(cond) => {
+                        self.set_loc(self.ctx.get_expr(*cond).span);
+                        self.branch(*cond, body_block, end)?
+                    }
+                    None => self.ins().br(body_block),
+                }
+
+                self.switch_to(body_block);
+                self.loop_body(*body, end, step_block)?;
+                self.br_to(step_block);
+
+                self.switch_to(step_block);
+                if let Some(step) = step {
+                    self.set_loc(self.ctx.get_expr(*step).span);
+                    self.effect(*step)?;
+                }
+                self.br_to(cond_block);
+                self.switch_to(end);
+            }
+            Goto { ident } => {
+                let block = self.label_block(ident.symbol, ident.span);
+                self.ins().br(block);
+            }
+            Continue { continue_span, .. } => {
+                let Some(block) = self.continues.last().cloned() else {
+                    return Err(LowerError::ContinueOutside {
+                        span: *continue_span,
+                    });
+                };
+                self.ins().br(block);
+            }
+            Break { break_span, .. } => {
+                let Some(block) = self.breaks.last().cloned() else {
+                    return Err(LowerError::BreakOutside { span: *break_span });
+                };
+                self.ins().br(block);
+            }
+            Return { expr, .. } => self.ret(*expr)?,
+            Compound { stmts, .. } => {
+                for x in stmts.iter() {
+                    self.stmt_key(*x)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 循环体，`break` 跳到 `end`，`continue` 跳到 `next`
+    fn loop_body(&mut self, body: StmtKey, end: BlockId, next: BlockId) -> LowerResult<()> {
+        self.breaks.push(end);
+        self.continues.push(next);
+        let result = self.stmt_key(body);
+        self.breaks.pop();
+        self.continues.pop();
+        result
+    }
+
+    /// 先收集所有 case 生成跳转表，再转换 switch 的语句体
+    fn switch(&mut self, expr: crate::parser::ast::ExprKey, body: StmtKey) -> LowerResult<()> {
+        let ty = promote(self.ctx, self.ctx.get_expr(expr).ty);
+        let bits = ir_type(self.ctx, ty).bits(8);
+        let value = self.rvalue_as(expr, ty)?;
+
+        let mut cases = Vec::new();
+        collect_cases(self.ctx, body, &mut cases);
+        let end = self.create_block();
+        let mut blocks = FxHashMap::default();
+        let mut targets = Vec::new();
+        let mut seen = FxHashSet::default();
+        let mut default = None;
+        for key in cases {
+            let stmt = self.ctx.get_stmt(key);
+            let block = self.create_block();
+            match &stmt.kind {
+                StmtKind::Case { expr, .. } => {
+                    let span = self.ctx.get_expr(*expr).span;
+                    let Some(case) = eval_int(self.ctx, *expr) else {
+                        return Err(LowerError::NotIntConstant { span });
+                    };
+                    // case 的值转换为控制表达式提升后的类型
+                    let case = const_eval::convert(self.ctx, ConstValue::Int(case), ty).as_int();
+                    if !seen.insert(case) {
+                        return Err(LowerError::DuplicateCase { value: case, span });
+                    }
+                    targets.push((truncate(case as u64, bits), block));
+                }
+                _ => {
+                    if default.is_some() {
+                        return Err(LowerError::DuplicateDefault { span: stmt.span });
+                    }
+                    default = Some(block);
+                }
+            }
+            blocks.insert(key, block);
+        }
+        self.ins().switch(value, default.unwrap_or(end), targets);
+
+        self.switches.push(blocks);
+        self.breaks.push(end);
+        let result = self.stmt_key(body);
+        self.switches.pop();
+        self.breaks.pop();
+        result?;
+        self.br_to(end);
+        self.switch_to(end);
+        Ok(())
+    }
+
+    /// 返回 struct / union 时复制到 `sret` 指向的对象
+    fn ret(&mut self, expr: Option<crate::parser::ast::ExprKey>) -> LowerResult<()> {
+        let ret = self.func.sig.ret;
+        let Some(expr) = expr else {
+            let value = (ret != Type::Void).then_some(Value::Undef(ret));
+            self.ins().ret(value);
+            return Ok(());
+        };
+
+        if let Some(sret) = self.sret {
+            let value = self.rvalue(expr)?;
+            let (size, align) = size_align(self.ctx, self.ret_ty);
+            let mut builder = self.ins();
+            builder.memcpy(sret, value, size, align);
+            builder.ret(None);
+        } else if ret == Type::Void {
+            self.effect(expr)?;
+            self.ins().ret(None);
+        } else {
+            let value = self.rvalue_as(expr, self.ret_ty)?;
+            self.ins().ret(Some(value));
+        }
+        Ok(())
+    }
+
+    /// 块作用域的声明，`static` 和 `extern` 变量是全局变量
+    fn local_decl(&mut self, key: DeclKey) -> LowerResult<()> {
+        let decl = self.ctx.get_decl(key);
+        let storage = decl.storage.as_ref().map(|x| x.kind);
+        match &decl.kind {
+            DeclKind::VarDef { .. } if storage == Some(StorageSpecKind::Static) => {
+                let name = self.func.name.clone();
+                self.m.static_local(&name, key)?;
+            }
+            DeclKind::VarDef { .. } if storage == Some(StorageSpecKind::Extern) => {
+                self.m.declare_global(key)?;
+            }
+            DeclKind::VarDef { init } => {
+                if is_vla(self.ctx, decl.ty) {
+                    return Err(LowerError::unsupported("variable length array", decl.span));
+                }
+                let (size, align) = decl_size_align(self.ctx, decl);
+                let addr = self.alloca_size(size, align);
+                self.locals.insert(key, addr);
+                self.debug_var(key, addr, None);
+                if let Some(init) = init {
+                    self.local_init(addr, decl.ty, init, decl.span)?;
+                }
+            }
+            DeclKind::VarDecl { .. } => {
+                self.m.declare_global(key)?;
+            }
+            DeclKind::FuncDecl { .. } => {
+                self.m.declare_func(key);
+            }
+            DeclKind::TypeDef => self.m.debug_typedef(key),
+            _ => {}
+        }
+        Ok(())
+    }
+}
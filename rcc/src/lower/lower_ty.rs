@@ -0,0 +1,305 @@
+use crate::err::lower_error::{LowerError, LowerResult};
+use crate::parser::ast::TypeKey;
+use crate::parser::ast::decls::decl::Decl;
+use crate::parser::ast::types::{
+    ArraySize, FloatSize, IntegerSize, RecordLayout, TypeKind, TypeLayout,
+};
+use crate::parser::comp_ctx::CompCtx;
+use crate::types::span::Span;
+use backend::ir::{AbiParam, AggShape, ParamAttr, Signature, Type};
+
+///
+/// C 类型在 IR 中的表示
+/// - `Void`: 没有值
+/// - `Scalar`: 整数、浮点、指针，可以放在一个 IR 值中
+/// - `Memory`: struct / union / 数组 / 函数，值就是对象的地址
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TyClass {
+    Void,
+    Scalar(Type),
+    Memory,
+}
+
+pub fn classify(ctx: &CompCtx, ty: TypeKey) -> TyClass {
+    use TypeKind::*;
+    match &ctx.type_ctx.get_type(ty).kind {
+        Void => TyClass::Void,
+        Integer { size, .. } => TyClass::Scalar(int_type(size.sizeof(&ctx.target))),
+        Enum { .. } => TyClass::Scalar(Type::I32),
+        // `long double` 按 `double` 计算，布局不同的目标上不能生成它的值，见 `check_value`
+        Floating { size } => TyClass::Scalar(match size {
+            FloatSize::Float => Type::F32,
+            FloatSize::Double | FloatSize::LongDouble => Type::F64,
+        }),
+        Pointer { .. } => TyClass::Scalar(Type::Ptr),
+        Array { .. } | Record { .. } | Function { .. } => TyClass::Memory,
+        Unknown => TyClass::Void,
+    }
+}
+
+/// 能否生成 `ty` 类型的值；`long double` 的布局和 `double` 不同时只能声明和取地址
+pub fn check_value(ctx: &CompCtx, ty: TypeKey, span: Span) -> LowerResult<()> {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Floating { size } if !size.has_value(&ctx.target) => Err(
+            LowerError::unsupported(format!("'{}' value on this target", size), span),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn int_type(bytes: usize) -> Type {
+    Type::int(bytes as u32 * 8).expect("integer size must be 1, 2, 4 or 8")
+}
+
+/// 和指针同宽的整数类型，用于指针和整数的转换、指针相减、`gep` 的下标
+pub fn intptr_type(ctx: &CompCtx) -> Type {
+    int_type(ctx.target.ptr_bytes as usize)
+}
+
+/// 值的 IR 类型，`Memory` 类型的值是地址
+pub fn ir_type(ctx: &CompCtx, ty: TypeKey) -> Type {
+    match classify(ctx, ty) {
+        TyClass::Void => Type::Void,
+        TyClass::Scalar(x) => x,
+        TyClass::Memory => Type::Ptr,
+    }
+}
+
+/// struct / union
+pub fn is_record(ctx: &CompCtx, ty: TypeKey) -> bool {
+    ctx.type_ctx.get_type(ty).kind.is_record()
+}
+
+/// 指针按无符号处理
+pub fn is_signed(ctx: &CompCtx, ty: TypeKey) -> bool {
+    let ty = ctx.type_ctx.get_type(ty);
+    ty.int_info(ctx).map(|x| x.0).unwrap_or(false)
+}
+
+pub fn is_float(ctx: &CompCtx, ty: TypeKey) -> bool {
+    ctx.type_ctx.get_type(ty).kind.is_floating()
+}
+
+/// 指针、数组、函数的值都是地址
+pub fn is_pointer_like(ctx: &CompCtx, ty: TypeKey) -> bool {
+    matches!(
+        ctx.type_ctx.get_type(ty).kind,
+        TypeKind::Pointer { .. } | TypeKind::Array { .. } | TypeKind::Function { .. }
+    )
+}
+
+/// 指针或数组的元素类型
+pub fn pointee(ctx: &CompCtx, ty: TypeKey) -> Option<TypeKey> {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Pointer { elem_ty } | TypeKind::Array { elem_ty, .. } => Some(*elem_ty),
+        _ => None,
+    }
+}
+
+pub fn is_vla(ctx: &CompCtx, ty: TypeKey) -> bool {
+    matches!(
+        ctx.type_ctx.get_type(ty).kind,
+        TypeKind::Array {
+            size: ArraySize::VLA,
+            ..
+        }
+    )
+}
+
+/// 大小和对齐
+pub fn size_align(ctx: &CompCtx, ty: TypeKey) -> (u64, u32) {
+    let layout = TypeLayout::of(ctx, ty);
+    (layout.size as u64, layout.align as u32)
+}
+
+/// 变量的大小和对齐，`_Alignas` 只会让对齐更严格
+pub fn decl_size_align(ctx: &CompCtx, decl: &Decl) -> (u64, u32) {
+    let (size, align) = size_align(ctx, decl.ty);
+    let explicit = decl.align.map_or(0, |x| x.align as u32);
+    (size, align.max(explicit))
+}
+
+/// 指针运算的步长，`void *` 和函数指针按 1 计算（gcc 扩展）
+pub fn stride(ctx: &CompCtx, ptr_ty: TypeKey) -> u64 {
+    match pointee(ctx, ptr_ty) {
+        Some(x) if !ctx.type_ctx.get_type(x).kind.is_void() => size_align(ctx, x).0,
+        _ => 1,
+    }
+}
+
+/// 函数类型的返回值、参数、是否变参
+pub fn func_parts(ctx: &CompCtx, ty: TypeKey) -> Option<(TypeKey, &Vec<TypeKey>, bool)> {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Function {
+            ret_ty,
+            params,
+            is_variadic,
+            ..
+        } => Some((*ret_ty, params, *is_variadic)),
+        TypeKind::Pointer { elem_ty } => func_parts(ctx, *elem_ty),
+        _ => None,
+    }
+}
+
+/// 把对象展开为标量成员，位域按存储单元计算
+fn flatten_scalars(
+    ctx: &CompCtx,
+    ty: TypeKey,
+    offset: u64,
+    out: &mut Vec<(u64, Type)>,
+) -> Option<()> {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Record { .. } => {
+            for field in RecordLayout::of(ctx, ty)?.fields {
+                flatten_scalars(ctx, field.ty, offset + field.offset as u64, out)?;
+            }
+        }
+        TypeKind::Array {
+            elem_ty,
+            size: ArraySize::Static(n),
+        } => {
+            let elem_size = size_align(ctx, *elem_ty).0;
+            for i in 0..*n as u64 {
+                flatten_scalars(ctx, *elem_ty, offset + i * elem_size, out)?;
+            }
+        }
+        _ => match classify(ctx, ty) {
+            TyClass::Scalar(x) => out.push((offset, x)),
+            _ => return None,
+        },
+    }
+    Some(())
+}
+
+/// 不超过 16 字节的 struct / union 的标量成员，用于目标 ABI 的寄存器传递
+fn agg_shape(ctx: &CompCtx, ty: TypeKey) -> AggShape {
+    let mut fields = Vec::new();
+    if size_align(ctx, ty).0 > 16 || flatten_scalars(ctx, ty, 0, &mut fields).is_none() {
+        return AggShape::default();
+    }
+    AggShape::new(&fields).unwrap_or_default()
+}
+
+fn abi_param(ctx: &CompCtx, ty: TypeKey) -> AbiParam {
+    match is_record(ctx, ty) {
+        true => {
+            let (size, align) = size_align(ctx, ty);
+            let attr = ParamAttr::ByVal {
+                size: size as u32,
+                align,
+                shape: agg_shape(ctx, ty),
+            };
+            AbiParam::with_attr(Type::Ptr, attr)
+        }
+        false => AbiParam::new(ir_type(ctx, ty)),
+    }
+}
+
+/// 函数类型的签名，返回 struct / union 时第一个参数是 `sret` 指针
+pub fn signature(ctx: &CompCtx, ty: TypeKey) -> Signature {
+    let (ret_ty, params, is_variadic) = func_parts(ctx, ty).expect("not a function type");
+    let mut abi_params = Vec::new();
+    let ret = match is_record(ctx, ret_ty) {
+        true => {
+            let (size, align) = size_align(ctx, ret_ty);
+            let attr = ParamAttr::SRet {
+                size: size as u32,
+                align,
+                shape: agg_shape(ctx, ret_ty),
+            };
+            abi_params.push(AbiParam::with_attr(Type::Ptr, attr));
+            Type::Void
+        }
+        false => ir_type(ctx, ret_ty),
+    };
+    abi_params.extend(params.iter().map(|x| abi_param(ctx, *x)));
+    Signature::new(abi_params, ret, is_variadic)
+}
+
+///
+/// 按 C 的有效类型规则给标量访问分配的类型标签，见 IR `load` / `store` 的 `tbaa`
+///
+/// 只有兼容的类型可以访问同一个对象：有无符号和限定符不同的同一种整数、enum 和 int（`Enum`
+/// 的 IR 类型是 `i32`）、所有的指针使用相同的标签；字符类型可以访问任何对象，标签为 `0`
+///
+pub fn alias_tag(ctx: &CompCtx, ty: TypeKey) -> u32 {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Integer { size, .. } => match size {
+            IntegerSize::Char => 0,
+            IntegerSize::Short => 1,
+            IntegerSize::Int => 2,
+            IntegerSize::Long => 3,
+            IntegerSize::LongLong => 4,
+            IntegerSize::Bool => 9,
+        },
+        TypeKind::Enum { .. } => 2,
+        TypeKind::Floating { size } => match size {
+            FloatSize::Float => 5,
+            FloatSize::Double => 6,
+            FloatSize::LongDouble => 7,
+        },
+        TypeKind::Pointer { .. } => 8,
+        _ => 0,
+    }
+}
+
+/// 整数提升：比 int 小的整数和 enum 提升为 int
+pub fn promote(ctx: &CompCtx, ty: TypeKey) -> TypeKey {
+    let int = ctx.type_ctx.get_int_type(IntegerSize::Int, true);
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Integer { size, .. } if size.rank() < IntegerSize::Int.rank() => int,
+        TypeKind::Enum { .. } => int,
+        _ => ty,
+    }
+}
+
+/// 变参的默认提升：整数提升，float 提升为 double
+pub fn default_promote(ctx: &CompCtx, ty: TypeKey) -> TypeKey {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Floating {
+            size: FloatSize::Float,
+        } => ctx.type_ctx.get_float_type(FloatSize::Double),
+        _ => promote(ctx, ty),
+    }
+}
+
+/// usual arithmetic conversion，两个操作数都必须是算术类型
+pub fn arith_type(ctx: &CompCtx, a: TypeKey, b: TypeKey) -> TypeKey {
+    use TypeKind::*;
+    let (a, b) = (promote(ctx, a), promote(ctx, b));
+    let a_ty = ctx.type_ctx.get_type(a);
+    let b_ty = ctx.type_ctx.get_type(b);
+
+    match (&a_ty.kind, &b_ty.kind) {
+        (Floating { size: x }, Floating { size: y }) => match x.rank() >= y.rank() {
+            true => a,
+            false => b,
+        },
+        (Floating { .. }, _) => a,
+        (_, Floating { .. }) => b,
+        (
+            Integer {
+                is_signed: sa,
+                size: ra,
+            },
+            Integer {
+                is_signed: sb,
+                size: rb,
+            },
+        ) => {
+            if sa == sb {
+                return if ra.rank() >= rb.rank() { a } else { b };
+            }
+            let (unsigned, signed) = if *sa { (rb, ra) } else { (ra, rb) };
+            if unsigned.rank() >= signed.rank() {
+                ctx.type_ctx.get_int_type(*unsigned, false)
+            } else if signed.sizeof(&ctx.target) > unsigned.sizeof(&ctx.target) {
+                ctx.type_ctx.get_int_type(*signed, true)
+            } else {
+                ctx.type_ctx.get_int_type(*signed, false)
+            }
+        }
+        _ => a,
+    }
+}
@@ -1,7 +1,23 @@
 use rcc::compiler::c_compiler::CCompiler;
+use rcc::compiler::options::CompilerOptions;
+use rcc::err::driver_error::{DriverError, DriverResult};
+use std::process::ExitCode;
 
-fn main() {
-    let code = include_str!("../resources/example/test1.c");
-    let compiler = CCompiler::new(code.to_string());
-    compiler.compile();
+fn run() -> DriverResult<i32> {
+    let options = CompilerOptions::parse(std::env::args().skip(1))?;
+    let path = options.input.clone().ok_or(DriverError::NoInput)?;
+    let code = std::fs::read_to_string(&path).map_err(|err| DriverError::Io { path, err })?;
+
+    let compiler = CCompiler::new(code, options);
+    compiler.compile()
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => ExitCode::from(code as u8),
+        Err(err) => {
+            eprintln!("rcc: error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
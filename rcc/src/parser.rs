@@ -16,3 +16,5 @@ mod parser_stmt;
 mod semantic;
 
 pub use crate::parser::semantic::{ast, common, comp_ctx};
+pub(crate) use crate::parser::semantic::{decl_spec, sema};
+pub(crate) use parser_extern::parse_translation_unit;
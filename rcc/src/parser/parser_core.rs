@@ -34,23 +34,6 @@ pub(crate) fn check_keyword(ctx: &CompCtx, keyword: Keyword) -> bool {
     kind == TokenKind::Keyword(keyword)
 }
 
-/// 同上，不建议用此函数预期TokenKind下的子类型
-pub(crate) fn expects(ctx: &mut CompCtx, kinds: &[TokenKind]) -> ParserResult<Token> {
-    let expected = checks(ctx, kinds);
-
-    if expected {
-        Ok(ctx.stream.next())
-    } else {
-        let expect: Vec<_> = kinds.iter().map(|x| x.to_string()).collect();
-        let expect = expect.join(", ");
-        let found = ctx.stream.peek().kind.to_string();
-
-        let error_kind = parser_error::ErrorKind::ExpectButFound { expect, found };
-        let error = error_here(ctx, error_kind);
-        Err(error)
-    }
-}
-
 /// 同上，不建议用此函数预期TokenKind下的子类型
 pub(crate) fn expect(ctx: &mut CompCtx, kind: TokenKind) -> ParserResult<Token> {
     let expected = ctx.stream.peek().kind == kind;
@@ -111,7 +94,7 @@ pub(crate) fn expect_keyword_pair(
     if expected {
         Ok(ctx.stream.next())
     } else {
-        let expect = format!("{}, {}", kw1.to_string(), kw2.to_string());
+        let expect = format!("{}, {}", kw1, kw2);
         let error_kind = parser_error::ErrorKind::Expect { expect };
         let error = error_here(ctx, error_kind);
         Err(error)
@@ -147,15 +130,6 @@ pub(crate) fn consume_keyword(ctx: &mut CompCtx, keyword: Keyword) -> Option<Tok
     next_conditional(ctx, is_keyword)
 }
 
-pub(crate) fn consume_keyword_pair(ctx: &mut CompCtx, kw1: Keyword, kw2: Keyword) -> Option<Token> {
-    let kind = ctx.stream.peek().kind;
-    let is_kw = match kind {
-        TokenKind::Keyword(k) => k == kw1 || k == kw2,
-        _ => false,
-    };
-    next_conditional(ctx, is_kw)
-}
-
 pub(crate) fn consume_ident(ctx: &mut CompCtx) -> Option<Token> {
     let is_ident = check_ident(ctx);
     next_conditional(ctx, is_ident)
@@ -190,7 +164,8 @@ pub fn is_type_spec(ctx: &CompCtx, token: &Token) -> bool {
         TokenKind::Ident(_) => is_type_name(ctx, token),
         TokenKind::Keyword(x) => matches!(
             x,
-            Char | Short
+            Bool | Char
+                | Short
                 | Int
                 | Long
                 | Float
@@ -217,15 +192,23 @@ pub fn is_type_qual(token: &Token) -> bool {
 pub fn is_storage_spec(token: &Token) -> bool {
     use Keyword::*;
     match token.kind {
-        TokenKind::Keyword(x) => matches!(x, Typedef | Extern | Static | Auto | Register),
+        TokenKind::Keyword(x) => {
+            matches!(x, Typedef | Extern | Static | Auto | Register | ThreadLocal)
+        }
         _ => false,
     }
 }
 
+pub fn is_align_spec(token: &Token) -> bool {
+    token.kind == TokenKind::Keyword(Keyword::Alignas)
+}
+
 pub fn is_func_spec(ctx: &CompCtx, token: &Token) -> bool {
     match token.kind {
         TokenKind::Ident(_) => is_type_name(ctx, token),
-        TokenKind::Keyword(x) => matches!(x, Keyword::Inline),
+        TokenKind::Keyword(x) => {
+            matches!(x, Keyword::Inline | Keyword::Noreturn | Keyword::Attribute)
+        }
         _ => false,
     }
 }
@@ -1,6 +1,7 @@
 use std::rc::Rc;
 
-use crate::constant::str::EXPECT_IDENT_OR_LB;
+use crate::constant::str::{EXPECT_IDENT_OR_LB, NORETURN_NOT_FUNC};
+use crate::parser::ast::exprs::MemberDesignator;
 use crate::parser::parser_core::error_here;
 use crate::parser::semantic::decl_spec::{EnumSuffix, RecordSuffix, TypeQualKind};
 use crate::parser::semantic::declarator::DeclPrefix;
@@ -8,7 +9,7 @@ use crate::parser::semantic::sema::decl::declarator::act_on_init_declarator;
 use crate::{
     constant::str::DECL_SPEC,
     err::parser_error::{self, ParserError, ParserResult},
-    lex::types::token_kind::{Keyword, TokenKind},
+    lex::types::token_kind::{Keyword, LiteralKind, TokenKind},
     parser::{
         ast::{
             DeclKey, TypeKey,
@@ -18,43 +19,41 @@ use crate::{
         comp_ctx::CompCtx,
         parser_core::{
             check, check_ident, check_keyword, consume, consume_ident, expect, expect_ident,
-            expect_keyword, expect_keyword_pair, is_storage_spec, is_type_qual, is_type_spec,
+            expect_keyword, expect_keyword_pair, is_align_spec, is_func_spec, is_spec_qual, is_storage_spec, is_type_qual,
+            is_type_spec,
         },
-        parser_expr::parse_assign_expr,
+        parser_expr::{parse_assign_expr, parse_conditional_expr},
         semantic::{
             decl_spec::{
-                DeclSpec, EnumSpec, Enumerator, FuncSpec, ParamDecl, ParamList,
+                AlignSpec, DeclSpec, Enumerator, FuncSpec, FuncSpecKind, ParamDecl, ParamList,
                 StorageSpec, StructDeclarator, TypeQual, TypeQuals, TypeSpec,
-                TypeSpecKind,
+                TypeSpecKind, VisibilityKind, VisibilitySpec,
             },
             declarator::{Declarator, DeclaratorChunk, DeclaratorChunkKind, InitDeclarator},
             sema::decl::{
-                record::{insert_enum_decl, insert_record_decl},
+                declarator::{act_on_param_var, act_on_record_field},
+                record::{
+                    act_on_enum_ref, act_on_enumerator, act_on_record_ref, insert_enum_decl,
+                    insert_enum_def, insert_record_decl, insert_record_def, new_enum_type,
+                    new_record_type,
+                },
             },
         },
     },
     types::span::Span,
 };
-use crate::parser::ast::decls::decl::{DeclGroup, InitializerList};
+use crate::parser::ast::decls::decl::{DeclGroup, DeclKind, InitializerList};
 use crate::parser::ast::decls::initializer::Initializer;
-use crate::parser::semantic::sema::decl::decl_spec::DeclSpecBuilder;
+use crate::parser::semantic::sema::decl::decl_spec::{
+    DeclSpecBuilder, act_on_align_expr, type_align,
+};
 use crate::parser::semantic::sema::type_ctx::declarator::resolve_declarator;
 
-fn expect_semi_or_lbrace_error(ctx: &CompCtx) -> ParserError {
-    let kind = parser_error::ErrorKind::Expect {
-        expect: "identifier or '{'".to_owned(),
-    };
-    error_here(ctx, kind)
-}
-
-/// 检查 declarator `{` '(` `[` `ident`
+/// 检查 declarator `(` `[` `ident`
 fn check_declarator(ctx: &CompCtx) -> bool {
     use TokenKind::*;
     let kind = &ctx.stream.peek().kind;
-    match kind {
-        LParen | LBrace | LBracket | Ident(_) => true,
-        _ => false,
-    }
+    matches!(kind, LParen | LBracket | Ident(_))
 }
 
 // 检查指针
@@ -73,7 +72,7 @@ pub(crate) fn parse_decl_prefix(ctx: &mut CompCtx) -> ParserResult<DeclPrefix>{
         // 遇到 ; 结束了
         None 
     } else {
-        let mut declarator = Declarator::new(Rc::clone(&decl_spec))
+        let mut declarator = Declarator::new(Rc::clone(&decl_spec));
         parse_declarator(ctx, &mut declarator)?;
         Some(declarator)
     };
@@ -94,13 +93,15 @@ pub(crate) fn parse_decl_after_declarator(
 ) -> ParserResult<DeclGroup> {
     let mut group = DeclGroup::default();
 
+    // 具名的 tag 或者没有 declarator 的匿名 tag 作为组内第一个声明，匿名 tag 会内联到 declarator 的类型里
+    if let Some(tag) = prefix.decl_spec.tag
+        && (prefix.declarator.is_none() || ctx.get_decl(tag).name.is_some()) {
+            group.decls.push(tag);
+        }
+
     if let Some(x) = prefix.declarator {
         // 解析到 declarator 继续解析
         parse_init_declarator_list(ctx, x, &mut group)?;
-    } else {
-        // 没有 declarator 结束
-        // act on decl_spec
-        todo!()
     }
 
     let _ = expect(ctx, TokenKind::Semi)?;
@@ -119,6 +120,8 @@ pub(crate) fn parse_decl_spec(ctx: &mut CompCtx) -> ParserResult<Rc<DeclSpec>> {
     let mut storages: Vec<StorageSpec> = Vec::new();
     let mut type_quals: Vec<TypeQual> = Vec::new();
     let mut func_specs: Vec<FuncSpec> = Vec::new();
+    let mut visibilities: Vec<VisibilitySpec> = Vec::new();
+    let mut aligns: Vec<AlignSpec> = Vec::new();
     let mut type_specs: Vec<TypeSpec> = Vec::new();
 
     loop {
@@ -127,7 +130,9 @@ pub(crate) fn parse_decl_spec(ctx: &mut CompCtx) -> ParserResult<Rc<DeclSpec>> {
             let spec = StorageSpec::new(ctx.stream.next());
             storages.push(spec);
             // typedef extern static auto register
-        } else if is_type_spec(ctx, token) {
+        } else if is_type_spec(ctx, token)
+            && (type_specs.is_empty() || !matches!(token.kind, TokenKind::Ident(_)))
+        {
             // 解析组合下一个 type spec
             let spec = parse_type_spec(ctx)?;
             type_specs.push(spec);
@@ -135,26 +140,51 @@ pub(crate) fn parse_decl_spec(ctx: &mut CompCtx) -> ParserResult<Rc<DeclSpec>> {
             // const restrict volatile
             let spec = TypeQual::new(ctx.stream.next());
             type_quals.push(spec);
-        } else if check_keyword(ctx, Keyword::Inline) {
-            // inline
+        } else if check_keyword(ctx, Keyword::Inline) || check_keyword(ctx, Keyword::Noreturn) {
+            // inline _Noreturn
             let spec = parse_function_spec(ctx)?;
             func_specs.push(spec);
+        } else if check_keyword(ctx, Keyword::Attribute) {
+            // __attribute__((...))
+            parse_attributes(ctx, &mut func_specs, &mut visibilities)?;
+        } else if check_keyword(ctx, Keyword::Alignas) {
+            // _Alignas(...)
+            let spec = parse_align_spec(ctx)?;
+            aligns.push(spec);
         } else {
             break;
         };
     }
+
+    // C99 之后不再有隐式 int，type spec 不能为空
+    if type_specs.is_empty() {
+        let kind = parser_error::ErrorKind::Expect {
+            expect: "type specifier".to_owned(),
+        };
+        return Err(error_here(ctx, kind));
+    }
     let hi = ctx.stream.prev_span();
     let span = Span::span(lo, hi);
 
-    // 肯定不能为空
-    debug_assert!(!type_specs.is_empty());
+    // 在这里声明或定义的 tag，引用之前声明的 tag 不算
+    let tag = type_specs.iter().find_map(|x| match x.kind {
+        TypeSpecKind::Record(decl) | TypeSpecKind::Enum(decl)
+            if ctx.get_decl(decl).span.start >= lo.start =>
+        {
+            Some(decl)
+        }
+        _ => None,
+    });
 
     // 构建 decl_spec
     let builder = DeclSpecBuilder {
         storages,
         type_quals,
         func_specs,
+        visibilities,
+        aligns,
         type_specs,
+        tag,
         span,
     };
 
@@ -163,6 +193,26 @@ pub(crate) fn parse_decl_spec(ctx: &mut CompCtx) -> ParserResult<Rc<DeclSpec>> {
     Ok(decl_spec)
 }
 
+/// 解析 `_Alignas ( type-name )` 或 `_Alignas ( constant-expression )`
+fn parse_align_spec(ctx: &mut CompCtx) -> ParserResult<AlignSpec> {
+    let lo = expect_keyword(ctx, Keyword::Alignas)?.span;
+    expect(ctx, TokenKind::LParen)?;
+    let token = ctx.stream.peek();
+    let align = if is_type_spec(ctx, token) || is_type_qual(token) {
+        let type_lo = ctx.stream.span();
+        let ty = parse_type_name(ctx)?;
+        let span = Span::span(type_lo, ctx.stream.prev_span());
+        type_align(ctx, "_Alignas", ty, span)?
+    } else {
+        let expr = parse_conditional_expr(ctx)?;
+        act_on_align_expr(ctx, expr)?
+    };
+    expect(ctx, TokenKind::RParen)?;
+    let span = Span::span(lo, ctx.stream.prev_span());
+
+    Ok(AlignSpec { align, span })
+}
+
 /// 解析type spec
 fn parse_type_spec(ctx: &mut CompCtx) -> ParserResult<TypeSpec> {
     let token = ctx.stream.peek();
@@ -199,7 +249,11 @@ fn parse_type_spec(ctx: &mut CompCtx) -> ParserResult<TypeSpec> {
             }
 
             // 一定是那堆 keyword
-            _ => TypeSpecKind::new(*kw),
+            kw => {
+                let kw = *kw;
+                let _ = ctx.stream.next();
+                TypeSpecKind::new(kw)
+            }
         },
         _ => unreachable!(),
     };
@@ -244,11 +298,93 @@ fn parse_type_spec(ctx: &mut CompCtx) -> ParserResult<TypeSpec> {
 // }
 
 fn parse_function_spec(ctx: &mut CompCtx) -> ParserResult<FuncSpec> {
-    let inline = ctx.stream.next();
-    let func_spec = FuncSpec::new(inline);
+    let token = ctx.stream.next();
+    let func_spec = FuncSpec::new(token);
     Ok(func_spec)
 }
 
+/// GNU `__attribute__((a, b(...), ...))`，只识别 `always_inline` `noinline` `noreturn` `visibility("...")`，
+/// 其他属性连同参数一起跳过
+fn parse_attributes(
+    ctx: &mut CompCtx,
+    func_specs: &mut Vec<FuncSpec>,
+    visibilities: &mut Vec<VisibilitySpec>,
+) -> ParserResult<()> {
+    let _ = ctx.stream.next();
+    let _ = expect(ctx, TokenKind::LParen)?;
+    let _ = expect(ctx, TokenKind::LParen)?;
+
+    while !check(ctx, TokenKind::RParen) {
+        // 属性名可能是标识符或关键字（如 `const`）
+        let token = ctx.stream.next();
+        let name = match token.kind {
+            TokenKind::Ident(symbol) => symbol.get().trim_matches('_'),
+            _ => "",
+        };
+        // `__noinline__` 和 `noinline` 相同
+        let kind = match name {
+            "always_inline" => Some(FuncSpecKind::AlwaysInline),
+            "noinline" => Some(FuncSpecKind::NoInline),
+            "noreturn" => Some(FuncSpecKind::Noreturn),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            func_specs.push(FuncSpec {
+                kind,
+                span: token.span,
+            });
+        }
+
+        if name == "visibility" {
+            let spec = parse_visibility(ctx)?;
+            visibilities.push(spec);
+        } else if consume(ctx, TokenKind::LParen).is_some() {
+            // 跳过参数
+            let mut depth = 1;
+            while depth > 0 {
+                if check(ctx, TokenKind::Eof) {
+                    let _ = expect(ctx, TokenKind::RParen)?;
+                }
+                match ctx.stream.next().kind {
+                    TokenKind::LParen => depth += 1,
+                    TokenKind::RParen => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+
+        if consume(ctx, TokenKind::Comma).is_none() {
+            break;
+        }
+    }
+
+    let _ = expect(ctx, TokenKind::RParen)?;
+    let _ = expect(ctx, TokenKind::RParen)?;
+    Ok(())
+}
+
+/// `visibility("default" | "hidden" | "protected")` 的参数部分，`internal` 按 `hidden` 处理
+fn parse_visibility(ctx: &mut CompCtx) -> ParserResult<VisibilitySpec> {
+    let lo = expect(ctx, TokenKind::LParen)?.span;
+    let token = ctx.stream.next();
+    let value = match &token.kind {
+        TokenKind::Literal(LiteralKind::String { value }) => value.get().trim_matches('"'),
+        _ => "",
+    };
+    let kind = match value {
+        "default" => VisibilityKind::Default,
+        "hidden" | "internal" => VisibilityKind::Hidden,
+        "protected" => VisibilityKind::Protected,
+        _ => {
+            let msg = r#"visibility argument must be one of "default", "hidden", "protected""#;
+            return Err(ParserError::error(msg.to_owned(), token.span));
+        }
+    };
+    let hi = expect(ctx, TokenKind::RParen)?.span;
+    let span = Span::span(lo, hi);
+    Ok(VisibilitySpec { kind, span })
+}
+
 /// 兼容 abstract_declarator
 /// 假设 `int **( (*a)() )[]` 结果应该是 `setname(a) [ * () [] * * ] int`
 /// 解析的时候应该反过来
@@ -272,8 +408,13 @@ pub(crate) fn parse_declarator(ctx: &mut CompCtx, declarator: &mut Declarator) -
     pointers.reverse();
     declarator.chunks.append(&mut pointers);
 
+    // 空的 abstract declarator 没有消耗 token，使用 lo 处的空区间
     let hi = ctx.stream.prev_span();
-    let span = Span::span(lo, hi);
+    let span = if hi.end <= lo.start {
+        Span::new(lo.start, lo.start)
+    } else {
+        Span::span(lo, hi)
+    };
     declarator.span = span;
 
     Ok(())
@@ -289,20 +430,31 @@ fn parse_direct_declarator_suffix(
         // 设置name
         let ident = Ident::new(ident);
         declarator.name = Some(ident);
-    } else if let Some(_) = consume(ctx, TokenKind::LParen) {
+    } else if check(ctx, TokenKind::LParen) && !check_param_list(ctx) {
         // 解析 括号 (xxx)
+        let _ = ctx.stream.next();
         parse_declarator(ctx, declarator)?;
         let _ = expect(ctx, TokenKind::RParen)?;
-    } else {
-        unreachable!(
-            "parse_direct_declarator_suffix: unexpected {:?}",
-            ctx.stream.peek()
-        );
-    };
+    }
+    // 否则是 abstract declarator，直接进入 `[]` `()` 部分
 
     Ok(())
 }
 
+/// 当前的 `(` 是否是参数列表的开始，而不是括号包围的 declarator
+fn check_param_list(ctx: &CompCtx) -> bool {
+    let next = ctx.stream.peek_next();
+    matches!(next.kind, TokenKind::RParen) || check_param_spec(ctx, next)
+}
+
+/// 参数声明的开始
+fn check_param_spec(ctx: &CompCtx, token: &crate::lex::types::token::Token) -> bool {
+    is_spec_qual(ctx, token)
+        || is_storage_spec(token)
+        || is_func_spec(ctx, token)
+        || is_align_spec(token)
+}
+
 /// 解析direct declarator
 fn parse_direct_declarator(ctx: &mut CompCtx, declarator: &mut Declarator) -> ParserResult<()> {
     // 非循环部分
@@ -312,7 +464,7 @@ fn parse_direct_declarator(ctx: &mut CompCtx, declarator: &mut Declarator) -> Pa
     loop {
         let lo = ctx.stream.span();
 
-        let kind = if let Some(_) = consume(ctx, TokenKind::LBracket) {
+        let kind = if consume(ctx, TokenKind::LBracket).is_some() {
             // array []
             // let type_qual = parse_type_qual_list_opt(ctx)?;
             // 是否是空括号[]
@@ -326,7 +478,7 @@ fn parse_direct_declarator(ctx: &mut CompCtx, declarator: &mut Declarator) -> Pa
             // func ()
 
             // 参数类型
-            let param = if is_type_spec(ctx, ctx.stream.peek()) {
+            let param = if check_param_spec(ctx, ctx.stream.peek()) {
                 // 普通函数参数
                 let list = parse_parameter_list(ctx)?;
                 ParamDecl::Params(list)
@@ -384,14 +536,6 @@ fn parse_pointer(ctx: &mut CompCtx, chunks: &mut Vec<DeclaratorChunk>) -> Parser
     Ok(())
 }
 
-fn parse_type_qual_list_opt(ctx: &mut CompCtx) -> ParserResult<Option<TypeQuals>> {
-    if is_type_qual(ctx.stream.peek()) {
-        parse_type_qual_list(ctx).map(|list| Some(list))
-    } else {
-        Ok(None)
-    }
-}
-
 fn parse_type_qual_list(ctx: &mut CompCtx) -> ParserResult<TypeQuals> {
     use TypeQualKind::*;
 
@@ -412,6 +556,7 @@ fn parse_type_qual_list(ctx: &mut CompCtx) -> ParserResult<TypeQuals> {
                 let error = ParserError::duplicate(qual.to_string(), DECL_SPEC, qual.span);
                 ctx.send_error(error)?;
             }
+            *field = Some(qual);
         } else {
             break;
         }
@@ -429,7 +574,7 @@ fn parse_init_declarator_list(
     let init = parse_init_declarator(ctx, Rc::clone(&decl_spec), Some(declarator))?;
     group.decls.push(init);
 
-    while let Some(comma) = consume(ctx, TokenKind::Comma) {
+    while consume(ctx, TokenKind::Comma).is_some() {
         let init = parse_init_declarator(ctx, Rc::clone(&decl_spec), None)?;
         group.decls.push(init);
     }
@@ -445,7 +590,8 @@ fn parse_init_declarator(
     decl_spec: Rc<DeclSpec>,
     declarator: Option<Declarator>,
 ) -> ParserResult<DeclKey> {
-    let lo = ctx.stream.span();
+    // 和 clang 一样，组内每个声明都从 decl_spec 开始
+    let lo = decl_spec.span;
 
     // 解析declarator
     let declarator = match declarator {
@@ -478,11 +624,11 @@ fn parse_init_declarator(
 }
 
 /// 解析 initializer
-fn parse_initializer(ctx: &mut CompCtx) -> ParserResult<Initializer> {
-    let init = if let Some(lparen) = consume(ctx, TokenKind::LParen) {
-        let l = lparen.span.to_pos();
-        let inits = parse_initializer_list(ctx)?;
-        let r = expect(ctx, TokenKind::RParen)?.span.to_pos();
+pub(crate) fn parse_initializer(ctx: &mut CompCtx) -> ParserResult<Initializer> {
+    let init = if let Some(lbrace) = consume(ctx, TokenKind::LBrace) {
+        let mut inits = parse_initializer_list(ctx)?;
+        let rbrace = expect(ctx, TokenKind::RBrace)?;
+        inits.span = Span::span(lbrace.span, rbrace.span);
         Initializer::InitList { inits }
     } else {
         let expr = parse_assign_expr(ctx)?;
@@ -493,19 +639,49 @@ fn parse_initializer(ctx: &mut CompCtx) -> ParserResult<Initializer> {
 
 fn parse_initializer_list(ctx: &mut CompCtx) -> ParserResult<InitializerList> {
     let mut list = InitializerList::new();
-    let init = parse_initializer(ctx)?;
+    let init = parse_designated_initializer(ctx)?;
     list.inits.push(init);
 
-    while let Some(comma) = consume(ctx, TokenKind::Comma) {
-        if check(ctx, TokenKind::RParen) {
+    while consume(ctx, TokenKind::Comma).is_some() {
+        if check(ctx, TokenKind::RBrace) {
             break;
         }
-        let init = parse_initializer(ctx)?;
+        let init = parse_designated_initializer(ctx)?;
         list.inits.push(init);
     }
     Ok(list)
 }
 
+/// 解析 `[designation] initializer`
+fn parse_designated_initializer(ctx: &mut CompCtx) -> ParserResult<Initializer> {
+    let lo = ctx.stream.span();
+    let mut designators = Vec::new();
+    loop {
+        let designator = if consume(ctx, TokenKind::LBracket).is_some() {
+            let index = parse_conditional_expr(ctx)?;
+            let _ = expect(ctx, TokenKind::RBracket)?;
+            MemberDesignator::Index(index)
+        } else if consume(ctx, TokenKind::Dot).is_some() {
+            MemberDesignator::Field(Ident::new(expect_ident(ctx)?))
+        } else {
+            break;
+        };
+        designators.push(designator);
+    }
+    if designators.is_empty() {
+        return parse_initializer(ctx);
+    }
+
+    expect(ctx, TokenKind::Assign)?;
+    let init = Box::new(parse_initializer(ctx)?);
+    let span = Span::span(lo, ctx.stream.prev_span());
+    Ok(Initializer::Designated {
+        designators,
+        init,
+        span,
+    })
+}
+
 /// 解析 record `struct/union [ident]` 部分
 fn parse_record_suffix(ctx: &mut CompCtx) -> ParserResult<RecordSuffix> {
     let lo = ctx.stream.span();
@@ -530,10 +706,17 @@ fn parse_record_suffix(ctx: &mut CompCtx) -> ParserResult<RecordSuffix> {
 fn parse_record_spec(ctx: &mut CompCtx) -> ParserResult<DeclKey> {
     // 解析前缀
     let suffix = parse_record_suffix(ctx)?;
+    let is_def = check(ctx, TokenKind::LBrace);
 
-    // 前向声明，如果没有名字则不做前向声明
-    let fwd_decl = match suffix.name {
-        Some(x) => Some(insert_record_decl(
+    // 前向声明，如果没有名字则不做前向声明；引用已有的 record 时不重新声明
+    let fwd_decl = match suffix.name.clone() {
+        Some(x) if is_def => Some(insert_record_decl(
+            ctx,
+            suffix.record.clone(),
+            x,
+            suffix.span,
+        )?),
+        Some(x) => Some(act_on_record_ref(
             ctx,
             suffix.record.clone(),
             x,
@@ -545,13 +728,24 @@ fn parse_record_spec(ctx: &mut CompCtx) -> ParserResult<DeclKey> {
     // 尝试解析定义
     let mut def_decl: Option<DeclKey> = None;
     if consume(ctx, TokenKind::LBrace).is_some() {
-        let group = parse_struct_decl_list(ctx)?;
+        ctx.scope_mgr.enter_record();
+        let fields = parse_struct_decl_list(ctx);
+        ctx.scope_mgr.leave_record();
+        let fields = fields?;
         let _ = expect(ctx, TokenKind::RBrace)?;
 
         let hi = ctx.stream.prev_span();
         let span = Span::span(suffix.span, hi);
 
-        def_decl = todo!("act_on_record_def")
+        let ty = match fwd_decl {
+            Some(x) => ctx.get_decl(x).ty,
+            None => new_record_type(ctx, suffix.record.kind, span)?,
+        };
+        let kind = DeclKind::RecordDef {
+            kind: suffix.record.clone(),
+            fields,
+        };
+        def_decl = Some(insert_record_def(ctx, ty, kind, suffix.name, span)?);
     };
 
     // fwd, def 必须存在一个，否则出错
@@ -593,7 +787,7 @@ fn parse_struct_decl(ctx: &mut CompCtx) -> ParserResult<DeclGroup> {
     let decl_spec = parse_decl_spec(ctx)?;
     let mut group = DeclGroup::default();
     parse_struct_declarator_list(ctx, &mut group, decl_spec)?;
-    let semi = expect(ctx, TokenKind::Semi)?.span.to_pos();
+    let _ = expect(ctx, TokenKind::Semi)?;
 
     let hi = ctx.stream.prev_span();
     let span = Span::span(lo, hi);
@@ -612,7 +806,7 @@ fn parse_struct_declarator_list(
     let decl = parse_struct_declarator(ctx, Rc::clone(&decl_spec))?;
     group.decls.push(decl);
 
-    while let Some(_) = consume(ctx, TokenKind::Comma) {
+    while consume(ctx, TokenKind::Comma).is_some() {
         let decl = parse_struct_declarator(ctx, Rc::clone(&decl_spec))?;
         group.decls.push(decl);
     }
@@ -622,19 +816,17 @@ fn parse_struct_declarator_list(
 
 /// 解析struct的成员，负责插入符号表，应该插入member
 fn parse_struct_declarator(ctx: &mut CompCtx, decl_spec: Rc<DeclSpec>) -> ParserResult<DeclKey> {
+    // 和变量声明一样从 decl_spec 开始
+    let lo = decl_spec.span;
     let mut declarator = Declarator::new(decl_spec);
 
-    let lo = ctx.stream.span();
-
-    let mut colon = None;
     let mut bit_field = None;
 
-    if check_declarator(ctx) {
+    if check_declarator(ctx) || check_pointer(ctx) {
         parse_declarator(ctx, &mut declarator)?;
     }
 
-    if let Some(colon_token) = consume(ctx, TokenKind::Colon) {
-        colon = Some(colon_token.span.to_pos());
+    if consume(ctx, TokenKind::Colon).is_some() {
         bit_field = Some(parse_assign_expr(ctx)?);
     }
 
@@ -648,7 +840,7 @@ fn parse_struct_declarator(ctx: &mut CompCtx, decl_spec: Rc<DeclSpec>) -> Parser
     };
 
     // 语义分析，获取类型
-    let decl = sema.act_on_record_field(struct_declarator)?;
+    let decl = act_on_record_field(ctx, struct_declarator)?;
     Ok(decl)
 }
 
@@ -657,7 +849,7 @@ fn parse_enum_suffix(ctx: &mut CompCtx) -> ParserResult<EnumSuffix> {
     expect_keyword(ctx, Keyword::Enum)?;
 
     // 检查是否合法
-    if check_ident(ctx) || check(ctx, TokenKind::LBrace) {
+    if !check_ident(ctx) && !check(ctx, TokenKind::LBrace) {
         let kind = parser_error::ErrorKind::Expect {
             expect: "identifier or '{'".to_owned(),
         };
@@ -673,36 +865,34 @@ fn parse_enum_suffix(ctx: &mut CompCtx) -> ParserResult<EnumSuffix> {
     Ok(EnumSuffix { name, span })
 }
 
-// todo 重构成 parse_record_spec 的样子
 /// 解析enum声明或定义
 fn parse_enum_spec(ctx: &mut CompCtx) -> ParserResult<DeclKey> {
-    // 准备枚举上下文
-    // sema.enter_decl(DeclContextKind::Enum);
-
     let suffix = parse_enum_suffix(ctx)?;
+    let is_def = check(ctx, TokenKind::LBrace);
 
     // 前向声明，虽然 enum 定义不需要前向声明
     let fwd_decl = match suffix.name.clone() {
-        Some(x) => Some(insert_enum_decl(ctx, x, suffix.span)?),
+        Some(x) if is_def => Some(insert_enum_decl(ctx, x, suffix.span)?),
+        Some(x) => Some(act_on_enum_ref(ctx, x, suffix.span)?),
         None => None,
     };
 
-    let def_decl: Option<DeclKey> = None;
+    let mut def_decl: Option<DeclKey> = None;
 
-    if consume(ctx, TokenKind::RBrace).is_some() {
-        // 解析枚举列表
-        let enums = parse_enumerator_list(ctx)?;
+    if consume(ctx, TokenKind::LBrace).is_some() {
+        let ty = match fwd_decl {
+            Some(x) => ctx.get_decl(x).ty,
+            None => new_enum_type(ctx, suffix.span)?,
+        };
+
+        // 解析枚举列表，成员在后面的成员中可见
+        let enums = parse_enumerator_list(ctx, ty)?;
         expect(ctx, TokenKind::RBrace)?;
 
         let hi = ctx.stream.prev_span();
         let span = Span::span(suffix.span, hi);
-        let spec = EnumSpec {
-            name: suffix.name,
-            enums,
-            span,
-        };
 
-        todo!("act on enum spec def")
+        def_decl = Some(insert_enum_def(ctx, ty, enums, suffix.name, span)?);
     }
 
     // 二者选其一，必须有 decl, def 优先
@@ -718,13 +908,14 @@ fn parse_enum_spec(ctx: &mut CompCtx) -> ParserResult<DeclKey> {
     Ok(decl)
 }
 
-fn parse_enumerator_list(ctx: &mut CompCtx) -> ParserResult<Vec<Enumerator>> {
-    let mut enums: Vec<Enumerator> = Vec::new();
+/// 解析枚举列表，支持尾部逗号
+fn parse_enumerator_list(ctx: &mut CompCtx, ty: TypeKey) -> ParserResult<Vec<DeclKey>> {
+    let mut enums: Vec<DeclKey> = Vec::new();
     loop {
         let enumerator = parse_enumerator(ctx)?;
-        enums.push(enumerator);
+        enums.push(act_on_enumerator(ctx, enumerator, ty)?);
 
-        if consume(ctx, TokenKind::Comma).is_none() {
+        if consume(ctx, TokenKind::Comma).is_none() || check(ctx, TokenKind::RBrace) {
             break;
         }
     }
@@ -773,12 +964,21 @@ fn parse_parameter_list(ctx: &mut CompCtx) -> ParserResult<ParamList> {
         }
     }
 
+    // `(void)` 表示没有参数
+    if let [param] = params.as_slice() {
+        let decl = ctx.get_decl(*param);
+        if decl.name.is_none() && ctx.type_ctx.get_type(decl.ty).kind.is_void() {
+            params.clear();
+        }
+    }
+
     let hi = ctx.stream.prev_span();
     let span = Span::span(lo, hi);
 
     let list = ParamList {
         params,
         is_variadic,
+        has_prototype: true,
         span,
     };
     Ok(list)
@@ -802,31 +1002,41 @@ fn parse_parameter_decl(ctx: &mut CompCtx) -> ParserResult<DeclKey> {
     declarator.span = span;
 
     // 这个函数要进行必要的检测，不负责管理符号表
-    let decl = sema.act_on_param_var(declarator)?;
+    let decl = act_on_param_var(ctx, declarator)?;
 
     Ok(decl)
 }
 
 fn parse_ident_list(ctx: &mut CompCtx) -> ParserResult<IdentList> {
+    let lo = ctx.stream.span();
     let mut list = IdentList::new();
     let ident = expect_ident(ctx)?;
     let ident = Ident::new(ident);
     list.idents.push(ident);
 
-    while let Some(_) = consume(ctx, TokenKind::Comma) {
+    while consume(ctx, TokenKind::Comma).is_some() {
         let ident = expect_ident(ctx)?;
         let ident = Ident::new(ident);
         list.idents.push(ident);
     }
 
+    let hi = ctx.stream.prev_span();
+    list.span = Span::span(lo, hi);
     Ok(list)
 }
 
 /// 解析 type name
 pub(crate) fn parse_type_name(ctx: &mut CompCtx) -> ParserResult<TypeKey> {
     let decl_specs = parse_decl_spec(ctx)?;
+    if let Some(spec) = decl_specs.align {
+        let msg = "'_Alignas' cannot be applied to a type name".to_owned();
+        return Err(ParserError::error(msg, spec.span));
+    }
+    if let Some(spec) = &decl_specs.noreturn {
+        return Err(ParserError::error(NORETURN_NOT_FUNC.to_owned(), spec.span));
+    }
     let mut declarator = Declarator::new(decl_specs);
-    if check_declarator(ctx) {
+    if check_declarator(ctx) || check_pointer(ctx) {
         parse_declarator(ctx, &mut declarator)?;
     };
 
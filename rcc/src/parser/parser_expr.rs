@@ -2,12 +2,17 @@ use crate::err::parser_error;
 use crate::err::parser_error::ParserResult;
 use crate::lex::types::token::Token;
 use crate::lex::types::token_kind::{Keyword, LiteralKind, TokenKind};
-use crate::parser::ast::ExprKey;
-use crate::parser::ast::exprs::{ExprKind, Parameter};
+use crate::parser::ast::exprs::{
+    Builtin, BuiltinArg, BuiltinParam, ExprKind, MemberDesignator, Parameter,
+};
+use crate::parser::ast::{ExprKey, TypeKey};
+use crate::parser::common::Ident;
 use crate::parser::comp_ctx::CompCtx;
 use crate::parser::parser_core::*;
-use crate::parser::parser_decl::parse_type_name;
+use crate::parser::parser_decl::{parse_initializer, parse_type_name};
+use crate::parser::semantic::sema::decl::initializer::act_on_compound_literal;
 use crate::parser::semantic::sema::expr::sema_expr::make_expr;
+use crate::parser::semantic::sema::scope::scope_struct::ScopeKind;
 use crate::types::span::Span;
 
 fn check_string(ctx: &CompCtx) -> bool {
@@ -22,6 +27,22 @@ fn next_is_type_name(ctx: &CompCtx) -> bool {
     is_type_qual(token) || is_type_spec(ctx, token)
 }
 
+/// 作用域中没有同名声明的 `__builtin_xxx`
+fn check_builtin(ctx: &CompCtx) -> Option<Builtin> {
+    let token = ctx.stream.peek();
+    let TokenKind::Ident(symbol) = token.kind else {
+        return None;
+    };
+    let ident = Ident {
+        symbol,
+        span: token.span,
+    };
+    match ctx.scope_mgr.lookup_ident(&ident) {
+        Some(_) => None,
+        None => Builtin::lookup(symbol.get()),
+    }
+}
+
 fn consume_constant(ctx: &mut CompCtx) -> Option<Token> {
     let is_constant = match &ctx.stream.peek().kind {
         TokenKind::Literal(x) => !matches!(x, LiteralKind::String { .. }),
@@ -74,7 +95,10 @@ fn parse_string(ctx: &mut CompCtx) -> Vec<Token> {
 
 fn parse_primary_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
     let lo = ctx.stream.span();
-    let kind = if let Some(ident) = consume_ident(ctx) {
+    let kind = if let Some(builtin) = check_builtin(ctx) {
+        // __builtin_xxx(args)
+        parse_builtin_call(ctx, builtin)?
+    } else if let Some(ident) = consume_ident(ctx) {
         // ident
         ExprKind::make_decl_ref(ident)
     } else if let Some(constant) = consume_constant(ctx) {
@@ -84,7 +108,7 @@ fn parse_primary_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
         // string
         let strings = parse_string(ctx);
         ExprKind::make_string(strings)
-    } else if let Some(_) = consume(ctx, TokenKind::LParen) {
+    } else if consume(ctx, TokenKind::LParen).is_some() {
         // ( exprs )
         let expr = parse_expr(ctx)?;
         let _ = expect(ctx, TokenKind::RParen)?;
@@ -106,14 +130,53 @@ fn parse_primary_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
     Ok(expr)
 }
 
+/// builtin-call: builtin-name '(' builtin-arg (',' builtin-arg)* ')'
+fn parse_builtin_call(ctx: &mut CompCtx, builtin: Builtin) -> ParserResult<ExprKind> {
+    let _ = expect_ident(ctx)?;
+    let _ = expect(ctx, TokenKind::LParen)?;
+    let mut args = Vec::with_capacity(builtin.params().len());
+    for (i, param) in builtin.params().iter().enumerate() {
+        if i > 0 {
+            let _ = expect(ctx, TokenKind::Comma)?;
+        }
+        let arg = match param {
+            BuiltinParam::Expr => BuiltinArg::Expr(parse_assign_expr(ctx)?),
+            BuiltinParam::Type => BuiltinArg::Type(parse_type_name(ctx)?),
+            BuiltinParam::Member => BuiltinArg::Member(parse_member_designator(ctx)?),
+        };
+        args.push(arg);
+    }
+    let _ = expect(ctx, TokenKind::RParen)?;
+    Ok(ExprKind::make_builtin_call(builtin, args))
+}
+
+/// member-designator: identifier ('.' identifier | '[' expression ']')*
+fn parse_member_designator(ctx: &mut CompCtx) -> ParserResult<Vec<MemberDesignator>> {
+    let mut designators = vec![MemberDesignator::Field(Ident::new(expect_ident(ctx)?))];
+    loop {
+        let designator = if consume(ctx, TokenKind::Dot).is_some() {
+            MemberDesignator::Field(Ident::new(expect_ident(ctx)?))
+        } else if consume(ctx, TokenKind::LBracket).is_some() {
+            let index = parse_expr(ctx)?;
+            let _ = expect(ctx, TokenKind::RBracket)?;
+            MemberDesignator::Index(index)
+        } else {
+            break;
+        };
+        designators.push(designator);
+    }
+    Ok(designators)
+}
+
 fn parse_postfix_expr_suffix(ctx: &mut CompCtx, mut lhs: ExprKey) -> ParserResult<ExprKey> {
     use TokenKind::*;
-    let lo = ctx.stream.span();
+    // 后缀表达式从 base 开始
+    let lo = ctx.get_expr(lhs).span;
     loop {
-        let kind = if let Some(lparen) = consume(ctx, LBracket) {
+        let kind = if let Some(_lparen) = consume(ctx, LBracket) {
             // 数组访问[]
             let index = parse_expr(ctx)?;
-            let rparen = expect(ctx, RBracket)?;
+            let _rparen = expect(ctx, RBracket)?;
             ExprKind::make_index(lhs, index)
         } else if let Some(lparen) = consume(ctx, LParen) {
             // 函数调用()
@@ -160,7 +223,7 @@ fn parse_expr_list(ctx: &mut CompCtx) -> ParserResult<Parameter> {
         let expr = parse_assign_expr(ctx)?;
         param.exprs.push(expr);
 
-        if let Some(_) = consume(ctx, TokenKind::Comma) {
+        if consume(ctx, TokenKind::Comma).is_some() {
         } else if check(ctx, TokenKind::RParen) {
             break;
         } else {
@@ -192,11 +255,23 @@ fn parse_unary_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
             // sizeof typename
             let type_name = parse_type_name(ctx)?;
             let rparen = expect(ctx, TokenKind::RParen)?;
-            ExprKind::make_size_of_type(sizeof, lparen, type_name, rparen)
+            if check(ctx, TokenKind::LBrace) {
+                // sizeof (type){...}
+                let expr = parse_compound_literal(ctx, lparen, type_name, rparen)?;
+                ExprKind::make_size_of_expr(sizeof, expr)
+            } else {
+                ExprKind::make_size_of_type(sizeof, lparen, type_name, rparen)
+            }
         } else {
             let expr = parse_unary_expr(ctx)?;
             ExprKind::make_size_of_expr(sizeof, expr)
         }
+    } else if let Some(alignof) = consume_keyword(ctx, Keyword::Alignof) {
+        // _Alignof 只接受 typename
+        let lparen = expect(ctx, TokenKind::LParen)?;
+        let type_name = parse_type_name(ctx)?;
+        let rparen = expect(ctx, TokenKind::RParen)?;
+        ExprKind::make_align_of_type(alignof, lparen, type_name, rparen)
     } else {
         // 什么都不是
         return parse_postfix_expr(ctx);
@@ -208,12 +283,32 @@ fn parse_unary_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
     Ok(expr)
 }
 
+/// 解析 (type-name) 之后的 { initializer-list }，复合字面量是后缀表达式的 base
+fn parse_compound_literal(
+    ctx: &mut CompCtx,
+    lparen: Token,
+    type_name: TypeKey,
+    rparen: Token,
+) -> ParserResult<ExprKey> {
+    let lo = lparen.span;
+    let init = parse_initializer(ctx)?;
+    let span = Span::span(lo, ctx.stream.prev_span());
+    let ty = act_on_compound_literal(ctx, type_name, &init, span)?;
+    let file_scope = ctx.scope_mgr.get_kind() == ScopeKind::File;
+    let kind = ExprKind::make_compound_literal(lparen, ty, rparen, init, file_scope);
+    let expr = make_expr(ctx, kind, span)?;
+    parse_postfix_expr_suffix(ctx, expr)
+}
+
 fn parse_cast_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
     let lo = ctx.stream.span();
     let kind = if check(ctx, TokenKind::LParen) && next_is_type_name(ctx) {
         let lparen = ctx.stream.next();
         let type_name = parse_type_name(ctx)?;
         let rparen = expect(ctx, TokenKind::RParen)?;
+        if check(ctx, TokenKind::LBrace) {
+            return parse_compound_literal(ctx, lparen, type_name, rparen);
+        }
         let expr = parse_cast_expr(ctx)?;
         ExprKind::make_cast(lparen, type_name, rparen, expr)
     } else {
@@ -453,7 +548,7 @@ fn parse_logical_or_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
     Ok(expr)
 }
 
-fn parse_conditional_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
+pub(crate) fn parse_conditional_expr(ctx: &mut CompCtx) -> ParserResult<ExprKey> {
     let lo = ctx.stream.span();
     let cond = parse_logical_or_expr(ctx)?;
 
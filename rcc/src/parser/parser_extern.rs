@@ -8,6 +8,10 @@ use crate::parser::parser_stmt::parse_compound_stmt;
 use crate::parser::semantic::ast::func::{ExternalDecl, FuncDecl, FuncDef, TranslationUnit};
 use crate::parser::semantic::ast::stmt::Stmt;
 use crate::parser::semantic::declarator::DeclPrefix;
+use crate::parser::semantic::sema::decl::declarator::{
+    act_on_func_decl, act_on_func_def, act_on_params,
+};
+use crate::parser::semantic::sema::expr::builtin::declare_builtin_types;
 use crate::types::span::Span;
 
 fn check_decl_spec(ctx: &CompCtx) -> bool {
@@ -17,20 +21,22 @@ fn check_decl_spec(ctx: &CompCtx) -> bool {
         || is_spec_qual(ctx, token)
         || is_storage_spec(token)
         || is_func_spec(ctx, token)
+        || is_align_spec(token)
 }
 
 pub(crate) fn parse_translation_unit(ctx: &mut CompCtx) -> ParserResult<TranslationUnit> {
     let mut translation_unit = TranslationUnit::new();
 
-    // 进入 File 作用域
+    // 进入 File 作用域，声明内建类型
+    ctx.scope_mgr.enter_global();
+    declare_builtin_types(ctx);
 
     while !check(ctx, TokenKind::Eof) {
         parse_external_decl(ctx, &mut translation_unit)?;
     }
 
-    // 处理暂定定义
-    todo!();
-    // 退出 File 作用域
+    // 退出 File 作用域，暂定定义在 lower 时初始化为 0
+    ctx.scope_mgr.leave_global();
     Ok(translation_unit)
 }
 
@@ -62,11 +68,14 @@ fn parse_function_def(ctx: &mut CompCtx, prefix: DeclPrefix) -> ParserResult<Fun
         prefix.declarator.is_some(),
         "function declarator never be none"
     );
-    // 进入参数作用域
-
-    // KR函数的参数
+    // KR函数的参数，在参数作用域中解析
     let decl_list = match check_decl_spec(ctx) {
-        true => Some(parse_decl_list(ctx)?),
+        true => {
+            ctx.scope_mgr.enter_param();
+            let list = parse_decl_list(ctx);
+            ctx.scope_mgr.leave_param();
+            Some(list?)
+        }
         false => None,
     };
 
@@ -79,16 +88,21 @@ fn parse_function_def(ctx: &mut CompCtx, prefix: DeclPrefix) -> ParserResult<Fun
         span,
     };
 
-    // 函数声明
-    let decl = sema.act_on_func_decl(func_decl)?;
+    // 函数声明，函数体中可以递归调用
+    let (decl, params) = act_on_func_decl(ctx, func_decl)?;
 
-    // compound stmt会调用exit_decl
-    let kind = parse_compound_stmt(ctx, false, false)?;
+    // 参数和函数体共用函数作用域
+    let body_lo = ctx.stream.span();
+    ctx.scope_mgr.enter_function();
+    let kind = act_on_params(ctx, &params).and_then(|_| parse_compound_stmt(ctx, false, false));
+    ctx.scope_mgr.leave_function();
+    let kind = kind?;
 
     let hi = ctx.stream.prev_span();
     let span = Span::span(prefix.lo, hi);
 
-    let body = Stmt::new_key(ctx, kind, span);
+    let body = Stmt::new_key(ctx, kind, Span::span(body_lo, hi));
+    let decl = act_on_func_def(ctx, decl, params, body, span)?;
     let def = FuncDef { decl, body, span };
 
     Ok(def)
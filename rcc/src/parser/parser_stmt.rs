@@ -49,20 +49,23 @@ fn check_jump_stmt(ctx: &CompCtx) -> bool {
 
 fn check_decl(ctx: &CompCtx) -> bool {
     let token = ctx.stream.peek();
-    is_type_spec(ctx, token) || is_type_qual(token) || is_storage_spec(token)
+    is_type_spec(ctx, token)
+        || is_type_qual(token)
+        || is_storage_spec(token)
+        || is_align_spec(token)
 }
 
 /// statement
 /// # Arguments
 /// only stmt: 只解析stmt无decl
-pub(crate) fn parse_stmt(ctx: &mut CompCtx, only_stmt: bool) -> ParserResult<StmtKey> {
+pub(crate) fn parse_stmt(ctx: &mut CompCtx, _only_stmt: bool) -> ParserResult<StmtKey> {
     let lo = ctx.stream.span();
     let kind = if check_labeled_stmt(ctx) {
         // label
         parse_labeled_stmt(ctx)?
     } else if check(ctx, TokenKind::LBrace) {
-        // compound
-        parse_compound_stmt(ctx, only_stmt, true)?
+        // compound，内部总是可以有声明
+        parse_compound_stmt(ctx, false, true)?
     } else if check_selection_stmt(ctx) {
         //
         parse_selection_stmt(ctx)?
@@ -94,7 +97,7 @@ fn parse_labeled_stmt(ctx: &mut CompCtx) -> ParserResult<StmtKind> {
         let symbol = ident.kind.into_ident().unwrap();
         let ident = Ident { symbol, span };
 
-        let colon = expect(ctx, TokenKind::Colon)?.span.to_pos();
+        let _colon = expect(ctx, TokenKind::Colon)?.span.to_pos();
         let stmt = parse_stmt(ctx, false)?;
         StmtKind::Label { ident, stmt }
     } else if let Some(kw_case) = consume_keyword(ctx, Keyword::Case) {
@@ -134,35 +137,48 @@ pub(crate) fn parse_compound_stmt(
     only_stmt: bool,
     new_context: bool,
 ) -> ParserResult<StmtKind> {
-    // todo 符号表
-
     let l = expect(ctx, TokenKind::LBrace)?.span.to_pos();
+
+    if new_context {
+        ctx.scope_mgr.enter_block();
+    }
+    let stmts = parse_block_items(ctx, only_stmt);
+    if new_context {
+        ctx.scope_mgr.leave_block();
+    }
+    let stmts = stmts?;
+
+    let r = expect(ctx, TokenKind::RBrace)?.span.to_pos();
+
+    let kind = StmtKind::Compound { l, stmts, r };
+    Ok(kind)
+}
+
+/// 解析 `{` `}` 之间的语句和声明，不负责括号
+fn parse_block_items(ctx: &mut CompCtx, only_stmt: bool) -> ParserResult<Vec<StmtKey>> {
     let mut stmts = Vec::new();
     loop {
         let stmt = if check(ctx, TokenKind::RBrace) {
             break;
         } else if !only_stmt && check_decl(ctx) {
-            let lo = ctx.stream.span();
-            let decl = parse_decl(ctx)?;
-            let hi = ctx.stream.span();
-            let span = Span::span(lo, hi);
-
-            let kind = StmtKind::Decl { decl };
-            Stmt::new_key(ctx, kind, span)
+            parse_decl_stmt(ctx)?
         } else {
             parse_stmt(ctx, false)?
         };
         stmts.push(stmt);
     }
-    let r = expect(ctx, TokenKind::RBrace)?.span.to_pos();
+    Ok(stmts)
+}
 
-    // 符号表退出
-    let kind = StmtKind::Compound {
-        l,
-        stmts,
-        r,
-    };
-    Ok(kind)
+/// 声明语句，包括结尾的分号
+fn parse_decl_stmt(ctx: &mut CompCtx) -> ParserResult<StmtKey> {
+    let lo = ctx.stream.span();
+    let decl = parse_decl(ctx)?;
+    let hi = ctx.stream.prev_span();
+    let span = Span::span(lo, hi);
+
+    let kind = StmtKind::Decl { decl };
+    Ok(Stmt::new_key(ctx, kind, span))
 }
 
 fn parse_selection_stmt(ctx: &mut CompCtx) -> ParserResult<StmtKind> {
@@ -252,46 +268,71 @@ fn parse_iteration_stmt(ctx: &mut CompCtx) -> ParserResult<StmtKind> {
         }
     } else if let Some(for_token) = consume_keyword(ctx, Keyword::For) {
         // for(;;)
-        let for_span = for_token.span;
-        let l = expect(ctx, TokenKind::LParen)?.span.to_pos();
+        // for 语句自身是一个作用域，初始化的声明只在循环中可见
+        ctx.scope_mgr.enter_block();
+        let kind = parse_for_stmt(ctx, for_token.span);
+        ctx.scope_mgr.leave_block();
+        kind?
+    } else {
+        unreachable!()
+    };
 
-        let init = match check(ctx, TokenKind::Semi) {
-            true => Some(parse_expr(ctx)?),
-            false => None,
-        };
-        let semi1 = expect(ctx, TokenKind::Semi)?.span.to_pos();
-        let cond = match check(ctx, TokenKind::Semi) {
-            true => Some(parse_expr(ctx)?),
-            false => None,
-        };
-        let semi2 = expect(ctx, TokenKind::Semi)?.span.to_pos();
-        let step = match check(ctx, TokenKind::RParen) {
-            true => Some(parse_expr(ctx)?),
-            false => None,
-        };
-        let r = expect(ctx, TokenKind::RParen)?.span.to_pos();
-        let body = parse_stmt(ctx, true)?;
+    Ok(kind)
+}
 
-        StmtKind::For {
-            for_span,
-            l,
-            init,
-            semi1,
-            cond,
-            semi2,
-            step,
-            r,
-            body,
-        }
+/// 解析 `for` 关键字之后的部分
+fn parse_for_stmt(ctx: &mut CompCtx, for_span: Span) -> ParserResult<StmtKind> {
+    let l = expect(ctx, TokenKind::LParen)?.span.to_pos();
+
+    // 初始化部分是声明或表达式语句，都包括分号
+    let (init, semi1) = if check_decl(ctx) {
+        let init = parse_decl_stmt(ctx)?;
+        (Some(init), ctx.stream.prev_span().to_pos())
     } else {
-        unreachable!()
+        let lo = ctx.stream.span();
+        let expr = match check(ctx, TokenKind::Semi) {
+            true => None,
+            false => Some(parse_expr(ctx)?),
+        };
+        let semi = expect(ctx, TokenKind::Semi)?.span.to_pos();
+        let span = Span::span(lo, ctx.stream.prev_span());
+        let init = expr.map(|x| {
+            let kind = StmtKind::Expr {
+                expr: Some(x),
+                semi,
+            };
+            Stmt::new_key(ctx, kind, span)
+        });
+        (init, semi)
     };
+    let cond = match check(ctx, TokenKind::Semi) {
+        true => None,
+        false => Some(parse_expr(ctx)?),
+    };
+    let semi2 = expect(ctx, TokenKind::Semi)?.span.to_pos();
+    let step = match check(ctx, TokenKind::RParen) {
+        true => None,
+        false => Some(parse_expr(ctx)?),
+    };
+    let r = expect(ctx, TokenKind::RParen)?.span.to_pos();
+    let body = parse_stmt(ctx, true)?;
 
+    let kind = StmtKind::For {
+        for_span,
+        l,
+        init,
+        semi1,
+        cond,
+        semi2,
+        step,
+        r,
+        body,
+    };
     Ok(kind)
 }
 
 fn parse_jump_stmt(ctx: &mut CompCtx) -> ParserResult<StmtKind> {
-    let kind = if let Some(goto_token) = consume_keyword(ctx, Keyword::Goto) {
+    let kind = if let Some(_goto_token) = consume_keyword(ctx, Keyword::Goto) {
         // goto label;
         let ident = expect_ident(ctx)?;
         let span = ident.span;
@@ -299,9 +340,7 @@ fn parse_jump_stmt(ctx: &mut CompCtx) -> ParserResult<StmtKind> {
         let ident = Ident { span, symbol };
         let _ = expect(ctx, TokenKind::Semi)?;
 
-        StmtKind::Goto {
-            ident,
-        }
+        StmtKind::Goto { ident }
     } else if let Some(continue_token) = consume_keyword(ctx, Keyword::Continue) {
         // continue;
         let continue_span = continue_token.span;
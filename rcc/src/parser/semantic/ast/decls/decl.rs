@@ -3,7 +3,7 @@ use crate::parser::ast::decls::initializer::Initializer;
 use crate::parser::ast::{DeclKey, ExprKey, TypeKey};
 use crate::parser::semantic::ast::stmt::Stmt;
 use crate::parser::semantic::common::Ident;
-use crate::parser::semantic::decl_spec::{FuncSpec, StorageSpec};
+use crate::parser::semantic::decl_spec::{AlignSpec, FuncSpec, StorageSpec, VisibilitySpec};
 use crate::types::span::Span;
 use enum_as_inner::EnumAsInner;
 
@@ -13,6 +13,12 @@ pub struct InitializerList {
     pub span: Span,
 }
 
+impl Default for InitializerList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InitializerList {
     pub fn new() -> Self {
         Self {
@@ -25,6 +31,11 @@ impl InitializerList {
 #[derive(Debug, Clone)]
 pub struct Decl {
     pub storage: Option<StorageSpec>,
+    pub func_spec: Option<FuncSpec>,
+    pub noreturn: Option<FuncSpec>,
+    pub visibility: Option<VisibilitySpec>,
+    pub thread_local: Option<StorageSpec>,
+    pub align: Option<AlignSpec>,
     pub name: Option<Ident>,
     pub kind: DeclKind,
     pub ty: TypeKey,
@@ -50,6 +61,7 @@ pub enum DeclKind {
     },
     FuncDef {
         inline: Option<FuncSpec>,
+        params: Vec<DeclKey>, // ParamVar，保留参数名
         body: Box<Stmt>,
     },
 
@@ -103,21 +115,10 @@ impl Decl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DeclGroup {
     pub decls: Vec<DeclKey>,
     // pub commas: Vec<Pos>,
     // pub semi: Pos,
     pub span: Span,
 }
-
-impl Default for DeclGroup {
-    fn default() -> Self {
-        Self {
-            decls: Vec::new(),
-            // commas: Vec::new(),
-            // semi: Pos::default(),
-            span: Span::default(),
-        }
-    }
-}
@@ -1,8 +1,35 @@
 use crate::parser::ast::ExprKey;
 use crate::parser::ast::decls::decl::InitializerList;
+use crate::parser::ast::exprs::MemberDesignator;
+use crate::types::span::Span;
 
 #[derive(Debug, Clone)]
 pub enum Initializer {
     Expr(ExprKey),
-    InitList { inits: InitializerList },
+    InitList {
+        inits: InitializerList,
+    },
+    /// 只出现在初始化列表中 `.x = 1` `[2].y = 3`
+    Designated {
+        designators: Vec<MemberDesignator>,
+        init: Box<Initializer>,
+        span: Span,
+    },
+}
+
+impl Initializer {
+    /// 去掉指示符后的初始值
+    pub fn value(&self) -> &Initializer {
+        match self {
+            Initializer::Designated { init, .. } => init,
+            _ => self,
+        }
+    }
+
+    pub fn designators(&self) -> &[MemberDesignator] {
+        match self {
+            Initializer::Designated { designators, .. } => designators,
+            _ => &[],
+        }
+    }
 }
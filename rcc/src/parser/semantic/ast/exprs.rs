@@ -1,5 +1,7 @@
+mod builtin;
 mod op;
 mod expr;
 
+pub use builtin::*;
 pub use op::*;
 pub use expr::*;
@@ -0,0 +1,136 @@
+use crate::parser::ast::types::IntegerSize;
+use crate::parser::ast::{ExprKey, TypeKey};
+use crate::parser::semantic::common::Ident;
+use enum_as_inner::EnumAsInner;
+
+/// 编译器内建函数，DeclRef 在作用域中找不到名字时查 `BUILTINS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    VaStart,
+    VaArg,
+    VaEnd,
+    VaCopy,
+    Expect,
+    Unreachable,
+    Offsetof,
+    TypesCompatibleP,
+    ConstantP,
+    Popcount(IntegerSize),
+    Clz(IntegerSize),
+    Ctz(IntegerSize),
+    Memcpy,
+}
+
+///
+/// 内建函数参数的语法形式
+///
+/// # Members
+/// - `Expr`: 赋值表达式
+/// - `Type`: 类型名，`__builtin_va_arg` 的第二个参数
+/// - `Member`: 成员指示符，`__builtin_offsetof` 的第二个参数
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinParam {
+    Expr,
+    Type,
+    Member,
+}
+
+/// `__builtin_offsetof` 的成员指示符 `a.b[1].c`
+#[derive(Debug, Clone)]
+pub enum MemberDesignator {
+    Field(Ident),
+    Index(ExprKey),
+}
+
+#[derive(Debug, Clone, EnumAsInner)]
+pub enum BuiltinArg {
+    Expr(ExprKey),
+    Type(TypeKey),
+    Member(Vec<MemberDesignator>),
+}
+
+impl BuiltinArg {
+    /// 参数中的表达式，包括成员指示符中的下标
+    pub fn exprs(&self) -> Vec<ExprKey> {
+        match self {
+            BuiltinArg::Expr(x) => vec![*x],
+            BuiltinArg::Type(_) => Vec::new(),
+            BuiltinArg::Member(designators) => designators
+                .iter()
+                .filter_map(|x| match x {
+                    MemberDesignator::Index(x) => Some(*x),
+                    MemberDesignator::Field(_) => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 内建函数注册表，`l` `ll` 后缀对应参数类型的 long 和 long long 版本
+pub const BUILTINS: &[(&str, Builtin)] = &[
+    ("__builtin_va_start", Builtin::VaStart),
+    ("__builtin_va_arg", Builtin::VaArg),
+    ("__builtin_va_end", Builtin::VaEnd),
+    ("__builtin_va_copy", Builtin::VaCopy),
+    ("__builtin_expect", Builtin::Expect),
+    ("__builtin_unreachable", Builtin::Unreachable),
+    ("__builtin_offsetof", Builtin::Offsetof),
+    ("__builtin_types_compatible_p", Builtin::TypesCompatibleP),
+    ("__builtin_constant_p", Builtin::ConstantP),
+    ("__builtin_popcount", Builtin::Popcount(IntegerSize::Int)),
+    ("__builtin_popcountl", Builtin::Popcount(IntegerSize::Long)),
+    (
+        "__builtin_popcountll",
+        Builtin::Popcount(IntegerSize::LongLong),
+    ),
+    ("__builtin_clz", Builtin::Clz(IntegerSize::Int)),
+    ("__builtin_clzl", Builtin::Clz(IntegerSize::Long)),
+    ("__builtin_clzll", Builtin::Clz(IntegerSize::LongLong)),
+    ("__builtin_ctz", Builtin::Ctz(IntegerSize::Int)),
+    ("__builtin_ctzl", Builtin::Ctz(IntegerSize::Long)),
+    ("__builtin_ctzll", Builtin::Ctz(IntegerSize::LongLong)),
+    ("__builtin_memcpy", Builtin::Memcpy),
+];
+
+impl Builtin {
+    /// 按名字查找内建函数
+    pub fn lookup(name: &str) -> Option<Self> {
+        BUILTINS.iter().find(|(x, _)| *x == name).map(|(_, x)| *x)
+    }
+
+    pub fn name(self) -> &'static str {
+        BUILTINS
+            .iter()
+            .find(|(_, x)| *x == self)
+            .map(|(x, _)| *x)
+            .unwrap()
+    }
+
+    /// 第 `index` 个参数是否为 va_list
+    pub fn takes_va_list(self, index: usize) -> bool {
+        match self {
+            Builtin::VaStart | Builtin::VaArg | Builtin::VaEnd => index == 0,
+            Builtin::VaCopy => true,
+            _ => false,
+        }
+    }
+
+    /// 参数的语法形式，决定 parser 如何解析每个参数
+    pub fn params(self) -> &'static [BuiltinParam] {
+        use BuiltinParam::*;
+        match self {
+            Builtin::VaStart => &[Expr, Expr],
+            Builtin::VaArg => &[Expr, Type],
+            Builtin::VaEnd => &[Expr],
+            Builtin::VaCopy => &[Expr, Expr],
+            Builtin::Expect => &[Expr, Expr],
+            Builtin::Unreachable => &[],
+            Builtin::Offsetof => &[Type, Member],
+            Builtin::TypesCompatibleP => &[Type, Type],
+            Builtin::ConstantP => &[Expr],
+            Builtin::Popcount(_) | Builtin::Clz(_) | Builtin::Ctz(_) => &[Expr],
+            Builtin::Memcpy => &[Expr, Expr, Expr],
+        }
+    }
+}
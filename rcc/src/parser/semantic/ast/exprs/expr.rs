@@ -1,8 +1,9 @@
 use crate::err::parser_error::{ParserError, ParserResult};
 use crate::lex::types::token::Token;
 use crate::lex::types::token_kind::{LiteralKind, Symbol, TokenKind};
-use crate::parser::ast::exprs::{AssignOp, BinOp, UnaryOp, UnaryOpKind};
-use crate::parser::ast::{ExprKey, TypeKey};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{AssignOp, BinOp, Builtin, BuiltinArg, UnaryOp, UnaryOpKind};
+use crate::parser::ast::{DeclKey, ExprKey, TypeKey};
 use crate::parser::semantic::common::Ident;
 use crate::parser::semantic::sema::expr::value_type::ValueType;
 use crate::types::span::Span;
@@ -20,7 +21,10 @@ pub struct Expr {
 
 #[derive(Clone, Debug, EnumAsInner)]
 pub enum ExprKind {
-    DeclRef(Ident),
+    DeclRef {
+        ident: Ident,
+        decl: Option<DeclKey>, // sema 解析后引用的声明
+    },
     Literal(LiteralKind), // 字符串
     // Paren { l: Pos, expr: ExprKey, r: Pos }, no need to wrap
     ArraySubscript {
@@ -42,6 +46,9 @@ pub enum ExprKind {
     SizeofType {
         ty: TypeKey,
     }, // sizeof()
+    AlignofType {
+        ty: TypeKey,
+    }, // _Alignof()
     Unary {
         op: UnaryOp,
         rhs: ExprKey,
@@ -60,19 +67,31 @@ pub enum ExprKind {
         ty: TypeKey,
         expr: ExprKey,
     }, // (type)
+    CompoundLiteral {
+        ty: TypeKey,
+        init: Initializer,
+        file_scope: bool, // 文件作用域的是静态存储期，否则是自动存储期
+    }, // (type){...}
     Ternary {
         // cond ? a : b
         cond: ExprKey,
         then_expr: ExprKey,
         else_expr: ExprKey,
     },
+    BuiltinCall {
+        builtin: Builtin,
+        args: Vec<BuiltinArg>,
+    }, // __builtin_xxx()
 }
 
 impl ExprKind {
     pub fn make_decl_ref(ident: Token) -> Self {
         let span = ident.span;
         let symbol = ident.kind.into_ident().unwrap();
-        Self::DeclRef(Ident { symbol, span })
+        Self::DeclRef {
+            ident: Ident { symbol, span },
+            decl: None,
+        }
     }
 
     pub fn make_literal(token: Token) -> Self {
@@ -98,9 +117,7 @@ impl ExprKind {
         Self::ArraySubscript { base, index }
     }
 
-    pub fn make_call(base: ExprKey, l: Token, params: Parameter, r: Token) -> Self {
-        let l = l.span.to_pos();
-        let r = r.span.to_pos();
+    pub fn make_call(base: ExprKey, _l: Token, params: Parameter, _r: Token) -> Self {
         Self::Call { base, params }
     }
 
@@ -113,11 +130,15 @@ impl ExprKind {
         Self::MemberAccess { kind, base, field }
     }
 
-    pub fn make_size_of_type(sizeof: Token, l: Token, ty: TypeKey, r: Token) -> Self {
+    pub fn make_size_of_type(_sizeof: Token, _l: Token, ty: TypeKey, _r: Token) -> Self {
         Self::SizeofType { ty }
     }
 
-    pub fn make_size_of_expr(sizeof: Token, expr: ExprKey) -> Self {
+    pub fn make_align_of_type(_alignof: Token, _l: Token, ty: TypeKey, _r: Token) -> Self {
+        Self::AlignofType { ty }
+    }
+
+    pub fn make_size_of_expr(_sizeof: Token, expr: ExprKey) -> Self {
         Self::SizeofExpr { expr }
     }
 
@@ -157,10 +178,24 @@ impl ExprKind {
         Self::Binary { lhs, op, rhs }
     }
 
-    pub fn make_cast(l: Token, ty: TypeKey, r: Token, expr: ExprKey) -> Self {
+    pub fn make_cast(_l: Token, ty: TypeKey, _r: Token, expr: ExprKey) -> Self {
         Self::Cast { ty, expr }
     }
 
+    pub fn make_compound_literal(
+        _l: Token,
+        ty: TypeKey,
+        _r: Token,
+        init: Initializer,
+        file_scope: bool,
+    ) -> Self {
+        Self::CompoundLiteral {
+            ty,
+            init,
+            file_scope,
+        }
+    }
+
     pub fn make_assign(lhs: ExprKey, op: Token, rhs: ExprKey) -> Self {
         let op = AssignOp::new(op);
         Self::Assign { lhs, op, rhs }
@@ -168,9 +203,9 @@ impl ExprKind {
 
     pub fn make_ternary(
         cond: ExprKey,
-        question: Token,
+        _question: Token,
         then_expr: ExprKey,
-        colon: Token,
+        _colon: Token,
         else_expr: ExprKey,
     ) -> Self {
         Self::Ternary {
@@ -179,6 +214,10 @@ impl ExprKind {
             else_expr,
         }
     }
+
+    pub fn make_builtin_call(builtin: Builtin, args: Vec<BuiltinArg>) -> Self {
+        Self::BuiltinCall { builtin, args }
+    }
 }
 
 impl Expr {
@@ -192,7 +231,7 @@ impl Expr {
     }
 
     pub fn is_lvalue(&self) -> bool {
-        ValueType::value_type(self) == ValueType::LValue
+        ValueType::of(self) == ValueType::LValue
     }
 
     pub fn should_int_constant(&self) -> ParserResult<APInt> {
@@ -227,6 +266,12 @@ pub struct Parameter {
     pub exprs: Vec<ExprKey>,
 }
 
+impl Default for Parameter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Parameter {
     pub fn new() -> Self {
         Self {
@@ -1,8 +1,9 @@
 use crate::lex::types::token::Token;
 use crate::lex::types::token_kind::TokenKind;
 use crate::types::span::Span;
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AssignOpKind {
     Assign,
     PlusEq,
@@ -45,7 +46,7 @@ impl AssignOp {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinOpKind {
     Plus, Minus, Mul, Div, Mod,
     BitAnd, BitOr, BitXor,
@@ -90,7 +91,7 @@ impl BinOp {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOpKind {
     AddrOf,
     Deref,
@@ -125,4 +126,75 @@ impl UnaryOp {
         let span = token.span;
         Self { kind, span }
     }
-}
\ No newline at end of file
+}
+impl Display for AssignOpKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use AssignOpKind::*;
+        let str = match self {
+            Assign => "=",
+            PlusEq => "+=",
+            MinusEq => "-=",
+            StarEq => "*=",
+            SlashEq => "/=",
+            PercentEq => "%=",
+            ShlEq => "<<=",
+            ShrEq => ">>=",
+            AmpEq => "&=",
+            CaretEq => "^=",
+            PipeEq => "|=",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl Display for BinOpKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use BinOpKind::*;
+        let str = match self {
+            Plus => "+",
+            Minus => "-",
+            Mul => "*",
+            Div => "/",
+            Mod => "%",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor | Xor => "^",
+            Shl => "<<",
+            Shr => ">>",
+            Lt => "<",
+            Gt => ">",
+            Eq => "==",
+            Ne => "!=",
+            Le => "<=",
+            Ge => ">=",
+            And => "&&",
+            Or => "||",
+            Comma => ",",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl Display for UnaryOpKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use UnaryOpKind::*;
+        let str = match self {
+            AddrOf => "&",
+            Deref => "*",
+            Plus => "+",
+            Minus => "-",
+            Not => "!",
+            BitNot => "~",
+            PostInc | PreInc => "++",
+            PostDec | PreDec => "--",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl UnaryOpKind {
+    /// 是否为后缀运算符 `a++` `a--`
+    pub fn is_postfix(&self) -> bool {
+        matches!(self, UnaryOpKind::PostInc | UnaryOpKind::PostDec)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::parser::ast::{DeclKey, StmtKey};
-use crate::parser::semantic::ast::decl::{DeclGroup};
+use crate::parser::semantic::ast::decls::decl::DeclGroup;
 use crate::parser::semantic::declarator::Declarator;
 use crate::types::span::Span;
 
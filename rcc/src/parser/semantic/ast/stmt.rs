@@ -1,5 +1,5 @@
 pub(crate) use crate::parser::ast::{ExprKey, StmtKey};
-use crate::parser::semantic::ast::decl::DeclGroup;
+use crate::parser::semantic::ast::decls::decl::DeclGroup;
 use crate::parser::semantic::common::Ident;
 use crate::parser::semantic::comp_ctx::CompCtx;
 use crate::types::span::{Pos, Span};
@@ -66,10 +66,10 @@ pub enum StmtKind {
     },
     For {
         // for ( init; cond; step ) stmt
-        for_span: Span, // for
-        l: Pos,         // (
-        init: Option<ExprKey>,
-        semi1: Pos, // init ;
+        for_span: Span,        // for
+        l: Pos,                // (
+        init: Option<StmtKey>, // Expr 或者 Decl 语句
+        semi1: Pos,            // init ;
         cond: Option<ExprKey>,
         semi2: Pos,            // cond ;
         step: Option<ExprKey>, // step
@@ -10,4 +10,5 @@ pub use type_struct::*;
 pub use qualifier::*;
 pub use primitives::*;
 pub use record::*;
+pub use layout::*;
 
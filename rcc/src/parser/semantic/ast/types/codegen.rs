@@ -1,5 +1,7 @@
 use crate::parser::{
-    ast::types::{Qualifier, Type},
+    ast::common::RecordKind,
+    ast::DeclKey,
+    ast::types::{ArraySize, IntegerSize, Qualifier, Type, TypeKind},
     semantic::comp_ctx::CompCtx,
 };
 
@@ -22,81 +24,128 @@ impl Qualifier {
     }
 }
 
+impl ArraySize {
+    pub fn to_code(&self) -> String {
+        match self {
+            ArraySize::Static(x) => format!("[{}]", x),
+            ArraySize::VLA => "[*]".to_owned(),
+            ArraySize::Incomplete => "[]".to_owned(),
+        }
+    }
+}
+
 impl Type {
+    /// 类型名（抽象声明符），如 `int (*)[10]`
     pub fn to_code(&self, ctx: &CompCtx) -> String {
-        // 抽到外面
-        let mut code = String::new();
-        let qual = self.qual.to_code();
+        self.to_decl_code(ctx, "")
+    }
+
+    /// 带名字的声明，如 `int (*name)[10]`，`name` 为空时等价于 `to_code`
+    pub fn to_decl_code(&self, ctx: &CompCtx, name: &str) -> String {
+        let (base, declarator) = self.declarator_code(ctx, name);
+        let base = base.base_code(ctx);
+        if declarator.is_empty() {
+            base
+        } else {
+            format!("{} {}", base, declarator)
+        }
+    }
 
-        code.push_str(&qual);
+    /// 拆分为基础类型和声明符，如 `int (*name)[10]` 拆成 `int` 和 `(*name)[10]`
+    ///
+    /// C 的声明符是 “由内向外” 的：指针写在名字左边，数组和函数写在右边，
+    /// 所以从最外层类型开始，一层层把 declarator 包起来，最后剩下基础类型
+    pub fn declarator_code<'a>(&'a self, ctx: &'a CompCtx, name: &str) -> (&'a Type, String) {
+        use TypeKind::*;
+        let mut declarator = name.to_owned();
+        let mut ty = self;
 
-        todo!()
-        // match &self.kind {
-        //     TypeKind::Void => code.push_str("void "),
-        //     TypeKind::Integer{ is_signed, size } => {
-        //         if *is_signed {
-        //             code.push_str("signed ");
-        //         } else {
-        //             code.push_str("unsigned ");
-        //         }
-        //         code.push_str(size.to_code());
-        //         code.push(' ');
-        //     },
-        //     TypeKind::Floating{ size } => {
-        //         code.push_str(size.to_code());
-        //         code.push(' ');
-        //     }
-        //     TypeKind::Pointer{ elem_ty } => {
-        //         code.push('*');
-        //         let elem_code = ctx.type_ctx.get_type(*elem_ty).to_code(ctx);
-        //         code.push_str(elem_code.as_str());
-        //         code.push(' ');
-        //     }
-        //     TypeKind::Array{ size, elem_ty } => {
-        //         let elem_code = ctx.type_ctx.get_type(*elem_ty).to_code(ctx);
-        //         code.push_str(elem_code.as_str());
-        //         code.push_str(&size.to_code());
-        //         code.push(' ');
-        //     }
-        //     TypeKind::Function{ ret_ty, params, is_variadic } => {
-        //         code.push_str("fn ");
-        //         let param = params.iter()
-        //             .map(|x| ctx.type_ctx.get_type(*x).to_code(ctx))
-        //             .collect::<Vec<_>>()
-        //             .join(",");
-        //         let variadic = is_variadic.then(|| ",...").unwrap_or_default();
-        //         code.push_str(&format!("({}{})", param, variadic));
-        //         code.push_str(" -> ");
-        //         let ret_code = ctx.get_type(*ret_ty).to_code(ctx);
-        //         code.push_str(ret_code.as_str());
-        //         code.push(' ');
-        //     }
-        //     TypeKind::Record{ name, fields, .. } => {
-        //         let name = name.as_ref().map(|x| x.symbol.get()).unwrap_or_default();
-        //         let fields: String = fields.iter().map(|x| x.to_code(ctx)).collect();
-        //         code.push_str("struct ");
-        //         code.push_str(name);
-        //         code.push('{');
-        //         code.push_str(&fields);
-        //         code.push('}');
-        //         code.push(' ');
-        //     }
-        //     TypeKind::Enum{ name, fields, .. } => {
-        //         let name = name.as_ref().map(|x| x.symbol.get()).unwrap_or_default();
-        //         let fields = fields.iter()
-        //             .map(|x| x.to_code())
-        //             .collect::<Vec<_>>()
-        //             .join(",");
-        //         code.push_str("union ");
-        //         code.push_str(name);
-        //         code.push('{');
-        //         code.push_str(&fields);
-        //         code.push('}');
-        //         code.push(' ');
-        //     }
-        //     TypeKind::Unknown => {
-        //         code.push_str("$ERROR$");
-        //     }
-        // }
+        loop {
+            match &ty.kind {
+                Pointer { elem_ty } => {
+                    // 指针的限定符写在 * 右边，如 `int *const p`
+                    let qual = ty.qual.to_code();
+                    declarator = format!("*{}{}", qual, declarator);
+                    ty = ctx.type_ctx.get_type(*elem_ty);
+                }
+                Array { elem_ty, size } => {
+                    // 指向数组的指针需要括号 `int (*p)[10]`
+                    if declarator.starts_with('*') {
+                        declarator = format!("({})", declarator.trim_end());
+                    }
+                    declarator.push_str(&size.to_code());
+                    ty = ctx.type_ctx.get_type(*elem_ty);
+                }
+                Function {
+                    ret_ty,
+                    params,
+                    is_variadic,
+                    has_prototype,
+                } => {
+                    if declarator.starts_with('*') {
+                        declarator = format!("({})", declarator.trim_end());
+                    }
+                    let mut params: Vec<_> = params
+                        .iter()
+                        .map(|x| ctx.type_ctx.get_type(*x).to_code(ctx))
+                        .collect();
+                    if *is_variadic {
+                        params.push("...".to_owned());
+                    }
+                    // `f()` 没有原型，`f(void)` 有
+                    if params.is_empty() && *has_prototype {
+                        params.push("void".to_owned());
+                    }
+                    declarator.push_str(&format!("({})", params.join(", ")));
+                    ty = ctx.type_ctx.get_type(*ret_ty);
+                }
+                _ => break,
+            }
+        }
+
+        (ty, declarator.trim_end().to_owned())
     }
+
+    /// 声明说明符部分（带限定符），如 `const unsigned int` `struct A`
+    pub fn base_code(&self, ctx: &CompCtx) -> String {
+        use TypeKind::*;
+        let mut code = self.qual.to_code();
+
+        match &self.kind {
+            Void => code.push_str("void"),
+            Integer { is_signed, size } => {
+                // 普通 char 在 TypeCtx 中就是 signed char，这里不再区分
+                if !*is_signed && *size != IntegerSize::Bool {
+                    code.push_str("unsigned ");
+                }
+                code.push_str(&size.to_string());
+            }
+            Floating { size } => code.push_str(&size.to_string()),
+            Record { kind, id, def } => {
+                let kw = match kind {
+                    RecordKind::Struct => "struct",
+                    RecordKind::Union => "union",
+                };
+                code.push_str(kw);
+                code.push(' ');
+                code.push_str(&tag_name(ctx, *def, id.0));
+            }
+            Enum { id, def } => {
+                code.push_str("enum ");
+                code.push_str(&tag_name(ctx, *def, id.0));
+            }
+            Unknown => code.push_str("$ERROR$"),
+            Pointer { .. } | Array { .. } | Function { .. } => {
+                unreachable!("derived type is handled by declarator")
+            }
+        }
+
+        code
+    }
+}
+
+/// tag 名字，匿名 tag 使用 `(anonymous#id)` 表示
+fn tag_name(ctx: &CompCtx, def: Option<DeclKey>, id: usize) -> String {
+    def.and_then(|x| ctx.get_decl(x).name.as_ref().map(|x| x.symbol.get().to_owned()))
+        .unwrap_or_else(|| format!("(anonymous#{})", id))
 }
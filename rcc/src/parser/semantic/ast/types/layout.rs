@@ -1,5 +1,14 @@
-use crate::{constant::typ::{DEFAULT_ALIGN, DEFAULT_SIZE}, parser::{ast::types::Type, comp_ctx::CompCtx}};
+use crate::constant::typ::{DEFAULT_ALIGN, DEFAULT_SIZE};
+use crate::lex::types::token_kind::Symbol;
+use crate::parser::ast::common::RecordKind;
+use crate::parser::ast::decls::decl::{DeclGroup, DeclKind};
+use crate::parser::ast::types::{ArraySize, Type, TypeKind};
+use crate::parser::ast::{DeclKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::sema::expr::const_eval::eval_int;
+use backend::target::TargetInfo;
 
+/// 类型的大小和对齐，`long` 和指针的大小来自 `ctx.target`
 #[derive(Debug, Clone)]
 pub struct TypeLayout {
     pub size: usize,
@@ -8,41 +17,234 @@ pub struct TypeLayout {
 
 impl TypeLayout {
     pub fn new(ctx: &CompCtx, ty: &Type) -> Self {
-        let size = Self::sizeof(ty);
+        let size = Self::sizeof(ctx, ty);
         let align = Self::alignof(ctx, ty).unwrap_or(DEFAULT_ALIGN);
 
         Self { size, align }
     }
 
+    /// 通过 key 计算
+    pub fn of(ctx: &CompCtx, ty: TypeKey) -> Self {
+        ctx.type_ctx.get_type(ty).layout(ctx).clone()
+    }
+
+    /// 指针的大小和对齐，都是目标的指针宽度
+    pub fn pointer(target: &TargetInfo) -> Self {
+        let bytes = target.ptr_bytes as usize;
+        Self {
+            size: bytes,
+            align: bytes,
+        }
+    }
 
-    // todo: type is immutable, that should store in typestruct 
     pub fn alignof(ctx: &CompCtx, ty: &Type) -> Option<usize> {
         use crate::parser::semantic::ast::types::type_struct::TypeKind::*;
         match &ty.kind {
-            Void | Unknown  => None,
-            Integer{ .. } => Some(4),
-            Floating{ .. } => Some(4),
-            Pointer{ .. } => Some(8),
-            Array{ size, elem_ty } =>
-                todo!(),
-            Function{ .. } => Some(8),
-            Record {..} => todo!(),
-            Enum{ .. } => Some(8),
+            Void | Unknown => None,
+            Integer { size, .. } => Some(size.sizeof(&ctx.target)),
+            Floating { size } => Some(size.alignof(&ctx.target)),
+            Pointer { .. } => Some(Self::pointer(&ctx.target).align),
+            Array { elem_ty, .. } => Self::alignof(ctx, ctx.type_ctx.get_type(*elem_ty)),
+            Function { .. } => Some(1),
+            Record { def, .. } => def.and_then(|x| RecordLayout::new(ctx, x)).map(|x| x.align),
+            Enum { .. } => Some(4),
         }
     }
 
-    pub fn sizeof(ty: &Type) -> usize {
+    pub fn sizeof(ctx: &CompCtx, ty: &Type) -> usize {
         use super::TypeKind::*;
         match &ty.kind {
             Void => 1,
-            Integer { size, .. } => size.sizeof(),
-            Floating { size, .. } => size.sizeof(),
-            Pointer { .. } => 8,
-            Array { size, elem_ty } => todo!(),
+            Integer { size, .. } => size.sizeof(&ctx.target),
+            Floating { size, .. } => size.sizeof(&ctx.target),
+            Pointer { .. } => Self::pointer(&ctx.target).size,
+            Array { size, elem_ty } => match size {
+                ArraySize::Static(n) => n * ctx.type_ctx.get_type(*elem_ty).layout(ctx).size,
+                // 不完整数组和 VLA 没有编译期大小
+                ArraySize::Incomplete | ArraySize::VLA => 0,
+            },
             Function { .. } => 1,
-            Record { .. } => todo!(), 
+            Record { def, .. } => def
+                .and_then(|x| RecordLayout::new(ctx, x))
+                .map(|x| x.size)
+                .unwrap_or(DEFAULT_SIZE),
             Enum { .. } => 4,
-            Unknown => return DEFAULT_SIZE,
+            Unknown => DEFAULT_SIZE,
+        }
+    }
+}
+
+///
+/// 位域在存储单元中的位置
+///
+/// # Members
+/// - `bit_offset`: 存储单元中的起始位，从低位开始
+/// - `width`: 位宽
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFieldLayout {
+    pub bit_offset: usize,
+    pub width: usize,
+}
+
+///
+/// record 成员的位置
+///
+/// # Members
+/// - `decl`: 成员的声明
+/// - `name`: 成员名，匿名成员为 None
+/// - `ty`: 成员类型
+/// - `offset`: 字节偏移，位域为所在存储单元的偏移，存储单元的大小是成员类型的大小
+/// - `bit_field`: 位域
+///
+#[derive(Debug, Clone)]
+pub struct FieldLayout {
+    pub decl: DeclKey,
+    pub name: Option<Symbol>,
+    pub ty: TypeKey,
+    pub offset: usize,
+    pub bit_field: Option<BitFieldLayout>,
+}
+
+///
+/// struct / union 的布局
+///
+/// 位域按 System V 的规则分配：位域不跨越与成员类型对齐的存储单元，
+/// 放不下时从下一个存储单元开始，宽度为 0 的位域把位置对齐到下一个存储单元
+///
+/// # Members
+/// - `kind`: struct 或 union
+/// - `size` `align`: 大小和对齐
+/// - `fields`: 有名字的成员和匿名 record 成员，按声明顺序，不包括宽度为 0 的位域
+///
+#[derive(Debug, Clone)]
+pub struct RecordLayout {
+    pub kind: RecordKind,
+    pub size: usize,
+    pub align: usize,
+    pub fields: Vec<FieldLayout>,
+}
+
+fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// 找到 record 的定义，`def` 可以是 `RecordDecl` 或 `RecordDef`
+fn record_def(ctx: &CompCtx, def: DeclKey) -> Option<(RecordKind, &Vec<DeclGroup>)> {
+    match &ctx.get_decl(def).kind {
+        DeclKind::RecordDef { kind, fields } => Some((kind.kind, fields)),
+        DeclKind::RecordDecl { def: Some(x), .. } => record_def(ctx, *x),
+        _ => None,
+    }
+}
+
+impl RecordLayout {
+    /// 计算 record 的布局，不完整类型返回 None
+    pub fn new(ctx: &CompCtx, def: DeclKey) -> Option<Self> {
+        let (kind, groups) = record_def(ctx, def)?;
+        let is_union = kind == RecordKind::Union;
+
+        let mut fields = Vec::new();
+        let mut bit_pos = 0; // 下一个可用的位
+        let mut size = 0;
+        let mut align = 1;
+
+        let decls = groups.iter().flat_map(|x| x.decls.iter().cloned());
+        for key in decls {
+            let decl = ctx.get_decl(key);
+            let bit_width = match &decl.kind {
+                DeclKind::RecordField { bit_field } => bit_field.map(|x| eval_int(ctx, x).unwrap_or(0) as usize),
+                _ => continue, // 嵌套的 tag 声明
+            };
+            let ty = ctx.type_ctx.get_type(decl.ty);
+            let layout = ty.layout(ctx);
+            let unit_bits = layout.size * 8;
+            if is_union {
+                bit_pos = 0;
+            }
+
+            let field = match bit_width {
+                // 宽度为 0 的位域只影响后面成员的位置
+                Some(0) => {
+                    bit_pos = align_to(bit_pos, unit_bits.max(8));
+                    continue;
+                }
+                Some(width) => {
+                    // 跨越存储单元时移动到下一个存储单元
+                    if bit_pos / unit_bits != (bit_pos + width - 1) / unit_bits {
+                        bit_pos = align_to(bit_pos, unit_bits);
+                    }
+                    let offset = bit_pos / unit_bits * layout.size;
+                    let bit_offset = bit_pos - offset * 8;
+                    bit_pos += width;
+                    // 有名字的位域才影响 record 的对齐
+                    if decl.name.is_some() {
+                        align = align.max(layout.align);
+                    }
+                    let bit_field = Some(BitFieldLayout { bit_offset, width });
+                    FieldLayout {
+                        decl: key,
+                        name: decl.name.as_ref().map(|x| x.symbol),
+                        ty: decl.ty,
+                        offset,
+                        bit_field,
+                    }
+                }
+                None => {
+                    // `_Alignas` 只会让成员的对齐更严格
+                    let field_align = layout.align.max(decl.align.map_or(0, |x| x.align));
+                    let offset = align_to(bit_pos.div_ceil(8), field_align);
+                    bit_pos = (offset + layout.size) * 8;
+                    align = align.max(field_align);
+                    FieldLayout {
+                        decl: key,
+                        name: decl.name.as_ref().map(|x| x.symbol),
+                        ty: decl.ty,
+                        offset,
+                        bit_field: None,
+                    }
+                }
+            };
+            size = size.max(bit_pos.div_ceil(8));
+            // 匿名位域只占位置
+            if field.name.is_some() || field.bit_field.is_none() {
+                fields.push(field);
+            }
+        }
+
+        Some(Self {
+            kind,
+            size: align_to(size, align),
+            align,
+            fields,
+        })
+    }
+
+    /// 通过 record 类型计算
+    pub fn of(ctx: &CompCtx, ty: TypeKey) -> Option<Self> {
+        match &ctx.type_ctx.get_type(ty).kind {
+            TypeKind::Record { def, .. } => Self::new(ctx, (*def)?),
+            _ => None,
+        }
+    }
+
+    /// 按名字查找成员，会查找匿名 struct / union 成员，返回的偏移相对于当前 record
+    pub fn find_field(&self, ctx: &CompCtx, name: Symbol) -> Option<FieldLayout> {
+        for field in self.fields.iter() {
+            match field.name {
+                Some(x) if x == name => return Some(field.clone()),
+                Some(_) => {}
+                None => {
+                    let Some(inner) = Self::of(ctx, field.ty) else {
+                        continue;
+                    };
+                    if let Some(mut x) = inner.find_field(ctx, name) {
+                        x.offset += field.offset;
+                        return Some(x);
+                    }
+                }
+            }
         }
+        None
     }
 }
@@ -1,8 +1,10 @@
+use backend::target::TargetInfo;
 use enum_as_inner::EnumAsInner;
 use std::fmt::Display;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy, Hash)]
 pub enum IntegerSize {
+    Bool,
     Char,
     Short,
     Int,
@@ -14,6 +16,7 @@ impl Display for IntegerSize {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use IntegerSize::*;
         let str = match self {
+            Bool => "_Bool",
             Char => "char",
             Short => "short",
             Int => "int",
@@ -25,9 +28,13 @@ impl Display for IntegerSize {
 }
 
 impl IntegerSize {
+    /// 普通的 `char` 在所有目标上都有符号
+    pub const CHAR_SIGNED: bool = true;
+
     pub fn rank(self) -> usize {
         use IntegerSize::*;
         match self {
+            Bool => 0x0,
             Char => 0x1,
             Short => 0x2,
             Int => 0x3,
@@ -36,13 +43,14 @@ impl IntegerSize {
         }
     }
 
-    pub fn sizeof(self) -> usize {
+    /// `long` 的大小由目标决定（LP64 为 8，ILP32 为 4）
+    pub fn sizeof(self, target: &TargetInfo) -> usize {
         use IntegerSize::*;
         match self {
-            Char => 1,
+            Bool | Char => 1,
             Short => 2,
             Int => 4,
-            Long => 8,
+            Long => target.long_bytes as usize,
             LongLong => 8,
         }
     }
@@ -78,14 +86,28 @@ impl FloatSize {
         }
     }
 
-    pub fn sizeof(self) -> usize {
+    /// `long double` 的布局由目标的 ABI 决定：x86-64 是 x87 扩展精度，riscv64 是四精度，
+    /// 都占 16 字节；wasm32 和 `double` 相同
+    pub fn sizeof(self, target: &TargetInfo) -> usize {
         use FloatSize::*;
         match self {
             Float => 4,
             Double => 8,
-            LongDouble => 8,
+            LongDouble => target.long_double_bytes as usize,
         }
     }
+
+    pub fn alignof(self, target: &TargetInfo) -> usize {
+        match self {
+            FloatSize::LongDouble => target.long_double_align as usize,
+            _ => self.sizeof(target),
+        }
+    }
+
+    /// IR 没有扩展精度，只有和 `double` 布局相同的 `long double` 可以按 `double` 计算
+    pub fn has_value(self, target: &TargetInfo) -> bool {
+        self != FloatSize::LongDouble || self.sizeof(target) == FloatSize::Double.sizeof(target)
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Copy, EnumAsInner)]
@@ -1,6 +1,6 @@
 use crate::parser::semantic::decl_spec::TypeQuals;
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
 pub struct Qualifier {
     pub is_const: bool,
     pub is_volatile: bool,
@@ -16,13 +16,3 @@ impl Qualifier {
         }
     }
 }
-
-impl Default for Qualifier {
-    fn default() -> Self {
-        Self {
-            is_const: false,
-            is_volatile: false,
-            is_restrict: false,
-        }
-    }
-}
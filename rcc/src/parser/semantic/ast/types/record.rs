@@ -23,12 +23,10 @@ impl RecordField {
         // todo 抽到外面
         let mut code = String::new();
 
-        let ty = ctx.type_ctx.get_type(self.ty).to_code(ctx);
         let name = self.name.as_ref().map(|x| x.get()).unwrap_or_default();
+        let ty = ctx.type_ctx.get_type(self.ty).to_decl_code(ctx, name);
 
         code.push_str(&ty);
-        code.push(' ');
-        code.push_str(name);
 
         match self.bit_field.map(|x| x.to_string()) {
             None => {}
@@ -54,6 +52,6 @@ impl Eq for RecordField {}
 impl Hash for RecordField {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
-        &self.ty.hash(state);
+        self.ty.hash(state);
     }
 }
@@ -1,4 +1,4 @@
-use crate::parser::ast::types::{Type, TypeKind};
+use crate::parser::ast::types::{IntegerSize, Type, TypeKind};
 use crate::parser::semantic::comp_ctx::CompCtx;
 
 impl Type {
@@ -35,4 +35,26 @@ impl Type {
     pub fn is_integer(&self) -> bool {
         self.kind.is_integer()
     }
+
+    /// `_Bool`，转换为它时结果是操作数是否不为 0，而不是截断
+    pub fn is_bool(&self) -> bool {
+        matches!(
+            self.kind,
+            TypeKind::Integer {
+                size: IntegerSize::Bool,
+                ..
+            }
+        )
+    }
+
+    /// 整数类型的符号和位宽，enum 按 int 处理
+    pub fn int_info(&self, ctx: &CompCtx) -> Option<(bool, usize)> {
+        match &self.kind {
+            TypeKind::Integer { is_signed, size } => {
+                Some((*is_signed, size.sizeof(&ctx.target) * 8))
+            }
+            TypeKind::Enum { .. } => Some((true, 32)),
+            _ => None,
+        }
+    }
 }
@@ -54,8 +54,22 @@ impl Type {
         }
     }
 
+    /// 设置 record / enum 的定义，定义之前计算的 layout 失效
+    pub fn set_def(&mut self, decl: DeclKey) {
+        match &mut self.kind {
+            TypeKind::Record { def, .. } | TypeKind::Enum { def, .. } => *def = Some(decl),
+            _ => return,
+        }
+        self.layout = OnceLock::new();
+    }
+
     /// 获取 layout
     pub fn get_layout(&mut self, ctx: &CompCtx) -> &TypeLayout {
+        self.layout(ctx)
+    }
+
+    /// 获取 layout，第一次调用时计算
+    pub fn layout(&self, ctx: &CompCtx) -> &TypeLayout {
         self.layout.get_or_init(|| TypeLayout::new(ctx, self))
     }
 }
@@ -81,6 +95,7 @@ pub enum TypeKind {
         ret_ty: TypeKey,
         params: Vec<TypeKey>,
         is_variadic: bool,
+        has_prototype: bool, // `f()` 没有原型，`f(void)` 有
     },
     Record {
         kind: RecordKind,
@@ -1,9 +1,257 @@
+use crate::parser::ast::decls::decl::{Decl, DeclGroup, DeclKind};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{ExprKind, MemberDesignator};
+use crate::parser::ast::func::{ExternalDecl, FuncDef, TranslationUnit};
+use crate::parser::ast::stmt::{Stmt, StmtKind};
 use crate::parser::ast::{DeclKey, ExprKey, StmtKey};
-use crate::parser::ast::func::FuncDef;
-use crate::parser::semantic::ast::decl::DeclGroup;
-use crate::parser::semantic::ast::func::{ExternalDecl, TranslationUnit};
+use crate::parser::comp_ctx::CompCtx;
+
+///
+/// 只读遍历 AST
+///
+/// - `visit_*` 是访问入口，默认调用 `walk_*`，重写后需要自己决定是否继续 `walk_*`
+/// - `walk_*` 默认实现覆盖所有 `DeclKind` `StmtKind` `ExprKind`，按源码顺序访问子节点
+/// - `pre_*` 在访问子节点前调用，返回 `false` 跳过子节点（`post_*` 也不会调用）
+/// - `post_*` 在访问子节点后调用
+///
+/// 节点都在 `CompCtx` 中，`ctx()` 返回的引用生命周期与 `self` 无关，
+/// 所以遍历时不需要 clone 节点
+///
+pub trait Visitor<'a> {
+    fn ctx(&self) -> &'a CompCtx;
+
+    fn walk_translation_unit(&mut self, unit: &'a TranslationUnit) {
+        for ext_decl in unit {
+            self.walk_external_decl(ext_decl);
+        }
+    }
+
+    fn walk_external_decl(&mut self, decl: &'a ExternalDecl) {
+        match decl {
+            ExternalDecl::FunctionDefinition(x) => self.walk_func_def(x),
+            ExternalDecl::Declaration(x) => self.walk_decl_group(x),
+        }
+    }
+
+    fn walk_func_def(&mut self, decl: &'a FuncDef) {
+        self.visit_decl(decl.decl);
+        // 函数体已经挂在 DeclKind::FuncDef 上时不再重复访问
+        if !self.ctx().get_decl(decl.decl).kind.is_func_def() {
+            self.visit_stmt(decl.body);
+        }
+    }
+
+    fn walk_decl_group(&mut self, decl_group: &'a DeclGroup) {
+        for x in decl_group.decls.iter().cloned() {
+            self.visit_decl(x);
+        }
+    }
+
+    fn visit_decl(&mut self, decl: DeclKey) {
+        self.walk_decl(decl);
+    }
+
+    fn visit_expr(&mut self, expr: ExprKey) {
+        self.walk_expr(expr);
+    }
+
+    fn visit_stmt(&mut self, stmt: StmtKey) {
+        self.walk_stmt(stmt);
+    }
+
+    fn visit_initializer(&mut self, init: &'a Initializer) {
+        self.walk_initializer(init);
+    }
+
+    /// 函数体不在 stmt 池中，没有 key，也就没有 `pre_stmt` `post_stmt`
+    fn visit_func_body(&mut self, body: &'a Stmt) {
+        self.walk_stmt_kind(&body.kind);
+    }
+
+    fn pre_decl(&mut self, _decl: DeclKey) -> bool {
+        true
+    }
+
+    fn post_decl(&mut self, _decl: DeclKey) {}
+
+    fn pre_expr(&mut self, _expr: ExprKey) -> bool {
+        true
+    }
+
+    fn post_expr(&mut self, _expr: ExprKey) {}
+
+    fn pre_stmt(&mut self, _stmt: StmtKey) -> bool {
+        true
+    }
+
+    fn post_stmt(&mut self, _stmt: StmtKey) {}
+
+    fn walk_decl(&mut self, key: DeclKey) {
+        if !self.pre_decl(key) {
+            return;
+        }
+        let decl = self.ctx().get_decl(key);
+        self.walk_decl_kind(decl);
+        self.post_decl(key);
+    }
+
+    fn walk_decl_kind(&mut self, decl: &'a Decl) {
+        use DeclKind::*;
+        match &decl.kind {
+            TypeDef | ParamVar => {}
+            VarDecl { .. } | FuncDecl { .. } | RecordDecl { .. } | EnumDecl { .. } => {}
+            VarDef { init } => init.iter().for_each(|x| self.visit_initializer(x)),
+            FuncDef { params, body, .. } => {
+                params.iter().for_each(|x| self.visit_decl(*x));
+                self.visit_func_body(body);
+            }
+            RecordField { bit_field } => bit_field.iter().for_each(|x| self.visit_expr(*x)),
+            RecordDef { fields, .. } => fields.iter().for_each(|x| self.walk_decl_group(x)),
+            EnumField { expr } => expr.iter().for_each(|x| self.visit_expr(*x)),
+            EnumDef { enums } => enums.iter().flatten().for_each(|x| self.visit_decl(*x)),
+        }
+    }
+
+    fn walk_initializer(&mut self, init: &'a Initializer) {
+        match init {
+            Initializer::Expr(x) => self.visit_expr(*x),
+            Initializer::InitList { inits } => {
+                inits.inits.iter().for_each(|x| self.visit_initializer(x))
+            }
+            Initializer::Designated {
+                designators, init, ..
+            } => {
+                designators.iter().for_each(|x| {
+                    if let MemberDesignator::Index(expr) = x {
+                        self.visit_expr(*expr)
+                    }
+                });
+                self.visit_initializer(init);
+            }
+        }
+    }
+
+    fn walk_expr(&mut self, key: ExprKey) {
+        if !self.pre_expr(key) {
+            return;
+        }
+        let expr = self.ctx().get_expr(key);
+        self.walk_expr_kind(&expr.kind);
+        self.post_expr(key);
+    }
+
+    fn walk_expr_kind(&mut self, kind: &'a ExprKind) {
+        use ExprKind::*;
+        match kind {
+            DeclRef { .. } | Literal(_) | SizeofType { .. } | AlignofType { .. } => {}
+            ArraySubscript { base, index } => {
+                self.visit_expr(*base);
+                self.visit_expr(*index);
+            }
+            Call { base, params } => {
+                self.visit_expr(*base);
+                params.exprs.iter().for_each(|x| self.visit_expr(*x));
+            }
+            MemberAccess { base, .. } => self.visit_expr(*base),
+            SizeofExpr { expr } => self.visit_expr(*expr),
+            Unary { rhs, .. } => self.visit_expr(*rhs),
+            Binary { lhs, rhs, .. } | Assign { lhs, rhs, .. } => {
+                self.visit_expr(*lhs);
+                self.visit_expr(*rhs);
+            }
+            Cast { expr, .. } => self.visit_expr(*expr),
+            CompoundLiteral { init, .. } => self.visit_initializer(init),
+            Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => {
+                self.visit_expr(*cond);
+                self.visit_expr(*then_expr);
+                self.visit_expr(*else_expr);
+            }
+            BuiltinCall { args, .. } => args
+                .iter()
+                .flat_map(|x| x.exprs())
+                .for_each(|x| self.visit_expr(x)),
+        }
+    }
+
+    fn walk_stmt(&mut self, key: StmtKey) {
+        if !self.pre_stmt(key) {
+            return;
+        }
+        let stmt = self.ctx().get_stmt(key);
+        self.walk_stmt_kind(&stmt.kind);
+        self.post_stmt(key);
+    }
+
+    fn walk_stmt_kind(&mut self, kind: &'a StmtKind) {
+        use StmtKind::*;
+        match kind {
+            Expr { expr, .. } => expr.iter().for_each(|x| self.visit_expr(*x)),
+            Decl { decl } => self.walk_decl_group(decl),
+            Label { stmt, .. } | Default { stmt, .. } => self.visit_stmt(*stmt),
+            Case { expr, stmt, .. } => {
+                self.visit_expr(*expr);
+                self.visit_stmt(*stmt);
+            }
+            IfElse {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                self.visit_expr(*cond);
+                self.visit_stmt(*then_stmt);
+                else_stmt.iter().for_each(|x| self.visit_stmt(*x));
+            }
+            Switch { expr, body, .. } => {
+                self.visit_expr(*expr);
+                self.visit_stmt(*body);
+            }
+            While { cond, body, .. } => {
+                self.visit_expr(*cond);
+                self.visit_stmt(*body);
+            }
+            DoWhile { body, cond, .. } => {
+                self.visit_stmt(*body);
+                self.visit_expr(*cond);
+            }
+            For {
+                init,
+                cond,
+                step,
+                body,
+                ..
+            } => {
+                init.iter().for_each(|x| self.visit_stmt(*x));
+                [cond, step]
+                    .into_iter()
+                    .flatten()
+                    .for_each(|x| self.visit_expr(*x));
+                self.visit_stmt(*body);
+            }
+            Goto { .. } | Continue { .. } | Break { .. } => {}
+            Return { expr, .. } => expr.iter().for_each(|x| self.visit_expr(*x)),
+            Compound { stmts, .. } => stmts.iter().for_each(|x| self.visit_stmt(*x)),
+        }
+    }
+}
+
+///
+/// 可修改的 AST 遍历，用于改写 `CompCtx` 中节点的 pass（常量折叠、隐式转换插入等）
+///
+/// 与 `Visitor` 结构相同，区别：
+/// - 通过 `ctx_mut()` 拿到可变的 `CompCtx`，`pre_*` `post_*` 中可以原地改写当前节点，
+///   key 不变，父节点不需要修改；也可以改写父节点的 kind 来替换子节点的 key
+/// - 访问子节点前先收集子节点的 key，遍历期间所有节点都留在 `CompCtx` 中，
+///   所以子节点中也可以读取祖先节点
+/// - `post_*` 改写的子节点不会再被访问，父节点的 `post_*` 看到的是改写后的结果
+///
+pub trait VisitorMut {
+    fn ctx_mut(&mut self) -> &mut CompCtx;
 
-pub trait Visitor {
     fn walk_translation_unit(&mut self, unit: &mut TranslationUnit) {
         for ext_decl in unit {
             self.walk_external_decl(ext_decl);
@@ -13,25 +261,209 @@ pub trait Visitor {
     fn walk_external_decl(&mut self, decl: &mut ExternalDecl) {
         match decl {
             ExternalDecl::FunctionDefinition(x) => self.walk_func_def(x),
-            ExternalDecl::Declaration(x) => self.walk_decl_group(x)
+            ExternalDecl::Declaration(x) => self.walk_decl_group(x),
         }
     }
 
     fn walk_func_def(&mut self, decl: &mut FuncDef) {
         self.visit_decl(decl.decl);
-        self.visit_stmt(decl.body);
+        // 函数体已经挂在 DeclKind::FuncDef 上时不再重复访问
+        if !self.ctx_mut().get_decl(decl.decl).kind.is_func_def() {
+            self.visit_stmt(decl.body);
+        }
     }
 
-    fn walk_decl_group(&mut self, decl_group: &DeclGroup) {
+    fn walk_decl_group(&mut self, decl_group: &mut DeclGroup) {
         for x in decl_group.decls.iter().cloned() {
             self.visit_decl(x);
         }
     }
 
-    fn visit_decl(&mut self, decl: DeclKey);
+    fn visit_decl(&mut self, decl: DeclKey) {
+        self.walk_decl(decl);
+    }
+
+    fn visit_expr(&mut self, expr: ExprKey) {
+        self.walk_expr(expr);
+    }
+
+    fn visit_stmt(&mut self, stmt: StmtKey) {
+        self.walk_stmt(stmt);
+    }
+
+    fn pre_decl(&mut self, _decl: DeclKey) -> bool {
+        true
+    }
+
+    fn post_decl(&mut self, _decl: DeclKey) {}
+
+    fn pre_expr(&mut self, _expr: ExprKey) -> bool {
+        true
+    }
+
+    fn post_expr(&mut self, _expr: ExprKey) {}
+
+    fn pre_stmt(&mut self, _stmt: StmtKey) -> bool {
+        true
+    }
+
+    fn post_stmt(&mut self, _stmt: StmtKey) {}
+
+    fn walk_decl(&mut self, key: DeclKey) {
+        if !self.pre_decl(key) {
+            return;
+        }
+        let children = decl_children(&self.ctx_mut().get_decl(key).kind);
+        self.walk_children(children);
+        self.post_decl(key);
+    }
+
+    fn walk_expr(&mut self, key: ExprKey) {
+        if !self.pre_expr(key) {
+            return;
+        }
+        let children = expr_children(&self.ctx_mut().get_expr(key).kind);
+        self.walk_children(children);
+        self.post_expr(key);
+    }
+
+    fn walk_stmt(&mut self, key: StmtKey) {
+        if !self.pre_stmt(key) {
+            return;
+        }
+        let children = stmt_children(&self.ctx_mut().get_stmt(key).kind);
+        self.walk_children(children);
+        self.post_stmt(key);
+    }
+
+    /// 按源码顺序访问收集到的子节点
+    fn walk_children(&mut self, children: Vec<Child>) {
+        for child in children {
+            match child {
+                Child::Decl(x) => self.visit_decl(x),
+                Child::Expr(x) => self.visit_expr(x),
+                Child::Stmt(x) => self.visit_stmt(x),
+            }
+        }
+    }
+}
+
+/// `VisitorMut` 收集的子节点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Child {
+    Decl(DeclKey),
+    Expr(ExprKey),
+    Stmt(StmtKey),
+}
+
+/// decl 的子节点，函数体不在 stmt 池中，直接展开为它的子节点
+fn decl_children(kind: &DeclKind) -> Vec<Child> {
+    use DeclKind::*;
+    let mut children = Vec::new();
+    match kind {
+        TypeDef | ParamVar => {}
+        VarDecl { .. } | FuncDecl { .. } | RecordDecl { .. } | EnumDecl { .. } => {}
+        VarDef { init } => init
+            .iter()
+            .for_each(|x| initializer_children(x, &mut children)),
+        FuncDef { params, body, .. } => {
+            children.extend(params.iter().map(|x| Child::Decl(*x)));
+            children.extend(stmt_children(&body.kind));
+        }
+        RecordField { bit_field } => children.extend(bit_field.map(Child::Expr)),
+        RecordDef { fields, .. } => {
+            let decls = fields.iter().flat_map(|x| x.decls.iter());
+            children.extend(decls.map(|x| Child::Decl(*x)));
+        }
+        EnumField { expr } => children.extend(expr.map(Child::Expr)),
+        EnumDef { enums } => children.extend(enums.iter().flatten().map(|x| Child::Decl(*x))),
+    }
+    children
+}
 
-    fn visit_expr(&mut self, expr: ExprKey);
+fn initializer_children(init: &Initializer, children: &mut Vec<Child>) {
+    match init {
+        Initializer::Expr(x) => children.push(Child::Expr(*x)),
+        Initializer::InitList { inits } => inits
+            .inits
+            .iter()
+            .for_each(|x| initializer_children(x, children)),
+        Initializer::Designated {
+            designators, init, ..
+        } => {
+            for x in designators {
+                if let MemberDesignator::Index(expr) = x {
+                    children.push(Child::Expr(*expr));
+                }
+            }
+            initializer_children(init, children);
+        }
+    }
+}
 
-    fn visit_stmt(&mut self, stmt: StmtKey);
+fn expr_children(kind: &ExprKind) -> Vec<Child> {
+    use ExprKind::*;
+    let exprs = match kind {
+        DeclRef { .. } | Literal(_) | SizeofType { .. } | AlignofType { .. } => vec![],
+        ArraySubscript { base, index } => vec![*base, *index],
+        Call { base, params } => std::iter::once(*base)
+            .chain(params.exprs.iter().copied())
+            .collect(),
+        MemberAccess { base, .. } => vec![*base],
+        SizeofExpr { expr } => vec![*expr],
+        Unary { rhs, .. } => vec![*rhs],
+        Binary { lhs, rhs, .. } | Assign { lhs, rhs, .. } => vec![*lhs, *rhs],
+        Cast { expr, .. } => vec![*expr],
+        CompoundLiteral { init, .. } => {
+            let mut children = vec![];
+            initializer_children(init, &mut children);
+            return children;
+        }
+        Ternary {
+            cond,
+            then_expr,
+            else_expr,
+        } => vec![*cond, *then_expr, *else_expr],
+        BuiltinCall { args, .. } => args.iter().flat_map(|x| x.exprs()).collect(),
+    };
+    exprs.into_iter().map(Child::Expr).collect()
+}
 
-}
\ No newline at end of file
+fn stmt_children(kind: &StmtKind) -> Vec<Child> {
+    use StmtKind::*;
+    let expr = |x: &ExprKey| Child::Expr(*x);
+    let stmt = |x: &StmtKey| Child::Stmt(*x);
+    match kind {
+        Expr { expr: x, .. } | Return { expr: x, .. } => x.iter().map(expr).collect(),
+        Decl { decl } => decl.decls.iter().map(|x| Child::Decl(*x)).collect(),
+        Label { stmt: x, .. } | Default { stmt: x, .. } => vec![stmt(x)],
+        Case {
+            expr: e, stmt: s, ..
+        } => vec![expr(e), stmt(s)],
+        IfElse {
+            cond,
+            then_stmt,
+            else_stmt,
+            ..
+        } => [expr(cond), stmt(then_stmt)]
+            .into_iter()
+            .chain(else_stmt.iter().map(stmt))
+            .collect(),
+        Switch { expr: e, body, .. } | While { cond: e, body, .. } => vec![expr(e), stmt(body)],
+        DoWhile { body, cond, .. } => vec![stmt(body), expr(cond)],
+        For {
+            init,
+            cond,
+            step,
+            body,
+            ..
+        } => init
+            .iter()
+            .map(stmt)
+            .chain([cond, step].into_iter().flatten().map(expr))
+            .chain([stmt(body)])
+            .collect(),
+        Goto { .. } | Continue { .. } | Break { .. } => vec![],
+        Compound { stmts, .. } => stmts.iter().map(stmt).collect(),
+    }
+}
@@ -1,6 +1,6 @@
 use crate::lex::types::token::Token;
 use crate::lex::types::token_kind::Symbol;
-use crate::types::span::{Pos, Span};
+use crate::types::span::Span;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Ident {
@@ -23,6 +23,12 @@ pub struct IdentList {
     pub span: Span,
 }
 
+impl Default for IdentList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl IdentList {
     pub fn new() -> Self {
         Self {
@@ -37,6 +43,7 @@ impl IdentList {
 pub enum TypeSpecState {
     Init,
     Void,
+    Bool,
     Char,
     Short,
     Int,
@@ -56,11 +63,12 @@ impl TypeSpecState {
         match (state1, state2) {
             (Init, _) => Some(state2),
             (Void, _) => None,
+            (Bool, _) => None,
             (Char, Int) => Some(Char),
             (Short, Int) => Some(Short),
             (Int, Char) => Some(Char),
             (Int, Short) => Some(Short),
-            (Int, Long) => Some(Int),
+            (Int, Long) => Some(Long),
             (Int, LongLong) => Some(LongLong),
             (Long, Int) => Some(Long),
             (Long, Long) => Some(LongLong),
@@ -1,4 +1,4 @@
-use crate::err::parser_error::{ParserError, ParserResult};
+use crate::err::parser_error::{ErrorLevel, ParserError, ParserResult};
 use crate::lex::token_stream::TokenStream;
 use crate::parser::ast::decls::decl::Decl;
 use crate::parser::ast::exprs::Expr;
@@ -6,6 +6,7 @@ use crate::parser::ast::stmt::Stmt;
 use crate::parser::ast::{DeclKey, ExprKey, StmtKey};
 use crate::parser::semantic::sema::scope::scope_manager::ScopeMgr;
 use crate::parser::semantic::sema::type_ctx::type_ctx::TypeCtx;
+use backend::target::TargetInfo;
 use slotmap::SlotMap;
 
 macro_rules! make_get {
@@ -35,11 +36,12 @@ pub struct CompCtx {
     pub type_ctx: TypeCtx,
     pub errors: Vec<ParserError>,
     pub stream: TokenStream,
+    pub target: TargetInfo, // 目标，决定 long、指针的大小和 `__builtin_va_list` 的类型
 }
 
 impl CompCtx {
     pub fn new(stream: TokenStream) -> Self {
-        return Self {
+        Self {
             decls: SlotMap::with_key(),
             exprs: SlotMap::with_key(),
             stmts: SlotMap::with_key(),
@@ -47,7 +49,8 @@ impl CompCtx {
             errors: Vec::new(),
             scope_mgr: ScopeMgr::new(),
             stream,
-        };
+            target: TargetInfo::host(),
+        }
     }
 
     make_get!(get_decl, get_decl_mut, insert_decl, decls, DeclKey, Decl);
@@ -58,7 +61,24 @@ impl CompCtx {
         self.exprs.remove(key).expect("exprssion not exist")
     }
 
-    pub fn send_error(&mut self, _error: ParserError) -> ParserResult<()> {
-        todo!()
+    /// 所有表达式的 key，顺序不保证
+    pub fn expr_keys(&self) -> impl Iterator<Item = ExprKey> + '_ {
+        self.exprs.keys()
+    }
+
+    /// 所有语句的 key，顺序不保证
+    pub fn stmt_keys(&self) -> impl Iterator<Item = StmtKey> + '_ {
+        self.stmts.keys()
+    }
+
+    /// 警告记录下来继续解析，错误直接返回
+    pub fn send_error(&mut self, error: ParserError) -> ParserResult<()> {
+        match error.level {
+            ErrorLevel::Error => Err(error),
+            ErrorLevel::Note | ErrorLevel::Warning => {
+                self.errors.push(error);
+                Ok(())
+            }
+        }
     }
 }
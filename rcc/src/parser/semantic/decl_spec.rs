@@ -2,12 +2,11 @@ use crate::lex::types::token::Token;
 use crate::lex::types::token_kind::Keyword;
 use crate::lex::types::token_kind::TokenKind;
 use crate::parser::ast::common::StructOrUnion;
-use crate::parser::ast::decls::decl::DeclGroup;
 use crate::parser::ast::{DeclKey, ExprKey};
 use crate::parser::semantic::common::{Ident, IdentList};
 use crate::parser::semantic::declarator::*;
 use crate::parser::semantic::sema::type_ctx::type_builder::TypeBuilderKind;
-use crate::types::span::{Pos, Span};
+use crate::types::span::Span;
 use enum_as_inner::EnumAsInner;
 use std::fmt::{Display, Formatter};
 
@@ -19,13 +18,22 @@ use std::fmt::{Display, Formatter};
 /// - `signed`: Signed Unsigned
 /// - `type_quals`:
 /// - `func_spec`:
+/// - `noreturn`: `_Noreturn` 或 `__attribute__((noreturn))`，可以和 `inline` 一起出现，不放在 `func_spec` 中
+/// - `visibility`: `__attribute__((visibility("...")))`
+/// - `align`: `_Alignas`，有多个时取最严格的，对齐为 0 的没有效果
+/// - `thread_local`: `_Thread_local` 或 `__thread`，可以和 `static` `extern` 一起出现，不放在 `storage` 中
 /// - `span`:
 #[derive(Debug, Clone)]
 pub struct DeclSpec {
     pub storage: Option<StorageSpec>, // 全局上下文的时候默认extern
+    pub thread_local: Option<StorageSpec>,
     pub kind: TypeBuilderKind,
     pub type_quals: TypeQuals,
     pub func_spec: Option<FuncSpec>,
+    pub noreturn: Option<FuncSpec>,
+    pub visibility: Option<VisibilitySpec>,
+    pub align: Option<AlignSpec>,
+    pub tag: Option<DeclKey>, // 在 decl spec 中声明或定义的 struct/union/enum
     pub span: Span,
 }
 
@@ -36,6 +44,7 @@ pub enum StorageSpecKind {
     Static,
     Auto,
     Register,
+    ThreadLocal,
 }
 
 #[derive(Debug, Clone)]
@@ -53,6 +62,7 @@ impl Display for StorageSpec {
             Static => "static",
             Auto => "auto",
             Register => "register",
+            ThreadLocal => "_Thread_local",
         };
         write!(f, "{}", str)
     }
@@ -67,6 +77,7 @@ impl StorageSpec {
                 Static => StorageSpecKind::Static,
                 Auto => StorageSpecKind::Auto,
                 Register => StorageSpecKind::Register,
+                ThreadLocal => StorageSpecKind::ThreadLocal,
                 _ => unreachable!(),
             },
             _ => unreachable!("{:?}", token),
@@ -88,6 +99,7 @@ impl StorageSpec {
 #[derive(Debug, Clone, EnumAsInner)]
 pub enum TypeSpecKind {
     Void,
+    Bool,
     Char,
     Short,
     Int,
@@ -104,8 +116,9 @@ pub enum TypeSpecKind {
 impl TypeSpecKind {
     pub fn new(kw: Keyword) -> Self {
         use Keyword::*;
-        let kind = match kw {
+        match kw {
             Void => TypeSpecKind::Void,
+            Bool => TypeSpecKind::Bool,
             Char => TypeSpecKind::Char,
             Short => TypeSpecKind::Short,
             Int => TypeSpecKind::Int,
@@ -115,9 +128,7 @@ impl TypeSpecKind {
             Signed => TypeSpecKind::Signed,
             Unsigned => TypeSpecKind::Unsigned,
             _ => unreachable!(),
-        };
-
-        kind
+        }
     }
 }
 
@@ -128,22 +139,6 @@ pub struct TypeSpec {
 }
 
 impl TypeSpec {
-    pub fn new(token: Token) -> Self {
-        let keyword = token.kind.into_keyword().unwrap();
-        let kind = TypeSpecKind::new(keyword);
-        Self {
-            kind,
-            span: token.span,
-        }
-    }
-
-    pub fn from_kind(kind: TypeSpecKind) -> Self {
-        Self {
-            kind,
-            span: Span::default(),
-        }
-    }
-
     pub fn is(&self, kind: &TypeSpecKind) -> bool {
         std::mem::discriminant(&self.kind) == std::mem::discriminant(kind)
     }
@@ -153,6 +148,7 @@ impl Display for TypeSpec {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let msg = match &self.kind {
             TypeSpecKind::Void => "void",
+            TypeSpecKind::Bool => "_Bool",
             TypeSpecKind::Char => "char",
             TypeSpecKind::Short => "short",
             TypeSpecKind::Int => "int",
@@ -163,7 +159,7 @@ impl Display for TypeSpec {
             TypeSpecKind::Unsigned => "unsigned",
             TypeSpecKind::Record(_) => "record",
             TypeSpecKind::Enum(_) => "enum",
-            TypeSpecKind::TypeName(_, _) => "type-name",
+            TypeSpecKind::TypeName(name, _) => name.symbol.get(),
         };
         write!(f, "{}", msg)
     }
@@ -220,9 +216,13 @@ pub struct TypeQuals {
     pub is_volatile: Option<TypeQual>,
 }
 
-#[derive(Debug, Clone)]
+/// 函数说明符，`AlwaysInline` `NoInline` 来自 GNU 的 `__attribute__((always_inline))` `__attribute__((noinline))`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FuncSpecKind {
     Inline,
+    AlwaysInline,
+    NoInline,
+    Noreturn,
 }
 
 #[derive(Debug, Clone)]
@@ -235,10 +235,8 @@ impl FuncSpec {
     pub fn new(token: Token) -> Self {
         use crate::lex::types::token_kind::Keyword::*;
         let kind = match token.kind {
-            TokenKind::Keyword(kw) => match kw {
-                Inline => FuncSpecKind::Inline,
-                _ => unreachable!(),
-            },
+            TokenKind::Keyword(Inline) => FuncSpecKind::Inline,
+            TokenKind::Keyword(Noreturn) => FuncSpecKind::Noreturn,
             _ => unreachable!(),
         };
         Self {
@@ -248,38 +246,88 @@ impl FuncSpec {
     }
 }
 
+impl FuncSpecKind {
+    /// 关键字或属性的名字：`inline` `always_inline` `noinline` `noreturn`
+    pub fn name(self) -> &'static str {
+        match self {
+            FuncSpecKind::Inline => "inline",
+            FuncSpecKind::AlwaysInline => "always_inline",
+            FuncSpecKind::NoInline => "noinline",
+            FuncSpecKind::Noreturn => "noreturn",
+        }
+    }
+}
+
 impl Display for FuncSpec {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let str = match self.kind {
             FuncSpecKind::Inline => "inline",
+            FuncSpecKind::AlwaysInline => "__attribute__((always_inline))",
+            FuncSpecKind::NoInline => "__attribute__((noinline))",
+            FuncSpecKind::Noreturn => "_Noreturn",
         };
         write!(f, "{}", str)
     }
 }
 
+/// 符号可见性，来自 GNU 的 `__attribute__((visibility("default" | "hidden" | "protected")))`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibilityKind {
+    Default,
+    Hidden,
+    Protected,
+}
+
+#[derive(Debug, Clone)]
+pub struct VisibilitySpec {
+    pub kind: VisibilityKind,
+    pub span: Span,
+}
+
+impl VisibilityKind {
+    /// 属性参数中的名字
+    pub fn name(self) -> &'static str {
+        match self {
+            VisibilityKind::Default => "default",
+            VisibilityKind::Hidden => "hidden",
+            VisibilityKind::Protected => "protected",
+        }
+    }
+}
+
+impl Display for VisibilitySpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "__attribute__((visibility(\"{}\")))", self.kind.name())
+    }
+}
+
+/// 对齐说明符 `_Alignas(type-name)` `_Alignas(constant-expression)`，`align` 是求出的对齐
+#[derive(Debug, Clone, Copy)]
+pub struct AlignSpec {
+    pub align: usize,
+    pub span: Span,
+}
+
+impl Display for AlignSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "_Alignas({})", self.align)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum ParamDecl {
     Idents(IdentList),
     Params(ParamList),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ParamList {
     pub params: Vec<DeclKey>,
     pub is_variadic: bool,
+    pub has_prototype: bool, // 空括号 `()` 不是原型
     pub span: Span,
 }
 
-impl Default for ParamList {
-    fn default() -> Self {
-        Self {
-            params: Vec::new(),
-            is_variadic: false,
-            span: Span::default(),
-        }
-    }
-}
-
 /// Record 解析前期
 pub struct RecordSuffix {
     pub record: StructOrUnion,
@@ -293,24 +341,6 @@ pub struct EnumSuffix {
     pub span: Span,
 }
 
-// todo 可能不需要了
-#[derive(Clone, Debug)]
-pub struct StructSpecBody {
-    pub l: Pos,
-    pub groups: Vec<DeclGroup>,
-    pub r: Pos,
-}
-
-// todo 可能不需要了
-// struct or union
-#[derive(Clone, Debug)]
-pub struct StructSpec {
-    pub kind: StructOrUnion,
-    pub name: Option<Ident>,
-    pub body: Option<StructSpecBody>,
-    pub span: Span,
-}
-
 #[derive(Clone, Debug)]
 pub struct StructDeclarator {
     pub declarator: Declarator,
@@ -324,10 +354,3 @@ pub struct Enumerator {
     pub expr: Option<ExprKey>,
     pub span: Span,
 }
-
-#[derive(Clone, Debug)]
-pub struct EnumSpec {
-    pub name: Option<Ident>,
-    pub enums: Vec<Enumerator>, // 内部声明，应该都是 enumerator
-    pub span: Span,
-}
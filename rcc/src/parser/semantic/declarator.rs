@@ -50,21 +50,6 @@ pub struct InitDeclarator {
     pub span: Span,
 }
 
-#[derive(Clone, Debug)]
-pub struct InitDeclaratorList {
-    pub inits: Vec<InitDeclarator>,
-    pub span: Span,
-}
-
-impl InitDeclaratorList {
-    pub fn new() -> Self {
-        Self {
-            inits: Vec::new(),
-            span: Span::default(),
-        }
-    }
-}
-
 /// decl 解析前缀
 pub struct DeclPrefix {
     pub decl_spec: Rc<DeclSpec>,
@@ -1,4 +1,5 @@
 pub mod decl_spec;
 /// decl将在运行阶段解析
 pub mod declarator;
+pub mod initializer;
 pub mod record;
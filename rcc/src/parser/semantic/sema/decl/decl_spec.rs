@@ -1,13 +1,13 @@
 use crate::constant::str::DECL_SPEC;
 use crate::err::parser_error::{ParserError, ParserResult};
-use crate::parser::ast::DeclKey;
-use crate::parser::ast::decl::DeclKind;
-use crate::parser::ast::types::{FloatSize, IntegerSize};
+use crate::parser::ast::types::{FloatSize, IntegerSize, TypeKind, TypeLayout};
+use crate::parser::ast::{DeclKey, ExprKey, TypeKey};
 use crate::parser::common::TypeSpecState;
 use crate::parser::comp_ctx::CompCtx;
 use crate::parser::semantic::decl_spec::{
-    DeclSpec, FuncSpec, StorageSpec, TypeQual, TypeQuals, TypeSpec,
+    AlignSpec, DeclSpec, FuncSpec, StorageSpec, TypeQual, TypeQuals, TypeSpec, VisibilitySpec,
 };
+use crate::parser::semantic::sema::expr::const_eval::eval_int;
 use crate::parser::semantic::sema::type_ctx::type_builder::TypeBuilderKind;
 use crate::types::span::Span;
 use std::rc::Rc;
@@ -16,38 +16,67 @@ pub struct DeclSpecBuilder {
     pub storages: Vec<StorageSpec>,
     pub type_quals: Vec<TypeQual>,
     pub func_specs: Vec<FuncSpec>,
+    pub visibilities: Vec<VisibilitySpec>,
+    pub aligns: Vec<AlignSpec>,
     pub type_specs: Vec<TypeSpec>,
+    pub tag: Option<DeclKey>,
     pub span: Span,
 }
 impl DeclSpecBuilder {
     pub fn build(self, ctx: &mut CompCtx) -> ParserResult<Rc<DeclSpec>> {
-        let storage = Self::act_on_storages(self.storages)?;
+        let (storage, thread_local) = Self::act_on_storages(self.storages)?;
         let type_quals = Self::act_on_type_quals(self.type_quals)?;
-        let func_spec = Self::act_on_func_specs(self.func_specs)?;
+        let (func_spec, noreturn) = Self::act_on_func_specs(self.func_specs)?;
+        let visibility = Self::act_on_visibilities(self.visibilities);
+        let align = Self::act_on_align_specs(self.aligns);
         let kind = Self::act_on_type_specs(ctx, self.type_specs)?;
 
         let decl_spec = Rc::new(DeclSpec {
             storage,
+            thread_local,
             type_quals,
             func_spec,
+            noreturn,
+            visibility,
+            align,
             kind,
+            tag: self.tag,
             span: self.span,
         });
 
         Ok(decl_spec)
     }
 
-    fn act_on_storages(storages: Vec<StorageSpec>) -> ParserResult<Option<StorageSpec>> {
+    /// `_Thread_local` 单独返回，它只能和 `static` `extern` 一起出现
+    fn act_on_storages(
+        storages: Vec<StorageSpec>,
+    ) -> ParserResult<(Option<StorageSpec>, Option<StorageSpec>)> {
+        use crate::parser::semantic::decl_spec::StorageSpecKind::*;
         let mut storage: Option<StorageSpec> = None;
+        let mut thread_local: Option<StorageSpec> = None;
         for spec in storages {
-            if let Some(x) = storage {
+            let field = match spec.kind {
+                ThreadLocal => &mut thread_local,
+                _ => &mut storage,
+            };
+            if let Some(x) = field {
                 let err = ParserError::duplicate(x.to_string(), DECL_SPEC, spec.span);
                 return Err(err);
             }
-            storage = Some(spec);
+            *field = Some(spec);
+        }
+        if let (Some(x), Some(y)) = (&storage, &thread_local)
+            && !matches!(x.kind, Static | Extern)
+        {
+            let (first, second) = match x.span.start < y.span.start {
+                true => (x, y),
+                false => (y, x),
+            };
+            let err = ParserError::non_combinable(first.to_string(), DECL_SPEC, second.span);
+            return Err(err);
         }
 
-        Ok(storage)
+        Ok((storage, thread_local))
     }
 
     fn act_on_type_quals(quals: Vec<TypeQual>) -> ParserResult<TypeQuals> {
@@ -70,17 +99,50 @@ impl DeclSpecBuilder {
         Ok(res)
     }
 
-    fn act_on_func_specs(specs: Vec<FuncSpec>) -> ParserResult<Option<FuncSpec>> {
+    /// 多个 `_Alignas` 时取最严格的，`_Alignas(0)` 没有效果
+    fn act_on_align_specs(specs: Vec<AlignSpec>) -> Option<AlignSpec> {
+        specs.into_iter().filter(|x| x.align != 0).max_by_key(|x| x.align)
+    }
+
+    /// 多个 visibility 属性时以最后一个为准
+    fn act_on_visibilities(specs: Vec<VisibilitySpec>) -> Option<VisibilitySpec> {
+        specs.into_iter().last()
+    }
+
+    /// `inline` 可以和 `always_inline` `noinline` 一起出现，结果取属性；
+    /// `_Noreturn` 单独返回，可以重复出现（头文件中常和 `__attribute__((noreturn))` 一起使用）
+    fn act_on_func_specs(
+        specs: Vec<FuncSpec>,
+    ) -> ParserResult<(Option<FuncSpec>, Option<FuncSpec>)> {
+        use crate::parser::semantic::decl_spec::FuncSpecKind::*;
         let mut func_spec: Option<FuncSpec> = None;
+        let mut noreturn: Option<FuncSpec> = None;
         for spec in specs {
+            if spec.kind == Noreturn {
+                noreturn = noreturn.or(Some(spec));
+                continue;
+            }
             if let Some(x) = func_spec {
-                let err = ParserError::duplicate(x.to_string(), DECL_SPEC, spec.span);
-                return Err(err);
+                match (x.kind, spec.kind) {
+                    (a, b) if a == b => {
+                        let err = ParserError::duplicate(x.to_string(), DECL_SPEC, spec.span);
+                        return Err(err);
+                    }
+                    (Inline, _) => {}
+                    (_, Inline) => {
+                        func_spec = Some(x);
+                        continue;
+                    }
+                    _ => {
+                        let err = ParserError::non_combinable(x.to_string(), DECL_SPEC, spec.span);
+                        return Err(err);
+                    }
+                }
             }
             func_spec = Some(spec);
         }
 
-        Ok(func_spec)
+        Ok((func_spec, noreturn))
     }
 
     /// 检查type spec是否正确
@@ -97,7 +159,7 @@ impl DeclSpecBuilder {
                 let err = ParserError::non_combinable(spec.to_string(), DECL_SPEC, spec.span);
                 return Err(err);
             }
-            Float | Double | Record(_) | Enum(_) | TypeName(_, _) if is_signed.is_some() => {
+            Bool | Float | Double | Record(_) | Enum(_) | TypeName(_, _) if is_signed.is_some() => {
                 let prev = is_signed.expect("impossible").to_string();
                 let err = ParserError::non_combinable(prev, DECL_SPEC, spec.span);
                 return Err(err);
@@ -136,6 +198,7 @@ impl DeclSpecBuilder {
 
             let next = match &spec.kind {
                 Void => TypeSpecState::Void,
+                Bool => TypeSpecState::Bool,
                 Char => TypeSpecState::Char,
                 Short => TypeSpecState::Short,
                 Int => {
@@ -172,7 +235,8 @@ impl DeclSpecBuilder {
                 }
             };
         }
-        let is_signed = is_signed.map(|x| x.kind.is_signed()).unwrap_or(false);
+        // 没有 signed unsigned 时默认有符号
+        let is_signed = is_signed.is_none_or(|x| x.kind.is_signed());
         let builder_kind = Self::get_type_build_kind(ctx, state, is_signed, decl);
 
         Ok(builder_kind)
@@ -187,8 +251,12 @@ impl DeclSpecBuilder {
     ) -> TypeBuilderKind {
         use crate::parser::semantic::common::TypeSpecState::*;
         // 根据最后状态判断类型
-        let builder = match state {
+        match state {
             Void => TypeBuilderKind::Void,
+            Bool => TypeBuilderKind::Integer {
+                is_signed: false,
+                size: IntegerSize::Bool,
+            },
             Char => TypeBuilderKind::Integer {
                 is_signed,
                 size: IntegerSize::Char,
@@ -197,7 +265,8 @@ impl DeclSpecBuilder {
                 is_signed,
                 size: IntegerSize::Short,
             },
-            Int => TypeBuilderKind::Integer {
+            // 只有 signed unsigned 时为 int
+            Int | Init => TypeBuilderKind::Integer {
                 is_signed,
                 size: IntegerSize::Int,
             },
@@ -218,26 +287,44 @@ impl DeclSpecBuilder {
             LongDouble => TypeBuilderKind::Floating {
                 size: FloatSize::LongDouble,
             },
-            Record => {
-                let decl = decl.expect("record decl should not be none");
-                let decl = ctx.get_decl(decl);
-                let record_kind = match &decl.kind {
-                    DeclKind::RecordDecl { kind, .. } => kind.kind,
-                    DeclKind::RecordDef { kind, .. } => kind.kind,
-                    _ => unreachable!(""),
-                };
-                TypeBuilderKind::new_record(ctx, record_kind)
-            }
-            Enum => TypeBuilderKind::new_enum(ctx),
-            TypeName => {
-                let decl = decl.expect("record decl should not be none");
+            // tag 声明和 typedef 已经有类型，使用同一个 record / enum id
+            Record | Enum | TypeName => {
+                let decl = decl.expect("tag or typedef decl should not be none");
                 let decl = ctx.get_decl(decl);
                 let ty = ctx.type_ctx.get_type(decl.ty);
 
                 TypeBuilderKind::from_type_kind(&ty.kind)
             }
-            Init => unreachable!("should not be init"),
-        };
-        builder
+        }
+    }
+}
+
+/// `_Alignof` 和 `_Alignas` 的类型操作数必须是完整的对象类型，结果是类型的对齐
+pub fn type_align(ctx: &CompCtx, op: &str, ty: TypeKey, span: Span) -> ParserResult<usize> {
+    let target = ctx.type_ctx.get_type(ty);
+    let msg = match &target.kind {
+        TypeKind::Function { .. } => format!("invalid application of '{}' to a function type", op),
+        _ if !target.is_complete() => format!(
+            "invalid application of '{}' to an incomplete type '{}'",
+            op,
+            target.to_code(ctx)
+        ),
+        _ => return Ok(TypeLayout::of(ctx, ty).align),
+    };
+    Err(ParserError::error(msg, span))
+}
+
+/// `_Alignas(constant-expression)` 的值必须是 0 或 2 的幂
+pub fn act_on_align_expr(ctx: &CompCtx, expr: ExprKey) -> ParserResult<usize> {
+    let span = ctx.get_expr(expr).span;
+    let Some(value) = eval_int(ctx, expr) else {
+        return Err(ParserError::not_int_constant(span));
+    };
+    match usize::try_from(value) {
+        Ok(x) if x == 0 || x.is_power_of_two() => Ok(x),
+        _ => {
+            let msg = "requested alignment is not a power of 2".to_owned();
+            Err(ParserError::error(msg, span))
+        }
     }
 }
@@ -1,15 +1,24 @@
-use crate::constant::str::TYPEDEF_REQUIRE_NAME;
+use crate::constant::str::{NORETURN_NOT_FUNC, THREAD_LOCAL_IN_BLOCK, TYPEDEF_REQUIRE_NAME};
+use crate::constant::typ::MAX_STACK_ALIGN;
 use crate::err::parser_error::{ParserError, ParserResult};
-use crate::parser::ast::types::{ArraySize, TypeKind};
+use crate::err::scope_error::ScopeSource;
+use crate::parser::ast::decls::decl::{Decl, DeclGroup, DeclKind};
+use crate::parser::ast::func::FuncDecl;
+use crate::parser::ast::stmt::{Stmt, StmtKey};
+use crate::parser::ast::types::{IntegerSize, TypeKind};
 use crate::parser::ast::{DeclKey, TypeKey};
+use crate::parser::common::Ident;
 use crate::parser::comp_ctx::CompCtx;
-use crate::parser::semantic::decl_spec::{StorageSpec, StorageSpecKind};
-use crate::parser::semantic::declarator::InitDeclarator;
-use crate::parser::semantic::sema::scope::scope_struct::{ScopeKind, ScopeSymbol};
+use crate::parser::semantic::decl_spec::{
+    ParamDecl, ParamList, StorageSpec, StorageSpecKind, StructDeclarator,
+};
+use crate::parser::semantic::declarator::{Declarator, DeclaratorChunkKind, InitDeclarator};
+use crate::parser::semantic::sema::decl::initializer::act_on_initializer;
+use crate::parser::semantic::sema::scope::lookup::{lookup_or_insert_decl, lookup_or_insert_def};
+use crate::parser::semantic::sema::scope::scope_struct::{MemberSymbol, ScopeKind, ScopeSymbol};
 use crate::parser::semantic::sema::type_ctx::declarator::{DeclInfo, resolve_declarator};
+use crate::types::span::Span;
 use std::collections::hash_map::Entry;
-use crate::parser::ast::decls::initializer::Initializer;
-use crate::parser::ast::types::TypeKind::{Unknown, Void};
 
 /// 将 typedef 插入符号表，负责处理名字问题，类型不匹配问题
 /// todo: 可能放到 scope 模块更合适
@@ -51,6 +60,73 @@ fn insert_typedef(ctx: &mut CompCtx, decl_key: DeclKey) -> ParserResult<()> {
     Ok(())
 }
 
+/// 插入只有定义没有声明的符号（参数、局部变量），同一作用域重复出现是重定义
+fn insert_unique_ident(ctx: &mut CompCtx, decl_key: DeclKey) -> ParserResult<()> {
+    let decl = ctx.get_decl(decl_key);
+    let ty = decl.ty;
+    let Some(name) = decl.name.clone() else {
+        return Ok(());
+    };
+
+    match ctx.scope_mgr.entry_local_ident(name.symbol) {
+        Entry::Occupied(x) => {
+            let error = ParserError::redefinition(x.get().get_decl(), name);
+            Err(error)
+        }
+        Entry::Vacant(x) => {
+            x.insert(ScopeSymbol {
+                name: name.symbol,
+                decls: Vec::new(),
+                def: Some(decl_key),
+                ty,
+            });
+            Ok(())
+        }
+    }
+}
+
+/// 文件作用域的变量定义，没有初始值的暂定定义可以重复出现
+fn insert_var_def(ctx: &mut CompCtx, decl_key: DeclKey) -> ParserResult<()> {
+    let decl = ctx.get_decl(decl_key);
+    let ty = decl.ty;
+    let has_init = decl.kind.as_var_def().is_some_and(|x| x.is_some());
+    let name = decl.name.clone().expect("variable without name");
+
+    let prev = ctx
+        .scope_mgr
+        .lookup_local_ident(name.symbol)
+        .and_then(|x| x.def);
+    if let Some(prev) = prev
+        && ctx.scope_mgr.get_kind() == ScopeKind::File
+    {
+        let prev_init = match &ctx.get_decl(prev).kind {
+            DeclKind::VarDef { init } => Some(init.is_some()),
+            _ => None,
+        };
+        match prev_init {
+            // 两个都有初始值才是重定义
+            Some(prev_init) if !(prev_init && has_init) => {
+                let symbol = ctx.scope_mgr.entry_local_ident(name.symbol);
+                if let Entry::Occupied(mut x) = symbol
+                    && has_init
+                {
+                    x.get_mut().def = Some(decl_key);
+                }
+                return Ok(());
+            }
+            _ => return Err(ParserError::redefinition(prev, name)),
+        }
+    }
+
+    let decls = lookup_or_insert_def(ctx, decl_key, ty, ScopeSource::Ident)?;
+    for decl in decls {
+        if let DeclKind::VarDecl { def } = &mut ctx.get_decl_mut(decl).kind {
+            *def = Some(decl_key);
+        }
+    }
+    Ok(())
+}
+
 fn default_storage_kind(ctx: &CompCtx) -> StorageSpecKind {
     match ctx.scope_mgr.get_kind() {
         ScopeKind::File => StorageSpecKind::Extern,
@@ -88,9 +164,56 @@ fn is_definition(ctx: &mut CompCtx, decl_info: &DeclInfo, has_init: bool) -> boo
     }
 }
 
+/// `_Alignas` 只能用于变量和非位域成员，`invalid` 为不能使用的声明；对齐不能比类型本身的宽松
+fn check_align(ctx: &CompCtx, decl_info: &DeclInfo, invalid: Option<&str>) -> ParserResult<()> {
+    let Some(spec) = decl_info.align else {
+        return Ok(());
+    };
+    if let Some(what) = invalid {
+        let msg = format!("'_Alignas' cannot be applied to {}", what);
+        return Err(ParserError::error(msg, spec.span));
+    }
+    let ty = ctx.type_ctx.get_type(decl_info.ty);
+    let natural = ty.layout(ctx).align;
+    if spec.align < natural {
+        let msg = format!(
+            "requested alignment is less than minimum alignment of {} for type '{}'",
+            natural,
+            ty.to_code(ctx)
+        );
+        return Err(ParserError::error(msg, spec.span));
+    }
+    Ok(())
+}
+
+/// `_Noreturn` 只能用于函数
+fn check_noreturn(decl_info: &DeclInfo) -> ParserResult<()> {
+    match &decl_info.noreturn {
+        Some(x) => Err(ParserError::error(NORETURN_NOT_FUNC.to_owned(), x.span)),
+        None => Ok(()),
+    }
+}
+
+/// 自动变量的对齐不能超过栈帧的对齐
+fn check_stack_align(ctx: &CompCtx, decl_info: &DeclInfo) -> ParserResult<()> {
+    let explicit = decl_info.align.map_or(0, |x| x.align);
+    let align = ctx.type_ctx.get_type(decl_info.ty).layout(ctx).align.max(explicit);
+    if align <= MAX_STACK_ALIGN {
+        return Ok(());
+    }
+    let msg = format!(
+        "alignment {} of an automatic variable exceeds the maximum supported alignment {}",
+        align, MAX_STACK_ALIGN
+    );
+    let span = decl_info.align.map_or(decl_info.span, |x| x.span);
+    Err(ParserError::error(msg, span))
+}
+
 // 处理 typedef
 fn act_on_typedef(ctx: &mut CompCtx, decl_info: DeclInfo, has_init: bool) -> ParserResult<DeclKey> {
     debug_assert!(is_typedef(decl_info.storage.as_ref())); // 必须是 typedef
+    check_align(ctx, &decl_info, Some("a typedef"))?;
+    check_noreturn(&decl_info)?;
 
     // typedef 不能初始化
     if has_init {
@@ -103,6 +226,11 @@ fn act_on_typedef(ctx: &mut CompCtx, decl_info: DeclInfo, has_init: bool) -> Par
     // 构造 decl
     let decl = Decl {
         storage: decl_info.storage,
+        func_spec: decl_info.func_spec,
+        noreturn: decl_info.noreturn,
+        visibility: decl_info.visibility,
+        thread_local: decl_info.thread_local,
+        align: decl_info.align,
         name: decl_info.name,
         kind: DeclKind::TypeDef,
         ty: decl_info.ty,
@@ -116,63 +244,30 @@ fn act_on_typedef(ctx: &mut CompCtx, decl_info: DeclInfo, has_init: bool) -> Par
     Ok(decl_key)
 }
 
-fn act_on_array_initializer(ctx: &mut CompCtx, elem_ty: TypeKey, size: &mut ArraySize, init: &Initializer) -> ParserResult<()> {
-    let inits = match init {
-        Initializer::Expr(x) => {
-            // 报错，数组不能用一个普通expression初始化
-            todo!()
-        }
-        Initializer::InitList { inits } =>
-           inits
-    };
-
-    match size {
-        ArraySize::Static(x) => match init {
-            Initializer::Expr(x) => {
-                // 数组没有初始化
-                todo!("")
-            }
-            Initializer::InitList { inits } => {}
-        }
-           todo!("超出了 发一个警告"),
-        ArraySize::Incomplete => {
-            // Incomplete推导
-            *size = ArraySize::Static(inits.inits.len());
-        }
-        _ => ,
-    },
-
-    Ok(())
-}
-
-fn check_initializer_type(ctx: &mut CompCtx, ty: TypeKey, init: &Initializer) -> ParserResult<()> {
-    use TypeKind::*;
-    let ty = ctx.type_ctx.get_type_mut(ty);
-    match &mut ty.kind {
-        Unknown | Function { .. } | Void => unreachable!(),
-        Integer { .. } | Floating { .. } | Pointer { .. } | Enum { .. } => {}
-        Array { elem_ty, size } =>
-            act_on_array_initializer(ctx, *elem_ty, size, init)?,
-        Record { .. } => {}
+/// 由 decl_info 构造 decl
+fn new_decl(decl_info: DeclInfo, kind: DeclKind) -> Decl {
+    Decl {
+        storage: decl_info.storage,
+        func_spec: decl_info.func_spec,
+        noreturn: decl_info.noreturn,
+        visibility: decl_info.visibility,
+        thread_local: decl_info.thread_local,
+        align: decl_info.align,
+        name: decl_info.name,
+        kind,
+        ty: decl_info.ty,
+        span: decl_info.span,
     }
-    Ok(())
-}
-
-fn act_on_initializer(
-    ctx: &CompCtx,
-    decl: DeclKey,
-    is_def: bool,
-    init: Option<Initializer>,
-) -> ParserResult<Initializer> {
-    let decl = ctx.get_decl(decl);
 }
 
 pub fn act_on_init_declarator(
     ctx: &mut CompCtx,
     init_declarator: InitDeclarator,
 ) -> ParserResult<DeclKey> {
-    // let declarator = init_declarator.declarator;
-    // let name = declarator.name.clone();
+    // K&R 函数定义的参数声明
+    if ctx.scope_mgr.get_kind() == ScopeKind::ParamList {
+        return act_on_param_var(ctx, init_declarator.declarator);
+    }
 
     // 构建类型
     let decl_info = resolve_declarator(ctx, init_declarator.declarator)?;
@@ -184,393 +279,317 @@ pub fn act_on_init_declarator(
         return act_on_typedef(ctx, decl_info, has_init);
     }
 
+    let name = match &decl_info.name {
+        Some(x) => x.clone(),
+        None => {
+            let error = ParserError::expect("identifier", decl_info.span);
+            return Err(error);
+        }
+    };
+
+    // 函数声明
+    if ctx.type_ctx.get_type(decl_info.ty).kind.is_function() {
+        check_align(ctx, &decl_info, Some("a function"))?;
+        if let Some(storage) = &decl_info.storage
+            && has_init
+        {
+            let error =
+                ParserError::illegal_init(storage.to_string(), name.symbol, storage.span);
+            return Err(error);
+        }
+        let ty = decl_info.ty;
+        let decl_key = ctx.insert_decl(new_decl(decl_info, DeclKind::FuncDecl { def: None }));
+        let def = lookup_or_insert_decl(ctx, decl_key, ty, ScopeSource::Ident);
+        if let DeclKind::FuncDecl { def: x } = &mut ctx.get_decl_mut(decl_key).kind {
+            *x = def;
+        }
+        return Ok(decl_key);
+    }
+
+    let register = decl_info
+        .storage
+        .as_ref()
+        .is_some_and(|x| x.kind == StorageSpecKind::Register);
+    check_align(ctx, &decl_info, register.then_some("a 'register' variable"))?;
+    check_noreturn(&decl_info)?;
+
+    // 块作用域的线程局部变量必须有静态存储期
+    if let Some(x) = &decl_info.thread_local
+        && matches!(default_storage_kind(ctx), StorageSpecKind::Auto)
+        && decl_info.storage.is_none()
+    {
+        let error = ParserError::error(THREAD_LOCAL_IN_BLOCK.to_owned(), x.span);
+        return Err(error);
+    }
+
     // 是否是定义
     let is_def = is_definition(ctx, &decl_info, has_init);
 
-    // 检查 init
-    todo!();
+    // 块作用域的 extern 不能初始化
+    if let Some(storage) = &decl_info.storage
+        && storage.kind.is_extern()
+        && has_init
+        && ctx.scope_mgr.get_kind() != ScopeKind::File
+    {
+        let error = ParserError::illegal_init(storage.to_string(), name.symbol, storage.span);
+        return Err(error);
+    }
 
-    // 构建decl
-    todo!();
+    let ty = decl_info.ty;
+    if !is_def {
+        // extern 声明
+        let decl_key = ctx.insert_decl(new_decl(decl_info, DeclKind::VarDecl { def: None }));
+        let def = lookup_or_insert_decl(ctx, decl_key, ty, ScopeSource::Ident);
+        if let DeclKind::VarDecl { def: x } = &mut ctx.get_decl_mut(decl_key).kind {
+            *x = def;
+        }
+        return Ok(decl_key);
+    }
+
+    // 变量定义，不完整数组的大小由初始值决定
+    let mut decl_info = decl_info;
+    if let Some(init) = &init_declarator.init {
+        decl_info.ty = act_on_initializer(ctx, ty, init, init_declarator.span)?;
+    }
+    let is_static = matches!(default_storage_kind(ctx), StorageSpecKind::Extern)
+        || decl_info.storage.as_ref().is_some_and(|x| x.kind == StorageSpecKind::Static);
+    if !is_static {
+        check_stack_align(ctx, &decl_info)?;
+    }
+    let kind = DeclKind::VarDef {
+        init: init_declarator.init,
+    };
+    // 定义的范围包括 initializer
+    let decl = Decl {
+        span: init_declarator.span,
+        ..new_decl(decl_info, kind)
+    };
+    let decl_key = ctx.insert_decl(decl);
+    insert_var_def(ctx, decl_key)?;
+
+    Ok(decl_key)
+}
+
+/// 解析record的成员，插入 member 作用域
+pub fn act_on_record_field(
+    ctx: &mut CompCtx,
+    struct_declarator: StructDeclarator,
+) -> ParserResult<DeclKey> {
+    // 成员不允许有 storage
+    if let Some(storage) = &struct_declarator.declarator.decl_spec.storage {
+        let msg = format!("type name does not allow storage class '{}'", storage);
+        return Err(ParserError::error(msg, storage.span));
+    }
+
+    let decl_info = resolve_declarator(ctx, struct_declarator.declarator)?;
+    let bit_field = struct_declarator.bit_field.is_some();
+    check_align(ctx, &decl_info, bit_field.then_some("a bit-field"))?;
+    check_noreturn(&decl_info)?;
+    let ty = decl_info.ty;
+    let name = decl_info.name.clone();
+    let kind = DeclKind::RecordField {
+        bit_field: struct_declarator.bit_field,
+    };
+    let mut decl = new_decl(decl_info, kind);
+    decl.span = struct_declarator.span;
+    let decl_key = ctx.insert_decl(decl);
+
+    // 匿名的位域不需要插入符号表
+    let Some(name) = name else {
+        return Ok(decl_key);
+    };
+    match ctx.scope_mgr.entry_local_member(name.symbol) {
+        Entry::Occupied(x) => {
+            let error = ParserError::redefinition(x.get().decl, name);
+            return Err(error);
+        }
+        Entry::Vacant(x) => {
+            x.insert(MemberSymbol {
+                name: name.symbol,
+                decl: decl_key,
+                ty: Some(ty),
+            });
+        }
+    }
+
+    Ok(decl_key)
+}
+
+/// 参数的类型调整，数组和函数调整为指针
+fn adjust_param_type(ctx: &mut CompCtx, ty: TypeKey) -> TypeKey {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Array { elem_ty, .. } => {
+            let elem_ty = *elem_ty;
+            ctx.type_ctx.get_pointer(elem_ty)
+        }
+        TypeKind::Function { .. } => ctx.type_ctx.get_pointer(ty),
+        _ => ty,
+    }
+}
+
+/// 类型参数，不负责插入符号表
+pub fn act_on_param_var(ctx: &mut CompCtx, declarator: Declarator) -> ParserResult<DeclKey> {
+    // storage只能是register
+    if let Some(storage) = &declarator.decl_spec.storage
+        && storage.kind != StorageSpecKind::Register
+    {
+        let msg = format!("invalid storage class specifier '{}' in parameter", storage);
+        return Err(ParserError::error(msg, storage.span));
+    }
+
+    let mut decl_info = resolve_declarator(ctx, declarator)?;
+    check_align(ctx, &decl_info, Some("a parameter"))?;
+    check_noreturn(&decl_info)?;
+    decl_info.ty = adjust_param_type(ctx, decl_info.ty);
+
+    let decl = new_decl(decl_info, DeclKind::ParamVar);
+    Ok(ctx.insert_decl(decl))
+}
+
+/// K&R 函数定义的参数，按标识符顺序取出声明，没有声明的参数默认为 int
+fn resolve_kr_params(
+    ctx: &mut CompCtx,
+    idents: &[Ident],
+    decl_list: &[DeclGroup],
+) -> ParserResult<Vec<DeclKey>> {
+    let mut decls: Vec<DeclKey> = decl_list
+        .iter()
+        .flat_map(|x| x.decls.iter().copied())
+        .collect();
+
+    let mut params = Vec::new();
+    for ident in idents {
+        let pos = decls
+            .iter()
+            .position(|x| ctx.get_decl(*x).name.as_ref().map(|x| x.symbol) == Some(ident.symbol));
+        let decl = match pos {
+            Some(pos) => decls.remove(pos),
+            None => {
+                let ty = ctx.type_ctx.get_int_type(IntegerSize::Int, true);
+                ctx.insert_decl(Decl {
+                    storage: None,
+                    func_spec: None,
+                    noreturn: None,
+                    visibility: None,
+                    thread_local: None,
+                    align: None,
+                    name: Some(ident.clone()),
+                    kind: DeclKind::ParamVar,
+                    ty,
+                    span: ident.span,
+                })
+            }
+        };
+        params.push(decl);
+    }
+
+    // 声明了不存在的参数
+    if let Some(decl) = decls.first() {
+        let decl = ctx.get_decl(*decl);
+        let name = decl.name.as_ref().map(|x| x.symbol.get()).unwrap_or("");
+        let msg = format!("parameter named '{}' is missing", name);
+        return Err(ParserError::error(msg, decl.span));
+    }
+
+    Ok(params)
+}
+
+/// 函数声明，在解析函数体前插入文件作用域，支持递归调用
+///
+/// # Returns
+/// `(DeclKey, Vec<DeclKey>)`: 函数声明和参数
+pub fn act_on_func_decl(
+    ctx: &mut CompCtx,
+    func_decl: FuncDecl,
+) -> ParserResult<(DeclKey, Vec<DeclKey>)> {
+    let mut declarator = func_decl.declarator;
+
+    // 最外层必须是函数
+    let chunk = match declarator.chunks.first_mut() {
+        Some(x) if matches!(x.kind, DeclaratorChunkKind::Function { .. }) => x,
+        _ => {
+            let msg = "expected ';' after top level declarator".to_owned();
+            return Err(ParserError::error(msg, declarator.span));
+        }
+    };
+    let DeclaratorChunkKind::Function { param } = &mut chunk.kind else {
+        unreachable!()
+    };
+
+    let params = match param {
+        ParamDecl::Params(list) => list.params.clone(),
+        ParamDecl::Idents(list) => {
+            let decl_list = func_decl.decl_list.unwrap_or_default();
+            let params = resolve_kr_params(ctx, &list.idents, &decl_list)?;
+            // 参数已经确定，按原型处理
+            *param = ParamDecl::Params(ParamList {
+                params: params.clone(),
+                is_variadic: false,
+                has_prototype: true,
+                span: chunk.span,
+            });
+            params
+        }
+    };
+
+    // 参数必须有名字
+    for param in params.iter() {
+        let decl = ctx.get_decl(*param);
+        if decl.name.is_none() {
+            let msg = "parameter name omitted".to_owned();
+            return Err(ParserError::error(msg, decl.span));
+        }
+    }
+
+    let decl_info = resolve_declarator(ctx, declarator)?;
+    check_align(ctx, &decl_info, Some("a function"))?;
+    let ty = decl_info.ty;
+    let mut decl = new_decl(decl_info, DeclKind::FuncDecl { def: None });
+    decl.span = func_decl.span;
+    let decl_key = ctx.insert_decl(decl);
+
+    let def = lookup_or_insert_decl(ctx, decl_key, ty, ScopeSource::Ident);
+    if let Some(prev) = def {
+        let name = ctx.get_decl(decl_key).name.clone().expect("impossible");
+        return Err(ParserError::redefinition(prev, name));
+    }
+
+    Ok((decl_key, params))
+}
 
-    // todo: 插入符号表
-    todo!();
+/// 参数插入函数作用域
+pub fn act_on_params(ctx: &mut CompCtx, params: &[DeclKey]) -> ParserResult<()> {
+    for param in params.iter().copied() {
+        insert_unique_ident(ctx, param)?;
+    }
+    Ok(())
 }
 
-// impl Sema {
-//
-//     /// 解析record的成员，插入decl
-//     pub fn act_on_record_field(
-//         &mut self,
-//         ctx: &mut CompCtx,
-//         struct_declarator: StructDeclarator,
-//     ) -> ParserResult<DeclKey> {
-//         let kind = DeclKind::RecordField {
-//             colon: struct_declarator.colon,
-//             bit_field: struct_declarator.bit_field,
-//         };
-//         let PartialDecl {
-//             storage,
-//             name,
-//             ty_key: ty,
-//         } = self.act_on_declarator(struct_declarator.declarator)?;
-//         let decl = ctx.insert_decl(Decl {
-//             storage,
-//             name,
-//             kind,
-//             ty,
-//             span: struct_declarator.span,
-//         });
-//         // 添加decl
-//         self.insert_decl(decl)?;
-//         Ok(decl)
-//     }
-//
-//     /// 解析枚举成员，插入符号表
-//     pub fn act_on_enumerator(
-//         &mut self,
-//         ctx: &mut CompCtx,
-//         enumerator: Enumerator,
-//     ) -> ParserResult<DeclKey> {
-//         let kind = DeclKind::EnumField {
-//             eq: enumerator.eq,
-//             expr: enumerator.expr,
-//         };
-//         let ty = self.type_context.get_int_type(IntegerSize::Int, true);
-//         let decl = ctx.insert_decl(Decl {
-//             storage: None,
-//             name: Some(enumerator.name),
-//             kind,
-//             ty,
-//             span: enumerator.span,
-//         });
-//         // 添加decl
-//         self.insert_decl(decl)?;
-//         Ok(decl)
-//     }
-//
-//     /// 类型参数
-//     pub fn act_on_param_var(
-//         &mut self,
-//         ctx: &mut CompCtx,
-//         declarator: Declarator,
-//     ) -> ParserResult<DeclKey> {
-//         let span = declarator.span;
-//         let PartialDecl {
-//             storage,
-//             name,
-//             ty_key: ty,
-//         } = self.act_on_param_declarator(declarator)?;
-//
-//         let kind = DeclKind::ParamVar;
-//         let decl = ctx.insert_decl(Decl {
-//             storage,
-//             name,
-//             kind,
-//             ty,
-//             span,
-//         });
-//         Ok(decl)
-//     }
-//
-//     /// 函数声明，添加函数声明和参数列表进入符号表
-//     pub fn act_on_func_decl(
-//         &mut self,
-//         ctx: &mut CompCtx,
-//         func_decl: FuncDecl,
-//     ) -> ParserResult<DeclKey> {
-//         let param = match func_decl.declarator.chunks.first() {
-//             Some(x) => x,
-//             None => {
-//                 // 这不是函数声明
-//                 todo!()
-//             }
-//         };
-//
-//         let param = match &param.kind {
-//             DeclaratorChunkKind::Function { param, .. } => param,
-//             _ => {
-//                 // 不是函数，出错
-//                 todo!()
-//             }
-//         };
-//
-//         let mut is_variadic = false;
-//         let mut params = Vec::new();
-//
-//         match param {
-//             ParamDecl::Params(x) => {
-//                 // 普通param类型声明
-//                 is_variadic = x.ellipsis.is_some();
-//                 params.extend(x.params.iter().cloned());
-//             }
-//             ParamDecl::Idents(x) => {
-//                 // K&R函数声明
-//                 let decl = match &func_decl.decl_list {
-//                     Some(x) => x,
-//                     None => {
-//                         // 这样一定出错
-//                         todo!()
-//                     }
-//                 };
-//
-//                 let mut name_map: FxHashMap<Ident, DeclKey> = FxHashMap::default();
-//
-//                 let decls = decl.into_iter().map(|x| &x.decls).flatten().cloned();
-//
-//                 for x in decls {
-//                     let decl = ctx.get_decl(x);
-//                     let name = match decl.name.clone() {
-//                         Some(x) => x,
-//                         None => {
-//                             // 不能没名字
-//                             todo!()
-//                         }
-//                     };
-//                     drop(decl);
-//                     name_map.insert(name, x);
-//                 }
-//
-//                 // 检查是否是一一对应
-//                 for x in &x.idents {
-//                     let decl = match name_map.remove(&x) {
-//                         Some(x) => x,
-//                         None => {
-//                             // 没有对应的出错
-//                             todo!()
-//                         }
-//                     };
-//                     params.push(decl);
-//                 }
-//
-//                 for (_, _decl) in name_map {
-//                     // 存在不存在的声明，出错
-//                     todo!()
-//                 }
-//             }
-//         };
-//
-//         let PartialDecl {
-//             storage,
-//             name,
-//             ty_key,
-//         } = self.act_on_param_declarator(func_decl.declarator)?;
-//         let mut decl_context = self.curr_decl.borrow_mut();
-//
-//         let ret_ty = match &ctx.get_type(ty_key).kind {
-//             TypeKind::Function { ret_ty, .. } => ctx.get_type(*ret_ty),
-//             _ => unreachable!(),
-//         };
-//
-//         // 将参数压入context
-//         for x in params.iter().copied() {
-//             // 参数没名字，直接出错
-//             if ctx.get_decl(x).name.is_none() {
-//                 todo!()
-//             }
-//             decl_context.insert(ctx, x)?;
-//         }
-//         drop(decl_context);
-//
-//         let kind = DeclKind::FuncRef;
-//
-//         let decl = ctx.insert_decl(Decl {
-//             storage,
-//             name,
-//             kind,
-//             ty: ty_key,
-//             span: func_decl.span,
-//         });
-//
-//         self.insert_parent(decl)?;
-//         Ok(decl)
-//     }
-//
-//     /// 将声明插入符号表
-//     pub fn act_on_record_ref(
-//         &mut self,
-//         ctx: &mut CompCtx,
-//         record_kind: Record,
-//         name: Ident,
-//         span: Span,
-//     ) -> ParserResult<()> {
-//         let ty = self.type_context.resolve_record_ref(&record_kind, &name)?;
-//         let kind = DeclKind::RecordRef { kind: record_kind };
-//         let decl = ctx.insert_decl(Decl {
-//             storage: None,
-//             ty,
-//             name: Some(name),
-//             kind,
-//             span,
-//         });
-//         self.insert_decl(decl)?;
-//         Ok(())
-//     }
-//
-//     /// 完成record声明或定义，会调用exit退出作用域 ,插入符号表
-//     pub fn act_on_finish_record(
-//         &mut self,
-//         ctx: &mut CompCtx,
-//         spec: StructSpec,
-//     ) -> ParserResult<DeclKey> {
-//         let decl_context = self.exit_decl();
-//
-//         let ty = self.type_context.resolve_record(&spec)?;
-//
-//         let kind = match spec.body {
-//             None => DeclKind::RecordRef { kind: spec.kind },
-//             Some(x) => DeclKind::Record {
-//                 kind: spec.kind,
-//                 l: x.l,
-//                 fields: x.groups,
-//                 r: x.r,
-//                 decl_context,
-//             },
-//         };
-//
-//         let decl = ctx.insert_decl(Decl {
-//             storage: None,
-//             ty,
-//             name: spec.name,
-//             kind,
-//             span: spec.span,
-//         });
-//
-//         self.insert_decl(decl)?;
-//         Ok(decl)
-//     }
-//
-//     /// 完成enum声明/定义，会调用exit退出作用域 enum插入符号表
-//     pub fn act_on_finish_enum(
-//         &mut self,
-//         ctx: &mut CompCtx,
-//         spec: EnumSpec,
-//     ) -> ParserResult<DeclKey> {
-//         let decl_context = self.exit_decl();
-//         let ty = self.type_context.resolve_enum(&spec)?;
-//         let tkw = spec.enum_span;
-//         let kind = match spec.enums {
-//             None => DeclKind::EnumRef { kw },
-//             Some(x) => DeclKind::Enum {
-//                 kw,
-//                 l: x.l,
-//                 enums: x.decls,
-//                 commas: x.commas,
-//                 r: x.r,
-//                 decl_context,
-//             },
-//         };
-//
-//         let decl = ctx.insert_decl(Decl {
-//             storage: None,
-//             ty,
-//             name: spec.name,
-//             kind,
-//             span: spec.span,
-//         });
-//
-//         self.insert_decl(decl)?;
-//         Ok(decl)
-//     }
-//
-//     /// 解析declarator
-//     pub fn act_on_declarator(&mut self, declarator: Declarator) -> ParserResult<PartialDecl> {
-//         let kind = self.curr_decl.borrow().get_kind();
-//         let decl = match kind {
-//             DeclContextKind::File => self.act_on_file_declarator(declarator)?,
-//             DeclContextKind::Block => self.act_on_block_declarator(declarator)?,
-//             DeclContextKind::Record => self.act_on_struct_declarator(declarator)?,
-//             DeclContextKind::Enum => unreachable!(), // enum 内部没有 declarator
-//         };
-//
-//         Ok(decl)
-//     }
-//
-//     fn act_on_file_declarator(&mut self, declarator: Declarator) -> ParserResult<PartialDecl> {
-//         // 默认extern
-//         let storage = declarator
-//             .decl_spec
-//             .storage
-//             .clone()
-//             .unwrap_or(StorageSpec::from_kind(StorageSpecKind::Extern));
-//         let name = declarator.name.clone();
-//         let ty_key = self.type_context.resolve_declarator(&declarator)?;
-//
-//         // 顶级声明不能有auto和register
-//         match &storage.kind {
-//             StorageSpecKind::Auto | StorageSpecKind::Register => {
-//                 todo!()
-//             }
-//             _ => {}
-//         }
-//
-//         let result = PartialDecl {
-//             storage: Some(storage),
-//             name,
-//             ty_key,
-//         };
-//         Ok(result)
-//     }
-//
-//     fn act_on_block_declarator(&mut self, declarator: Declarator) -> ParserResult<PartialDecl> {
-//         // 默认auto
-//         let storage = declarator
-//             .decl_spec
-//             .storage
-//             .clone()
-//             .unwrap_or(StorageSpec::from_kind(StorageSpecKind::Auto));
-//         let name = declarator.name.clone();
-//         let ty = self.type_context.resolve_declarator(&declarator)?;
-//
-//         let result = PartialDecl {
-//             storage: Some(storage),
-//             name,
-//             ty_key: ty,
-//         };
-//         Ok(result)
-//     }
-//
-//     fn act_on_struct_declarator(&mut self, declarator: Declarator) -> ParserResult<PartialDecl> {
-//         // 不允许任何storage声明
-//         if declarator.decl_spec.storage.is_some() {
-//             todo!()
-//         }
-//
-//         let name = declarator.name.clone();
-//         let ty = self.type_context.resolve_declarator(&declarator)?;
-//
-//         let result = PartialDecl {
-//             storage: None,
-//             name,
-//             ty_key: ty,
-//         };
-//         Ok(result)
-//     }
-//
-//     fn act_on_param_declarator(&mut self, declarator: Declarator) -> ParserResult<PartialDecl> {
-//         let storage = declarator.decl_spec.storage.clone(); // 没有默认storage
-//         let name = declarator.name.clone();
-//         let ty = self.type_context.resolve_declarator(&declarator)?;
-//
-//         // storage只能是register
-//         match &storage {
-//             Some(x) => match x.kind {
-//                 StorageSpecKind::Typedef
-//                 | StorageSpecKind::Extern
-//                 | StorageSpecKind::Static
-//                 | StorageSpecKind::Auto => {
-//                     todo!()
-//                 }
-//                 _ => {}
-//             },
-//             None => {}
-//         }
-//
-//         let result = PartialDecl {
-//             storage,
-//             name,
-//             ty_key: ty,
-//         };
-//         Ok(result)
-//     }
-// }
+/// 函数定义，`decl` 为 `act_on_func_decl` 的结果，回填所有前向声明
+pub fn act_on_func_def(
+    ctx: &mut CompCtx,
+    decl: DeclKey,
+    params: Vec<DeclKey>,
+    body: StmtKey,
+    span: Span,
+) -> ParserResult<DeclKey> {
+    let body = Box::new(Stmt::clone(ctx.get_stmt(body)));
+    let mut def = ctx.get_decl(decl).clone();
+    def.kind = DeclKind::FuncDef {
+        inline: def.func_spec.clone(),
+        params,
+        body,
+    };
+    def.span = span;
+    let ty = def.ty;
+    let def = ctx.insert_decl(def);
+
+    let decls = lookup_or_insert_def(ctx, def, ty, ScopeSource::Ident)?;
+    for decl in decls {
+        if let DeclKind::FuncDecl { def: x } = &mut ctx.get_decl_mut(decl).kind {
+            *x = Some(def);
+        }
+    }
+
+    Ok(def)
+}
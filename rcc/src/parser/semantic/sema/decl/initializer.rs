@@ -0,0 +1,537 @@
+use crate::err::parser_error::{ErrorKind, ParserError, ParserResult};
+use crate::lex::types::token_kind::LiteralKind;
+use crate::parser::ast::common::RecordKind;
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{ExprKind, MemberDesignator};
+use crate::parser::ast::types::{
+    ArraySize, BitFieldLayout, FieldLayout, IntegerSize, RecordLayout, TypeKind, TypeLayout,
+};
+use crate::parser::ast::{ExprKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::semantic::sema::type_ctx::type_builder::{TypeBuilder, TypeBuilderKind};
+use crate::types::span::Span;
+use crate::util::literal;
+use rustc_hash::FxHashSet;
+
+///
+/// 展开后的初始值，偏移相对于被初始化的对象
+/// - `Scalar`: 标量，或者用表达式初始化的 struct / union
+/// - `BitField`: 位域，`offset` 是存储单元的偏移
+/// - `String`: 用字符串字面量初始化字符数组，只使用前 `len` 个字节
+///
+#[derive(Debug, Clone)]
+pub enum InitElem {
+    Scalar {
+        offset: u64,
+        ty: TypeKey,
+        expr: ExprKey,
+    },
+    BitField {
+        offset: u64,
+        ty: TypeKey,
+        bit_field: BitFieldLayout,
+        expr: ExprKey,
+    },
+    String {
+        offset: u64,
+        bytes: Vec<u8>,
+        len: u64,
+        expr: ExprKey,
+    },
+}
+
+impl InitElem {
+    /// 初始化的位置，位域还要区分存储单元中的位
+    fn slot(&self) -> (u64, Option<usize>) {
+        match self {
+            InitElem::Scalar { offset, .. } | InitElem::String { offset, .. } => (*offset, None),
+            InitElem::BitField {
+                offset, bit_field, ..
+            } => (*offset, Some(bit_field.bit_offset)),
+        }
+    }
+
+    pub fn expr(&self) -> Option<ExprKey> {
+        match self {
+            InitElem::Scalar { expr, .. }
+            | InitElem::BitField { expr, .. }
+            | InitElem::String { expr, .. } => Some(*expr),
+        }
+    }
+}
+
+/// 字符串字面量的内容
+fn string_literal(ctx: &CompCtx, expr: ExprKey) -> Option<Vec<u8>> {
+    match &ctx.get_expr(expr).kind {
+        ExprKind::Literal(LiteralKind::String { value }) => {
+            Some(literal::string_bytes(value.get()))
+        }
+        _ => None,
+    }
+}
+
+pub fn is_aggregate(ctx: &CompCtx, ty: TypeKey) -> bool {
+    matches!(
+        ctx.type_ctx.get_type(ty).kind,
+        TypeKind::Array { .. } | TypeKind::Record { .. }
+    )
+}
+
+fn size_of(ctx: &CompCtx, ty: TypeKey) -> u64 {
+    TypeLayout::of(ctx, ty).size as u64
+}
+
+fn excess_init(span: Span) -> ParserError {
+    ParserError::error("excess elements in initializer".to_owned(), span)
+}
+
+fn designator_span(ctx: &CompCtx, designator: &MemberDesignator) -> Span {
+    match designator {
+        MemberDesignator::Field(x) => x.span,
+        MemberDesignator::Index(x) => ctx.get_expr(*x).span,
+    }
+}
+
+/// 可以初始化的成员，跳过匿名的位域
+fn record_fields(layout: &RecordLayout) -> impl Iterator<Item = &FieldLayout> {
+    layout
+        .fields
+        .iter()
+        .filter(|x| x.name.is_some() || x.bit_field.is_none())
+}
+
+///
+/// 聚合类型的一个成员
+///
+/// # Members
+/// - `index`: 数组下标或者第几个可以初始化的成员
+/// - `offset`: 相对于聚合类型的偏移
+///
+struct Member {
+    index: usize,
+    ty: TypeKey,
+    offset: u64,
+    bit_field: Option<BitFieldLayout>,
+}
+
+impl Member {
+    fn field(index: usize, field: &FieldLayout) -> Self {
+        Self {
+            index,
+            ty: field.ty,
+            offset: field.offset as u64,
+            bit_field: field.bit_field,
+        }
+    }
+}
+
+///
+/// 把初始化列表展开为每个标量的初始值，处理花括号省略
+///
+/// # Members
+/// - `elems`: 展开的结果，未初始化的部分为 0
+/// - `span`: 声明的位置，用于报错
+///
+struct Flatten<'a> {
+    ctx: &'a CompCtx,
+    elems: Vec<InitElem>,
+    span: Span,
+}
+
+impl Flatten<'_> {
+    fn not_aggregate(&self, designators: &[MemberDesignator]) -> ParserError {
+        let msg = "designator in initializer for scalar type";
+        let span = designator_span(self.ctx, &designators[0]);
+        ParserError::error(msg.to_owned(), span)
+    }
+
+    /// 用字符串字面量初始化字符数组
+    fn is_string_init(&self, ty: TypeKey, expr: ExprKey) -> bool {
+        let is_char_array = match &self.ctx.type_ctx.get_type(ty).kind {
+            TypeKind::Array { elem_ty, .. } => matches!(
+                self.ctx.type_ctx.get_type(*elem_ty).kind,
+                TypeKind::Integer {
+                    size: IntegerSize::Char,
+                    ..
+                }
+            ),
+            _ => false,
+        };
+        is_char_array && string_literal(self.ctx, expr).is_some()
+    }
+
+    /// 用同类型的 struct / union 表达式初始化
+    fn is_record_copy(&self, ty: TypeKey, expr: ExprKey) -> bool {
+        let expr_ty = self.ctx.get_expr(expr).ty;
+        match (
+            &self.ctx.type_ctx.get_type(ty).kind,
+            &self.ctx.type_ctx.get_type(expr_ty).kind,
+        ) {
+            (TypeKind::Record { id: a, .. }, TypeKind::Record { id: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+
+    /// 初始化 `offset` 处 `ty` 类型的对象，返回数组的元素个数（用于确定不完整数组的大小）
+    fn init(
+        &mut self,
+        ty: TypeKey,
+        offset: u64,
+        bit_field: Option<BitFieldLayout>,
+        init: &Initializer,
+    ) -> ParserResult<u64> {
+        let list = match init.value() {
+            Initializer::Expr(expr) => return self.expr(ty, offset, bit_field, *expr),
+            Initializer::InitList { inits } => &inits.inits,
+            Initializer::Designated { .. } => unreachable!("nested designated initializer"),
+        };
+
+        // 标量也可以加花括号 `int x = {1};`
+        if !is_aggregate(self.ctx, ty) {
+            return match list.as_slice() {
+                [] => Ok(1),
+                [x] if !x.designators().is_empty() => Err(self.not_aggregate(x.designators())),
+                [x] => self.init(ty, offset, bit_field, x),
+                _ => Err(excess_init(self.span)),
+            };
+        }
+        // `char s[] = {"abc"};`
+        if let [Initializer::Expr(expr)] = list.as_slice()
+            && self.is_string_init(ty, *expr)
+        {
+            return self.expr(ty, offset, None, *expr);
+        }
+
+        let mut pos = 0;
+        let count = self.aggregate(ty, offset, list, &mut pos, None)?;
+        if pos < list.len() {
+            return Err(excess_init(self.span));
+        }
+        Ok(count)
+    }
+
+    fn expr(
+        &mut self,
+        ty: TypeKey,
+        offset: u64,
+        bit_field: Option<BitFieldLayout>,
+        expr: ExprKey,
+    ) -> ParserResult<u64> {
+        if self.is_string_init(ty, expr) {
+            let bytes = string_literal(self.ctx, expr).unwrap();
+            // `char s[3] = "abc";` 不保留结尾的 0
+            let len = match &self.ctx.type_ctx.get_type(ty).kind {
+                TypeKind::Array {
+                    size: ArraySize::Static(n),
+                    ..
+                } => *n as u64,
+                _ => bytes.len() as u64,
+            };
+            self.elems.push(InitElem::String {
+                offset,
+                bytes,
+                len,
+                expr,
+            });
+            return Ok(len);
+        }
+        if matches!(self.ctx.type_ctx.get_type(ty).kind, TypeKind::Array { .. }) {
+            let span = self.ctx.get_expr(expr).span;
+            let msg = "array initializer must be an initializer list or string literal";
+            return Err(ParserError::error(msg.to_owned(), span));
+        }
+
+        let elem = match bit_field {
+            Some(bit_field) => InitElem::BitField {
+                offset,
+                ty,
+                bit_field,
+                expr,
+            },
+            None => InitElem::Scalar { offset, ty, expr },
+        };
+        self.elems.push(elem);
+        Ok(1)
+    }
+
+    ///
+    /// 用 `list[pos..]` 依次初始化聚合类型的成员，省略花括号时只消耗需要的部分
+    ///
+    /// 指示符属于花括号所在的一层：`designators` 为 `None` 时当前层就是花括号的这一层，
+    /// 由它处理每一项的指示符；否则是省略了花括号或者嵌套指示符 `.a.b` 的子对象，
+    /// `designators` 是第一项剩下的指示符，遇到之后带指示符的项就返回上一层
+    ///
+    fn aggregate(
+        &mut self,
+        ty: TypeKey,
+        offset: u64,
+        list: &[Initializer],
+        pos: &mut usize,
+        designators: Option<&[MemberDesignator]>,
+    ) -> ParserResult<u64> {
+        let braced = designators.is_none();
+        let mut first = designators;
+        let mut index = 0;
+        let mut count = 0;
+        while *pos < list.len() {
+            let designators = match first.take() {
+                Some(x) => x,
+                None if braced => list[*pos].designators(),
+                None if list[*pos].designators().is_empty() => &[],
+                None => break,
+            };
+            let member = match designators.split_first() {
+                Some((designator, rest)) => {
+                    let (member, implicit) = self.designate(ty, designator)?;
+                    index = member.index;
+                    // 匿名 struct / union 中的成员，指示符留给匿名成员处理
+                    let rest = if implicit { designators } else { rest };
+                    Some((member, rest))
+                }
+                None => self.nth_member(ty, index).map(|x| (x, designators)),
+            };
+            let Some((member, rest)) = member else {
+                break;
+            };
+
+            let offset = offset + member.offset;
+            match rest.is_empty() {
+                true => self.member(member.ty, offset, member.bit_field, list, pos)?,
+                false if is_aggregate(self.ctx, member.ty) => {
+                    self.aggregate(member.ty, offset, list, pos, Some(rest))?;
+                }
+                false => return Err(self.not_aggregate(rest)),
+            }
+            index += 1;
+            count = count.max(index);
+        }
+
+        // union 只有一个成员，struct 只有一个对象
+        match self.ctx.type_ctx.get_type(ty).kind {
+            TypeKind::Array { .. } => Ok(count as u64),
+            _ => Ok(1),
+        }
+    }
+
+    /// 第 `index` 个成员，超出范围时返回 None
+    fn nth_member(&self, ty: TypeKey, index: usize) -> Option<Member> {
+        match &self.ctx.type_ctx.get_type(ty).kind {
+            TypeKind::Array { elem_ty, size } => {
+                if let ArraySize::Static(n) = size
+                    && index >= *n
+                {
+                    return None;
+                }
+                Some(Member {
+                    index,
+                    ty: *elem_ty,
+                    offset: index as u64 * size_of(self.ctx, *elem_ty),
+                    bit_field: None,
+                })
+            }
+            _ => {
+                // union 只初始化第一个成员
+                let layout = RecordLayout::of(self.ctx, ty)?;
+                if layout.kind == RecordKind::Union && index > 0 {
+                    return None;
+                }
+                let field = record_fields(&layout).nth(index)?;
+                Some(Member::field(index, field))
+            }
+        }
+    }
+
+    ///
+    /// 指示符指定的成员
+    ///
+    /// 成员在匿名 struct / union 中时返回匿名成员，同时返回 true
+    ///
+    fn designate(
+        &self,
+        ty: TypeKey,
+        designator: &MemberDesignator,
+    ) -> ParserResult<(Member, bool)> {
+        let kind = &self.ctx.type_ctx.get_type(ty).kind;
+        match (kind, designator) {
+            (TypeKind::Array { .. }, MemberDesignator::Index(expr)) => {
+                let expr = self.ctx.get_expr(*expr);
+                let Some(value) = expr.value.as_ref().and_then(|x| x.as_intager()) else {
+                    return Err(ParserError::not_int_constant(expr.span));
+                };
+                let index = value.as_i128();
+                let member = usize::try_from(index)
+                    .ok()
+                    .and_then(|x| self.nth_member(ty, x));
+                let Some(member) = member else {
+                    let msg = format!("array index {} is out of bounds", index);
+                    return Err(ParserError::error(msg, expr.span));
+                };
+                Ok((member, false))
+            }
+            (TypeKind::Record { .. }, MemberDesignator::Field(name)) => {
+                let Some(layout) = RecordLayout::of(self.ctx, ty) else {
+                    let msg = format!(
+                        "incomplete definition of type '{}'",
+                        self.ctx.type_ctx.get_type(ty).to_code(self.ctx)
+                    );
+                    return Err(ParserError::error(msg, name.span));
+                };
+                let found = record_fields(&layout)
+                    .enumerate()
+                    .find(|(_, x)| match x.name {
+                        Some(field) => field == name.symbol,
+                        None => RecordLayout::of(self.ctx, x.ty)
+                            .is_some_and(|x| x.find_field(self.ctx, name.symbol).is_some()),
+                    });
+                let Some((index, field)) = found else {
+                    let kind = ErrorKind::NoMember {
+                        field: name.symbol.get().to_owned(),
+                        ty: self.ctx.type_ctx.get_type(ty).to_code(self.ctx),
+                    };
+                    return Err(ParserError::new(kind, name.span));
+                };
+                Ok((Member::field(index, field), field.name.is_none()))
+            }
+            (_, MemberDesignator::Field(name)) => {
+                let msg = "field designator cannot initialize a non-struct, non-union type";
+                Err(ParserError::error(msg.to_owned(), name.span))
+            }
+            (_, MemberDesignator::Index(expr)) => {
+                let msg = "array designator cannot initialize non-array type";
+                Err(ParserError::error(
+                    msg.to_owned(),
+                    self.ctx.get_expr(*expr).span,
+                ))
+            }
+        }
+    }
+
+    /// 初始化一个成员，成员是聚合类型而初始值不是花括号时省略了花括号
+    fn member(
+        &mut self,
+        ty: TypeKey,
+        offset: u64,
+        bit_field: Option<BitFieldLayout>,
+        list: &[Initializer],
+        pos: &mut usize,
+    ) -> ParserResult<()> {
+        let item = list[*pos].value();
+        if let Initializer::Expr(expr) = item
+            && is_aggregate(self.ctx, ty)
+            && !self.is_string_init(ty, *expr)
+            && !self.is_record_copy(ty, *expr)
+        {
+            self.aggregate(ty, offset, list, pos, Some(&[]))?;
+            return Ok(());
+        }
+        *pos += 1;
+        self.init(ty, offset, bit_field, item)?;
+        Ok(())
+    }
+}
+
+/// 展开初始值，同时返回对象的大小，不完整数组的大小由初始值决定
+pub fn flatten_init(
+    ctx: &CompCtx,
+    ty: TypeKey,
+    init: &Initializer,
+    span: Span,
+) -> ParserResult<(Vec<InitElem>, u64)> {
+    let mut flatten = Flatten {
+        ctx,
+        elems: Vec::new(),
+        span,
+    };
+    let count = flatten.init(ty, 0, None, init)?;
+    let size = match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Array {
+            elem_ty,
+            size: ArraySize::Incomplete,
+        } => count * size_of(ctx, *elem_ty),
+        _ => size_of(ctx, ty),
+    };
+
+    // 指示符可以重复初始化同一个成员 `{[0] = 1, [0] = 2}`，后面的覆盖前面的
+    let mut seen = FxHashSet::default();
+    let mut elems: Vec<_> = flatten
+        .elems
+        .into_iter()
+        .rev()
+        .filter(|x| seen.insert(x.slot()))
+        .collect();
+    elems.reverse();
+    Ok((elems, size))
+}
+
+///
+/// 检查初始值，不完整数组的大小由初始值决定，返回补全后的类型
+///
+/// `char s[] = "abc";` 是 `char [4]`，`int a[] = {1, 2, 3};` 是 `int [3]`
+///
+pub fn act_on_initializer(
+    ctx: &mut CompCtx,
+    ty: TypeKey,
+    init: &Initializer,
+    span: Span,
+) -> ParserResult<TypeKey> {
+    let (_, size) = flatten_init(ctx, ty, init, span)?;
+    let array = ctx.type_ctx.get_type(ty);
+    let TypeKind::Array {
+        elem_ty,
+        size: ArraySize::Incomplete,
+    } = array.kind
+    else {
+        return Ok(ty);
+    };
+    let qual = array.qual;
+
+    let len = match size_of(ctx, elem_ty) {
+        0 => 0,
+        x => size / x,
+    };
+    let kind = TypeBuilderKind::Array {
+        elem_ty,
+        size: ArraySize::Static(len as usize),
+    };
+    let builder = TypeBuilder::new_with_qual(qual, kind);
+    ctx.type_ctx
+        .build_type(builder)
+        .map_err(|err| ParserError::from_type_error(err, span))
+}
+
+///
+/// 检查复合字面量 `(type){...}`，返回补全后的类型
+///
+/// 类型必须是对象类型或者不完整数组，不能是变长数组
+///
+pub fn act_on_compound_literal(
+    ctx: &mut CompCtx,
+    ty: TypeKey,
+    init: &Initializer,
+    span: Span,
+) -> ParserResult<TypeKey> {
+    let target = ctx.type_ctx.get_type(ty);
+    let msg = match &target.kind {
+        TypeKind::Array {
+            size: ArraySize::VLA,
+            ..
+        } => Some("compound literal cannot be of variable-length array type".to_owned()),
+        TypeKind::Array {
+            size: ArraySize::Incomplete,
+            ..
+        } => None,
+        TypeKind::Function { .. } => Some(format!(
+            "compound literal has function type '{}'",
+            target.to_code(ctx)
+        )),
+        _ if !target.is_complete() => Some(format!(
+            "compound literal has incomplete type '{}'",
+            target.to_code(ctx)
+        )),
+        _ => None,
+    };
+    match msg {
+        Some(msg) => Err(ParserError::error(msg, span)),
+        None => act_on_initializer(ctx, ty, init, span),
+    }
+}
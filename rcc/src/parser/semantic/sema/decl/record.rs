@@ -1,15 +1,18 @@
 use crate::err::scope_error::ScopeSource;
 use crate::parser::ast::TypeKey;
 use crate::parser::ast::common::{RecordKind, StructOrUnion};
+use crate::parser::ast::types::TypeKind;
+use crate::parser::semantic::decl_spec::Enumerator;
 use crate::parser::semantic::sema::scope::lookup::{
     conflict_error_if, lookup_or_insert_decl, lookup_or_insert_def,
 };
+use crate::parser::semantic::sema::scope::scope_struct::ScopeSymbol;
 use crate::{
     err::parser_error::{ParserError, ParserResult},
     parser::{
         ast::{
             DeclKey,
-            decl::{Decl, DeclKind},
+            decls::decl::{Decl, DeclKind},
         },
         common::Ident,
         comp_ctx::CompCtx,
@@ -17,6 +20,7 @@ use crate::{
     },
     types::span::Span,
 };
+use std::collections::hash_map::Entry;
 
 /// decl 是否是 enum ，如果不是返回 DeclNotMatch 错误
 fn is_enum(ctx: &CompCtx, ty: TypeKey) -> bool {
@@ -33,6 +37,24 @@ fn is_record(ctx: &CompCtx, kind: RecordKind, ty: TypeKey) -> bool {
         .unwrap_or(false)
 }
 
+/// 构建一个新的 enum 类型
+pub fn new_enum_type(ctx: &mut CompCtx, span: Span) -> ParserResult<TypeKey> {
+    let kind = TypeBuilderKind::new_enum(ctx);
+    let builder = TypeBuilder::new(kind);
+    ctx.type_ctx
+        .build_type(builder)
+        .map_err(|err| ParserError::from_type_error(err, span))
+}
+
+/// 构建一个新的 record 类型
+pub fn new_record_type(ctx: &mut CompCtx, kind: RecordKind, span: Span) -> ParserResult<TypeKey> {
+    let kind = TypeBuilderKind::new_record(ctx, kind);
+    let builder = TypeBuilder::new(kind);
+    ctx.type_ctx
+        .build_type(builder)
+        .map_err(|err| ParserError::from_type_error(err, span))
+}
+
 /// 在当前作用域插入 enum 声明
 pub fn insert_enum_decl(ctx: &mut CompCtx, name: Ident, span: Span) -> ParserResult<DeclKey> {
     // 查询同级是否已经存在声明
@@ -43,20 +65,19 @@ pub fn insert_enum_decl(ctx: &mut CompCtx, name: Ident, span: Span) -> ParserRes
             conflict_error_if(is_enum(ctx, x.ty), &name, x.get_decl(), ScopeSource::Tag)?;
             x.ty
         }
-        None => {
-            // 不存在，构建类型
-            let kind = TypeBuilderKind::new_enum(ctx);
-            let builder = TypeBuilder::new(kind);
-            ctx.type_ctx
-                .build_type(builder)
-                .map_err(|err| ParserError::from_type_error(err, span))?
-        }
+        // 不存在，构建类型
+        None => new_enum_type(ctx, span)?,
     };
 
     // 构造 DeclDecl
-    let kind = DeclKind::EnumDef { enums: None };
+    let kind = DeclKind::EnumDecl { def: None };
     let decl = Decl {
         storage: None,
+        func_spec: None,
+        noreturn: None,
+        visibility: None,
+        thread_local: None,
+        align: None,
         kind,
         name: Some(name.clone()),
         ty,
@@ -79,6 +100,17 @@ pub fn insert_enum_decl(ctx: &mut CompCtx, name: Ident, span: Span) -> ParserRes
     Ok(decl_key)
 }
 
+/// 引用 enum `enum E x;`，所有作用域中都找不到时在当前作用域声明
+pub fn act_on_enum_ref(ctx: &mut CompCtx, name: Ident, span: Span) -> ParserResult<DeclKey> {
+    match ctx.scope_mgr.lookup_tag(&name) {
+        Some(x) => {
+            conflict_error_if(is_enum(ctx, x.ty), &name, x.get_decl(), ScopeSource::Tag)?;
+            Ok(x.get_decl())
+        }
+        None => insert_enum_decl(ctx, name, span),
+    }
+}
+
 /// 在当前作用域插入 record 声明
 pub fn insert_record_decl(
     ctx: &mut CompCtx,
@@ -99,14 +131,8 @@ pub fn insert_record_decl(
             )?;
             x.ty
         }
-        None => {
-            // 不存在，构建类型
-            let kind = TypeBuilderKind::new_enum(ctx);
-            let builder = TypeBuilder::new(kind);
-            ctx.type_ctx
-                .build_type(builder)
-                .map_err(|err| ParserError::from_type_error(err, span))?
-        }
+        // 不存在，构建类型
+        None => new_record_type(ctx, record.kind, span)?,
     };
 
     // 构造 DeclDecl
@@ -116,6 +142,11 @@ pub fn insert_record_decl(
     };
     let decl = Decl {
         storage: None,
+        func_spec: None,
+        noreturn: None,
+        visibility: None,
+        thread_local: None,
+        align: None,
         kind,
         name: Some(name.clone()),
         ty,
@@ -131,13 +162,34 @@ pub fn insert_record_decl(
     // 设置 definition
     let decl = ctx.get_decl_mut(decl_key);
     match &mut decl.kind {
-        DeclKind::EnumDecl { def } => *def = record_def,
+        DeclKind::RecordDecl { def, .. } => *def = record_def,
         _ => unreachable!(),
     }
 
     Ok(decl_key)
 }
 
+/// 引用 record `struct S *p;`，所有作用域中都找不到时在当前作用域声明
+pub fn act_on_record_ref(
+    ctx: &mut CompCtx,
+    record: StructOrUnion,
+    name: Ident,
+    span: Span,
+) -> ParserResult<DeclKey> {
+    match ctx.scope_mgr.lookup_tag(&name) {
+        Some(x) => {
+            conflict_error_if(
+                is_record(ctx, record.kind, x.ty),
+                &name,
+                x.get_decl(),
+                ScopeSource::Tag,
+            )?;
+            Ok(x.get_decl())
+        }
+        None => insert_record_decl(ctx, record, name, span),
+    }
+}
+
 /// 填充 record 的前向声明
 pub fn fill_record_fwd_ref(ctx: &mut CompCtx, definition: DeclKey, decls: Vec<DeclKey>) {
     for decl in decls.into_iter() {
@@ -152,56 +204,133 @@ pub fn fill_record_fwd_ref(ctx: &mut CompCtx, definition: DeclKey, decls: Vec<De
     }
 }
 
-/// 插入 record 定义
+/// 填充 enum 的前向声明
+fn fill_enum_fwd_ref(ctx: &mut CompCtx, definition: DeclKey, decls: Vec<DeclKey>) {
+    for decl in decls.into_iter() {
+        match &mut ctx.get_decl_mut(decl).kind {
+            DeclKind::EnumDecl { def } => *def = Some(definition),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// 插入 record 定义，`ty` 是前向声明的类型，匿名 record 不插入符号表
 pub fn insert_record_def(
     ctx: &mut CompCtx,
+    ty: TypeKey,
     kind: DeclKind,
-    name: Ident,
+    name: Option<Ident>,
     span: Span,
 ) -> ParserResult<DeclKey> {
     debug_assert!(kind.is_record_def());
-    let record_kind = match &kind {
-        DeclKind::RecordDef { kind, .. } => kind.kind.clone(),
-        _ => unreachable!(),
+
+    // 构建 decl
+    let decl = Decl {
+        storage: None,
+        func_spec: None,
+        noreturn: None,
+        visibility: None,
+        thread_local: None,
+        align: None,
+        kind,
+        name,
+        ty,
+        span,
     };
 
-    let symbol = ctx.scope_mgr.lookup_local_tag(name.symbol);
-    let ty = match symbol {
-        Some(x) => {
-            // 检查tag声明是否相同
-            conflict_error_if(
-                is_record(ctx, record_kind, x.ty),
-                &name,
-                x.get_decl(),
-                ScopeSource::Tag,
-            )?;
-            x.ty
-        }
-        None => {
-            // 不存在，构建类型
-            let kind = TypeBuilderKind::new_enum(ctx);
-            let builder = TypeBuilder::new(kind);
-            ctx.type_ctx
-                .build_type(builder)
-                .map_err(|err| ParserError::from_type_error(err, span))?
-        }
+    let def = ctx.insert_decl(decl);
+
+    if ctx.get_decl(def).name.is_some() {
+        // 添加到符号表
+        let decls = lookup_or_insert_def(ctx, def, ty, ScopeSource::Tag)?;
+        // 填充前向引用
+        fill_record_fwd_ref(ctx, def, decls);
+    }
+
+    // 类型指向定义
+    let id = match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Record { id, .. } => *id,
+        _ => unreachable!(),
     };
+    ctx.type_ctx.set_record_def(id, def);
 
-    // 构建 decl
+    Ok(def)
+}
+
+/// 插入 enum 定义，`ty` 是前向声明的类型，匿名 enum 不插入符号表
+pub fn insert_enum_def(
+    ctx: &mut CompCtx,
+    ty: TypeKey,
+    enums: Vec<DeclKey>,
+    name: Option<Ident>,
+    span: Span,
+) -> ParserResult<DeclKey> {
     let decl = Decl {
         storage: None,
-        kind,
-        name: Some(name.clone()),
+        func_spec: None,
+        noreturn: None,
+        visibility: None,
+        thread_local: None,
+        align: None,
+        kind: DeclKind::EnumDef { enums: Some(enums) },
+        name,
         ty,
         span,
     };
 
     let def = ctx.insert_decl(decl);
 
-    // 添加到符号表
-    let decls = lookup_or_insert_def(ctx, def, ty, ScopeSource::Tag)?;
-    // 填充前向引用
-    fill_record_fwd_ref(ctx, def, decls);
+    if ctx.get_decl(def).name.is_some() {
+        let decls = lookup_or_insert_def(ctx, def, ty, ScopeSource::Tag)?;
+        fill_enum_fwd_ref(ctx, def, decls);
+    }
+
+    let id = match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Enum { id, .. } => *id,
+        _ => unreachable!(),
+    };
+    ctx.type_ctx.set_enum_def(id, def);
 
     Ok(def)
 }
+
+/// 枚举成员，类型为所属的 enum，插入当前作用域
+pub fn act_on_enumerator(
+    ctx: &mut CompCtx,
+    enumerator: Enumerator,
+    ty: TypeKey,
+) -> ParserResult<DeclKey> {
+    let name = enumerator.name;
+    let decl = Decl {
+        storage: None,
+        func_spec: None,
+        noreturn: None,
+        visibility: None,
+        thread_local: None,
+        align: None,
+        kind: DeclKind::EnumField {
+            expr: enumerator.expr,
+        },
+        name: Some(name.clone()),
+        ty,
+        span: enumerator.span,
+    };
+    let decl_key = ctx.insert_decl(decl);
+
+    match ctx.scope_mgr.entry_local_ident(name.symbol) {
+        Entry::Occupied(x) => {
+            let error = ParserError::redefinition(x.get().get_decl(), name);
+            return Err(error);
+        }
+        Entry::Vacant(x) => {
+            x.insert(ScopeSymbol {
+                name: name.symbol,
+                decls: Vec::new(),
+                def: Some(decl_key),
+                ty,
+            });
+        }
+    }
+
+    Ok(decl_key)
+}
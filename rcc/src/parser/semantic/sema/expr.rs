@@ -1,5 +1,6 @@
 pub mod sema_expr;
 pub mod value_type;
-pub(crate) mod decay;
+pub(crate) mod builtin;
 pub(crate) mod ty;
-pub(crate) mod fold;
\ No newline at end of file
+pub(crate) mod fold;
+pub(crate) mod const_eval;
@@ -0,0 +1,246 @@
+use crate::err::parser_error::{ParserError, ParserResult};
+use crate::lex::types::token_kind::{LiteralKind, Symbol};
+use crate::parser::ast::decls::decl::{Decl, DeclKind};
+use crate::parser::ast::exprs::{Builtin, BuiltinArg, ExprKind, MemberDesignator};
+use crate::parser::ast::types::{IntegerSize, RecordLayout, TypeKind, TypeLayout};
+use crate::parser::ast::{ExprKey, TypeKey};
+use crate::parser::common::Ident;
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::semantic::sema::expr::const_eval::{ConstValue, eval_const, eval_int};
+use crate::parser::semantic::sema::scope::scope_struct::ScopeSymbol;
+use crate::parser::semantic::sema::type_ctx::type_builder::TypeBuilderKind;
+use crate::types::span::Span;
+
+const VA_LIST: &str = "__builtin_va_list";
+
+/// 在 File 作用域声明 `typedef ... __builtin_va_list;`，`<stdarg.h>` 用它定义 `va_list`
+pub fn declare_builtin_types(ctx: &mut CompCtx) {
+    let ty = ctx.type_ctx.get_va_list(ctx.target.arch);
+    let name = Ident {
+        symbol: Symbol::new(VA_LIST),
+        span: Span::default(),
+    };
+    let decl = Decl {
+        storage: None,
+        func_spec: None,
+        noreturn: None,
+        visibility: None,
+        thread_local: None,
+        align: None,
+        name: Some(name.clone()),
+        kind: DeclKind::TypeDef,
+        ty,
+        span: Span::default(),
+    };
+    let def = ctx.insert_decl(decl);
+    let symbol = ScopeSymbol {
+        name: name.symbol,
+        decls: Vec::new(),
+        def: Some(def),
+        ty,
+    };
+    ctx.scope_mgr
+        .entry_local_ident(name.symbol)
+        .or_insert(symbol);
+}
+
+/// `ty` 能否作为 va_list 使用，数组类型的 va_list 作为参数时已经调整为指针
+pub fn is_va_list(ctx: &mut CompCtx, ty: TypeKey) -> bool {
+    let va_list = ctx.type_ctx.get_va_list(ctx.target.arch);
+    if ty == va_list {
+        return true;
+    }
+    match (
+        &ctx.type_ctx.get_type(va_list).kind,
+        &ctx.type_ctx.get_type(ty).kind,
+    ) {
+        (TypeKind::Array { elem_ty: a, .. }, TypeKind::Pointer { elem_ty: b }) => a == b,
+        _ => false,
+    }
+}
+
+fn arg_expr(arg: &BuiltinArg) -> ExprKey {
+    *arg.as_expr()
+        .expect("parser guarantees an expression argument")
+}
+
+fn arg_type(arg: &BuiltinArg) -> TypeKey {
+    *arg.as_type().expect("parser guarantees a type argument")
+}
+
+fn check_va_list(ctx: &mut CompCtx, builtin: Builtin, arg: &BuiltinArg) -> ParserResult<()> {
+    let expr = ctx.get_expr(arg_expr(arg));
+    let (ty, span) = (expr.ty, expr.span);
+    match is_va_list(ctx, ty) {
+        true => Ok(()),
+        false => {
+            let msg = format!("expected an expression of type '{}'", VA_LIST);
+            Err(ParserError::builtin_arg(builtin.name(), msg, span))
+        }
+    }
+}
+
+fn check_integer(ctx: &CompCtx, builtin: Builtin, arg: &BuiltinArg) -> ParserResult<()> {
+    let expr = ctx.get_expr(arg_expr(arg));
+    match ctx.type_ctx.get_type(expr.ty).kind {
+        TypeKind::Integer { .. } | TypeKind::Enum { .. } => Ok(()),
+        _ => {
+            let msg = "expected an integer expression".to_owned();
+            Err(ParserError::builtin_arg(builtin.name(), msg, expr.span))
+        }
+    }
+}
+
+fn check_pointer(ctx: &CompCtx, builtin: Builtin, arg: &BuiltinArg) -> ParserResult<()> {
+    let expr = ctx.get_expr(arg_expr(arg));
+    match ctx.type_ctx.get_type(expr.ty).kind {
+        TypeKind::Pointer { .. } | TypeKind::Array { .. } => Ok(()),
+        _ => {
+            let msg = "expected a pointer".to_owned();
+            Err(ParserError::builtin_arg(builtin.name(), msg, expr.span))
+        }
+    }
+}
+
+/// 检查内建函数的参数并计算结果类型
+pub(crate) fn builtin_type(
+    ctx: &mut CompCtx,
+    builtin: Builtin,
+    args: &[BuiltinArg],
+    span: Span,
+) -> ParserResult<TypeKey> {
+    use Builtin::*;
+    let void = ctx.type_ctx.get_void_type();
+    let int = ctx.type_ctx.get_int_type(IntegerSize::Int, true);
+    let ty = match builtin {
+        VaStart | VaEnd => {
+            check_va_list(ctx, builtin, &args[0])?;
+            void
+        }
+        VaCopy => {
+            check_va_list(ctx, builtin, &args[0])?;
+            check_va_list(ctx, builtin, &args[1])?;
+            void
+        }
+        VaArg => {
+            check_va_list(ctx, builtin, &args[0])?;
+            let ty = arg_type(&args[1]);
+            let is_object = match ctx.type_ctx.get_type(ty).kind {
+                TypeKind::Function { .. } | TypeKind::Array { .. } => false,
+                _ => ctx.type_ctx.get_type(ty).is_complete(),
+            };
+            if !is_object {
+                let msg = "expected a complete object type".to_owned();
+                return Err(ParserError::builtin_arg(builtin.name(), msg, span));
+            }
+            ty
+        }
+        Expect => {
+            check_integer(ctx, builtin, &args[0])?;
+            check_integer(ctx, builtin, &args[1])?;
+            ctx.type_ctx.get_int_type(IntegerSize::Long, true)
+        }
+        Unreachable => void,
+        Offsetof => {
+            let designators = args[1].as_member().expect("parser guarantees a designator");
+            offsetof(ctx, arg_type(&args[0]), designators)
+                .map_err(|msg| ParserError::builtin_arg(builtin.name(), msg, span))?;
+            ctx.type_ctx.get_int_type(IntegerSize::Long, false)
+        }
+        TypesCompatibleP | ConstantP => int,
+        Popcount(_) | Clz(_) | Ctz(_) => {
+            check_integer(ctx, builtin, &args[0])?;
+            int
+        }
+        Memcpy => {
+            check_pointer(ctx, builtin, &args[0])?;
+            check_pointer(ctx, builtin, &args[1])?;
+            check_integer(ctx, builtin, &args[2])?;
+            ctx.type_ctx.get_pointer(void)
+        }
+    };
+    Ok(ty)
+}
+
+/// `__builtin_offsetof` 的值，成员指示符不合法时返回错误信息
+pub fn offsetof(
+    ctx: &CompCtx,
+    ty: TypeKey,
+    designators: &[MemberDesignator],
+) -> Result<i128, String> {
+    let mut ty = ty;
+    let mut offset = 0;
+    for designator in designators.iter() {
+        match designator {
+            MemberDesignator::Field(ident) => {
+                let layout = RecordLayout::of(ctx, ty)
+                    .ok_or_else(|| "expected a complete struct or union type".to_owned())?;
+                let field = layout
+                    .find_field(ctx, ident.symbol)
+                    .ok_or_else(|| format!("no member named '{}'", ident.symbol))?;
+                if field.bit_field.is_some() {
+                    return Err(format!("'{}' is a bit-field", ident.symbol));
+                }
+                offset += field.offset as i128;
+                ty = field.ty;
+            }
+            MemberDesignator::Index(index) => {
+                let TypeKind::Array { elem_ty, .. } = ctx.type_ctx.get_type(ty).kind else {
+                    return Err("subscripted value is not an array".to_owned());
+                };
+                let index = eval_int(ctx, *index)
+                    .ok_or_else(|| "array index is not an integer constant".to_owned())?;
+                offset += index * TypeLayout::of(ctx, elem_ty).size as i128;
+                ty = elem_ty;
+            }
+        }
+    }
+    Ok(offset)
+}
+
+/// 忽略限定符后两个类型是否相同
+fn types_compatible(ctx: &CompCtx, a: TypeKey, b: TypeKey) -> bool {
+    let a = TypeBuilderKind::from_type_kind(&ctx.type_ctx.get_type(a).kind);
+    let b = TypeBuilderKind::from_type_kind(&ctx.type_ctx.get_type(b).kind);
+    a == b
+}
+
+/// 整数参数按内建函数的宽度截断为无符号数，返回值和位数
+fn unsigned_arg(ctx: &CompCtx, arg: &BuiltinArg, size: IntegerSize) -> Option<(u128, u32)> {
+    let bits = size.sizeof(&ctx.target) as u32 * 8;
+    let value = eval_int(ctx, arg_expr(arg))? as u128;
+    Some((value & ((1u128 << bits) - 1), bits))
+}
+
+/// 内建函数的常量值，不能在编译期求值时返回 None
+pub fn eval_builtin(ctx: &CompCtx, builtin: Builtin, args: &[BuiltinArg]) -> Option<ConstValue> {
+    use Builtin::*;
+    let value = match builtin {
+        Offsetof => {
+            let designators = args[1].as_member()?;
+            offsetof(ctx, arg_type(&args[0]), designators).ok()?
+        }
+        TypesCompatibleP => types_compatible(ctx, arg_type(&args[0]), arg_type(&args[1])) as i128,
+        ConstantP => {
+            let key = arg_expr(&args[0]);
+            let is_string = matches!(
+                ctx.get_expr(key).kind,
+                ExprKind::Literal(LiteralKind::String { .. })
+            );
+            (is_string || eval_const(ctx, key).is_some()) as i128
+        }
+        Expect => return eval_const(ctx, arg_expr(&args[0])),
+        Popcount(size) => unsigned_arg(ctx, &args[0], size)?.0.count_ones() as i128,
+        // 参数为 0 时结果未定义，留到运行时
+        Clz(size) => match unsigned_arg(ctx, &args[0], size)? {
+            (0, _) => return None,
+            (x, bits) => (x.leading_zeros() - (128 - bits)) as i128,
+        },
+        Ctz(size) => match unsigned_arg(ctx, &args[0], size)? {
+            (0, _) => return None,
+            (x, _) => x.trailing_zeros() as i128,
+        },
+        VaStart | VaArg | VaEnd | VaCopy | Unreachable | Memcpy => return None,
+    };
+    Some(ConstValue::Int(value))
+}
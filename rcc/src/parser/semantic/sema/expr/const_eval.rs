@@ -0,0 +1,273 @@
+use crate::lex::types::token_kind::LiteralKind;
+use crate::parser::ast::decls::decl::DeclKind;
+use crate::parser::ast::exprs::{BinOpKind, Constant, ExprKind, UnaryOpKind};
+use crate::parser::ast::types::{TypeKind, TypeLayout};
+use crate::parser::ast::{DeclKey, ExprKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::semantic::sema::expr::builtin::eval_builtin;
+use crate::util::ap_float::APFloat;
+use crate::util::literal;
+
+/// 算术常量的值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    Int(i128),
+    Float(f64),
+}
+
+impl ConstValue {
+    pub fn as_int(self) -> i128 {
+        match self {
+            ConstValue::Int(x) => x,
+            ConstValue::Float(x) => x as i128,
+        }
+    }
+
+    pub fn as_float(self) -> f64 {
+        match self {
+            ConstValue::Int(x) => x as f64,
+            ConstValue::Float(x) => x,
+        }
+    }
+
+    pub fn is_true(self) -> bool {
+        match self {
+            ConstValue::Int(x) => x != 0,
+            ConstValue::Float(x) => x != 0.0,
+        }
+    }
+}
+
+/// 整数常量表达式求值，不是整数常量表达式返回 None
+pub fn eval_int(ctx: &CompCtx, key: ExprKey) -> Option<i128> {
+    match eval_const(ctx, key)? {
+        ConstValue::Int(x) => Some(x),
+        ConstValue::Float(_) => None,
+    }
+}
+
+/// 把 `value` 转换为 `ty` 类型，整数按位宽截断
+pub fn convert(ctx: &CompCtx, value: ConstValue, ty: TypeKey) -> ConstValue {
+    let ty = ctx.type_ctx.get_type(ty);
+    if ty.is_bool() {
+        return ConstValue::Int(value.is_true() as i128);
+    }
+    if let Some((is_signed, bits)) = ty.int_info(ctx) {
+        return ConstValue::Int(wrap(value.as_int(), is_signed, bits));
+    }
+    match &ty.kind {
+        TypeKind::Floating { .. } => ConstValue::Float(value.as_float()),
+        TypeKind::Pointer { .. } => {
+            let bits = ctx.target.ptr_bytes as usize * 8;
+            ConstValue::Int(wrap(value.as_int(), false, bits))
+        }
+        _ => value,
+    }
+}
+
+fn wrap(value: i128, is_signed: bool, bits: usize) -> i128 {
+    if bits >= 128 {
+        return value;
+    }
+    let value = value & ((1i128 << bits) - 1);
+    match is_signed && value >> (bits - 1) & 1 == 1 {
+        true => value - (1i128 << bits),
+        false => value,
+    }
+}
+
+/// 操作数的整数类型，指针按指针宽度的无符号数处理
+fn int_info(ctx: &CompCtx, ty: TypeKey) -> (bool, usize) {
+    let ty = ctx.type_ctx.get_type(ty);
+    match &ty.kind {
+        TypeKind::Pointer { .. } | TypeKind::Array { .. } => {
+            (false, ctx.target.ptr_bytes as usize * 8)
+        }
+        _ => ty.int_info(ctx).unwrap_or((true, 32)),
+    }
+}
+
+/// 整数提升后的公共类型
+fn common_int(a: (bool, usize), b: (bool, usize)) -> (bool, usize) {
+    let promote = |x: (bool, usize)| if x.1 < 32 { (true, 32) } else { x };
+    let (a, b) = (promote(a), promote(b));
+    if a.0 == b.0 {
+        return (a.0, a.1.max(b.1));
+    }
+    let (unsigned, signed) = if a.0 { (b, a) } else { (a, b) };
+    match unsigned.1 >= signed.1 {
+        true => unsigned,
+        false => signed,
+    }
+}
+
+/// 算术常量表达式求值，优先使用 sema 折叠的结果
+pub fn eval_const(ctx: &CompCtx, key: ExprKey) -> Option<ConstValue> {
+    use ExprKind::*;
+    let expr = ctx.get_expr(key);
+    match &expr.value {
+        Some(Constant::Intager { value }) => return Some(ConstValue::Int(value.as_i128())),
+        Some(Constant::Float { value }) => {
+            let value = match value {
+                APFloat::F32(x) => *x as f64,
+                APFloat::F64(x) | APFloat::F80(x) => *x,
+            };
+            return Some(ConstValue::Float(value));
+        }
+        _ => {}
+    }
+
+    let value = match &expr.kind {
+        Literal(LiteralKind::Integer { value, .. }) => {
+            ConstValue::Int(literal::parse_int(value.get())? as i128)
+        }
+        Literal(LiteralKind::Float { value, .. }) => {
+            ConstValue::Float(literal::parse_float(value.get())?)
+        }
+        Literal(LiteralKind::Char { value }) => {
+            ConstValue::Int(literal::char_value(value.get()) as i128)
+        }
+        Literal(LiteralKind::String { .. }) => return None,
+        DeclRef { decl, .. } => ConstValue::Int(enum_value(ctx, (*decl)?)?),
+        SizeofType { ty } => ConstValue::Int(TypeLayout::of(ctx, *ty).size as i128),
+        AlignofType { ty } => ConstValue::Int(TypeLayout::of(ctx, *ty).align as i128),
+        SizeofExpr { expr } => {
+            ConstValue::Int(TypeLayout::of(ctx, ctx.get_expr(*expr).ty).size as i128)
+        }
+        Cast { expr, .. } => eval_const(ctx, *expr)?,
+        Unary { op, rhs } => {
+            let value = eval_const(ctx, *rhs)?;
+            match (op.kind, value) {
+                (UnaryOpKind::Plus, _) => value,
+                (UnaryOpKind::Minus, ConstValue::Int(x)) => ConstValue::Int(x.wrapping_neg()),
+                (UnaryOpKind::Minus, ConstValue::Float(x)) => ConstValue::Float(-x),
+                (UnaryOpKind::Not, _) => ConstValue::Int(!value.is_true() as i128),
+                (UnaryOpKind::BitNot, ConstValue::Int(x)) => ConstValue::Int(!x),
+                _ => return None,
+            }
+        }
+        Binary { lhs, op, rhs } => {
+            let lhs_ty = ctx.get_expr(*lhs).ty;
+            let rhs_ty = ctx.get_expr(*rhs).ty;
+            let a = eval_const(ctx, *lhs)?;
+            // 短路运算的右边不一定是常量
+            match op.kind {
+                BinOpKind::And if !a.is_true() => return Some(ConstValue::Int(0)),
+                BinOpKind::Or if a.is_true() => return Some(ConstValue::Int(1)),
+                _ => {}
+            }
+            let b = eval_const(ctx, *rhs)?;
+            match (a, b) {
+                (ConstValue::Int(a), ConstValue::Int(b)) => {
+                    let common = common_int(int_info(ctx, lhs_ty), int_info(ctx, rhs_ty));
+                    ConstValue::Int(eval_int_binary(op.kind, a, b, common)?)
+                }
+                _ => eval_float_binary(op.kind, a.as_float(), b.as_float())?,
+            }
+        }
+        Ternary {
+            cond,
+            then_expr,
+            else_expr,
+        } => match eval_const(ctx, *cond)?.is_true() {
+            true => eval_const(ctx, *then_expr)?,
+            false => eval_const(ctx, *else_expr)?,
+        },
+        BuiltinCall { builtin, args } => eval_builtin(ctx, *builtin, args)?,
+        ArraySubscript { .. }
+        | Call { .. }
+        | MemberAccess { .. }
+        | Assign { .. }
+        | CompoundLiteral { .. } => return None,
+    };
+
+    Some(convert(ctx, value, expr.ty))
+}
+
+fn eval_int_binary(
+    op: BinOpKind,
+    a: i128,
+    b: i128,
+    (is_signed, bits): (bool, usize),
+) -> Option<i128> {
+    use BinOpKind::*;
+    let a = wrap(a, is_signed, bits);
+    let b = wrap(b, is_signed, bits);
+    let value = match op {
+        Plus => a.wrapping_add(b),
+        Minus => a.wrapping_sub(b),
+        Mul => a.wrapping_mul(b),
+        Div => a.checked_div(b)?,
+        Mod => a.checked_rem(b)?,
+        BitAnd => a & b,
+        BitOr => a | b,
+        BitXor | Xor => a ^ b,
+        Shl => a.checked_shl(b as u32)?,
+        Shr => a.checked_shr(b as u32)?,
+        Lt => (a < b) as i128,
+        Gt => (a > b) as i128,
+        Eq => (a == b) as i128,
+        Ne => (a != b) as i128,
+        Le => (a <= b) as i128,
+        Ge => (a >= b) as i128,
+        And => (a != 0 && b != 0) as i128,
+        Or => (a != 0 || b != 0) as i128,
+        Comma => b,
+    };
+    Some(value)
+}
+
+fn eval_float_binary(op: BinOpKind, a: f64, b: f64) -> Option<ConstValue> {
+    use BinOpKind::*;
+    let value = match op {
+        Plus => ConstValue::Float(a + b),
+        Minus => ConstValue::Float(a - b),
+        Mul => ConstValue::Float(a * b),
+        Div => ConstValue::Float(a / b),
+        Lt => ConstValue::Int((a < b) as i128),
+        Gt => ConstValue::Int((a > b) as i128),
+        Eq => ConstValue::Int((a == b) as i128),
+        Ne => ConstValue::Int((a != b) as i128),
+        Le => ConstValue::Int((a <= b) as i128),
+        Ge => ConstValue::Int((a >= b) as i128),
+        And => ConstValue::Int((a != 0.0 && b != 0.0) as i128),
+        Or => ConstValue::Int((a != 0.0 || b != 0.0) as i128),
+        Comma => ConstValue::Float(b),
+        Mod | BitAnd | BitOr | BitXor | Xor | Shl | Shr => return None,
+    };
+    Some(value)
+}
+
+/// enum 的成员列表，`def` 可以是 `EnumDecl` 或 `EnumDef`
+fn enum_fields(ctx: &CompCtx, def: DeclKey) -> Option<&Vec<DeclKey>> {
+    match &ctx.get_decl(def).kind {
+        DeclKind::EnumDef { enums } => enums.as_ref(),
+        DeclKind::EnumDecl { def: Some(x) } => enum_fields(ctx, *x),
+        _ => None,
+    }
+}
+
+/// enum 常量的值，没有显式的值时为前一个加一
+pub fn enum_value(ctx: &CompCtx, field: DeclKey) -> Option<i128> {
+    let decl = ctx.get_decl(field);
+    let expr = *decl.kind.as_enum_field()?;
+
+    if let TypeKind::Enum { def: Some(def), .. } = &ctx.type_ctx.get_type(decl.ty).kind
+        && let Some(enums) = enum_fields(ctx, *def)
+    {
+        let mut next = 0;
+        for key in enums.iter().cloned() {
+            let value = match ctx.get_decl(key).kind.as_enum_field()? {
+                Some(x) => eval_int(ctx, *x)?,
+                None => next,
+            };
+            if key == field {
+                return Some(value);
+            }
+            next = value + 1;
+        }
+    }
+
+    // 成员的类型不是 enum 时只能使用显式的值
+    expr.and_then(|x| eval_int(ctx, x))
+}
@@ -1,70 +1,32 @@
-use crate::err::parser_error::{ParserResult};
+use crate::err::parser_error::ParserResult;
 use crate::parser::ast::ExprKey;
-use crate::parser::ast::exprs::{Expr, ExprKind, UnaryOpKind};
+use crate::parser::ast::exprs::{Expr, ExprKind};
 use crate::parser::comp_ctx::CompCtx;
-use crate::parser::semantic::sema::expr::decay::{self, decay_expr};
 use crate::parser::semantic::sema::expr::fold::fold_expr;
 use crate::parser::semantic::sema::expr::ty::expr_type;
-use crate::parser::semantic::sema::expr::value_type::{ValueCtx, ValueType};
-
+use crate::types::span::Span;
 
 /// 构建expression 折叠表达式
-pub fn make_expr(ctx: &mut CompCtx, kind: ExprKind, span: Span) -> ParserResult<ExprKey> {
-    // 1. 默认转换 衰变，左右值
+///
+/// 数组、函数的衰变和左值转换只影响类型，由 `expr_type` 计算，值的转换在 lower 时处理
+pub fn make_expr(ctx: &mut CompCtx, mut kind: ExprKind, span: Span) -> ParserResult<ExprKey> {
+    // 1. 解析引用的声明
     default_conversions(ctx, &mut kind);
     // 2. 类型推导
     let ty = expr_type(ctx, &kind, span)?;
 
-    // 3. 尝试表达式折叠
-    let value = fold_expr(ctx, &kind)?;
+    let expr = ctx.insert_expr(Expr::new(kind, ty, span));
 
-    let expr = Expr { kind, ty, span, value};
+    // 3. 尝试表达式折叠
+    let value = fold_expr(ctx, expr);
+    ctx.get_expr_mut(expr).value = value;
 
     Ok(expr)
 }
 
-
-// 衰变左右值变换
+/// 记录引用的声明，后续 pass 不再依赖已经弹出的作用域
 pub fn default_conversions(ctx: &mut CompCtx, kind: &mut ExprKind) {
-    use ExprKind::*;
-    use ValueCtx::*;
-    match kind {
-        DeclRef(x) => {}
-        Literal(x) => {}
-        ArraySubscript { base, index } => {
-            *base = decay_expr(ctx, *base, NoValue);
-            *index = decay_expr(ctx, *index, Value);
-        }
-        MemberAccess { base , ..} => {
-            *base = decay_expr(ctx, *base, Value); 
-        }
-        SizeofType { ty } => {}
-        SizeofExpr { expr } => {}
-        Call { params, base } => {
-            params.exprs.iter_mut().for_each(|x| {
-                *x = decay_expr(ctx, *x, Value);
-            });
-            *base = decay_expr(ctx, *base, NoValue);
-        }
-        Unary { op, rhs } => {
-            let value = match op.kind {
-                UnaryOpKind::AddrOf => NoValue,
-                _ => Value,
-            };
-            *rhs = decay_expr(ctx, *rhs, value);
-        }
-        Binary { lhs, rhs , .. } => {
-            *lhs = decay_expr(ctx, *lhs, Value);
-            *rhs = decay_expr(ctx, *rhs, Value);
-        }
-        Assign { lhs, op, rhs } => {
-            *rhs = decay_expr(ctx, *rhs, Value);
-        }
-        Cast { expr, .. } => {}
-        Ternary { cond, then_expr, else_expr, .. } => {
-            *cond = decay_expr(ctx, *cond, Value);
-            *then_expr = decay_expr(ctx, *then_expr, Value);
-            *else_expr = decay_expr(ctx, *else_expr, Value);
-        }
-    };
-}
\ No newline at end of file
+    if let ExprKind::DeclRef { ident, decl } = kind {
+        *decl = ctx.scope_mgr.lookup_ident(ident).map(|x| x.get_decl());
+    }
+}
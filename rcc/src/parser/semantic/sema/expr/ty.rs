@@ -1,733 +1,523 @@
 /// 表达式类型推导
-use crate::{err::parser_error::{self, ParserError, ParserResult}, lex::types::token_kind::{LiteralKind, Symbol}, parser::{ast::{ExprKey, TypeKey, exprs::{AssignOpKind, BinOpKind, ExprKind, MemberAccessKind, UnaryOpKind}, types::{IntegerSize, Qualifier, Type, TypeKind}}, common::Ident, comp_ctx::CompCtx, semantic::sema::expr::value_type::ValueType}, types::span::Span};
-
-
-/// 检查和计算当前表达式的类型，要做 表达式类型 decay
+use crate::{
+    err::parser_error::{self, ParserError, ParserResult},
+    lex::types::token_kind::{IntSuffix, LiteralKind, Symbol},
+    parser::{
+        ast::{
+            DeclKey, ExprKey, TypeKey,
+            exprs::{AssignOpKind, BinOpKind, ExprKind, MemberAccessKind, UnaryOpKind},
+            types::{IntegerSize, RecordLayout, Type, TypeKind},
+        },
+        common::Ident,
+        comp_ctx::CompCtx,
+        semantic::sema::decl::decl_spec::type_align,
+        semantic::sema::expr::{builtin::builtin_type, value_type::ValueType},
+    },
+    types::span::Span,
+    util::literal,
+};
+
+/// 检查和计算当前表达式的类型，数组和函数作为操作数时按衰变后的指针计算
 pub(crate) fn expr_type(ctx: &mut CompCtx, kind: &ExprKind, span: Span) -> ParserResult<TypeKey> {
     use ExprKind::*;
     let ty = match kind {
-        DeclRef(x) => var_expr_type(ctx, x)?,
+        DeclRef { ident, decl } => var_expr_type(ctx, ident, *decl)?,
         Literal(x) => literal_expr_type(ctx, x),
-        // ExprKind::Paren { expr, .. } => Rc::clone(&expr.ty),
-        ArraySubscript { base, index, .. } => 
-            array_subscript_type(ctx, *base, *index)?,
-        Call { base, params, .. } => {
-            let base = ctx.get_expr(*base);
-            call_expr_type(ctx, base.ty, &params.exprs, span)?
+        ArraySubscript { base, index } => array_subscript_type(ctx, *base, *index)?,
+        Call { base, params } => {
+            let base = ctx.get_expr(*base).ty;
+            call_expr_type(ctx, base, &params.exprs, span)?
         }
-        MemberAccess { base, field, kind, .. } => {
-            let base = ctx.get_expr(*base);
-            member_access_expr_type(ctx, base.ty, kind.clone(), *field, span)?
+        MemberAccess { base, field, kind } => {
+            let base = ctx.get_expr(*base).ty;
+            member_access_expr_type(ctx, base, kind.clone(), *field, span)?
         }
-        SizeofType { .. } | ExprKind::SizeofExpr { .. } => {
-            type_context.get_int_type(IntegerSize::Long, false)
+        SizeofType { .. } | SizeofExpr { .. } => {
+            ctx.type_ctx.get_int_type(IntegerSize::Long, false)
+        }
+        AlignofType { ty } => {
+            type_align(ctx, "_Alignof", *ty, span)?;
+            ctx.type_ctx.get_int_type(IntegerSize::Long, false)
         }
         Unary { op, rhs } => {
-            let rhs = ctx.get_expr(*rhs);  
-            let valuety = ValueType::value_type(rhs)
-            unary_type(ctx, op.kind.clone(), rhs.ty, valuety, span)?
+            let rhs = ctx.get_expr(*rhs);
+            let value_type = ValueType::of(rhs);
+            unary_type(ctx, op.kind, rhs.ty, value_type, span)?
         }
         Binary { op, lhs, rhs } => {
-            let lhs = ctx.get_expr(*lhs);
-            let rhs = ctx.get_expr(*rhs); 
-            binary_type(ctx, lhs, op.kind.clone(), rhs.ty.clone(), span)?
+            let lhs = decayed(ctx, *lhs);
+            let rhs = decayed(ctx, *rhs);
+            binary_type(ctx, lhs, op.kind, rhs, span)?
         }
-        Assign { lhs, op, rhs } => {
-            let lhs = ctx.get_expr(*lhs);
-            let rhs = ctx.get_expr(*rhs);
-            assign_type(ctx, lhs, op.kind.clone(), rhs, span)?
+        Assign { lhs, op, rhs } => assign_type(ctx, *lhs, op.kind, *rhs, span)?,
+        Cast { ty, expr } => {
+            let from = decayed(ctx, *expr);
+            cast_expr_type(ctx, from, *ty, span)?
         }
-        Cast { ty, expr, .. } => {
-            let from = ctx.get_expr(*expr).ty;
-            let to = *ty;
-            cast_expr_type(ctx, from, to, span)?
-        }
-        Ternary { cond, then_expr, else_expr, .. } => {
-            let cond = ctx.get_expr(*cond).ty;
-            let then_expr = ctx.get_expr(*then_expr).ty;
-            let else_expr = ctx.get_expr(*else_expr).ty;
-            ternary_expr_type(ctx, cond, else_expr, then_expr, span)?
+        CompoundLiteral { ty, .. } => *ty,
+        Ternary {
+            cond,
+            then_expr,
+            else_expr,
+        } => {
+            let cond = decayed(ctx, *cond);
+            let then_expr = decayed(ctx, *then_expr);
+            let else_expr = decayed(ctx, *else_expr);
+            ternary_expr_type(ctx, cond, then_expr, else_expr, span)?
         }
+        BuiltinCall { builtin, args } => builtin_type(ctx, *builtin, args, span)?,
     };
 
     Ok(ty)
 }
 
+/// 数组衰变为指向元素的指针，函数衰变为函数指针，其他类型不变
+pub fn decay(ctx: &mut CompCtx, ty: TypeKey) -> TypeKey {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Array { elem_ty, .. } => {
+            let elem_ty = *elem_ty;
+            ctx.type_ctx.get_pointer(elem_ty)
+        }
+        TypeKind::Function { .. } => ctx.type_ctx.get_pointer(ty),
+        _ => ty,
+    }
+}
+
+/// 表达式作为操作数时衰变后的类型
+fn decayed(ctx: &mut CompCtx, expr: ExprKey) -> TypeKey {
+    let ty = ctx.get_expr(expr).ty;
+    decay(ctx, ty)
+}
+
 /// 获取literal的类型
 fn literal_expr_type(ctx: &mut CompCtx, literal: &LiteralKind) -> TypeKey {
     use LiteralKind::*;
     match literal {
-        Integer { suffix, .. } => 
-            ctx.type_ctx.get_by_int_sfx(suffix.clone()),
-        Float { suffix, ..} => 
-            ctx.type_ctx.get_by_float_sfx(suffix.clone()),
-        Char { .. } => ctx.type_ctx.get_char(),
-        String { value } => // 假定已经处理转义
-            ctx.type_ctx.get_string_type(value.get().len() as u64),
+        Integer { suffix, value } => int_literal_type(ctx, *suffix, value.get()),
+        Float { suffix, .. } => ctx.type_ctx.get_by_float_sfx(*suffix),
+        // 字符常量的类型是 int
+        Char { .. } => ctx.type_ctx.get_int_type(IntegerSize::Int, true),
+        String { value } => {
+            let len = literal::string_bytes(value.get()).len();
+            ctx.type_ctx.get_string_type(len)
+        }
     }
 }
 
-/// 获取变量表达式类型
-fn var_expr_type(ctx: &CompCtx, ident: &Ident) -> ParserResult<TypeKey> {
-    // 去符号表lookup ident，获取类型
-    let decl = ctx.scope_mgr.must_lookup_ident(ident.symbol)
-        .map_err(|err| ParserError::from_scope_error(err, ident.span))?;
+/// 整数常量的类型是后缀允许的类型中第一个能放下它的，十进制常量不会选择无符号类型
+fn int_literal_type(ctx: &CompCtx, suffix: Option<IntSuffix>, text: &str) -> TypeKey {
+    use IntSuffix::*;
+    use IntegerSize::*;
+    let value = literal::parse_int(text).unwrap_or(0);
+    let is_decimal = !text.starts_with('0') || text == "0";
+    let (is_unsigned, min) = match suffix {
+        None => (false, Int),
+        Some(U) => (true, Int),
+        Some(L) => (false, Long),
+        Some(UL) => (true, Long),
+        Some(LL) => (false, LongLong),
+        Some(ULL) => (true, LongLong),
+    };
 
-    let decl = ctx.get_decl(decl);
-    let ty = decl.ty;
-    Ok(ty)
+    for size in [Int, Long, LongLong] {
+        if size.rank() < min.rank() {
+            continue;
+        }
+        let bits = size.sizeof(&ctx.target) * 8;
+        if !is_unsigned && value < 1 << (bits - 1) {
+            return ctx.type_ctx.get_int_type(size, true);
+        }
+        if (is_unsigned || !is_decimal) && value < 1 << bits {
+            return ctx.type_ctx.get_int_type(size, false);
+        }
+    }
+    ctx.type_ctx.get_int_type(LongLong, false)
 }
 
-/// 数组访问表达式类型，不衰变函数
-fn array_subscript_type(ctx: &CompCtx, base: ExprKey, index: ExprKey) -> ParserResult<TypeKey> {
-    let base = ctx.get_expr(base);
-    let index = ctx.get_expr(index);
-    let index_ty = ctx.type_ctx.get_type(index.ty);
-    let base_ty = ctx.type_ctx.get_type(base.ty);
-
-    // 非整数索引
-    if !index_ty.kind.is_integer() {
-        let err = ParserError::not_int_constant(index.span);
-        return Err(err)
+/// 获取变量表达式类型，`decl` 是在作用域中找到的声明
+fn var_expr_type(ctx: &CompCtx, ident: &Ident, decl: Option<DeclKey>) -> ParserResult<TypeKey> {
+    match decl {
+        Some(x) => Ok(ctx.get_decl(x).ty),
+        None => Err(ParserError::undefined_symbol(ident)),
     }
+}
 
-    let ty = match &base_ty.kind {
-        TypeKind::Pointer { elem_ty }
-        | TypeKind::Array { elem_ty, .. } => *elem_ty,
-        // 不是可索引的类型
-        _ => return Err(ParserError::non_subscripted(base.span))
+/// 数组访问表达式类型，`a[i]` 和 `i[a]` 等价
+fn array_subscript_type(ctx: &mut CompCtx, base: ExprKey, index: ExprKey) -> ParserResult<TypeKey> {
+    let span = ctx.get_expr(base).span;
+    let base = decayed(ctx, base);
+    let index = decayed(ctx, index);
+
+    let (ptr, int) = match ctx.type_ctx.get_type(base).kind.is_pointer() {
+        true => (base, index),
+        false => (index, base),
     };
 
-    Ok(ty)
+    // 索引必须是整数
+    if ctx.type_ctx.get_type(int).int_info(ctx).is_none() {
+        return Err(ParserError::non_subscripted(span));
+    }
+
+    match &ctx.type_ctx.get_type(ptr).kind {
+        TypeKind::Pointer { elem_ty } => Ok(*elem_ty),
+        _ => Err(ParserError::non_subscripted(span)),
+    }
 }
 
-/// 函数调用类型
-fn call_expr_type(ctx: &CompCtx, ty: TypeKey, call_params: &[ExprKey], span: Span) -> ParserResult<TypeKey> {
-    let ty = ctx.type_ctx.get_type(ty);
-    let ty = match &ty.kind {
-        TypeKind::Pointer { elem_ty } => {
-            call_expr_type(ctx, *elem_ty, call_params, span)?
+/// 函数调用类型，有原型时检查参数个数
+fn call_expr_type(
+    ctx: &CompCtx,
+    ty: TypeKey,
+    call_params: &[ExprKey],
+    span: Span,
+) -> ParserResult<TypeKey> {
+    match &ctx.type_ctx.get_type(ty).kind {
+        // 函数指针
+        TypeKind::Pointer { elem_ty } if ctx.type_ctx.get_type(*elem_ty).kind.is_function() => {
+            call_expr_type(ctx, *elem_ty, call_params, span)
         }
-        TypeKind::Function { ret_ty, params, .. } => {
-            let call = call_params.iter().copied()
-                .map(|x| ctx.get_expr(x))
-                .map(|x| x.ty);
-
-            // 检查参数
-            if !call.eq(params.iter().cloned()) {
-                todo!()
+        TypeKind::Function {
+            ret_ty,
+            params,
+            is_variadic,
+            has_prototype,
+        } => {
+            // 没有参数的声明 `f()` 不是原型，不检查；`f(void)` 不能有参数
+            let count = call_params.len();
+            let too_few = count < params.len();
+            let too_many = count > params.len() && !is_variadic && *has_prototype;
+            if too_few || too_many {
+                let msg = format!(
+                    "too {} arguments to function call, expected {}, have {}",
+                    if too_few { "few" } else { "many" },
+                    params.len(),
+                    count
+                );
+                return Err(ParserError::error(msg, span));
             }
-            *ret_ty
-        },
-        _ => return Err(ParserError::new(parser_error::ErrorKind::UnCallable, span))
-    };
-    Ok(ty)
+            Ok(*ret_ty)
+        }
+        _ => Err(ParserError::new(parser_error::ErrorKind::UnCallable, span)),
+    }
 }
 
-fn member_access_expr_type(ctx: &CompCtx, ty_key: TypeKey, op: MemberAccessKind, field: Symbol, span: Span) -> ParserResult<TypeKey> {
-    let ty = ctx.type_ctx.get_type(ty_key);
-    match op {
-        MemberAccessKind::Arrow => {
-            let elem_ty=  match ty.kind.as_pointer() {
-                None => todo!("不是指针错误"),
-                Some(x) => *x,
-            };
-            member_access_expr_type(ctx, elem_ty, MemberAccessKind::Dot, field, span)
+/// 成员访问类型，在 record 的布局中查找，包括匿名成员
+fn member_access_expr_type(
+    ctx: &CompCtx,
+    ty: TypeKey,
+    op: MemberAccessKind,
+    field: Symbol,
+    span: Span,
+) -> ParserResult<TypeKey> {
+    let record = match (op, &ctx.type_ctx.get_type(ty).kind) {
+        (MemberAccessKind::Arrow, TypeKind::Pointer { elem_ty })
+        | (MemberAccessKind::Arrow, TypeKind::Array { elem_ty, .. }) => *elem_ty,
+        (MemberAccessKind::Dot, _) => ty,
+        (MemberAccessKind::Arrow, _) => {
+            let kind = parser_error::ErrorKind::NotStructOrUnion { ty };
+            return Err(ParserError::new(kind, span));
         }
-        MemberAccessKind::Dot => {
-            let (name, fields) = match &ty.kind {
-                TypeKind::Struct { name, fields, .. }
-                | TypeKind::Union { name, fields, .. } => {
-                    (name, fields)
-                }
-                _ => {
-                    let kind = parser_error::ErrorKind::NotStructOrUnion { ty: ty_key };
-                    let error = ParserError::new(kind, span);
-                    return Err(error)
-                },
-            };
-            fields.iter()
-                .find(|x| x.name.as_ref().map(|x| x.symbol == field).unwrap_or_default())
-                .map(|x| x.ty)
-                .ok_or_else(|| { // 找不到出错
-                    let field = field.get().to_string();
-                    let ty = name.as_ref().map(|x| x.symbol.get().to_string()).unwrap_or_default();
-                    let kind = parser_error::ErrorKind::NoMember { field, ty };
-                    ParserError::new(kind, span)
-                })
+    };
+
+    let record_ty = ctx.type_ctx.get_type(record);
+    if !record_ty.kind.is_record() {
+        let kind = parser_error::ErrorKind::NotStructOrUnion { ty: record };
+        return Err(ParserError::new(kind, span));
+    }
+    let Some(layout) = RecordLayout::of(ctx, record) else {
+        let msg = format!("incomplete definition of type '{}'", record_ty.to_code(ctx));
+        return Err(ParserError::error(msg, span));
+    };
 
+    match layout.find_field(ctx, field) {
+        Some(x) => Ok(x.ty),
+        None => {
+            let kind = parser_error::ErrorKind::NoMember {
+                field: field.get().to_owned(),
+                ty: record_ty.to_code(ctx),
+            };
+            Err(ParserError::new(kind, span))
         }
     }
 }
 
-fn cast_expr_type(ctx: &CompCtx, from_key: TypeKey, to_key: TypeKey, span: Span) -> ParserResult<TypeKey> {
-    let from = ctx.type_ctx.get_type(from_key);
-    let to = ctx.type_ctx.get_type(to_key);
-    if cast_compatible(from, to) {
-        Ok(to_key)
+/// 显式类型转换，只能在标量之间转换，或者转换为 void
+fn cast_expr_type(ctx: &CompCtx, from: TypeKey, to: TypeKey, span: Span) -> ParserResult<TypeKey> {
+    let from_ty = ctx.type_ctx.get_type(from);
+    let to_ty = ctx.type_ctx.get_type(to);
+    if to_ty.kind.is_void() || cast_compatible(from_ty, to_ty) {
+        Ok(to)
     } else {
-        Err(ParserError::error("Wrong Cast".to_owned(), span))
+        Err(ParserError::incompatable(from, to, span))
     }
 }
 
 /// 三元运算符类型
 fn ternary_expr_type(
-    ctx: &CompCtx,
-    cond_key: TypeKey,
+    ctx: &mut CompCtx,
+    cond: TypeKey,
     a_key: TypeKey,
     b_key: TypeKey,
     span: Span,
-) -> ParserResult<TypeKey>
-{
+) -> ParserResult<TypeKey> {
     use TypeKind::*;
 
-    // a 和 b 完全相同 —— 直接返回
+    // cond 必须是标量类型
+    if !is_scalar(ctx.type_ctx.get_type(cond)) {
+        return Err(ParserError::not_scalar_type(cond, span));
+    }
+
     if a_key == b_key {
         return Ok(a_key);
     }
 
-    let cond = ctx.type_ctx.get_type(cond_key);
     let a = ctx.type_ctx.get_type(a_key);
     let b = ctx.type_ctx.get_type(b_key);
-        
-
-
-
-    // cond 必须是可转换为 bool/整数的类型
-    match &cond.kind {
-        Integer { .. } | Floating { .. } | Pointer { .. } => {}
-        Array { .. } | Function { .. } => {
-            todo!("无意义的代码，这些地址永远为true，所以发一个warning")
-        }
-        Void => unreachable!("got void expression, weird"), // 这个理论上不会出现
-        Record { .. } | Enum { .. } | Unknown =>  // 不是 scalar 类型出错
-            return Err(ParserError::not_scalar_type(cond_key, span)),
-    }
-
-    
 
     // 都是算术类型 → usual arithmetic conversion
-    if a.is_arithmetic() && b.is_arithmetic() {
-        return arith_promote(ctx, a_key, b_key, span);
+    if is_arithmetic(a) && is_arithmetic(b) {
+        return Ok(arith_type(ctx, a_key, b_key));
     }
 
-    // ============================================================
-    // 4. 
-    // ============================================================
     match (&a.kind, &b.kind) {
-        (Record { id: id1, .. }, Record { id: id2, .. }) if id1 == id2 =>  // record id必须一致
-            return Ok(a_key), 
-        (Pointer { elem_ty: ae, .. }, Pointer { elem_ty: be, .. }) => { // 指针特殊处理
-            if ae != be {
-                todo!("指针不一致，报错，gcc/clang都只是警告") 
-            }
-            return Ok(a_key)
-        }
-        (Void, Void) => return Ok(a_key),  // 理论上不会出现
-
-        _ => {}
+        (Record { id: id1, .. }, Record { id: id2, .. }) if id1 == id2 => Ok(a_key),
+        // void* 和其他指针得到 void*
+        (Pointer { .. }, Pointer { .. }) if b.is_void_ptr(ctx) => Ok(b_key),
+        (Pointer { .. }, Pointer { .. }) => Ok(a_key),
+        // 空指针常量
+        (Pointer { .. }, Integer { .. }) => Ok(a_key),
+        (Integer { .. }, Pointer { .. }) => Ok(b_key),
+        (Void, Void) => Ok(a_key),
+        _ => Err(ParserError::incompatable(a_key, b_key, span)),
     }
+}
 
-    // // 指针处理 cast_compatible
-    // if let Pointer { elem_ty: ae } = &a.kind {
-    //     if let Pointer { elem_ty: be } = &b.kind {
-    //         // 指向同元素类型 → 返回该指针
-    //         if ae == be {
-    //             return Ok(a_key);
-    //         }
-
-    //         // void* + T* → void*
-    //         if a.is_void_ptr(ctx) {
-    //             return Ok(a_key);
-    //         }
-    //         if b.is_void_ptr(ctx) {
-    //             return Ok(b_key);
-    //         }
-
-    //         // 不兼容
-    //         todo!("三元运算符的两个指针类型不兼容")
-    //     }
-    // }
-
-    // 尝试转换为二者其一
-    if cast_compatible(&a, &b) {
-        return Ok(b_key)
-    }
-    if cast_compatible(&b, &a) {
-        return Ok(a_key);
-    }
+/// 算术类型，enum 按 int 处理
+fn is_arithmetic(ty: &Type) -> bool {
+    ty.is_arithmetic() || ty.kind.is_enum()
+}
 
-    // 都不行出错
-    Err(ParserError::incompatable(a_key, b_key, span))
+fn is_scalar(ty: &Type) -> bool {
+    ty.is_scalar() || ty.kind.is_enum()
 }
 
-/// 算数时类型提升，只支持 Float 和 Integer
-fn arith_promote(
-    ctx: &CompCtx,
-    a_key: TypeKey,
-    b_key: TypeKey,
-    span: Span
-) -> ParserResult<TypeKey> {
-    use TypeKind::*;
+fn is_int(ty: &Type) -> bool {
+    ty.is_integer() || ty.kind.is_enum()
+}
 
-    let a = ctx.type_ctx.get_type(a_key);
-    let b = ctx.type_ctx.get_type(b_key);
-    debug_assert!(a.kind.is_integer() || a.kind.is_floating(), "expact integer, floating");
-    debug_assert!(b.kind.is_integer() || b.kind.is_floating(), "expact interger, floating");
-
-    // 1. 两者都是浮点，返回较宽浮点
-    if let (Floating { size: sa }, Floating { size: sb }) = (&a.kind, &b.kind) {
-        let ty = match sa.rank() > sb.rank() {
-            true => a_key,
-            false => b_key,
-        };
-        return Ok(ty);
+/// 整数提升：比 int 小的整数和 enum 提升为 int
+fn int_promote(ctx: &CompCtx, ty: TypeKey) -> TypeKey {
+    match &ctx.type_ctx.get_type(ty).kind {
+        TypeKind::Integer { size, .. } if size.rank() >= IntegerSize::Int.rank() => {
+            // 去掉限定符
+            let (is_signed, size) = ctx.type_ctx.get_type(ty).kind.as_integer().unwrap();
+            ctx.type_ctx.get_int_type(*size, *is_signed)
+        }
+        TypeKind::Integer { .. } | TypeKind::Enum { .. } => {
+            ctx.type_ctx.get_int_type(IntegerSize::Int, true)
+        }
+        TypeKind::Floating { size } => ctx.type_ctx.get_float_type(*size),
+        _ => ty,
     }
+}
 
-   
-    match (&a.kind, &b.kind) {
-
-         // 2. 浮点 + 整数 → 浮点类型，整数先转浮点
-        (Floating { .. }, Integer { .. }) => return Ok(a_key),
-        (Integer { .. }, Floating { size: fs }) => return Ok(b_key), 
-
-
-        // 3. 两者都是整数 → integer promotion + rank 比较
+/// usual arithmetic conversion 的结果类型
+fn arith_type(ctx: &CompCtx, a: TypeKey, b: TypeKey) -> TypeKey {
+    use TypeKind::*;
+    let (a, b) = (int_promote(ctx, a), int_promote(ctx, b));
+    match (&ctx.type_ctx.get_type(a).kind, &ctx.type_ctx.get_type(b).kind) {
+        (Floating { size: x }, Floating { size: y }) => match x.rank() >= y.rank() {
+            true => a,
+            false => b,
+        },
+        (Floating { .. }, _) => a,
+        (_, Floating { .. }) => b,
         (
-            Integer { is_signed: sa, size: ra }, 
-            Integer { is_signed: sb, size: rb }
-        ) => { 
-             // (1) 做 integer promotion
-            let sza = int_promote(*ra);
-            let szb = int_promote(*rb);
-
-            // let pa = ctx.get_int_type(sza, *sa);
-            // let pb = ctx.get_int_type(szb, *sb);
-
-            // (2) 比较 rank
-            if ra.rank() != rb.rank() {
-                let ty = match ra.rank() > rb.rank() {
-                    true => ctx.type_ctx.get_int_type(sza, *sa),
-                    false => ctx.type_ctx.get_int_type(szb, *sb)
-                };
-                return Ok(ty)
+            Integer {
+                is_signed: sa,
+                size: ra,
+            },
+            Integer {
+                is_signed: sb,
+                size: rb,
+            },
+        ) => {
+            if sa == sb {
+                return if ra.rank() >= rb.rank() { a } else { b };
             }
-
-            // (3) rank 相同，处理 signed/unsigned 混合情况
-            match (sa, sb) {
-                // 都是无符号
-                (false, false) => {
-                    return Ok(ctx.type_ctx.get_int_type(sza, *sa))
-                }
-                // 都是有符号
-                (true, true) => {
-                    return Ok(ctx.type_ctx.get_int_type(sza, *sa))
-                }
-                // 一个 signed 一个 unsigned
-                (true, false) | (false, true) => {
-                    // 规则：
-                    // 若 unsigned rank >= signed rank → unsigned
-                    // 否则 → signed 的类型（若能表示所有 unsigned）
-                    // C 的完整逻辑：
-                    //
-                    // 1. 如果 unsigned 的 rank >= signed 的 rank：
-                    //        转成 unsigned 同 rank
-                    // 2. 否则：
-                    //    如果 signed 类型能表示 unsigned 的所有值：
-                    //         转成 signed 类型
-                    //    否则：
-                    //         转成 unsigned same-rank
-
-                    let unsigned_side = if *sa == false { (false, ra) } else { (false, rb) };
-                    let signed_side   = if *sa == true  { (true,  ra) } else { (true,  rb) };
-
-                    // rank 比较
-                    if unsigned_side.1.rank() >= signed_side.1.rank() {
-                        return Ok(ctx.type_ctx.get_int_type(*unsigned_side.1, false))
-                    } else {
-                        // 构造 signed
-                        return Ok(ctx.type_ctx.get_int_type(*signed_side.1, true))
-                    }
-                }
+            let (unsigned, signed) = if *sa { (*rb, *ra) } else { (*ra, *rb) };
+            if unsigned.rank() >= signed.rank() {
+                ctx.type_ctx.get_int_type(unsigned, false)
+            } else if signed.sizeof(&ctx.target) > unsigned.sizeof(&ctx.target) {
+                // 有符号类型能表示无符号类型的所有值
+                ctx.type_ctx.get_int_type(signed, true)
+            } else {
+                ctx.type_ctx.get_int_type(signed, false)
             }
         }
-
-        _ => {}
+        _ => a,
     }
-
-    // 
-    // if let (Integer { is_signed: sa, size: ra },
-    //     Integer { is_signed: sb, size: rb }) = (&a.kind, &b.kind)
-    // {
-    //     // (1) 做 integer promotion
-    //     let sza = int_promote(*ra);
-    //     let szb = int_promote(*rb);
-
-    //     // let pa = ctx.get_int_type(sza, *sa);
-    //     // let pb = ctx.get_int_type(szb, *sb);
-
-    //     // (2) 比较 rank
-    //     if ra.rank() != rb.rank() {
-    //         let ty = match ra.rank() > rb.rank() {
-    //             true => ctx.type_ctx.get_int_type(sza, *sa),
-    //             false => ctx.type_ctx.get_int_type(szb, *sb)
-    //         };
-    //         return Ok(ty)
-    //     }
-
-    //     // (3) rank 相同，处理 signed/unsigned 混合情况
-    //     match (sa, sb) {
-    //         // 都是无符号
-    //         (false, false) => {
-    //             return Ok(ctx.type_ctx.get_int_type(sza, *sa))
-    //         }
-    //         // 都是有符号
-    //         (true, true) => {
-    //             return Ok(ctx.type_ctx.get_int_type(sza, *sa))
-    //         }
-    //         // 一个 signed 一个 unsigned
-    //         (true, false) | (false, true) => {
-    //             // 规则：
-    //             // 若 unsigned rank >= signed rank → unsigned
-    //             // 否则 → signed 的类型（若能表示所有 unsigned）
-    //             // C 的完整逻辑：
-    //             //
-    //             // 1. 如果 unsigned 的 rank >= signed 的 rank：
-    //             //        转成 unsigned 同 rank
-    //             // 2. 否则：
-    //             //    如果 signed 类型能表示 unsigned 的所有值：
-    //             //         转成 signed 类型
-    //             //    否则：
-    //             //         转成 unsigned same-rank
-
-    //             let unsigned_side = if *sa == false { (false, ra) } else { (false, rb) };
-    //             let signed_side   = if *sa == true  { (true,  ra) } else { (true,  rb) };
-
-    //             // rank 比较
-    //             if unsigned_side.1.rank() >= signed_side.1.rank() {
-    //                 return Ok(type_context.get_int_type(*unsigned_side.1, false))
-    //             } else {
-    //                 // 构造 signed
-    //                 return Ok(type_context.get_int_type(*signed_side.1, true))
-    //             }
-    //         }
-    //     }
-    // }
-
-    Err(ParserError::incompatable(a_key, b_key, span))
 }
 
-/// 算数类型提升
+/// 二元运算的类型，操作数已经衰变
 fn binary_type(
-    ctx: &CompCtx,
+    ctx: &mut CompCtx,
     a_key: TypeKey,
     op: BinOpKind,
     b_key: TypeKey,
-    span: Span
+    span: Span,
 ) -> ParserResult<TypeKey> {
     use BinOpKind::*;
 
+    let int = ctx.type_ctx.get_int_type(IntegerSize::Int, true);
     let a = ctx.type_ctx.get_type(a_key);
     let b = ctx.type_ctx.get_type(b_key);
+    let arith = is_arithmetic(a) && is_arithmetic(b);
 
-
-    match op {
-        // ======================================
-        // 加法：整数+整数，浮点+浮点，
-        // 指针 + 整数
-        // ======================================
-        Plus => {
-            if a.is_arithmetic() && b.is_arithmetic() {
-                arith_promote(ctx, a_key, b_key, span)
-            } else if a.is_pointer() && b.kind.is_integer() {
-                // pointer + int  → pointer
-                return Ok(a_key);
-            } else if a.kind.is_integer() && b.is_pointer() {
-                // int + pointer → pointer
-                return Ok(b_key);
-            } else {
-                todo!("Plus 类型错误")
-            }
-        }
-
-        // ======================================
-        // 减法：整数/浮点
-        // 指针 - 整数
+    let ty = match op {
+        Plus | Minus | Mul | Div if arith => arith_type(ctx, a_key, b_key),
+        // 指针 ± 整数
+        Plus | Minus if a.is_pointer() && is_int(b) => a_key,
+        Plus if is_int(a) && b.is_pointer() => b_key,
         // 指针 - 指针 → ptrdiff_t
-        // ======================================
-        Minus => {
-            if a.is_arithmetic() && b.is_arithmetic() {
-                arith_promote(ctx, a_key, b_key, span)
-            } else if a.is_pointer() && b.kind.is_integer() {
-                // pointer - int → pointer
-                Ok(a_key)
-            } else if a.is_pointer() && b.is_pointer() {
-                // pointer - pointer → ptrdiff_t
-                // ptrdiff_t 定义为 long long
-                Ok(type_context.get_int_type(IntegerSize::LongLong, true))
-            } else {
-                todo!("Minus 类型错误")
-            }
-        }
-
-        // 乘法 / 除法，只允许算术类型
-        Mul | Div => {
-            if a.is_arithmetic() && b.is_arithmetic() {
-                arith_promote(ctx, a_key, b_key, span)
-            } else {
-                todo!("Mul/Div 类型错误")
-            }
-        }
-
-        // 取模：仅整数
-        Mod => {
-            if a.kind.is_integer() && b.kind.is_integer() {
-                arith_promote(ctx, a_key, b_key, span)
-            } else {
-                todo!("Mod 类型错误")
-            }
-        }
-
-        // 移位运算：a << b, a >> b
-        // 左操作数必须是整数，右操作数必须是整数
-        // 返回左操作数类型
-        Shl | Shr => {
-            if a.kind.is_integer() && b.kind.is_integer() {
-                Ok(a_key)
-            } else {
-                todo!("Shift 类型错误")
-            }
+        Minus if a.is_pointer() && b.is_pointer() => {
+            ctx.type_ctx.get_int_type(IntegerSize::Long, true)
         }
 
-        // 比较：< > <= >= == !=
-        // 返回 int（或 bool）
-        Lt | Gt | Le | Ge | Eq | Ne => {
-            if a.is_arithmetic() && b.is_arithmetic() {
-                return Ok(type_context.get_int_type(IntegerSize::Int, true));
-            }
-            if a.is_pointer() && b.is_pointer() {
-                return Ok(type_context.get_int_type(IntegerSize::Int, true));
-            }
-            todo!("比较运算类型错误")
+        // 取模和按位运算：仅整数
+        Mod | BitAnd | BitOr | BitXor | Xor if is_int(a) && is_int(b) => {
+            arith_type(ctx, a_key, b_key)
         }
 
-        // 逻辑与 && ，逻辑或 ||
-        // 返回 int
-        And | Or => {
-            if a.is_scalar() && b.is_scalar() {
-                return Ok(type_context.get_int_type(IntegerSize::Int, true));
-            }
-            todo!("逻辑运算类型错误")
-        }
+        // 移位运算：结果是左操作数提升后的类型
+        Shl | Shr if is_int(a) && is_int(b) => int_promote(ctx, a_key),
 
-        // ======================================
-        // 按位运算：整数 &  |  ^
-        // ======================================
-        BitAnd | BitOr | BitXor => {
-            if a.kind.is_integer() && b.kind.is_integer() {
-                return arith_promote(ctx, a_key, b_key, span);
-            }
-            todo!("位运算类型错误")
+        // 比较：指针可以和指针或者空指针常量比较
+        Lt | Gt | Le | Ge | Eq | Ne
+            if arith
+                || (a.is_pointer() && (b.is_pointer() || is_int(b)))
+                || (is_int(a) && b.is_pointer()) =>
+        {
+            int
         }
 
-        Xor => {
-            if a.is_scalar() && b.is_scalar() {
-                return Ok(type_context.get_int_type(IntegerSize::Int, true));
-            }
-            todo!("Xor 类型错误")
-        }
+        And | Or if is_scalar(a) && is_scalar(b) => int,
 
         // 逗号表达式：返回右侧类型
-        Comma => {
-            Ok(b_key)
-        }
-    }
-}
+        Comma => b_key,
 
+        _ => return Err(ParserError::incompatable(a_key, b_key, span)),
+    };
+    Ok(ty)
+}
 
+/// 赋值和复合赋值，结果是左边的类型
 fn assign_type(
-    ctx: &CompCtx,
-    a: &Expr,          // 左值类型
+    ctx: &mut CompCtx,
+    lhs: ExprKey,
     op: AssignOpKind,
-    b: &Expr,
-    span: Span
+    rhs: ExprKey,
+    span: Span,
 ) -> ParserResult<TypeKey> {
     use AssignOpKind::*;
 
-    let aty_key = a.ty;
-    let bty_key = b.ty;
-
-    let aty = ctx.type_ctx.get_type(aty_key);
-    let bty = ctx.type_ctx.get_type(bty_key);
-
-    // 不是左值
-    if !a.is_lvalue() {
-        todo!("赋值给右值出错")
-    }
+    let a_key = ctx.get_expr(lhs).ty;
+    let b_key = decayed(ctx, rhs);
 
     let bin_op = match op {
-        PlusEq  => BinOpKind::Plus,
+        PlusEq => BinOpKind::Plus,
         MinusEq => BinOpKind::Minus,
-        StarEq  => BinOpKind::Mul,
+        StarEq => BinOpKind::Mul,
         SlashEq => BinOpKind::Div,
         PercentEq => BinOpKind::Mod,
-
-        ShlEq   => BinOpKind::Shl,
-        ShrEq   => BinOpKind::Shr,
-
-        AmpEq   => BinOpKind::BitAnd,
+        ShlEq => BinOpKind::Shl,
+        ShrEq => BinOpKind::Shr,
+        AmpEq => BinOpKind::BitAnd,
         CaretEq => BinOpKind::BitXor,
-        PipeEq  => BinOpKind::BitOr,
-
-        Assign => match cast_compatible(aty, bty) {
-            true => return Ok(aty_key),
-            false => todo!(),
+        PipeEq => BinOpKind::BitOr,
+        Assign => {
+            let a = ctx.type_ctx.get_type(a_key);
+            let b = ctx.type_ctx.get_type(b_key);
+            return match cast_compatible(b, a) {
+                true => Ok(a_key),
+                false => Err(ParserError::incompatable(a_key, b_key, span)),
+            };
         }
     };
 
-    let result_key = binary_type(ctx, aty_key, bin_op, bty_key, span)?;
-    let result_ty = ctx.type_ctx.get_type(result_key);
-
-    if !cast_compatible(aty, result_ty) {
-        todo!("不兼容类型错误")
-    }
-    // C规范返回lhs操作数
-    Ok(aty_key)
+    binary_type(ctx, a_key, bin_op, b_key, span)?;
+    Ok(a_key)
 }
 
-
-
+/// 一元运算的类型，`a_key` 是没有衰变的操作数类型
 fn unary_type(
-    ctx: &CompCtx,
+    ctx: &mut CompCtx,
     op: UnaryOpKind,
     a_key: TypeKey,
     value_type: ValueType,
-    span: Span
+    span: Span,
 ) -> ParserResult<TypeKey> {
+    use UnaryOpKind::*;
     let a = ctx.type_ctx.get_type(a_key);
+    let error = |ctx: &CompCtx| {
+        let ty = ctx.type_ctx.get_type(a_key).to_code(ctx);
+        let msg = format!("invalid argument type '{}' to unary expression", ty);
+        ParserError::error(msg, span)
+    };
+
     let ty = match op {
-        UnaryOpKind::AddrOf => {
-            if value_type != LValue {
-                // return Err(ParserError::)
-                todo!()
-            } else if matches!(a.kind, TypeKind::Unknown) {
-                todo!()
+        AddrOf => {
+            // 函数名和数组名也可以取地址
+            if value_type != ValueType::LValue && !a.kind.is_function() {
+                let msg = "cannot take the address of an rvalue".to_owned();
+                return Err(ParserError::error(msg, span));
             }
-
-            let kind = TypeKind::Pointer { elem_ty: a_key };
-            let ty = Type::new_qual(Qualifier::default(), kind);
-            type_context.get_or_set(ty)
+            ctx.type_ctx.get_pointer(a_key)
         }
-        UnaryOpKind::Deref => {
-            match &a.kind {
+        Deref => {
+            let a_key = decay(ctx, a_key);
+            match &ctx.type_ctx.get_type(a_key).kind {
                 TypeKind::Pointer { elem_ty } => *elem_ty,
-                TypeKind::Array { elem_ty, .. } => *elem_ty,
-                _ => {
-                    todo!()
-                }
+                _ => return Err(error(ctx)),
             }
         }
-
-        // 返回自己
-        UnaryOpKind::PostInc
-        | UnaryOpKind::PostDec
-        | UnaryOpKind::PreInc
-        | UnaryOpKind::PreDec => match &a.kind {
-            TypeKind::Integer{ .. }
-            | TypeKind::Floating{ .. }
-            | TypeKind::Pointer{ .. } => a,
-            _ => todo!()
+        PostInc | PostDec | PreInc | PreDec => {
+            if value_type != ValueType::LValue {
+                let kind = parser_error::ErrorKind::NotAssignable { ty: a.to_code(ctx) };
+                return Err(ParserError::new(kind, span));
+            }
+            match is_arithmetic(a) || a.kind.is_pointer() {
+                true => a_key,
+                false => return Err(error(ctx)),
+            }
         }
-
-
-        //
-        UnaryOpKind::Plus
-        | UnaryOpKind::Minus
-        | UnaryOpKind::Not => match &a.kind {
-            TypeKind::Integer{ .. }
-            | TypeKind::Floating{ .. } => a,
-            _ => todo!()
-        },
-
-        UnaryOpKind::BitNot => match &a.kind {
-            TypeKind::Integer{ .. } => a,
-            _ => todo!()
-        },
-
+        Plus | Minus if is_arithmetic(a) => int_promote(ctx, a_key),
+        BitNot if is_int(a) => int_promote(ctx, a_key),
+        Not if is_scalar(a) || a.kind.is_function() => {
+            ctx.type_ctx.get_int_type(IntegerSize::Int, true)
+        }
+        Plus | Minus | BitNot | Not => return Err(error(ctx)),
     };
     Ok(ty)
 }
 
-/// 显示cast兼容性
-fn cast_compatible(a: &Type, b: &Type) -> bool {
+/// `from` 能否转换为 `to`
+fn cast_compatible(from: &Type, to: &Type) -> bool {
     use TypeKind::*;
 
-    match (&a.kind, &b.kind) {
-        // 基础类型，我不知道什么情况能出现这种情况
-        (Void, Void) => true,
-
-        // 整型 <-> 整型
-        (Integer { .. }, Integer { .. }) => true,
-
-        // 浮点 <-> 浮点
-        (Floating { .. }, Floating { .. }) => true,
+    match (&from.kind, &to.kind) {
+        // 算术类型之间
+        (Integer { .. } | Enum { .. } | Floating { .. }, Integer { .. } | Enum { .. }) => true,
+        (Integer { .. } | Enum { .. } | Floating { .. }, Floating { .. }) => true,
 
-        // 整型 <-> 浮点
-        (Integer { .. }, Floating { .. }) |
-        (Floating { .. }, Integer { .. }) => true,
+        // 指针之间，整数和指针之间
+        (Pointer { .. } | Function { .. } | Array { .. }, Pointer { .. }) => true,
+        (Integer { .. } | Enum { .. }, Pointer { .. }) => true,
+        (Pointer { .. }, Integer { .. } | Enum { .. }) => true,
 
-        // 指针 <-> 指针
-        (Pointer { .. }, Pointer { .. }) => true,
-
-        // 整数 <-> 指针
-        (Pointer { .. }, Integer { .. }) |
-        (Integer { .. }, Pointer { .. }) => true,
-
-        // 数组衰变到指针
-        (Array { elem_ty: aelem, .. }, Pointer { elem_ty: belem })
-        | (Pointer { elem_ty: aelem }, Array { elem_ty: belem, .. }) // 指针 <-> 数组
-        | (Array { elem_ty: aelem, .. }, Array { elem_ty: belem, .. }) // 数组 <-> 数组：要求元素类型一致
-        => aelem == belem,
-
-        // 函数类型
-        (
-            Function {
-                ret_ty: ar,
-                params: ap,
-                is_variadic: av,
-            },
-            Function {
-                ret_ty: br,
-                params: bp,
-                is_variadic: bv,
-            }
-        ) => {
-            ar == br && av == bv
-                && ap.len() == bp.len()
-                && ap.iter().zip(bp.iter()).all(|(x, y)| x == y)
-        }
-
-        // todo 这里还差了一些 enum 之间的兼容
-        // ===== Struct / StructRef =====
-        (Record { id: id1, .. }, Record { id: id2, .. }) => id1 == id2, 
-        (Enum { id: id1, .. }, Enum { id: id2, .. }) => id1 == id2,
-
-        // ===== Unknown 不兼容任何 =====
-        (Unknown, _) | (_, Unknown) => false,
+        (Record { id: id1, .. }, Record { id: id2, .. }) => id1 == id2,
+        (Void, Void) => true,
 
-        // 其余组合不兼容
         _ => false,
     }
 }
-
-
-
-pub fn int_promote(sz: IntegerSize) -> IntegerSize {
-    match sz {
-        IntegerSize::Char => IntegerSize::Int,
-        IntegerSize::Short => IntegerSize::Int,
-        IntegerSize::Int => IntegerSize::Int,
-        IntegerSize::Long => sz,
-        IntegerSize::LongLong => sz,
-    }
-}
\ No newline at end of file
@@ -1,11 +1,10 @@
 use crate::{
     err::scope_error::{ScopeError, ScopeErrorKind, ScopeResult, ScopeSource},
-    lex::types::token_kind::Symbol,
     parser::{
-        ast::{DeclKey, StmtKey, TypeKey, decl::DeclKind},
+        ast::{DeclKey, TypeKey},
         common::Ident,
         comp_ctx::CompCtx,
-        semantic::sema::scope::scope_struct::{LabelSymbol, ScopeSymbol},
+        semantic::sema::scope::scope_struct::ScopeSymbol,
     },
 };
 
@@ -31,13 +30,6 @@ pub fn conflict_error_if(
     Err(err)
 }
 
-fn check_conflict(ctx: &mut CompCtx, decl_key: DeclKey, ty: TypeKey) {
-    use DeclKind::*;
-    let decl = ctx.get_decl(decl_key);
-    let ty = ctx.type_ctx.get_type(ty);
-}
-
-///
 fn lookup_or_insert<'a>(
     ctx: &'a mut CompCtx,
     ident: &Ident,
@@ -122,64 +114,5 @@ pub fn lookup_or_insert_def(
     symbol.def = Some(decl_key);
 
     // 返回所有前向声明，用于回填
-    return Ok(symbol.decls.clone());
-}
-
-fn label_lookup_or_insert(ctx: &mut CompCtx, symbol: Symbol) -> &mut LabelSymbol {
-    let scope = ctx
-        .scope_mgr
-        .labels
-        .last_mut()
-        .expect("label stack should not be none");
-
-    scope.lookup_or_insert(symbol)
-}
-
-/// 插入 label , 不负责回填
-///
-/// # Arguments
-/// - `stmt_key`: 必须是label statement
-///
-/// # Returns
-/// `Vec<StmtKey>`: gotos 用于回填
-pub fn lookup_or_insert_label(ctx: &mut CompCtx, stmt_key: StmtKey) -> ScopeResult<Vec<StmtKey>> {
-    let stmt = ctx.get_stmt(stmt_key);
-    debug_assert!(stmt.kind.is_label());
-    let (ident, _) = stmt.kind.as_label().expect("impossible");
-    let ident = ident.clone();
-
-    let symbol = label_lookup_or_insert(ctx, ident.symbol);
-
-    // 检查是否重定义
-    if let Some(prev) = symbol.stmt {
-        let kind = ScopeErrorKind::RedefinedLabel { prev };
-        let err = ScopeError {
-            kind,
-            name: ident.symbol.get(),
-            scope: ScopeSource::Label,
-            span: ident.span,
-        };
-        return Err(err);
-    }
-    Ok(symbol.pending_gotos.clone())
-}
-
-/// 插入 goto -> label , 不负责回填
-///
-/// # Arguments
-/// - `stmt_key`: 必须是label statement
-///
-/// # Argument
-/// `Option<StmtKey>`: label statement
-///
-pub fn lookup_or_insert_goto(ctx: &mut CompCtx, stmt_key: StmtKey) -> Option<StmtKey> {
-    let stmt = ctx.get_stmt(stmt_key);
-    debug_assert!(stmt.kind.is_goto());
-    let ident = stmt.kind.as_goto().expect("impossible").clone();
-
-    let symbol = label_lookup_or_insert(ctx, ident.symbol);
-
-    symbol.pending_gotos.push(stmt_key);
-
-    symbol.stmt
+    Ok(symbol.decls.clone())
 }
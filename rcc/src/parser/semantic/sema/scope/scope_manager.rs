@@ -58,7 +58,7 @@ macro_rules! scope_lookup {
             scope.sym_ht.get(&sym)
         }
 
-        pub fn $entry_local(&mut self, sym: Symbol) -> Entry<Symbol, $return> {
+        pub fn $entry_local(&mut self, sym: Symbol) -> Entry<'_, Symbol, $return> {
             let scope = match self.$field.last_mut() {
                 Some(x) => x,
                 None => unreachable!("`{}` can't be empty", stringify!($field)),
@@ -196,7 +196,7 @@ impl ScopeMgr {
 
     pub fn get_kind(&self) -> ScopeKind {
         debug_assert!(!self.kinds.is_empty());
-        self.kinds.last().expect("impossible").clone()
+        *self.kinds.last().expect("impossible")
     }
 
     pub fn insert_tag(&mut self, name: Ident, symbol: ScopeSymbol) -> ScopeResult<()> {
@@ -1,4 +1,3 @@
-use crate::err::scope_error::ScopeError;
 use crate::lex::types::token_kind::Symbol;
 use crate::parser::ast::{DeclKey, StmtKey, TypeKey};
 use rustc_hash::FxHashMap;
@@ -14,14 +13,6 @@ pub enum ScopeKind {
     // Enum,
 }
 
-/// 不知道叫什么名字好
-pub trait TScope {
-    type Item;
-    fn lookup(&mut self, symbol: Symbol) -> Option<&mut Self::Item>;
-
-    fn insert(&mut self, symbol: Symbol, t: Self::Item) -> Result<(), ScopeError>;
-}
-
 /// Scope 的符号对象
 /// - `name`: 符号名
 /// - `decls`: 声明 decl 对象
@@ -70,22 +61,6 @@ pub struct LabelScope {
     pub sym_ht: FxHashMap<Symbol, LabelSymbol>,
 }
 
-impl LabelScope {
-    /// 一定要手动插入声明或者定义，否则可能出错
-    pub(crate) fn lookup_or_insert(&mut self, symbol: Symbol) -> &mut LabelSymbol {
-        let symbol = self
-            .sym_ht
-            .entry(symbol)
-            .or_insert_with_key(|x| LabelSymbol {
-                name: symbol,
-                stmt: None,
-                pending_gotos: Vec::new(),
-            });
-
-        symbol
-    }
-}
-
 // 表示一个作用域
 /// - `sym_ht`: symbol hash table
 #[derive(Debug, Default)]
@@ -101,21 +76,12 @@ impl Scope {
     /// - `curr`:
     /// - `ty`:
     pub(crate) fn lookup_or_insert(&mut self, symbol: Symbol, ty: TypeKey) -> &mut ScopeSymbol {
-        let symbol = self
-            .sym_ht
-            .entry(symbol)
-            .or_insert_with_key(|x| ScopeSymbol {
-                name: *x,
-                decls: Vec::new(),
-                def: None,
-                ty,
-            });
-
-        symbol
-    }
-
-    fn lookup(&mut self, symbol: Symbol) -> Option<&mut ScopeSymbol> {
-        return self.sym_ht.get_mut(&symbol);
+        self.sym_ht.entry(symbol).or_insert_with(|| ScopeSymbol {
+            name: symbol,
+            decls: Vec::new(),
+            def: None,
+            ty,
+        })
     }
 }
 
@@ -1,6 +1,8 @@
 use crate::parser::ast::types::Qualifier;
 use crate::parser::common::Ident;
-use crate::parser::semantic::decl_spec::{DeclSpec, StorageSpec, TypeQuals};
+use crate::parser::semantic::decl_spec::{
+    AlignSpec, DeclSpec, FuncSpec, StorageSpec, TypeQuals, VisibilitySpec,
+};
 use crate::parser::semantic::declarator::{Declarator, DeclaratorChunkKind};
 use crate::types::span::Span;
 use crate::{
@@ -20,6 +22,11 @@ pub struct DeclInfo {
     pub ty: TypeKey,
     pub name: Option<Ident>,
     pub storage: Option<StorageSpec>,
+    pub func_spec: Option<FuncSpec>,
+    pub noreturn: Option<FuncSpec>,
+    pub visibility: Option<VisibilitySpec>,
+    pub thread_local: Option<StorageSpec>,
+    pub align: Option<AlignSpec>,
     pub span: Span,
 }
 
@@ -61,6 +68,11 @@ pub fn resolve_declarator(ctx: &mut CompCtx, declarator: Declarator) -> ParserRe
         ty,
         name: declarator.name,
         storage: decl_spec.storage.clone(),
+        func_spec: decl_spec.func_spec.clone(),
+        noreturn: decl_spec.noreturn.clone(),
+        visibility: decl_spec.visibility.clone(),
+        thread_local: decl_spec.thread_local.clone(),
+        align: decl_spec.align,
         span: declarator.span,
     };
 
@@ -92,10 +104,9 @@ fn resolve_array(
 /// todo 重构
 fn resolve_array_size(ctx: &mut CompCtx, expr: ExprKey) -> ParserResult<ArraySize> {
     let expr = ctx.pop_expr(expr);
-    let expr_ty = ctx.type_ctx.get_type(expr.ty);
 
     // 不是 int 直接出错
-    let array_size = expr.value.map(|x| x.as_intager().cloned()).flatten();
+    let array_size = expr.value.and_then(|x| x.as_intager().cloned());
     let array_size = match array_size {
         Some(x) => x,
         None => {
@@ -120,13 +131,15 @@ fn resolve_function(
     // 获取参数列表，可能是KR类型，这个类型理论上是不能用于声明函数类型的
     let list = match param {
         ParamDecl::Params(list) => list,
-        ParamDecl::Idents(_) => {
-            todo!("声明不能用K&R参数，可以默认int也可以报错，但是新标准应该是报错")
+        ParamDecl::Idents(list) => {
+            let msg = "a parameter list without types is only allowed in a function definition";
+            return Err(ParserError::error(msg.to_owned(), list.span));
         }
     };
 
     // 获取参数列表类型
     let is_variadic = list.is_variadic;
+    let has_prototype = list.has_prototype;
     let params: Vec<_> = list
         .params
         .iter()
@@ -139,6 +152,7 @@ fn resolve_function(
         ret_ty,
         params,
         is_variadic,
+        has_prototype,
     };
 
     Ok(TypeBuilder::new(func))
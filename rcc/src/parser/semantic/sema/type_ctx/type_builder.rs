@@ -57,10 +57,12 @@ impl TypeBuilder {
                 ret_ty,
                 params,
                 is_variadic,
+                has_prototype,
             } => TypeKind::Function {
                 ret_ty,
                 params,
                 is_variadic,
+                has_prototype,
             },
             Record { kind, id } => TypeKind::Record {
                 kind,
@@ -75,14 +77,37 @@ impl TypeBuilder {
         Ok(ty)
     }
 
-    // 检查是否正确
+    /// restrict 只能修饰指针，指向的类型在 [`TypeCtx::build_type`] 中检查
+    ///
+    /// [`TypeCtx::build_type`]: crate::parser::semantic::sema::type_ctx::type_ctx::TypeCtx::build_type
     fn check_restrict(ty: &Type) -> Result<(), TypeError> {
         // 如果没用restrict直接忽略
         if !ty.qual.is_restrict {
             return Ok(());
         }
 
-        todo!("实现restrict检查机制，只能用指针，指针指向的内容也要检查")
+        use TypeKind::*;
+        let invalid = match &ty.kind {
+            Pointer { .. } | Unknown => return Ok(()),
+            Void => "void".to_owned(),
+            Integer { is_signed, size } if !*is_signed && *size != IntegerSize::Bool => {
+                format!("unsigned {}", size)
+            }
+            Integer { size, .. } => size.to_string(),
+            Floating { size } => size.to_string(),
+            Array { .. } => "array".to_owned(),
+            Function { .. } => "function".to_owned(),
+            Record {
+                kind: RecordKind::Struct,
+                ..
+            } => "struct".to_owned(),
+            Record {
+                kind: RecordKind::Union,
+                ..
+            } => "union".to_owned(),
+            Enum { .. } => "enum".to_owned(),
+        };
+        Err(TypeError::RestrictError { invalid })
     }
 }
 
@@ -107,6 +132,7 @@ pub enum TypeBuilderKind {
         ret_ty: TypeKey,
         params: Vec<TypeKey>,
         is_variadic: bool,
+        has_prototype: bool,
     },
     Record {
         kind: RecordKind,
@@ -151,10 +177,12 @@ impl TypeBuilderKind {
                 ret_ty,
                 params,
                 is_variadic,
+                has_prototype,
             } => TypeBuilderKind::Function {
                 ret_ty: *ret_ty,
                 params: params.clone(),
                 is_variadic: *is_variadic,
+                has_prototype: *has_prototype,
             },
             TypeKind::Record { id, kind, .. } => TypeBuilderKind::Record {
                 kind: *kind,
@@ -1,10 +1,15 @@
+#![allow(clippy::module_inception)]
+
 use std::collections::hash_map::Entry;
 
 use crate::err::type_error::TypeError;
 use crate::lex::types::token_kind::{FloatSuffix, IntSuffix};
-use crate::parser::ast::TypeKey;
-use crate::parser::ast::types::{ArraySize, EnumID, FloatSize, IntegerSize, RecordID, Type};
+use crate::parser::ast::types::{
+    ArraySize, EnumID, FloatSize, IntegerSize, RecordID, Type, TypeKind,
+};
+use crate::parser::ast::{DeclKey, TypeKey};
 use crate::parser::semantic::sema::type_ctx::type_builder::{TypeBuilder, TypeBuilderKind};
+use backend::target::Arch;
 use rustc_hash::FxHashMap;
 use slotmap::SlotMap;
 
@@ -14,6 +19,8 @@ pub struct TypeCtx {
 
     enum_counter: usize,
     record_counter: usize,
+    record_defs: FxHashMap<RecordID, DeclKey>,
+    enum_defs: FxHashMap<EnumID, DeclKey>,
 }
 impl TypeCtx {
     pub fn new() -> Self {
@@ -24,6 +31,8 @@ impl TypeCtx {
             pool,
             enum_counter: 0,
             record_counter: 0,
+            record_defs: FxHashMap::default(),
+            enum_defs: FxHashMap::default(),
         };
 
         Self::init(&mut ctx);
@@ -44,6 +53,7 @@ impl TypeCtx {
         let ulong = TypeBuilder::new_int(false, Long);
         let ll = TypeBuilder::new_int(true, LongLong);
         let ull = TypeBuilder::new_int(false, LongLong);
+        let bool_ = TypeBuilder::new_int(false, Bool);
 
         let float = TypeBuilder::new_float(FloatSize::Float);
         let double = TypeBuilder::new_float(FloatSize::Double);
@@ -68,6 +78,7 @@ impl TypeCtx {
             float,
             double,
             long_double,
+            bool_,
         ];
 
         for ele in types {
@@ -89,7 +100,23 @@ impl TypeCtx {
         let key = match entry {
             Entry::Occupied(o) => *o.get(),
             Entry::Vacant(v) => {
-                let value = v.key().clone().build()?;
+                let mut value = v.key().clone().build()?;
+                // restrict 指针只能指向对象类型
+                if let TypeKind::Pointer { elem_ty } = value.kind
+                    && value.qual.is_restrict
+                    && self.pool[elem_ty].kind.is_function()
+                {
+                    return Err(TypeError::RestrictFunction);
+                }
+                // 已经定义的 record / enum，新的限定类型也要指向定义
+                let def = match &value.kind {
+                    TypeKind::Record { id, .. } => self.record_defs.get(id),
+                    TypeKind::Enum { id, .. } => self.enum_defs.get(id),
+                    _ => None,
+                };
+                if let Some(def) = def {
+                    value.set_def(*def);
+                }
                 let id = self.pool.insert(value);
                 *v.insert(id)
             }
@@ -138,7 +165,7 @@ impl TypeCtx {
 
     // char 类型
     pub fn get_char(&self) -> TypeKey {
-        self.get_int_type(IntegerSize::Char, true)
+        self.get_int_type(IntegerSize::Char, IntegerSize::CHAR_SIGNED)
     }
 
     // 获取 void type
@@ -165,6 +192,23 @@ impl TypeCtx {
         self.build_type(builder).expect("build pointer fail")
     }
 
+    /// `__builtin_va_list` 的类型：x86-64 是 24 字节的 `__va_list_tag[1]`，用 `unsigned long[3]` 表示；
+    /// 其他目标是指向下一个参数的 `void *`
+    pub fn get_va_list(&mut self, arch: Arch) -> TypeKey {
+        match arch {
+            Arch::X86_64 => {
+                let elem_ty = self.get_int_type(IntegerSize::Long, false);
+                let size = ArraySize::Static(3);
+                let ty = TypeBuilder::new(TypeBuilderKind::Array { elem_ty, size });
+                self.build_type(ty).expect("build va_list failed")
+            }
+            Arch::Riscv64 | Arch::Wasm32 => {
+                let void = self.get_void_type();
+                self.get_pointer(void)
+            }
+        }
+    }
+
     /// 获取未知类型
     pub fn get_unknown_type(&mut self) -> TypeKey {
         let ty = TypeBuilder::new(TypeBuilderKind::Unknown);
@@ -183,4 +227,23 @@ impl TypeCtx {
         enum_id
     }
 
+    /// 设置 record 的定义，同一个 record 的所有类型（包括限定类型）都指向定义
+    pub fn set_record_def(&mut self, record_id: RecordID, def: DeclKey) {
+        self.record_defs.insert(record_id, def);
+        for ty in self.pool.values_mut() {
+            if matches!(ty.kind, TypeKind::Record { id, .. } if id == record_id) {
+                ty.set_def(def);
+            }
+        }
+    }
+
+    /// 设置 enum 的定义，同 `set_record_def`
+    pub fn set_enum_def(&mut self, enum_id: EnumID, def: DeclKey) {
+        self.enum_defs.insert(enum_id, def);
+        for ty in self.pool.values_mut() {
+            if matches!(ty.kind, TypeKind::Enum { id, .. } if id == enum_id) {
+                ty.set_def(def);
+            }
+        }
+    }
 }
@@ -0,0 +1,20 @@
+//!
+//! 预处理：源码 --> 预处理 --> lexer
+//!
+//! - 按逻辑行处理，续行和跨行的注释属于同一行；以 `#` 开头的行是指令
+//! - 宏展开使用隐藏集合（hide set），展开结果重新扫描时不再展开产生它的宏
+//! - 输出是文本，没有宏的行原样输出；输出中的位置通过 `source_map` 对应到源文件的行列，
+//!   `#line` 和 GNU 的行标记会修改对应的文件名和行号
+//! - 内置的独立环境头文件由目标平台生成，见 `headers`
+//! - 预定义宏和命令行的 `-D` `-U` 在源文件之前处理，见 `predefined`
+//!
+
+pub mod headers;
+pub mod pp_core;
+pub mod pp_expr;
+pub mod pp_macro;
+pub mod pp_token;
+pub mod predefined;
+pub mod source_map;
+
+pub use pp_core::Preprocessor;
@@ -0,0 +1,458 @@
+//!
+//! 内置的独立环境（freestanding）头文件，内容由前端在目标平台上的类型布局生成，和 `sizeof` 的结果一致
+//!
+//! 它们位于虚拟的包含目录 `<rcc>/include` 中，在 `-I` 目录之后、系统目录之前查找
+//!
+
+use crate::parser::ast::types::{IntegerSize, TypeLayout};
+use backend::target::{Arch, TargetInfo};
+use std::fmt::Write;
+
+/// 虚拟包含目录，出现在诊断中
+pub const BUILTIN_INCLUDE_DIR: &str = "<rcc>/include";
+
+/// 所有内置头文件
+pub const BUILTIN_HEADERS: &[&str] = &[
+    "float.h",
+    "iso646.h",
+    "limits.h",
+    "stdalign.h",
+    "stdarg.h",
+    "stdbool.h",
+    "stddef.h",
+    "stdint.h",
+    "stdnoreturn.h",
+];
+
+/// 内置头文件的内容，`name` 不是内置头文件时返回 None
+pub fn builtin_header(name: &str, target: &TargetInfo) -> Option<String> {
+    let model = DataModel::new(target);
+    let body = match name {
+        "float.h" => float_h(target),
+        "iso646.h" => ISO646_H.to_owned(),
+        "limits.h" => model.limits_h(),
+        "stdalign.h" => STDALIGN_H.to_owned(),
+        "stdarg.h" => STDARG_H.to_owned(),
+        "stdbool.h" => STDBOOL_H.to_owned(),
+        "stddef.h" => model.stddef_h(),
+        "stdint.h" => model.stdint_h(),
+        "stdnoreturn.h" => STDNORETURN_H.to_owned(),
+        _ => return None,
+    };
+    let guard = format!("__RCC_{}", name.to_ascii_uppercase().replace('.', "_"));
+    Some(format!(
+        "#ifndef {guard}\n#define {guard}\n\n{body}\n#endif /* {guard} */\n"
+    ))
+}
+
+///
+/// C 的整数类型
+///
+/// # Members
+/// - `name`: 有符号类型的名字
+/// - `bits`: 位数
+/// - `suffix`: 常量的后缀，不含 `U`
+///
+#[derive(Debug, Clone, Copy)]
+struct CInt {
+    name: &'static str,
+    bits: u32,
+    suffix: &'static str,
+}
+
+impl CInt {
+    fn unsigned_name(self) -> String {
+        match self.name {
+            "signed char" => "unsigned char".to_owned(),
+            name => format!("unsigned {}", name),
+        }
+    }
+
+    fn max(self) -> String {
+        format!("{}{}", (1u128 << (self.bits - 1)) - 1, self.suffix)
+    }
+
+    /// 小于 `int` 的类型整数提升为 `int`，最大值不带 `U`
+    fn umax(self) -> String {
+        let max = (1u128 << self.bits) - 1;
+        match self.bits < 32 {
+            true => max.to_string(),
+            false => format!("{}U{}", max, self.suffix),
+        }
+    }
+
+    /// 最小值的绝对值超出有符号类型的范围，写成 `-MAX - 1`
+    fn min(self, max_name: &str) -> String {
+        match self.bits < 32 {
+            true => format!("(-{})", 1u128 << (self.bits - 1)),
+            false => format!("(-{} - 1{})", max_name, self.suffix),
+        }
+    }
+}
+
+///
+/// 目标平台上整数类型的选择
+///
+/// # Members
+/// - `long`: `long`，LP64 为 64 位，ILP32 为 32 位
+/// - `int64`: 64 位整数，`long` 为 64 位时用 `long`
+/// - `intptr`: 和指针一样宽的整数，`ptrdiff_t` `intptr_t` 使用，`size_t` 是它的无符号版本
+/// - `fast`: `int_fast16_t` `int_fast32_t` 使用的类型
+/// - `char_signed`: `char` 是否有符号
+///
+struct DataModel {
+    long: CInt,
+    int64: CInt,
+    intptr: CInt,
+    fast: CInt,
+    char_signed: bool,
+}
+
+const SCHAR: CInt = CInt {
+    name: "signed char",
+    bits: 8,
+    suffix: "",
+};
+const SHORT: CInt = CInt {
+    name: "short",
+    bits: 16,
+    suffix: "",
+};
+const INT: CInt = CInt {
+    name: "int",
+    bits: 32,
+    suffix: "",
+};
+const LLONG: CInt = CInt {
+    name: "long long",
+    bits: 64,
+    suffix: "LL",
+};
+
+pub(crate) fn define(out: &mut String, name: &str, value: impl std::fmt::Display) {
+    writeln!(out, "#define {} {}", name, value).unwrap();
+}
+
+impl DataModel {
+    fn new(target: &TargetInfo) -> Self {
+        let long = CInt {
+            name: "long",
+            bits: IntegerSize::Long.sizeof(target) as u32 * 8,
+            suffix: "L",
+        };
+        let int64 = if long.bits == 64 { long } else { LLONG };
+        let ptr_bits = TypeLayout::pointer(target).size as u32 * 8;
+        let intptr = [long, INT, LLONG]
+            .into_iter()
+            .find(|x| x.bits == ptr_bits)
+            .unwrap_or(int64);
+        let fast = if ptr_bits == 64 { intptr } else { INT };
+        Self {
+            long,
+            int64,
+            intptr,
+            fast,
+            char_signed: IntegerSize::CHAR_SIGNED,
+        }
+    }
+
+    fn limits_h(&self) -> String {
+        let mut out = String::new();
+        define(&mut out, "CHAR_BIT", 8);
+        define(&mut out, "MB_LEN_MAX", 16);
+        out.push('\n');
+        define(&mut out, "SCHAR_MIN", SCHAR.min("SCHAR_MAX"));
+        define(&mut out, "SCHAR_MAX", SCHAR.max());
+        define(&mut out, "UCHAR_MAX", SCHAR.umax());
+        match self.char_signed {
+            true => {
+                define(&mut out, "CHAR_MIN", "SCHAR_MIN");
+                define(&mut out, "CHAR_MAX", "SCHAR_MAX");
+            }
+            false => {
+                define(&mut out, "CHAR_MIN", 0);
+                define(&mut out, "CHAR_MAX", "UCHAR_MAX");
+            }
+        }
+        out.push('\n');
+        let types = [
+            ("SHRT", "USHRT", SHORT),
+            ("INT", "UINT", INT),
+            ("LONG", "ULONG", self.long),
+            ("LLONG", "ULLONG", LLONG),
+        ];
+        for (name, unsigned, ty) in types {
+            let max = format!("{}_MAX", name);
+            define(&mut out, &format!("{}_MIN", name), ty.min(&max));
+            define(&mut out, &max, ty.max());
+            define(&mut out, &format!("{}_MAX", unsigned), ty.umax());
+        }
+        out
+    }
+
+    fn stddef_h(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "typedef {} ptrdiff_t;", self.intptr.name).unwrap();
+        writeln!(out, "typedef {} size_t;", self.intptr.unsigned_name()).unwrap();
+        writeln!(out, "typedef int wchar_t;").unwrap();
+        writeln!(out, "typedef struct {{").unwrap();
+        writeln!(out, "    long long __max_align_ll;").unwrap();
+        writeln!(out, "    long double __max_align_ld;").unwrap();
+        writeln!(out, "}} max_align_t;\n").unwrap();
+        define(&mut out, "NULL", "((void *)0)");
+        define(
+            &mut out,
+            "offsetof(type, member)",
+            "__builtin_offsetof(type, member)",
+        );
+        out
+    }
+
+    fn stdint_h(&self) -> String {
+        let mut out = String::new();
+        let exact = [(8, SCHAR), (16, SHORT), (32, INT), (64, self.int64)];
+        let fast = [
+            (8, SCHAR),
+            (16, self.fast),
+            (32, self.fast),
+            (64, self.int64),
+        ];
+        for (prefix, types) in [("", exact), ("_least", exact), ("_fast", fast)] {
+            for (bits, ty) in types {
+                writeln!(out, "typedef {} int{}{}_t;", ty.name, prefix, bits).unwrap();
+                let unsigned = ty.unsigned_name();
+                writeln!(out, "typedef {} uint{}{}_t;", unsigned, prefix, bits).unwrap();
+            }
+        }
+        writeln!(out, "typedef {} intptr_t;", self.intptr.name).unwrap();
+        writeln!(out, "typedef {} uintptr_t;", self.intptr.unsigned_name()).unwrap();
+        writeln!(out, "typedef {} intmax_t;", self.int64.name).unwrap();
+        writeln!(out, "typedef {} uintmax_t;\n", self.int64.unsigned_name()).unwrap();
+
+        let mut limits = |name: &str, ty: CInt| {
+            let max = format!("INT{}_MAX", name);
+            define(&mut out, &format!("INT{}_MIN", name), ty.min(&max));
+            define(&mut out, &max, ty.max());
+            define(&mut out, &format!("UINT{}_MAX", name), ty.umax());
+        };
+        for (prefix, types) in [("", exact), ("_LEAST", exact), ("_FAST", fast)] {
+            for (bits, ty) in types {
+                limits(&format!("{}{}", prefix, bits), ty);
+            }
+        }
+        limits("PTR", self.intptr);
+        limits("MAX", self.int64);
+        out.push('\n');
+        define(&mut out, "PTRDIFF_MIN", self.intptr.min("PTRDIFF_MAX"));
+        define(&mut out, "PTRDIFF_MAX", self.intptr.max());
+        define(&mut out, "SIZE_MAX", self.intptr.umax());
+        define(&mut out, "SIG_ATOMIC_MIN", "INT32_MIN");
+        define(&mut out, "SIG_ATOMIC_MAX", "INT32_MAX");
+        define(&mut out, "WCHAR_MIN", "INT32_MIN");
+        define(&mut out, "WCHAR_MAX", "INT32_MAX");
+        define(&mut out, "WINT_MIN", "0U");
+        define(&mut out, "WINT_MAX", "UINT32_MAX");
+        out.push('\n');
+
+        // 常量宏的结果是 `int_leastN_t` 提升后的类型，小于 `int` 的不加后缀
+        for (bits, ty) in exact {
+            let (signed, unsigned) = match ty.bits < 32 {
+                true => ("c".to_owned(), "c".to_owned()),
+                false if ty.suffix.is_empty() => ("c".to_owned(), "c ## U".to_owned()),
+                false => (
+                    format!("c ## {}", ty.suffix),
+                    format!("c ## U{}", ty.suffix),
+                ),
+            };
+            define(&mut out, &format!("INT{}_C(c)", bits), signed);
+            define(&mut out, &format!("UINT{}_C(c)", bits), unsigned);
+        }
+        define(&mut out, "INTMAX_C(c)", "INT64_C(c)");
+        define(&mut out, "UINTMAX_C(c)", "UINT64_C(c)");
+        out
+    }
+}
+
+///
+/// 浮点格式的参数
+///
+/// # Members
+/// - `mant_dig`: 有效位数（包括隐含的最高位）
+/// - `dig`: 能准确表示的十进制位数
+/// - `decimal_dig`: 往返转换需要的十进制位数
+/// - `min_exp` `max_exp`: 二进制指数的范围
+/// - `min_10_exp` `max_10_exp`: 十进制指数的范围
+/// - `max` `epsilon` `min` `true_min`: 最大值、机器精度、最小规格化数和最小非规格化数的字面量，不含后缀
+///
+struct FloatFormat {
+    mant_dig: u32,
+    dig: u32,
+    decimal_dig: u32,
+    min_exp: i32,
+    max_exp: i32,
+    min_10_exp: i32,
+    max_10_exp: i32,
+    max: String,
+    epsilon: String,
+    min: String,
+    true_min: String,
+}
+
+impl FloatFormat {
+    fn single() -> Self {
+        Self {
+            mant_dig: f32::MANTISSA_DIGITS,
+            dig: f32::DIGITS,
+            decimal_dig: 9,
+            min_exp: f32::MIN_EXP,
+            max_exp: f32::MAX_EXP,
+            min_10_exp: f32::MIN_10_EXP,
+            max_10_exp: f32::MAX_10_EXP,
+            max: format!("{:e}", f32::MAX),
+            epsilon: format!("{:e}", f32::EPSILON),
+            min: format!("{:e}", f32::MIN_POSITIVE),
+            true_min: format!("{:e}", f32::from_bits(1)),
+        }
+    }
+
+    fn double() -> Self {
+        Self {
+            mant_dig: f64::MANTISSA_DIGITS,
+            dig: f64::DIGITS,
+            decimal_dig: 17,
+            min_exp: f64::MIN_EXP,
+            max_exp: f64::MAX_EXP,
+            min_10_exp: f64::MIN_10_EXP,
+            max_10_exp: f64::MAX_10_EXP,
+            max: format!("{:e}", f64::MAX),
+            epsilon: format!("{:e}", f64::EPSILON),
+            min: format!("{:e}", f64::MIN_POSITIVE),
+            true_min: format!("{:e}", f64::from_bits(1)),
+        }
+    }
+
+    /// x87 扩展精度，x86-64 的 `long double`；超出 `f64` 的范围，值直接写出
+    fn x87() -> Self {
+        Self {
+            mant_dig: 64,
+            dig: 18,
+            decimal_dig: 21,
+            min_exp: -16381,
+            max_exp: 16384,
+            min_10_exp: -4931,
+            max_10_exp: 4932,
+            max: "1.18973149535723176502e+4932".to_owned(),
+            epsilon: "1.08420217248550443401e-19".to_owned(),
+            min: "3.36210314311209350626e-4932".to_owned(),
+            true_min: "3.64519953188247460253e-4951".to_owned(),
+        }
+    }
+
+    /// IEEE 四精度，riscv64 的 `long double`
+    fn quad() -> Self {
+        Self {
+            mant_dig: 113,
+            dig: 33,
+            decimal_dig: 36,
+            min_exp: -16381,
+            max_exp: 16384,
+            min_10_exp: -4931,
+            max_10_exp: 4932,
+            max: "1.18973149535723176508575932662800702e+4932".to_owned(),
+            epsilon: "1.92592994438723585305597794258492732e-34".to_owned(),
+            min: "3.36210314311209350626267781732175260e-4932".to_owned(),
+            true_min: "6.47517511943802511092443895822764655e-4966".to_owned(),
+        }
+    }
+
+    /// 目标的 `long double`，见 `FloatSize::sizeof`
+    fn long_double(target: &TargetInfo) -> Self {
+        match (target.long_double_bytes, target.arch) {
+            (8, _) => Self::double(),
+            (_, Arch::X86_64) => Self::x87(),
+            _ => Self::quad(),
+        }
+    }
+
+    fn define(&self, out: &mut String, prefix: &str, suffix: &str) {
+        let name = |x: &str| format!("{}_{}", prefix, x);
+        define(out, &name("MANT_DIG"), self.mant_dig);
+        define(out, &name("DIG"), self.dig);
+        define(out, &name("DECIMAL_DIG"), self.decimal_dig);
+        define(out, &name("MIN_EXP"), format!("({})", self.min_exp));
+        define(out, &name("MAX_EXP"), self.max_exp);
+        define(out, &name("MIN_10_EXP"), format!("({})", self.min_10_exp));
+        define(out, &name("MAX_10_EXP"), self.max_10_exp);
+        define(out, &name("MAX"), format!("{}{}", self.max, suffix));
+        define(out, &name("EPSILON"), format!("{}{}", self.epsilon, suffix));
+        define(out, &name("MIN"), format!("{}{}", self.min, suffix));
+        define(
+            out,
+            &name("TRUE_MIN"),
+            format!("{}{}", self.true_min, suffix),
+        );
+        define(out, &name("HAS_SUBNORM"), 1);
+        out.push('\n');
+    }
+}
+
+fn float_h(target: &TargetInfo) -> String {
+    let mut out = String::new();
+    define(&mut out, "FLT_RADIX", 2);
+    define(&mut out, "FLT_ROUNDS", 1);
+    define(&mut out, "FLT_EVAL_METHOD", 0);
+    out.push('\n');
+    FloatFormat::single().define(&mut out, "FLT", "F");
+    FloatFormat::double().define(&mut out, "DBL", "");
+    FloatFormat::long_double(target).define(&mut out, "LDBL", "L");
+    define(&mut out, "DECIMAL_DIG", "LDBL_DECIMAL_DIG");
+    out
+}
+
+const STDARG_H: &str = "\
+typedef __builtin_va_list va_list;
+
+#define va_start(ap, param) __builtin_va_start(ap, param)
+#define va_arg(ap, type) __builtin_va_arg(ap, type)
+#define va_end(ap) __builtin_va_end(ap)
+#define va_copy(dest, src) __builtin_va_copy(dest, src)
+#define __va_copy(dest, src) __builtin_va_copy(dest, src)
+
+/* glibc 的 <stdio.h> 用它声明 vprintf 等函数 */
+#ifndef __GNUC_VA_LIST
+#define __GNUC_VA_LIST 1
+typedef __builtin_va_list __gnuc_va_list;
+#endif
+";
+
+const STDBOOL_H: &str = "\
+#define bool _Bool
+#define true 1
+#define false 0
+#define __bool_true_false_are_defined 1
+";
+
+const STDALIGN_H: &str = "\
+#define alignas _Alignas
+#define alignof _Alignof
+#define __alignas_is_defined 1
+#define __alignof_is_defined 1
+";
+
+const STDNORETURN_H: &str = "\
+#define noreturn _Noreturn
+";
+
+const ISO646_H: &str = "\
+#define and &&
+#define and_eq &=
+#define bitand &
+#define bitor |
+#define compl ~
+#define not !
+#define not_eq !=
+#define or ||
+#define or_eq |=
+#define xor ^
+#define xor_eq ^=
+";
@@ -0,0 +1,534 @@
+use crate::err::preprocess_error::{PreprocessError, PreprocessErrorKind, PreprocessResult};
+use crate::preprocess::headers::{BUILTIN_INCLUDE_DIR, builtin_header};
+use crate::preprocess::pp_macro::{DynamicMacro, Macro, TokenInput};
+use crate::preprocess::pp_token::{
+    Line, PpToken, PpTokenKind, split_lines, write_tokens, write_tokens_with,
+};
+use crate::preprocess::predefined::predefined_macros;
+use crate::preprocess::source_map::{SourceLoc, SourceMap};
+use backend::target::{Arch, TargetInfo};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// 系统头文件目录，排在内置头文件之后；Debian 系的多架构目录插在 `/usr/include` 之前
+fn system_include_dirs(target: &TargetInfo) -> Vec<PathBuf> {
+    let multiarch = match target.arch {
+        Arch::X86_64 => Some("/usr/include/x86_64-linux-gnu"),
+        Arch::Riscv64 => Some("/usr/include/riscv64-linux-gnu"),
+        Arch::Wasm32 => None,
+    };
+    ["/usr/local/include"]
+        .into_iter()
+        .chain(multiarch)
+        .chain(["/usr/include"])
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// `#include` 的最大嵌套深度
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+///
+/// 正在处理的文件
+///
+/// # Members
+/// - `path`: 诊断中显示的路径
+/// - `dir`: 所在目录，`#include "..."` 首先在这里查找；内置头文件没有目录
+///
+#[derive(Debug, Clone, Default)]
+pub struct SourceFile {
+    pub path: String,
+    pub dir: Option<PathBuf>,
+}
+
+///
+/// 条件编译的一层
+///
+/// # Members
+/// - `active`: 当前分支是否输出
+/// - `taken`: 是否已经有分支被选中
+/// - `parent`: 外层是否输出
+/// - `seen_else`: 是否已经遇到 `#else`
+/// - `line`: `#if` 所在的行
+///
+struct Cond {
+    active: bool,
+    taken: bool,
+    parent: bool,
+    seen_else: bool,
+    line: usize,
+}
+
+///
+/// 预处理器，处理指令和宏展开，输出交给 lexer 的文本
+///
+/// 输出保持每个文件的行结构：指令行和跳过的行输出为空行，没有宏的行原样输出，
+/// 因此没有 `#include` 的文件行列号不变；`#include` 的内容插入在指令的位置。
+/// 输出中每个位置来自哪个文件的哪一行记录在 `map` 中
+///
+/// # Members
+/// - `target`: 目标平台，内置头文件的内容由它生成
+/// - `include_dirs`: `-I` 指定的目录
+/// - `system_dirs`: 系统头文件目录
+/// - `macros`: 已定义的宏，包括 `__FILE__` 等动态的预定义宏
+/// - `counter`: `__COUNTER__` 的下一个值
+/// - `once`: `#pragma once` 的文件
+/// - `file` `line`: 当前位置，用于诊断，`#line` 可以修改它们
+/// - `depth`: `#include` 的嵌套深度
+/// - `out`: 输出
+/// - `map`: 输出到源文件位置的映射
+///
+pub struct Preprocessor {
+    pub target: TargetInfo,
+    pub include_dirs: Vec<PathBuf>,
+    pub system_dirs: Vec<PathBuf>,
+    pub macros: FxHashMap<Rc<str>, Macro>,
+    pub(crate) counter: Cell<u64>,
+    once: FxHashSet<String>,
+    pub(crate) file: SourceFile,
+    pub(crate) line: usize,
+    depth: usize,
+    out: String,
+    map: SourceMap,
+}
+
+impl Preprocessor {
+    pub fn new(target: TargetInfo) -> Self {
+        let system_dirs = system_include_dirs(&target);
+        Self {
+            target,
+            include_dirs: Vec::new(),
+            system_dirs,
+            macros: DynamicMacro::ALL
+                .iter()
+                .map(|x| (Rc::from(x.name()), Macro::dynamic(*x)))
+                .collect(),
+            counter: Cell::new(0),
+            once: FxHashSet::default(),
+            file: SourceFile::default(),
+            line: 1,
+            depth: 0,
+            out: String::new(),
+            map: SourceMap::default(),
+        }
+    }
+
+    pub fn with_include_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.include_dirs = dirs;
+        self
+    }
+
+    /// 预处理文件 `path` 的内容 `code`，输出的源码映射由 `take_source_map` 取得
+    pub fn preprocess(&mut self, path: &str, code: &str) -> PreprocessResult<String> {
+        let dir = Path::new(path).parent().map(Path::to_path_buf);
+        self.file = SourceFile {
+            path: path.to_owned(),
+            dir,
+        };
+        self.line = 1;
+        self.map = SourceMap::default();
+        self.map.file_id(path);
+        self.process(code)?;
+        Ok(std::mem::take(&mut self.out))
+    }
+
+    /// 上一次 `preprocess` 的输出到源文件位置的映射，主文件的下标为 0
+    pub fn take_source_map(&mut self) -> SourceMap {
+        std::mem::take(&mut self.map)
+    }
+
+    /// 定义预定义宏，`std_version` 为 `__STDC_VERSION__` 的值
+    pub fn predefine(&mut self, std_version: Option<u32>) -> PreprocessResult<()> {
+        let code = predefined_macros(&self.target, std_version);
+        self.preprocess("<built-in>", &code)?;
+        Ok(())
+    }
+
+    /// 命令行的 `-D name` `-D name=value`，没有值时定义为 1
+    pub fn define(&mut self, def: &str) -> PreprocessResult<()> {
+        let code = match def.split_once('=') {
+            Some((name, value)) => format!("#define {} {}\n", name, value),
+            None => format!("#define {} 1\n", def),
+        };
+        self.preprocess("<command line>", &code)?;
+        Ok(())
+    }
+
+    /// 命令行的 `-U name`
+    pub fn undef(&mut self, name: &str) -> PreprocessResult<()> {
+        self.preprocess("<command line>", &format!("#undef {}\n", name))?;
+        Ok(())
+    }
+
+    /// `-dM` 的输出：按名字排序的所有宏定义，不包括动态的预定义宏
+    pub fn dump_macros(&self) -> String {
+        let mut macros: Vec<_> = self
+            .macros
+            .iter()
+            .filter(|(_, x)| x.dynamic.is_none())
+            .collect();
+        macros.sort_by(|a, b| a.0.cmp(b.0));
+        let mut out = String::new();
+        for (name, makro) in macros {
+            out.push_str(&format!("#define {}\n", makro.definition(name)));
+        }
+        out
+    }
+
+    /// 当前位置的错误
+    pub(crate) fn error(&self, kind: PreprocessErrorKind) -> PreprocessError {
+        PreprocessError {
+            file: self.file.path.clone(),
+            line: self.line,
+            kind,
+        }
+    }
+
+    fn push_newlines(&mut self, count: usize) {
+        self.out.extend(std::iter::repeat_n('\n', count));
+        self.line += count;
+    }
+
+    fn process(&mut self, code: &str) -> PreprocessResult<()> {
+        let lines = split_lines(code);
+        let mut conds: Vec<Cond> = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let active = conds.last().is_none_or(|x| x.active);
+            let line = &lines[i];
+            if line.is_directive() {
+                self.directive(line, &mut conds, active)?;
+            } else if active {
+                // 连续的文本行一起处理，函数式宏的实参可以跨行
+                let end = lines[i..]
+                    .iter()
+                    .position(Line::is_directive)
+                    .map_or(lines.len(), |x| i + x);
+                self.text(code, &lines[i..end])?;
+                i = end;
+                continue;
+            }
+            self.push_newlines(line.newlines);
+            i += 1;
+        }
+        match conds.last() {
+            Some(cond) => {
+                self.line = cond.line;
+                Err(self.error(PreprocessErrorKind::UnterminatedConditional))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 文本行：没有宏的行原样输出，否则输出展开后的记号，并补上展开消耗的换行
+    fn text(&mut self, code: &str, lines: &[Line]) -> PreprocessResult<()> {
+        let newline = PpToken::new(PpTokenKind::Newline, "\n", true);
+        let mut tokens = Vec::new();
+        // 每行结束后的位置
+        let mut ends = Vec::new();
+        for line in lines.iter() {
+            tokens.extend(line.tokens.iter().cloned());
+            tokens.push(newline.clone());
+            ends.push(tokens.len());
+        }
+
+        let mut k = 0;
+        while k < lines.len() {
+            let line = &lines[k];
+            if !line.spliced && !line.tokens.iter().any(|x| self.is_macro(x)) {
+                self.copy(&code[line.range.clone()]);
+                self.line += line.newlines;
+                k += 1;
+                continue;
+            }
+            let start = if k == 0 { 0 } else { ends[k - 1] };
+            let mut input = TokenInput::new(&tokens[start..]);
+            let expanded = self.expand(&mut input)?;
+            let last = ends.partition_point(|x| *x <= start + input.pos);
+            // 记号的行号相对于这一组的第一行计算
+            let (file, line, base) = (self.file_id(), self.line, line.range.start);
+            let map = &mut self.map;
+            write_tokens_with(&mut self.out, &expanded, |out, token| {
+                let pos = token.pos.max(base);
+                let start = code[..pos].rfind('\n').map_or(0, |x| x + 1);
+                let loc = SourceLoc {
+                    file,
+                    line: line + code[base..pos].matches('\n').count(),
+                    col: pos - start + 1,
+                };
+                map.push(out, loc);
+            });
+            let newlines = lines[k..last].iter().map(|x| x.newlines).sum();
+            self.push_newlines(newlines);
+            k = last;
+        }
+        Ok(())
+    }
+
+    /// 原样输出，每一行记录对应的源文件位置
+    fn copy(&mut self, text: &str) {
+        let file = self.file_id();
+        for (i, part) in text.split_inclusive('\n').enumerate() {
+            let loc = SourceLoc {
+                file,
+                line: self.line + i,
+                col: 1,
+            };
+            self.map.push(self.out.len(), loc);
+            self.out.push_str(part);
+        }
+    }
+
+    /// 当前文件在源码映射中的下标
+    fn file_id(&mut self) -> usize {
+        self.map.file_id(&self.file.path)
+    }
+
+    fn directive(
+        &mut self,
+        line: &Line,
+        conds: &mut Vec<Cond>,
+        active: bool,
+    ) -> PreprocessResult<()> {
+        let tokens = &line.tokens[1..];
+        // 空指令
+        let Some(name) = tokens.first() else {
+            return Ok(());
+        };
+        let args = &tokens[1..];
+        match &*name.text {
+            "if" | "ifdef" | "ifndef" => {
+                let taken = active
+                    && match &*name.text {
+                        "if" => self.eval_if(args)?,
+                        "ifdef" => self.is_defined(args)?,
+                        _ => !self.is_defined(args)?,
+                    };
+                conds.push(Cond {
+                    active: taken,
+                    taken,
+                    parent: active,
+                    seen_else: false,
+                    line: self.line,
+                });
+            }
+            "elif" => {
+                let cond = self.last_cond(conds, "elif")?;
+                let taken = !cond.taken && cond.parent && self.eval_if(args)?;
+                cond.active = taken;
+                cond.taken |= taken;
+            }
+            "else" => {
+                let cond = self.last_cond(conds, "else")?;
+                cond.active = cond.parent && !cond.taken;
+                cond.taken = true;
+                cond.seen_else = true;
+            }
+            "endif" => {
+                if conds.pop().is_none() {
+                    return Err(self.error(PreprocessErrorKind::UnmatchedDirective("endif")));
+                }
+            }
+            _ if !active => {}
+            "define" => {
+                let (name, makro) = Macro::parse(args).map_err(|x| self.error(x))?;
+                self.macros.insert(name, makro);
+            }
+            "undef" => {
+                let name = self.macro_name(args)?;
+                self.macros.remove(name);
+            }
+            "include" | "include_next" => self.include(args)?,
+            "error" => {
+                let mut msg = String::new();
+                write_tokens(&mut msg, args);
+                return Err(self.error(PreprocessErrorKind::ErrorDirective(msg)));
+            }
+            "warning" => {
+                let mut msg = String::new();
+                write_tokens(&mut msg, args);
+                eprintln!("{}:{}: warning: {}", self.file.path, self.line, msg);
+            }
+            "pragma" => {
+                if args.first().is_some_and(|x| &*x.text == "once") {
+                    self.once.insert(self.file.path.clone());
+                }
+            }
+            // `#line 行号 "文件名"`，参数不是数字时先展开宏
+            "line" => {
+                let args = match args.first() {
+                    Some(x) if x.kind == PpTokenKind::Number => args.to_vec(),
+                    _ => self.expand_all(args.to_vec())?,
+                };
+                self.line_marker(&args, line.newlines, true)?;
+            }
+            // GNU 的行标记 `# 行号 "文件名" 标志...`，`-E` 的输出中会有
+            _ if name.kind == PpTokenKind::Number => {
+                self.line_marker(tokens, line.newlines, false)?;
+            }
+            _ => {
+                let kind = PreprocessErrorKind::UnknownDirective(name.text.to_string());
+                return Err(self.error(kind));
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// `#line` 和行标记：下一行的行号为 `args[0]`，有文件名时之后的 `__FILE__` 和位置使用它
+    ///
+    /// `newlines` 是指令本身占的行数，处理完指令后会加上；`strict` 时不能有多余的参数，
+    /// 否则是行标记，文件名后面的标志被忽略
+    ///
+    fn line_marker(
+        &mut self,
+        args: &[PpToken],
+        newlines: usize,
+        strict: bool,
+    ) -> PreprocessResult<()> {
+        let line = args
+            .first()
+            .filter(|x| x.kind == PpTokenKind::Number && x.text.bytes().all(|c| c.is_ascii_digit()))
+            .and_then(|x| x.text.parse::<usize>().ok())
+            .ok_or_else(|| self.error(PreprocessErrorKind::LineNumber))?;
+        let path = match args.get(1) {
+            None => None,
+            Some(x) if x.kind == PpTokenKind::Literal && x.text.starts_with('"') => {
+                Some(unquote(&x.text[1..x.text.len() - 1]))
+            }
+            Some(_) => return Err(self.error(PreprocessErrorKind::LineFile)),
+        };
+        if strict && args.len() > 2 {
+            return Err(self.error(PreprocessErrorKind::ExtraTokens("line")));
+        }
+        if let Some(path) = path {
+            self.file.path = path;
+        }
+        self.line = line.saturating_sub(newlines);
+        Ok(())
+    }
+
+    /// `#elif` `#else` 所属的 `#if`
+    fn last_cond<'a>(
+        &self,
+        conds: &'a mut [Cond],
+        directive: &'static str,
+    ) -> PreprocessResult<&'a mut Cond> {
+        let Some(cond) = conds.last_mut() else {
+            return Err(self.error(PreprocessErrorKind::UnmatchedDirective(directive)));
+        };
+        if cond.seen_else {
+            return Err(self.error(PreprocessErrorKind::AfterElse(directive)));
+        }
+        Ok(cond)
+    }
+
+    fn macro_name<'a>(&self, args: &'a [PpToken]) -> PreprocessResult<&'a str> {
+        match args.first() {
+            Some(x) if x.is_ident() => Ok(&*x.text),
+            _ => Err(self.error(PreprocessErrorKind::MacroName)),
+        }
+    }
+
+    fn is_defined(&self, args: &[PpToken]) -> PreprocessResult<bool> {
+        let name = self.macro_name(args)?;
+        Ok(self.macros.contains_key(name))
+    }
+
+    fn include(&mut self, args: &[PpToken]) -> PreprocessResult<()> {
+        let (name, angled) = match include_name(args) {
+            Some(x) => x,
+            None => {
+                let expanded = self.expand_all(args.to_vec())?;
+                include_name(&expanded)
+                    .ok_or_else(|| self.error(PreprocessErrorKind::IncludeName))?
+            }
+        };
+        let Some((file, code)) = self.find_include(&name, angled) else {
+            return Err(self.error(PreprocessErrorKind::IncludeNotFound(name)));
+        };
+        if self.once.contains(&file.path) {
+            return Ok(());
+        }
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return Err(self.error(PreprocessErrorKind::IncludeDepth));
+        }
+
+        let saved = (std::mem::replace(&mut self.file, file), self.line);
+        self.line = 1;
+        self.depth += 1;
+        let result = self.process(&code);
+        self.depth -= 1;
+        (self.file, self.line) = saved;
+        result
+    }
+
+    ///
+    /// 查找头文件，返回文件和内容
+    ///
+    /// 查找顺序：`"..."` 先在当前文件的目录查找，然后是 `-I` 目录、内置头文件、系统目录
+    ///
+    fn find_include(&self, name: &str, angled: bool) -> Option<(SourceFile, String)> {
+        let current = self.file.dir.iter().filter(|_| !angled);
+        for dir in current.chain(self.include_dirs.iter()) {
+            if let Some(found) = read_include(dir, name) {
+                return Some(found);
+            }
+        }
+        if let Some(code) = builtin_header(name, &self.target) {
+            let file = SourceFile {
+                path: format!("{}/{}", BUILTIN_INCLUDE_DIR, name),
+                dir: None,
+            };
+            return Some((file, code));
+        }
+        self.system_dirs
+            .iter()
+            .find_map(|dir| read_include(dir, name))
+    }
+}
+
+fn read_include(dir: &Path, name: &str) -> Option<(SourceFile, String)> {
+    let path = dir.join(name);
+    let code = std::fs::read_to_string(&path).ok()?;
+    let file = SourceFile {
+        path: path.to_string_lossy().into_owned(),
+        dir: path.parent().map(Path::to_path_buf),
+    };
+    Some((file, code))
+}
+
+/// 字符串字面量的内容，只处理 `\\` 和 `\"`，行标记中的文件名是这样转义的
+fn unquote(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(chr) = chars.next() {
+        match chr {
+            '\\' => out.extend(chars.next()),
+            _ => out.push(chr),
+        }
+    }
+    out
+}
+
+/// `"name"` 或 `<name>`，返回名字和是否为尖括号形式
+fn include_name(tokens: &[PpToken]) -> Option<(String, bool)> {
+    let first = tokens.first()?;
+    if first.kind == PpTokenKind::Literal && first.text.starts_with('"') && first.text.len() >= 2 {
+        return Some((first.text[1..first.text.len() - 1].to_owned(), false));
+    }
+    if !first.is_punct("<") {
+        return None;
+    }
+    let end = tokens.iter().position(|x| x.is_punct(">"))?;
+    let mut name = String::new();
+    for token in tokens[1..end].iter() {
+        if token.space && !name.is_empty() {
+            name.push(' ');
+        }
+        name.push_str(&token.text);
+    }
+    Some((name, true))
+}
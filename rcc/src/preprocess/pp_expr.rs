@@ -0,0 +1,268 @@
+use crate::err::preprocess_error::{PreprocessErrorKind, PreprocessResult};
+use crate::preprocess::pp_core::Preprocessor;
+use crate::preprocess::pp_token::{PpToken, PpTokenKind};
+use crate::util::literal::{char_value, parse_int};
+
+/// `#if` 中的整数，按 `intmax_t` / `uintmax_t` 计算
+#[derive(Debug, Clone, Copy)]
+struct PpValue {
+    value: i64,
+    unsigned: bool,
+}
+
+impl PpValue {
+    fn int(value: i64) -> Self {
+        Self {
+            value,
+            unsigned: false,
+        }
+    }
+}
+
+impl Preprocessor {
+    /// `#if` `#elif` 的条件：先替换 `defined`，再展开宏，剩下的标识符按 0 处理
+    pub(crate) fn eval_if(&self, tokens: &[PpToken]) -> PreprocessResult<bool> {
+        let tokens = self.replace_defined(tokens)?;
+        let tokens = self.expand_all(tokens)?;
+        let mut parser = ExprParser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        match parser.parse() {
+            Ok(value) => Ok(value.value != 0),
+            Err(msg) => Err(self.error(PreprocessErrorKind::InvalidExpr(msg))),
+        }
+    }
+
+    /// `defined X` `defined(X)` 替换为 1 或 0
+    fn replace_defined(&self, tokens: &[PpToken]) -> PreprocessResult<Vec<PpToken>> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if !(token.is_ident() && &*token.text == "defined") {
+                out.push(token.clone());
+                i += 1;
+                continue;
+            }
+            let (name, len) = match (tokens.get(i + 1), tokens.get(i + 2), tokens.get(i + 3)) {
+                (Some(l), Some(x), Some(r))
+                    if l.is_punct("(") && x.is_ident() && r.is_punct(")") =>
+                {
+                    (x, 4)
+                }
+                (Some(x), ..) if x.is_ident() => (x, 2),
+                _ => return Err(self.error(PreprocessErrorKind::MacroName)),
+            };
+            let value = match self.is_macro(name) {
+                true => "1",
+                false => "0",
+            };
+            out.push(PpToken::new(PpTokenKind::Number, value, token.space));
+            i += len;
+        }
+        Ok(out)
+    }
+}
+
+///
+/// `#if` 表达式的递归下降求值
+///
+/// # Members
+/// - `tokens`: 展开后的记号
+/// - `pos`: 当前位置
+///
+struct ExprParser<'a> {
+    tokens: &'a [PpToken],
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn parse(&mut self) -> Result<PpValue, String> {
+        let value = self.conditional(true)?;
+        match self.tokens.get(self.pos) {
+            None => Ok(value),
+            Some(x) => Err(format!("unexpected '{}'", x.text)),
+        }
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = self.tokens.get(self.pos).is_some_and(|x| x.is_punct(punct));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        match self.eat(punct) {
+            true => Ok(()),
+            false => Err(format!("expected '{}'", punct)),
+        }
+    }
+
+    /// 下一个二元运算符
+    fn peek_op(&self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos)?;
+        let op = &*token.text;
+        (token.kind == PpTokenKind::Punct && precedence(op) > 0).then_some(op)
+    }
+
+    /// `eval` 为 false 时处于短路的分支中，除零不报错
+    fn conditional(&mut self, eval: bool) -> Result<PpValue, String> {
+        let cond = self.binary(1, eval)?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let taken = cond.value != 0;
+        let then = self.conditional(eval && taken)?;
+        self.expect(":")?;
+        let els = self.conditional(eval && !taken)?;
+        let value = if taken { then.value } else { els.value };
+        Ok(PpValue {
+            value,
+            unsigned: then.unsigned || els.unsigned,
+        })
+    }
+
+    /// 优先级不低于 `min` 的二元运算
+    fn binary(&mut self, min: u8, eval: bool) -> Result<PpValue, String> {
+        let mut lhs = self.unary(eval)?;
+        while let Some(op) = self.peek_op()
+            && precedence(op) >= min
+        {
+            self.pos += 1;
+            let rhs_eval = match op {
+                "&&" => eval && lhs.value != 0,
+                "||" => eval && lhs.value == 0,
+                _ => eval,
+            };
+            let rhs = self.binary(precedence(op) + 1, rhs_eval)?;
+            lhs = apply(op, lhs, rhs, eval)?;
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self, eval: bool) -> Result<PpValue, String> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err("expected value".to_owned());
+        };
+        self.pos += 1;
+        match token.kind {
+            PpTokenKind::Number => parse_number(&token.text),
+            PpTokenKind::Literal if token.text.ends_with('\'') => {
+                let text = token.text.trim_start_matches(['L', 'u', 'U', '8']);
+                Ok(PpValue::int(char_value(text) as i64))
+            }
+            // 展开后剩下的标识符
+            PpTokenKind::Ident => Ok(PpValue::int(0)),
+            PpTokenKind::Punct => {
+                let value = match &*token.text {
+                    "(" => {
+                        let value = self.conditional(eval)?;
+                        self.expect(")")?;
+                        return Ok(value);
+                    }
+                    "+" => return self.unary(eval),
+                    "-" | "~" | "!" => self.unary(eval)?,
+                    _ => return Err(format!("unexpected '{}'", token.text)),
+                };
+                Ok(match &*token.text {
+                    "-" => PpValue {
+                        value: value.value.wrapping_neg(),
+                        ..value
+                    },
+                    "~" => PpValue {
+                        value: !value.value,
+                        ..value
+                    },
+                    _ => PpValue::int((value.value == 0) as i64),
+                })
+            }
+            _ => Err(format!("unexpected '{}'", token.text)),
+        }
+    }
+}
+
+fn precedence(op: &str) -> u8 {
+    match op {
+        "*" | "/" | "%" => 10,
+        "+" | "-" => 9,
+        "<<" | ">>" => 8,
+        "<" | ">" | "<=" | ">=" => 7,
+        "==" | "!=" => 6,
+        "&" => 5,
+        "^" => 4,
+        "|" => 3,
+        "&&" => 2,
+        "||" => 1,
+        _ => 0,
+    }
+}
+
+/// 整数常量，超出 `intmax_t` 的按无符号处理
+fn parse_number(text: &str) -> Result<PpValue, String> {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    let value = parse_int(digits)
+        .filter(|x| *x <= u64::MAX as u128)
+        .ok_or_else(|| format!("invalid integer constant '{}'", text))?;
+    Ok(PpValue {
+        value: value as u64 as i64,
+        unsigned: text[digits.len()..].contains(['u', 'U']) || value > i64::MAX as u128,
+    })
+}
+
+/// 二元运算，有一个操作数无符号时按无符号计算
+fn apply(op: &str, lhs: PpValue, rhs: PpValue, eval: bool) -> Result<PpValue, String> {
+    let unsigned = lhs.unsigned || rhs.unsigned;
+    let (x, y) = (lhs.value, rhs.value);
+    let arith = |value: i64| PpValue { value, unsigned };
+    let bool = |value: bool| PpValue::int(value as i64);
+    let value = match op {
+        "*" => arith(x.wrapping_mul(y)),
+        "/" | "%" if y == 0 => match eval {
+            true => return Err("division by zero".to_owned()),
+            false => arith(0),
+        },
+        "/" if unsigned => arith((x as u64 / y as u64) as i64),
+        "%" if unsigned => arith((x as u64 % y as u64) as i64),
+        "/" => arith(x.wrapping_div(y)),
+        "%" => arith(x.wrapping_rem(y)),
+        "+" => arith(x.wrapping_add(y)),
+        "-" => arith(x.wrapping_sub(y)),
+        // 移位的结果类型是左操作数的类型
+        "<<" => PpValue {
+            value: x.wrapping_shl(y as u32),
+            ..lhs
+        },
+        ">>" if lhs.unsigned => PpValue {
+            value: (x as u64).wrapping_shr(y as u32) as i64,
+            ..lhs
+        },
+        ">>" => PpValue {
+            value: x.wrapping_shr(y as u32),
+            ..lhs
+        },
+        "<" | ">" | "<=" | ">=" => {
+            let ord = match unsigned {
+                true => (x as u64).cmp(&(y as u64)),
+                false => x.cmp(&y),
+            };
+            bool(match op {
+                "<" => ord.is_lt(),
+                ">" => ord.is_gt(),
+                "<=" => ord.is_le(),
+                _ => ord.is_ge(),
+            })
+        }
+        "==" => bool(x == y),
+        "!=" => bool(x != y),
+        "&" => arith(x & y),
+        "^" => arith(x ^ y),
+        "|" => arith(x | y),
+        "&&" => bool(x != 0 && y != 0),
+        "||" => bool(x != 0 || y != 0),
+        _ => unreachable!("not a binary operator"),
+    };
+    Ok(value)
+}
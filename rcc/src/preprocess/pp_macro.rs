@@ -0,0 +1,481 @@
+use crate::err::preprocess_error::{PreprocessErrorKind, PreprocessResult};
+use crate::preprocess::pp_core::Preprocessor;
+use crate::preprocess::pp_token::{PpToken, PpTokenKind, tokenize, write_tokens};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// 值由展开时的位置决定的预定义宏
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicMacro {
+    /// `__FILE__` 当前文件名
+    File,
+    /// `__LINE__` 当前行号
+    Line,
+    /// `__COUNTER__` 从 0 开始，每次展开加 1
+    Counter,
+}
+
+impl DynamicMacro {
+    pub const ALL: [DynamicMacro; 3] = [
+        DynamicMacro::File,
+        DynamicMacro::Line,
+        DynamicMacro::Counter,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DynamicMacro::File => "__FILE__",
+            DynamicMacro::Line => "__LINE__",
+            DynamicMacro::Counter => "__COUNTER__",
+        }
+    }
+}
+
+///
+/// 宏定义
+///
+/// # Members
+/// - `params`: 形参，对象式宏为 None
+/// - `variadic`: 最后一个形参是否为 `...`，可变部分用 `__VA_ARGS__` 引用
+/// - `body`: 替换列表
+/// - `dynamic`: 值在展开时计算的预定义宏，此时 `body` 为空
+///
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub params: Option<Vec<Rc<str>>>,
+    pub variadic: bool,
+    pub body: Vec<PpToken>,
+    pub dynamic: Option<DynamicMacro>,
+}
+
+impl Macro {
+    pub fn dynamic(kind: DynamicMacro) -> Self {
+        Self {
+            params: None,
+            variadic: false,
+            body: Vec::new(),
+            dynamic: Some(kind),
+        }
+    }
+
+    /// `#define` 之后的文本，用于 `-dM`
+    pub fn definition(&self, name: &str) -> String {
+        let mut out = name.to_owned();
+        if let Some(params) = &self.params {
+            let mut list: Vec<&str> = params.iter().map(|x| &**x).collect();
+            if self.variadic {
+                list.push("...");
+            }
+            out.push_str(&format!("({})", list.join(",")));
+        }
+        if !self.body.is_empty() {
+            out.push(' ');
+            write_tokens(&mut out, &self.body);
+        }
+        out
+    }
+
+    /// 解析 `#define` 之后的记号，返回宏名和定义
+    pub fn parse(tokens: &[PpToken]) -> Result<(Rc<str>, Macro), PreprocessErrorKind> {
+        let name = match tokens.first() {
+            Some(x) if x.is_ident() && &*x.text != "defined" => x.text.clone(),
+            _ => return Err(PreprocessErrorKind::MacroName),
+        };
+        let mut rest = &tokens[1..];
+        let (mut params, mut variadic) = (None, false);
+        // 宏名后紧跟 `(` 才是函数式宏
+        if let Some(lparen) = rest.first()
+            && lparen.is_punct("(")
+            && !lparen.space
+        {
+            let (list, is_variadic, len) = parse_params(&rest[1..])?;
+            (params, variadic) = (Some(list), is_variadic);
+            rest = &rest[1 + len..];
+        }
+
+        let mut body = rest.to_vec();
+        if let Some(first) = body.first_mut() {
+            first.space = false;
+        }
+        if body.first().is_some_and(|x| x.is_punct("##"))
+            || body.last().is_some_and(|x| x.is_punct("##"))
+        {
+            let msg = "'##' cannot appear at either end of a macro expansion".to_owned();
+            return Err(PreprocessErrorKind::InvalidMacro(msg));
+        }
+        let makro = Macro {
+            params,
+            variadic,
+            body,
+            dynamic: None,
+        };
+        let is_stringify = |x: &[PpToken]| x[0].is_punct("#") && makro.param(&x[1]).is_none();
+        if makro.params.is_some()
+            && (makro.body.windows(2).any(is_stringify)
+                || makro.body.last().is_some_and(|x| x.is_punct("#")))
+        {
+            let msg = "'#' is not followed by a macro parameter".to_owned();
+            return Err(PreprocessErrorKind::InvalidMacro(msg));
+        }
+        Ok((name, makro))
+    }
+
+    /// `token` 是第几个形参，`__VA_ARGS__` 排在命名的形参之后
+    fn param(&self, token: &PpToken) -> Option<usize> {
+        let params = self.params.as_ref()?;
+        if !token.is_ident() {
+            return None;
+        }
+        match params.iter().position(|x| *x == token.text) {
+            Some(index) => Some(index),
+            None if self.variadic && &*token.text == "__VA_ARGS__" => Some(params.len()),
+            None => None,
+        }
+    }
+}
+
+/// 解析 `(` 之后的形参列表，返回形参、是否可变和消耗的记号数（包括 `)`）
+fn parse_params(tokens: &[PpToken]) -> Result<(Vec<Rc<str>>, bool, usize), PreprocessErrorKind> {
+    let error = |msg: &str| PreprocessErrorKind::InvalidMacro(msg.to_owned());
+    let mut params: Vec<Rc<str>> = Vec::new();
+    if tokens.first().is_some_and(|x| x.is_punct(")")) {
+        return Ok((params, false, 1));
+    }
+    let mut i = 0;
+    loop {
+        match tokens.get(i) {
+            Some(x) if x.is_punct("...") => {
+                return match tokens.get(i + 1) {
+                    Some(x) if x.is_punct(")") => Ok((params, true, i + 2)),
+                    _ => Err(error("expected ')' after '...'")),
+                };
+            }
+            Some(x) if x.is_ident() => {
+                if params.contains(&x.text) {
+                    return Err(error(&format!("duplicate macro parameter '{}'", x.text)));
+                }
+                params.push(x.text.clone());
+            }
+            _ => return Err(error("expected parameter name")),
+        }
+        match tokens.get(i + 1) {
+            Some(x) if x.is_punct(",") => i += 2,
+            Some(x) if x.is_punct(")") => return Ok((params, false, i + 2)),
+            _ => return Err(error("expected ',' or ')'")),
+        }
+    }
+}
+
+///
+/// 宏展开的输入
+///
+/// # Members
+/// - `pending`: 替换得到的记号，先于源码中的记号重新扫描
+/// - `tokens`: 源码中的记号
+/// - `pos`: 下一个源码记号的位置
+///
+pub struct TokenInput<'a> {
+    pending: VecDeque<PpToken>,
+    tokens: &'a [PpToken],
+    pub pos: usize,
+}
+
+impl<'a> TokenInput<'a> {
+    pub fn new(tokens: &'a [PpToken]) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            tokens,
+            pos: 0,
+        }
+    }
+
+    fn next(&mut self) -> Option<PpToken> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+        let token = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn push_front(&mut self, tokens: Vec<PpToken>) {
+        for token in tokens.into_iter().rev() {
+            self.pending.push_front(token);
+        }
+    }
+
+    /// 下一个记号是否为 `(`，是则消耗掉；调用函数式宏的实参可以跨行
+    fn eat_lparen(&mut self) -> bool {
+        if let Some(token) = self.pending.front() {
+            let found = token.is_punct("(");
+            if found {
+                self.pending.pop_front();
+            }
+            return found;
+        }
+        let rest = &self.tokens[self.pos..];
+        let skip = rest
+            .iter()
+            .take_while(|x| x.kind == PpTokenKind::Newline)
+            .count();
+        match rest.get(skip) {
+            Some(x) if x.is_punct("(") => {
+                self.pos += skip + 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Preprocessor {
+    /// `token` 是否为已定义的宏名
+    pub(crate) fn is_macro(&self, token: &PpToken) -> bool {
+        token.is_ident() && self.macros.contains_key(&*token.text)
+    }
+
+    ///
+    /// 展开宏直到行尾或者输入结束，行尾的换行记号被消耗掉
+    ///
+    /// 展开的结果放回输入重新扫描，每个记号记录产生它的宏，不会再次展开这些宏；
+    /// 嵌套展开的结果的位置是最外层的宏名的位置
+    ///
+    pub(crate) fn expand(&self, input: &mut TokenInput) -> PreprocessResult<Vec<PpToken>> {
+        let mut out = Vec::new();
+        while let Some(token) = input.next() {
+            if token.kind == PpTokenKind::Newline {
+                break;
+            }
+            if !self.is_macro(&token) || token.is_hidden(&token.text) {
+                out.push(token);
+                continue;
+            }
+            let makro = &self.macros[&*token.text];
+            if let Some(kind) = makro.dynamic {
+                let mut value = self.expand_dynamic(kind, token.space);
+                value.pos = token.pos;
+                out.push(value);
+                continue;
+            }
+            let args = match &makro.params {
+                None => Vec::new(),
+                Some(_) if !input.eat_lparen() => {
+                    out.push(token);
+                    continue;
+                }
+                Some(params) => {
+                    let args =
+                        self.collect_args(input, &token.text, params.len(), makro.variadic)?;
+                    self.check_args(&token.text, makro, args)?
+                }
+            };
+            let mut hide = (*token.hide).clone();
+            hide.push(token.text.clone());
+            let mut body = self.substitute(makro, &args, &hide)?;
+            if let Some(first) = body.first_mut() {
+                first.space = token.space;
+            }
+            // 展开结果的位置都是宏名的位置
+            for x in body.iter_mut() {
+                x.pos = token.pos;
+            }
+            input.push_front(body);
+        }
+        Ok(out)
+    }
+
+    /// `__FILE__` `__LINE__` `__COUNTER__` 的值
+    fn expand_dynamic(&self, kind: DynamicMacro, space: bool) -> PpToken {
+        let (kind, text) = match kind {
+            DynamicMacro::File => {
+                let path = self.file.path.replace('\\', "\\\\").replace('"', "\\\"");
+                (PpTokenKind::Literal, format!("\"{}\"", path))
+            }
+            DynamicMacro::Line => (PpTokenKind::Number, self.line.to_string()),
+            DynamicMacro::Counter => {
+                let value = self.counter.get();
+                self.counter.set(value + 1);
+                (PpTokenKind::Number, value.to_string())
+            }
+        };
+        PpToken::new(kind, &text, space)
+    }
+
+    /// 完整展开一个记号序列，用于实参和 `#if` `#include` 的操作数
+    pub(crate) fn expand_all(&self, tokens: Vec<PpToken>) -> PreprocessResult<Vec<PpToken>> {
+        let mut input = TokenInput::new(&tokens);
+        self.expand(&mut input)
+    }
+
+    /// 收集 `(` 之后的实参，可变参数部分的逗号不分割实参
+    fn collect_args(
+        &self,
+        input: &mut TokenInput,
+        name: &str,
+        params: usize,
+        variadic: bool,
+    ) -> PreprocessResult<Vec<Vec<PpToken>>> {
+        let mut args = vec![Vec::new()];
+        let mut depth = 0;
+        let mut space = false;
+        loop {
+            let Some(mut token) = input.next() else {
+                let kind = PreprocessErrorKind::UnterminatedArgs(name.to_owned());
+                return Err(self.error(kind));
+            };
+            if token.kind == PpTokenKind::Newline {
+                space = true;
+                continue;
+            }
+            token.space |= space;
+            space = false;
+            if token.is_punct("(") {
+                depth += 1;
+            } else if token.is_punct(")") {
+                if depth == 0 {
+                    return Ok(args);
+                }
+                depth -= 1;
+            } else if token.is_punct(",") && depth == 0 && !(variadic && args.len() > params) {
+                args.push(Vec::new());
+                continue;
+            }
+            args.last_mut().unwrap().push(token);
+        }
+    }
+
+    /// 检查实参个数，可变参数为空时补上空的实参
+    fn check_args(
+        &self,
+        name: &str,
+        makro: &Macro,
+        mut args: Vec<Vec<PpToken>>,
+    ) -> PreprocessResult<Vec<Vec<PpToken>>> {
+        let params = makro.params.as_ref().map_or(0, |x| x.len());
+        // `f()` 调用没有形参的宏时是零个实参
+        if params == 0 && !makro.variadic && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+        let valid = match makro.variadic {
+            true => args.len() >= params,
+            false => args.len() == params,
+        };
+        if !valid {
+            let kind = PreprocessErrorKind::ArgCount {
+                name: name.to_owned(),
+                expected: params,
+                found: args.len(),
+            };
+            return Err(self.error(kind));
+        }
+        if makro.variadic && args.len() == params {
+            args.push(Vec::new());
+        }
+        Ok(args)
+    }
+
+    /// 用实参替换形参，处理 `#` 和 `##`，结果的每个记号加入隐藏集合 `hide`
+    fn substitute(
+        &self,
+        makro: &Macro,
+        args: &[Vec<PpToken>],
+        hide: &[Rc<str>],
+    ) -> PreprocessResult<Vec<PpToken>> {
+        let body = &makro.body;
+        let mut out: Vec<PpToken> = Vec::new();
+        // 上一个形参的实参为空，后面的 `##` 没有左操作数
+        let mut lhs_empty = false;
+        let mut i = 0;
+        while i < body.len() {
+            let token = &body[i];
+            let next = body.get(i + 1);
+            if makro.params.is_some()
+                && token.is_punct("#")
+                && let Some(index) = next.and_then(|x| makro.param(x))
+            {
+                out.push(stringify(&args[index], token.space));
+                lhs_empty = false;
+                i += 2;
+            } else if token.is_punct("##")
+                && let Some(rhs) = next
+            {
+                let index = makro.param(rhs);
+                let mut rhs_tokens = match index {
+                    Some(index) => args[index].clone(),
+                    None => vec![rhs.clone()],
+                };
+                let is_va_args = &*rhs.text == "__VA_ARGS__" && index.is_some();
+                if is_va_args && rhs_tokens.is_empty() && !lhs_empty {
+                    // GNU 扩展：`, ## __VA_ARGS__` 在可变参数为空时删除逗号
+                    if out.last().is_some_and(|x| x.is_punct(",")) {
+                        out.pop();
+                    }
+                } else if !lhs_empty
+                    && !rhs_tokens.is_empty()
+                    && let Some(lhs) = out.pop()
+                {
+                    let first = rhs_tokens.remove(0);
+                    out.extend(paste(&lhs, &first));
+                }
+                lhs_empty = lhs_empty && rhs_tokens.is_empty();
+                out.extend(rhs_tokens);
+                i += 2;
+            } else if let Some(index) = makro.param(token) {
+                // `##` 的操作数不展开
+                let raw = next.is_some_and(|x| x.is_punct("##"));
+                let mut tokens = match raw {
+                    true => args[index].clone(),
+                    false => self.expand_all(args[index].clone())?,
+                };
+                lhs_empty = raw && tokens.is_empty();
+                if let Some(first) = tokens.first_mut() {
+                    first.space = token.space;
+                }
+                out.extend(tokens);
+                i += 1;
+            } else {
+                out.push(token.clone());
+                lhs_empty = false;
+                i += 1;
+            }
+        }
+        for token in out.iter_mut() {
+            token.add_hide(hide);
+        }
+        Ok(out)
+    }
+}
+
+/// `#` 把实参转换为字符串字面量，记号之间的空白变为一个空格
+fn stringify(arg: &[PpToken], space: bool) -> PpToken {
+    let mut text = String::from("\"");
+    for (i, token) in arg.iter().enumerate() {
+        if i > 0 && token.space {
+            text.push(' ');
+        }
+        match token.kind {
+            PpTokenKind::Literal => {
+                for chr in token.text.chars() {
+                    if chr == '"' || chr == '\\' {
+                        text.push('\\');
+                    }
+                    text.push(chr);
+                }
+            }
+            _ => text.push_str(&token.text),
+        }
+    }
+    text.push('"');
+    PpToken::new(PpTokenKind::Literal, &text, space)
+}
+
+/// `##` 拼接两个记号，拼接结果重新切分为记号
+fn paste(lhs: &PpToken, rhs: &PpToken) -> Vec<PpToken> {
+    let text = format!("{}{}", lhs.text, rhs.text);
+    let mut tokens = tokenize(&text);
+    if let Some(first) = tokens.first_mut() {
+        first.space = lhs.space;
+        first.hide = lhs.hide.clone();
+    }
+    tokens
+}
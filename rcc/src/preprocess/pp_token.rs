@@ -0,0 +1,359 @@
+use std::ops::Range;
+use std::rc::Rc;
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+/// 预处理记号的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpTokenKind {
+    Ident,
+    /// pp-number，包括整数和浮点数
+    Number,
+    /// 字符串和字符字面量，包括前缀
+    Literal,
+    Punct,
+    /// 不能组成其他记号的单个字符
+    Other,
+    /// 行尾，只在宏展开的输入中出现
+    Newline,
+}
+
+/// 重新扫描时不能再展开的宏名
+pub type HideSet = Rc<Vec<Rc<str>>>;
+
+///
+/// 预处理记号
+///
+/// # Members
+/// - `kind`: 种类
+/// - `text`: 记号的拼写，不含续行
+/// - `space`: 前面是否有空白，注释也算空白
+/// - `hide`: 产生该记号的宏
+/// - `pos`: 在所在文件中的字节偏移；宏展开产生的记号为展开处宏名的偏移
+///
+#[derive(Debug, Clone)]
+pub struct PpToken {
+    pub kind: PpTokenKind,
+    pub text: Rc<str>,
+    pub space: bool,
+    pub hide: HideSet,
+    pub pos: usize,
+}
+
+impl PpToken {
+    pub fn new(kind: PpTokenKind, text: &str, space: bool) -> Self {
+        Self {
+            kind,
+            text: Rc::from(text),
+            space,
+            hide: HideSet::default(),
+            pos: 0,
+        }
+    }
+
+    pub fn is_punct(&self, punct: &str) -> bool {
+        self.kind == PpTokenKind::Punct && &*self.text == punct
+    }
+
+    pub fn is_ident(&self) -> bool {
+        self.kind == PpTokenKind::Ident
+    }
+
+    /// 该记号是否由名为 `name` 的宏产生
+    pub fn is_hidden(&self, name: &str) -> bool {
+        self.hide.iter().any(|x| &**x == name)
+    }
+
+    /// 把 `hide` 中的宏名加入隐藏集合
+    pub fn add_hide(&mut self, hide: &[Rc<str>]) {
+        let new: Vec<_> = hide
+            .iter()
+            .filter(|x| !self.is_hidden(x))
+            .cloned()
+            .collect();
+        if !new.is_empty() {
+            Rc::make_mut(&mut self.hide).extend(new);
+        }
+    }
+}
+
+///
+/// 逻辑行，续行和跨行的块注释都属于同一行
+///
+/// # Members
+/// - `range`: 在源码中的区间，包括行尾的换行符
+/// - `tokens`: 行内的记号
+/// - `newlines`: 区间内换行符的个数
+/// - `spliced`: 是否有续行，有续行的行不能原样交给 lexer
+///
+#[derive(Debug)]
+pub struct Line {
+    pub range: Range<usize>,
+    pub tokens: Vec<PpToken>,
+    pub newlines: usize,
+    pub spliced: bool,
+}
+
+impl Line {
+    /// 第一个记号是 `#` 的行是预处理指令
+    pub fn is_directive(&self) -> bool {
+        self.tokens.first().is_some_and(|x| x.is_punct("#"))
+    }
+}
+
+/// 多字符的标点，长的在前
+const PUNCTS: &[&str] = &[
+    "...", "<<=", ">>=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=",
+    "/=", "%=", "+=", "-=", "&=", "^=", "|=", "##",
+];
+
+/// 单字符的标点
+const SINGLE_PUNCTS: &str = "[](){}.&*+-~!/%<>^|?:;=,#";
+
+///
+/// 把源码切分为逻辑行和预处理记号，注释替换为空白
+///
+/// # Members
+/// - `text`: 源码
+/// - `pos`: 当前位置
+/// - `newlines`: 当前行已经跨过的换行符个数
+/// - `spliced`: 当前行是否有续行
+///
+#[derive(Clone, Copy)]
+struct PpLexer<'a> {
+    text: &'a str,
+    pos: usize,
+    newlines: usize,
+    spliced: bool,
+}
+
+impl PpLexer<'_> {
+    /// 跳过续行 `\` + 换行
+    fn splice(&mut self) {
+        loop {
+            let rest = &self.text[self.pos..];
+            let len = if rest.starts_with("\\\n") {
+                2
+            } else if rest.starts_with("\\\r\n") {
+                3
+            } else {
+                break;
+            };
+            self.pos += len;
+            self.newlines += 1;
+            self.spliced = true;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.splice();
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let chr = self.peek()?;
+        self.pos += chr.len_utf8();
+        if chr == '\n' {
+            self.newlines += 1;
+        }
+        Some(chr)
+    }
+
+    /// 向后看第 `n` 个字符，跳过续行
+    fn lookahead(&self, n: usize) -> Option<char> {
+        let mut lex = *self;
+        for _ in 0..n {
+            lex.bump();
+        }
+        lex.peek()
+    }
+
+    fn skip_line_comment(&mut self) {
+        while let Some(chr) = self.peek() {
+            if chr == '\n' {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    /// 未闭合的块注释吃掉剩下的所有内容，由 lexer 报告
+    fn skip_block_comment(&mut self) {
+        self.bump();
+        self.bump();
+        while let Some(chr) = self.bump() {
+            if chr == '*' && self.peek() == Some('/') {
+                self.bump();
+                break;
+            }
+        }
+    }
+
+    fn token(&mut self, space: bool) -> PpToken {
+        let chr = self.peek().unwrap();
+        let pos = self.pos;
+        let mut text = String::new();
+        let kind = if chr == '_' || is_xid_start(chr) {
+            while let Some(chr) = self.peek().filter(|x| is_xid_continue(*x)) {
+                text.push(chr);
+                self.bump();
+            }
+            match self.peek() {
+                Some(quote @ ('"' | '\'')) if matches!(text.as_str(), "L" | "u" | "U" | "u8") => {
+                    self.literal(&mut text, quote);
+                    PpTokenKind::Literal
+                }
+                _ => PpTokenKind::Ident,
+            }
+        } else if chr.is_ascii_digit()
+            || (chr == '.' && self.lookahead(1).is_some_and(|x| x.is_ascii_digit()))
+        {
+            while let Some(chr) = self.peek() {
+                if matches!(chr, 'e' | 'E' | 'p' | 'P')
+                    && matches!(self.lookahead(1), Some('+' | '-'))
+                {
+                    text.push(chr);
+                    self.bump();
+                } else if !(chr == '.' || is_xid_continue(chr)) {
+                    break;
+                }
+                text.extend(self.bump());
+            }
+            PpTokenKind::Number
+        } else if chr == '"' || chr == '\'' {
+            self.literal(&mut text, chr);
+            PpTokenKind::Literal
+        } else if let Some(punct) = PUNCTS.iter().find(|x| self.matches(x)) {
+            for _ in 0..punct.len() {
+                text.extend(self.bump());
+            }
+            PpTokenKind::Punct
+        } else {
+            text.extend(self.bump());
+            match SINGLE_PUNCTS.contains(chr) {
+                true => PpTokenKind::Punct,
+                false => PpTokenKind::Other,
+            }
+        };
+        let mut token = PpToken::new(kind, &text, space);
+        token.pos = pos;
+        token
+    }
+
+    fn matches(&self, punct: &str) -> bool {
+        punct
+            .chars()
+            .enumerate()
+            .all(|(i, x)| self.lookahead(i) == Some(x))
+    }
+
+    /// 字符串或字符字面量，未闭合时停在行尾
+    fn literal(&mut self, text: &mut String, quote: char) {
+        text.extend(self.bump());
+        while let Some(chr) = self.peek() {
+            if chr == '\n' {
+                break;
+            }
+            text.extend(self.bump());
+            if chr == quote {
+                break;
+            }
+            if chr == '\\' && self.peek().is_some_and(|x| x != '\n') {
+                text.extend(self.bump());
+            }
+        }
+    }
+}
+
+/// 把源码切分为逻辑行
+pub fn split_lines(text: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut lex = PpLexer {
+        text,
+        pos: 0,
+        newlines: 0,
+        spliced: false,
+    };
+    loop {
+        let beg = lex.pos;
+        lex.newlines = 0;
+        lex.spliced = false;
+        let mut tokens = Vec::new();
+        let mut space = false;
+        let mut eol = false;
+        while let Some(chr) = lex.peek() {
+            if chr == '\n' {
+                lex.bump();
+                eol = true;
+                break;
+            } else if chr.is_whitespace() {
+                lex.bump();
+                space = true;
+            } else if chr == '/' && lex.lookahead(1) == Some('/') {
+                lex.skip_line_comment();
+                space = true;
+            } else if chr == '/' && lex.lookahead(1) == Some('*') {
+                lex.skip_block_comment();
+                space = true;
+            } else {
+                tokens.push(lex.token(space));
+                space = false;
+            }
+        }
+        if lex.pos == beg {
+            break;
+        }
+        lines.push(Line {
+            range: beg..lex.pos,
+            tokens,
+            newlines: lex.newlines,
+            spliced: lex.spliced,
+        });
+        if !eol {
+            break;
+        }
+    }
+    lines
+}
+
+/// 一段文本中的所有记号，用于 `##` 拼接的结果
+pub fn tokenize(text: &str) -> Vec<PpToken> {
+    split_lines(text)
+        .into_iter()
+        .flat_map(|x| x.tokens)
+        .collect()
+}
+
+/// 输出记号序列；相邻的两个记号直接拼接会变成别的记号时中间加空格
+pub fn write_tokens(out: &mut String, tokens: &[PpToken]) {
+    write_tokens_with(out, tokens, |_, _| {});
+}
+
+/// 同 `write_tokens`，输出每个记号之前用它在 `out` 中的偏移调用 `mark`
+pub fn write_tokens_with(
+    out: &mut String,
+    tokens: &[PpToken],
+    mut mark: impl FnMut(usize, &PpToken),
+) {
+    let mut prev: Option<&PpToken> = None;
+    for token in tokens.iter() {
+        if let Some(prev) = prev
+            && (token.space || would_merge(prev, token))
+        {
+            out.push(' ');
+        }
+        mark(out.len(), token);
+        out.push_str(&token.text);
+        prev = Some(token);
+    }
+}
+
+fn would_merge(prev: &PpToken, next: &PpToken) -> bool {
+    let is_word = |x: char| x == '_' || x == '.' || is_xid_continue(x);
+    let is_op = |x: char| "+-*/%<>=&|^!#.:".contains(x);
+    let (Some(a), Some(b)) = (prev.text.chars().last(), next.text.chars().next()) else {
+        return false;
+    };
+    (is_word(a) && is_word(b))
+        || (is_op(a) && is_op(b))
+        || (next.kind == PpTokenKind::Literal && is_word(a))
+}
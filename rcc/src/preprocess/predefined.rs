@@ -0,0 +1,142 @@
+//!
+//! 预定义宏：标准要求的宏、编译器版本和目标平台的数据模型，类型的大小来自前端的类型布局
+//!
+//! `__FILE__` `__LINE__` `__COUNTER__` 的值随展开的位置变化，见 `DynamicMacro`
+//!
+
+use crate::parser::ast::types::{FloatSize, IntegerSize, TypeLayout};
+use crate::preprocess::headers::define;
+use backend::target::{Arch, TargetInfo};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// 预定义宏的 `#define` 文本，`std_version` 为 `__STDC_VERSION__` 的值
+pub fn predefined_macros(target: &TargetInfo, std_version: Option<u32>) -> String {
+    let mut out = String::new();
+    define(&mut out, "__STDC__", 1);
+    if let Some(version) = std_version {
+        define(&mut out, "__STDC_VERSION__", format!("{}L", version));
+    }
+    define(&mut out, "__STDC_HOSTED__", 1);
+    let (date, time) = date_time(build_time());
+    define(&mut out, "__DATE__", format!("\"{}\"", date));
+    define(&mut out, "__TIME__", format!("\"{}\"", time));
+
+    define(&mut out, "__rustcc__", 1);
+    let version = [
+        ("__rustcc_major__", env!("CARGO_PKG_VERSION_MAJOR")),
+        ("__rustcc_minor__", env!("CARGO_PKG_VERSION_MINOR")),
+        ("__rustcc_patchlevel__", env!("CARGO_PKG_VERSION_PATCH")),
+    ];
+    for (name, value) in version {
+        define(&mut out, name, value);
+    }
+    let version = format!("\"{}\"", env!("CARGO_PKG_VERSION"));
+    define(&mut out, "__rustcc_version__", version);
+
+    let arch: &[(&str, u32)] = match target.arch {
+        Arch::X86_64 => &[
+            ("__x86_64__", 1),
+            ("__x86_64", 1),
+            ("__amd64__", 1),
+            ("__amd64", 1),
+        ],
+        Arch::Riscv64 => &[("__riscv", 1), ("__riscv_xlen", 64)],
+        Arch::Wasm32 => &[("__wasm__", 1), ("__wasm", 1), ("__wasm32__", 1)],
+    };
+    for (name, value) in arch {
+        define(&mut out, name, value);
+    }
+    if target.triple.contains("linux") {
+        for name in [
+            "__linux__",
+            "__linux",
+            "__gnu_linux__",
+            "__unix__",
+            "__unix",
+            "__ELF__",
+        ] {
+            define(&mut out, name, 1);
+        }
+    }
+
+    let long = IntegerSize::Long.sizeof(target);
+    let pointer = TypeLayout::pointer(target).size;
+    match (long, pointer) {
+        (8, 8) => {
+            define(&mut out, "_LP64", 1);
+            define(&mut out, "__LP64__", 1);
+        }
+        (4, 4) => {
+            define(&mut out, "_ILP32", 1);
+            define(&mut out, "__ILP32__", 1);
+        }
+        _ => {}
+    }
+    define(&mut out, "__CHAR_BIT__", 8);
+    if !IntegerSize::CHAR_SIGNED {
+        define(&mut out, "__CHAR_UNSIGNED__", 1);
+    }
+    // `size_t` `ptrdiff_t` 和指针一样宽，`wchar_t` 是 `int`
+    let sizes = [
+        ("SHORT", IntegerSize::Short.sizeof(target)),
+        ("INT", IntegerSize::Int.sizeof(target)),
+        ("LONG", long),
+        ("LONG_LONG", IntegerSize::LongLong.sizeof(target)),
+        ("POINTER", pointer),
+        ("FLOAT", FloatSize::Float.sizeof(target)),
+        ("DOUBLE", FloatSize::Double.sizeof(target)),
+        ("LONG_DOUBLE", FloatSize::LongDouble.sizeof(target)),
+        ("SIZE_T", pointer),
+        ("PTRDIFF_T", pointer),
+        ("WCHAR_T", IntegerSize::Int.sizeof(target)),
+    ];
+    for (name, size) in sizes {
+        define(&mut out, &format!("__SIZEOF_{}__", name), size);
+    }
+    // 所有目标都是小端
+    define(&mut out, "__ORDER_LITTLE_ENDIAN__", 1234);
+    define(&mut out, "__ORDER_BIG_ENDIAN__", 4321);
+    define(&mut out, "__BYTE_ORDER__", "__ORDER_LITTLE_ENDIAN__");
+    out
+}
+
+/// 编译时间的 Unix 时间戳，设置了 `SOURCE_DATE_EPOCH` 时使用它，保证输出可以复现
+fn build_time() -> u64 {
+    if let Some(time) = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|x| x.parse().ok())
+    {
+        return time;
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+/// `__DATE__` `__TIME__` 的值，格式为 `Mmm dd yyyy` 和 `hh:mm:ss`，按 UTC 计算
+pub fn date_time(time: u64) -> (String, String) {
+    let (days, secs) = (time / 86400, time % 86400);
+    // 公历日期，见 Howard Hinnant 的 civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+
+    let date = format!("{} {:2} {}", MONTHS[month as usize - 1], day, year);
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    );
+    (date, time)
+}
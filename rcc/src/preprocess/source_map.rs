@@ -0,0 +1,76 @@
+use rustc_hash::FxHashMap;
+
+///
+/// 源文件中的位置
+///
+/// # Members
+/// - `file`: 文件在 `SourceMap::files` 中的下标，0 是主文件
+/// - `line` `col`: 行和列，从 1 开始，列按字节计算
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLoc {
+    pub file: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+///
+/// 预处理输出中的一段，从 `out` 开始的字节依次对应 `loc` 开始的列，不跨行
+///
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    out: usize,
+    loc: SourceLoc,
+}
+
+///
+/// 预处理输出的字节偏移到源文件位置的映射
+///
+/// 原样输出的行每行一段；宏展开的行每个记号一段，宏展开产生的记号对应展开处的宏名。
+/// 位置使用 `#line` 和行标记指定的文件名和行号
+///
+/// # Members
+/// - `files`: 出现过的文件，下标 0 是主文件
+/// - `ids`: 文件名到下标
+/// - `segments`: 按 `out` 排列的段
+///
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    pub files: Vec<String>,
+    ids: FxHashMap<String, usize>,
+    segments: Vec<Segment>,
+}
+
+impl SourceMap {
+    /// 文件的下标，第一次出现时加入
+    pub fn file_id(&mut self, path: &str) -> usize {
+        if let Some(id) = self.ids.get(path) {
+            return *id;
+        }
+        self.files.push(path.to_owned());
+        self.ids.insert(path.to_owned(), self.files.len() - 1);
+        self.files.len() - 1
+    }
+
+    /// 输出偏移 `out` 开始的内容来自 `loc`，`out` 不能比已经记录的小
+    pub fn push(&mut self, out: usize, loc: SourceLoc) {
+        debug_assert!(self.segments.last().is_none_or(|x| x.out <= out));
+        if let Some(last) = self.segments.last_mut()
+            && last.out == out
+        {
+            last.loc = loc;
+            return;
+        }
+        self.segments.push(Segment { out, loc });
+    }
+
+    /// 输出偏移 `pos` 对应的源文件位置，在第一段之前的为 None
+    pub fn lookup(&self, pos: usize) -> Option<SourceLoc> {
+        let idx = self.segments.partition_point(|x| x.out <= pos);
+        let segment = self.segments.get(idx.checked_sub(1)?)?;
+        Some(SourceLoc {
+            col: segment.loc.col + pos - segment.out,
+            ..segment.loc
+        })
+    }
+}
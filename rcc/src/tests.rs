@@ -1 +1,10 @@
-mod test_lex;
\ No newline at end of file
+mod test_ast_dump;
+mod test_ast_graph;
+mod test_ast_json;
+mod test_lex;
+mod test_lower;
+mod test_parser;
+mod test_preprocess;
+mod test_printer;
+mod test_programs;
+mod test_visitor;
\ No newline at end of file
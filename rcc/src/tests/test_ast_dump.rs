@@ -0,0 +1,59 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::parser::ast::visitor::Visitor;
+use crate::writer::ast_dump::AstDumper;
+
+const CODE: &str = r#"struct P { int x; } p, *q;
+int f(int a) { return a; }
+static int arr[3] = {1, 2, 3}, n = 2;
+int main(void) {
+    unsigned long k = 1, m;
+    return p.x + q->x + f(arr[1]) + arr[n] + (int)k;
+}
+"#;
+
+fn dump(code: &str, filter: Option<&str>) -> String {
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (content, ctx, unit) = compiler.parse().expect("parse failed");
+    let mut dumper = AstDumper::new(&ctx, &content, filter);
+    dumper.walk_translation_unit(&unit);
+    dumper.finish()
+}
+
+#[test]
+fn test_ast_dump() {
+    let expect = include_str!("../../resources/golden/ast_dump.txt");
+    assert_eq!(dump(CODE, None), expect);
+}
+
+#[test]
+fn test_ast_dump_filter() {
+    let expect = include_str!("../../resources/golden/ast_dump_filter.txt");
+    assert_eq!(dump(CODE, Some("main")), expect);
+}
+
+/// 浮点常量的值保留小数点，很大或很小时用科学计数法
+#[test]
+fn test_ast_dump_float() {
+    let code = "double a = 1e-5, b = (double)3, c = 1e20; float d = 0.1f;";
+    let text = dump(code, None);
+    for value in ["= 1e-5\n", "= 3.0\n", "= 1e20\n", "'float' 0.1 = 0.1\n"] {
+        assert!(text.contains(value), "{}\n{}", value, text);
+    }
+}
+
+/// 位置是源文件中的行列：头文件之后的行号不变，宏展开对应宏名，`#line` 修改文件名和行号
+#[test]
+fn test_ast_dump_locations() {
+    let code = "#include <stddef.h>\n#define ADD(a, b) ((a) + (b))\nint main(void) {\n    size_t n = ADD(1,\n        2);\n    return n;\n}\n#line 100 \"other.c\"\nint g;\n";
+    let text = dump(code, None);
+    for loc in [
+        "FunctionDecl DeclKey(12v1) <3:1, 7:2> main",
+        "BinaryOperator ExprKey(3v1) <4:16, 4:17>",
+        "ReturnStmt StmtKey(2v1) <6:5, 6:14>",
+        "<other.c:100:1, other.c:100:6> g",
+        "<<rcc>/include/stddef.h:5:",
+    ] {
+        assert!(text.contains(loc), "{}\n{}", loc, text);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::parser::ast::visitor::Visitor;
+use crate::writer::ast_graph::AstGraph;
+
+fn dot(code: &str) -> String {
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    let mut graph = AstGraph::new(&ctx).with_decl_refs(true);
+    graph.walk_translation_unit(&unit);
+    graph.to_dot()
+}
+
+#[test]
+fn test_dot() {
+    let code = r#"int main(void) { char *s = "a\"b\\n"; return 1u + 2.5f + s[0] + '"'; }"#;
+    let dot = dot(code);
+    let labels: Vec<_> = dot
+        .lines()
+        .filter_map(|x| x.split_once("label = ").map(|x| x.1))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            r#""TranslationUnit" shape = box]"#,
+            r#""FuncDef main\lint (void)" shape = box]"#,
+            r#""Compound" shape = box]"#,
+            r#""DeclStmt" shape = box]"#,
+            r#""VarDef s\lchar *" shape = box]"#,
+            r#""Literal \"a\\\"b\\\\n\"\lchar [6]" shape = box]"#,
+            r#""Return" shape = box]"#,
+            r#""Binary +\lfloat" shape = box]"#,
+            r#""Binary +\lfloat" shape = box]"#,
+            r#""Binary +\lfloat" shape = box]"#,
+            r#""Literal 1u\lunsigned int" shape = box]"#,
+            r#""Literal 2.5f\lfloat" shape = box]"#,
+            r#""ArraySubscript\lchar" shape = box]"#,
+            r#""DeclRef s\lchar *" shape = box]"#,
+            r#""Literal 0\lint" shape = box]"#,
+            r#""Literal '\"'\lint" shape = box]"#,
+        ]
+    );
+    // 子节点边没有 label，引用边是虚线
+    assert!(dot.contains("    2 -> 3 [ ]\n"));
+    assert!(dot.contains("    13 -> 4 [ style = dashed, color = blue, constraint = false]\n"));
+}
@@ -0,0 +1,20 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::writer::ast_json::{to_json, AST_JSON_VERSION};
+
+fn json(code: &str) -> String {
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (content, ctx, unit) = compiler.parse().expect("parse failed");
+    to_json(&ctx, &content, &unit)
+}
+
+#[test]
+fn test_deterministic() {
+    let code = include_str!("../../resources/golden/ast_json.c");
+    let first = json(code);
+    assert_eq!(first, json(code));
+    assert_eq!(first, include_str!("../../resources/golden/ast_json.json"));
+
+    let value: serde_json::Value = serde_json::from_str(&first).unwrap();
+    assert_eq!(value["version"], AST_JSON_VERSION);
+}
@@ -0,0 +1,548 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::err::lower_error::LowerError;
+use crate::lower::lower_unit;
+use backend::interp::Interpreter;
+use backend::ir::debug::{DiType, DiTypeId};
+use backend::ir::verifier::verify_module;
+use backend::ir::{Module, RelocModel, Visibility};
+
+fn lower(code: &str) -> Result<Module, LowerError> {
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    lower_unit(&ctx, &unit)
+}
+
+/// 用解释器运行，返回 `main` 的返回值
+fn run(code: &str) -> i32 {
+    let module = lower(code).expect("lower failed");
+    let mut interp = Interpreter::new(&module).expect("bad module");
+    interp.run_main(&["a.out"]).expect("run failed")
+}
+
+#[test]
+fn test_control_flow() {
+    let code = r#"
+        struct point { int x, y; unsigned flag : 3; };
+        static int table[] = {1, 2, 3};
+        const char *name = "rcc";
+
+        int sum(struct point p, int n, ...) {
+            int total = 0;
+            for (int i = 0; i < n && i < 3; i++) {
+                switch (table[i]) {
+                case 1: total += p.x; break;
+                case 2: total += p.y;
+                default: continue;
+                }
+            }
+            p.flag = 5;
+        again:
+            if (total < 0 || !n) goto again;
+            return n > 0 ? total : p.flag;
+        }
+
+        int main(void) {
+            struct point p = {1, 2};
+            return sum(p, 3, 1.5f, name) + (name[1] == 'c') * 10;
+        }
+    "#;
+    assert_eq!(run(code), 13);
+}
+
+#[test]
+fn test_bit_field() {
+    let code = r#"
+        struct s { unsigned f : 3; int g : 4; unsigned h : 5; };
+
+        int main(void) {
+            struct s s = {0, 0, 31};
+            if ((s.f = 9) != 1) return 1;
+            s.f = 7;
+            if (++s.f != 0 || s.f != 0) return 2;
+            s.f = 7;
+            if (s.f++ != 7 || s.f != 0) return 3;
+            if ((s.f -= 1) != 7) return 4;
+            s.g = 7;
+            if ((s.g += 1) != -8) return 5;
+            if (--s.g != 7) return 6;
+            return s.h;
+        }
+    "#;
+    assert_eq!(run(code), 31);
+
+    // 只写入一个位域，另一个位域仍然未初始化
+    let code = "struct S { int a : 3; unsigned b : 5; };
+        int main(void) { struct S s; s.a = 3; return s.a; }";
+    assert_eq!(run(code), 3);
+    let code = "struct S { int a : 3; unsigned b : 5; };
+        int main(void) { struct S s; s.a = 3; return s.b; }";
+    let module = lower(code).expect("lower failed");
+    let mut interp = Interpreter::new(&module).expect("bad module");
+    assert!(interp.run_main(&["a.out"]).is_err());
+}
+
+#[test]
+fn test_incomplete_array() {
+    let code = r#"
+        char s[] = "hello";
+        int a[] = {1, 2, 3};
+        struct p { int x, y; } ps[] = {1, 2, 3, 4, 5};
+        int m[][2] = {{1, 2}, {3, 4}, {5}};
+        int n = sizeof(a) / sizeof(a[0]);
+
+        int main(void) {
+            char t[] = {"abc"};
+            const int c[] = {7, 8, 9, 10};
+            if (sizeof(s) != 6 || sizeof(t) != 4 || n != 3) return 1;
+            if (sizeof(ps) != 3 * sizeof(struct p) || sizeof(m) != 6 * sizeof(int)) return 2;
+            if (sizeof(c) != 4 * sizeof(int)) return 3;
+            return s[4] + a[2] + ps[2].x + m[2][0] + t[2] + c[3];
+        }
+    "#;
+    assert_eq!(run(code), 'o' as i32 + 3 + 5 + 5 + 'c' as i32 + 10);
+
+    let parses = |code: &str| {
+        CCompiler::new(code.to_owned(), CompilerOptions::default())
+            .parse()
+            .is_ok()
+    };
+    assert!(!parses("int a[2] = {1, 2, 3};"));
+    assert!(!parses("int b[] = 1;"));
+}
+
+#[test]
+fn test_designated_initializer() {
+    let code = r#"
+        struct P { int x, y; };
+        struct Q { struct P p; int z; union { int u; char c; }; int w : 4; };
+        int a[] = {[3] = 7, [1] = 2, 9};
+        struct P g = {.y = 5};
+        struct Q q = {.p.y = 1, 2, .c = 'a', .w = 3, .z = 4};
+        struct P ps[] = {[2].y = 6, {1}, [0] = {.x = 8}};
+        int m[3][2] = {[1] = {1, 2}, [2][1] = 5, [0][0] = 1, [0][0] = 4};
+        union U { int i; char c[4]; } u = {.c = {1, 2}};
+
+        int f(int v) {
+            struct P l = {.y = v, .x = v + 1};
+            int b[4] = {[2] = v, [1] = v * 2, [2] = v * 3};
+            return l.x + l.y + b[1] + b[2];
+        }
+
+        int main(void) {
+            if (sizeof(a) != 4 * sizeof(int) || a[0] || a[1] != 2 || a[2] != 9 || a[3] != 7)
+                return 1;
+            if (g.x != 0 || g.y != 5) return 2;
+            if (q.p.x != 0 || q.p.y != 1 || q.z != 4 || q.c != 'a' || q.w != 3) return 3;
+            if (sizeof(ps) != 4 * sizeof(struct P) || ps[0].x != 8 || ps[2].y != 6 || ps[3].x != 1)
+                return 4;
+            if (m[1][1] != 2 || m[2][1] != 5 || m[0][0] != 4 || m[0][1] != 0) return 5;
+            if (u.c[1] != 2) return 6;
+            return f(2);
+        }
+    "#;
+    assert_eq!(run(code), 15);
+
+    let parses = |code: &str| {
+        CCompiler::new(code.to_owned(), CompilerOptions::default())
+            .parse()
+            .is_ok()
+    };
+    assert!(!parses("int x = {.a = 1};"));
+    assert!(!parses("struct P { int x; } p = {.z = 1};"));
+    assert!(!parses("struct P { int x; } p = {[0] = 1};"));
+    assert!(!parses("int a[2] = {.x = 1};"));
+    assert!(!parses("int a[2] = {[2] = 1};"));
+    assert!(!parses("int a[2] = {[-1] = 1};"));
+    assert!(!parses("int n; int a[2] = {[n] = 1};"));
+    assert!(!parses("int a[2] = {[0].x = 1};"));
+}
+
+#[test]
+fn test_compound_literal() {
+    let code = r#"
+        struct P { int x, y; };
+        int *gp = (int[]){4, 5, 6};
+        struct P *gs = &(struct P){.y = 7};
+
+        int sum(int *a, int n) {
+            int s = 0;
+            for (int i = 0; i < n; i++) s += a[i];
+            return s;
+        }
+
+        int main(void) {
+            int r = (int[]){1, 2, 3}[1];
+            r += sizeof (int[]){1, 2, 3} / sizeof(int);
+            struct P p = (struct P){.x = 10, 20};
+            r += p.x + p.y;
+            r += sum((int[]){r, 1}, 2);
+            for (int i = 0; i < 3; i++) {
+                int *q = (int[]){0};
+                r += *q;
+                *q = 100;
+            }
+            (struct P){1, 2}.x = 5;
+            r += gp[2] + gs->y;
+            gp[2] = 1;
+            return r + gp[2];
+        }
+    "#;
+    assert_eq!(run(code), 85);
+
+    let parses = |code: &str| {
+        CCompiler::new(code.to_owned(), CompilerOptions::default())
+            .parse()
+            .is_ok()
+    };
+    assert!(!parses("int f(void) { return (int[2]){1, 2, 3}[0]; }"));
+    assert!(!parses("void f(void) { (void){0}; }"));
+    assert!(!parses("struct S; void f(void) { (struct S){0}; }"));
+    assert!(matches!(
+        lower("int x; int *p = (int[]){x};"),
+        Err(LowerError::NotConstant { .. })
+    ));
+}
+
+#[test]
+fn test_errors() {
+    let code = "int f(void) { goto missing; return 0; }";
+    assert!(matches!(
+        lower(code),
+        Err(LowerError::UndefinedLabel { .. })
+    ));
+
+    let code = "int f(int x) { switch (x) { case 1: case 1: break; } return 0; }";
+    assert!(matches!(
+        lower(code),
+        Err(LowerError::DuplicateCase { value: 1, .. })
+    ));
+
+    let code = "int x; int *p = &x + 1; int y = x;";
+    assert!(matches!(lower(code), Err(LowerError::NotConstant { .. })));
+}
+
+#[test]
+fn test_debug_info() {
+    let code = r#"typedef struct node node_t;
+struct node { int value; struct node *next; unsigned flag : 3; };
+enum color { RED, GREEN = 5 };
+node_t origin;
+
+int main(int argc, char **argv) {
+    const node_t p = {7};
+    enum color c = GREEN;
+    return p.value + c;
+}
+"#;
+    let options = CompilerOptions {
+        input: Some("test.c".to_owned()),
+        debug: Some(4),
+        ..Default::default()
+    };
+    let compiler = CCompiler::new(code.to_owned(), options);
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    let module = compiler.lower(&ctx, &unit).expect("lower failed");
+    let debug = module.debug.as_ref().expect("no debug info");
+    assert_eq!((debug.file.as_str(), debug.version), ("test.c", 4));
+
+    // struct 只转换一次，成员指针指回自身，const 包在外面
+    let records: Vec<_> = debug
+        .types
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| matches!(x, DiType::Record { .. }))
+        .collect();
+    assert_eq!(records.len(), 1);
+    let (node, DiType::Record { size, members, .. }) = records[0] else {
+        unreachable!()
+    };
+    assert_eq!(*size, Some(24));
+    assert_eq!(members[1].offset, 8);
+    assert_eq!(members[2].bit_field, Some((0, 3)));
+    assert_eq!(
+        debug.types[members[1].ty.0 as usize],
+        DiType::Pointer {
+            pointee: Some(DiTypeId(node as u32)),
+            size: 8
+        }
+    );
+    assert!(debug.types.iter().any(|x| matches!(
+        x,
+        DiType::Typedef { name, line: 1, .. } if name == "node_t"
+    )));
+    assert!(debug.types.iter().any(|x| matches!(
+        x,
+        DiType::Enum { enumerators, .. } if enumerators[1] == ("GREEN".to_owned(), 5)
+    )));
+    assert_eq!(debug.globals[0].name, "origin");
+    assert_eq!(debug.globals[0].line, 4);
+
+    let main = &module.funcs[module.func_by_name("main").unwrap()];
+    let sub = main.debug.as_ref().expect("no subprogram");
+    assert_eq!(sub.line, 6);
+    let vars: Vec<_> = sub
+        .vars
+        .iter()
+        .map(|x| (x.name.as_str(), x.arg, x.line))
+        .collect();
+    assert_eq!(
+        vars,
+        [
+            ("argc", Some(1), 6),
+            ("argv", Some(2), 6),
+            ("p", None, 7),
+            ("c", None, 8)
+        ]
+    );
+    assert!(matches!(
+        debug.types[sub.vars[2].ty.0 as usize],
+        DiType::Const(Some(_))
+    ));
+    let mut lines: Vec<_> = main.locs.values().map(|x| x.line).collect();
+    lines.dedup();
+    assert_eq!(lines.first(), Some(&6));
+    assert!(lines.contains(&9));
+}
+
+/// 头文件、宏展开和 `#line` 之后的位置是源文件中的文件和行
+#[test]
+fn test_debug_locations() {
+    let code = "#include <stddef.h>\n#define ADD(a, b) ((a) + (b))\nint main(void) {\n    size_t n = ADD(1,\n        2);\n    return n;\n}\n#line 100 \"other.c\"\nint g;\n";
+    let options = CompilerOptions {
+        input: Some("test.c".to_owned()),
+        debug: Some(5),
+        ..Default::default()
+    };
+    let compiler = CCompiler::new(code.to_owned(), options);
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    let module = compiler.lower(&ctx, &unit).expect("lower failed");
+    let debug = module.debug.as_ref().expect("no debug info");
+    let file = |name: &str| debug.files.iter().position(|x| x.ends_with(name)).unwrap() as u32 + 2;
+
+    let main = &module.funcs[module.func_by_name("main").unwrap()];
+    let sub = main.debug.as_ref().expect("no subprogram");
+    assert_eq!((sub.file, sub.line), (1, 3));
+    let mut lines: Vec<_> = main.locs.values().map(|x| (x.file, x.line)).collect();
+    lines.sort();
+    lines.dedup();
+    assert_eq!(lines, [(1, 4), (1, 6)]);
+    assert_eq!(
+        (debug.globals[0].file, debug.globals[0].line),
+        (file("other.c"), 100)
+    );
+    assert!(debug.types.iter().any(|x| matches!(
+        x,
+        DiType::Typedef { name, file: f, .. } if name == "size_t" && *f == file("stddef.h")
+    )));
+}
+
+#[test]
+fn test_visibility() {
+    let code = r#"
+        __attribute__((visibility("hidden"))) int counter;
+        int shared_value = 1;
+        __attribute__((noinline, visibility("protected"))) int get(void) { return counter; }
+        int put(int x);
+        __attribute__((visibility("hidden"))) int put(int x) { return counter = x; }
+    "#;
+    let args = ["-shared", "-o", "libplugin.so"].map(String::from);
+    let options = CompilerOptions::parse(args).expect("bad options");
+    let compiler = CCompiler::new(code.to_owned(), options);
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    let module = compiler.lower(&ctx, &unit).expect("lower failed");
+    assert_eq!(module.reloc_model, RelocModel::Pic);
+
+    let global = |name| module.globals[module.global_by_name(name).unwrap()].visibility;
+    let func = |name| &module.funcs[module.func_by_name(name).unwrap()];
+    assert_eq!(global("counter"), Visibility::Hidden);
+    assert_eq!(global("shared_value"), Visibility::Default);
+    assert_eq!(func("get").visibility, Visibility::Protected);
+    assert_eq!(func("put").visibility, Visibility::Hidden);
+}
+
+#[test]
+fn test_thread_local() {
+    let code = r#"
+        _Thread_local int counter = 1;
+        extern __thread int shared;
+        int next(void) {
+            static _Thread_local int calls;
+            calls++;
+            return counter + shared + calls;
+        }
+    "#;
+    let module = lower(code).expect("lower failed");
+    let global = |name| &module.globals[module.global_by_name(name).unwrap()];
+    assert!(global("counter").thread_local);
+    assert!(global("shared").thread_local);
+    assert!(
+        module
+            .globals
+            .values()
+            .any(|x| x.name.contains("calls") && x.thread_local)
+    );
+
+    assert!(global("shared").is_declaration());
+
+    let code = r#"
+        _Thread_local int counter = 1;
+        int next(void) {
+            static _Thread_local int calls;
+            calls++;
+            return counter + calls;
+        }
+        int main(void) {
+            next();
+            next();
+            return next();
+        }
+    "#;
+    assert_eq!(run(code), 4);
+
+    let code = "void f(void) { _Thread_local int x; }";
+    assert!(
+        CCompiler::new(code.to_owned(), CompilerOptions::default())
+            .parse()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_alignas() {
+    let code = r#"
+        struct S { char c; _Alignas(16) int x; _Alignas(double) char d; };
+        _Alignas(64) char buf[3];
+        _Alignas(0) _Alignas(32) _Alignas(8) int g = 5;
+        int next(void) {
+            static _Alignas(128) int calls;
+            _Alignas(16) char local[5];
+            local[0] = 1;
+            return ++calls + local[0];
+        }
+        int main(void) {
+            struct S s;
+            unsigned long base = (unsigned long)&s;
+            return _Alignof(struct S) + sizeof(struct S) + ((unsigned long)&s.x - base)
+                + ((unsigned long)&s.d - base) + next();
+        }
+    "#;
+    let module = lower(code).expect("lower failed");
+    let global = |name| &module.globals[module.global_by_name(name).unwrap()];
+    assert_eq!(global("buf").align, 64);
+    assert_eq!(global("g").align, 32);
+    assert!(module.globals.values().any(|x| x.name.contains("calls") && x.align == 128));
+    assert_eq!(run(code), 16 + 32 + 16 + 24 + 2);
+
+    for code in [
+        "typedef _Alignas(8) int T;",
+        "_Alignas(8) int f(void);",
+        "void f(_Alignas(8) int x);",
+        "struct A { _Alignas(8) int x : 3; };",
+        "void f(void) { register _Alignas(8) int x; }",
+        "_Alignas(3) int x;",
+        "_Alignas(1) int x;",
+        "int n; _Alignas(n) int x;",
+        "_Alignas(void (void)) int x;",
+        "unsigned long a = _Alignof(struct Q);",
+        "void f(void) { _Alignas(32) int x; }",
+    ] {
+        assert!(
+            CCompiler::new(code.to_owned(), CompilerOptions::default())
+                .parse()
+                .is_err(),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_builtins() {
+    let code = r#"
+        struct s { int a; char b[4]; struct { long c; }; };
+        int folded[] = {
+            __builtin_offsetof(struct s, b[2]),
+            __builtin_offsetof(struct s, c),
+            __builtin_types_compatible_p(const int, int),
+            __builtin_types_compatible_p(int, long),
+            __builtin_constant_p(3 * 4),
+            __builtin_popcount(0xff),
+            __builtin_clzll(1),
+            __builtin_ctz(8),
+        };
+
+        int sum(int n, ...) {
+            __builtin_va_list ap, copy;
+            __builtin_va_start(ap, n);
+            __builtin_va_copy(copy, ap);
+            int total = 0;
+            for (int i = 0; i < n; i++) total += __builtin_va_arg(ap, int);
+            total += __builtin_va_arg(copy, int) * 100;
+            __builtin_va_end(copy);
+            __builtin_va_end(ap);
+            return total;
+        }
+
+        int main(void) {
+            int expect[] = {6, 8, 1, 0, 1, 8, 63, 3};
+            for (int i = 0; i < 8; i++)
+                if (folded[i] != expect[i]) return i + 1;
+            unsigned x = 0xf0;
+            char src[4] = "abc", dst[4];
+            __builtin_memcpy(dst, src, sizeof(src));
+            if (__builtin_expect(dst[1] != 'b', 0)) __builtin_unreachable();
+            return sum(3, 1, 2, 3) + __builtin_popcount(x) + __builtin_clz(x) + __builtin_ctz(x);
+        }
+    "#;
+    assert_eq!(run(code), 138);
+
+    let code = "int f(int n) { __builtin_va_list ap; __builtin_va_start(ap, n); return 0; }";
+    assert!(matches!(
+        lower(code),
+        Err(LowerError::VaStartOutside { .. })
+    ));
+
+    let code = "int f(int n, ...) { int ap; __builtin_va_start(ap, n); return 0; }";
+    assert!(
+        CCompiler::new(code.to_owned(), CompilerOptions::default())
+            .parse()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_target_layout() {
+    let code = r#"
+        struct s { char c; long l; void *p; };
+        char l[sizeof(long)];
+        char p[sizeof(void *)];
+        char s[sizeof(struct s)];
+        char off[__builtin_offsetof(struct s, p)];
+        char big[sizeof(2147483648)];
+
+        long diff(int *a, int *b) { return a - b; }
+        unsigned long addr(int *a, int i) { return (unsigned long)(a + i) + (unsigned long)--a; }
+        int sum(int *a, int n) {
+            int total = 0;
+            for (int i = 0; i < n; i++) total += a[i] * 4;
+            return total;
+        }
+    "#;
+    let sizes = |target: &str| {
+        let options = CompilerOptions {
+            target: Some(target.to_owned()),
+            opt_level: 2,
+            ..Default::default()
+        };
+        let compiler = CCompiler::new(code.to_owned(), options);
+        let (_, ctx, unit) = compiler.parse().expect("parse failed");
+        let module = compiler.lower(&ctx, &unit).expect("lower failed");
+        verify_module(&module).expect("bad module");
+        let size = |name: &str| module.globals[module.global_by_name(name).unwrap()].size;
+        let sizes = ["l", "p", "s", "off", "big"].map(size);
+        (module.ptr_bytes, sizes, Interpreter::new(&module).is_ok())
+    };
+    assert_eq!(sizes("x86_64-linux-gnu"), (8, [8, 8, 24, 16, 8], true));
+    assert_eq!(sizes("wasm32"), (4, [4, 4, 12, 8, 8], false));
+}
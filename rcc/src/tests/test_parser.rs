@@ -0,0 +1,63 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::lower::lower_unit;
+use backend::interp::Interpreter;
+
+fn parses(code: &str) -> bool {
+    CCompiler::new(code.to_owned(), CompilerOptions::default())
+        .parse()
+        .is_ok()
+}
+
+#[test]
+fn test_abstract_declarator() {
+    assert!(parses("int f(int );"));
+    assert!(parses("int f(int , int);"));
+    assert!(parses("int f(int *, char [], void (*)(int ));"));
+
+    assert!(parses(
+        "int main(void) { return sizeof(int) + (int)1.5 + (char *)0 != 0; }"
+    ));
+    // 定义中省略参数名是错误，但不能 panic
+    assert!(!parses("int f(int ) { return 0; }"));
+}
+
+#[test]
+fn test_restrict() {
+    let code = r#"
+        typedef int *ip;
+        int *restrict p = 0;
+        int f(int *restrict a, const ip restrict b) { return *a + *b; }
+        int main(void) {
+            int x = 1;
+            int *restrict q = &x;
+            return f(q, q) + (p == 0);
+        }
+    "#;
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    let module = lower_unit(&ctx, &unit).expect("lower failed");
+    let mut interp = Interpreter::new(&module).expect("bad module");
+    assert_eq!(interp.run_main(&["a.out"]).expect("run failed"), 3);
+
+    assert!(!parses("int restrict x;"));
+    assert!(!parses("struct s { int a; } restrict s;"));
+    assert!(!parses("typedef int a[2]; restrict a x;"));
+    assert!(!parses("void (*restrict g)(void);"));
+}
+
+#[test]
+fn test_prototype() {
+    // `f()` 不是原型，不检查参数个数
+    assert!(parses("int f(); int g(void) { return f(1, 2); }"));
+    assert!(parses("int f() { return 0; } int g(void) { return f(); }"));
+
+    assert!(!parses(
+        "int f(void) { return 0; } int g(void) { return f(1, 2); }"
+    ));
+    assert!(!parses("int (*p)(void); int g(void) { return p(1); }"));
+    assert!(!parses("int f(int a); int g(void) { return f(); }"));
+    assert!(parses(
+        "int f(int a, ...); int g(void) { return f(1, 2, 3); }"
+    ));
+}
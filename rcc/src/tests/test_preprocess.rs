@@ -0,0 +1,359 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::err::lower_error::LowerError;
+use crate::err::preprocess_error::PreprocessErrorKind;
+use crate::lower::lower_unit;
+use crate::preprocess::Preprocessor;
+use crate::preprocess::headers::{BUILTIN_HEADERS, builtin_header};
+use crate::preprocess::predefined::date_time;
+use backend::interp::Interpreter;
+use backend::target::TargetInfo;
+
+fn preprocess(code: &str) -> String {
+    let mut pp = Preprocessor::new(TargetInfo::x86_64_linux());
+    pp.preprocess("t.c", code).expect("preprocess failed")
+}
+
+fn error(code: &str) -> PreprocessErrorKind {
+    let mut pp = Preprocessor::new(TargetInfo::x86_64_linux());
+    pp.preprocess("t.c", code).expect_err("should fail").kind
+}
+
+/// 在每个目标上编译 `code`，每个全局数组的大小是一个条件，要求都成立（大小为 1）
+fn assert_all_targets(code: &str) {
+    for target in ["x86_64", "riscv64", "wasm32"] {
+        let args = ["-target", target].map(String::from);
+        let options = CompilerOptions::parse(args).expect("bad options");
+        let compiler = CCompiler::new(code.to_owned(), options);
+        let (_, ctx, unit) = compiler.parse().expect("parse failed");
+        let module = lower_unit(&ctx, &unit).expect("lower failed");
+        for id in module.global_ids() {
+            let global = &module.globals[id];
+            assert_eq!(global.size, 1, "{} on {}", global.name, target);
+        }
+    }
+}
+
+/// 编译后用解释器运行，返回 `main` 的返回值
+fn run(code: &str) -> i32 {
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    let module = lower_unit(&ctx, &unit).expect("lower failed");
+    let mut interp = Interpreter::new(&module).expect("bad module");
+    interp.run_main(&["a.out"]).expect("run failed")
+}
+
+#[test]
+fn test_macro() {
+    let code =
+        "#define N 10\nint a[N];\n#define F(x, y) ((x) * (y))\nint b = F(1 + 2,\n  N);\nint c;\n";
+    assert_eq!(
+        preprocess(code),
+        "\nint a[10];\n\nint b = ((1 + 2) * (10));\n\nint c;\n"
+    );
+
+    let code =
+        "#define STR(x) #x\n#define XSTR(x) STR(x)\n#define N 42\nSTR(a  \"b\\n\") XSTR(N)\n";
+    assert_eq!(preprocess(code), "\n\n\n\"a \\\"b\\\\n\\\"\" \"42\"\n");
+
+    let code = "#define CAT(a, b) a ## b\nCAT(x, 1) CAT(, y) CAT(z,)\n";
+    assert_eq!(preprocess(code), "\nx1 y z\n");
+
+    // 展开过的宏名不再展开
+    let code = "#define f(x) x + f(x)\nf(1)\n#define g h\n#define h g\ng\n";
+    assert_eq!(preprocess(code), "\n1 + f(1)\n\n\ng\n");
+
+    let code = "#define V(fmt, ...) p(fmt, ## __VA_ARGS__)\nV(\"a\") V(\"a\", 1, 2)\n";
+    assert_eq!(preprocess(code), "\np(\"a\") p(\"a\",1, 2)\n");
+}
+
+#[test]
+fn test_conditional() {
+    let code =
+        "#define A 1\n#if A == 2\na2\n#elif A == 1\na1\n#elif 1 / 0\nbad\n#else\nelse\n#endif\n";
+    assert_eq!(preprocess(code), "\n\n\n\na1\n\n\n\n\n\n");
+
+    let code = "#if -1 < 0u\nbad\n#elif defined(A) || !defined A\nok\n#endif\n";
+    assert_eq!(preprocess(code).trim(), "ok");
+
+    assert!(matches!(
+        error("#if 1\n"),
+        PreprocessErrorKind::UnterminatedConditional
+    ));
+    assert!(matches!(
+        error("#else\n"),
+        PreprocessErrorKind::UnmatchedDirective("else")
+    ));
+    assert!(matches!(
+        error("#define F(a, b) a\nF(1)\n"),
+        PreprocessErrorKind::ArgCount { .. }
+    ));
+    assert!(matches!(
+        error("#include \"missing.h\"\n"),
+        PreprocessErrorKind::IncludeNotFound(_)
+    ));
+    assert!(matches!(
+        error("#error bad thing\n"),
+        PreprocessErrorKind::ErrorDirective(x) if x == "bad thing"
+    ));
+}
+
+#[test]
+fn test_headers() {
+    for target in [
+        TargetInfo::x86_64_linux(),
+        TargetInfo::riscv64_linux(),
+        TargetInfo::wasm32(),
+    ] {
+        for name in BUILTIN_HEADERS {
+            let text = builtin_header(name, &target).expect("missing header");
+            let mut pp = Preprocessor::new(target.clone());
+            pp.preprocess(name, &text).expect("preprocess failed");
+        }
+    }
+
+    let code =
+        "#include <stdint.h>\n#include <stdint.h>\nSIZE_MAX\n#include <limits.h>\nLONG_MAX\n";
+    let mut pp = Preprocessor::new(TargetInfo::wasm32());
+    let text = pp.preprocess("t.c", code).expect("preprocess failed");
+    assert!(text.contains("4294967295U"));
+    assert!(text.contains("2147483647L"));
+    let text = preprocess(code);
+    assert!(text.contains("18446744073709551615UL"));
+    assert!(text.contains("9223372036854775807L"));
+
+    // 头文件和前端的类型布局一致
+    let code = r#"
+        #include <float.h>
+        #include <limits.h>
+        #include <stddef.h>
+        #include <stdint.h>
+        char c0[sizeof(size_t) == sizeof(void *) && sizeof(ptrdiff_t) == sizeof(void *)];
+        char c1[sizeof(intptr_t) == sizeof(void *) && (uintptr_t)-1 == UINTPTR_MAX];
+        char c2[(size_t)-1 == SIZE_MAX && (unsigned long)-1 == ULONG_MAX];
+        char c3[LONG_MAX == (long)(ULONG_MAX >> 1) && LONG_MIN < 0];
+        char c4[((char)-1 < 0) == (CHAR_MIN < 0) && (char)CHAR_MAX == CHAR_MAX];
+        #if defined(__x86_64__)
+        char c5[sizeof(long double) == 16 && _Alignof(long double) == 16 && LDBL_MANT_DIG == 64];
+        #elif defined(__riscv)
+        char c5[sizeof(long double) == 16 && _Alignof(long double) == 16 && LDBL_MANT_DIG == 113];
+        #else
+        char c5[sizeof(long double) == 8 && LDBL_MANT_DIG == DBL_MANT_DIG];
+        #endif
+        char c8[sizeof(long double) == __SIZEOF_LONG_DOUBLE__
+            && sizeof(struct { char c; long double x; }) == 2 * sizeof(long double)
+            && _Alignof(max_align_t) == _Alignof(long double)];
+        char c6[sizeof(int64_t) == 8 && (uint64_t)-1 == UINT64_MAX];
+        char c7[sizeof(_Bool) == 1 && (_Bool)256 == 1 && (_Bool)0.5 == 1 && (_Bool)0.0 == 0];
+    "#;
+    assert_all_targets(code);
+
+    // 使用内置头文件的程序可以编译运行
+    let code = r#"
+        #include <stdbool.h>
+        struct F { bool a : 1; _Bool b; };
+        static bool g = 5;
+        bool odd(int x) { return x & 1; }
+        int main(void) {
+            bool b = 256;
+            _Bool c = 0.5;
+            struct F f = { 2, 3 };
+            b++;
+            c--;
+            return b + c * 2 + f.a * 4 + f.b * 8 + g * 16 + odd(7) * 32 + (true + !false) * 64
+                + __bool_true_false_are_defined * 256;
+        }
+    "#;
+    assert_eq!(run(code), 1 + 4 + 8 + 16 + 32 + 128 + 256);
+
+    let code = r#"
+        #include <stdalign.h>
+        struct S { char c; alignas(16) int x; };
+        alignas(32) static char buf[2];
+        int main(void) {
+            alignas(long long) char c = 1;
+            return alignof(struct S) + sizeof(struct S) + (int)((unsigned long)buf % 32)
+                + (int)((unsigned long)&c % alignof(long long)) + c
+                + __alignas_is_defined + __alignof_is_defined;
+        }
+    "#;
+    assert_eq!(run(code), 16 + 32 + 1 + 2);
+
+    let code = r#"
+        #include <stdlib.h>
+        #include <stdnoreturn.h>
+        noreturn static void quit(int code) { exit(code); }
+        static inline _Noreturn void fail(void) { abort(); }
+        __attribute__((noreturn)) _Noreturn void stop(int code);
+        void stop(int code) { quit(code); }
+        int main(void) {
+            if (0)
+                fail();
+            stop(42);
+        }
+    "#;
+    assert_eq!(run(code), 42);
+
+    // x86-64 上 `long double` 只能声明和取地址，不能按 `double` 生成它的值
+    let code = r#"
+        long double strtold(const char *s, char **end);
+        struct L { long double x; };
+        long double *pick(struct L *l) { return &l->x; }
+        int main(void) {
+            struct L l, m;
+            m = l;
+            return (sizeof(m) == sizeof(long double)) + (pick(&l) == &l.x);
+        }
+    "#;
+    assert_eq!(run(code), 2);
+    for code in [
+        "long double x = 1;",
+        "long double f(void); int g(void) { return f() > 0; }",
+        "void h(long double x) {}",
+        "double q(long double *p) { return *p; }",
+        "void k(long double); void m(void) { k(1.0); }",
+    ] {
+        let args = ["-target", "x86_64"].map(String::from);
+        let options = CompilerOptions::parse(args).expect("bad options");
+        let compiler = CCompiler::new(code.to_owned(), options);
+        let (_, ctx, unit) = compiler.parse().expect("parse failed");
+        let err = lower_unit(&ctx, &unit).expect_err(code);
+        assert!(matches!(err, LowerError::Unsupported { .. }), "{}", code);
+    }
+
+    for code in [
+        "_Noreturn int x;",
+        "typedef _Noreturn void F(void);",
+        "void f(_Noreturn int x);",
+        "struct A { _Noreturn int x; };",
+        "unsigned long n = sizeof(_Noreturn int);",
+    ] {
+        let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+        assert!(compiler.parse().is_err(), "{}", code);
+    }
+}
+
+/// `#line` 和行标记修改之后的行号和文件名，输出位置映射回源文件
+#[test]
+fn test_line_directive() {
+    let code = "#line 10\n__LINE__\n#define L 20 \"b.c\"\n#line L\n__LINE__ __FILE__\n# 5 \"c.h\" 1\n__LINE__ __FILE__\n";
+    assert_eq!(preprocess(code), "\n10\n\n\n20 \"b.c\"\n\n5 \"c.h\"\n");
+
+    let code = "#include <stddef.h>\n#define N 42\nint a =\n  N;\n#line 100 \"other.c\"\nint b;\n";
+    let mut pp = Preprocessor::new(TargetInfo::x86_64_linux());
+    let text = pp.preprocess("t.c", code).expect("preprocess failed");
+    let map = pp.take_source_map();
+    let loc = |needle: &str| map.lookup(text.find(needle).unwrap()).unwrap();
+    assert_eq!(
+        (loc("int a").file, loc("int a").line, loc("int a").col),
+        (0, 3, 1)
+    );
+    // 宏展开的记号对应展开处的宏名
+    assert_eq!((loc("42").line, loc("42").col), (4, 3));
+    let b = loc("int b");
+    assert_eq!((map.files[b.file].as_str(), b.line), ("other.c", 100));
+    let size_t = loc("size_t");
+    assert!(map.files[size_t.file].ends_with("stddef.h"));
+
+    assert!(matches!(
+        error("#line x\n"),
+        PreprocessErrorKind::LineNumber
+    ));
+    assert!(matches!(
+        error("#line 0x10\n"),
+        PreprocessErrorKind::LineNumber
+    ));
+    assert!(matches!(
+        error("#line 5 x\n"),
+        PreprocessErrorKind::LineFile
+    ));
+    assert!(matches!(
+        error("#line 5 \"a.c\" 1\n"),
+        PreprocessErrorKind::ExtraTokens("line")
+    ));
+}
+
+#[test]
+fn test_system_dirs() {
+    let dirs = Preprocessor::new(TargetInfo::x86_64_linux()).system_dirs;
+    let pos = |name: &str| dirs.iter().position(|d| d.as_os_str() == name);
+    // 多架构目录必须排在 `/usr/include` 之前，否则 glibc 找不到 bits/*.h
+    assert!(pos("/usr/include/x86_64-linux-gnu") < pos("/usr/include"));
+    assert!(pos("/usr/include/x86_64-linux-gnu").is_some());
+    let dirs = Preprocessor::new(TargetInfo::riscv64_linux()).system_dirs;
+    assert!(dirs.iter().any(|d| d.ends_with("riscv64-linux-gnu")));
+    let dirs = Preprocessor::new(TargetInfo::wasm32()).system_dirs;
+    assert_eq!(dirs.len(), 2);
+}
+
+#[test]
+fn test_predefined() {
+    let mut pp = Preprocessor::new(TargetInfo::riscv64_linux());
+    pp.predefine(Some(201112)).expect("predefine failed");
+    pp.define("N=3").expect("define failed");
+    pp.define("DEBUG").expect("define failed");
+    pp.undef("__linux__").expect("undef failed");
+    let code = "__STDC_VERSION__ N DEBUG\n#if defined(__riscv) && __SIZEOF_LONG__ == 8 && !defined __linux__\n__FILE__ __LINE__\n#endif\n__COUNTER__ __COUNTER__\n";
+    assert_eq!(
+        pp.preprocess("dir/t.c", code).expect("preprocess failed"),
+        "201112L 3 1\n\n\"dir/t.c\" 3\n\n0 1\n"
+    );
+
+    let dump = pp.dump_macros();
+    assert!(dump.contains("#define N 3\n"));
+    assert!(dump.contains("#define __riscv_xlen 64\n"));
+    assert!(!dump.contains("__CHAR_UNSIGNED__"));
+    assert!(!dump.contains("__linux__"));
+    assert!(!dump.contains("__LINE__"));
+
+    assert_eq!(
+        date_time(951786061),
+        ("Feb 29 2000".to_owned(), "01:01:01".to_owned())
+    );
+    assert_eq!(
+        date_time(0),
+        ("Jan  1 1970".to_owned(), "00:00:00".to_owned())
+    );
+
+    let args = [
+        "-std=c89",
+        "-DF(x)=x+1",
+        "-U",
+        "__STDC_HOSTED__",
+        "-E",
+        "-target",
+        "wasm32",
+    ];
+    let options = CompilerOptions::parse(args.map(String::from)).expect("bad options");
+    let compiler = CCompiler::new(
+        "__STDC_VERSION__ __STDC_HOSTED__ F(2) __ILP32__\n".to_owned(),
+        options,
+    );
+    assert_eq!(
+        compiler.source().expect("preprocess failed"),
+        "__STDC_VERSION__ __STDC_HOSTED__ 2+1 1\n"
+    );
+
+    // 预定义宏和前端的类型布局一致
+    let code = r#"
+        char c0[__SIZEOF_SHORT__ == sizeof(short) && __SIZEOF_INT__ == sizeof(int)];
+        char c1[__SIZEOF_LONG__ == sizeof(long) && __SIZEOF_LONG_LONG__ == sizeof(long long)];
+        char c2[__SIZEOF_POINTER__ == sizeof(void *) && __SIZEOF_SIZE_T__ == sizeof(sizeof(0))];
+        char c3[__SIZEOF_PTRDIFF_T__ == sizeof((char *)0 - (char *)0)];
+        char c4[__SIZEOF_FLOAT__ == sizeof(float) && __SIZEOF_DOUBLE__ == sizeof(double)];
+        char c5[__SIZEOF_LONG_DOUBLE__ == sizeof(long double)];
+        #if defined(_LP64) == defined(_ILP32)
+        char c6[0];
+        #elif defined(_LP64)
+        char c6[sizeof(long) == 8 && sizeof(void *) == 8];
+        #else
+        char c6[sizeof(long) == 4 && sizeof(void *) == 4];
+        #endif
+        #ifdef __CHAR_UNSIGNED__
+        char c7[(char)-1 > 0];
+        #else
+        char c7[(char)-1 < 0];
+        #endif
+    "#;
+    assert_all_targets(code);
+}
@@ -0,0 +1,89 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::writer::ast_json::to_json;
+use crate::writer::c_printer::{CPrinter, ParenStyle};
+use serde_json::Value;
+
+fn print(code: &str, parens: ParenStyle) -> String {
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    CPrinter::new(&ctx, parens).print_unit(&unit)
+}
+
+/// 去掉 span 后的 AST，打印前后源码位置必然不同
+fn ast(code: &str) -> Value {
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (content, ctx, unit) = compiler.parse().expect("parse failed");
+    let mut value = serde_json::from_str(&to_json(&ctx, &content, &unit)).unwrap();
+    strip_span(&mut value);
+    value
+}
+
+fn strip_span(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.remove("span");
+            map.values_mut().for_each(strip_span);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_span),
+        _ => {}
+    }
+}
+
+/// parse(print(parse(src))) 和 parse(src) 的 AST 相同
+fn round_trip(code: &str) {
+    let expected = ast(code);
+    for parens in [ParenStyle::Minimal, ParenStyle::Full] {
+        let printed = print(code, parens);
+        assert_eq!(expected, ast(&printed), "{}", printed);
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    round_trip(include_str!("../../resources/programs/declaration.c"));
+    round_trip(include_str!("../../resources/programs/expression.c"));
+    round_trip(include_str!("../../resources/programs/statement.c"));
+    round_trip("static _Thread_local int a = 1, b; __thread int c; extern _Thread_local int d;");
+    round_trip(
+        "_Alignas(16) int a; struct S { char c; _Alignas(8) int x; };
+        void f(void) { static _Alignas(32) char b[4]; _Alignas(double) int c = _Alignof(struct S); }",
+    );
+    round_trip(
+        "static inline int f(void) { return 1; }
+        __attribute__((always_inline)) inline int g(void) { return f(); }
+        __attribute__((noinline)) int h(void);
+        int h(void) { return g(); }
+        _Noreturn void e(void);
+        static inline _Noreturn void q(void) { e(); }",
+    );
+    round_trip(
+        "__attribute__((visibility(\"hidden\"))) int x = 1;
+        __attribute__((visibility(\"protected\"))) extern int y;
+        __attribute__((visibility(\"default\"))) static inline int f(void) { return x; }",
+    );
+    round_trip(
+        "typedef struct { int a; } T, *PT;
+        typedef const T CT;
+        T y, z;
+        CT w;
+        PT p = &y;
+        void f(void) { z = y; z = w; *p = sizeof(T) ? y : *(CT *)p; }",
+    );
+    round_trip(
+        "struct O { struct I { int x; } i, *pi; union { int u; float v; } un; enum E { A, B } e : 2; };
+        struct I j;
+        enum E k = B;
+        struct L { struct L *next; struct M *m; };
+        struct M { int v; };",
+    );
+    round_trip("int f(); int g(void); int h() { return f(1) + g(); }");
+    round_trip(
+        "struct P { int x, y; } ps[] = {[2].y = 6, {1}, [0] = {.x = 8}};
+        int a[] = {[1 + 2] = 7, [1] = 2, 9};",
+    );
+    round_trip(
+        "struct P { int x, y; } *p = &(struct P){.y = 1};
+        int f(void) { return (int[]){1, 2}[1] + sizeof (char[]){\"ab\"} + (struct P){3}.x; }",
+    );
+}
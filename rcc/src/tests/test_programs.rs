@@ -0,0 +1,49 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use backend::interp::Interpreter;
+
+/// 和 `--run` 相同：预处理、编译后用解释器执行 `main`，返回退出码
+fn run(file: &str, code: &str, opt_level: u32) -> i32 {
+    let options = CompilerOptions {
+        input: Some(file.to_owned()),
+        opt_level,
+        ..Default::default()
+    };
+    let compiler = CCompiler::new(code.to_owned(), options);
+    let (_, ctx, unit) = compiler.parse().expect("parse failed");
+    let module = compiler.lower(&ctx, &unit).expect("lower failed");
+    let mut interp = Interpreter::new(&module).expect("bad module");
+    interp.run_main(&["a.out"]).expect("run failed")
+}
+
+#[test]
+fn test_programs() {
+    let programs = [
+        (
+            "declaration.c",
+            include_str!("../../resources/programs/declaration.c"),
+            98,
+        ),
+        (
+            "expression.c",
+            include_str!("../../resources/programs/expression.c"),
+            139,
+        ),
+        (
+            "statement.c",
+            include_str!("../../resources/programs/statement.c"),
+            95,
+        ),
+    ];
+    for (file, code, status) in programs {
+        for opt_level in [0, 2] {
+            assert_eq!(
+                run(file, code, opt_level),
+                status,
+                "{} -O{}",
+                file,
+                opt_level
+            );
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::compiler::c_compiler::CCompiler;
+use crate::compiler::options::CompilerOptions;
+use crate::lex::types::token_kind::{LiteralKind, Symbol};
+use crate::parser::ast::exprs::{BinOpKind, ExprKind};
+use crate::parser::ast::func::{ExternalDecl, TranslationUnit};
+use crate::parser::ast::visitor::{Visitor, VisitorMut};
+use crate::parser::ast::{DeclKey, ExprKey, StmtKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::writer::c_printer::{CPrinter, ParenStyle};
+use rustc_hash::FxHashMap;
+
+/// 把两个整数字面量的 `+` `*` 替换成一个字面量
+struct ConstReplace<'a> {
+    ctx: &'a mut CompCtx,
+}
+
+impl ConstReplace<'_> {
+    fn int(&self, key: ExprKey) -> Option<i64> {
+        match &self.ctx.get_expr(key).kind {
+            ExprKind::Literal(LiteralKind::Integer { value, .. }) => value.get().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl VisitorMut for ConstReplace<'_> {
+    fn ctx_mut(&mut self) -> &mut CompCtx {
+        self.ctx
+    }
+
+    fn post_expr(&mut self, key: ExprKey) {
+        let ExprKind::Binary { lhs, op, rhs } = &self.ctx.get_expr(key).kind else {
+            return;
+        };
+        let value = match (self.int(*lhs), op.kind, self.int(*rhs)) {
+            (Some(l), BinOpKind::Plus, Some(r)) => l + r,
+            (Some(l), BinOpKind::Mul, Some(r)) => l * r,
+            _ => return,
+        };
+        let kind = LiteralKind::Integer {
+            value: Symbol::new(&value.to_string()),
+            suffix: None,
+        };
+        self.ctx.get_expr_mut(key).kind = ExprKind::Literal(kind);
+    }
+}
+
+#[test]
+fn test_const_replace() {
+    let code = r#"
+        int g = 2 * 3 + 1;
+        int main(void) {
+            int x = 1;
+            return (1 + 2) * 4 - x + g;
+        }
+    "#;
+    let compiler = CCompiler::new(code.to_owned(), CompilerOptions::default());
+    let (_, mut ctx, mut unit) = compiler.parse().expect("parse failed");
+    ConstReplace { ctx: &mut ctx }.walk_translation_unit(&mut unit);
+
+    let printed = CPrinter::new(&ctx, ParenStyle::Minimal).print_unit(&unit);
+    assert!(printed.contains("int g = 7;"), "{}", printed);
+    assert!(printed.contains("int x = 1;"), "{}", printed);
+    assert!(printed.contains("return 12 - x + g;"), "{}", printed);
+}
+
+/// 覆盖所有节点种类的程序
+const ALL_NODES: &str = r#"
+    struct S { int a : 3; unsigned b : 5; struct S *next; };
+    enum E { A, B = A + 2 };
+    typedef int T;
+    int g[3] = { 1, [2] = 2 }, *gp = &g[1];
+    int f(int x, ...);
+    int h(int x) {
+        struct S s = { 1, 2, 0 };
+        int i = 0;
+        for (int j = 0; j < 3; j++) i += j;
+        for (;;) break;
+        while (i > 10) i--;
+        do { i++; } while (i < 5);
+        switch (x) {
+        case A: i = -i; break;
+        default: goto out;
+        }
+        if (x) ; else i = x ? sizeof(T) : sizeof x;
+    out:
+        s.next = &(struct S){ .b = i };
+        return f(i, s.next->a, (long)g[0], __builtin_expect(i, 0));
+    }
+    int main(void) { return h(1); }
+"#;
+
+/// 统计每个节点被访问的次数
+#[derive(Default)]
+struct Counter {
+    decls: FxHashMap<DeclKey, usize>,
+    exprs: FxHashMap<ExprKey, usize>,
+    stmts: FxHashMap<StmtKey, usize>,
+}
+
+struct CountVisitor<'a> {
+    ctx: &'a CompCtx,
+    count: Counter,
+}
+
+impl<'a> Visitor<'a> for CountVisitor<'a> {
+    fn ctx(&self) -> &'a CompCtx {
+        self.ctx
+    }
+
+    fn pre_decl(&mut self, decl: DeclKey) -> bool {
+        *self.count.decls.entry(decl).or_default() += 1;
+        true
+    }
+
+    fn pre_expr(&mut self, expr: ExprKey) -> bool {
+        *self.count.exprs.entry(expr).or_default() += 1;
+        true
+    }
+
+    fn pre_stmt(&mut self, stmt: StmtKey) -> bool {
+        *self.count.stmts.entry(stmt).or_default() += 1;
+        true
+    }
+}
+
+/// 遍历时读取所有祖先节点，并统计访问次数
+struct CountVisitorMut<'a> {
+    ctx: &'a mut CompCtx,
+    count: Counter,
+    parents: Vec<ExprKey>,
+}
+
+impl VisitorMut for CountVisitorMut<'_> {
+    fn ctx_mut(&mut self) -> &mut CompCtx {
+        self.ctx
+    }
+
+    fn pre_decl(&mut self, decl: DeclKey) -> bool {
+        *self.count.decls.entry(decl).or_default() += 1;
+        true
+    }
+
+    fn pre_expr(&mut self, expr: ExprKey) -> bool {
+        *self.count.exprs.entry(expr).or_default() += 1;
+        // 有子节点的祖先不可能是 SizeofType
+        for x in &self.parents {
+            let kind = &self.ctx.get_expr(*x).kind;
+            assert!(!matches!(kind, ExprKind::SizeofType { .. }), "{:?}", kind);
+        }
+        self.parents.push(expr);
+        true
+    }
+
+    fn post_expr(&mut self, _expr: ExprKey) {
+        self.parents.pop();
+    }
+
+    fn pre_stmt(&mut self, stmt: StmtKey) -> bool {
+        *self.count.stmts.entry(stmt).or_default() += 1;
+        true
+    }
+}
+
+fn check_count(ctx: &CompCtx, unit: &TranslationUnit, count: &Counter) {
+    for key in ctx.expr_keys() {
+        let expr = ctx.get_expr(key);
+        assert_eq!(count.exprs.get(&key), Some(&1), "{:?}", expr);
+    }
+    // 函数体挂在 DeclKind::FuncDef 上，没有 key
+    let bodies: Vec<_> = unit
+        .iter()
+        .filter_map(|x| match x {
+            ExternalDecl::FunctionDefinition(x) => Some(x.body),
+            ExternalDecl::Declaration(_) => None,
+        })
+        .collect();
+    for key in ctx.stmt_keys().filter(|x| !bodies.contains(x)) {
+        let stmt = ctx.get_stmt(key);
+        assert_eq!(count.stmts.get(&key), Some(&1), "{:?}", stmt);
+    }
+    assert!(count.decls.values().all(|x| *x == 1));
+}
+
+#[test]
+fn test_visit_all() {
+    let compiler = CCompiler::new(ALL_NODES.to_owned(), CompilerOptions::default());
+    let (_, mut ctx, mut unit) = compiler.parse().expect("parse failed");
+
+    let mut visitor = CountVisitor {
+        ctx: &ctx,
+        count: Counter::default(),
+    };
+    visitor.walk_translation_unit(&unit);
+    let count = visitor.count;
+    check_count(&ctx, &unit, &count);
+
+    let mut visitor = CountVisitorMut {
+        ctx: &mut ctx,
+        count: Counter::default(),
+        parents: Vec::new(),
+    };
+    visitor.walk_translation_unit(&mut unit);
+    let count_mut = visitor.count;
+    check_count(&ctx, &unit, &count_mut);
+    assert_eq!(count.decls, count_mut.decls);
+}
@@ -14,6 +14,7 @@ impl Span {
         Span { start, end }
     }
     
+    #[allow(clippy::self_named_constructors)]
     pub fn span(lo: Span, hi: Span) -> Self {
         let start = lo.start;
         let end = hi.end;
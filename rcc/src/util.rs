@@ -1,4 +1,5 @@
 
 pub mod utf8;
 pub mod ap_float;
-pub mod ap_int;
\ No newline at end of file
+pub mod ap_int;
+pub mod literal;
@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter, LowerExp};
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FloatTy {
     F32,
@@ -80,3 +82,27 @@ impl APFloat {
     }
 }
 
+impl Display for APFloat {
+    /// 最短的能还原出原值的十进制表示，整数值保留 `.0`，绝对值很大或很小时用科学计数法
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            APFloat::F32(x) => write_float(f, *x),
+            APFloat::F64(x) | APFloat::F80(x) => write_float(f, *x),
+        }
+    }
+}
+
+fn write_float<T>(f: &mut Formatter<'_>, value: T) -> std::fmt::Result
+where
+    T: Copy + Display + LowerExp + Into<f64>,
+{
+    let abs = value.into().abs();
+    if abs != 0.0 && abs.is_finite() && !(1e-4..1e16).contains(&abs) {
+        return write!(f, "{:e}", value);
+    }
+    let text = value.to_string();
+    match abs.is_finite() && !text.contains('.') {
+        true => write!(f, "{}.0", text),
+        false => f.write_str(&text),
+    }
+}
@@ -1,6 +1,7 @@
 use ibig::{IBig, ibig};
 
 use crate::constant::typ::INT_BITWIDTH;
+use std::fmt::{Display, Formatter};
 
 /// 目前先用大数类型表示
 #[derive(Debug, Clone)]
@@ -113,6 +114,27 @@ impl APInt {
     }
 
     pub fn as_usize(&self) -> usize {
-        todo!("这里截断掉")
+        self.as_u64() as usize
+    }
+
+    /// 低 64 位，负数按补码
+    pub fn as_u64(&self) -> u64 {
+        let mask = (IBig::from(1) << 64) - 1;
+        u64::try_from(&self.value & mask).expect("masked to 64 bits")
+    }
+
+    /// 数值，超出 i128 时截断到低 128 位
+    pub fn as_i128(&self) -> i128 {
+        i128::try_from(&self.value).unwrap_or_else(|_| {
+            let mask = (IBig::from(1) << 128) - 1;
+            u128::try_from(&self.value & mask).expect("masked to 128 bits") as i128
+        })
+    }
+}
+
+
+impl Display for APInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
     }
 }
@@ -0,0 +1,127 @@
+//!
+//! 字面量的拼写转换为值，lexer 保留的是源码中的拼写
+//!
+
+/// 整数字面量，支持十进制、八进制（`0` 开头）、十六进制（`0x`）、二进制（`0b`），不含后缀
+pub fn parse_int(text: &str) -> Option<u128> {
+    let lower = text.to_ascii_lowercase();
+    let (digits, radix) = if let Some(x) = lower.strip_prefix("0x") {
+        (x, 16)
+    } else if let Some(x) = lower.strip_prefix("0b") {
+        (x, 2)
+    } else if lower.len() > 1 && lower.starts_with('0') {
+        (&lower[1..], 8)
+    } else {
+        (lower.as_str(), 10)
+    };
+    u128::from_str_radix(digits, radix).ok()
+}
+
+/// 浮点字面量，不含后缀
+pub fn parse_float(text: &str) -> Option<f64> {
+    let lower = text.to_ascii_lowercase();
+    match lower.strip_prefix("0x") {
+        Some(x) => parse_hex_float(x),
+        None => lower.parse().ok(),
+    }
+}
+
+/// 十六进制浮点 `1.8p3`，不含 `0x`
+fn parse_hex_float(text: &str) -> Option<f64> {
+    let (mantissa, exp) = text.split_once('p')?;
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut value = 0f64;
+    for c in int.chars() {
+        value = value * 16.0 + c.to_digit(16)? as f64;
+    }
+    let mut scale = 1.0 / 16.0;
+    for c in frac.chars() {
+        value += c.to_digit(16)? as f64 * scale;
+        scale /= 16.0;
+    }
+    let exp: i32 = exp.parse().ok()?;
+    Some(value * 2f64.powi(exp))
+}
+
+/// 解析一个转义序列，`chars` 指向 `\` 之后
+fn unescape(chars: &mut std::iter::Peekable<std::str::Chars>, out: &mut Vec<u8>) {
+    let Some(c) = chars.next() else {
+        return;
+    };
+    let byte = match c {
+        'n' => b'\n',
+        't' => b'\t',
+        'r' => b'\r',
+        'a' => 0x07,
+        'b' => 0x08,
+        'f' => 0x0c,
+        'v' => 0x0b,
+        'e' => 0x1b,
+        '0'..='7' => {
+            let mut value = c.to_digit(8).unwrap();
+            for _ in 0..2 {
+                match chars.peek().and_then(|x| x.to_digit(8)) {
+                    Some(x) => {
+                        value = value * 8 + x;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            value as u8
+        }
+        'x' => {
+            let mut value = 0u32;
+            while let Some(x) = chars.peek().and_then(|x| x.to_digit(16)) {
+                value = value.wrapping_mul(16) + x;
+                chars.next();
+            }
+            value as u8
+        }
+        // `\\` `\'` `\"` `\?` 和未知的转义都是字符本身
+        c => {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            return;
+        }
+    };
+    out.push(byte);
+}
+
+/// 引号之间的内容转为字节，`text` 可以是相邻的多个字符串 `"a" "b"`，引号外的内容被忽略
+fn quoted_bytes(text: &str, quote: char) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut inside = false;
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c == quote => inside = !inside,
+            '\\' if inside => unescape(&mut chars, &mut out),
+            _ if inside => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            _ => {} // 前缀和字符串之间的空白
+        }
+    }
+    out
+}
+
+/// 字符串字面量的内容，包含结尾的 0
+pub fn string_bytes(text: &str) -> Vec<u8> {
+    let mut bytes = quoted_bytes(text, '"');
+    bytes.push(0);
+    bytes
+}
+
+/// 字符字面量的值，多字符常量按 gcc 的方式从高到低拼接，结果为 `int`
+pub fn char_value(text: &str) -> i32 {
+    let bytes = quoted_bytes(text, '\'');
+    match bytes.as_slice() {
+        // 单个字符按 signed char 扩展
+        [x] => *x as i8 as i32,
+        _ => bytes
+            .iter()
+            .fold(0i32, |acc, x| acc.wrapping_shl(8) | *x as i32),
+    }
+}
@@ -1 +1,4 @@
-pub mod ast_graph;
\ No newline at end of file
+pub mod ast_dump;
+pub mod ast_graph;
+pub mod ast_json;
+pub mod c_printer;
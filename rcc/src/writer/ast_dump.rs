@@ -0,0 +1,503 @@
+use crate::content_manager::ContentManager;
+use crate::parser::ast::decls::decl::{DeclGroup, DeclKind};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{Constant, ExprKind, MemberAccessKind, MemberDesignator};
+use crate::parser::ast::func::{ExternalDecl, FuncDef, TranslationUnit};
+use crate::parser::ast::stmt::{Stmt, StmtKind};
+use crate::parser::ast::visitor::Visitor;
+use crate::parser::ast::{DeclKey, ExprKey, StmtKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::sema::expr::value_type::ValueType;
+use crate::lex::types::token_kind::LiteralKind;
+use crate::preprocess::source_map::SourceLoc;
+use crate::types::span::Span;
+use std::fmt::Write;
+
+/// 待输出的子节点，先收集再输出，这样才知道哪个是最后一个
+enum Child<'a> {
+    Decl(DeclKey),
+    Stmt(StmtKey),
+    Expr(ExprKey),
+    Init(&'a Initializer),
+    Body(&'a Stmt), // 函数体挂在 decl 上，不在 stmt 池里
+    Null, // 可选子节点缺失，如 `for(;;)`
+}
+
+///
+/// 类似 `clang -ast-dump` 的 AST 打印
+///
+/// # Members
+/// - `ctx`: 编译上下文，节点都存在这里
+/// - `content`: 用于把 span 转换成 `line:col`
+/// - `filter`: 只输出名字相同的顶层声明
+/// - `prefix`: 当前缩进前缀
+/// - `depth`: 当前深度，根节点为 0
+/// - `last`: 当前节点是否为父节点的最后一个孩子
+/// - `out`: 输出
+///
+pub struct AstDumper<'a> {
+    ctx: &'a CompCtx,
+    content: &'a ContentManager,
+    filter: Option<&'a str>,
+    prefix: String,
+    depth: usize,
+    last: bool,
+    out: String,
+}
+
+impl<'a> AstDumper<'a> {
+    pub fn new(ctx: &'a CompCtx, content: &'a ContentManager, filter: Option<&'a str>) -> Self {
+        Self {
+            ctx,
+            content,
+            filter,
+            prefix: String::new(),
+            depth: 0,
+            last: true,
+            out: String::new(),
+        }
+    }
+
+    /// 结束，返回输出内容
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    /// 主文件中的位置为 `line:col`，其他文件中的为 `file:line:col`
+    fn loc(&self, loc: SourceLoc) -> String {
+        match self.content.file_name(loc.file).filter(|_| loc.file != 0) {
+            Some(file) => format!("{}:{}:{}", file, loc.line, loc.col),
+            None => format!("{}:{}", loc.line, loc.col),
+        }
+    }
+
+    fn range(&self, span: Span) -> String {
+        let start = self.content.location(span.start);
+        let end = self.content.end_location(span);
+        format!("<{}, {}>", self.loc(start), self.loc(end))
+    }
+
+    fn ty(&self, ty: TypeKey) -> String {
+        format!("'{}'", self.ctx.type_ctx.get_type(ty).to_code(self.ctx))
+    }
+
+    /// 输出一行节点，根节点没有分支符号
+    fn line(&mut self, label: String) {
+        let branch = match (self.depth, self.last) {
+            (0, _) => "",
+            (_, true) => "`-",
+            (_, false) => "|-",
+        };
+        let _ = writeln!(self.out, "{}{}{}", self.prefix, branch, label);
+    }
+
+    /// 输出子节点，进入子节点时追加缩进前缀，结束后恢复
+    fn children(&mut self, children: Vec<Child<'a>>) {
+        if children.is_empty() {
+            return;
+        }
+        let prev_len = self.prefix.len();
+        if self.depth > 0 {
+            self.prefix.push_str(if self.last { "  " } else { "| " });
+        }
+        self.depth += 1;
+
+        let len = children.len();
+        for (i, child) in children.into_iter().enumerate() {
+            self.last = i + 1 == len;
+            match child {
+                Child::Decl(x) => self.visit_decl(x),
+                Child::Stmt(x) => self.visit_stmt(x),
+                Child::Expr(x) => self.visit_expr(x),
+                Child::Init(x) => self.dump_initializer(x),
+                Child::Body(x) => self.dump_stmt(x, None),
+                Child::Null => self.line("<<<NULL>>>".to_owned()),
+            }
+        }
+
+        self.depth -= 1;
+        self.prefix.truncate(prev_len);
+    }
+
+    /// 顶层声明是否通过过滤
+    fn is_selected(&self, decl: DeclKey) -> bool {
+        let Some(filter) = self.filter else {
+            return true;
+        };
+        let decl = self.ctx.get_decl(decl);
+        decl.name.as_ref().is_some_and(|x| x.symbol.get() == filter)
+    }
+
+    fn group_children(group: &DeclGroup) -> Vec<Child<'a>> {
+        group.decls.iter().map(|x| Child::Decl(*x)).collect()
+    }
+
+    fn dump_initializer(&mut self, init: &'a Initializer) {
+        match init {
+            Initializer::Expr(x) => self.visit_expr(*x),
+            Initializer::InitList { inits } => {
+                let label = format!("InitListExpr {}", self.range(inits.span));
+                self.line(label);
+                let children = inits.inits.iter().map(Child::Init).collect();
+                self.children(children);
+            }
+            Initializer::Designated {
+                designators,
+                init,
+                span,
+            } => {
+                // 下标表达式作为子节点输出
+                let mut code = String::new();
+                let mut children = Vec::new();
+                for x in designators {
+                    match x {
+                        MemberDesignator::Field(name) => code += &format!(".{}", name.symbol),
+                        MemberDesignator::Index(expr) => {
+                            code += "[]";
+                            children.push(Child::Expr(*expr));
+                        }
+                    }
+                }
+                let label = format!("DesignatedInitExpr {} {}", self.range(*span), code);
+                self.line(label);
+                children.push(Child::Init(init));
+                self.children(children);
+            }
+        }
+    }
+
+    fn dump_stmt(&mut self, stmt: &'a Stmt, key: Option<StmtKey>) {
+        use StmtKind::*;
+        let key = key.map(|x| format!(" {:?}", x)).unwrap_or_default();
+        let range = self.range(stmt.span);
+        let opt = |x: &Option<ExprKey>| x.map(Child::Expr).unwrap_or(Child::Null);
+
+        let (label, children) = match &stmt.kind {
+            Expr { expr: None, .. } => ("NullStmt".to_owned(), vec![]),
+            Expr { expr: Some(x), .. } => {
+                // 表达式语句直接输出表达式
+                self.visit_expr(*x);
+                return;
+            }
+            Decl { decl } => ("DeclStmt".to_owned(), Self::group_children(decl)),
+            Label { ident, stmt } => (
+                format!("LabelStmt '{}'", ident.symbol),
+                vec![Child::Stmt(*stmt)],
+            ),
+            Case { expr, stmt, .. } => (
+                "CaseStmt".to_owned(),
+                vec![Child::Expr(*expr), Child::Stmt(*stmt)],
+            ),
+            Default { stmt, .. } => ("DefaultStmt".to_owned(), vec![Child::Stmt(*stmt)]),
+            IfElse {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let mut children = vec![Child::Expr(*cond), Child::Stmt(*then_stmt)];
+                let label = match else_stmt {
+                    Some(x) => {
+                        children.push(Child::Stmt(*x));
+                        "IfStmt has_else"
+                    }
+                    None => "IfStmt",
+                };
+                (label.to_owned(), children)
+            }
+            Switch { expr, body, .. } => (
+                "SwitchStmt".to_owned(),
+                vec![Child::Expr(*expr), Child::Stmt(*body)],
+            ),
+            While { cond, body, .. } => (
+                "WhileStmt".to_owned(),
+                vec![Child::Expr(*cond), Child::Stmt(*body)],
+            ),
+            DoWhile { body, cond, .. } => (
+                "DoStmt".to_owned(),
+                vec![Child::Stmt(*body), Child::Expr(*cond)],
+            ),
+            For {
+                init,
+                cond,
+                step,
+                body,
+                ..
+            } => (
+                "ForStmt".to_owned(),
+                vec![
+                    init.map(Child::Stmt).unwrap_or(Child::Null),
+                    opt(cond),
+                    opt(step),
+                    Child::Stmt(*body),
+                ],
+            ),
+            Goto { ident } => (format!("GotoStmt '{}'", ident.symbol), vec![]),
+            Continue { .. } => ("ContinueStmt".to_owned(), vec![]),
+            Break { .. } => ("BreakStmt".to_owned(), vec![]),
+            Return { expr, .. } => (
+                "ReturnStmt".to_owned(),
+                expr.iter().map(|x| Child::Expr(*x)).collect(),
+            ),
+            Compound { stmts, .. } => (
+                "CompoundStmt".to_owned(),
+                stmts.iter().map(|x| Child::Stmt(*x)).collect(),
+            ),
+        };
+
+        let (name, extra) = label.split_once(' ').unwrap_or((label.as_str(), ""));
+        let label = format!("{}{} {} {}", name, key, range, extra);
+        self.line(label.trim_end().to_owned());
+        self.children(children);
+    }
+}
+
+impl<'a> Visitor<'a> for AstDumper<'a> {
+    fn ctx(&self) -> &'a CompCtx {
+        self.ctx
+    }
+
+    fn walk_translation_unit(&mut self, unit: &'a TranslationUnit) {
+        self.line("TranslationUnitDecl".to_owned());
+        let mut children = Vec::new();
+        for ext_decl in unit.iter() {
+            match ext_decl {
+                ExternalDecl::FunctionDefinition(x) => children.push(x.decl),
+                ExternalDecl::Declaration(x) => children.extend(x.decls.iter().cloned()),
+            }
+        }
+        let children = children
+            .into_iter()
+            .filter(|x| self.is_selected(*x))
+            .map(Child::Decl)
+            .collect();
+        self.children(children);
+    }
+
+    fn walk_func_def(&mut self, decl: &'a FuncDef) {
+        if self.is_selected(decl.decl) {
+            self.visit_decl(decl.decl);
+        }
+    }
+
+    fn walk_decl_group(&mut self, decl_group: &'a DeclGroup) {
+        for x in decl_group.decls.iter().cloned() {
+            if self.is_selected(x) {
+                self.visit_decl(x);
+            }
+        }
+    }
+
+    fn visit_decl(&mut self, key: DeclKey) {
+        use DeclKind::*;
+        let ctx = self.ctx;
+        let decl = ctx.get_decl(key);
+        let name = decl
+            .name
+            .as_ref()
+            .map(|x| x.symbol.get())
+            .unwrap_or_default();
+        let mut storage: String = [&decl.storage, &decl.thread_local]
+            .into_iter()
+            .flatten()
+            .map(|x| format!(" {}", x))
+            .collect();
+        if let Some(x) = &decl.func_spec {
+            storage += &format!(" {}", x.kind.name());
+        }
+        if decl.noreturn.is_some() {
+            storage += " noreturn";
+        }
+        if let Some(x) = &decl.visibility {
+            storage += &format!(" visibility({})", x.kind.name());
+        }
+        if let Some(x) = &decl.align {
+            storage += &format!(" aligned({})", x.align);
+        }
+        let ty = self.ty(decl.ty);
+        let head = |kind: &str| format!("{} {:?} {}", kind, key, self.range(decl.span));
+        let refer = |kind: &str, x: &Option<DeclKey>| match x {
+            Some(x) => format!(" {}: {:?}", kind, x),
+            None => String::new(),
+        };
+
+        let (label, children) = match &decl.kind {
+            TypeDef => (format!("{} {} {}", head("TypedefDecl"), name, ty), vec![]),
+            ParamVar => (format!("{} {} {}", head("ParmVarDecl"), name, ty), vec![]),
+            VarDecl { def } => (
+                format!("{} {} {}{}{}", head("VarDecl"), name, ty, storage, refer("def", def)),
+                vec![],
+            ),
+            VarDef { init } => (
+                format!("{} {} {}{}", head("VarDecl"), name, ty, storage),
+                init.iter().map(Child::Init).collect(),
+            ),
+            FuncDecl { def } => (
+                format!("{} {} {}{}{}", head("FunctionDecl"), name, ty, storage, refer("def", def)),
+                vec![],
+            ),
+            FuncDef { params, body, .. } => {
+                let mut children: Vec<_> = params.iter().map(|x| Child::Decl(*x)).collect();
+                children.push(Child::Body(body));
+                (
+                    format!("{} {} {}{}", head("FunctionDecl"), name, ty, storage),
+                    children,
+                )
+            }
+            RecordField { bit_field } => (
+                format!("{} {} {}", head("FieldDecl"), name, ty),
+                bit_field.iter().map(|x| Child::Expr(*x)).collect(),
+            ),
+            RecordDecl { kind, def } => (
+                format!("{} {:?} {}{}", head("RecordDecl"), kind.kind, name, refer("def", def)),
+                vec![],
+            ),
+            RecordDef { kind, fields } => (
+                format!("{} {:?} {} definition", head("RecordDecl"), kind.kind, name),
+                fields.iter().flat_map(Self::group_children).collect(),
+            ),
+            EnumField { expr } => {
+                let value = expr
+                    .and_then(|x| ctx.get_expr(x).value.as_ref())
+                    .map(|x| format!(" = {}", constant_code(x)))
+                    .unwrap_or_default();
+                (
+                    format!("{} {} {}{}", head("EnumConstantDecl"), name, ty, value),
+                    expr.iter().map(|x| Child::Expr(*x)).collect(),
+                )
+            }
+            EnumDecl { def } => (format!("{} {}{}", head("EnumDecl"), name, refer("def", def)), vec![]),
+            EnumDef { enums } => (
+                format!("{} {} definition", head("EnumDecl"), name),
+                enums.iter().flatten().map(|x| Child::Decl(*x)).collect(),
+            ),
+        };
+
+        self.line(label);
+        self.children(children);
+    }
+
+    fn visit_expr(&mut self, key: ExprKey) {
+        use ExprKind::*;
+        let ctx = self.ctx;
+        let expr = ctx.get_expr(key);
+        let category = match ValueType::of(expr) {
+            ValueType::LValue => " lvalue",
+            ValueType::RValue => "",
+        };
+        let head = |kind: &str| {
+            format!("{} {:?} {} {}{}", kind, key, self.range(expr.span), self.ty(expr.ty), category)
+        };
+
+        let (label, children) = match &expr.kind {
+            DeclRef { ident, decl } => {
+                let decl = decl.map(|x| format!(" {:?}", x)).unwrap_or_default();
+                (format!("{} '{}'{}", head("DeclRefExpr"), ident.symbol, decl), vec![])
+            }
+            Literal(lit) => {
+                let (kind, value) = match lit {
+                    LiteralKind::Integer { value, .. } => ("IntegerLiteral", value),
+                    LiteralKind::Float { value, .. } => ("FloatingLiteral", value),
+                    LiteralKind::Char { value } => ("CharacterLiteral", value),
+                    LiteralKind::String { value } => ("StringLiteral", value),
+                };
+                (format!("{} {}", head(kind), value), vec![])
+            }
+            ArraySubscript { base, index } => (
+                head("ArraySubscriptExpr"),
+                vec![Child::Expr(*base), Child::Expr(*index)],
+            ),
+            Call { base, params } => {
+                let mut children = vec![Child::Expr(*base)];
+                children.extend(params.exprs.iter().map(|x| Child::Expr(*x)));
+                (head("CallExpr"), children)
+            }
+            MemberAccess { kind, base, field } => {
+                let op = match kind {
+                    MemberAccessKind::Arrow => "->",
+                    MemberAccessKind::Dot => ".",
+                };
+                (format!("{} {}{}", head("MemberExpr"), op, field), vec![Child::Expr(*base)])
+            }
+            SizeofExpr { expr } => (
+                format!("{} sizeof", head("UnaryExprOrTypeTraitExpr")),
+                vec![Child::Expr(*expr)],
+            ),
+            SizeofType { ty } => (
+                format!("{} sizeof {}", head("UnaryExprOrTypeTraitExpr"), self.ty(*ty)),
+                vec![],
+            ),
+            AlignofType { ty } => (
+                format!("{} alignof {}", head("UnaryExprOrTypeTraitExpr"), self.ty(*ty)),
+                vec![],
+            ),
+            Unary { op, rhs } => {
+                let fix = if op.kind.is_postfix() { "postfix" } else { "prefix" };
+                (
+                    format!("{} {} '{}'", head("UnaryOperator"), fix, op.kind),
+                    vec![Child::Expr(*rhs)],
+                )
+            }
+            Binary { lhs, op, rhs } => (
+                format!("{} '{}'", head("BinaryOperator"), op.kind),
+                vec![Child::Expr(*lhs), Child::Expr(*rhs)],
+            ),
+            Assign { lhs, op, rhs } => (
+                format!("{} '{}'", head("AssignOperator"), op.kind),
+                vec![Child::Expr(*lhs), Child::Expr(*rhs)],
+            ),
+            Cast { expr, .. } => (head("CStyleCastExpr"), vec![Child::Expr(*expr)]),
+            CompoundLiteral { init, .. } => (head("CompoundLiteralExpr"), vec![Child::Init(init)]),
+            Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => (
+                head("ConditionalOperator"),
+                vec![
+                    Child::Expr(*cond),
+                    Child::Expr(*then_expr),
+                    Child::Expr(*else_expr),
+                ],
+            ),
+            BuiltinCall { builtin, args } => {
+                let types: String = args
+                    .iter()
+                    .filter_map(|x| x.as_type())
+                    .map(|x| format!(" {}", self.ty(*x)))
+                    .collect();
+                let children = args
+                    .iter()
+                    .flat_map(|x| x.exprs())
+                    .map(Child::Expr)
+                    .collect();
+                (
+                    format!("{} {}{}", head("BuiltinCallExpr"), builtin.name(), types),
+                    children,
+                )
+            }
+        };
+
+        // 折叠后的常量值
+        let value = expr
+            .value
+            .as_ref()
+            .map(|x| format!(" = {}", constant_code(x)))
+            .unwrap_or_default();
+        self.line(format!("{}{}", label, value));
+        self.children(children);
+    }
+
+    fn visit_stmt(&mut self, key: StmtKey) {
+        let stmt = self.ctx.get_stmt(key);
+        self.dump_stmt(stmt, Some(key));
+    }
+}
+
+fn constant_code(value: &Constant) -> String {
+    match value {
+        Constant::Intager { value } => value.to_string(),
+        Constant::Float { value } => value.to_string(),
+        Constant::String { value } => format!("{:?}", String::from_utf8_lossy(value)),
+    }
+}
@@ -1,270 +1,450 @@
-// use crate::parser::ast::ExprKey;
-// use crate::parser::ast::decl::{DeclGroup, DeclKind, Initializer};
-// use crate::parser::ast::exprs::ExprKind;
-// use crate::parser::ast::func::{ExternalDecl, FuncDef, TranslationUnit};
-// use crate::parser::ast::stmt::{StmtKey, StmtKind};
-// use crate::parser::ast::visitor::Visitor;
-// use crate::parser::comp_ctx::CompCtx;
-// use petgraph::graph::{DiGraph, NodeIndex};
+use crate::parser::ast::decls::decl::{DeclGroup, DeclKind};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{BuiltinArg, ExprKind, MemberAccessKind, MemberDesignator};
+use crate::parser::ast::func::{FuncDef, TranslationUnit};
+use crate::parser::ast::stmt::{Stmt, StmtKind};
+use crate::parser::ast::visitor::Visitor;
+use crate::parser::ast::{DeclKey, ExprKey, StmtKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::writer::c_printer::literal_code;
+use petgraph::dot::{Config, Dot};
+use petgraph::graph::{DiGraph, EdgeReference, NodeIndex};
+use rustc_hash::FxHashMap;
+use std::fmt::{Display, Formatter};
 
-// pub struct AstGraph<'a> {
-//     pub tree: DiGraph<String, ()>,
-//     ctx: &'a CompCtx,
-//     current: NodeIndex,
-// }
+type AstTree = DiGraph<String, AstEdge>;
 
-// impl<'a> AstGraph<'a> {
-//     pub fn new(ctx: &'a CompCtx) -> Self {
-//         let tree = DiGraph::new();
-//         Self {
-//             tree,
-//             ctx,
-//             current: NodeIndex::default(),
-//         }
-//     }
+/// 边的种类
+/// - `Child`: AST 父子关系
+/// - `Ref`: `DeclRefExpr` 到被引用声明，虚线表示
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstEdge {
+    Child,
+    Ref,
+}
 
-//     /// 返回旧的节点
-//     fn make_node(&mut self, name: String) -> NodeIndex {
-//         let prev = self.current;
-//         self.current = self.connect_node(name);
-//         prev
-//     }
+/// 边不输出 label（`Config::EdgeNoLabel`），样式由 attr getter 给出
+impl Display for AstEdge {
+    fn fmt(&self, _: &mut Formatter<'_>) -> std::fmt::Result {
+        Ok(())
+    }
+}
 
-//     fn connect_node(&mut self, name: String) -> NodeIndex {
-//         let node_idx = self.tree.add_node(name);
-//         self.tree.add_edge(self.current, node_idx, ());
-//         node_idx
-//     }
-// }
+///
+/// AST 转 Graphviz 图
+///
+/// # Members
+/// - `tree`: 图，节点是标签
+/// - `ctx`: 编译上下文
+/// - `current`: 当前父节点
+/// - `filter`: 只输出某个函数（或顶层声明）的子树
+/// - `decl_refs`: 是否输出引用边
+/// - `decl_nodes`: decl 对应的节点，用于连接引用边
+/// - `pending_refs`: 待连接的引用边，被引用的声明可能还没访问到
+///
+pub struct AstGraph<'a> {
+    pub tree: AstTree,
+    ctx: &'a CompCtx,
+    current: Option<NodeIndex>,
+    filter: Option<&'a str>,
+    decl_refs: bool,
+    decl_nodes: FxHashMap<DeclKey, NodeIndex>,
+    pending_refs: Vec<(NodeIndex, DeclKey)>,
+}
 
-// impl AstGraph<'_> {
-//     fn visit_initializer(&mut self, initializer: &mut Initializer) {
-//         let prev = self.make_node("initializer".to_owned());
-//         match initializer {
-//             Initializer::Expr(x) => self.visit_expr(x),
-//             Initializer::InitList { inits, .. } => inits
-//                 .inits
-//                 .iter_mut()
-//                 .for_each(|x| self.visit_initializer(x)),
-//         }
-//         self.current = prev;
-//     }
-// }
+impl<'a> AstGraph<'a> {
+    pub fn new(ctx: &'a CompCtx) -> Self {
+        Self {
+            tree: DiGraph::new(),
+            ctx,
+            current: None,
+            filter: None,
+            decl_refs: false,
+            decl_nodes: FxHashMap::default(),
+            pending_refs: Vec::new(),
+        }
+    }
 
-// impl Visitor for AstGraph<'_> {
-//     fn walk_translation_unit(&mut self, unit: &mut TranslationUnit) {
-//         let prev = self.make_node("translation_unit".to_string());
-//         for ext_decl in unit {
-//             self.walk_external_decl(ext_decl);
-//         }
-//         self.current = prev;
-//     }
+    /// 只渲染名字为 `name` 的函数（顶层声明）子树
+    pub fn with_filter(mut self, name: Option<&'a str>) -> Self {
+        self.filter = name;
+        self
+    }
 
-//     fn walk_external_decl(&mut self, decl: &mut ExternalDecl) {
-//         let prev = self.make_node("external_decl".to_string());
-//         match decl {
-//             ExternalDecl::FunctionDefinition(x) => self.walk_func_def(x),
-//             ExternalDecl::Declaration(x) => self.walk_decl_group(x),
-//         }
-//         self.current = prev;
-//     }
+    /// 是否输出 `DeclRefExpr` 到声明的引用边
+    pub fn with_decl_refs(mut self, decl_refs: bool) -> Self {
+        self.decl_refs = decl_refs;
+        self
+    }
 
-//     fn walk_func_def(&mut self, decl: &mut FuncDef) {
-//         let prev = self.make_node("func_def".to_string());
-//         self.visit_decl(decl.decl);
-//         self.visit_stmt(decl.body);
-//         self.current = prev;
-//     }
+    /// 输出 DOT 文本，需要在 walk 之后调用
+    pub fn to_dot(&mut self) -> String {
+        // 连接引用边，只连接图中存在的声明
+        for (from, decl) in std::mem::take(&mut self.pending_refs) {
+            if let Some(to) = self.decl_nodes.get(&decl) {
+                self.tree.add_edge(from, *to, AstEdge::Ref);
+            }
+        }
 
-//     fn walk_decl_group(&mut self, decl_group: &DeclGroup) {
-//         let prev = self.make_node("decl_group".to_string());
-//         for x in decl_group.decls.iter().cloned() {
-//             self.visit_decl(x);
-//         }
-//         self.current = prev;
-//     }
+        let edge_attr = |_: &AstTree, edge: EdgeReference<AstEdge>| match edge.weight() {
+            AstEdge::Child => String::new(),
+            AstEdge::Ref => "style = dashed, color = blue, constraint = false".to_owned(),
+        };
+        let node_attr = |_: &AstTree, _: (NodeIndex, &String)| "shape = box".to_owned();
+        let dot = Dot::with_attr_getters(
+            &self.tree,
+            &[Config::EdgeNoLabel],
+            &edge_attr,
+            &node_attr,
+        );
+        // Display 只做 DOT 转义，Debug 会把标签再转义一次
+        format!("{}", dot)
+    }
 
-//     fn visit_decl(&mut self, decl: DeclKey) {
-//         let prev;
-//         let mut decl = self.ctx.get_decl(decl);
-//         let name = decl
-//             .name
-//             .as_ref()
-//             .map(|x| x.symbol.get())
-//             .unwrap_or_default();
-//         self.connect_node(name.to_string());
+    /// 连接到当前节点，并成为新的当前节点，返回旧的节点
+    fn make_node(&mut self, name: String) -> Option<NodeIndex> {
+        let prev = self.current;
+        self.current = Some(self.connect_node(name));
+        prev
+    }
 
-//         match &mut decl.kind {
-//             DeclKind::ParamVar => {
-//                 let ty = self.ctx.get_type(decl.ty);
-//                 let ty = ty.to_code(self.ctx);
-//                 prev = self.make_node(ty);
-//             }
-//             DeclKind::VarInit { init, .. } => {
-//                 prev = self.make_node("var_init".to_owned());
-//                 if let Some(x) = init {
-//                     self.visit_initializer(x);
-//                 }
-//             }
-//             DeclKind::FuncRef {} => {
-//                 prev = self.make_node("func_ref".to_owned());
-//                 // let ret = ret_ty.to_code();
-//                 // self.connect_node(ret);
-//                 // for x in params.iter() {
-//                 //     self.visit_decl(Rc::clone(x));
-//                 // }
-//             }
-//             DeclKind::Func { body } => {
-//                 prev = self.make_node("func_def".to_owned());
-//                 // let ret = ret_ty.to_code();
-//                 // self.connect_node(ret);
-//                 // for x in params.iter() {
-//                 //     self.visit_decl(Rc::clone(x));
-//                 // }
-//                 self.visit_stmt(body);
-//             }
-//             DeclKind::RecordField { bit_field, .. } => {
-//                 prev = self.make_node("record_field".to_owned());
-//                 if let Some(x) = bit_field.as_mut() {
-//                     self.visit_expr(x)
-//                 };
-//             }
-//             DeclKind::RecordDef { fields, .. } => {
-//                 prev = self.make_node("record_def".to_owned());
-//                 fields.iter_mut().for_each(|x| self.walk_decl_group(x))
-//             }
-//             DeclKind::RecordRef { kind } => {
-//                 prev = self.make_node("record_ref".to_owned());
-//             }
-//             DeclKind::EnumField { expr, .. } => {
-//                 prev = self.make_node("enum_field".to_owned());
-//                 if let Some(x) = expr {
-//                     self.visit_expr(*x)
-//                 }
-//             }
-//             DeclKind::EnumDef { enums, .. } => {
-//                 prev = self.make_node("enum_def".to_owned());
-//                 enums.iter().for_each(|x| self.visit_decl(*x))
-//             }
-//             DeclKind::EnumRef { .. } => {
-//                 prev = self.make_node("enum_ref".to_owned());
-//             }
-//             DeclKind::TypeDef {} => {
-//                 prev = self.make_node("typedef".to_owned());
-//             }
-//         }
-//         self.current = prev;
-//     }
+    /// 连接到当前节点
+    fn connect_node(&mut self, name: String) -> NodeIndex {
+        let node_idx = self.tree.add_node(name);
+        if let Some(current) = self.current {
+            self.tree.add_edge(current, node_idx, AstEdge::Child);
+        }
+        node_idx
+    }
 
-//     fn visit_expr(&mut self, expr: ExprKey) {
-//         let expr = self.ctx.get_expr_mut(expr);
-//         let prev;
-//         match &mut expr.kind {
-//             ExprKind::DeclRef(x) => {
-//                 prev = self.make_node(format!("var {}", x.symbol.get()));
-//             }
-//             ExprKind::Literal(x) => {
-//                 prev = self.make_node(format!("{:?}", x));
-//             }
-//             // ExprKind::Paren { expr, .. } => {
-//             //     prev = self.make_node("exprs".to_owned());
-//             //     self.visit_expr(expr)
-//             // }
-//             ExprKind::ArraySubscript { base, index, .. } => {
-//                 prev = self.make_node("arr_acc".to_owned());
-//                 self.visit_expr(*base);
-//                 self.visit_expr(*index);
-//             }
-//             ExprKind::Call { base, params, .. } => {
-//                 prev = self.make_node("arr_acc".to_owned());
-//                 self.visit_expr(*base);
-//                 params.exprs.iter_mut().for_each(|x| self.visit_expr(x));
-//             }
-//             ExprKind::MemberAccess { base, field, .. } => {
-//                 prev = self.make_node("mem_acc".to_owned());
-//                 self.visit_expr(*base);
-//                 self.connect_node(field.get().to_owned());
-//             }
-//             ExprKind::SizeofExpr { expr, .. } => {
-//                 prev = self.make_node("sizeof".to_owned());
-//                 self.visit_expr(*expr);
-//             }
-//             ExprKind::SizeofType { ty, .. } => {
-//                 prev = self.make_node("sizeof".to_owned());
-//                 let ty = self.ctx.type_ctx.get_type(*ty);
-//                 self.connect_node(ty.to_code(self.ctx));
-//             }
-//             ExprKind::Unary { op, rhs, .. } => {
-//                 prev = self.make_node(format!("{:?}", op.kind));
-//                 self.visit_expr(*rhs)
-//             }
-//             ExprKind::Assign { op, lhs, rhs, .. } => {
-//                 prev = self.make_node(format!("{:?}", op.kind));
-//                 self.visit_expr(*lhs);
-//                 self.visit_expr(*rhs);
-//             }
-//             ExprKind::Binary { op, lhs, rhs, .. } => {
-//                 prev = self.make_node(format!("{:?}", op.kind));
-//                 self.visit_expr(*lhs);
-//                 self.visit_expr(*rhs);
-//             }
-//             ExprKind::Cast { ty, expr, .. } => {
-//                 prev = self.make_node("cast".to_owned());
-//                 let ty = self.ctx.type_ctx.get_type(*ty);
-//                 self.connect_node(ty.to_code(self.ctx));
-//                 self.visit_expr(*expr)
-//             }
-//             ExprKind::Ternary {
-//                 cond,
-//                 then_expr,
-//                 else_expr,
-//                 ..
-//             } => {
-//                 prev = self.make_node("cond_expr".to_owned());
-//                 self.visit_expr(*cond);
-//                 self.visit_expr(*then_expr);
-//                 self.visit_expr(*else_expr);
-//             }
-//         }
-//         self.current = prev;
-//     }
+    fn ty(&self, ty: TypeKey) -> String {
+        self.ctx.type_ctx.get_type(ty).to_code(self.ctx)
+    }
 
-//     fn visit_stmt(&mut self, stmt: StmtKey) {
-//         let prev;
-//         let stmt = self.ctx.get_stmt(stmt);
-//         match &stmt.kind {
-//             StmtKind::Expr { expr, .. } => {
-//                 prev = self.make_node("expr_stmt".to_owned());
-//                 if let Some(x) = expr {
-//                     self.visit_expr(*x)
-//                 }
-//             }
-//             // StmtKind::Decl { .. } => {}
-//             // StmtKind::Label { .. } => {}
-//             // StmtKind::Case { .. } => {}
-//             // StmtKind::Default { .. } => {}
-//             // StmtKind::IfElse { .. } => {}
-//             // StmtKind::Switch { .. } => {}
-//             // StmtKind::While { .. } => {}
-//             // StmtKind::DoWhile { .. } => {}
-//             // StmtKind::For { .. } => {}
-//             // StmtKind::Goto { .. } => {}
-//             // StmtKind::Continue { .. } => {}
-//             // StmtKind::Break { .. } => {}
-//             StmtKind::Return { expr, .. } => {
-//                 prev = self.make_node("compound_stmt".to_owned());
-//                 if let Some(x) = expr {
-//                     self.visit_expr(*x);
-//                 }
-//             }
-//             StmtKind::Compound { stmts, .. } => {
-//                 prev = self.make_node("compound_stmt".to_owned());
-//                 stmts.iter_mut().for_each(|x| self.visit_stmt(*x));
-//             }
-//             _ => {
-//                 prev = self.make_node("UnImplement".to_owned());
-//             }
-//         }
-//         self.current = prev;
-//     }
-// }
+    /// 顶层声明是否通过过滤
+    fn is_selected(&self, decl: DeclKey) -> bool {
+        let Some(filter) = self.filter else {
+            return true;
+        };
+        let decl = self.ctx.get_decl(decl);
+        decl.name.as_ref().is_some_and(|x| x.symbol.get() == filter)
+    }
+
+    fn visit_opt_expr(&mut self, expr: Option<ExprKey>) {
+        match expr {
+            Some(x) => self.visit_expr(x),
+            None => {
+                self.connect_node("<null>".to_owned());
+            }
+        }
+    }
+
+    fn visit_stmt_inner(&mut self, stmt: &'a Stmt) {
+        use StmtKind::*;
+        let prev = match &stmt.kind {
+            Expr { expr, .. } => {
+                let prev = self.make_node("ExprStmt".to_owned());
+                expr.iter().for_each(|x| self.visit_expr(*x));
+                prev
+            }
+            Decl { decl } => {
+                let prev = self.make_node("DeclStmt".to_owned());
+                decl.decls.iter().for_each(|x| self.visit_decl(*x));
+                prev
+            }
+            Label { ident, stmt } => {
+                let prev = self.make_node(format!("Label\n{}", ident.symbol));
+                self.visit_stmt(*stmt);
+                prev
+            }
+            Case { expr, stmt, .. } => {
+                let prev = self.make_node("Case".to_owned());
+                self.visit_expr(*expr);
+                self.visit_stmt(*stmt);
+                prev
+            }
+            Default { stmt, .. } => {
+                let prev = self.make_node("Default".to_owned());
+                self.visit_stmt(*stmt);
+                prev
+            }
+            IfElse {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let prev = self.make_node("If".to_owned());
+                self.visit_expr(*cond);
+                self.visit_stmt(*then_stmt);
+                else_stmt.iter().for_each(|x| self.visit_stmt(*x));
+                prev
+            }
+            Switch { expr, body, .. } => {
+                let prev = self.make_node("Switch".to_owned());
+                self.visit_expr(*expr);
+                self.visit_stmt(*body);
+                prev
+            }
+            While { cond, body, .. } => {
+                let prev = self.make_node("While".to_owned());
+                self.visit_expr(*cond);
+                self.visit_stmt(*body);
+                prev
+            }
+            DoWhile { body, cond, .. } => {
+                let prev = self.make_node("DoWhile".to_owned());
+                self.visit_stmt(*body);
+                self.visit_expr(*cond);
+                prev
+            }
+            For {
+                init,
+                cond,
+                step,
+                body,
+                ..
+            } => {
+                let prev = self.make_node("For".to_owned());
+                init.iter().for_each(|x| self.visit_stmt(*x));
+                self.visit_opt_expr(*cond);
+                self.visit_opt_expr(*step);
+                self.visit_stmt(*body);
+                prev
+            }
+            Goto { ident } => self.make_node(format!("Goto\n{}", ident.symbol)),
+            Continue { .. } => self.make_node("Continue".to_owned()),
+            Break { .. } => self.make_node("Break".to_owned()),
+            Return { expr, .. } => {
+                let prev = self.make_node("Return".to_owned());
+                expr.iter().for_each(|x| self.visit_expr(*x));
+                prev
+            }
+            Compound { stmts, .. } => {
+                let prev = self.make_node("Compound".to_owned());
+                stmts.iter().for_each(|x| self.visit_stmt(*x));
+                prev
+            }
+        };
+        self.current = prev;
+    }
+}
+
+impl<'a> Visitor<'a> for AstGraph<'a> {
+    fn ctx(&self) -> &'a CompCtx {
+        self.ctx
+    }
+
+    fn walk_translation_unit(&mut self, unit: &'a TranslationUnit) {
+        // 只渲染一个函数时，不需要 TranslationUnit 根节点
+        let prev = match self.filter {
+            Some(_) => self.current,
+            None => self.make_node("TranslationUnit".to_owned()),
+        };
+        for ext_decl in unit {
+            self.walk_external_decl(ext_decl);
+        }
+        self.current = prev;
+    }
+
+    fn walk_func_def(&mut self, decl: &'a FuncDef) {
+        if self.is_selected(decl.decl) {
+            self.visit_decl(decl.decl);
+        }
+    }
+
+    fn walk_decl_group(&mut self, decl_group: &'a DeclGroup) {
+        for x in decl_group.decls.iter().cloned() {
+            if self.is_selected(x) {
+                self.visit_decl(x);
+            }
+        }
+    }
+
+    fn visit_initializer(&mut self, initializer: &'a Initializer) {
+        match initializer {
+            Initializer::Expr(x) => self.visit_expr(*x),
+            Initializer::InitList { inits } => {
+                let prev = self.make_node("InitList".to_owned());
+                inits.inits.iter().for_each(|x| self.visit_initializer(x));
+                self.current = prev;
+            }
+            Initializer::Designated {
+                designators, init, ..
+            } => {
+                let prev = self.make_node("Designated".to_owned());
+                for x in designators {
+                    match x {
+                        MemberDesignator::Field(name) => {
+                            self.connect_node(format!(".{}", name.symbol));
+                        }
+                        MemberDesignator::Index(expr) => self.visit_expr(*expr),
+                    }
+                }
+                self.visit_initializer(init);
+                self.current = prev;
+            }
+        }
+    }
+
+    fn visit_decl(&mut self, key: DeclKey) {
+        use DeclKind::*;
+        let ctx = self.ctx;
+        let decl = ctx.get_decl(key);
+        let name = decl
+            .name
+            .as_ref()
+            .map(|x| x.symbol.get())
+            .unwrap_or_default();
+        let ty = self.ty(decl.ty);
+
+        let label = match &decl.kind {
+            TypeDef => "TypeDef",
+            ParamVar => "ParamVar",
+            VarDecl { .. } => "VarDecl",
+            VarDef { .. } => "VarDef",
+            FuncDecl { .. } => "FuncDecl",
+            FuncDef { .. } => "FuncDef",
+            RecordField { .. } => "RecordField",
+            RecordDecl { .. } => "RecordDecl",
+            RecordDef { .. } => "RecordDef",
+            EnumField { .. } => "EnumField",
+            EnumDecl { .. } => "EnumDecl",
+            EnumDef { .. } => "EnumDef",
+        };
+        let prev = self.make_node(format!("{} {}\n{}", label, name, ty));
+        let node = self.current.expect("decl node just created");
+        self.decl_nodes.insert(key, node);
+
+        match &decl.kind {
+            VarDef { init: Some(init) } => self.visit_initializer(init),
+            FuncDef { params, body, .. } => {
+                params.iter().for_each(|x| self.visit_decl(*x));
+                self.visit_stmt_inner(body)
+            }
+            RecordField {
+                bit_field: Some(x),
+            } => self.visit_expr(*x),
+            RecordDef { fields, .. } => fields
+                .iter()
+                .flat_map(|x| x.decls.iter())
+                .for_each(|x| self.visit_decl(*x)),
+            EnumField { expr: Some(x) } => self.visit_expr(*x),
+            EnumDef { enums: Some(enums) } => enums.iter().for_each(|x| self.visit_decl(*x)),
+            _ => {}
+        }
+        self.current = prev;
+    }
+
+    fn visit_expr(&mut self, key: ExprKey) {
+        use ExprKind::*;
+        let ctx = self.ctx;
+        let expr = ctx.get_expr(key);
+        let ty = self.ty(expr.ty);
+        let label = |kind: String| format!("{}\n{}", kind, ty);
+
+        let prev = match &expr.kind {
+            DeclRef { ident, decl } => {
+                let prev = self.make_node(label(format!("DeclRef {}", ident.symbol)));
+                if let (true, Some(decl)) = (self.decl_refs, decl) {
+                    let node = self.current.expect("expr node just created");
+                    self.pending_refs.push((node, *decl));
+                }
+                prev
+            }
+            Literal(x) => self.make_node(label(format!("Literal {}", literal_code(x)))),
+            ArraySubscript { base, index } => {
+                let prev = self.make_node(label("ArraySubscript".to_owned()));
+                self.visit_expr(*base);
+                self.visit_expr(*index);
+                prev
+            }
+            Call { base, params } => {
+                let prev = self.make_node(label("Call".to_owned()));
+                self.visit_expr(*base);
+                params.exprs.iter().for_each(|x| self.visit_expr(*x));
+                prev
+            }
+            MemberAccess { kind, base, field } => {
+                let op = match kind {
+                    MemberAccessKind::Arrow => "->",
+                    MemberAccessKind::Dot => ".",
+                };
+                let prev = self.make_node(label(format!("MemberAccess {}{}", op, field)));
+                self.visit_expr(*base);
+                prev
+            }
+            SizeofExpr { expr } => {
+                let prev = self.make_node(label("Sizeof".to_owned()));
+                self.visit_expr(*expr);
+                prev
+            }
+            SizeofType { ty } => {
+                let prev = self.make_node(label("Sizeof".to_owned()));
+                self.connect_node(self.ty(*ty));
+                prev
+            }
+            AlignofType { ty } => {
+                let prev = self.make_node(label("Alignof".to_owned()));
+                self.connect_node(self.ty(*ty));
+                prev
+            }
+            Unary { op, rhs } => {
+                let prev = self.make_node(label(format!("Unary {:?}", op.kind)));
+                self.visit_expr(*rhs);
+                prev
+            }
+            Binary { lhs, op, rhs } => {
+                let prev = self.make_node(label(format!("Binary {}", op.kind)));
+                self.visit_expr(*lhs);
+                self.visit_expr(*rhs);
+                prev
+            }
+            Assign { lhs, op, rhs } => {
+                let prev = self.make_node(label(format!("Assign {}", op.kind)));
+                self.visit_expr(*lhs);
+                self.visit_expr(*rhs);
+                prev
+            }
+            Cast { expr, .. } => {
+                let prev = self.make_node(label("Cast".to_owned()));
+                self.visit_expr(*expr);
+                prev
+            }
+            CompoundLiteral { init, .. } => {
+                let prev = self.make_node(label("CompoundLiteral".to_owned()));
+                self.visit_initializer(init);
+                prev
+            }
+            Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => {
+                let prev = self.make_node(label("Ternary".to_owned()));
+                self.visit_expr(*cond);
+                self.visit_expr(*then_expr);
+                self.visit_expr(*else_expr);
+                prev
+            }
+            BuiltinCall { builtin, args } => {
+                let prev = self.make_node(label(format!("BuiltinCall {}", builtin.name())));
+                for arg in args.iter() {
+                    match arg {
+                        BuiltinArg::Type(ty) => {
+                            self.connect_node(self.ty(*ty));
+                        }
+                        _ => arg.exprs().into_iter().for_each(|x| self.visit_expr(x)),
+                    }
+                }
+                prev
+            }
+        };
+        self.current = prev;
+    }
+
+    fn visit_stmt(&mut self, key: StmtKey) {
+        let stmt = self.ctx.get_stmt(key);
+        self.visit_stmt_inner(stmt);
+    }
+}
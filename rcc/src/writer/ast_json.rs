@@ -0,0 +1,1008 @@
+use crate::content_manager::ContentManager;
+use crate::parser::ast::common::RecordKind;
+use crate::parser::ast::decls::decl::{Decl, DeclKind};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{
+    BuiltinArg, Constant, ExprKind, MemberAccessKind, MemberDesignator,
+};
+use crate::parser::ast::func::{ExternalDecl, TranslationUnit};
+use crate::parser::ast::stmt::{Stmt, StmtKind};
+use crate::parser::ast::types::{ArraySize, TypeKind};
+use crate::parser::ast::visitor::Visitor;
+use crate::parser::ast::{DeclKey, ExprKey, StmtKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::parser::sema::expr::value_type::ValueType;
+use crate::types::span::Span;
+use crate::writer::c_printer::literal_code;
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+
+/// JSON 格式版本，格式有不兼容的修改时递增
+pub const AST_JSON_VERSION: u32 = 2;
+
+///
+/// AST 转 JSON，给外部工具使用
+///
+/// 输出结构：
+/// - `version`: `AST_JSON_VERSION`
+/// - `files`: 源文件，`span` 中的 `file` 是它的下标，0 是主文件
+/// - `unit`: 顶层声明 id
+/// - `decls` `stmts` `exprs` `types` `scopes`: 扁平的表，下标就是 id，节点之间通过 id 引用
+///
+/// id 按照 AST 先序遍历的顺序分配，类型按首次引用的顺序分配，
+/// 与 `SlotMap` 中的 key 无关，所以相同的输入得到相同的输出，diff 干净
+///
+/// span 是源文件中的位置 `{file, begin: [行, 列], end: [行, 列]}`，`end` 不包含，
+/// 由 `content` 的源码映射得到；宏展开的结果位于展开处的宏名
+///
+pub fn to_json(ctx: &CompCtx, content: &ContentManager, unit: &TranslationUnit) -> String {
+    let mut numbering = Numbering::new(ctx);
+    numbering.walk_translation_unit(unit);
+
+    let mut builder = JsonBuilder {
+        ctx,
+        content,
+        types: TypeTable::default(),
+        numbering: &numbering,
+    };
+
+    let decls = numbering
+        .decls
+        .iter()
+        .enumerate()
+        .map(|(id, key)| builder.decl(id, *key))
+        .collect();
+    let stmts = numbering
+        .stmts
+        .iter()
+        .enumerate()
+        .map(|(id, stmt)| builder.stmt(id, stmt))
+        .collect();
+    let exprs = numbering
+        .exprs
+        .iter()
+        .enumerate()
+        .map(|(id, key)| builder.expr(id, *key))
+        .collect();
+    let unit = numbering.unit.clone();
+    let types = builder.finish();
+
+    let ast = JsonAst {
+        version: AST_JSON_VERSION,
+        files: content.files().to_vec(),
+        unit,
+        decls,
+        stmts,
+        exprs,
+        types,
+        scopes: numbering.scopes,
+    };
+    serde_json::to_string_pretty(&ast).expect("ast json serialize failed")
+}
+
+#[derive(Serialize)]
+struct JsonAst {
+    version: u32,
+    files: Vec<String>,
+    unit: Vec<usize>,
+    decls: Vec<JsonDecl>,
+    stmts: Vec<JsonStmt>,
+    exprs: Vec<JsonExpr>,
+    types: Vec<JsonType>,
+    scopes: Vec<JsonScope>,
+}
+
+#[derive(Serialize)]
+struct JsonDecl {
+    id: usize,
+    #[serde(flatten)]
+    kind: JsonDeclKind,
+    name: Option<String>,
+    storage: Option<String>,
+    thread_local: bool,
+    func_spec: Option<&'static str>,
+    noreturn: bool,
+    visibility: Option<&'static str>,
+    align: Option<usize>,
+    #[serde(rename = "type")]
+    ty: usize,
+    scope: usize,
+    span: JsonSpan,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonDeclKind {
+    TypeDef,
+    ParamVar,
+    VarDecl {
+        def: Option<usize>,
+    },
+    VarDef {
+        init: Option<JsonInit>,
+    },
+    FuncDecl {
+        def: Option<usize>,
+    },
+    FuncDef {
+        inline: bool,
+        params: Vec<usize>,
+        body: usize,
+    },
+    RecordField {
+        bit_field: Option<usize>,
+    },
+    RecordDecl {
+        tag: &'static str,
+        def: Option<usize>,
+    },
+    RecordDef {
+        tag: &'static str,
+        fields: Vec<usize>,
+    },
+    EnumField {
+        expr: Option<usize>,
+        value: Option<JsonConstant>,
+    },
+    EnumDecl {
+        def: Option<usize>,
+    },
+    EnumDef {
+        enums: Option<Vec<usize>>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonInit {
+    Expr { expr: usize },
+    InitList { inits: Vec<JsonInit>, span: JsonSpan },
+    Designated {
+        designators: Vec<JsonDesignator>,
+        init: Box<JsonInit>,
+        span: JsonSpan,
+    },
+}
+
+/// 源文件中的区间，`begin` `end` 是 `[行, 列]`
+#[derive(Serialize)]
+struct JsonSpan {
+    file: usize,
+    begin: [usize; 2],
+    end: [usize; 2],
+}
+
+#[derive(Serialize)]
+struct JsonStmt {
+    id: usize,
+    #[serde(flatten)]
+    kind: JsonStmtKind,
+    span: JsonSpan,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonStmtKind {
+    Expr {
+        expr: Option<usize>,
+    },
+    Decl {
+        decls: Vec<usize>,
+    },
+    Label {
+        label: String,
+        stmt: usize,
+    },
+    Case {
+        expr: usize,
+        stmt: usize,
+    },
+    Default {
+        stmt: usize,
+    },
+    IfElse {
+        cond: usize,
+        then_stmt: usize,
+        else_stmt: Option<usize>,
+    },
+    Switch {
+        expr: usize,
+        body: usize,
+    },
+    While {
+        cond: usize,
+        body: usize,
+    },
+    DoWhile {
+        body: usize,
+        cond: usize,
+    },
+    For {
+        /// 语句 id
+        init: Option<usize>,
+        cond: Option<usize>,
+        step: Option<usize>,
+        body: usize,
+    },
+    Goto {
+        label: String,
+    },
+    Continue,
+    Break,
+    Return {
+        expr: Option<usize>,
+    },
+    Compound {
+        stmts: Vec<usize>,
+        scope: Option<usize>,
+    },
+}
+
+#[derive(Serialize)]
+struct JsonExpr {
+    id: usize,
+    #[serde(flatten)]
+    kind: JsonExprKind,
+    #[serde(rename = "type")]
+    ty: usize,
+    value_category: &'static str,
+    value: Option<JsonConstant>,
+    span: JsonSpan,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonExprKind {
+    DeclRef {
+        name: String,
+        decl: Option<usize>,
+    },
+    Literal {
+        literal: &'static str,
+        spelling: String,
+    },
+    ArraySubscript {
+        base: usize,
+        index: usize,
+    },
+    Call {
+        base: usize,
+        args: Vec<usize>,
+    },
+    MemberAccess {
+        op: &'static str,
+        base: usize,
+        field: String,
+    },
+    SizeofExpr {
+        expr: usize,
+    },
+    SizeofType {
+        #[serde(rename = "type_operand")]
+        ty: usize,
+    },
+    AlignofType {
+        #[serde(rename = "type_operand")]
+        ty: usize,
+    },
+    Unary {
+        op: String,
+        postfix: bool,
+        rhs: usize,
+    },
+    Binary {
+        op: String,
+        lhs: usize,
+        rhs: usize,
+    },
+    Assign {
+        op: String,
+        lhs: usize,
+        rhs: usize,
+    },
+    Cast {
+        #[serde(rename = "type_operand")]
+        ty: usize,
+        expr: usize,
+    },
+    CompoundLiteral {
+        #[serde(rename = "type_operand")]
+        ty: usize,
+        init: JsonInit,
+        file_scope: bool,
+    },
+    Ternary {
+        cond: usize,
+        then_expr: usize,
+        else_expr: usize,
+    },
+    BuiltinCall {
+        builtin: &'static str,
+        args: Vec<JsonBuiltinArg>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonBuiltinArg {
+    Expr {
+        expr: usize,
+    },
+    Type {
+        #[serde(rename = "type_operand")]
+        ty: usize,
+    },
+    Member {
+        designators: Vec<JsonDesignator>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonDesignator {
+    Field { name: String },
+    Index { expr: usize },
+}
+
+/// 常量，整数和浮点数以字符串保存，避免精度丢失
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonConstant {
+    Integer { value: String },
+    Float { value: String },
+    String { bytes: Vec<u8> },
+}
+
+#[derive(Serialize)]
+struct JsonType {
+    id: usize,
+    #[serde(flatten)]
+    kind: JsonTypeKind,
+    #[serde(rename = "const")]
+    is_const: bool,
+    #[serde(rename = "volatile")]
+    is_volatile: bool,
+    #[serde(rename = "restrict")]
+    is_restrict: bool,
+    spelling: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum JsonTypeKind {
+    Void,
+    Integer {
+        signed: bool,
+        size: String,
+    },
+    Floating {
+        size: String,
+    },
+    Pointer {
+        elem: usize,
+    },
+    Array {
+        elem: usize,
+        /// `static` `vla` `incomplete`
+        size_kind: &'static str,
+        len: Option<usize>,
+    },
+    Function {
+        ret: usize,
+        params: Vec<usize>,
+        variadic: bool,
+        prototype: bool,
+    },
+    Record {
+        tag: &'static str,
+        def: Option<usize>,
+    },
+    Enum {
+        def: Option<usize>,
+    },
+    Unknown,
+}
+
+/// 作用域，parse 结束后 `ScopeMgr` 中的作用域已经弹出，这里按照 AST 结构重建
+#[derive(Serialize)]
+struct JsonScope {
+    id: usize,
+    kind: &'static str,
+    parent: Option<usize>,
+    decls: Vec<usize>,
+}
+
+///
+/// 第一遍：按照先序遍历给节点编号，同时重建作用域
+///
+/// # Members
+/// - `unit`: 顶层声明
+/// - `decls` `stmts` `exprs`: 编号到节点，函数体不在 stmt 池中所以 stmt 直接保存引用
+/// - `*_ids`: 节点到编号
+/// - `scope_stack`: 当前作用域栈
+/// - `pushed`: 进入节点时是否创建了作用域，离开时据此弹出
+///
+struct Numbering<'a> {
+    ctx: &'a CompCtx,
+    unit: Vec<usize>,
+    decls: Vec<DeclKey>,
+    stmts: Vec<&'a Stmt>,
+    exprs: Vec<ExprKey>,
+    decl_ids: FxHashMap<DeclKey, usize>,
+    stmt_ids: FxHashMap<StmtKey, usize>,
+    body_ids: FxHashMap<*const Stmt, usize>,
+    expr_ids: FxHashMap<ExprKey, usize>,
+    decl_scopes: Vec<usize>,
+    compound_scopes: FxHashMap<usize, usize>,
+    scopes: Vec<JsonScope>,
+    scope_stack: Vec<usize>,
+    pushed: Vec<bool>,
+}
+
+impl<'a> Numbering<'a> {
+    fn new(ctx: &'a CompCtx) -> Self {
+        let mut numbering = Self {
+            ctx,
+            unit: Vec::new(),
+            decls: Vec::new(),
+            stmts: Vec::new(),
+            exprs: Vec::new(),
+            decl_ids: FxHashMap::default(),
+            stmt_ids: FxHashMap::default(),
+            body_ids: FxHashMap::default(),
+            expr_ids: FxHashMap::default(),
+            decl_scopes: Vec::new(),
+            compound_scopes: FxHashMap::default(),
+            scopes: Vec::new(),
+            scope_stack: Vec::new(),
+            pushed: Vec::new(),
+        };
+        numbering.push_scope("file");
+        numbering
+    }
+
+    fn push_scope(&mut self, kind: &'static str) -> usize {
+        let id = self.scopes.len();
+        self.scopes.push(JsonScope {
+            id,
+            kind,
+            parent: self.scope_stack.last().cloned(),
+            decls: Vec::new(),
+        });
+        self.scope_stack.push(id);
+        id
+    }
+
+    fn push_stmt(&mut self, stmt: &'a Stmt) -> usize {
+        let id = self.stmts.len();
+        self.stmts.push(stmt);
+        id
+    }
+}
+
+impl<'a> Visitor<'a> for Numbering<'a> {
+    fn ctx(&self) -> &'a CompCtx {
+        self.ctx
+    }
+
+    fn walk_translation_unit(&mut self, unit: &'a TranslationUnit) {
+        for ext_decl in unit {
+            self.walk_external_decl(ext_decl);
+            let keys = match ext_decl {
+                ExternalDecl::FunctionDefinition(x) => std::slice::from_ref(&x.decl),
+                ExternalDecl::Declaration(x) => x.decls.as_slice(),
+            };
+            let ids = keys.iter().map(|x| self.decl_ids[x]);
+            self.unit.extend(ids);
+        }
+    }
+
+    fn visit_func_body(&mut self, body: &'a Stmt) {
+        // 函数体与参数共用 function 作用域，不再创建 block 作用域
+        let id = self.push_stmt(body);
+        self.body_ids.insert(body as *const Stmt, id);
+        self.walk_stmt_kind(&body.kind);
+    }
+
+    fn pre_decl(&mut self, key: DeclKey) -> bool {
+        if self.decl_ids.contains_key(&key) {
+            return false;
+        }
+        let id = self.decls.len();
+        self.decls.push(key);
+        self.decl_ids.insert(key, id);
+
+        let scope = *self.scope_stack.last().expect("file scope never pop");
+        self.decl_scopes.push(scope);
+        self.scopes[scope].decls.push(id);
+
+        let kind = match &self.ctx.get_decl(key).kind {
+            DeclKind::FuncDef { .. } => Some("function"),
+            DeclKind::RecordDef { .. } => Some("record"),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            self.push_scope(kind);
+        }
+        self.pushed.push(kind.is_some());
+        true
+    }
+
+    fn post_decl(&mut self, _decl: DeclKey) {
+        if self.pushed.pop().expect("pre_decl pushed") {
+            self.scope_stack.pop();
+        }
+    }
+
+    fn pre_stmt(&mut self, key: StmtKey) -> bool {
+        let stmt = self.ctx.get_stmt(key);
+        let id = self.push_stmt(stmt);
+        self.stmt_ids.insert(key, id);
+        let is_compound = stmt.kind.is_compound();
+        if is_compound {
+            let scope = self.push_scope("block");
+            self.compound_scopes.insert(id, scope);
+        }
+        self.pushed.push(is_compound);
+        true
+    }
+
+    fn post_stmt(&mut self, _stmt: StmtKey) {
+        if self.pushed.pop().expect("pre_stmt pushed") {
+            self.scope_stack.pop();
+        }
+    }
+
+    fn pre_expr(&mut self, key: ExprKey) -> bool {
+        self.expr_ids.insert(key, self.exprs.len());
+        self.exprs.push(key);
+        true
+    }
+}
+
+/// 类型表，按首次引用的顺序编号
+#[derive(Default)]
+struct TypeTable {
+    ids: FxHashMap<TypeKey, usize>,
+    keys: Vec<TypeKey>,
+}
+
+///
+/// 第二遍：把节点转换成 JSON 结构
+///
+struct JsonBuilder<'a> {
+    ctx: &'a CompCtx,
+    content: &'a ContentManager,
+    types: TypeTable,
+    numbering: &'a Numbering<'a>,
+}
+
+impl<'a> JsonBuilder<'a> {
+    fn span(&self, span: Span) -> JsonSpan {
+        let begin = self.content.location(span.start);
+        let end = self.content.end_location(span);
+        JsonSpan {
+            file: begin.file,
+            begin: [begin.line, begin.col],
+            end: [end.line, end.col],
+        }
+    }
+
+    fn decl_id(&self, key: DeclKey) -> usize {
+        self.numbering.decl_ids[&key]
+    }
+
+    /// 被引用的声明可能不在 AST 中（如只在 sema 中出现），这时为 null
+    fn decl_ref(&self, key: Option<DeclKey>) -> Option<usize> {
+        key.and_then(|x| self.numbering.decl_ids.get(&x).cloned())
+    }
+
+    fn stmt_id(&self, key: StmtKey) -> usize {
+        self.numbering.stmt_ids[&key]
+    }
+
+    fn expr_id(&self, key: ExprKey) -> usize {
+        self.numbering.expr_ids[&key]
+    }
+
+    fn type_id(&mut self, key: TypeKey) -> usize {
+        if let Some(id) = self.types.ids.get(&key) {
+            return *id;
+        }
+        let id = self.types.keys.len();
+        self.types.ids.insert(key, id);
+        self.types.keys.push(key);
+        id
+    }
+
+    fn decl(&mut self, id: usize, key: DeclKey) -> JsonDecl {
+        use DeclKind::*;
+        let ctx = self.ctx;
+        let decl: &Decl = ctx.get_decl(key);
+        let kind = match &decl.kind {
+            TypeDef => JsonDeclKind::TypeDef,
+            ParamVar => JsonDeclKind::ParamVar,
+            VarDecl { def } => JsonDeclKind::VarDecl {
+                def: self.decl_ref(*def),
+            },
+            VarDef { init } => JsonDeclKind::VarDef {
+                init: init.as_ref().map(|x| self.initializer(x)),
+            },
+            FuncDecl { def } => JsonDeclKind::FuncDecl {
+                def: self.decl_ref(*def),
+            },
+            FuncDef {
+                inline,
+                params,
+                body,
+            } => JsonDeclKind::FuncDef {
+                inline: inline.is_some(),
+                params: params.iter().map(|x| self.decl_id(*x)).collect(),
+                body: self.numbering.body_ids[&(body.as_ref() as *const Stmt)],
+            },
+            RecordField { bit_field } => JsonDeclKind::RecordField {
+                bit_field: bit_field.map(|x| self.expr_id(x)),
+            },
+            RecordDecl { kind, def } => JsonDeclKind::RecordDecl {
+                tag: record_tag(&kind.kind),
+                def: self.decl_ref(*def),
+            },
+            RecordDef { kind, fields } => JsonDeclKind::RecordDef {
+                tag: record_tag(&kind.kind),
+                fields: fields
+                    .iter()
+                    .flat_map(|x| x.decls.iter())
+                    .map(|x| self.decl_id(*x))
+                    .collect(),
+            },
+            EnumField { expr } => JsonDeclKind::EnumField {
+                expr: expr.map(|x| self.expr_id(x)),
+                value: expr.and_then(|x| ctx.get_expr(x).value.as_ref().map(constant)),
+            },
+            EnumDecl { def } => JsonDeclKind::EnumDecl {
+                def: self.decl_ref(*def),
+            },
+            EnumDef { enums } => JsonDeclKind::EnumDef {
+                enums: enums
+                    .as_ref()
+                    .map(|x| x.iter().map(|x| self.decl_id(*x)).collect()),
+            },
+        };
+
+        JsonDecl {
+            id,
+            kind,
+            name: decl.name.as_ref().map(|x| x.symbol.get().to_owned()),
+            storage: decl.storage.as_ref().map(|x| x.to_string()),
+            thread_local: decl.thread_local.is_some(),
+            func_spec: decl.func_spec.as_ref().map(|x| x.kind.name()),
+            noreturn: decl.noreturn.is_some(),
+            visibility: decl.visibility.as_ref().map(|x| x.kind.name()),
+            align: decl.align.map(|x| x.align),
+            ty: self.type_id(decl.ty),
+            scope: self.numbering.decl_scopes[id],
+            span: self.span(decl.span),
+        }
+    }
+
+    fn initializer(&self, init: &Initializer) -> JsonInit {
+        match init {
+            Initializer::Expr(x) => JsonInit::Expr {
+                expr: self.expr_id(*x),
+            },
+            Initializer::InitList { inits } => JsonInit::InitList {
+                inits: inits.inits.iter().map(|x| self.initializer(x)).collect(),
+                span: self.span(inits.span),
+            },
+            Initializer::Designated {
+                designators,
+                init,
+                span: x,
+            } => JsonInit::Designated {
+                designators: self.designators(designators),
+                init: Box::new(self.initializer(init)),
+                span: self.span(*x),
+            },
+        }
+    }
+
+    fn stmt(&mut self, id: usize, stmt: &Stmt) -> JsonStmt {
+        use StmtKind::*;
+        let kind = match &stmt.kind {
+            Expr { expr, .. } => JsonStmtKind::Expr {
+                expr: expr.map(|x| self.expr_id(x)),
+            },
+            Decl { decl } => JsonStmtKind::Decl {
+                decls: decl.decls.iter().map(|x| self.decl_id(*x)).collect(),
+            },
+            Label { ident, stmt } => JsonStmtKind::Label {
+                label: ident.symbol.get().to_owned(),
+                stmt: self.stmt_id(*stmt),
+            },
+            Case { expr, stmt, .. } => JsonStmtKind::Case {
+                expr: self.expr_id(*expr),
+                stmt: self.stmt_id(*stmt),
+            },
+            Default { stmt, .. } => JsonStmtKind::Default {
+                stmt: self.stmt_id(*stmt),
+            },
+            IfElse {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => JsonStmtKind::IfElse {
+                cond: self.expr_id(*cond),
+                then_stmt: self.stmt_id(*then_stmt),
+                else_stmt: else_stmt.map(|x| self.stmt_id(x)),
+            },
+            Switch { expr, body, .. } => JsonStmtKind::Switch {
+                expr: self.expr_id(*expr),
+                body: self.stmt_id(*body),
+            },
+            While { cond, body, .. } => JsonStmtKind::While {
+                cond: self.expr_id(*cond),
+                body: self.stmt_id(*body),
+            },
+            DoWhile { body, cond, .. } => JsonStmtKind::DoWhile {
+                body: self.stmt_id(*body),
+                cond: self.expr_id(*cond),
+            },
+            For {
+                init,
+                cond,
+                step,
+                body,
+                ..
+            } => JsonStmtKind::For {
+                init: init.map(|x| self.stmt_id(x)),
+                cond: cond.map(|x| self.expr_id(x)),
+                step: step.map(|x| self.expr_id(x)),
+                body: self.stmt_id(*body),
+            },
+            Goto { ident } => JsonStmtKind::Goto {
+                label: ident.symbol.get().to_owned(),
+            },
+            Continue { .. } => JsonStmtKind::Continue,
+            Break { .. } => JsonStmtKind::Break,
+            Return { expr, .. } => JsonStmtKind::Return {
+                expr: expr.map(|x| self.expr_id(x)),
+            },
+            Compound { stmts, .. } => JsonStmtKind::Compound {
+                stmts: stmts.iter().map(|x| self.stmt_id(*x)).collect(),
+                // 函数体没有自己的作用域，使用 function 作用域
+                scope: self.numbering.compound_scopes.get(&id).cloned(),
+            },
+        };
+
+        JsonStmt {
+            id,
+            kind,
+            span: self.span(stmt.span),
+        }
+    }
+
+    fn expr(&mut self, id: usize, key: ExprKey) -> JsonExpr {
+        use ExprKind::*;
+        let ctx = self.ctx;
+        let expr = ctx.get_expr(key);
+        let kind = match &expr.kind {
+            DeclRef { ident, decl } => JsonExprKind::DeclRef {
+                name: ident.symbol.get().to_owned(),
+                decl: self.decl_ref(*decl),
+            },
+            Literal(lit) => {
+                use crate::lex::types::token_kind::LiteralKind;
+                let literal = match lit {
+                    LiteralKind::Integer { .. } => "integer",
+                    LiteralKind::Float { .. } => "float",
+                    LiteralKind::Char { .. } => "char",
+                    LiteralKind::String { .. } => "string",
+                };
+                JsonExprKind::Literal {
+                    literal,
+                    spelling: literal_code(lit),
+                }
+            }
+            ArraySubscript { base, index } => JsonExprKind::ArraySubscript {
+                base: self.expr_id(*base),
+                index: self.expr_id(*index),
+            },
+            Call { base, params } => JsonExprKind::Call {
+                base: self.expr_id(*base),
+                args: params.exprs.iter().map(|x| self.expr_id(*x)).collect(),
+            },
+            MemberAccess { kind, base, field } => JsonExprKind::MemberAccess {
+                op: match kind {
+                    MemberAccessKind::Arrow => "->",
+                    MemberAccessKind::Dot => ".",
+                },
+                base: self.expr_id(*base),
+                field: field.get().to_owned(),
+            },
+            SizeofExpr { expr } => JsonExprKind::SizeofExpr {
+                expr: self.expr_id(*expr),
+            },
+            SizeofType { ty } => JsonExprKind::SizeofType {
+                ty: self.type_id(*ty),
+            },
+            AlignofType { ty } => JsonExprKind::AlignofType {
+                ty: self.type_id(*ty),
+            },
+            Unary { op, rhs } => JsonExprKind::Unary {
+                op: op.kind.to_string(),
+                postfix: op.kind.is_postfix(),
+                rhs: self.expr_id(*rhs),
+            },
+            Binary { lhs, op, rhs } => JsonExprKind::Binary {
+                op: op.kind.to_string(),
+                lhs: self.expr_id(*lhs),
+                rhs: self.expr_id(*rhs),
+            },
+            Assign { lhs, op, rhs } => JsonExprKind::Assign {
+                op: op.kind.to_string(),
+                lhs: self.expr_id(*lhs),
+                rhs: self.expr_id(*rhs),
+            },
+            Cast { ty, expr } => JsonExprKind::Cast {
+                ty: self.type_id(*ty),
+                expr: self.expr_id(*expr),
+            },
+            CompoundLiteral {
+                ty,
+                init,
+                file_scope,
+            } => JsonExprKind::CompoundLiteral {
+                ty: self.type_id(*ty),
+                init: self.initializer(init),
+                file_scope: *file_scope,
+            },
+            Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => JsonExprKind::Ternary {
+                cond: self.expr_id(*cond),
+                then_expr: self.expr_id(*then_expr),
+                else_expr: self.expr_id(*else_expr),
+            },
+            BuiltinCall { builtin, args } => JsonExprKind::BuiltinCall {
+                builtin: builtin.name(),
+                args: args.iter().map(|x| self.builtin_arg(x)).collect(),
+            },
+        };
+        let value_category = match ValueType::of(expr) {
+            ValueType::LValue => "lvalue",
+            ValueType::RValue => "rvalue",
+        };
+
+        JsonExpr {
+            id,
+            kind,
+            ty: self.type_id(expr.ty),
+            value_category,
+            value: expr.value.as_ref().map(constant),
+            span: self.span(expr.span),
+        }
+    }
+
+    fn builtin_arg(&mut self, arg: &BuiltinArg) -> JsonBuiltinArg {
+        match arg {
+            BuiltinArg::Expr(x) => JsonBuiltinArg::Expr {
+                expr: self.expr_id(*x),
+            },
+            BuiltinArg::Type(x) => JsonBuiltinArg::Type {
+                ty: self.type_id(*x),
+            },
+            BuiltinArg::Member(designators) => JsonBuiltinArg::Member {
+                designators: self.designators(designators),
+            },
+        }
+    }
+
+    fn designators(&self, designators: &[MemberDesignator]) -> Vec<JsonDesignator> {
+        designators
+            .iter()
+            .map(|x| match x {
+                MemberDesignator::Field(x) => JsonDesignator::Field {
+                    name: x.symbol.get().to_owned(),
+                },
+                MemberDesignator::Index(x) => JsonDesignator::Index {
+                    expr: self.expr_id(*x),
+                },
+            })
+            .collect()
+    }
+
+    /// 输出类型表，类型之间的引用也会分配编号，所以表会在遍历过程中增长
+    fn finish(mut self) -> Vec<JsonType> {
+        let mut types = Vec::new();
+        let mut id = 0;
+        while id < self.types.keys.len() {
+            let key = self.types.keys[id];
+            types.push(self.ty(id, key));
+            id += 1;
+        }
+        types
+    }
+
+    fn ty(&mut self, id: usize, key: TypeKey) -> JsonType {
+        use TypeKind::*;
+        let ctx = self.ctx;
+        let ty = ctx.type_ctx.get_type(key);
+        let kind = match &ty.kind {
+            Void => JsonTypeKind::Void,
+            Integer { is_signed, size } => JsonTypeKind::Integer {
+                signed: *is_signed,
+                size: size.to_string(),
+            },
+            Floating { size } => JsonTypeKind::Floating {
+                size: size.to_string(),
+            },
+            Pointer { elem_ty } => JsonTypeKind::Pointer {
+                elem: self.type_id(*elem_ty),
+            },
+            Array { elem_ty, size } => {
+                let (size_kind, len) = match size {
+                    ArraySize::Static(x) => ("static", Some(*x)),
+                    ArraySize::VLA => ("vla", None),
+                    ArraySize::Incomplete => ("incomplete", None),
+                };
+                JsonTypeKind::Array {
+                    elem: self.type_id(*elem_ty),
+                    size_kind,
+                    len,
+                }
+            }
+            Function {
+                ret_ty,
+                params,
+                is_variadic,
+                has_prototype,
+            } => JsonTypeKind::Function {
+                ret: self.type_id(*ret_ty),
+                params: params.iter().map(|x| self.type_id(*x)).collect(),
+                variadic: *is_variadic,
+                prototype: *has_prototype,
+            },
+            Record { kind, def, .. } => JsonTypeKind::Record {
+                tag: record_tag(kind),
+                def: self.decl_ref(*def),
+            },
+            Enum { def, .. } => JsonTypeKind::Enum {
+                def: self.decl_ref(*def),
+            },
+            Unknown => JsonTypeKind::Unknown,
+        };
+
+        JsonType {
+            id,
+            kind,
+            is_const: ty.qual.is_const,
+            is_volatile: ty.qual.is_volatile,
+            is_restrict: ty.qual.is_restrict,
+            spelling: ty.to_code(ctx),
+        }
+    }
+}
+
+fn record_tag(kind: &RecordKind) -> &'static str {
+    match kind {
+        RecordKind::Struct => "struct",
+        RecordKind::Union => "union",
+    }
+}
+
+fn constant(value: &Constant) -> JsonConstant {
+    match value {
+        Constant::Intager { value } => JsonConstant::Integer {
+            value: value.to_string(),
+        },
+        Constant::Float { value } => JsonConstant::Float {
+            value: value.to_string(),
+        },
+        Constant::String { value } => JsonConstant::String {
+            bytes: value.clone(),
+        },
+    }
+}
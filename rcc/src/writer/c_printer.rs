@@ -0,0 +1,661 @@
+use crate::lex::types::token_kind::{FloatSuffix, IntSuffix, LiteralKind};
+use crate::parser::ast::common::RecordKind;
+use crate::parser::ast::decls::decl::{Decl, DeclGroup, DeclKind};
+use crate::parser::ast::decls::initializer::Initializer;
+use crate::parser::ast::exprs::{
+    BinOpKind, BuiltinArg, ExprKind, MemberAccessKind, MemberDesignator,
+};
+use crate::parser::ast::func::{ExternalDecl, TranslationUnit};
+use crate::parser::ast::stmt::{Stmt, StmtKind};
+use crate::parser::ast::types::{Type, TypeKind};
+use crate::parser::ast::{DeclKey, ExprKey, StmtKey, TypeKey};
+use crate::parser::comp_ctx::CompCtx;
+use crate::types::span::Span;
+use rustc_hash::FxHashMap;
+
+/// 括号风格
+/// - `Minimal`: 只在优先级 / 结合性需要时加括号
+/// - `Full`: 所有非基本表达式的操作数都加括号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParenStyle {
+    #[default]
+    Minimal,
+    Full,
+}
+
+/// 表达式优先级，数值越大越紧
+mod prec {
+    pub const COMMA: u8 = 1;
+    pub const ASSIGN: u8 = 2;
+    pub const TERNARY: u8 = 3;
+    pub const LOGIC_OR: u8 = 4;
+    pub const UNARY: u8 = 14;
+    pub const POSTFIX: u8 = 15;
+    pub const PRIMARY: u8 = 16;
+}
+
+///
+/// AST 还原为 C 代码，保证 parse → print → parse 得到等价的 AST
+///
+/// # Members
+/// - `ctx`: 编译上下文
+/// - `parens`: 括号风格
+/// - `indent`: 当前缩进层级
+/// - `out`: 输出
+/// - `typedef_names`: 匿名 record / enum 的 typedef 名字，之后的声明通过名字引用同一个类型
+///
+pub struct CPrinter<'a> {
+    ctx: &'a CompCtx,
+    parens: ParenStyle,
+    indent: usize,
+    out: String,
+    typedef_names: FxHashMap<DeclKey, &'a str>,
+}
+
+impl<'a> CPrinter<'a> {
+    pub fn new(ctx: &'a CompCtx, parens: ParenStyle) -> Self {
+        Self {
+            ctx,
+            parens,
+            indent: 0,
+            out: String::new(),
+            typedef_names: FxHashMap::default(),
+        }
+    }
+
+    /// 输出整个翻译单元
+    pub fn print_unit(mut self, unit: &TranslationUnit) -> String {
+        for ext_decl in unit {
+            match ext_decl {
+                ExternalDecl::FunctionDefinition(x) => self.decl(x.decl),
+                ExternalDecl::Declaration(x) => self.decl_group(x),
+            }
+        }
+        self.out
+    }
+
+    fn line(&mut self, code: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(code);
+        self.out.push('\n');
+    }
+
+    /// 多个变量的声明组合并成一条声明输出，否则 parse 回来的 AST 不同
+    fn decl_group(&mut self, group: &DeclGroup) {
+        let is_vars = group.decls.iter().all(|x| {
+            let kind = &self.ctx.get_decl(*x).kind;
+            matches!(kind, DeclKind::VarDecl { .. } | DeclKind::VarDef { .. })
+        });
+        match is_vars && group.decls.len() > 1 {
+            true => {
+                let code = self.var_group_code(group);
+                self.line(&format!("{};", code));
+            }
+            false => group.decls.iter().for_each(|x| self.decl(*x)),
+        }
+    }
+
+    /// 声明说明符，匿名 record / enum 使用 typedef 名字，没有时把定义内联进去，否则无法还原
+    fn base_code(&self, ty: &Type) -> String {
+        match &ty.kind {
+            TypeKind::Record { def: Some(def), .. } | TypeKind::Enum { def: Some(def), .. }
+                if self.ctx.get_decl(*def).name.is_none() =>
+            {
+                match self.typedef_names.get(def) {
+                    Some(name) => format!("{}{}", ty.qual.to_code(), name),
+                    None => format!("{}{}", ty.qual.to_code(), self.tag_body(*def)),
+                }
+            }
+            _ => ty.base_code(self.ctx),
+        }
+    }
+
+    /// 在 `span` 内定义的具名 record / enum，如 `struct A { struct B { int b; } b; }` 中的 `struct B`
+    fn defined_tag(&self, ty: &Type, span: Span) -> Option<DeclKey> {
+        let def = match &ty.kind {
+            TypeKind::Record { def: Some(def), .. } | TypeKind::Enum { def: Some(def), .. } => *def,
+            _ => return None,
+        };
+        let decl = self.ctx.get_decl(def);
+        let inside = span.start <= decl.span.start && decl.span.end <= span.end;
+        (decl.name.is_some() && inside).then_some(def)
+    }
+
+    /// 完整声明 `base declarator`
+    fn decl_code(&self, ty: TypeKey, name: &str) -> String {
+        let ty = self.ctx.type_ctx.get_type(ty);
+        let (base, declarator) = ty.declarator_code(self.ctx, name);
+        let base = self.base_code(base);
+        match declarator.is_empty() {
+            true => base,
+            false => format!("{} {}", base, declarator),
+        }
+    }
+
+    /// record / enum 定义体，单行输出，如 `struct A { int a; }`
+    fn tag_body(&self, def: DeclKey) -> String {
+        let decl = self.ctx.get_decl(def);
+        let name = decl
+            .name
+            .as_ref()
+            .map(|x| format!(" {}", x.symbol))
+            .unwrap_or_default();
+
+        match &decl.kind {
+            DeclKind::RecordDef { kind, fields } => {
+                let kw = match kind.kind {
+                    RecordKind::Struct => "struct",
+                    RecordKind::Union => "union",
+                };
+                let fields: String = fields
+                    .iter()
+                    .map(|x| format!(" {}", self.field_group_code(x)))
+                    .collect();
+                format!("{}{} {{{} }}", kw, name, fields)
+            }
+            DeclKind::EnumDef { enums } => {
+                let enums: Vec<_> = enums
+                    .iter()
+                    .flatten()
+                    .map(|x| {
+                        let field = self.ctx.get_decl(*x);
+                        let name = field
+                            .name
+                            .as_ref()
+                            .map(|x| x.symbol.get())
+                            .unwrap_or_default();
+                        match field.kind.as_enum_field().and_then(|x| *x) {
+                            Some(expr) => {
+                                format!("{} = {}", name, self.operand(expr, prec::TERNARY))
+                            }
+                            None => name.to_owned(),
+                        }
+                    })
+                    .collect();
+                format!("enum{} {{ {} }}", name, enums.join(", "))
+            }
+            _ => unreachable!("not a tag definition"),
+        }
+    }
+
+    /// 成员的声明组，共用声明说明符，在这里定义的 tag 也要在这里输出
+    fn field_group_code(&self, group: &DeclGroup) -> String {
+        let decls: Vec<_> = group
+            .decls
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let decl = self.ctx.get_decl(*key);
+                let name = decl.name.as_ref().map(|x| x.symbol.get()).unwrap_or_default();
+                let ty = self.ctx.type_ctx.get_type(decl.ty);
+                let (base, declarator) = ty.declarator_code(self.ctx, name);
+                let code = match (i, self.defined_tag(base, group.span)) {
+                    (0, Some(def)) => {
+                        let base = format!("{}{}", base.qual.to_code(), self.tag_body(def));
+                        format!("{}{} {}", specifiers(decl), base, declarator)
+                    }
+                    (0, None) => {
+                        format!("{}{} {}", specifiers(decl), self.base_code(base), declarator)
+                    }
+                    _ => declarator,
+                };
+                let code = code.trim_end().to_owned();
+                match decl.kind.as_record_field().and_then(|x| *x) {
+                    Some(bits) => format!("{} : {}", code, self.operand(bits, prec::TERNARY)),
+                    None => code,
+                }
+            })
+            .collect();
+        format!("{};", decls.join(", "))
+    }
+
+    fn decl(&mut self, key: DeclKey) {
+        use DeclKind::*;
+        let decl = self.ctx.get_decl(key);
+        let name = decl.name.as_ref().map(|x| x.symbol.get()).unwrap_or_default();
+        let storage = specifiers(decl);
+
+        match &decl.kind {
+            TypeDef => {
+                let code = self.decl_code(decl.ty, name);
+                self.line(&format!("typedef {};", code));
+                // 之后用 typedef 名字引用匿名 tag，再次内联定义会得到新的类型
+                let def = match &self.ctx.type_ctx.get_type(decl.ty).kind {
+                    TypeKind::Record { def, .. } | TypeKind::Enum { def, .. } => *def,
+                    _ => None,
+                };
+                if let Some(def) = def.filter(|x| self.ctx.get_decl(*x).name.is_none()) {
+                    self.typedef_names.entry(def).or_insert(name);
+                }
+            }
+            ParamVar | RecordField { .. } | EnumField { .. } => {
+                unreachable!("printed by the owner decl")
+            }
+            VarDecl { .. } | FuncDecl { .. } => {
+                let code = self.decl_code(decl.ty, name);
+                self.line(&format!("{}{};", storage, code));
+            }
+            VarDef { init } => {
+                let code = self.decl_code(decl.ty, name);
+                match init {
+                    Some(init) => {
+                        let init = self.initializer(init);
+                        self.line(&format!("{}{} = {};", storage, code, init));
+                    }
+                    None => self.line(&format!("{}{};", storage, code)),
+                }
+            }
+            FuncDef { params, body, .. } => {
+                let code = self.func_def_code(decl.ty, name, params);
+                self.line(&format!("{}{}", storage, code));
+                self.stmt_inner(body);
+                self.out.push('\n');
+            }
+            RecordDecl { kind, .. } => {
+                let kw = match kind.kind {
+                    RecordKind::Struct => "struct",
+                    RecordKind::Union => "union",
+                };
+                self.line(&format!("{} {};", kw, name));
+            }
+            EnumDecl { .. } => self.line(&format!("enum {};", name)),
+            RecordDef { .. } | EnumDef { .. } => {
+                let body = self.tag_body(key);
+                self.line(&format!("{};", body));
+            }
+        }
+    }
+
+    /// 函数定义头，参数带名字
+    fn func_def_code(&self, ty: TypeKey, name: &str, params: &[DeclKey]) -> String {
+        let ty = self.ctx.type_ctx.get_type(ty);
+        let (ret_ty, is_variadic, has_prototype) = match &ty.kind {
+            TypeKind::Function {
+                ret_ty,
+                is_variadic,
+                has_prototype,
+                ..
+            } => (*ret_ty, *is_variadic, *has_prototype),
+            _ => unreachable!("function definition must have function type"),
+        };
+
+        let mut params: Vec<_> = params
+            .iter()
+            .map(|x| {
+                let param = self.ctx.get_decl(*x);
+                let name = param.name.as_ref().map(|x| x.symbol.get()).unwrap_or_default();
+                self.decl_code(param.ty, name)
+            })
+            .collect();
+        if is_variadic {
+            params.push("...".to_owned());
+        }
+        if params.is_empty() && has_prototype {
+            params.push("void".to_owned());
+        }
+
+        // 返回类型作为 “基础类型”，函数名和参数作为它的声明符
+        let declarator = format!("{}({})", name, params.join(", "));
+        self.decl_code(ret_ty, &declarator)
+    }
+
+    fn initializer(&self, init: &Initializer) -> String {
+        match init {
+            Initializer::Expr(x) => self.operand(*x, prec::ASSIGN),
+            Initializer::InitList { inits } => {
+                let inits: Vec<_> = inits.inits.iter().map(|x| self.initializer(x)).collect();
+                format!("{{{}}}", inits.join(", "))
+            }
+            Initializer::Designated {
+                designators, init, ..
+            } => {
+                let designators: String = designators
+                    .iter()
+                    .map(|x| match x {
+                        MemberDesignator::Field(x) => format!(".{}", x.symbol),
+                        MemberDesignator::Index(x) => format!("[{}]", self.expr(*x)),
+                    })
+                    .collect();
+                format!("{} = {}", designators, self.initializer(init))
+            }
+        }
+    }
+
+    /// `for` 的初始化部分
+    fn for_init(&self, key: StmtKey) -> String {
+        match &self.ctx.get_stmt(key).kind {
+            StmtKind::Expr { expr, .. } => expr.map(|x| self.expr(x)).unwrap_or_default(),
+            StmtKind::Decl { decl } => self.var_group_code(decl),
+            _ => unreachable!("for init must be expression or declaration"),
+        }
+    }
+
+    /// 变量的声明组，多个变量共用声明说明符，如 `int i = 0, *p = 0`
+    fn var_group_code(&self, group: &DeclGroup) -> String {
+        let decls: Vec<_> = group
+            .decls
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let decl = self.ctx.get_decl(*key);
+                let name = decl.name.as_ref().map(|x| x.symbol.get()).unwrap_or_default();
+                let code = match i {
+                    0 => format!("{}{}", specifiers(decl), self.decl_code(decl.ty, name)),
+                    _ => {
+                        let ty = self.ctx.type_ctx.get_type(decl.ty);
+                        ty.declarator_code(self.ctx, name).1
+                    }
+                };
+                match decl.kind.as_var_def().and_then(|x| x.as_ref()) {
+                    Some(init) => format!("{} = {}", code, self.initializer(init)),
+                    None => code,
+                }
+            })
+            .collect();
+        decls.join(", ")
+    }
+
+    fn stmt(&mut self, key: StmtKey) {
+        let stmt = self.ctx.get_stmt(key);
+        self.stmt_inner(stmt);
+    }
+
+    /// 子语句，复合语句不额外缩进
+    fn sub_stmt(&mut self, key: StmtKey) {
+        let stmt = self.ctx.get_stmt(key);
+        match stmt.kind {
+            StmtKind::Compound { .. } => self.stmt_inner(stmt),
+            _ => {
+                self.indent += 1;
+                self.stmt_inner(stmt);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    fn stmt_inner(&mut self, stmt: &Stmt) {
+        use StmtKind::*;
+        let opt = |this: &Self, x: &Option<ExprKey>| match x {
+            Some(x) => this.expr(*x),
+            None => String::new(),
+        };
+
+        match &stmt.kind {
+            Expr { expr, .. } => {
+                let code = opt(self, expr);
+                self.line(&format!("{};", code));
+            }
+            Decl { decl } => self.decl_group(decl),
+            Label { ident, stmt } => {
+                self.line(&format!("{}:", ident.symbol));
+                self.stmt(*stmt);
+            }
+            Case { expr, stmt, .. } => {
+                let code = self.expr(*expr);
+                self.line(&format!("case {}:", code));
+                self.sub_stmt(*stmt);
+            }
+            Default { stmt, .. } => {
+                self.line("default:");
+                self.sub_stmt(*stmt);
+            }
+            IfElse {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let code = self.expr(*cond);
+                self.line(&format!("if ({})", code));
+                self.sub_stmt(*then_stmt);
+                if let Some(x) = else_stmt {
+                    self.line("else");
+                    self.sub_stmt(*x);
+                }
+            }
+            Switch { expr, body, .. } => {
+                let code = self.expr(*expr);
+                self.line(&format!("switch ({})", code));
+                self.sub_stmt(*body);
+            }
+            While { cond, body, .. } => {
+                let code = self.expr(*cond);
+                self.line(&format!("while ({})", code));
+                self.sub_stmt(*body);
+            }
+            DoWhile { body, cond, .. } => {
+                self.line("do");
+                self.sub_stmt(*body);
+                let code = self.expr(*cond);
+                self.line(&format!("while ({});", code));
+            }
+            For {
+                init,
+                cond,
+                step,
+                body,
+                ..
+            } => {
+                let code = format!(
+                    "for ({}; {}; {})",
+                    init.map(|x| self.for_init(x)).unwrap_or_default(),
+                    opt(self, cond),
+                    opt(self, step)
+                );
+                self.line(&code);
+                self.sub_stmt(*body);
+            }
+            Goto { ident } => self.line(&format!("goto {};", ident.symbol)),
+            Continue { .. } => self.line("continue;"),
+            Break { .. } => self.line("break;"),
+            Return { expr, .. } => match expr {
+                Some(x) => {
+                    let code = self.expr(*x);
+                    self.line(&format!("return {};", code));
+                }
+                None => self.line("return;"),
+            },
+            Compound { stmts, .. } => {
+                self.line("{");
+                self.indent += 1;
+                stmts.iter().for_each(|x| self.stmt(*x));
+                self.indent -= 1;
+                self.line("}");
+            }
+        }
+    }
+
+    /// 完整表达式
+    pub fn expr(&self, key: ExprKey) -> String {
+        self.expr_inner(key).0
+    }
+
+    /// 作为操作数输出，优先级低于 `min_prec` 时加括号
+    fn operand(&self, key: ExprKey, min_prec: u8) -> String {
+        let (code, prec) = self.expr_inner(key);
+        let paren = match self.parens {
+            ParenStyle::Minimal => prec < min_prec,
+            ParenStyle::Full => prec < prec::PRIMARY,
+        };
+        match paren {
+            true => format!("({})", code),
+            false => code,
+        }
+    }
+
+    /// 返回表达式代码和优先级
+    fn expr_inner(&self, key: ExprKey) -> (String, u8) {
+        use ExprKind::*;
+        let expr = self.ctx.get_expr(key);
+        match &expr.kind {
+            DeclRef { ident, .. } => (ident.symbol.to_string(), prec::PRIMARY),
+            Literal(x) => (literal_code(x), prec::PRIMARY),
+            ArraySubscript { base, index } => {
+                let base = self.operand(*base, prec::POSTFIX);
+                (format!("{}[{}]", base, self.expr(*index)), prec::POSTFIX)
+            }
+            Call { base, params } => {
+                let base = self.operand(*base, prec::POSTFIX);
+                let params: Vec<_> = params
+                    .exprs
+                    .iter()
+                    .map(|x| self.operand(*x, prec::ASSIGN))
+                    .collect();
+                (format!("{}({})", base, params.join(", ")), prec::POSTFIX)
+            }
+            MemberAccess { kind, base, field } => {
+                let base = self.operand(*base, prec::POSTFIX);
+                let op = match kind {
+                    MemberAccessKind::Arrow => "->",
+                    MemberAccessKind::Dot => ".",
+                };
+                (format!("{}{}{}", base, op, field), prec::POSTFIX)
+            }
+            SizeofExpr { expr } => {
+                // `sizeof (T)x` 会被解析成 sizeof(type)，操作数至少是后缀表达式
+                let code = self.operand(*expr, prec::POSTFIX);
+                (format!("sizeof {}", code), prec::UNARY)
+            }
+            SizeofType { ty } => {
+                let ty = self.decl_code(*ty, "");
+                (format!("sizeof({})", ty), prec::UNARY)
+            }
+            AlignofType { ty } => {
+                let ty = self.decl_code(*ty, "");
+                (format!("_Alignof({})", ty), prec::UNARY)
+            }
+            Unary { op, rhs } if op.kind.is_postfix() => {
+                let rhs = self.operand(*rhs, prec::POSTFIX);
+                (format!("{}{}", rhs, op.kind), prec::POSTFIX)
+            }
+            Unary { op, rhs } => {
+                let op = op.kind.to_string();
+                let rhs = self.operand(*rhs, prec::UNARY);
+                // `- -x` `& &x` 不能粘在一起
+                let space = match rhs.starts_with(op.chars().last().unwrap_or(' ')) {
+                    true => " ",
+                    false => "",
+                };
+                (format!("{}{}{}", op, space, rhs), prec::UNARY)
+            }
+            Binary { lhs, op, rhs } => {
+                let prec = binary_prec(op.kind);
+                // 左结合
+                let lhs = self.operand(*lhs, prec);
+                let rhs = self.operand(*rhs, prec + 1);
+                let code = match op.kind {
+                    BinOpKind::Comma => format!("{}, {}", lhs, rhs),
+                    _ => format!("{} {} {}", lhs, op.kind, rhs),
+                };
+                (code, prec)
+            }
+            Assign { lhs, op, rhs } => {
+                // 右结合
+                let lhs = self.operand(*lhs, prec::UNARY);
+                let rhs = self.operand(*rhs, prec::ASSIGN);
+                (format!("{} {} {}", lhs, op.kind, rhs), prec::ASSIGN)
+            }
+            Cast { ty, expr } => {
+                let ty = self.decl_code(*ty, "");
+                let expr = self.operand(*expr, prec::UNARY);
+                (format!("({}){}", ty, expr), prec::UNARY)
+            }
+            CompoundLiteral { ty, init, .. } => {
+                let ty = self.decl_code(*ty, "");
+                (format!("({}){}", ty, self.initializer(init)), prec::POSTFIX)
+            }
+            Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => {
+                let cond = self.operand(*cond, prec::LOGIC_OR);
+                let then_expr = self.operand(*then_expr, prec::COMMA);
+                let else_expr = self.operand(*else_expr, prec::TERNARY);
+                (
+                    format!("{} ? {} : {}", cond, then_expr, else_expr),
+                    prec::TERNARY,
+                )
+            }
+            BuiltinCall { builtin, args } => {
+                let args: Vec<_> = args.iter().map(|x| self.builtin_arg(x)).collect();
+                (
+                    format!("{}({})", builtin.name(), args.join(", ")),
+                    prec::POSTFIX,
+                )
+            }
+        }
+    }
+
+    fn builtin_arg(&self, arg: &BuiltinArg) -> String {
+        match arg {
+            BuiltinArg::Expr(x) => self.operand(*x, prec::ASSIGN),
+            BuiltinArg::Type(x) => self.decl_code(*x, ""),
+            BuiltinArg::Member(designators) => designators
+                .iter()
+                .enumerate()
+                .map(|(i, x)| match x {
+                    MemberDesignator::Field(x) if i == 0 => x.symbol.to_string(),
+                    MemberDesignator::Field(x) => format!(".{}", x.symbol),
+                    MemberDesignator::Index(x) => format!("[{}]", self.expr(*x)),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 声明说明符中类型以外的部分：可见性、存储类、`_Thread_local`、函数说明符和 `_Alignas`，非空时以空格结尾
+fn specifiers(decl: &Decl) -> String {
+    let visibility = decl.visibility.iter().map(|x| format!("{} ", x));
+    let storage = [&decl.storage, &decl.thread_local].into_iter().flatten();
+    let storage = storage.map(|x| format!("{} ", x));
+    let func_spec = [&decl.func_spec, &decl.noreturn].into_iter().flatten();
+    let func_spec = func_spec.map(|x| format!("{} ", x));
+    let align = decl.align.iter().map(|x| format!("{} ", x));
+    visibility.chain(storage).chain(func_spec).chain(align).collect()
+}
+
+fn binary_prec(op: BinOpKind) -> u8 {
+    use BinOpKind::*;
+    match op {
+        Mul | Div | Mod => 13,
+        Plus | Minus => 12,
+        Shl | Shr => 11,
+        Lt | Gt | Le | Ge => 10,
+        Eq | Ne => 9,
+        BitAnd => 8,
+        BitXor | Xor => 7,
+        BitOr => 6,
+        And => 5,
+        Or => prec::LOGIC_OR,
+        Comma => prec::COMMA,
+    }
+}
+
+/// 字面量保存的是源码原文（包括引号），后缀单独保存
+pub(crate) fn literal_code(lit: &LiteralKind) -> String {
+    match lit {
+        LiteralKind::Integer { value, suffix } => {
+            let suffix = match suffix {
+                None => "",
+                Some(IntSuffix::U) => "u",
+                Some(IntSuffix::L) => "l",
+                Some(IntSuffix::UL) => "ul",
+                Some(IntSuffix::LL) => "ll",
+                Some(IntSuffix::ULL) => "ull",
+            };
+            format!("{}{}", value, suffix)
+        }
+        LiteralKind::Float { value, suffix } => {
+            let suffix = match suffix {
+                None => "",
+                Some(FloatSuffix::F) => "f",
+                Some(FloatSuffix::L) => "l",
+            };
+            format!("{}{}", value, suffix)
+        }
+        LiteralKind::Char { value } | LiteralKind::String { value } => value.to_string(),
+    }
+}